@@ -1,8 +1,15 @@
-use csv::Writer;
-use nom::error::VerboseError;
-use prettytable::{Row, Table};
+use csv::{QuoteStyle, Terminator, Writer, WriterBuilder};
+use nom::error::{VerboseError, VerboseErrorKind};
+use prettytable::Row;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::result;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
 use crate::common;
 use crate::execution;
@@ -11,43 +18,149 @@ use crate::syntax;
 
 pub(crate) type AppResult<T> = result::Result<T, AppError>;
 
-#[derive(Fail, Debug)]
+//The byte range of the query an error is found at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug)]
 pub(crate) enum AppError {
-    #[fail(display = "Syntax Error: {}", _0)]
-    Syntax(String),
-    #[fail(display = "Input is fully consumed, the leftover are \"{}\"", _0)]
-    InputNotAllConsumed(String),
-    #[fail(display = "{}", _0)]
-    Parse(#[cause] logical::parser::ParseError),
-    #[fail(display = "{}", _0)]
-    PhysicalPlan(#[cause] logical::types::PhysicalPlanError),
-    #[fail(display = "{}", _0)]
-    CreateStream(#[cause] execution::types::CreateStreamError),
-    #[fail(display = "{}", _0)]
-    Stream(#[cause] execution::types::StreamError),
-    #[fail(display = "Invalid Log File Format")]
+    Syntax(String, Option<Span>),
+    InputNotAllConsumed(String, Span),
+    Parse(logical::parser::ParseError),
+    PhysicalPlan(logical::types::PhysicalPlanError),
+    CreateStream(execution::types::CreateStreamError),
+    Stream(execution::types::StreamError),
     InvalidLogFileFormat,
-    #[fail(display = "Invalid Table Spec String")]
     InvalidTableSpecString,
-    #[fail(display = "{}", _0)]
-    WriteCsv(#[cause] csv::Error),
-    #[fail(display = "{}", _0)]
-    WriteJson(#[cause] json::Error),
+    InvalidTableOption(String),
+    InvalidTypeAnnotation(String),
+    UnknownTable(String),
+    WriteCsv(csv::Error),
+    WriteJson(json::Error),
+    WriteOutput(std::io::Error),
+    Convert(execution::datasource::ReaderError),
+    UnsupportedConversionTarget(String),
+    UnknownPreset(String),
+    ReadConfig(String, String),
+    Macro(syntax::macros::MacroError),
+    WriteAuditLog(String, String),
+    OpenOutputFile(String, String),
+    InvalidOutputFilePattern(String),
+    FollowNeedsFiles,
+    InvalidChannelCapacity(String),
+    InvalidLogFormat(String),
+    UnknownColumn(String, String, Vec<String>),
+    UnionColumnsMismatch(Vec<String>, Vec<String>),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Syntax(s, _) => write!(fmt, "Syntax Error: {}", s),
+            AppError::InputNotAllConsumed(s, _) => write!(fmt, "Input is fully consumed, the leftover are \"{}\"", s),
+            AppError::Parse(e) => write!(fmt, "{}", e),
+            AppError::PhysicalPlan(e) => write!(fmt, "{}", e),
+            AppError::CreateStream(e) => write!(fmt, "{}", e),
+            AppError::Stream(e) => write!(fmt, "{}", e),
+            AppError::InvalidLogFileFormat => fmt.write_str("Invalid Log File Format"),
+            AppError::InvalidTableSpecString => fmt.write_str("Invalid Table Spec String"),
+            AppError::InvalidTableOption(s) => write!(fmt, "Invalid Table Option: {}", s),
+            AppError::InvalidTypeAnnotation(s) => write!(fmt, "Invalid Type Annotation: {}", s),
+            AppError::UnknownTable(s) => write!(fmt, "Unknown table \"{}\"", s),
+            AppError::WriteCsv(e) => write!(fmt, "{}", e),
+            AppError::WriteJson(e) => write!(fmt, "{}", e),
+            AppError::WriteOutput(e) => write!(fmt, "{}", e),
+            AppError::Convert(e) => write!(fmt, "{}", e),
+            AppError::UnsupportedConversionTarget(s) => {
+                write!(fmt, "Unsupported conversion target \"{}\", only logqc is supported", s)
+            }
+            AppError::UnknownPreset(s) => write!(fmt, "Unknown preset \"{}\"", s),
+            AppError::ReadConfig(path, e) => write!(fmt, "Failed to read the config \"{}\": {}", path, e),
+            AppError::Macro(e) => write!(fmt, "{}", e),
+            AppError::WriteAuditLog(path, e) => write!(fmt, "Failed to write the audit log \"{}\": {}", path, e),
+            AppError::OpenOutputFile(path, e) => write!(fmt, "Failed to open the output file \"{}\": {}", path, e),
+            AppError::InvalidOutputFilePattern(s) => write!(
+                fmt,
+                "The output file \"{}\" needs a %d or %0Nd for the rotated file number",
+                s
+            ),
+            AppError::FollowNeedsFiles => {
+                fmt.write_str("--follow re-reads the tables, which need to be files rather than stdin or exec")
+            }
+            AppError::InvalidChannelCapacity(s) => write!(
+                fmt,
+                "Invalid channel capacity \"{}\", expect a positive number of records",
+                s
+            ),
+            AppError::InvalidLogFormat(s) => write!(fmt, "Invalid log format: {}", s),
+            AppError::UnknownColumn(s, _, similar) if similar.is_empty() => write!(fmt, "Unknown column \"{}\"", s),
+            AppError::UnknownColumn(s, file_format, similar) => write!(
+                fmt,
+                "Unknown column \"{}\", the similar fields of {}: {}",
+                s,
+                file_format,
+                similar.join(", ")
+            ),
+            AppError::UnionColumnsMismatch(first, other) => write!(
+                fmt,
+                "The select statements of the union have different columns: ({}) and ({})",
+                first.join(", "),
+                other.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Parse(e) => Some(e),
+            AppError::PhysicalPlan(e) => Some(e),
+            AppError::CreateStream(e) => Some(e),
+            AppError::Stream(e) => Some(e),
+            AppError::WriteCsv(e) => Some(e),
+            AppError::WriteJson(e) => Some(e),
+            AppError::WriteOutput(e) => Some(e),
+            AppError::Convert(e) => Some(e),
+            AppError::Macro(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for AppError {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (AppError::Syntax(_), AppError::Syntax(_)) => true,
-            (AppError::InputNotAllConsumed(_), AppError::InputNotAllConsumed(_)) => true,
+            (AppError::Syntax(_, _), AppError::Syntax(_, _)) => true,
+            (AppError::InputNotAllConsumed(_, _), AppError::InputNotAllConsumed(_, _)) => true,
             (AppError::Parse(_), AppError::Parse(_)) => true,
             (AppError::PhysicalPlan(_), AppError::PhysicalPlan(_)) => true,
             (AppError::CreateStream(_), AppError::CreateStream(_)) => true,
             (AppError::Stream(_), AppError::Stream(_)) => true,
             (AppError::InvalidLogFileFormat, AppError::InvalidLogFileFormat) => true,
             (AppError::InvalidTableSpecString, AppError::InvalidTableSpecString) => true,
+            (AppError::InvalidTableOption(_), AppError::InvalidTableOption(_)) => true,
+            (AppError::InvalidTypeAnnotation(_), AppError::InvalidTypeAnnotation(_)) => true,
+            (AppError::UnknownTable(_), AppError::UnknownTable(_)) => true,
             (AppError::WriteCsv(_), AppError::WriteCsv(_)) => true,
             (AppError::WriteJson(_), AppError::WriteJson(_)) => true,
+            (AppError::WriteOutput(_), AppError::WriteOutput(_)) => true,
+            (AppError::Convert(_), AppError::Convert(_)) => true,
+            (AppError::UnsupportedConversionTarget(_), AppError::UnsupportedConversionTarget(_)) => true,
+            (AppError::UnknownPreset(_), AppError::UnknownPreset(_)) => true,
+            (AppError::ReadConfig(_, _), AppError::ReadConfig(_, _)) => true,
+            (AppError::Macro(_), AppError::Macro(_)) => true,
+            (AppError::WriteAuditLog(_, _), AppError::WriteAuditLog(_, _)) => true,
+            (AppError::OpenOutputFile(_, _), AppError::OpenOutputFile(_, _)) => true,
+            (AppError::InvalidOutputFilePattern(_), AppError::InvalidOutputFilePattern(_)) => true,
+            (AppError::FollowNeedsFiles, AppError::FollowNeedsFiles) => true,
+            (AppError::InvalidChannelCapacity(_), AppError::InvalidChannelCapacity(_)) => true,
+            (AppError::InvalidLogFormat(_), AppError::InvalidLogFormat(_)) => true,
+            (AppError::UnknownColumn(_, _, _), AppError::UnknownColumn(_, _, _)) => true,
+            (AppError::UnionColumnsMismatch(_, _), AppError::UnionColumnsMismatch(_, _)) => true,
             _ => false,
         }
     }
@@ -55,30 +168,190 @@ impl PartialEq for AppError {
 
 impl Eq for AppError {}
 
-impl From<nom::Err<VerboseError<&str>>> for AppError {
-    fn from(e: nom::Err<VerboseError<&str>>) -> AppError {
-        match e {
-            nom::Err::Failure(v) => {
-                let mut errors: String = String::new();
-                for (s, _) in v.errors {
-                    errors.push_str(&s.to_string());
-                    errors.push('\n');
-                }
+impl AppError {
+    //The span is where the parser stopped in the query, the errors refer to the rest of the query from there
+    pub(crate) fn syntax(query_str: &str, e: nom::Err<VerboseError<&str>>) -> AppError {
+        let (errors, with_context) = match e {
+            nom::Err::Failure(v) => (v.errors, true),
+            nom::Err::Error(v) => (v.errors, false),
+            _ => return AppError::Syntax(String::new(), None),
+        };
 
-                AppError::Syntax(errors)
+        let span = errors.first().map(|(rest, _)| {
+            let start = query_str.len().saturating_sub(rest.len());
+            let token_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            Span {
+                start,
+                end: start + token_len,
             }
-            nom::Err::Error(v) => {
-                let mut errors: String = String::new();
-                for (s, _) in v.errors {
-                    errors.push_str(&s.to_string());
-                    errors.push('\n');
-                }
-
-                AppError::Syntax(errors)
+        });
+        let mut message: String = String::new();
+        for (s, kind) in errors {
+            if let (VerboseErrorKind::Context(c), true) = (kind, with_context) {
+                message.push_str(c);
+                message.push_str(": ");
             }
-            _ => AppError::Syntax(String::new()),
+            message.push_str(s);
+            message.push('\n');
+        }
+
+        AppError::Syntax(message, span)
+    }
+
+    fn input_not_all_consumed(query_str: &str, rest_of_str: &str) -> AppError {
+        let span = Span {
+            start: query_str.len().saturating_sub(rest_of_str.len()),
+            end: query_str.len(),
+        };
+        AppError::InputNotAllConsumed(rest_of_str.to_string(), span)
+    }
+
+    //A stable name of the kind of the error for the scripts, the message may change
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            AppError::Syntax(_, _) => "syntax",
+            AppError::InputNotAllConsumed(_, _) => "input_not_all_consumed",
+            AppError::Parse(_) => "parse",
+            AppError::PhysicalPlan(_) => "physical_plan",
+            AppError::CreateStream(_) => "create_stream",
+            AppError::Stream(_) => "stream",
+            AppError::InvalidLogFileFormat => "invalid_log_file_format",
+            AppError::InvalidTableSpecString => "invalid_table_spec",
+            AppError::InvalidTableOption(_) => "invalid_table_option",
+            AppError::InvalidTypeAnnotation(_) => "invalid_type_annotation",
+            AppError::UnknownTable(_) => "unknown_table",
+            AppError::WriteCsv(_) => "write_csv",
+            AppError::WriteJson(_) => "write_json",
+            AppError::WriteOutput(_) => "write_output",
+            AppError::Convert(_) => "convert",
+            AppError::UnsupportedConversionTarget(_) => "unsupported_conversion_target",
+            AppError::UnknownPreset(_) => "unknown_preset",
+            AppError::ReadConfig(_, _) => "read_config",
+            AppError::Macro(_) => "macro",
+            AppError::WriteAuditLog(_, _) => "write_audit_log",
+            AppError::OpenOutputFile(_, _) => "open_output_file",
+            AppError::InvalidOutputFilePattern(_) => "invalid_output_file_pattern",
+            AppError::FollowNeedsFiles => "follow_needs_files",
+            AppError::InvalidChannelCapacity(_) => "invalid_channel_capacity",
+            AppError::InvalidLogFormat(_) => "invalid_log_format",
+            AppError::UnknownColumn(_, _, _) => "unknown_column",
+            AppError::UnionColumnsMismatch(_, _) => "union_columns_mismatch",
+        }
+    }
+
+    pub(crate) fn kind(&self) -> ErrorKind {
+        match self {
+            AppError::Syntax(_, _) => ErrorKind::Syntax,
+            AppError::InputNotAllConsumed(_, _) => ErrorKind::InputNotAllConsumed,
+            AppError::Parse(_) => ErrorKind::Parse,
+            AppError::PhysicalPlan(_) => ErrorKind::PhysicalPlan,
+            AppError::CreateStream(_) => ErrorKind::CreateStream,
+            AppError::Stream(_) => ErrorKind::Stream,
+            AppError::InvalidLogFileFormat => ErrorKind::InvalidLogFileFormat,
+            AppError::InvalidTableSpecString | AppError::InvalidTableOption(_) => ErrorKind::InvalidTableSpec,
+            AppError::InvalidTypeAnnotation(_) => ErrorKind::InvalidTypeAnnotation,
+            AppError::UnknownTable(_) => ErrorKind::UnknownTable,
+            AppError::WriteCsv(_) => ErrorKind::WriteCsv,
+            AppError::WriteJson(_) => ErrorKind::WriteJson,
+            AppError::WriteOutput(_) => ErrorKind::WriteOutput,
+            AppError::Convert(_) => ErrorKind::Convert,
+            AppError::UnsupportedConversionTarget(_) => ErrorKind::UnsupportedConversionTarget,
+            AppError::UnknownPreset(_) => ErrorKind::UnknownPreset,
+            AppError::ReadConfig(_, _) => ErrorKind::ReadConfig,
+            AppError::Macro(_) => ErrorKind::Macro,
+            AppError::WriteAuditLog(_, _) => ErrorKind::WriteAuditLog,
+            AppError::OpenOutputFile(_, _) => ErrorKind::OpenOutputFile,
+            AppError::InvalidOutputFilePattern(_) => ErrorKind::InvalidOutputFilePattern,
+            AppError::FollowNeedsFiles => ErrorKind::FollowNeedsFiles,
+            AppError::InvalidChannelCapacity(_) => ErrorKind::InvalidChannelCapacity,
+            AppError::InvalidLogFormat(_) => ErrorKind::InvalidLogFormat,
+            AppError::UnknownColumn(_, _, _) => ErrorKind::UnknownColumn,
+            AppError::UnionColumnsMismatch(_, _) => ErrorKind::UnionColumnsMismatch,
         }
     }
+
+    pub(crate) fn span(&self) -> Option<Span> {
+        match self {
+            AppError::Syntax(_, span) => *span,
+            AppError::InputNotAllConsumed(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+
+    //The error as a JSON object of the code, the message and the span in the query if any
+    pub(crate) fn to_json(&self) -> json::JsonValue {
+        let mut obj = json::JsonValue::new_object();
+        obj["code"] = self.code().into();
+        obj["message"] = self.to_string().into();
+        obj["span"] = match self.span() {
+            Some(span) => json::object! { "start" => span.start, "end" => span.end },
+            None => json::JsonValue::Null,
+        };
+        obj
+    }
+}
+
+//The kind of the error of the library entry point, more kinds may be added in the later releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    Syntax,
+    InputNotAllConsumed,
+    Parse,
+    PhysicalPlan,
+    CreateStream,
+    Stream,
+    InvalidLogFileFormat,
+    InvalidTableSpec,
+    InvalidTypeAnnotation,
+    UnknownTable,
+    WriteCsv,
+    WriteJson,
+    WriteOutput,
+    Convert,
+    UnsupportedConversionTarget,
+    UnknownPreset,
+    ReadConfig,
+    Macro,
+    WriteAuditLog,
+    OpenOutputFile,
+    InvalidOutputFilePattern,
+    FollowNeedsFiles,
+    InvalidChannelCapacity,
+    InvalidLogFormat,
+    UnknownColumn,
+    UnionColumnsMismatch,
+}
+
+//The error of the library entry point, with the same code and span as --error-format json prints. The kind can be
+//matched on after a downcast from anyhow::Error.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Error(AppError);
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind()
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.0.code()
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.0.span()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, fmt)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.0)
+    }
 }
 
 impl From<logical::parser::ParseError> for AppError {
@@ -93,6 +366,12 @@ impl From<logical::types::PhysicalPlanError> for AppError {
     }
 }
 
+impl From<syntax::macros::MacroError> for AppError {
+    fn from(err: syntax::macros::MacroError) -> AppError {
+        AppError::Macro(err)
+    }
+}
+
 impl From<execution::types::CreateStreamError> for AppError {
     fn from(err: execution::types::CreateStreamError) -> AppError {
         AppError::CreateStream(err)
@@ -117,7 +396,54 @@ impl From<json::Error> for AppError {
     }
 }
 
-pub(crate) enum OutputMode {
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> AppError {
+        AppError::WriteOutput(err)
+    }
+}
+
+impl From<execution::datasource::ReaderError> for AppError {
+    fn from(err: execution::datasource::ReaderError) -> AppError {
+        AppError::Convert(err)
+    }
+}
+
+//The options of a query run, applied on top of the query plan and to the output records
+#[derive(Debug, Default, Clone)]
+pub(crate) struct RunOptions {
+    pub(crate) columns: Option<Vec<String>>,
+    pub(crate) group_limit: Option<execution::stream::GroupLimit>,
+    pub(crate) macros: syntax::macros::Macros,
+    //Suppresses the summary footer after the table output
+    pub(crate) quiet: bool,
+    pub(crate) output_file: Option<PathBuf>,
+    pub(crate) compression: Option<Compression>,
+    pub(crate) rotation: Rotation,
+    pub(crate) datetime_format: common::types::DateTimeFormat,
+    //Only applies to the table output
+    pub(crate) number_format: common::types::NumberFormat,
+    pub(crate) renders: Vec<(String, common::types::Renderer)>,
+    pub(crate) csv_options: CsvOptions,
+    pub(crate) follow: Option<Follow>,
+}
+
+//Re-runs the query every interval with --follow, printing the whole result or, with --emit delta, the rows added
+//and removed since the last run as the json lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Follow {
+    pub(crate) interval: std::time::Duration,
+    pub(crate) delta: bool,
+}
+
+//The config holds the macro definitions, it is lowercased like the query
+pub(crate) fn load_config(path: &str) -> AppResult<syntax::macros::Macros> {
+    let content = std::fs::read_to_string(path).map_err(|e| AppError::ReadConfig(path.to_string(), e.to_string()))?;
+    let macros = syntax::macros::Macros::parse(&content.to_ascii_lowercase())?;
+    Ok(macros)
+}
+
+#[derive(Debug, Clone)]
+pub enum OutputMode {
     Table,
     Csv,
     Json,
@@ -136,13 +462,268 @@ impl FromStr for OutputMode {
     }
 }
 
-pub(crate) fn explain(query_str: &str, data_source: common::types::DataSource) -> AppResult<()> {
-    let (rest_of_str, select_stmt) = syntax::parser::select_query(&query_str)?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err("unknown compression".to_string()),
+        }
+    }
+}
+
+//Splits the output into the files numbered by the pattern of --output-file, e.g. out-%03d.csv. The next file is
+//started once the current one reaches either limit, the bytes are counted before the compression.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Rotation {
+    pub(crate) rows: Option<usize>,
+    pub(crate) bytes: Option<u64>,
+}
+
+impl Rotation {
+    fn is_enabled(&self) -> bool {
+        self.rows.is_some() || self.bytes.is_some()
+    }
+
+    fn is_full(&self, row_count: usize, written: u64) -> bool {
+        self.rows.is_some_and(|rows| row_count >= rows) || self.bytes.is_some_and(|bytes| written >= bytes)
+    }
+}
+
+//The quoting, the escape and the line ending of the csv output, for the strict parsers like Redshift COPY or Excel.
+//Without an escape character the quotes are doubled as in RFC 4180.
+#[derive(Debug, Clone)]
+pub(crate) struct CsvOptions {
+    pub(crate) quote_style: QuoteStyle,
+    pub(crate) escape: Option<u8>,
+    pub(crate) crlf: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            quote_style: QuoteStyle::Necessary,
+            escape: None,
+            crlf: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub(crate) fn parse_quote_style(s: &str) -> result::Result<QuoteStyle, String> {
+        match s {
+            "necessary" => Ok(QuoteStyle::Necessary),
+            "always" => Ok(QuoteStyle::Always),
+            "non-numeric" => Ok(QuoteStyle::NonNumeric),
+            "never" => Ok(QuoteStyle::Never),
+            _ => Err(format!("unknown quote style \"{}\"", s)),
+        }
+    }
+
+    pub(crate) fn parse_escape(s: &str) -> result::Result<u8, String> {
+        match s.as_bytes() {
+            [c] if c.is_ascii() => Ok(*c),
+            _ => Err(format!("the escape must be one ASCII character, got \"{}\"", s)),
+        }
+    }
+
+    fn writer<W: Write>(&self, wtr: W) -> Writer<W> {
+        let mut builder = WriterBuilder::new();
+        builder.quote_style(self.quote_style);
+        builder.terminator(if self.crlf {
+            Terminator::CRLF
+        } else {
+            Terminator::Any(b'\n')
+        });
+        if let Some(escape) = self.escape {
+            builder.double_quote(false).escape(escape);
+        }
+        builder.from_writer(wtr)
+    }
+}
+
+//The pattern with the printf style %d or %0Nd replaced by the chunk number
+fn chunk_path(pattern: &str, chunk: usize) -> Option<String> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d')?;
+    let spec = &rest[..end];
+    if !spec.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let width = spec.parse::<usize>().unwrap_or(0);
+    let number = if spec.starts_with('0') {
+        format!("{:0width$}", chunk, width = width)
+    } else {
+        format!("{:width$}", chunk, width = width)
+    };
+    Some(format!("{}{}{}", &pattern[..start], number, &rest[end + 1..]))
+}
+
+enum SinkWriter {
+    Stdout(std::io::BufWriter<std::io::Stdout>),
+    File(std::io::BufWriter<std::fs::File>),
+    Gzip(common::compress::GzipWriter<std::io::BufWriter<std::fs::File>>),
+    Zstd(common::compress::ZstdWriter),
+}
+
+//Where the query result is written, the file is compressed as it is written
+struct OutputSink {
+    wtr: SinkWriter,
+    //Shared with the rotation, since the sink is owned by the record writer
+    written: Rc<Cell<u64>>,
+}
+
+impl OutputSink {
+    fn open(path_opt: Option<&Path>, compression: Option<Compression>) -> AppResult<Self> {
+        let wtr = match path_opt {
+            Some(path) => {
+                let open_error =
+                    |e: std::io::Error| AppError::OpenOutputFile(path.display().to_string(), e.to_string());
+                let file = std::fs::File::create(path).map_err(open_error)?;
+                match compression {
+                    Some(Compression::Gzip) => {
+                        SinkWriter::Gzip(common::compress::GzipWriter::new(std::io::BufWriter::new(file))?)
+                    }
+                    Some(Compression::Zstd) => match common::compress::ZstdWriter::new(file) {
+                        Ok(wtr) => SinkWriter::Zstd(wtr),
+                        Err(e) => {
+                            //Not to leave an empty output file behind when the command can't be run
+                            let _ = std::fs::remove_file(path);
+                            return Err(open_error(e));
+                        }
+                    },
+                    None => SinkWriter::File(std::io::BufWriter::new(file)),
+                }
+            }
+            None => SinkWriter::Stdout(std::io::BufWriter::new(std::io::stdout())),
+        };
+
+        Ok(OutputSink {
+            wtr,
+            written: Rc::new(Cell::new(0)),
+        })
+    }
+
+    fn open_chunk(run_options: &RunOptions, chunk: usize) -> AppResult<Self> {
+        let output_file = run_options.output_file.as_deref();
+        match output_file {
+            Some(pattern) if run_options.rotation.is_enabled() => {
+                let pattern = pattern.to_string_lossy();
+                let path = chunk_path(&pattern, chunk)
+                    .ok_or_else(|| AppError::InvalidOutputFilePattern(pattern.to_string()))?;
+                OutputSink::open(Some(Path::new(&path)), run_options.compression)
+            }
+            _ => OutputSink::open(output_file, run_options.compression),
+        }
+    }
+
+    fn finish(self) -> AppResult<()> {
+        match self.wtr {
+            SinkWriter::Stdout(mut wtr) => wtr.flush()?,
+            SinkWriter::File(mut wtr) => wtr.flush()?,
+            SinkWriter::Gzip(wtr) => {
+                wtr.finish()?;
+            }
+            SinkWriter::Zstd(wtr) => wtr.finish()?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for OutputSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = match &mut self.wtr {
+            SinkWriter::Stdout(wtr) => wtr.write(buf)?,
+            SinkWriter::File(wtr) => wtr.write(buf)?,
+            SinkWriter::Gzip(wtr) => wtr.write(buf)?,
+            SinkWriter::Zstd(wtr) => wtr.write(buf)?,
+        };
+        self.written.set(self.written.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.wtr {
+            SinkWriter::Stdout(wtr) => wtr.flush(),
+            SinkWriter::File(wtr) => wtr.flush(),
+            SinkWriter::Gzip(wtr) => wtr.flush(),
+            SinkWriter::Zstd(wtr) => wtr.flush(),
+        }
+    }
+}
+
+//The canned queries of the common reports, run against the first table given by --table
+const PRESETS: [(&str, &str); 6] = [
+    (
+        "rule-priority",
+        r#"select p, count(*) as requests, ratio(elb_status_code >= "400") as error_rate from {table} group by matched_rule_priority as p order by p asc"#,
+    ),
+    (
+        "lambda-errors",
+        r#"select c, r, count(*) as requests from {table} group by lambda_error_class as c, error_reason as r order by requests desc"#,
+    ),
+    (
+        "status-matrix",
+        r#"select t, count_if(__status_class = "2xx") as status_2xx, count_if(__status_class = "3xx") as status_3xx, count_if(__status_class = "4xx") as status_4xx, count_if(__status_class = "5xx") as status_5xx, count(*) as total from {table} group by time_bucket("1 minute", timestamp) as t order by t asc"#,
+    ),
+    (
+        "client-ports",
+        r#"select t, ip, count(*) as requests, approx_count_distinct(client_port) as ports, min(client_port) as min_port, max(client_port) as max_port from {table} group by time_bucket("1 minute", timestamp) as t, client_ip as ip having ports >= 100 order by ports desc"#,
+    ),
+    (
+        "desync",
+        r#"select c, r, count(*) as requests from {table} where classification = "Ambiguous" or classification = "Severe" group by classification as c, classification_reason as r order by requests desc"#,
+    ),
+    (
+        "s3-requesters",
+        r#"select o, r, b, p, count(*) as requests, ratio(http_status >= "400") as error_rate, avg(turn_around_time) as avg_turn_around_ms, max(total_time) as max_total_ms from {table} group by operation as o, requester as r, bucket as b, key_prefix(key, 1) as p order by requests desc"#,
+    ),
+];
+
+pub(crate) fn preset_query(name: &str, table_name: &str) -> AppResult<String> {
+    PRESETS
+        .iter()
+        .find(|(preset_name, _)| *preset_name == name)
+        .map(|(_, query)| query.replace("{table}", table_name))
+        .ok_or_else(|| AppError::UnknownPreset(name.to_string()))
+}
+
+pub(crate) fn explain(
+    query_str: &str,
+    data_source: common::types::DataSource,
+    macros: &syntax::macros::Macros,
+) -> AppResult<()> {
+    let (rest_of_str, select_stmt) =
+        syntax::parser::select_query(query_str).map_err(|e| AppError::syntax(query_str, e))?;
     if !rest_of_str.is_empty() {
-        return Err(AppError::InputNotAllConsumed(rest_of_str.to_string()));
+        return Err(AppError::input_not_all_consumed(query_str, rest_of_str));
     }
+    let select_stmt = macros
+        .scoped(&[data_source.file_format(), data_source.table_name()])
+        .expand(select_stmt)?;
 
     let node = logical::parser::parse_query(select_stmt, data_source.clone())?;
+    println!("Logical Plan:");
+    println!("{:?}", node);
+
+    let (node, notes) = logical::optimizer::optimize(node);
+    println!("Optimized Logical Plan:");
+    println!("{:?}", node);
+    for note in notes.iter() {
+        println!("  - {}", note);
+    }
+
     let mut physical_plan_creator = logical::types::PhysicalPlanCreator::new(data_source);
     let (physical_plan, _variables) = node.physical(&mut physical_plan_creator)?;
 
@@ -151,92 +732,774 @@ pub(crate) fn explain(query_str: &str, data_source: common::types::DataSource) -
     Ok(())
 }
 
-pub(crate) fn run(query_str: &str, data_source: common::types::DataSource, output_mode: OutputMode) -> AppResult<()> {
-    let (rest_of_str, select_stmt) = syntax::parser::select_query(&query_str)?;
+fn build_stream(
+    query_str: &str,
+    data_sources: &[common::types::DataSource],
+    output_mode: &OutputMode,
+    run_options: &RunOptions,
+) -> AppResult<Box<dyn execution::stream::RecordStream>> {
+    let (rest_of_str, select_stmts) =
+        syntax::parser::union_query(query_str).map_err(|e| AppError::syntax(query_str, e))?;
     if !rest_of_str.is_empty() {
-        return Err(AppError::InputNotAllConsumed(rest_of_str.to_string()));
+        return Err(AppError::input_not_all_consumed(query_str, rest_of_str));
     }
 
-    let node = logical::parser::parse_query(select_stmt, data_source.clone())?;
-    let mut physical_plan_creator = logical::types::PhysicalPlanCreator::new(data_source);
-    let (physical_plan, variables) = node.physical(&mut physical_plan_creator)?;
+    //The order by and the limit after the last select of a union are over the rows of all the statements
+    let mut select_stmts = select_stmts;
+    let union = select_stmts.len() > 1;
+    let (order_by_expr_opt, limit_expr_opt) = match select_stmts.last_mut() {
+        Some(last) if union => (last.order_by_expr_opt.take(), last.limit_expr_opt.take()),
+        _ => (None, None),
+    };
+
+    let mut streams = Vec::new();
+    let mut first_columns: Option<Vec<String>> = None;
+    for select_stmt in select_stmts.into_iter() {
+        let data_source = find_data_source(&select_stmt, data_sources)?;
+        let select_stmt = run_options
+            .macros
+            .scoped(&[data_source.file_format(), data_source.table_name()])
+            .expand(select_stmt)?;
+        let node = match &select_stmt.join_opt {
+            Some(join) => {
+                let join_data_source = find_table(&join.table_reference, data_sources)?;
+                logical::parser::parse_join_query(select_stmt, data_source.clone(), join_data_source)?
+            }
+            None => logical::parser::parse_query(select_stmt, data_source.clone())?,
+        };
+        //The rows of the statements go to the same output, so they need the same columns
+        if let Some(columns) = node.output_columns() {
+            match &first_columns {
+                Some(first) if *first != columns => {
+                    return Err(AppError::UnionColumnsMismatch(first.clone(), columns));
+                }
+                Some(_) => {}
+                None => first_columns = Some(columns),
+            }
+        }
+        let (node, _notes) = logical::optimizer::optimize(node);
+        let mut physical_plan_creator =
+            logical::types::PhysicalPlanCreator::new(data_source).group_limit(run_options.group_limit.clone());
+        let (physical_plan, variables) = node.physical(&mut physical_plan_creator)?;
+
+        streams.push(physical_plan.get(variables)?);
+    }
+
+    let mut stream: Box<dyn execution::stream::RecordStream> = if streams.len() == 1 {
+        streams.pop().unwrap()
+    } else {
+        Box::new(execution::stream::UnionStream::new(streams))
+    };
+    if let Some(order_by_expr) = order_by_expr_opt {
+        let mut column_names = Vec::new();
+        let mut orderings = Vec::new();
+        for ordering_term in order_by_expr.ordering_terms.into_iter() {
+            let column_name = match ordering_term.position {
+                Some(position) => match first_columns.as_ref().and_then(|c| c.get(position.wrapping_sub(1))) {
+                    Some(column) => {
+                        syntax::ast::PathExpr::new(vec![syntax::ast::PathSegment::AttrName(column.clone())])
+                    }
+                    None => return Err(logical::parser::ParseError::InvalidOrderByPosition(position).into()),
+                },
+                None => ordering_term.column_name,
+            };
+            column_names.push(column_name);
+            orderings.push(match ordering_term.ordering {
+                syntax::ast::Ordering::Asc => execution::types::Ordering::Asc,
+                syntax::ast::Ordering::Desc => execution::types::Ordering::Desc,
+            });
+        }
+        stream = Box::new(execution::stream::OrderByStream::new(column_names, orderings, stream));
+    }
+    if let Some(limit_expr) = limit_expr_opt {
+        stream = Box::new(execution::stream::LimitStream::new(limit_expr.row_count, stream));
+    }
+    if let Some(columns) = &run_options.columns {
+        stream = Box::new(execution::stream::ColumnsStream::new(columns.clone(), stream));
+    }
+    if !run_options.renders.is_empty() {
+        stream = Box::new(execution::stream::RenderStream::new(
+            run_options.renders.clone(),
+            stream,
+        ));
+    }
+    if common::trace::enabled(common::trace::Level::Info) {
+        let span = common::trace::Span::enter(
+            common::trace::Level::Info,
+            "output",
+            format_args!("mode={:?}", output_mode),
+        );
+        stream = Box::new(execution::stream::TracedStream::new(span, stream));
+    }
+
+    Ok(stream)
+}
+
+//An unknown column is told with the fields of the first table it may be meant for
+fn stream_error(e: execution::types::StreamError, data_sources: &[common::types::DataSource]) -> AppError {
+    match e {
+        execution::types::StreamError::UnknownColumn(column) => {
+            let file_format = data_sources.first().map_or("", |d| d.file_format());
+            let similar = execution::field_docs::similar_fields(file_format, &column);
+            AppError::UnknownColumn(column, file_format.to_string(), similar)
+        }
+        e => AppError::Stream(e),
+    }
+}
+
+//A table of the library entry point, the log files of the format at the path as --table name:format=path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    name: String,
+    file_format: String,
+    path: PathBuf,
+}
+
+impl Table {
+    pub fn new<P: AsRef<Path>>(name: &str, file_format: &str, path: P) -> Self {
+        Table {
+            name: name.to_string(),
+            file_format: file_format.to_string(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    fn data_source(&self) -> AppResult<common::types::DataSource> {
+        if !execution::datasource::is_supported_format(&self.file_format) {
+            return Err(AppError::InvalidLogFileFormat);
+        }
+
+        Ok(common::types::DataSource::File(
+            self.path.clone(),
+            self.file_format.clone(),
+            self.name.clone(),
+            execution::datasource::ReaderOptions::default(),
+        ))
+    }
+}
+
+//Runs the query as the query subcommand does with the default options, but writes the result to the writer, and
+//returns the number of the rows written
+pub fn query<W: Write>(
+    query_str: &str,
+    tables: &[Table],
+    output_mode: OutputMode,
+    wtr: W,
+) -> result::Result<usize, Error> {
+    query_to(&query_str.to_ascii_lowercase(), tables, &output_mode, wtr).map_err(Error)
+}
+
+fn query_to<W: Write>(query_str: &str, tables: &[Table], output_mode: &OutputMode, wtr: W) -> AppResult<usize> {
+    let data_sources = tables.iter().map(Table::data_source).collect::<AppResult<Vec<_>>>()?;
+    let run_options = RunOptions::default();
+    let mut stream = build_stream(query_str, &data_sources, output_mode, &run_options)?;
+
+    write_records(stream.as_mut(), &data_sources, output_mode, &run_options, wtr)
+}
+
+pub(crate) fn run(
+    query_str: &str,
+    data_sources: Vec<common::types::DataSource>,
+    output_mode: OutputMode,
+    run_options: &RunOptions,
+) -> AppResult<usize> {
+    let mut stream = build_stream(query_str, &data_sources, &output_mode, run_options)?;
+    write_stream(stream.as_mut(), &data_sources, &output_mode, run_options, None)
+}
+
+//Writes the result to the output, followed by the footer of the table. The runs of --follow share the sink, which
+//is opened once and not rotated.
+fn write_stream(
+    stream: &mut dyn execution::stream::RecordStream,
+    data_sources: &[common::types::DataSource],
+    output_mode: &OutputMode,
+    run_options: &RunOptions,
+    follow_sink: Option<&mut OutputSink>,
+) -> AppResult<usize> {
+    let started = std::time::Instant::now();
+    //The counters are process-wide, the query may run many times with --follow
+    let (scanned_before, skipped_before) = execution::stream::scanned_lines();
+
+    let row_count = match follow_sink {
+        Some(sink) => write_records(stream, data_sources, output_mode, run_options, sink)?,
+        None => write_chunks(stream, data_sources, output_mode, run_options)?,
+    };
+
+    let (scanned, skipped) = execution::stream::scanned_lines();
+    let (scanned, skipped) = (scanned - scanned_before, skipped - skipped_before);
+    if !run_options.quiet && matches!(output_mode, OutputMode::Table) {
+        println!(
+            "{} rows returned, {} lines scanned, {} lines skipped due to parse errors, {:.3}s elapsed",
+            row_count,
+            scanned,
+            skipped,
+            started.elapsed().as_secs_f64()
+        );
+    }
+
+    //The footer of the table output already tells
+    if skipped > 0 && !matches!(output_mode, OutputMode::Table) {
+        common::trace::warning(format_args!("{} lines skipped due to parse errors", skipped));
+    }
+
+    Ok(row_count)
+}
+
+fn write_records<W: Write>(
+    stream: &mut dyn execution::stream::RecordStream,
+    data_sources: &[common::types::DataSource],
+    output_mode: &OutputMode,
+    run_options: &RunOptions,
+    wtr: W,
+) -> AppResult<usize> {
+    let mut wtr = RecordWriter::new(output_mode, &run_options.csv_options, wtr)?;
+    let mut row_count = 0;
+    while let Some(record) = stream.next().map_err(|e| stream_error(e, data_sources))? {
+        wtr.write_record(&record, run_options)?;
+        row_count += 1;
+    }
+    wtr.finish()?.flush()?;
+
+    Ok(row_count)
+}
+
+//Writes the result to the files numbered by the rotation, or to the output file or stdout if not rotated
+fn write_chunks(
+    stream: &mut dyn execution::stream::RecordStream,
+    data_sources: &[common::types::DataSource],
+    output_mode: &OutputMode,
+    run_options: &RunOptions,
+) -> AppResult<usize> {
+    let rotation = &run_options.rotation;
+    let mut chunk = 0;
+    let sink = OutputSink::open_chunk(run_options, chunk)?;
+    let mut written = sink.written.clone();
+    let mut wtr = RecordWriter::new(output_mode, &run_options.csv_options, sink)?;
+    let mut chunk_row_count = 0;
+    let mut row_count = 0;
+    while let Some(record) = stream.next().map_err(|e| stream_error(e, data_sources))? {
+        if rotation.is_full(chunk_row_count, written.get()) {
+            wtr.finish()?.finish()?;
+            chunk += 1;
+            chunk_row_count = 0;
+            let sink = OutputSink::open_chunk(run_options, chunk)?;
+            written = sink.written.clone();
+            wtr = RecordWriter::new(output_mode, &run_options.csv_options, sink)?;
+        }
+
+        wtr.write_record(&record, run_options)?;
+        chunk_row_count += 1;
+        row_count += 1;
+    }
+    wtr.finish()?.finish()?;
 
-    let mut stream = physical_plan.get(variables)?;
+    Ok(row_count)
+}
+
+//The csv record being written, shared with the csv writer. The csv writer buffers up to 8 KiB on its own, so the
+//record is taken out of it and written to the output at once, for the bytes of the output to include it.
+#[derive(Default, Clone)]
+struct RecordBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for RecordBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+//The records are written one by one, so the result set is never held in memory except for the table.
+//The table and the json array are closed by finish.
+enum RecordWriter<W: Write> {
+    Table(prettytable::Table, W),
+    Csv(Box<Writer<RecordBuffer>>, RecordBuffer, W),
+    Json(W, usize),
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn new(output_mode: &OutputMode, csv_options: &CsvOptions, mut wtr: W) -> AppResult<Self> {
+        match output_mode {
+            OutputMode::Table => Ok(RecordWriter::Table(prettytable::Table::new(), wtr)),
+            OutputMode::Csv => {
+                let record = RecordBuffer::default();
+                Ok(RecordWriter::Csv(
+                    Box::new(csv_options.writer(record.clone())),
+                    record,
+                    wtr,
+                ))
+            }
+            OutputMode::Json => {
+                wtr.write_all(b"[")?;
+                Ok(RecordWriter::Json(wtr, 0))
+            }
+        }
+    }
 
-    match output_mode {
-        OutputMode::Table => {
-            let mut table = Table::new();
+    fn write_record(&mut self, record: &execution::stream::Record, run_options: &RunOptions) -> AppResult<()> {
+        let datetime_format = &run_options.datetime_format;
+        match self {
+            RecordWriter::Table(table, _) => {
+                table.add_row(Row::new(record.to_row(datetime_format, run_options.number_format)));
+            }
+            RecordWriter::Csv(csv_wtr, buffer, wtr) => {
+                csv_wtr.write_record(record.to_csv_record(datetime_format))?;
+                csv_wtr.flush()?;
+                wtr.write_all(&buffer.0.borrow())?;
+                buffer.0.borrow_mut().clear();
+            }
+            RecordWriter::Json(wtr, row_count) => {
+                if *row_count > 0 {
+                    wtr.write_all(b",")?;
+                }
+                *row_count += 1;
 
-            while let Some(record) = stream.next()? {
-                table.add_row(Row::new(record.to_row()));
+                let mut obj = json::JsonValue::new_object();
+                for (key, val) in record.to_tuples() {
+                    obj[key] = common::types::value_to_json(&val, datetime_format);
+                }
+                obj.write(wtr)?;
             }
-            table.printstd();
         }
-        OutputMode::Csv => {
-            let mut wtr = Writer::from_writer(std::io::stdout());
-            while let Some(record) = stream.next()? {
-                let csv_record = record.to_csv_record();
-                wtr.write_record(csv_record)?;
+
+        Ok(())
+    }
+
+    fn finish(self) -> AppResult<W> {
+        match self {
+            RecordWriter::Table(table, mut wtr) => {
+                table.print(&mut wtr)?;
+                Ok(wtr)
+            }
+            RecordWriter::Csv(_, _, wtr) => Ok(wtr),
+            RecordWriter::Json(mut wtr, _) => {
+                wtr.write_all(b"]\n")?;
+                Ok(wtr)
             }
         }
-        OutputMode::Json => {
-            let mut data = json::JsonValue::new_array();
-            while let Some(record) = stream.next()? {
+    }
+}
+
+//One json line per row added or removed since the previous run, the rows are compared as a whole by their json
+fn write_delta<W: Write>(
+    wtr: &mut W,
+    previous: &[json::JsonValue],
+    current: &[json::JsonValue],
+    time: &str,
+) -> AppResult<()> {
+    let previous_rows: HashSet<String> = previous.iter().map(|row| row.dump()).collect();
+    let current_rows: HashSet<String> = current.iter().map(|row| row.dump()).collect();
+    let removed = previous.iter().filter(|row| !current_rows.contains(&row.dump()));
+    let added = current.iter().filter(|row| !previous_rows.contains(&row.dump()));
+    for (op, row) in removed.map(|row| ("remove", row)).chain(added.map(|row| ("add", row))) {
+        let line = json::object! {
+            "time" => time,
+            "op" => op,
+            "row" => row.clone()
+        };
+        writeln!(wtr, "{}", line.dump())?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+//The rows of the windows closed since the previous run, sorted by the window. A window is closed once the logs have
+//a later one, so the latest window is still open.
+fn closed_windows(
+    records: Vec<execution::stream::Record>,
+    emitted: Option<chrono::DateTime<chrono::FixedOffset>>,
+) -> Vec<(chrono::DateTime<chrono::FixedOffset>, execution::stream::Record)> {
+    let window = syntax::ast::PathExpr::new(vec![syntax::ast::PathSegment::AttrName("window".to_string())]);
+    let mut rows: Vec<(chrono::DateTime<chrono::FixedOffset>, execution::stream::Record)> = records
+        .into_iter()
+        .filter_map(|record| match record.get(&window) {
+            common::types::Value::DateTime(dt) => Some((dt, record)),
+            _ => None,
+        })
+        .collect();
+    let open = rows.iter().map(|(dt, _)| *dt).max();
+
+    rows.retain(|(dt, _)| Some(*dt) < open && emitted.is_none_or(|emitted| *dt > emitted));
+    rows.sort_by_key(|(dt, _)| *dt);
+    rows
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, AtomicOrdering::SeqCst);
+}
+
+//Ctrl-C, or Ctrl-D on the terminal, ends --follow after the current run instead of killing the process, so that the
+//audit log is still written. The stdin is only watched in the foreground, where reading it doesn't stop the process.
+fn watch_interrupts() {
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+        libc::signal(
+            libc::SIGTERM,
+            interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+
+    let foreground = unsafe { libc::isatty(0) == 1 && libc::tcgetpgrp(0) == libc::getpgrp() };
+    if foreground {
+        std::thread::spawn(|| {
+            let _ = std::io::copy(&mut std::io::stdin(), &mut std::io::sink());
+            INTERRUPTED.store(true, AtomicOrdering::SeqCst);
+        });
+    }
+}
+
+//Sleeps for the duration unless interrupted, false if interrupted
+fn sleep_unless_interrupted(duration: std::time::Duration) -> bool {
+    let deadline = std::time::Instant::now() + duration;
+    while !INTERRUPTED.load(AtomicOrdering::SeqCst) {
+        let now = std::time::Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep((deadline - now).min(std::time::Duration::from_millis(100)));
+    }
+    false
+}
+
+//Runs the query every interval until interrupted, and returns the number of the rows of all the runs. The files are
+//read from where the previous run stopped and the aggregates are kept between the runs, unless an operator of the
+//query can't continue, e.g. a gap fill over the appended records, then the query runs again from the start. The
+//output of every run is appended to the same output file or stdout.
+pub(crate) fn follow(
+    query_str: &str,
+    data_sources: Vec<common::types::DataSource>,
+    output_mode: OutputMode,
+    run_options: &RunOptions,
+) -> AppResult<usize> {
+    let follow = run_options.follow.clone().unwrap();
+    let (_, select_stmts) = syntax::parser::union_query(query_str).map_err(|e| AppError::syntax(query_str, e))?;
+    let windowed = select_stmts.iter().any(|stmt| stmt.emit_every_opt.is_some());
+    //The reader thread ends with the input, so it can't follow it
+    let data_sources: Vec<common::types::DataSource> = data_sources
+        .into_iter()
+        .map(|data_source| data_source.with_channel_capacity(None).with_follow(true))
+        .collect();
+    watch_interrupts();
+
+    let mut sink = OutputSink::open(run_options.output_file.as_deref(), run_options.compression)?;
+    let mut stream: Option<Box<dyn execution::stream::RecordStream>> = None;
+    let mut previous = Vec::new();
+    let mut emitted = None;
+    let mut row_count = 0;
+    loop {
+        let started = std::time::Instant::now();
+        let time = chrono::Utc::now().to_rfc3339();
+        let (mut current_stream, resume) = match stream.take() {
+            Some(mut s) => match s.resume() {
+                execution::stream::Resume::Unsupported => (
+                    build_stream(query_str, &data_sources, &output_mode, run_options)?,
+                    execution::stream::Resume::Full,
+                ),
+                resume => (s, resume),
+            },
+            None => (
+                build_stream(query_str, &data_sources, &output_mode, run_options)?,
+                execution::stream::Resume::Full,
+            ),
+        };
+
+        if windowed {
+            let mut records = Vec::new();
+            while let Some(record) = current_stream.next().map_err(|e| stream_error(e, &data_sources))? {
+                records.push(record);
+            }
+
+            let rows = closed_windows(records, emitted);
+            if !rows.is_empty() {
+                let mut wtr = RecordWriter::new(&output_mode, &run_options.csv_options, &mut sink)?;
+                for (dt, record) in rows.iter() {
+                    wtr.write_record(record, run_options)?;
+                    emitted = Some(*dt);
+                }
+                wtr.finish()?.flush()?;
+                row_count += rows.len();
+            }
+        } else if follow.delta {
+            let mut current = Vec::new();
+            while let Some(record) = current_stream.next().map_err(|e| stream_error(e, &data_sources))? {
                 let mut obj = json::JsonValue::new_object();
                 for (key, val) in record.to_tuples() {
-                    match val {
-                        common::types::Value::Boolean(b) => {
-                            obj[key] = b.into();
-                        }
-                        common::types::Value::DateTime(dt) => {
-                            obj[key] = dt.to_string().into();
-                        }
-                        common::types::Value::Float(f) => {
-                            obj[key] = f.into_inner().into();
-                        }
-                        common::types::Value::Host(h) => {
-                            obj[key] = h.to_string().into();
-                        }
-                        common::types::Value::HttpRequest(h) => {
-                            obj[key] = h.to_string().into();
-                        }
-                        common::types::Value::Int(i) => {
-                            obj[key] = i.into();
-                        }
-                        common::types::Value::Null => {
-                            obj[key] = json::Null;
-                        }
-                        common::types::Value::String(s) => {
-                            obj[key] = s.into();
-                        }
-                        common::types::Value::Missing => obj[key] = json::Null,
-                        common::types::Value::Object(_) => {
-                            //
-                            obj[key] = json::JsonValue::String("{ ... }".to_string());
-                        }
-                        common::types::Value::Array(_) => {
-                            obj[key] = json::JsonValue::String("[ ... ]".to_string());
-                        }
-                    }
+                    obj[key] = common::types::value_to_json(&val, &run_options.datetime_format);
                 }
+                current.push(obj);
+            }
 
-                data.push(obj)?;
+            //The appended records are only added, the earlier ones are still in the result
+            if resume == execution::stream::Resume::Incremental {
+                write_delta(&mut sink, &[], &current, &time)?;
+                row_count += current.len();
+                previous.extend(current);
+            } else {
+                write_delta(&mut sink, &previous, &current, &time)?;
+                row_count += current.len();
+                previous = current;
+            }
+        } else {
+            if matches!(output_mode, OutputMode::Table) && !run_options.quiet {
+                writeln!(sink, "Snapshot at {}", time)?;
             }
-            let s = data.dump();
-            println!("{}", s);
+            row_count += write_stream(
+                current_stream.as_mut(),
+                &data_sources,
+                &output_mode,
+                run_options,
+                Some(&mut sink),
+            )?;
+        }
+        stream = Some(current_stream);
+
+        if !sleep_unless_interrupted(follow.interval.saturating_sub(started.elapsed())) {
+            sink.finish()?;
+            return Ok(row_count);
+        }
+    }
+}
+
+//One JSON line per run is appended to the audit log, including the failed runs
+pub(crate) fn write_audit_log(
+    path: &Path,
+    query_str: &str,
+    inputs: &[String],
+    result: &AppResult<usize>,
+    duration: std::time::Duration,
+) -> AppResult<()> {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    let mut entry = json::JsonValue::new_object();
+    entry["time"] = chrono::Local::now().to_rfc3339().into();
+    entry["user"] = user.into();
+    entry["query"] = query_str.into();
+    entry["inputs"] = inputs.to_vec().into();
+    entry["duration_ms"] = (duration.as_millis() as u64).into();
+    match result {
+        Ok(row_count) => {
+            entry["rows"] = (*row_count).into();
+            entry["status"] = "ok".into();
+        }
+        Err(e) => {
+            entry["rows"] = json::JsonValue::Null;
+            entry["status"] = "error".into();
+            entry["error"] = e.to_string().into();
+            entry["error_code"] = e.code().into();
+        }
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| AppError::WriteAuditLog(path.display().to_string(), e.to_string()))?;
+    //The line is written at once, so the concurrent runs on a shared host don't interleave
+    file.write_all(format!("{}\n", entry.dump()).as_bytes())
+        .map_err(|e| AppError::WriteAuditLog(path.display().to_string(), e.to_string()))?;
+
+    Ok(())
+}
+
+//Writes the typed columnar copy of each log file, the directories are expanded to their log files.
+//The later queries read the copy instead of parsing the log file again.
+pub(crate) fn convert(
+    paths: Vec<PathBuf>,
+    file_format: &str,
+    options: &execution::datasource::ReaderOptions,
+    target: &str,
+) -> AppResult<()> {
+    if target != "logqc" {
+        return Err(AppError::UnsupportedConversionTarget(target.to_string()));
+    }
+
+    let builder = execution::datasource::ReaderBuilder::new(file_format.to_string())
+        .field_types(options.field_types.clone())
+        .time_format(options.time_format.clone())
+        .log_format(options.log_format.clone());
+    for path in paths.into_iter() {
+        let files = if path.is_dir() {
+            execution::datasource::list_log_files(&path).map_err(execution::datasource::ReaderError::Io)?
+        } else {
+            vec![path]
+        };
+
+        for file in files.into_iter() {
+            let (copy_path, count) = execution::columnar::convert(&builder, &file)?;
+            println!("{} -> {} ({} records)", file.display(), copy_path.display(), count);
         }
     }
 
     Ok(())
 }
 
+//The table a select statement reads is the first segment of its from clause
+fn find_data_source(
+    select_stmt: &syntax::ast::SelectStatement,
+    data_sources: &[common::types::DataSource],
+) -> AppResult<common::types::DataSource> {
+    match select_stmt.table_references.first() {
+        Some(table_reference) => find_table(table_reference, data_sources),
+        None => Err(AppError::InvalidTableSpecString),
+    }
+}
+
+fn find_table(
+    table_reference: &syntax::ast::TableReference,
+    data_sources: &[common::types::DataSource],
+) -> AppResult<common::types::DataSource> {
+    let table_name = match table_reference.path_expr.path_segments.first() {
+        Some(syntax::ast::PathSegment::AttrName(s)) => s.clone(),
+        _ => return Err(AppError::InvalidTableSpecString),
+    };
+
+    data_sources
+        .iter()
+        .find(|d| d.table_name() == table_name)
+        .cloned()
+        .ok_or(AppError::UnknownTable(table_name))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::execution::datasource::ReaderOptions;
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_query() {
+        let tables = [Table::new("it", "elb", "data/AWSELB.log")];
+        let mut buf = Vec::new();
+        let row_count = query("SELECT count(*) AS c FROM it", &tables, OutputMode::Csv, &mut buf).unwrap();
+        assert_eq!(1, row_count);
+        assert_eq!("668\n", String::from_utf8(buf).unwrap());
+
+        //The code and the span are kept through anyhow
+        let result = query("select from it", &tables, OutputMode::Csv, Vec::new());
+        let e = anyhow::Error::from(result.unwrap_err());
+        let e = e.downcast_ref::<Error>().unwrap();
+        assert_eq!(ErrorKind::Syntax, e.kind());
+        assert_eq!("syntax", e.code());
+        assert!(e.span().is_some());
+
+        let tables = [Table::new("it", "nope", "data/AWSELB.log")];
+        let e = query("select * from it", &tables, OutputMode::Csv, Vec::new()).unwrap_err();
+        assert_eq!(ErrorKind::InvalidLogFileFormat, e.kind());
+        assert_eq!("invalid_log_file_format", e.code());
+        assert_eq!(None, e.span());
+    }
+
+    #[test]
+    fn test_sum_nullable_columns() {
+        //The "-" of the turn around time and of the bytes sent are nulls, which the sums skip
+        let tables = [
+            Table::new("s", "s3", "tests/golden/s3.log"),
+            Table::new("a", "apache", "tests/golden/apache.log"),
+        ];
+        let mut buf = Vec::new();
+        query(
+            r#"select sum(turn_around_time) as t, sum_if(turn_around_time, http_status = "200") as u from s"#,
+            &tables,
+            OutputMode::Csv,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!("6,6\n", String::from_utf8(buf).unwrap());
+
+        let mut buf = Vec::new();
+        query("select sum(bytes_sent) as b from a", &tables, OutputMode::Csv, &mut buf).unwrap();
+        assert_eq!("4652\n", String::from_utf8(buf).unwrap());
+    }
+
+    #[test]
+    fn test_union_order_by_and_limit() {
+        //The order by and the limit after the last select are over the rows of the whole union
+        let tables = [
+            Table::new("a", "elb", "data/AWSELB.log"),
+            Table::new("b", "elb", "data/AWSELB.log"),
+        ];
+        let mut buf = Vec::new();
+        let row_count = query(
+            "select elb_status_code from a union all select elb_status_code from b limit 1",
+            &tables,
+            OutputMode::Csv,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(1, row_count);
+
+        let mut buf = Vec::new();
+        query(
+            r#"select count(*) as c from a union all select count(*) as c from b where elb_status_code = "200" order by 1 asc"#,
+            &tables,
+            OutputMode::Csv,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!("538\n668\n", String::from_utf8(buf).unwrap());
+
+        let result = query(
+            "select count(*) as c from a union all select count(*) as c from b order by 2 asc",
+            &tables,
+            OutputMode::Csv,
+            Vec::new(),
+        );
+        assert_eq!(ErrorKind::Parse, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_join() {
+        let dir = tempdir().unwrap();
+        let users_path = dir.path().join("users.log");
+        let mut file = File::create(&users_path).unwrap();
+        writeln!(file, r#"{{"id": 1, "name": "a"}}"#).unwrap();
+        writeln!(file, r#"{{"id": 2, "name": "b"}}"#).unwrap();
+        let orders_path = dir.path().join("orders.log");
+        let mut file = File::create(&orders_path).unwrap();
+        writeln!(file, r#"{{"user_id": 1, "amount": 5}}"#).unwrap();
+        writeln!(file, r#"{{"user_id": 1, "amount": 7}}"#).unwrap();
+        writeln!(file, r#"{{"user_id": 3, "amount": 1}}"#).unwrap();
+        let tables = [
+            Table::new("users", "jsonl", &users_path),
+            Table::new("orders", "jsonl", &orders_path),
+        ];
+
+        let mut buf = Vec::new();
+        query(
+            "select u.name, o.amount from users as u join orders as o on u.id = o.user_id",
+            &tables,
+            OutputMode::Csv,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!("a,5\na,7\n", String::from_utf8(buf).unwrap());
+
+        let mut buf = Vec::new();
+        query(
+            "select u.name, sum(o.amount) as total from users as u left join orders as o on o.user_id = u.id \
+             group by u.name order by total desc",
+            &tables,
+            OutputMode::Csv,
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!("a,12\nb,<null>\n", String::from_utf8(buf).unwrap());
+
+        dir.close().unwrap();
+    }
+
     #[test]
     fn test_run_explain_mode() {
         let query_str = "select * from it";
@@ -249,10 +1512,15 @@ mod tests {
         file.sync_all().unwrap();
         drop(file);
 
-        let data_source = common::types::DataSource::File(file_path, file_format.clone(), table_name.clone());
-        let result = run(&*query_str, data_source, OutputMode::Csv);
+        let data_source = common::types::DataSource::File(
+            file_path,
+            file_format.clone(),
+            table_name.clone(),
+            ReaderOptions::default(),
+        );
+        let result = run(query_str, vec![data_source], OutputMode::Csv, &RunOptions::default());
 
-        assert_eq!(result, Ok(()));
+        assert_eq!(result.map(|_| ()), Ok(()));
 
         dir.close().unwrap();
     }
@@ -268,34 +1536,193 @@ mod tests {
         file.sync_all().unwrap();
         drop(file);
 
-        let data_source = common::types::DataSource::File(file_path, file_format.clone(), table_name.clone());
+        let data_source = common::types::DataSource::File(
+            file_path,
+            file_format.clone(),
+            table_name.clone(),
+            ReaderOptions::default(),
+        );
         let result = run(
             r#"select t, sum(sent_bytes) as s from it group by time_bucket("5 seconds", timestamp) as t order by t asc limit 1"#,
-            data_source.clone(),
+            vec![data_source.clone()],
             OutputMode::Csv,
+            &RunOptions::default(),
         );
-        assert_eq!(result, Ok(()));
+        assert_eq!(result.map(|_| ()), Ok(()));
 
         let result = run(
             r#"select time_bucket("5 seconds", timestamp) as t, url_path_bucket(request, 1, "_") as s from it limit 1"#,
-            data_source.clone(),
+            vec![data_source.clone()],
             OutputMode::Csv,
+            &RunOptions::default(),
         );
-        assert_eq!(result, Ok(()));
+        assert_eq!(result.map(|_| ()), Ok(()));
 
         let result = run(
             r#"select time_bucket("5 seconds", timestamp) as t, percentile_disc(0.9) within group (order by backend_processing_time asc) as bps from it group by t"#,
-            data_source.clone(),
+            vec![data_source.clone()],
             OutputMode::Csv,
+            &RunOptions::default(),
         );
-        assert_eq!(result, Ok(()));
+        assert_eq!(result.map(|_| ()), Ok(()));
 
         let result = run(
             r#"select time_bucket("5 seconds", timestamp) as t, approx_percentile(0.9) within group (order by backend_processing_time asc) as bps from it group by t"#,
-            data_source.clone(),
+            vec![data_source.clone()],
+            OutputMode::Csv,
+            &RunOptions::default(),
+        );
+        assert_eq!(result.map(|_| ()), Ok(()));
+
+        let result = run(
+            r#"select percentile_disc(0.9, received_bytes) within group (order by backend_processing_time asc) as p, approx_percentile(0.9, received_bytes) within group (order by backend_processing_time asc) as q from it"#,
+            vec![data_source.clone()],
+            OutputMode::Csv,
+            &RunOptions::default(),
+        );
+        assert_eq!(result.map(|_| ()), Ok(()));
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_run_command_data_source() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("log_for_test.log");
+        let mut file = File::create(file_path.clone()).unwrap();
+        writeln!(file, r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_DENIED/407 3922 CONNECT d.dropbox.com:443 - HIER_NONE/- text/html"#).unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+
+        let data_source = common::types::DataSource::Command(
+            format!("cat {}", file_path.display()),
+            "squid".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        );
+        let result = run(
+            "select count(*) from it",
+            vec![data_source],
+            OutputMode::Csv,
+            &RunOptions::default(),
+        );
+        assert_eq!(result.map(|_| ()), Ok(()));
+
+        let data_source = common::types::DataSource::Command(
+            "exit 1".to_string(),
+            "squid".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        );
+        let result = run(
+            "select count(*) from it",
+            vec![data_source],
             OutputMode::Csv,
+            &RunOptions::default(),
         );
-        assert_eq!(result, Ok(()));
+        assert!(result.is_err());
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_convert_and_run_on_converted_copy() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("log_for_test.log");
+        let mut file = File::create(file_path.clone()).unwrap();
+        writeln!(file, r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_DENIED/407 3922 CONNECT d.dropbox.com:443 - HIER_NONE/- text/html"#).unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+
+        let result = convert(
+            vec![dir.path().to_path_buf()],
+            "squid",
+            &ReaderOptions::default(),
+            "parquet",
+        );
+        assert_eq!(
+            result,
+            Err(AppError::UnsupportedConversionTarget("parquet".to_string()))
+        );
+
+        let result = convert(
+            vec![dir.path().to_path_buf()],
+            "squid",
+            &ReaderOptions::default(),
+            "logqc",
+        );
+        assert_eq!(result.map(|_| ()), Ok(()));
+        assert!(execution::columnar::converted_path(&file_path).exists());
+
+        //Both the file and the directory tables read the copy, the directory listing skips the copy itself
+        for path in [file_path.clone(), dir.path().to_path_buf()] {
+            let data_source =
+                common::types::DataSource::File(path, "squid".to_string(), "it".to_string(), ReaderOptions::default());
+            let result = run(
+                "select count(*) from it",
+                vec![data_source],
+                OutputMode::Csv,
+                &RunOptions::default(),
+            );
+            assert_eq!(result.map(|_| ()), Ok(()));
+        }
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_run_union_of_tables() {
+        let dir = tempdir().unwrap();
+        let squid_path = dir.path().join("squid.log");
+        let mut file = File::create(squid_path.clone()).unwrap();
+        writeln!(file, r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_DENIED/407 3922 CONNECT d.dropbox.com:443 - HIER_NONE/- text/html"#).unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+
+        let jsonl_path = dir.path().join("jsonl.log");
+        let mut file = File::create(jsonl_path.clone()).unwrap();
+        writeln!(file, r#"{{"a": 1, "b": "123"}}"#).unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+
+        let data_sources = vec![
+            common::types::DataSource::File(
+                squid_path,
+                "squid".to_string(),
+                "s".to_string(),
+                ReaderOptions::default(),
+            ),
+            common::types::DataSource::File(
+                jsonl_path,
+                "jsonl".to_string(),
+                "j".to_string(),
+                ReaderOptions::default(),
+            ),
+        ];
+        let result = run(
+            "select count(*) as c from s union all select count(*) as c from j",
+            data_sources.clone(),
+            OutputMode::Csv,
+            &RunOptions::default(),
+        );
+        assert_eq!(result.map(|_| ()), Ok(()));
+
+        let result = run(
+            "select count(*) as c from s union all select a, count(*) as c from j group by a",
+            data_sources.clone(),
+            OutputMode::Csv,
+            &RunOptions::default(),
+        );
+        assert_eq!(
+            result,
+            Err(AppError::UnionColumnsMismatch(
+                vec!["c".to_string()],
+                vec!["a".to_string(), "c".to_string()]
+            ))
+        );
+
+        let result = run("select * from x", data_sources, OutputMode::Csv, &RunOptions::default());
+        assert_eq!(result, Err(AppError::UnknownTable("x".to_string())));
 
         dir.close().unwrap();
     }
@@ -315,28 +1742,490 @@ mod tests {
         file.sync_all().unwrap();
         drop(file);
 
-        let data_source = common::types::DataSource::File(file_path, file_format.clone(), table_name.clone());
+        let data_source = common::types::DataSource::File(
+            file_path,
+            file_format.clone(),
+            table_name.clone(),
+            ReaderOptions::default(),
+        );
         let result = run(
             r#"select b, e.f.g as x from it limit 1"#,
-            data_source.clone(),
+            vec![data_source.clone()],
             OutputMode::Csv,
+            &RunOptions::default(),
         );
-        assert_eq!(result, Ok(()));
+        assert_eq!(result.map(|_| ()), Ok(()));
 
         let result = run(
             r#"select b, count(e.f.g) as x from it group by b"#,
-            data_source.clone(),
+            vec![data_source.clone()],
             OutputMode::Csv,
+            &RunOptions::default(),
         );
-        assert_eq!(result, Ok(()));
+        assert_eq!(result.map(|_| ()), Ok(()));
 
         let result = run(
             r#"select x, count(*) as x from it group by d[0] as x"#,
-            data_source.clone(),
+            vec![data_source.clone()],
+            OutputMode::Csv,
+            &RunOptions::default(),
+        );
+        assert_eq!(result.map(|_| ()), Ok(()));
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_preset_query() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("alb.log");
+        let mut file = File::create(file_path.clone()).unwrap();
+        writeln!(
+            file,
+            r#"http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 503 - 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 1 2018-07-02T22:22:48.364000Z "forward" "-" "-""#
+        )
+        .unwrap();
+        file.sync_all().unwrap();
+        drop(file);
+
+        let data_source = common::types::DataSource::File(
+            file_path.clone(),
+            "alb".to_string(),
+            "lb".to_string(),
+            ReaderOptions::default(),
+        );
+        let query = preset_query("rule-priority", "lb").unwrap();
+        assert!(query.contains("from lb "));
+        let result = run(&query, vec![data_source], OutputMode::Csv, &RunOptions::default());
+        assert_eq!(result.map(|_| ()), Ok(()));
+
+        for preset in ["status-matrix", "client-ports", "desync"].iter() {
+            let query = preset_query(preset, "lb").unwrap();
+            let data_source = common::types::DataSource::File(
+                file_path.clone(),
+                "alb".to_string(),
+                "lb".to_string(),
+                ReaderOptions::default(),
+            );
+            let result = run(&query, vec![data_source], OutputMode::Csv, &RunOptions::default());
+            assert_eq!(result.map(|_| ()), Ok(()));
+        }
+
+        assert_eq!(
+            Err(AppError::UnknownPreset("nope".to_string())),
+            preset_query("nope", "lb")
+        );
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_json_array() {
+        let mut records = std::collections::VecDeque::new();
+        records.push_back(execution::stream::Record::new(
+            &vec!["a".to_string(), "b".to_string()],
+            vec![
+                common::types::Value::Int(1),
+                common::types::Value::String("x".to_string()),
+            ],
+        ));
+        records.push_back(execution::stream::Record::new(
+            &vec!["a".to_string(), "b".to_string()],
+            vec![common::types::Value::Int(2), common::types::Value::Null],
+        ));
+        records.push_back(execution::stream::Record::new(
+            &vec!["a".to_string(), "b".to_string()],
+            vec![
+                common::types::Value::Int(3),
+                common::types::Value::Array(vec![
+                    common::types::Value::String("y".to_string()),
+                    common::types::Value::Null,
+                ]),
+            ],
+        ));
+        let mut wtr = RecordWriter::new(&OutputMode::Json, &CsvOptions::default(), Vec::new()).unwrap();
+        for record in records.iter() {
+            wtr.write_record(record, &RunOptions::default()).unwrap();
+        }
+        assert_eq!(
+            "[{\"a\":1,\"b\":\"x\"},{\"a\":2,\"b\":null},{\"a\":3,\"b\":[\"y\",null]}]\n",
+            String::from_utf8(wtr.finish().unwrap()).unwrap()
+        );
+
+        let wtr = RecordWriter::new(&OutputMode::Json, &CsvOptions::default(), Vec::new()).unwrap();
+        assert_eq!("[]\n", String::from_utf8(wtr.finish().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_write_delta() {
+        let row = |status: &str, c: i32| {
+            json::object! {
+                "status" => status,
+                "c" => c
+            }
+        };
+        let previous = [row("200", 3), row("500", 1)];
+        let current = [row("200", 3), row("500", 2), row("502", 1)];
+
+        let mut wtr = Vec::new();
+        write_delta(&mut wtr, &previous, &current, "2019-06-07T18:00:00+00:00").unwrap();
+        let lines: Vec<json::JsonValue> = String::from_utf8(wtr)
+            .unwrap()
+            .lines()
+            .map(|line| json::parse(line).unwrap())
+            .collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("remove", lines[0]["op"]);
+        assert_eq!(row("500", 1), lines[0]["row"]);
+        assert_eq!("add", lines[1]["op"]);
+        assert_eq!(row("500", 2), lines[1]["row"]);
+        assert_eq!(row("502", 1), lines[2]["row"]);
+        assert_eq!("2019-06-07T18:00:00+00:00", lines[2]["time"]);
+
+        let mut wtr = Vec::new();
+        write_delta(&mut wtr, &current, &current, "2019-06-07T18:00:05+00:00").unwrap();
+        assert!(wtr.is_empty());
+    }
+
+    #[test]
+    fn test_follow_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = File::create(dir.path().join("elb.log")).unwrap();
+        for _ in 0..2 {
+            writeln!(file, r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 200 200 0 42355 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" - -"#).unwrap();
+        }
+        drop(file);
+
+        let data_source = common::types::DataSource::File(
+            dir.path().join("elb.log"),
+            "elb".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        );
+        //Runs once and stops, as if interrupted during the first run
+        INTERRUPTED.store(true, AtomicOrdering::SeqCst);
+        let query_str = "select elb_status_code as s, count(*) as c from it group by elb_status_code";
+        for delta in [false, true].iter() {
+            let run_options = RunOptions {
+                output_file: Some(dir.path().join("out")),
+                follow: Some(Follow {
+                    interval: std::time::Duration::from_secs(1),
+                    delta: *delta,
+                }),
+                ..RunOptions::default()
+            };
+            let output_mode = if *delta { OutputMode::Json } else { OutputMode::Csv };
+            let result = follow(query_str, vec![data_source.clone()], output_mode, &run_options);
+            assert_eq!(Ok(1), result);
+
+            let content = std::fs::read_to_string(dir.path().join("out")).unwrap();
+            if *delta {
+                let line = json::parse(content.trim_end()).unwrap();
+                assert_eq!("add", line["op"]);
+                assert_eq!(2, line["row"]["c"]);
+            } else {
+                assert_eq!("200,2\n", content);
+            }
+        }
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_closed_windows() {
+        let field_names = vec!["window".to_string(), "c".to_string()];
+        let window =
+            |minute: u32| chrono::DateTime::parse_from_rfc3339(&format!("2019-06-07T18:{:02}:00Z", minute)).unwrap();
+        let records = || {
+            vec![
+                execution::stream::Record::new(
+                    &field_names,
+                    vec![common::types::Value::DateTime(window(2)), common::types::Value::Int(1)],
+                ),
+                execution::stream::Record::new(
+                    &field_names,
+                    vec![common::types::Value::DateTime(window(0)), common::types::Value::Int(3)],
+                ),
+                execution::stream::Record::new(
+                    &field_names,
+                    vec![common::types::Value::DateTime(window(1)), common::types::Value::Int(2)],
+                ),
+            ]
+        };
+
+        let rows = closed_windows(records(), None);
+        assert_eq!(
+            vec![window(0), window(1)],
+            rows.iter().map(|(dt, _)| *dt).collect::<Vec<_>>()
+        );
+
+        let rows = closed_windows(records(), Some(window(0)));
+        assert_eq!(vec![window(1)], rows.iter().map(|(dt, _)| *dt).collect::<Vec<_>>());
+        assert!(closed_windows(records(), Some(window(1))).is_empty());
+    }
+
+    #[test]
+    fn test_csv_options() {
+        let record = execution::stream::Record::new(
+            &vec!["a".to_string(), "b".to_string()],
+            vec![
+                common::types::Value::Int(1),
+                common::types::Value::String("say \"hi\", bye".to_string()),
+            ],
+        );
+        let write_csv = |csv_options: &CsvOptions| {
+            let mut wtr = RecordWriter::new(&OutputMode::Csv, csv_options, Vec::new()).unwrap();
+            wtr.write_record(&record, &RunOptions::default()).unwrap();
+            String::from_utf8(wtr.finish().unwrap()).unwrap()
+        };
+
+        assert_eq!("1,\"say \"\"hi\"\", bye\"\n", write_csv(&CsvOptions::default()));
+        let csv_options = CsvOptions {
+            quote_style: CsvOptions::parse_quote_style("always").unwrap(),
+            escape: Some(CsvOptions::parse_escape("\\").unwrap()),
+            crlf: true,
+        };
+        assert_eq!("\"1\",\"say \\\"hi\\\", bye\"\r\n", write_csv(&csv_options));
+        let csv_options = CsvOptions {
+            quote_style: CsvOptions::parse_quote_style("non-numeric").unwrap(),
+            ..CsvOptions::default()
+        };
+        assert_eq!("1,\"say \"\"hi\"\", bye\"\n", write_csv(&csv_options));
+
+        assert!(CsvOptions::parse_quote_style("minimal").is_err());
+        assert!(CsvOptions::parse_escape("ab").is_err());
+    }
+
+    #[test]
+    fn test_run_mixed_alb_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = dir.path().join("logs");
+        std::fs::create_dir(&logs).unwrap();
+        let mut file = File::create(logs.join("2018.log")).unwrap();
+        writeln!(file, r#"http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 200 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-""#).unwrap();
+        drop(file);
+        let mut file = File::create(logs.join("2024.log")).unwrap();
+        writeln!(file, r#"http 2024-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 502 - 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2024-07-02T22:22:48.364000Z "forward" "-" "-" "10.0.0.1:80" "-" "-" "-" "TID_1234abcd5678ef90" "-" "-" "-""#).unwrap();
+        drop(file);
+
+        let data_source =
+            common::types::DataSource::File(logs, "alb".to_string(), "it".to_string(), ReaderOptions::default());
+        let run_options = RunOptions {
+            output_file: Some(dir.path().join("out.csv")),
+            ..RunOptions::default()
+        };
+        let result = run(
+            "select elb_status_code, target_port_list, conn_trace_id from it order by elb_status_code asc",
+            vec![data_source],
             OutputMode::Csv,
+            &run_options,
+        );
+        assert_eq!(Ok(2), result);
+        assert_eq!(
+            "200,<null>,<null>\n502,\"[\"\"10.0.0.1:80\"\"]\",TID_1234abcd5678ef90\n",
+            std::fs::read_to_string(dir.path().join("out.csv")).unwrap()
+        );
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_desync_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("alb.log");
+        let mut file = File::create(file_path.clone()).unwrap();
+        writeln!(file, r#"http 2024-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 400 - 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2024-07-02T22:22:48.364000Z "forward" "-" "-" "10.0.0.1:80" "-" "Acceptable" "-""#).unwrap();
+        writeln!(file, r#"http 2024-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 400 - 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2024-07-02T22:22:48.364000Z "forward" "-" "-" "10.0.0.1:80" "-" "Ambiguous" "UndefinedContentLengthSemantics""#).unwrap();
+        writeln!(file, r#"http 2024-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 400 - 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2024-07-02T22:22:48.364000Z "forward" "-" "-" "10.0.0.1:80" "-" "Severe" "DuplicateContentLength""#).unwrap();
+        writeln!(file, r#"http 2024-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 400 - 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2024-07-02T22:22:48.364000Z "forward" "-" "-" "10.0.0.1:80" "-" "Severe" "DuplicateContentLength""#).unwrap();
+        drop(file);
+
+        let data_source =
+            common::types::DataSource::File(file_path, "alb".to_string(), "it".to_string(), ReaderOptions::default());
+        let run_options = RunOptions {
+            output_file: Some(dir.path().join("out.csv")),
+            ..RunOptions::default()
+        };
+        let query = preset_query("desync", "it").unwrap();
+        let result = run(&query, vec![data_source], OutputMode::Csv, &run_options);
+        assert_eq!(Ok(2), result);
+        assert_eq!(
+            "Severe,DuplicateContentLength,2\nAmbiguous,UndefinedContentLengthSemantics,1\n",
+            std::fs::read_to_string(dir.path().join("out.csv")).unwrap()
         );
-        assert_eq!(result, Ok(()));
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_s3_requesters_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("s3.log");
+        let mut file = File::create(file_path.clone()).unwrap();
+        writeln!(file, r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be awsexamplebucket [06/Feb/2019:00:00:38 +0000] 192.0.2.3 arn:aws:iam::123456789012:user/alice 3E57427F3EXAMPLE REST.GET.OBJECT photos/2019/puppy.jpg "GET /awsexamplebucket/photos/2019/puppy.jpg HTTP/1.1" 200 - 113 113 7 4 "-" "aws-cli/2.0" - s9lzHYrFp76ZVxRcpX9= SigV4 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader awsexamplebucket.s3.amazonaws.com TLSv1.2"#).unwrap();
+        writeln!(file, r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be awsexamplebucket [06/Feb/2019:00:00:39 +0000] 192.0.2.3 arn:aws:iam::123456789012:user/alice 3E57427F3EXAMPLE REST.GET.OBJECT photos/2020/kitten.jpg "GET /awsexamplebucket/photos/2020/kitten.jpg HTTP/1.1" 404 NoSuchKey 243 - 9 - "-" "aws-cli/2.0" - s9lzHYrFp76ZVxRcpX9= SigV4 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader awsexamplebucket.s3.amazonaws.com TLSv1.2"#).unwrap();
+        drop(file);
 
+        let data_source =
+            common::types::DataSource::File(file_path, "s3".to_string(), "it".to_string(), ReaderOptions::default());
+        let run_options = RunOptions {
+            output_file: Some(dir.path().join("out.csv")),
+            ..RunOptions::default()
+        };
+        let query = preset_query("s3-requesters", "it").unwrap();
+        let result = run(&query, vec![data_source], OutputMode::Csv, &run_options);
+        assert_eq!(Ok(1), result);
+        assert_eq!(
+            "REST.GET.OBJECT,arn:aws:iam::123456789012:user/alice,awsexamplebucket,photos/,2,0.5,4,9\n",
+            std::fs::read_to_string(dir.path().join("out.csv")).unwrap()
+        );
         dir.close().unwrap();
     }
+
+    #[test]
+    fn test_rotate_output_files() {
+        assert_eq!(Some("out-007.csv".to_string()), chunk_path("out-%03d.csv", 7));
+        assert_eq!(Some("out-12.csv".to_string()), chunk_path("out-%d.csv", 12));
+        assert_eq!(None, chunk_path("out.csv", 1));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = File::create(dir.path().join("elb.log")).unwrap();
+        for _ in 0..5 {
+            writeln!(file, r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 200 200 0 42355 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" - -"#).unwrap();
+        }
+        drop(file);
+
+        let data_source = common::types::DataSource::File(
+            dir.path().join("elb.log"),
+            "elb".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        );
+        let run_options = RunOptions {
+            output_file: Some(dir.path().join("out-%02d.csv")),
+            rotation: Rotation {
+                rows: Some(2),
+                bytes: None,
+            },
+            ..RunOptions::default()
+        };
+        let result = run(
+            "select elb_status_code from it",
+            vec![data_source.clone()],
+            OutputMode::Csv,
+            &run_options,
+        );
+        assert_eq!(Ok(5), result);
+
+        let chunks: Vec<String> = (0..3)
+            .map(|i| std::fs::read_to_string(dir.path().join(format!("out-{:02}.csv", i))).unwrap())
+            .collect();
+        assert_eq!(vec!["200\n200\n", "200\n200\n", "200\n"], chunks);
+        assert!(!dir.path().join("out-03.csv").exists());
+
+        //The bytes of the csv records are counted as each record is written
+        let run_options = RunOptions {
+            output_file: Some(dir.path().join("bytes-%d.csv")),
+            rotation: Rotation {
+                rows: None,
+                bytes: Some(6),
+            },
+            ..RunOptions::default()
+        };
+        let result = run(
+            "select elb_status_code from it",
+            vec![data_source],
+            OutputMode::Csv,
+            &run_options,
+        );
+        assert_eq!(Ok(5), result);
+
+        let chunks: Vec<String> = (0..3)
+            .map(|i| std::fs::read_to_string(dir.path().join(format!("bytes-{}.csv", i))).unwrap())
+            .collect();
+        assert_eq!(vec!["200\n200\n", "200\n200\n", "200\n"], chunks);
+        assert!(!dir.path().join("bytes-3.csv").exists());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_audit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let inputs = ["it:alb=data/AWSALB.log".to_string()];
+
+        write_audit_log(
+            &path,
+            "select count(*) from it",
+            &inputs,
+            &Ok(1),
+            std::time::Duration::from_millis(12),
+        )
+        .unwrap();
+        write_audit_log(
+            &path,
+            "select",
+            &inputs,
+            &Err(AppError::InvalidTableSpecString),
+            std::time::Duration::from_millis(1),
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<json::JsonValue> = content.lines().map(|l| json::parse(l).unwrap()).collect();
+        assert_eq!(2, entries.len());
+        assert_eq!("select count(*) from it", entries[0]["query"]);
+        assert_eq!("it:alb=data/AWSALB.log", entries[0]["inputs"][0]);
+        assert_eq!(1, entries[0]["rows"]);
+        assert_eq!(12, entries[0]["duration_ms"]);
+        assert_eq!("ok", entries[0]["status"]);
+        assert_eq!("error", entries[1]["status"]);
+        assert_eq!("invalid_table_spec", entries[1]["error_code"]);
+        assert!(entries[1]["rows"].is_null());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_error_codes_and_spans() {
+        let data_source =
+            common::types::DataSource::Stdin("elb".to_string(), "it".to_string(), ReaderOptions::default());
+        let query = "select a from it whre a = 1";
+        let e = explain(query, data_source.clone(), &syntax::macros::Macros::default()).unwrap_err();
+        assert_eq!("input_not_all_consumed", e.code());
+        assert_eq!(Some(Span { start: 17, end: 27 }), e.span());
+
+        let query = "select a frm it";
+        let e = explain(query, data_source, &syntax::macros::Macros::default()).unwrap_err();
+        assert_eq!("syntax", e.code());
+        assert_eq!(Some(Span { start: 9, end: 12 }), e.span());
+        let obj = e.to_json();
+        assert_eq!("syntax", obj["code"]);
+        assert_eq!(9, obj["span"]["start"]);
+
+        let e = AppError::Stream(execution::types::StreamError::Reader);
+        assert_eq!("stream", e.code());
+        assert!(e.to_json()["span"].is_null());
+        assert!(std::error::Error::source(&e).is_some());
+    }
+
+    #[test]
+    fn test_unknown_column_with_similar_fields() {
+        let data_source = common::types::DataSource::File(
+            PathBuf::from("data/AWSALB.log"),
+            "alb".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let run_options = RunOptions {
+            columns: Some(vec!["elb_status".to_string()]),
+            output_file: Some(dir.path().join("out.csv")),
+            ..RunOptions::default()
+        };
+        let e = run("select * from it", vec![data_source], OutputMode::Csv, &run_options).unwrap_err();
+        assert_eq!("unknown_column", e.code());
+        assert!(e.to_string().starts_with(
+            "Unknown column \"elb_status\", the similar fields of alb: elb_status_code (the status code of the response \
+             from the load balancer, e.g. 200)"
+        ));
+
+        let e = stream_error(execution::types::StreamError::UnknownColumn("xyz".to_string()), &[]);
+        assert_eq!("Unknown column \"xyz\"", e.to_string());
+    }
 }
@@ -0,0 +1,544 @@
+use crate::app;
+use crate::app::AppError;
+use crate::common;
+use crate::execution;
+use crate::syntax;
+use clap::load_yaml;
+use clap::{App, ArgMatches};
+use prettytable::{Cell, Row, Table};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+lazy_static! {
+    //FIXME: use different type for string hostname and Ipv4
+    static ref TABLE_SPEC_REGEX: Regex = Regex::new(r#"^([0-9a-zA-Z]+):([0-9a-zA-Z_]+)"#).unwrap();
+}
+
+//The name and the value of each option of a table spec
+type TableOptions = Vec<(String, String)>;
+
+//The options in the parentheses after the format of a table spec, e.g. (time_format="%d/%b/%Y:%H:%M:%S %z"), and the
+//rest of the spec. The value with a comma or a parenthesis is quoted with " or ', and \ escapes the quote in it.
+fn parse_table_options(spec: &str) -> Result<(TableOptions, &str), AppError> {
+    let invalid = |reason: &str| AppError::InvalidTableOption(reason.to_string());
+    let mut options = Vec::new();
+    let mut rest = match spec.strip_prefix('(') {
+        Some(rest) => rest,
+        None => return Ok((options, spec)),
+    };
+
+    loop {
+        let eq = rest.find('=').ok_or_else(|| invalid("expect name=value"))?;
+        let name = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..].trim_start();
+
+        let mut value = String::new();
+        match rest.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                let mut end = None;
+                let mut chars = rest.char_indices().skip(1).peekable();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' if chars.peek().map(|(_, c)| *c) == Some(quote) => {
+                            value.push(quote);
+                            chars.next();
+                        }
+                        c if c == quote => {
+                            end = Some(i + 1);
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                rest = &rest[end.ok_or_else(|| invalid("unterminated quote"))?..];
+            }
+            _ => {
+                let end = rest.find([',', ')']).unwrap_or(rest.len());
+                value.push_str(rest[..end].trim());
+                rest = &rest[end..];
+            }
+        }
+        options.push((name, value));
+
+        rest = rest.trim_start();
+        if let Some(next) = rest.strip_prefix(',') {
+            rest = next;
+        } else if let Some(next) = rest.strip_prefix(')') {
+            return Ok((options, next));
+        } else {
+            return Err(invalid("expect a comma or a closing parenthesis after the value"));
+        }
+    }
+}
+
+fn parse_table_spec(table_spec_string: &str, sub_m: &ArgMatches) -> Result<common::types::DataSource, AppError> {
+    let cap = TABLE_SPEC_REGEX
+        .captures(table_spec_string)
+        .ok_or(AppError::InvalidTableSpecString)?;
+    let table_name = cap.get(1).map_or("", |m| m.as_str()).to_string();
+    let file_format = cap.get(2).map_or("", |m| m.as_str()).to_string();
+    let (table_options, rest) = parse_table_options(&table_spec_string[cap.get(0).unwrap().end()..])?;
+    let file_path = match rest.strip_prefix('=') {
+        Some(file_path) if !file_path.is_empty() => file_path.to_string(),
+        _ => return Err(AppError::InvalidTableSpecString),
+    };
+
+    if !execution::datasource::is_supported_format(&file_format) {
+        return Err(AppError::InvalidLogFileFormat);
+    }
+
+    let mut time_format = None;
+    let mut log_format = None;
+    for (name, value) in table_options.into_iter() {
+        match name.as_str() {
+            "time_format" => time_format = Some(value),
+            "log_format" if execution::datasource::LogFormat::is_supported(&file_format) => {
+                let parsed = execution::datasource::LogFormat::parse(&file_format, &value)
+                    .map_err(AppError::InvalidLogFormat)?;
+                log_format = Some(Box::new(parsed));
+            }
+            "log_format" => {
+                return Err(AppError::InvalidLogFormat(
+                    "the log format is only supported for the nginx, ingress_nginx, apache, squid, regex and cloudfront_rt tables".to_string(),
+                ));
+            }
+            _ => return Err(AppError::InvalidTableOption(format!("unknown option {}", name))),
+        }
+    }
+    if log_format.is_none() && file_format == "regex" {
+        return Err(AppError::InvalidLogFormat(
+            "the regex tables need the regex of the lines".to_string(),
+        ));
+    }
+
+    //The type annotations only apply to the tables of the generic format
+    let field_types = match sub_m.value_of("types") {
+        Some(types_str) if execution::datasource::is_generic_format(&file_format) => {
+            execution::datasource::parse_field_types(types_str).map_err(AppError::InvalidTypeAnnotation)?
+        }
+        _ => Vec::new(),
+    };
+    let channel_capacity = match sub_m.value_of("channel_capacity").map(|s| s.parse::<usize>()) {
+        Some(Ok(0)) | Some(Err(_)) => {
+            let capacity = sub_m.value_of("channel_capacity").unwrap_or_default().to_string();
+            return Err(AppError::InvalidChannelCapacity(capacity));
+        }
+        capacity => capacity.map(|c| c.unwrap()),
+    };
+    let options = execution::datasource::ReaderOptions {
+        field_types,
+        time_format,
+        time_range: execution::datasource::TimeRange::default(),
+        value_filters: Vec::new(),
+        index_dir: sub_m.value_of("index_dir").map(PathBuf::from),
+        channel_capacity,
+        log_format,
+        follow: false,
+        columns: None,
+    };
+
+    if file_path == "stdin" {
+        Ok(common::types::DataSource::Stdin(file_format, table_name, options))
+    } else if let Some(command) = file_path.strip_prefix("exec:") {
+        //The rest of the spec is the shell command, e.g. it:elb=exec:aws s3 cp s3://bucket/key -
+        if command.trim().is_empty() {
+            return Err(AppError::InvalidTableSpecString);
+        }
+        Ok(common::types::DataSource::Command(
+            command.to_string(),
+            file_format,
+            table_name,
+            options,
+        ))
+    } else {
+        let path = Path::new(&file_path);
+        Ok(common::types::DataSource::File(
+            path.to_path_buf(),
+            file_format,
+            table_name,
+            options,
+        ))
+    }
+}
+
+fn check_data_sources(
+    data_sources: Vec<common::types::DataSource>,
+    sub_m: &ArgMatches,
+) -> Result<Vec<common::types::DataSource>, AppError> {
+    let mut table_names = HashSet::new();
+    for data_source in data_sources.iter() {
+        if !table_names.insert(data_source.table_name()) {
+            return Err(AppError::InvalidTableSpecString);
+        }
+    }
+
+    let stdin_count = data_sources
+        .iter()
+        .filter(|d| matches!(d, common::types::DataSource::Stdin(..)))
+        .count();
+    let exec_count = data_sources
+        .iter()
+        .filter(|d| matches!(d, common::types::DataSource::Command(..)))
+        .count();
+    if stdin_count > 1 {
+        return Err(AppError::InvalidTableSpecString);
+    }
+    if sub_m.is_present("follow") && stdin_count + exec_count > 0 {
+        return Err(AppError::FollowNeedsFiles);
+    }
+
+    let has_generic_format = data_sources.iter().any(|d| match d {
+        common::types::DataSource::File(_, file_format, _, _) => execution::datasource::is_generic_format(file_format),
+        common::types::DataSource::Stdin(file_format, _, _) => execution::datasource::is_generic_format(file_format),
+        common::types::DataSource::Command(_, file_format, _, _) => {
+            execution::datasource::is_generic_format(file_format)
+        }
+    });
+    if sub_m.value_of("types").is_some() && !has_generic_format {
+        return Err(AppError::InvalidTypeAnnotation(
+            "type annotations are only supported for the generic formats".to_string(),
+        ));
+    }
+
+    Ok(data_sources)
+}
+
+pub fn main() {
+    let yaml = load_yaml!("cli.yml");
+    let app_m = App::from_yaml(yaml).get_matches();
+
+    if let Some(level) = app_m.value_of("log_level") {
+        //The possible values are checked by clap
+        common::trace::set_max_level(common::trace::Level::from_str(level).unwrap());
+    }
+
+    match app_m.subcommand() {
+        ("query", Some(sub_m)) => {
+            common::trace::set_diagnostics(!sub_m.is_present("quiet") && !sub_m.is_present("no_diagnostics"));
+            let preset_opt = sub_m.value_of("preset");
+            if let Some(query_str) = sub_m.value_of("query").or_else(|| preset_opt.map(|_| "")) {
+                let lower_case_query_str = query_str.to_ascii_lowercase();
+                let output_mode = if let Some(output_format) = sub_m.value_of("output") {
+                    match app::OutputMode::from_str(output_format) {
+                        Ok(output_mode) => output_mode,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                } else {
+                    app::OutputMode::Table
+                };
+
+                let max_groups = match sub_m.value_of("max_groups").map(|s| s.parse::<usize>()) {
+                    Some(Ok(0)) => None,
+                    Some(Ok(max_groups)) => Some(max_groups),
+                    Some(Err(_)) => {
+                        eprintln!("invalid max groups");
+                        std::process::exit(1);
+                    }
+                    None => None,
+                };
+
+                let (rotate_rows, rotate_bytes) = match (
+                    sub_m.value_of("rotate_rows").map(|s| s.parse::<usize>()).transpose(),
+                    sub_m.value_of("rotate_bytes").map(|s| s.parse::<u64>()).transpose(),
+                ) {
+                    (Ok(Some(0)), _) | (_, Ok(Some(0))) | (Err(_), _) | (_, Err(_)) => {
+                        eprintln!("invalid rotation limit");
+                        std::process::exit(1);
+                    }
+                    (Ok(rows), Ok(bytes)) => (rows, bytes),
+                };
+
+                let datetime_format = match (sub_m.value_of("datetime_format"), sub_m.value_of("epoch")) {
+                    (Some(format), _) => common::types::DateTimeFormat::strftime(format),
+                    (None, Some(unit)) => common::types::DateTimeFormat::epoch(unit),
+                    (None, None) => Ok(common::types::DateTimeFormat::Default),
+                };
+                let datetime_format = match datetime_format {
+                    Ok(datetime_format) => datetime_format,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+
+                let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+                    .iter()
+                    .filter_map(|name| std::env::var(name).ok())
+                    .find(|locale| !locale.is_empty());
+                let number_format = match sub_m
+                    .value_of("number_format")
+                    .map(|name| common::types::NumberFormat::new(name, locale.as_deref()))
+                {
+                    Some(Ok(number_format)) => number_format,
+                    Some(Err(e)) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    None => common::types::NumberFormat::Plain,
+                };
+
+                let renders = match sub_m.value_of("render").map(common::types::parse_renders) {
+                    Some(Ok(renders)) => renders,
+                    Some(Err(e)) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    None => Vec::new(),
+                };
+
+                let quote_style = sub_m
+                    .value_of("csv_quote")
+                    .map(app::CsvOptions::parse_quote_style)
+                    .transpose();
+                let escape = sub_m
+                    .value_of("csv_escape")
+                    .map(app::CsvOptions::parse_escape)
+                    .transpose();
+                let csv_options = match (quote_style, escape) {
+                    (Ok(quote_style), Ok(escape)) => app::CsvOptions {
+                        quote_style: quote_style.unwrap_or(app::CsvOptions::default().quote_style),
+                        escape,
+                        crlf: sub_m.value_of("csv_line_ending") == Some("crlf"),
+                    },
+                    (Err(e), _) | (_, Err(e)) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+
+                let follow = match sub_m.value_of("interval").map(|s| s.parse::<u64>()) {
+                    _ if !sub_m.is_present("follow") => None,
+                    Some(Ok(0)) | Some(Err(_)) => {
+                        eprintln!("invalid interval");
+                        std::process::exit(1);
+                    }
+                    interval => Some(app::Follow {
+                        interval: std::time::Duration::from_secs(interval.map_or(5, |i| i.unwrap())),
+                        delta: sub_m.value_of("emit") == Some("delta"),
+                    }),
+                };
+
+                let compression = match sub_m.value_of("compress").map(app::Compression::from_str) {
+                    Some(Ok(compression)) => Some(compression),
+                    Some(Err(e)) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    None => None,
+                };
+
+                let macros = match sub_m.value_of("config").map(app::load_config) {
+                    Some(Ok(macros)) => macros,
+                    Some(Err(e)) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    None => syntax::macros::Macros::default(),
+                };
+
+                let run_options = app::RunOptions {
+                    macros,
+                    group_limit: max_groups.map(|max_groups| execution::stream::GroupLimit {
+                        max_groups,
+                        abort: sub_m.is_present("abort_on_max_groups"),
+                    }),
+                    columns: sub_m.value_of("columns").map(|s| {
+                        s.split(',')
+                            .map(|c| c.trim().to_ascii_lowercase())
+                            .filter(|c| !c.is_empty())
+                            .collect()
+                    }),
+                    quiet: sub_m.is_present("quiet"),
+                    output_file: sub_m.value_of("output_file").map(PathBuf::from),
+                    compression,
+                    datetime_format,
+                    number_format,
+                    renders,
+                    csv_options,
+                    follow,
+                    rotation: app::Rotation {
+                        rows: rotate_rows,
+                        bytes: rotate_bytes,
+                    },
+                };
+
+                let started = std::time::Instant::now();
+                let result = if let Some(table_spec_strings) = sub_m.values_of("table") {
+                    table_spec_strings
+                        .map(|table_spec_string| parse_table_spec(table_spec_string, sub_m))
+                        .collect::<Result<Vec<_>, _>>()
+                        .and_then(|data_sources| check_data_sources(data_sources, sub_m))
+                        .and_then(|data_sources| {
+                            let query_str = match preset_opt {
+                                Some(preset) => app::preset_query(preset, data_sources[0].table_name())?,
+                                None => lower_case_query_str,
+                            };
+                            if run_options.follow.is_some() {
+                                app::follow(&query_str, data_sources, output_mode, &run_options)
+                            } else {
+                                app::run(&query_str, data_sources, output_mode, &run_options)
+                            }
+                        })
+                } else {
+                    Err(AppError::InvalidTableSpecString)
+                };
+
+                if let Some(audit_log) = sub_m.value_of("audit_log") {
+                    let audited_query_str = match preset_opt {
+                        Some(preset) => format!("--preset {}", preset),
+                        None => query_str.to_string(),
+                    };
+                    let inputs: Vec<String> = sub_m
+                        .values_of("table")
+                        .into_iter()
+                        .flatten()
+                        .map(|s| s.to_string())
+                        .collect();
+                    if let Err(e) = app::write_audit_log(
+                        Path::new(audit_log),
+                        &audited_query_str,
+                        &inputs,
+                        &result,
+                        started.elapsed(),
+                    ) {
+                        eprintln!("{}", e);
+                    }
+                }
+
+                if let Err(e) = result {
+                    if sub_m.value_of("error_format") == Some("json") {
+                        eprintln!("{}", e.to_json().dump());
+                    } else {
+                        eprintln!("{}", e);
+                    }
+                    std::process::exit(1);
+                }
+            } else {
+                println!("{}", sub_m.usage());
+            }
+        }
+        ("convert", Some(sub_m)) => {
+            let file_format = sub_m.value_of("format").unwrap_or("");
+            let result = if !execution::datasource::is_supported_format(file_format) {
+                Err(AppError::InvalidLogFileFormat)
+            } else {
+                let field_types = match sub_m.value_of("types") {
+                    Some(types_str) if execution::datasource::is_generic_format(file_format) => {
+                        execution::datasource::parse_field_types(types_str).map_err(AppError::InvalidTypeAnnotation)
+                    }
+                    Some(_) => Err(AppError::InvalidTypeAnnotation(
+                        "type annotations are only supported for the generic formats".to_string(),
+                    )),
+                    None => Ok(Vec::new()),
+                };
+                let log_format = match sub_m.value_of("log_format") {
+                    Some(log_format) if execution::datasource::LogFormat::is_supported(file_format) => {
+                        execution::datasource::LogFormat::parse(file_format, log_format)
+                            .map(|f| Some(Box::new(f)))
+                            .map_err(AppError::InvalidLogFormat)
+                    }
+                    Some(_) => Err(AppError::InvalidLogFormat(
+                        "the log format is only supported for the nginx, ingress_nginx, apache, squid, regex and cloudfront_rt tables"
+                            .to_string(),
+                    )),
+                    None if file_format == "regex" => Err(AppError::InvalidLogFormat(
+                        "the regex tables need the regex of the lines".to_string(),
+                    )),
+                    None => Ok(None),
+                };
+
+                field_types.and_then(|field_types| {
+                    let options = execution::datasource::ReaderOptions {
+                        field_types,
+                        time_format: sub_m.value_of("time_format").map(|s| s.to_string()),
+                        log_format: log_format?,
+                        ..execution::datasource::ReaderOptions::default()
+                    };
+                    let paths = sub_m
+                        .values_of("files")
+                        .into_iter()
+                        .flatten()
+                        .map(PathBuf::from)
+                        .collect();
+                    app::convert(paths, file_format, &options, sub_m.value_of("to").unwrap_or("logqc"))
+                })
+            };
+
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        ("explain", Some(sub_m)) => {
+            if let Some(query_str) = sub_m.value_of("query") {
+                let lower_case_query_str = query_str.to_ascii_lowercase();
+                let data_source = common::types::DataSource::Stdin(
+                    "jsonl".to_string(),
+                    "it".to_string(),
+                    execution::datasource::ReaderOptions::default(),
+                );
+                let result = sub_m
+                    .value_of("config")
+                    .map_or_else(|| Ok(syntax::macros::Macros::default()), app::load_config)
+                    .and_then(|macros| app::explain(&lower_case_query_str, data_source, &macros));
+
+                if let Err(e) = result {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            } else {
+                println!("{}", sub_m.usage());
+            }
+        }
+        ("schema", Some(sub_m)) => {
+            if let Some(type_str) = sub_m.value_of("type") {
+                if let Some(schema) = execution::datasource::schema_of(type_str) {
+                    let macros = match sub_m.value_of("config").map(app::load_config) {
+                        Some(Ok(macros)) => macros,
+                        Some(Err(e)) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                        None => syntax::macros::Macros::default(),
+                    };
+
+                    let mut table = Table::new();
+                    for (field, datatype) in schema.iter() {
+                        let (description, example) =
+                            execution::field_docs::field_doc(type_str, field).unwrap_or(("", ""));
+                        table.add_row(Row::new(vec![
+                            Cell::new(&*field.to_string()),
+                            Cell::new(&*datatype.to_string()),
+                            Cell::new(description),
+                            Cell::new(example),
+                        ]));
+                    }
+                    for column in macros.scoped(&[type_str]).derived_columns() {
+                        table.add_row(Row::new(vec![
+                            Cell::new(&column.name),
+                            Cell::new("Derived"),
+                            Cell::new(&column.definition),
+                            Cell::new(""),
+                        ]));
+                    }
+                    table.printstd();
+                } else {
+                    eprintln!("Unknown log format");
+                }
+            } else {
+                println!("The supported log format");
+                for file_format in execution::datasource::SUPPORTED_FORMATS.iter() {
+                    println!("* {}", file_format);
+                }
+            }
+        }
+        _ => {
+            println!("{}", app_m.usage());
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use miniz_oxide::deflate::core::{
+    compress, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus,
+};
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+//The flags of the optional fields of the gzip header, see RFC 1952
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+fn crc32_update(crc: u32, buf: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &b in buf {
+        c = CRC32_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+//Writes the gzip stream of what is written to it, the stream is only complete after finish
+pub(crate) struct GzipWriter<W: Write> {
+    inner: W,
+    compressor: Box<CompressorOxide>,
+    crc: u32,
+    size: u32,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> GzipWriter<W> {
+    pub(crate) fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(&GZIP_HEADER)?;
+        //The negative window bits is for the raw deflate stream, the gzip header and trailer are written here
+        let flags = create_comp_flags_from_zip_params(6, -15, 0);
+        Ok(GzipWriter {
+            inner,
+            compressor: Box::new(CompressorOxide::new(flags)),
+            crc: 0,
+            size: 0,
+            buf: vec![0; 64 * (1 << 10)],
+        })
+    }
+
+    fn deflate(&mut self, mut input: &[u8], flush: TDEFLFlush) -> io::Result<()> {
+        loop {
+            let (status, bytes_in, bytes_out) = compress(&mut self.compressor, input, &mut self.buf, flush);
+            self.inner.write_all(&self.buf[..bytes_out])?;
+            input = &input[bytes_in..];
+
+            match status {
+                TDEFLStatus::Done => return Ok(()),
+                TDEFLStatus::Okay if input.is_empty() && bytes_out < self.buf.len() => return Ok(()),
+                TDEFLStatus::Okay => {}
+                _ => return Err(io::Error::other("failed to compress the output")),
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.deflate(&[], TDEFLFlush::Finish)?;
+        let mut trailer = [0u8; 8];
+        trailer[..4].copy_from_slice(&self.crc.to_le_bytes());
+        trailer[4..].copy_from_slice(&self.size.to_le_bytes());
+        self.inner.write_all(&trailer)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for GzipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc = crc32_update(self.crc, buf);
+        //The size in the trailer is modulo 2^32
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        self.deflate(buf, TDEFLFlush::None)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deflate(&[], TDEFLFlush::Sync)?;
+        self.inner.flush()
+    }
+}
+
+const ZSTD_COMMAND: &str = "zstd";
+
+//Writes the zstd stream of what is written to it to the file. The zstd command compresses it, so that the crate
+//doesn't build the zstd library for an option of the output, and the command needs to be installed for --compress zstd.
+//The stream is only complete after finish, which waits for the command.
+pub(crate) struct ZstdWriter {
+    child: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+}
+
+impl ZstdWriter {
+    pub(crate) fn new(file: File) -> io::Result<Self> {
+        Self::spawn(ZSTD_COMMAND, file)
+    }
+
+    fn spawn(command: &str, file: File) -> io::Result<Self> {
+        let mut child = Command::new(command)
+            .arg("-q")
+            .arg("-c")
+            .stdin(Stdio::piped())
+            .stdout(file)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => io::Error::new(
+                    e.kind(),
+                    format!(
+                        "--compress zstd needs the {} command on the PATH, install zstd or use --compress gzip",
+                        command
+                    ),
+                ),
+                _ => e,
+            })?;
+        let stdin = child.stdin.take().map(BufWriter::new);
+
+        Ok(ZstdWriter { child, stdin })
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.flush()?;
+        }
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("zstd exited with {}", status)));
+        }
+
+        Ok(())
+    }
+
+    fn stdin(&mut self) -> &mut BufWriter<ChildStdin> {
+        //Only taken by finish
+        self.stdin.as_mut().unwrap()
+    }
+}
+
+impl Write for ZstdWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdin().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdin().flush()
+    }
+}
+
+//The output abandoned on an error is not left to the command to finish
+impl Drop for ZstdWriter {
+    fn drop(&mut self) {
+        if self.stdin.is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+//Reads the decompressed bytes of a gzip stream. The members of a concatenated stream, e.g. "cat a.gz b.gz", are read
+//one after another, and the checksum of each is verified at its end.
+pub(crate) struct GzipReader<R: BufRead> {
+    inner: R,
+    state: Box<InflateState>,
+    crc: u32,
+    size: u32,
+    in_member: bool,
+}
+
+impl<R: BufRead> GzipReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        GzipReader {
+            inner,
+            state: InflateState::new_boxed(DataFormat::Raw),
+            crc: 0,
+            size: 0,
+            in_member: false,
+        }
+    }
+
+    fn skip_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 10];
+        self.inner.read_exact(&mut header)?;
+        if header[..2] != GZIP_MAGIC || header[2] != 8 {
+            return Err(invalid_data("not a gzip stream"));
+        }
+
+        let flags = header[3];
+        if flags & FEXTRA != 0 {
+            let mut len = [0u8; 2];
+            self.inner.read_exact(&mut len)?;
+            let mut extra = vec![0u8; u16::from_le_bytes(len) as usize];
+            self.inner.read_exact(&mut extra)?;
+        }
+        for flag in [FNAME, FCOMMENT].iter() {
+            if flags & flag != 0 {
+                let mut field = Vec::new();
+                self.inner.read_until(0, &mut field)?;
+            }
+        }
+        if flags & FHCRC != 0 {
+            let mut crc = [0u8; 2];
+            self.inner.read_exact(&mut crc)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_trailer(&mut self) -> io::Result<()> {
+        let mut trailer = [0u8; 8];
+        self.inner
+            .read_exact(&mut trailer)
+            .map_err(|_| invalid_data("truncated gzip stream"))?;
+        if trailer[..4] != self.crc.to_le_bytes() || trailer[4..] != self.size.to_le_bytes() {
+            return Err(invalid_data("corrupt gzip stream, the checksum does not match"));
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for GzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if !self.in_member {
+                if self.inner.fill_buf()?.is_empty() {
+                    return Ok(0);
+                }
+                self.skip_header()?;
+                self.state = InflateState::new_boxed(DataFormat::Raw);
+                self.crc = 0;
+                self.size = 0;
+                self.in_member = true;
+            }
+
+            let input = self.inner.fill_buf()?;
+            let at_end = input.is_empty();
+            let result = inflate(&mut self.state, input, buf, MZFlush::None);
+            self.inner.consume(result.bytes_consumed);
+            let written = result.bytes_written;
+            self.crc = crc32_update(self.crc, &buf[..written]);
+            self.size = self.size.wrapping_add(written as u32);
+
+            match result.status {
+                Ok(MZStatus::StreamEnd) => {
+                    self.check_trailer()?;
+                    self.in_member = false;
+                }
+                Ok(_) | Err(MZError::Buf) if at_end && written == 0 => {
+                    return Err(invalid_data("truncated gzip stream"));
+                }
+                Ok(_) | Err(MZError::Buf) => {}
+                Err(_) => return Err(invalid_data("corrupt gzip stream")),
+            }
+
+            if written > 0 {
+                return Ok(written);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zstd_writer() {
+        //The zstd command may not be installed where the tests run
+        if Command::new("zstd").arg("--version").output().is_err() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv.zst");
+        let mut input = Vec::new();
+        for i in 0..10000 {
+            input.extend_from_slice(format!("{},elb1,200\n", i).as_bytes());
+        }
+
+        let mut wtr = ZstdWriter::new(File::create(&path).unwrap()).unwrap();
+        for chunk in input.chunks(1000) {
+            wtr.write_all(chunk).unwrap();
+        }
+        wtr.finish().unwrap();
+
+        assert!(std::fs::metadata(&path).unwrap().len() < input.len() as u64 / 4);
+        let output = Command::new("zstd").arg("-d").arg("-c").arg(&path).output().unwrap();
+        assert_eq!(input, output.stdout);
+    }
+
+    #[test]
+    fn test_zstd_writer_without_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::create(dir.path().join("out.csv.zst")).unwrap();
+
+        let e = ZstdWriter::spawn("logq-no-such-zstd", file).err().unwrap();
+        assert_eq!(io::ErrorKind::NotFound, e.kind());
+        assert_eq!(
+            "--compress zstd needs the logq-no-such-zstd command on the PATH, install zstd or use --compress gzip",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn test_gzip_writer() {
+        assert_eq!(0xcbf4_3926, crc32_update(0, b"123456789"));
+
+        let mut input = Vec::new();
+        for i in 0..10000 {
+            input.extend_from_slice(format!("{},elb1,200\n", i).as_bytes());
+        }
+
+        let mut wtr = GzipWriter::new(Vec::new()).unwrap();
+        for chunk in input.chunks(1000) {
+            wtr.write_all(chunk).unwrap();
+        }
+        wtr.flush().unwrap();
+        let output = wtr.finish().unwrap();
+
+        assert_eq!(&GZIP_HEADER[..], &output[..10]);
+        assert!(output.len() < input.len() / 4);
+        let trailer = &output[output.len() - 8..];
+        assert_eq!(crc32_update(0, &input).to_le_bytes(), trailer[..4]);
+        assert_eq!((input.len() as u32).to_le_bytes(), trailer[4..]);
+        let inflated = miniz_oxide::inflate::decompress_to_vec(&output[10..output.len() - 8]).unwrap();
+        assert_eq!(input, inflated);
+    }
+
+    #[test]
+    fn test_gzip_reader() {
+        let gzip = |input: &[u8]| {
+            let mut wtr = GzipWriter::new(Vec::new()).unwrap();
+            wtr.write_all(input).unwrap();
+            wtr.finish().unwrap()
+        };
+        let mut input = Vec::new();
+        for i in 0..10000 {
+            input.extend_from_slice(format!("{},elb1,200\n", i).as_bytes());
+        }
+
+        //Two members and a small buffer, the way a BufReader reads it
+        let mut stream = gzip(&input);
+        stream.extend(gzip(b"last\n"));
+        let mut rdr = GzipReader::new(io::BufReader::with_capacity(100, &stream[..]));
+        let mut output = Vec::new();
+        let mut buf = [0u8; 333];
+        loop {
+            let n = rdr.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+        input.extend_from_slice(b"last\n");
+        assert_eq!(input, output);
+
+        //The optional file name of gzip
+        let mut named = gzip(b"a\n");
+        named[3] = FNAME;
+        named.splice(10..10, b"access.log\0".iter().cloned());
+        let mut output = String::new();
+        GzipReader::new(&named[..]).read_to_string(&mut output).unwrap();
+        assert_eq!("a\n", output);
+
+        let stream = gzip(&input);
+        let mut output = Vec::new();
+        let truncated = GzipReader::new(&stream[..stream.len() / 2]).read_to_end(&mut output);
+        assert!(truncated.is_err());
+        let mut corrupt = stream.clone();
+        let len = corrupt.len();
+        corrupt[len - 5] ^= 0xff;
+        assert!(GzipReader::new(&corrupt[..]).read_to_end(&mut output).is_err());
+    }
+}
@@ -1 +1,3 @@
+pub mod compress;
+pub mod trace;
 pub mod types;
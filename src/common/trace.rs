@@ -0,0 +1,147 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref START: Instant = Instant::now();
+}
+
+//Nothing is logged until --log-level is given
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+//The warnings go to stderr unless --no-diagnostics or --quiet is given, the stdout is left to the query result
+static DIAGNOSTICS: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("unknown log level \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        write!(f, "{:>5}", s)
+    }
+}
+
+pub(crate) fn set_max_level(level: Level) {
+    lazy_static::initialize(&START);
+    MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled(level: Level) -> bool {
+    level as usize <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+pub(crate) fn set_diagnostics(enabled: bool) {
+    DIAGNOSTICS.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn warning(message: fmt::Arguments) {
+    if DIAGNOSTICS.load(Ordering::Relaxed) {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+pub(crate) fn event(level: Level, target: &str, message: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!(
+            "[{:>9.3}s {} {}] {}",
+            START.elapsed().as_secs_f64(),
+            level,
+            target,
+            message
+        );
+    }
+}
+
+//The span of an operator, the busy time is the time spent inside the operator including its sources,
+//the elapsed time is since the span was entered.
+pub(crate) struct Span {
+    level: Level,
+    name: String,
+    entered: Instant,
+    busy: Duration,
+    closed: bool,
+}
+
+impl Span {
+    pub(crate) fn enter(level: Level, name: &str, fields: fmt::Arguments) -> Self {
+        event(level, name, format_args!("enter {}", fields));
+        Span {
+            level,
+            name: name.to_string(),
+            entered: Instant::now(),
+            busy: Duration::default(),
+            closed: false,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn in_scope<T, F: FnOnce() -> T>(&mut self, f: F) -> T {
+        let start = Instant::now();
+        let ans = f();
+        self.busy += start.elapsed();
+        ans
+    }
+
+    pub(crate) fn close(&mut self, fields: fmt::Arguments) {
+        if self.closed {
+            return;
+        }
+
+        self.closed = true;
+        event(
+            self.level,
+            &self.name,
+            format_args!(
+                "close {} busy={:?} elapsed={:?}",
+                fields,
+                self.busy,
+                self.entered.elapsed()
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(Ok(Level::Debug), Level::from_str("debug"));
+        assert!(Level::from_str("verbose").is_err());
+        assert!(Level::Error < Level::Trace);
+        assert_eq!(" WARN", Level::Warn.to_string());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::common;
+use crate::execution::datasource::{ReaderOptions, TimeRange};
 use crate::syntax::ast;
 use chrono;
 use linked_hash_map::LinkedHashMap;
 use ordered_float::OrderedFloat;
 use regex::Regex;
+use std::convert::TryFrom;
 use std::fmt;
 use std::path::PathBuf;
 use std::result;
@@ -11,7 +13,8 @@ use url;
 
 lazy_static! {
     //FIXME: use different type for string hostname and Ipv4
-    static ref HOST_REGEX: Regex = Regex::new(r#"([\.0-9a-zA-Z]+):([0-9]+)"#).unwrap();
+    //The port follows the last colon, an IPv6 address may be in the brackets or not, e.g. 2001:db8::1:443
+    static ref HOST_REGEX: Regex = Regex::new(r#"\[?([\.0-9a-zA-Z:]*[\.0-9a-zA-Z])\]?:([0-9]+)"#).unwrap();
     static ref SPLIT_HTTP_LINE_REGEX: Regex = Regex::new(r#"[^\s"']+"#).unwrap();
     static ref SPLIT_TIME_INTERVAL_LINE_REGEX: Regex = Regex::new(r#"[^\s"']+"#).unwrap();
 }
@@ -19,6 +22,8 @@ lazy_static! {
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub(crate) enum Value {
     Int(i32),
+    //The integers out of the range of Int, e.g. the large sums and the byte offsets past 2 GiB
+    Long(i64),
     Float(OrderedFloat<f32>),
     Boolean(bool),
     String(String),
@@ -31,14 +36,45 @@ pub(crate) enum Value {
     Array(Vec<Value>),
 }
 
+impl Value {
+    //Long is only used out of the range of Int, so that the equal integers are always the equal values
+    pub(crate) fn integral(i: i64) -> Value {
+        i32::try_from(i).map_or(Value::Long(i), Value::Int)
+    }
+
+    pub(crate) fn as_integral(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(i64::from(*i)),
+            Value::Long(l) => Some(*l),
+            _ => None,
+        }
+    }
+}
+
 pub(crate) type ParseHostResult<T> = result::Result<T, ParseHostError>;
 
-#[derive(Fail, Debug)]
+#[derive(Debug)]
 pub(crate) enum ParseHostError {
-    #[fail(display = "Parse Host Error")]
     ParseHost,
-    #[fail(display = "{}", _0)]
-    ParsePort(#[cause] std::num::ParseIntError),
+    ParsePort(std::num::ParseIntError),
+}
+
+impl fmt::Display for ParseHostError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseHostError::ParseHost => fmt.write_str("Parse Host Error"),
+            ParseHostError::ParsePort(e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseHostError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseHostError::ParsePort(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<std::num::ParseIntError> for ParseHostError {
@@ -58,8 +94,12 @@ pub(crate) struct Host {
 
 impl fmt::Display for Host {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_str(&*self.hostname)?;
-        fmt.write_str(":")?;
+        if self.hostname.contains(':') {
+            write!(fmt, "[{}]:", self.hostname)?;
+        } else {
+            fmt.write_str(&self.hostname)?;
+            fmt.write_str(":")?;
+        }
         fmt.write_str(&*self.port.to_string())?;
         Ok(())
     }
@@ -80,18 +120,34 @@ pub(crate) fn parse_host(s: &str) -> ParseHostResult<Host> {
 
 pub(crate) type ParseHttpRequestResult<T> = result::Result<T, ParseHttpRequestError>;
 
-#[derive(Fail, Debug)]
+#[derive(Debug)]
 pub(crate) enum ParseHttpRequestError {
-    #[fail(display = "Parse Http Method Error")]
     ParseHttpMethod,
-    #[fail(display = "{}", _0)]
-    ParseUrl(#[cause] url::ParseError),
-    #[fail(display = "Parse Http Version Error")]
+    ParseUrl(url::ParseError),
     ParseHttpVersion,
-    #[fail(display = "Missing Field")]
     MissingField,
 }
 
+impl fmt::Display for ParseHttpRequestError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseHttpRequestError::ParseHttpMethod => fmt.write_str("Parse Http Method Error"),
+            ParseHttpRequestError::ParseUrl(e) => write!(fmt, "{}", e),
+            ParseHttpRequestError::ParseHttpVersion => fmt.write_str("Parse Http Version Error"),
+            ParseHttpRequestError::MissingField => fmt.write_str("Missing Field"),
+        }
+    }
+}
+
+impl std::error::Error for ParseHttpRequestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseHttpRequestError::ParseUrl(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<url::ParseError> for ParseHttpRequestError {
     fn from(err: url::ParseError) -> ParseHttpRequestError {
         ParseHttpRequestError::ParseUrl(err)
@@ -108,11 +164,20 @@ pub(crate) struct HttpRequest {
     pub(crate) http_version: String,
 }
 
+//The request target of the web servers is in the origin form, e.g. "GET /index.html HTTP/1.1", without the scheme
+//and the host that the load balancers log. It is kept as a URL without the host under this scheme, so that the url
+//functions work the same and the host is null.
+const ORIGIN_FORM_SCHEME: &str = "origin";
+
 impl fmt::Display for HttpRequest {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.write_str(&*self.http_method)?;
         fmt.write_str(" ")?;
-        fmt.write_str(&*self.url.to_string())?;
+        if self.url.scheme() == ORIGIN_FORM_SCHEME {
+            fmt.write_str(&self.url[url::Position::BeforePath..])?;
+        } else {
+            fmt.write_str(self.url.as_str())?;
+        }
         fmt.write_str(" ")?;
         fmt.write_str(&*self.http_version)?;
         Ok(())
@@ -146,7 +211,11 @@ pub(crate) fn parse_http_request(s: &str) -> ParseHttpRequestResult<HttpRequest>
     };
 
     let url_opt = if let Some(m) = iter.next() {
-        let url = url::Url::parse(m.as_str())?;
+        let url = if m.as_str().starts_with('/') {
+            url::Url::parse(&format!("{}:{}", ORIGIN_FORM_SCHEME, m.as_str()))?
+        } else {
+            url::Url::parse(m.as_str())?
+        };
         Some(url)
     } else {
         None
@@ -174,16 +243,32 @@ pub(crate) fn parse_http_request(s: &str) -> ParseHttpRequestResult<HttpRequest>
 
 pub(crate) type ParseTimeIntervalResult<T> = result::Result<T, ParseTimeIntervalError>;
 
-#[derive(Fail, PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub(crate) enum ParseTimeIntervalError {
-    #[fail(display = "Parse Integral Error: {}", _0)]
-    ParseIntegral(#[cause] std::num::ParseIntError),
-    #[fail(display = "Missing Part")]
+    ParseIntegral(std::num::ParseIntError),
     MissingPart,
-    #[fail(display = "Unknown Time Unit")]
     UnknownTimeUnit,
 }
 
+impl fmt::Display for ParseTimeIntervalError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseTimeIntervalError::ParseIntegral(e) => write!(fmt, "Parse Integral Error: {}", e),
+            ParseTimeIntervalError::MissingPart => fmt.write_str("Missing Part"),
+            ParseTimeIntervalError::UnknownTimeUnit => fmt.write_str("Unknown Time Unit"),
+        }
+    }
+}
+
+impl std::error::Error for ParseTimeIntervalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseTimeIntervalError::ParseIntegral(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<std::num::ParseIntError> for ParseTimeIntervalError {
     fn from(err: std::num::ParseIntError) -> ParseTimeIntervalError {
         ParseTimeIntervalError::ParseIntegral(err)
@@ -206,14 +291,37 @@ pub(crate) struct TimeInterval {
     pub(crate) unit: TimeIntervalUnit,
 }
 
+impl TimeInterval {
+    //None for the months and the years, which have no fixed length
+    pub(crate) fn duration(&self) -> Option<chrono::Duration> {
+        let n = i64::from(self.n);
+        match self.unit {
+            TimeIntervalUnit::Second => Some(chrono::Duration::seconds(n)),
+            TimeIntervalUnit::Minute => Some(chrono::Duration::minutes(n)),
+            TimeIntervalUnit::Hour => Some(chrono::Duration::hours(n)),
+            TimeIntervalUnit::Day => Some(chrono::Duration::days(n)),
+            TimeIntervalUnit::Month | TimeIntervalUnit::Year => None,
+        }
+    }
+}
+
 pub(crate) type ParseDatePartResult<T> = result::Result<T, ParseDatePartError>;
 
-#[derive(Fail, PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub(crate) enum ParseDatePartError {
-    #[fail(display = "Unknown DatePart Unit")]
     UnknownDatePartUnit,
 }
 
+impl fmt::Display for ParseDatePartError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseDatePartError::UnknownDatePartUnit => fmt.write_str("Unknown DatePart Unit"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDatePartError {}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) enum DatePartUnit {
     Second,
@@ -301,6 +409,274 @@ pub(crate) fn merge(left: &Variables, right: &Variables) -> Variables {
     left.iter().chain(right).map(|(k, v)| (k.clone(), v.clone())).collect()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EpochUnit {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+}
+
+//How the DateTime values are rendered in all the output modes, the epoch is a number in the json output
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) enum DateTimeFormat {
+    #[default]
+    Default,
+    Strftime(String),
+    Epoch(EpochUnit),
+}
+
+impl DateTimeFormat {
+    //The invalid format is rejected up front, since chrono panics while formatting with it
+    pub(crate) fn strftime(format: &str) -> result::Result<Self, String> {
+        if chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error) {
+            return Err(format!("invalid datetime format \"{}\"", format));
+        }
+
+        Ok(DateTimeFormat::Strftime(format.to_string()))
+    }
+
+    pub(crate) fn epoch(unit: &str) -> result::Result<Self, String> {
+        match unit {
+            "s" => Ok(DateTimeFormat::Epoch(EpochUnit::Seconds)),
+            "ms" => Ok(DateTimeFormat::Epoch(EpochUnit::Milliseconds)),
+            "us" => Ok(DateTimeFormat::Epoch(EpochUnit::Microseconds)),
+            _ => Err(format!("unknown epoch unit \"{}\"", unit)),
+        }
+    }
+
+    fn epoch_value(unit: EpochUnit, dt: &chrono::DateTime<chrono::FixedOffset>) -> i64 {
+        match unit {
+            EpochUnit::Seconds => dt.timestamp(),
+            EpochUnit::Milliseconds => dt.timestamp_millis(),
+            EpochUnit::Microseconds => dt.timestamp() * 1_000_000 + i64::from(dt.timestamp_subsec_micros()),
+        }
+    }
+
+    pub(crate) fn format(&self, dt: &chrono::DateTime<chrono::FixedOffset>) -> String {
+        match self {
+            DateTimeFormat::Default => dt.to_string(),
+            DateTimeFormat::Strftime(format) => dt.format(format).to_string(),
+            DateTimeFormat::Epoch(unit) => DateTimeFormat::epoch_value(*unit, dt).to_string(),
+        }
+    }
+
+    fn to_json(&self, dt: &chrono::DateTime<chrono::FixedOffset>) -> json::JsonValue {
+        match self {
+            DateTimeFormat::Epoch(unit) => DateTimeFormat::epoch_value(*unit, dt).into(),
+            _ => self.format(dt).into(),
+        }
+    }
+}
+
+//How the numbers are rendered in the table output, the csv and json outputs are left machine-readable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum NumberFormat {
+    #[default]
+    Plain,
+    Grouped {
+        separator: char,
+        decimal_mark: char,
+    },
+    Si,
+}
+
+impl NumberFormat {
+    //The separators of the grouped numbers follow the locale in LC_ALL, LC_NUMERIC or LANG, e.g. de_DE.UTF-8
+    pub(crate) fn new(name: &str, locale: Option<&str>) -> result::Result<Self, String> {
+        match name {
+            "plain" => Ok(NumberFormat::Plain),
+            "si" => Ok(NumberFormat::Si),
+            "grouped" => {
+                let language = locale.unwrap_or("").split(['_', '.']).next().unwrap_or("");
+                let (separator, decimal_mark) = match language {
+                    "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" => ('.', ','),
+                    "fr" | "ru" | "pl" | "sv" | "cs" | "fi" | "nb" | "uk" => (' ', ','),
+                    _ => (',', '.'),
+                };
+                Ok(NumberFormat::Grouped {
+                    separator,
+                    decimal_mark,
+                })
+            }
+            _ => Err(format!("unknown number format \"{}\"", name)),
+        }
+    }
+
+    fn group(digits: &str, separator: char, decimal_mark: char) -> String {
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", digits),
+        };
+        let (integral, fraction) = match digits.find('.') {
+            Some(pos) => (&digits[..pos], Some(&digits[pos + 1..])),
+            None => (digits, None),
+        };
+
+        let mut ans = sign.to_string();
+        for (i, c) in integral.chars().enumerate() {
+            if i > 0 && (integral.len() - i) % 3 == 0 {
+                ans.push(separator);
+            }
+            ans.push(c);
+        }
+        if let Some(fraction) = fraction {
+            ans.push(decimal_mark);
+            ans.push_str(fraction);
+        }
+        ans
+    }
+
+    //One decimal with the k, M, G, ... suffix once the number reaches a thousand
+    fn si(digits: String, value: f64) -> String {
+        const SUFFIXES: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
+        let mut scaled = value;
+        let mut idx = 0;
+        while scaled.abs() >= 999.95 && idx + 1 < SUFFIXES.len() {
+            scaled /= 1000.0;
+            idx += 1;
+        }
+
+        if idx == 0 {
+            digits
+        } else {
+            format!("{:.1}{}", scaled, SUFFIXES[idx])
+        }
+    }
+
+    pub(crate) fn format_int(self, i: i64) -> String {
+        match self {
+            NumberFormat::Plain => i.to_string(),
+            NumberFormat::Grouped {
+                separator,
+                decimal_mark,
+            } => NumberFormat::group(&i.to_string(), separator, decimal_mark),
+            NumberFormat::Si => NumberFormat::si(i.to_string(), i as f64),
+        }
+    }
+
+    pub(crate) fn format_float(self, f: f32) -> String {
+        match self {
+            NumberFormat::Plain => f.to_string(),
+            NumberFormat::Grouped {
+                separator,
+                decimal_mark,
+            } if f.is_finite() => NumberFormat::group(&f.to_string(), separator, decimal_mark),
+            NumberFormat::Grouped { .. } => f.to_string(),
+            NumberFormat::Si => NumberFormat::si(f.to_string(), f64::from(f)),
+        }
+    }
+}
+
+//The human-friendly rendering of a numeric output column, the durations are in seconds as in the load balancer logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Renderer {
+    Bytes,
+    Duration,
+    DurationMs,
+    Percent,
+}
+
+impl std::str::FromStr for Renderer {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(Renderer::Bytes),
+            "duration" => Ok(Renderer::Duration),
+            "duration_ms" => Ok(Renderer::DurationMs),
+            "percent" => Ok(Renderer::Percent),
+            _ => Err(format!("unknown renderer \"{}\"", s)),
+        }
+    }
+}
+
+impl Renderer {
+    fn render_bytes(n: f64) -> String {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if n.abs() < 1024.0 {
+            return format!("{} B", n);
+        }
+
+        let mut scaled = n / 1024.0;
+        let mut idx = 0;
+        while scaled.abs() >= 1024.0 && idx + 1 < UNITS.len() {
+            scaled /= 1024.0;
+            idx += 1;
+        }
+        format!("{:.1} {}", scaled, UNITS[idx])
+    }
+
+    fn render_duration(secs: f64) -> String {
+        if secs < 1e-3 {
+            format!("{:.0}us", secs * 1e6)
+        } else if secs < 1.0 {
+            format!("{:.1}ms", secs * 1e3)
+        } else if secs < 60.0 {
+            format!("{:.2}s", secs)
+        } else if secs < 3600.0 {
+            format!("{}m {:.0}s", (secs / 60.0).floor(), secs % 60.0)
+        } else {
+            format!("{}h {}m", (secs / 3600.0).floor(), ((secs % 3600.0) / 60.0).floor())
+        }
+    }
+
+    //The values other than the numbers are left as they are, so are the negative durations which mean unset
+    pub(crate) fn render(self, value: &Value) -> Value {
+        let n = match value {
+            Value::Int(i) => f64::from(*i),
+            Value::Long(l) => *l as f64,
+            Value::Float(f) => f64::from(f.into_inner()),
+            _ => return value.clone(),
+        };
+
+        match self {
+            Renderer::Bytes => Value::String(Renderer::render_bytes(n)),
+            Renderer::Duration | Renderer::DurationMs if n < 0.0 => value.clone(),
+            Renderer::Duration => Value::String(Renderer::render_duration(n)),
+            Renderer::DurationMs => Value::String(format!("{:.3}ms", n * 1e3)),
+            Renderer::Percent => Value::String(format!("{:.1}%", n * 100.0)),
+        }
+    }
+}
+
+//e.g. "sent_bytes=bytes,backend_processing_time=duration_ms"
+pub(crate) fn parse_renders(s: &str) -> result::Result<Vec<(String, Renderer)>, String> {
+    s.split(',')
+        .filter(|spec| !spec.trim().is_empty())
+        .map(|spec| match spec.split_once('=') {
+            Some((column, renderer)) => Ok((
+                column.trim().to_ascii_lowercase(),
+                renderer.trim().to_ascii_lowercase().parse::<Renderer>()?,
+            )),
+            None => Err(format!("invalid render \"{}\", expected column=renderer", spec)),
+        })
+        .collect()
+}
+
+//The lists and the maps are written as the json arrays and objects, in the json output as well as in the cells
+//of the csv output
+pub(crate) fn value_to_json(value: &Value, datetime_format: &DateTimeFormat) -> json::JsonValue {
+    match value {
+        Value::Int(i) => (*i).into(),
+        Value::Long(l) => (*l).into(),
+        Value::Float(f) => f.into_inner().into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::String(s) => s.as_str().into(),
+        Value::Null | Value::Missing => json::Null,
+        Value::DateTime(dt) => datetime_format.to_json(dt),
+        Value::HttpRequest(h) => h.to_string().into(),
+        Value::Host(h) => h.to_string().into(),
+        Value::Object(o) => {
+            let mut obj = json::JsonValue::new_object();
+            for (k, v) in o.iter() {
+                obj[k.as_str()] = value_to_json(v, datetime_format);
+            }
+            obj
+        }
+        Value::Array(a) => json::JsonValue::Array(a.iter().map(|v| value_to_json(v, datetime_format)).collect()),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct IndexBinding {
     pub(crate) idx: usize,
@@ -321,8 +697,144 @@ pub(crate) struct ParsingContext {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum DataSource {
-    File(PathBuf, String, String),
-    Stdin(String, String),
+    File(PathBuf, String, String, ReaderOptions),
+    Stdin(String, String, ReaderOptions),
+    Command(String, String, String, ReaderOptions),
+}
+
+impl DataSource {
+    pub(crate) fn with_time_range(self, time_range: TimeRange) -> Self {
+        match self {
+            DataSource::File(path, file_format, table_name, options) => {
+                DataSource::File(path, file_format, table_name, ReaderOptions { time_range, ..options })
+            }
+            DataSource::Stdin(file_format, table_name, options) => {
+                DataSource::Stdin(file_format, table_name, ReaderOptions { time_range, ..options })
+            }
+            DataSource::Command(command, file_format, table_name, options) => DataSource::Command(
+                command,
+                file_format,
+                table_name,
+                ReaderOptions { time_range, ..options },
+            ),
+        }
+    }
+
+    pub(crate) fn with_value_filters(self, value_filters: Vec<(String, Value)>) -> Self {
+        match self {
+            DataSource::File(path, file_format, table_name, options) => DataSource::File(
+                path,
+                file_format,
+                table_name,
+                ReaderOptions {
+                    value_filters,
+                    ..options
+                },
+            ),
+            DataSource::Stdin(file_format, table_name, options) => DataSource::Stdin(
+                file_format,
+                table_name,
+                ReaderOptions {
+                    value_filters,
+                    ..options
+                },
+            ),
+            DataSource::Command(command, file_format, table_name, options) => DataSource::Command(
+                command,
+                file_format,
+                table_name,
+                ReaderOptions {
+                    value_filters,
+                    ..options
+                },
+            ),
+        }
+    }
+
+    //Only the files may have a converted copy to read the columns of
+    pub(crate) fn with_columns(self, columns: Vec<String>) -> Self {
+        match self {
+            DataSource::File(path, file_format, table_name, options) => DataSource::File(
+                path,
+                file_format,
+                table_name,
+                ReaderOptions {
+                    columns: Some(columns),
+                    ..options
+                },
+            ),
+            data_source => data_source,
+        }
+    }
+
+    pub(crate) fn with_follow(self, follow: bool) -> Self {
+        match self {
+            DataSource::File(path, file_format, table_name, options) => {
+                DataSource::File(path, file_format, table_name, ReaderOptions { follow, ..options })
+            }
+            DataSource::Stdin(file_format, table_name, options) => {
+                DataSource::Stdin(file_format, table_name, ReaderOptions { follow, ..options })
+            }
+            DataSource::Command(command, file_format, table_name, options) => {
+                DataSource::Command(command, file_format, table_name, ReaderOptions { follow, ..options })
+            }
+        }
+    }
+
+    pub(crate) fn with_channel_capacity(self, channel_capacity: Option<usize>) -> Self {
+        match self {
+            DataSource::File(path, file_format, table_name, options) => DataSource::File(
+                path,
+                file_format,
+                table_name,
+                ReaderOptions {
+                    channel_capacity,
+                    ..options
+                },
+            ),
+            DataSource::Stdin(file_format, table_name, options) => DataSource::Stdin(
+                file_format,
+                table_name,
+                ReaderOptions {
+                    channel_capacity,
+                    ..options
+                },
+            ),
+            DataSource::Command(command, file_format, table_name, options) => DataSource::Command(
+                command,
+                file_format,
+                table_name,
+                ReaderOptions {
+                    channel_capacity,
+                    ..options
+                },
+            ),
+        }
+    }
+
+    pub(crate) fn options(&self) -> &ReaderOptions {
+        match self {
+            DataSource::File(_, _, _, options) => options,
+            DataSource::Stdin(_, _, options) => options,
+            DataSource::Command(_, _, _, options) => options,
+        }
+    }
+
+    pub(crate) fn file_format(&self) -> &str {
+        match self {
+            DataSource::File(_, file_format, _, _) => file_format,
+            DataSource::Stdin(file_format, _, _) => file_format,
+            DataSource::Command(_, file_format, _, _) => file_format,
+        }
+    }
+
+    pub(crate) fn table_name(&self) -> &str {
+        match self {
+            DataSource::File(_, _, table_name, _) => table_name,
+            DataSource::Stdin(_, table_name, _) => table_name,
+            DataSource::Command(_, _, table_name, _) => table_name,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -363,4 +875,82 @@ mod tests {
 
         assert_eq!(expected, ans);
     }
+
+    #[test]
+    fn test_datetime_format() {
+        let dt = chrono::DateTime::parse_from_rfc3339("2019-06-07T18:45:33.559871Z").unwrap();
+
+        assert_eq!("2019-06-07 18:45:33.559871 +00:00", DateTimeFormat::Default.format(&dt));
+        let format = DateTimeFormat::strftime("%Y-%m-%dT%H:%M:%S%.3fZ").unwrap();
+        assert_eq!("2019-06-07T18:45:33.559Z", format.format(&dt));
+        assert!(DateTimeFormat::strftime("%Y %Q").is_err());
+
+        let format = DateTimeFormat::epoch("ms").unwrap();
+        assert_eq!("1559933133559", format.format(&dt));
+        let format = DateTimeFormat::epoch("us").unwrap();
+        assert_eq!(
+            json::JsonValue::from(1_559_933_133_559_871i64),
+            value_to_json(&Value::DateTime(dt), &format)
+        );
+        assert!(DateTimeFormat::epoch("ns").is_err());
+    }
+
+    #[test]
+    fn test_number_format() {
+        let format = NumberFormat::new("grouped", None).unwrap();
+        assert_eq!("1,234,567", format.format_int(1_234_567));
+        assert_eq!("-123,456", format.format_int(-123_456));
+        assert_eq!("999", format.format_int(999));
+        assert_eq!("12,345.5", format.format_float(12_345.5));
+
+        let format = NumberFormat::new("grouped", Some("de_DE.UTF-8")).unwrap();
+        assert_eq!("1.234.567", format.format_int(1_234_567));
+        assert_eq!("12.345,5", format.format_float(12_345.5));
+
+        let format = NumberFormat::new("si", None).unwrap();
+        assert_eq!("999", format.format_int(999));
+        assert_eq!("1.2k", format.format_int(1234));
+        assert_eq!("1.0M", format.format_int(999_999));
+        assert_eq!("-2.1G", format.format_int(-2_100_000_000));
+        assert_eq!("0.25", format.format_float(0.25));
+
+        assert!(NumberFormat::new("fancy", None).is_err());
+    }
+
+    #[test]
+    fn test_renderer() {
+        let renders = parse_renders("Sent_Bytes=bytes, t=duration_ms,r=percent,d=duration").unwrap();
+        assert_eq!(("sent_bytes".to_string(), Renderer::Bytes), renders[0]);
+        assert_eq!(4, renders.len());
+        assert!(parse_renders("sent_bytes").is_err());
+        assert!(parse_renders("sent_bytes=kib").is_err());
+
+        let float = |f: f32| Value::Float(OrderedFloat::from(f));
+        assert_eq!(
+            Value::String("512 B".to_string()),
+            Renderer::Bytes.render(&Value::Int(512))
+        );
+        assert_eq!(
+            Value::String("1.5 MiB".to_string()),
+            Renderer::Bytes.render(&Value::Int(1_572_864))
+        );
+        assert_eq!(
+            Value::String("1.500ms".to_string()),
+            Renderer::DurationMs.render(&float(0.0015))
+        );
+        assert_eq!(float(-1.0), Renderer::DurationMs.render(&float(-1.0)));
+        assert_eq!(
+            Value::String("73us".to_string()),
+            Renderer::Duration.render(&float(0.000_073))
+        );
+        assert_eq!(
+            Value::String("2m 5s".to_string()),
+            Renderer::Duration.render(&Value::Int(125))
+        );
+        assert_eq!(
+            Value::String("12.5%".to_string()),
+            Renderer::Percent.render(&float(0.125))
+        );
+        assert_eq!(Value::Null, Renderer::Bytes.render(&Value::Null));
+    }
 }
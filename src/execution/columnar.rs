@@ -0,0 +1,444 @@
+use super::datasource::{ReaderBuilder, ReaderError, ReaderResult, RecordRead};
+use super::source::SourceMetadata;
+use super::stream::Record;
+use crate::common;
+use crate::common::types::{Value, Variables};
+use chrono::{FixedOffset, TimeZone};
+use linked_hash_map::LinkedHashMap;
+use ordered_float::OrderedFloat;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+//The copies of the older layout are ignored, the log file is read in their place until it is converted again
+const MAGIC: &[u8] = b"LOGQC\x02";
+const EXTENSION: &str = "logqc";
+const ROW_GROUP_SIZE: usize = 4096;
+
+const TAG_ABSENT: u8 = 0;
+const TAG_NULL: u8 = 1;
+const TAG_MISSING: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_FLOAT: u8 = 4;
+const TAG_BOOLEAN: u8 = 5;
+const TAG_STRING: u8 = 6;
+const TAG_DATETIME: u8 = 7;
+const TAG_HTTP_REQUEST: u8 = 8;
+const TAG_HOST: u8 = 9;
+const TAG_OBJECT: u8 = 10;
+const TAG_ARRAY: u8 = 11;
+const TAG_LONG: u8 = 12;
+
+//The converted copy sits next to the log file, e.g. "elb.log" -> "elb.log.logqc"
+pub(crate) fn converted_path(path: &Path) -> PathBuf {
+    let mut s = path.as_os_str().to_os_string();
+    s.push(".");
+    s.push(EXTENSION);
+    PathBuf::from(s)
+}
+
+//The temporary file of an interrupted conversion is also skipped
+pub(crate) fn is_converted_path(path: &Path) -> bool {
+    path.file_name().is_some_and(|f| {
+        let f = f.to_string_lossy();
+        f.ends_with(&format!(".{}", EXTENSION)) || f.ends_with(&format!(".{}.tmp", EXTENSION))
+    })
+}
+
+fn invalid_data(msg: &str) -> ReaderError {
+    ReaderError::Io(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()))
+}
+
+fn write_u32<W: Write>(w: &mut W, n: usize) -> io::Result<()> {
+    if n > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "too large to convert"));
+    }
+    w.write_all(&(n as u32).to_le_bytes())
+}
+
+fn write_str<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    write_u32(w, s.len())?;
+    w.write_all(s.as_bytes())
+}
+
+fn write_value<W: Write>(w: &mut W, value: &Value) -> io::Result<()> {
+    match value {
+        Value::Null => w.write_all(&[TAG_NULL]),
+        Value::Missing => w.write_all(&[TAG_MISSING]),
+        Value::Int(i) => {
+            w.write_all(&[TAG_INT])?;
+            w.write_all(&i.to_le_bytes())
+        }
+        Value::Long(l) => {
+            w.write_all(&[TAG_LONG])?;
+            w.write_all(&l.to_le_bytes())
+        }
+        Value::Float(f) => {
+            w.write_all(&[TAG_FLOAT])?;
+            w.write_all(&f.into_inner().to_le_bytes())
+        }
+        Value::Boolean(b) => w.write_all(&[TAG_BOOLEAN, *b as u8]),
+        Value::String(s) => {
+            w.write_all(&[TAG_STRING])?;
+            write_str(w, s)
+        }
+        Value::DateTime(dt) => {
+            w.write_all(&[TAG_DATETIME])?;
+            w.write_all(&dt.timestamp().to_le_bytes())?;
+            w.write_all(&dt.timestamp_subsec_nanos().to_le_bytes())?;
+            w.write_all(&dt.offset().local_minus_utc().to_le_bytes())
+        }
+        Value::HttpRequest(request) => {
+            w.write_all(&[TAG_HTTP_REQUEST])?;
+            write_str(w, &request.http_method)?;
+            write_str(w, request.url.as_str())?;
+            write_str(w, &request.http_version)
+        }
+        Value::Host(host) => {
+            w.write_all(&[TAG_HOST])?;
+            write_str(w, &host.hostname)?;
+            w.write_all(&host.port.to_le_bytes())
+        }
+        Value::Object(o) => {
+            w.write_all(&[TAG_OBJECT])?;
+            write_u32(w, o.len())?;
+            for (k, v) in o.iter() {
+                write_str(w, k)?;
+                write_value(w, v)?;
+            }
+            Ok(())
+        }
+        Value::Array(a) => {
+            w.write_all(&[TAG_ARRAY])?;
+            write_u32(w, a.len())?;
+            for v in a.iter() {
+                write_value(w, v)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+//A row group is stored column by column, the columns are the field names in the order of their first appearance
+//and a record without the field has an absent marker in the column. The column is prefixed by its length in bytes, so
+//the columns the query doesn't refer to are skipped without decoding them.
+fn write_row_group<W: Write>(w: &mut W, records: &[Record]) -> io::Result<()> {
+    let mut columns: Vec<String> = Vec::new();
+    for record in records.iter() {
+        for (name, _) in record.to_variables().iter() {
+            if !columns.contains(name) {
+                columns.push(name.clone());
+            }
+        }
+    }
+
+    write_u32(w, records.len())?;
+    write_u32(w, columns.len())?;
+    let mut buf = Vec::new();
+    for column in columns.iter() {
+        buf.clear();
+        for record in records.iter() {
+            match record.to_variables().get(column) {
+                Some(value) => write_value(&mut buf, value)?,
+                None => buf.write_all(&[TAG_ABSENT])?,
+            }
+        }
+        write_str(w, column)?;
+        write_u32(w, buf.len())?;
+        w.write_all(&buf)?;
+    }
+
+    Ok(())
+}
+
+fn read_bytes<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<usize> {
+    Ok(u32::from_le_bytes(read_bytes(r)?) as usize)
+}
+
+fn read_str<R: Read>(r: &mut R) -> ReaderResult<String> {
+    //A corrupted length should not allocate more than what is in the file
+    let len = read_u32(r)?;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(invalid_data("truncated converted file"));
+    }
+    String::from_utf8(buf).map_err(|_| invalid_data("invalid string in converted file"))
+}
+
+fn read_value<R: Read>(r: &mut R, tag: u8) -> ReaderResult<Value> {
+    match tag {
+        TAG_NULL => Ok(Value::Null),
+        TAG_MISSING => Ok(Value::Missing),
+        TAG_INT => Ok(Value::Int(i32::from_le_bytes(read_bytes(r)?))),
+        TAG_LONG => Ok(Value::Long(i64::from_le_bytes(read_bytes(r)?))),
+        TAG_FLOAT => Ok(Value::Float(OrderedFloat::from(f32::from_le_bytes(read_bytes(r)?)))),
+        TAG_BOOLEAN => Ok(Value::Boolean(read_bytes::<_, 1>(r)?[0] != 0)),
+        TAG_STRING => Ok(Value::String(read_str(r)?)),
+        TAG_DATETIME => {
+            let secs = i64::from_le_bytes(read_bytes(r)?);
+            let nanos = u32::from_le_bytes(read_bytes(r)?);
+            let offset = i32::from_le_bytes(read_bytes(r)?);
+            let dt = FixedOffset::east_opt(offset)
+                .and_then(|tz| tz.timestamp_opt(secs, nanos).single())
+                .ok_or_else(|| invalid_data("invalid datetime in converted file"))?;
+            Ok(Value::DateTime(dt))
+        }
+        TAG_HTTP_REQUEST => {
+            let http_method = read_str(r)?;
+            let url = url::Url::parse(&read_str(r)?)?;
+            let http_version = read_str(r)?;
+            Ok(Value::HttpRequest(common::types::HttpRequest {
+                http_method,
+                url,
+                http_version,
+            }))
+        }
+        TAG_HOST => {
+            let hostname = read_str(r)?;
+            let port = u16::from_le_bytes(read_bytes(r)?);
+            Ok(Value::Host(common::types::Host { hostname, port }))
+        }
+        TAG_OBJECT => {
+            let len = read_u32(r)?;
+            let mut o = LinkedHashMap::new();
+            for _ in 0..len {
+                let k = read_str(r)?;
+                let tag = read_bytes::<_, 1>(r)?[0];
+                o.insert(k, read_value(r, tag)?);
+            }
+            Ok(Value::Object(o))
+        }
+        TAG_ARRAY => {
+            let len = read_u32(r)?;
+            let mut a = Vec::new();
+            for _ in 0..len {
+                let tag = read_bytes::<_, 1>(r)?[0];
+                a.push(read_value(r, tag)?);
+            }
+            Ok(Value::Array(a))
+        }
+        _ => Err(invalid_data("unknown value tag in converted file")),
+    }
+}
+
+//The reader of the converted copy, the values are stored typed so nothing is parsed again except the requests and hosts
+pub(crate) struct ColumnarReader<R> {
+    rdr: BufReader<R>,
+    records: VecDeque<Record>,
+    //The columns decoded into the records, none for all of them
+    columns: Option<Vec<String>>,
+    //Of the log file rather than the copy, the records are the ones of the log file
+    metadata: SourceMetadata,
+}
+
+impl<R: Read + Seek> ColumnarReader<R> {
+    //The signature records the format and the type options the copy was converted with, a copy converted
+    //with the different options is not used.
+    pub(crate) fn new(rdr: R, signature: &str) -> ReaderResult<Option<Self>> {
+        let mut rdr = BufReader::new(rdr);
+        let mut magic = [0u8; 6];
+        if rdr.read_exact(&mut magic).is_err() || magic != MAGIC {
+            return Ok(None);
+        }
+        if read_str(&mut rdr)? != signature {
+            return Ok(None);
+        }
+
+        Ok(Some(ColumnarReader {
+            rdr,
+            records: VecDeque::new(),
+            columns: None,
+            metadata: SourceMetadata::default(),
+        }))
+    }
+
+    fn read_row_group(&mut self) -> ReaderResult<bool> {
+        let mut buf = [0u8; 4];
+        let n = self.rdr.read(&mut buf[..1])?;
+        if n == 0 {
+            return Ok(false);
+        }
+        self.rdr.read_exact(&mut buf[1..])?;
+        let row_count = u32::from_le_bytes(buf) as usize;
+        let column_count = read_u32(&mut self.rdr)?;
+
+        let mut rows: Vec<Variables> = (0..row_count).map(|_| Variables::new()).collect();
+        for _ in 0..column_count {
+            let column = read_str(&mut self.rdr)?;
+            let len = read_u32(&mut self.rdr)?;
+            if self.columns.as_ref().is_some_and(|columns| !columns.contains(&column)) {
+                self.rdr.seek_relative(len as i64)?;
+                continue;
+            }
+
+            for row in rows.iter_mut() {
+                let tag = read_bytes::<_, 1>(&mut self.rdr)?[0];
+                if tag != TAG_ABSENT {
+                    row.insert(column.clone(), read_value(&mut self.rdr, tag)?);
+                }
+            }
+        }
+
+        self.records.extend(rows.into_iter().map(Record::new_with_variables));
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> RecordRead for ColumnarReader<R> {
+    fn read_record(&mut self) -> ReaderResult<Option<Record>> {
+        while self.records.is_empty() {
+            if !self.read_row_group()? {
+                return Ok(None);
+            }
+        }
+
+        Ok(self.records.pop_front())
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        self.metadata.clone()
+    }
+
+    fn position(&self) -> Option<(usize, u64)> {
+        None
+    }
+}
+
+//The converted copy of the log file, if there is one converted with the same options and not older than the log file
+pub(crate) fn open_converted(builder: &ReaderBuilder, path: &Path) -> ReaderResult<Option<ColumnarReader<File>>> {
+    let copy_path = converted_path(path);
+    let (copy_modified, modified) = match (fs::metadata(&copy_path), fs::metadata(path)) {
+        (Ok(copy_metadata), Ok(metadata)) => (copy_metadata.modified()?, metadata.modified()?),
+        _ => return Ok(None),
+    };
+    if copy_modified < modified {
+        return Ok(None);
+    }
+
+    let reader = ColumnarReader::new(File::open(copy_path)?, &builder.signature())?;
+    Ok(reader.map(|reader| ColumnarReader {
+        metadata: SourceMetadata {
+            name: path.to_string_lossy().to_string(),
+            path: Some(path.to_path_buf()),
+        },
+        columns: builder.columns_to_read().map(<[String]>::to_vec),
+        ..reader
+    }))
+}
+
+//Parses the log file once and writes the typed columnar copy next to it, returns the path and the number of records
+pub(crate) fn convert(builder: &ReaderBuilder, path: &Path) -> ReaderResult<(PathBuf, usize)> {
+    let copy_path = converted_path(path);
+    let tmp_path = copy_path.with_extension(format!("{}.tmp", EXTENSION));
+
+    let mut reader = builder.with_path(path)?;
+    let mut w = BufWriter::new(File::create(&tmp_path)?);
+    w.write_all(MAGIC)?;
+    write_str(&mut w, &builder.signature())?;
+
+    let mut count = 0;
+    let mut records = Vec::with_capacity(ROW_GROUP_SIZE);
+    while let Some(record) = reader.read_record()? {
+        records.push(record);
+        if records.len() == ROW_GROUP_SIZE {
+            write_row_group(&mut w, &records)?;
+            count += records.len();
+            records.clear();
+        }
+    }
+    if !records.is_empty() {
+        write_row_group(&mut w, &records)?;
+        count += records.len();
+    }
+    w.flush()?;
+    drop(w);
+
+    fs::rename(&tmp_path, &copy_path)?;
+    Ok((copy_path, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_convert_round_trip() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("elb.log");
+        let content = r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 200 200 0 42355 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2
+2015-11-07T18:45:37.691548Z elb1 176.219.166.226:48384 - -1 -1 -1 504 0 0 0 "POST http://example.com:80/api HTTP/1.1" "-" - -"#;
+        fs::write(&log_path, content).unwrap();
+
+        let builder = ReaderBuilder::new("elb".to_string());
+        let mut expected = Vec::new();
+        let mut reader = builder.with_path(&log_path).unwrap();
+        while let Some(record) = reader.read_record().unwrap() {
+            expected.push(record);
+        }
+
+        assert!(open_converted(&builder, &log_path).unwrap().is_none());
+        let (copy_path, count) = convert(&builder, &log_path).unwrap();
+        assert_eq!(converted_path(&log_path), copy_path);
+        assert!(is_converted_path(&copy_path));
+        assert_eq!(2, count);
+
+        let mut reader = open_converted(&builder, &log_path).unwrap().unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = reader.read_record().unwrap() {
+            records.push(record);
+        }
+        assert_eq!(expected, records);
+
+        //Only the columns the query refers to are read
+        let columns = vec!["elb_status_code".to_string(), "user_agent".to_string()];
+        let mut reader = open_converted(&builder.clone().columns(Some(columns.clone())), &log_path)
+            .unwrap()
+            .unwrap();
+        for record in expected.iter() {
+            let projected = reader.read_record().unwrap().unwrap();
+            let mut names: Vec<String> = projected.to_variables().keys().cloned().collect();
+            names.sort();
+            assert_eq!(columns, names);
+            for name in columns.iter() {
+                assert_eq!(record.to_variables().get(name), projected.to_variables().get(name));
+            }
+        }
+        assert!(reader.read_record().unwrap().is_none());
+
+        //The copy converted with the other options is ignored
+        let other_builder = ReaderBuilder::new("elb".to_string()).time_format(Some("%s".to_string()));
+        assert!(open_converted(&other_builder, &log_path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_convert_round_trip_nested_values() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("app.jsonl");
+        let content = "{\"a\": 1, \"b\": {\"c\": [1.5, \"x\", null, true]}}\n{\"d\": \"only\"}\n";
+        fs::write(&log_path, content).unwrap();
+
+        let builder = ReaderBuilder::new("jsonl".to_string());
+        let mut expected = Vec::new();
+        let mut reader = builder.with_path(&log_path).unwrap();
+        while let Some(record) = reader.read_record().unwrap() {
+            expected.push(record);
+        }
+
+        convert(&builder, &log_path).unwrap();
+        let mut reader = open_converted(&builder, &log_path).unwrap().unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = reader.read_record().unwrap() {
+            records.push(record);
+        }
+        assert_eq!(expected, records);
+    }
+}
@@ -1,6 +1,8 @@
+use super::columnar;
+use super::source::{CommandOutput, FileSource, ReadSource, Source, SourceMetadata, TailSource};
 use super::stream::Record;
 use crate::common;
-use crate::common::types::Value;
+use crate::common::types::{Value, Variables};
 use json;
 use ordered_float::OrderedFloat;
 use regex::Regex;
@@ -8,17 +10,24 @@ use url;
 
 use json::JsonValue;
 use linked_hash_map::LinkedHashMap;
+use std::collections::VecDeque;
 use std::fmt;
-use std::fs::File;
 use std::io;
-use std::io::BufRead;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::result;
 use std::str::FromStr;
 
 lazy_static! {
-    static ref SPLIT_READER_LINE_REGEX: Regex =
-        Regex::new(r#"[^\s"'\[\]]+|"([^"]*)"|'([^']*)'|\[([^\[\]]*)\]"#).unwrap();
+    //The comma separated quoted strings, e.g. the ALPN preference list "h2","http/1.1" of NLB, are one field, so are
+    //the bracketed IPv6 address with its port and the quoted strings with the escaped quotes
+    static ref SPLIT_READER_LINE_REGEX: Regex = Regex::new(
+        r#"\[[0-9a-fA-F:.]*\]:[0-9]+|[^\s"'\[\]]+|"((?:[^"\\]|\\.)*)"(,"[^"]*")*|'([^']*)'|\[([^\[\]]*)\]"#
+    )
+    .unwrap();
+    //e.g. 123456789012_elasticloadbalancing_us-east-1_app.my-lb.1234567890abcdef_20140215T2340Z_172.160.001.192_20sg8hgm.log
+    static ref AWS_LOG_FILE_NAME_REGEX: Regex =
+        Regex::new(r#"^(\d{12})_elasticloadbalancing_([a-z0-9-]+)_(.+)_(\d{8}T\d{4}Z)_([^_]+)_([^_.]+)\.log(\.gz)?$"#)
+            .unwrap();
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -29,6 +38,13 @@ pub(crate) enum DataType {
     Float,
     Host,
     HttpRequest,
+    FormattedDateTime(String),
+    //A string in the double quotes, where "-" means the value is absent
+    QuotedString,
+    //The space separated values in the double quotes, e.g. the targets of the ALB retries, read as an array
+    QuotedList,
+    //The nested lists and objects, e.g. of the WAF logs
+    Json,
 }
 
 impl fmt::Display for DataType {
@@ -40,12 +56,147 @@ impl fmt::Display for DataType {
             DataType::Float => "Float",
             DataType::Host => "Host",
             DataType::HttpRequest => "HttpRequest",
+            DataType::QuotedString => "QuotedString",
+            DataType::QuotedList => "QuotedList",
+            DataType::Json => "Json",
+            DataType::FormattedDateTime(format) => {
+                return write!(f, "DateTime({})", format);
+            }
         };
 
         write!(f, "{}", name)
     }
 }
 
+impl FromStr for DataType {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        if trimmed.starts_with("datetime(") && trimmed.ends_with(')') {
+            let format = &trimmed["datetime(".len()..trimmed.len() - 1];
+            return Ok(DataType::FormattedDateTime(format.to_string()));
+        }
+
+        match &*trimmed.to_ascii_lowercase() {
+            "datetime" => Ok(DataType::DateTime),
+            "string" => Ok(DataType::String),
+            "int" | "integral" => Ok(DataType::Integral),
+            "float" => Ok(DataType::Float),
+            "host" => Ok(DataType::Host),
+            "request" | "http_request" => Ok(DataType::HttpRequest),
+            "quoted_string" => Ok(DataType::QuotedString),
+            "quoted_list" => Ok(DataType::QuotedList),
+            "json" => Ok(DataType::Json),
+            _ => Err(format!("unknown type \"{}\"", trimmed)),
+        }
+    }
+}
+
+pub(crate) type FieldTypes = Vec<(String, DataType)>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct ReaderOptions {
+    pub(crate) field_types: FieldTypes,
+    pub(crate) time_format: Option<String>,
+    //The bounds on the timestamp from the where clause, used to skip the files which can't match
+    pub(crate) time_range: TimeRange,
+    //The equalities of the fields with the literals from the where clause, used with the index to skip the files
+    pub(crate) value_filters: Vec<(String, Value)>,
+    pub(crate) index_dir: Option<PathBuf>,
+    //The records read ahead of the query on the thread of the reader, none to read on the thread of the query
+    pub(crate) channel_capacity: Option<usize>,
+    //The log format of the nginx and apache tables, the default of the format if none
+    pub(crate) log_format: Option<Box<LogFormat>>,
+    //The files are read as they grow by the runs of --follow
+    pub(crate) follow: bool,
+    //The top level fields the query refers to, a converted copy only reads these columns. None for all of them.
+    pub(crate) columns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct TimeRange {
+    pub(crate) start: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub(crate) end: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+impl TimeRange {
+    pub(crate) fn intersect(&self, other: &TimeRange) -> TimeRange {
+        let start = match (self.start, other.start) {
+            (Some(a), Some(b)) => Some(std::cmp::max(a, b)),
+            (a, b) => a.or(b),
+        };
+        let end = match (self.end, other.end) {
+            (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+            (a, b) => a.or(b),
+        };
+
+        TimeRange { start, end }
+    }
+
+    //The log file delivered every 5 or 60 minutes, named by the end of the interval. Allow a few minutes
+    //on the other side as the records can be written slightly after the interval ends.
+    pub(crate) fn may_overlap_file(&self, path: &Path) -> bool {
+        let end_time = path_columns(Some(path))
+            .into_iter()
+            .find(|(name, _)| name == "__end_time")
+            .and_then(|(_, v)| match v {
+                Value::DateTime(dt) => Some(dt),
+                _ => None,
+            });
+
+        if let Some(end_time) = end_time {
+            let file_start = end_time - chrono::Duration::minutes(65);
+            let file_end = end_time + chrono::Duration::minutes(5);
+            let after_range = self.end.is_some_and(|end| file_start > end);
+            let before_range = self.start.is_some_and(|start| file_end < start);
+            !(after_range || before_range)
+        } else {
+            true
+        }
+    }
+}
+
+//Parse the annotations like "status:int,time:float,timestamp:datetime(%d/%b/%Y:%H:%M:%S %z)",
+//the commas inside the parentheses belong to the datetime format.
+pub(crate) fn parse_field_types(s: &str) -> result::Result<FieldTypes, String> {
+    let mut annotations: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    for c in s.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+
+        if c == ',' && depth == 0 {
+            annotations.push(current.clone());
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    annotations.push(current);
+
+    let mut field_types = FieldTypes::new();
+    for annotation in annotations.iter() {
+        if let Some(pos) = annotation.find(':') {
+            let field_name = annotation[..pos].trim().to_ascii_lowercase();
+            if field_name.is_empty() {
+                return Err(format!("missing field name in \"{}\"", annotation));
+            }
+
+            let datatype = DataType::from_str(&annotation[pos + 1..])?;
+            field_types.push((field_name, datatype));
+        } else {
+            return Err(format!("expect \"field:type\" but got \"{}\"", annotation));
+        }
+    }
+
+    Ok(field_types)
+}
+
 lazy_static! {
     static ref AWS_ELB_DATATYPES: Vec<DataType> = {
         vec![
@@ -115,17 +266,28 @@ lazy_static! {
             DataType::String,
             DataType::String,
             DataType::String,
+            DataType::QuotedString,
             DataType::String,
+            DataType::Integral,
             DataType::String,
-            DataType::String,
-            DataType::String,
-            DataType::String,
-            DataType::String,
-            DataType::String,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedList,
+            DataType::QuotedList,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
         ]
     };
 }
 
+//The number of fields of the versions of the ALB logs, AWS appends the new fields to the end of the line
+const ALB_VARIANT_WIDTHS: [usize; 8] = [18, 20, 24, 25, 27, 29, 30, 33];
+
 lazy_static! {
     static ref AWS_ALB_FIELD_NAMES: Vec<String> = {
         vec![
@@ -154,6 +316,14 @@ lazy_static! {
             "action_executed".to_string(),
             "redirect_url".to_string(),
             "error_reason".to_string(),
+            "target_port_list".to_string(),
+            "target_status_code_list".to_string(),
+            "classification".to_string(),
+            "classification_reason".to_string(),
+            "conn_trace_id".to_string(),
+            "transformed_host".to_string(),
+            "transformed_uri".to_string(),
+            "request_transform_status".to_string(),
         ]
     };
 }
@@ -185,6 +355,8 @@ lazy_static! {
             "authentication_type".to_string(),
             "host_header".to_string(),
             "tls_version".to_string(),
+            "access_point_arn".to_string(),
+            "acl_required".to_string(),
         ]
     };
 }
@@ -205,6 +377,8 @@ lazy_static! {
             DataType::String,
             DataType::String,
             DataType::String,
+            DataType::Integral,
+            DataType::Integral,
             DataType::String,
             DataType::String,
             DataType::String,
@@ -254,122 +428,1006 @@ lazy_static! {
     };
 }
 
-//Reference: https://docs.aws.amazon.com/elasticloadbalancing/latest/classic/access-log-collection.html
-pub(crate) enum ClassicLoadBalancerLogField {
-    Timestamp = 0,
-    Elbname = 1,
-    ClientAndPort = 2,
-    BackendAndPort = 3,
-    RequestProcessingTime = 4,
-    BackendProcessingTime = 5,
-    ResponseProcessingTime = 6,
-    ELBStatusCode = 7,
-    BackendStatusCode = 8,
-    ReceivedBytes = 9,
-    SentBytes = 10,
-    Request = 11,
-    UserAgent = 12,
-    SSLCipher = 13,
-    SSLProtocol = 14,
-    TargetGroupArn = 15,
-    TraceID = 16,
+//The NLB times are in UTC without the timezone, e.g. 2018-12-20T02:59:40
+const NLB_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+lazy_static! {
+    static ref AWS_NLB_FIELD_NAMES: Vec<String> = {
+        vec![
+            "type".to_string(),
+            "version".to_string(),
+            "timestamp".to_string(),
+            "elb".to_string(),
+            "listener".to_string(),
+            "client_and_port".to_string(),
+            "destination_and_port".to_string(),
+            "connection_time".to_string(),
+            "tls_handshake_time".to_string(),
+            "received_bytes".to_string(),
+            "sent_bytes".to_string(),
+            "incoming_tls_alert".to_string(),
+            "chosen_cert_arn".to_string(),
+            "chosen_cert_serial".to_string(),
+            "tls_cipher".to_string(),
+            "tls_protocol_version".to_string(),
+            "tls_named_group".to_string(),
+            "domain_name".to_string(),
+            "alpn_fe_protocol".to_string(),
+            "alpn_be_protocol".to_string(),
+            "alpn_client_preference_list".to_string(),
+            "tls_connection_creation_time".to_string(),
+        ]
+    };
 }
 
-impl fmt::Display for ClassicLoadBalancerLogField {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name = match self {
-            ClassicLoadBalancerLogField::Timestamp => "timestamp",
-            ClassicLoadBalancerLogField::Elbname => "elbname",
-            ClassicLoadBalancerLogField::ClientAndPort => "client_and_port",
-            ClassicLoadBalancerLogField::BackendAndPort => "backend_and_port",
-            ClassicLoadBalancerLogField::RequestProcessingTime => "request_processing_time",
-            ClassicLoadBalancerLogField::BackendProcessingTime => "backend_processing_time",
-            ClassicLoadBalancerLogField::ResponseProcessingTime => "response_processing_time",
-            ClassicLoadBalancerLogField::ELBStatusCode => "elb_status_code",
-            ClassicLoadBalancerLogField::BackendStatusCode => "backend_status_code",
-            ClassicLoadBalancerLogField::ReceivedBytes => "received_bytes",
-            ClassicLoadBalancerLogField::SentBytes => "sent_bytes",
-            ClassicLoadBalancerLogField::Request => "request",
-            ClassicLoadBalancerLogField::UserAgent => "user_agent",
-            ClassicLoadBalancerLogField::SSLCipher => "ssl_cipher",
-            ClassicLoadBalancerLogField::SSLProtocol => "ssl_protocol",
-            ClassicLoadBalancerLogField::TargetGroupArn => "target_group_arn",
-            ClassicLoadBalancerLogField::TraceID => "trace_id",
-        };
+lazy_static! {
+    static ref AWS_NLB_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::String,
+            DataType::String,
+            DataType::FormattedDateTime(NLB_TIME_FORMAT.to_string()),
+            DataType::String,
+            DataType::String,
+            DataType::Host,
+            DataType::Host,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::String,
+            DataType::FormattedDateTime(NLB_TIME_FORMAT.to_string()),
+        ]
+    };
+}
 
-        write!(f, "{}", name)
-    }
+lazy_static! {
+    static ref AWS_ALB_CONN_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "client_ip".to_string(),
+            "client_port".to_string(),
+            "listener_port".to_string(),
+            "tls_protocol".to_string(),
+            "tls_cipher".to_string(),
+            "tls_handshake_latency".to_string(),
+            "leaf_client_cert_subject".to_string(),
+            "leaf_client_cert_validity".to_string(),
+            "leaf_client_cert_serial_number".to_string(),
+            "tls_verify_status".to_string(),
+            "conn_trace_id".to_string(),
+        ]
+    };
 }
 
-impl FromStr for ClassicLoadBalancerLogField {
-    type Err = String;
+lazy_static! {
+    static ref AWS_ALB_CONN_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::Float,
+            DataType::QuotedString,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+        ]
+    };
+}
 
-    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
-        match s {
-            "timestamp" => Ok(ClassicLoadBalancerLogField::Timestamp),
-            "elbname" => Ok(ClassicLoadBalancerLogField::Elbname),
-            "client_and_port" => Ok(ClassicLoadBalancerLogField::ClientAndPort),
-            "backend_and_port" => Ok(ClassicLoadBalancerLogField::BackendAndPort),
-            "request_processing_time" => Ok(ClassicLoadBalancerLogField::RequestProcessingTime),
-            "backend_processing_time" => Ok(ClassicLoadBalancerLogField::BackendProcessingTime),
-            "response_processing_time" => Ok(ClassicLoadBalancerLogField::ResponseProcessingTime),
-            "elb_status_code" => Ok(ClassicLoadBalancerLogField::ELBStatusCode),
-            "backend_status_code" => Ok(ClassicLoadBalancerLogField::BackendStatusCode),
-            "received_bytes" => Ok(ClassicLoadBalancerLogField::ReceivedBytes),
-            "sent_bytes" => Ok(ClassicLoadBalancerLogField::SentBytes),
-            "request" => Ok(ClassicLoadBalancerLogField::Request),
-            "user_agent" => Ok(ClassicLoadBalancerLogField::UserAgent),
-            "ssl_cipher" => Ok(ClassicLoadBalancerLogField::SSLCipher),
-            "ssl_protocol" => Ok(ClassicLoadBalancerLogField::SSLProtocol),
-            "target_group_arn" => Ok(ClassicLoadBalancerLogField::TargetGroupArn),
-            "trace_id" => Ok(ClassicLoadBalancerLogField::TraceID),
-            _ => Err("unknown column name".to_string()),
-        }
-    }
+lazy_static! {
+    static ref HAPROXY_HTTP_FIELD_NAMES: Vec<String> = {
+        vec![
+            "client_ip".to_string(),
+            "client_port".to_string(),
+            "timestamp".to_string(),
+            "frontend_name".to_string(),
+            "backend_name".to_string(),
+            "server_name".to_string(),
+            "tq".to_string(),
+            "tw".to_string(),
+            "tc".to_string(),
+            "tr".to_string(),
+            "tt".to_string(),
+            "status_code".to_string(),
+            "bytes_read".to_string(),
+            "captured_request_cookie".to_string(),
+            "captured_response_cookie".to_string(),
+            "termination_state".to_string(),
+            "actconn".to_string(),
+            "feconn".to_string(),
+            "beconn".to_string(),
+            "srv_conn".to_string(),
+            "retries".to_string(),
+            "srv_queue".to_string(),
+            "backend_queue".to_string(),
+            "captured_request_headers".to_string(),
+            "captured_response_headers".to_string(),
+            "request".to_string(),
+        ]
+    };
 }
 
-impl ClassicLoadBalancerLogField {
-    pub(crate) fn len() -> usize {
-        17
-    }
+lazy_static! {
+    static ref HAPROXY_HTTP_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::String,
+            DataType::Integral,
+            DataType::FormattedDateTime(HAPROXY_TIME_FORMAT.to_string()),
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::HttpRequest,
+        ]
+    };
+}
 
-    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
-        &AWS_ELB_FIELD_NAMES
-    }
+lazy_static! {
+    //A capture group per field, after the syslog header if any. The "+" in front of the total time, the bytes and the
+    //retries marks the logasap or the redispatch, and the captured headers are only there when configured.
+    static ref HAPROXY_HTTP_LINE_REGEX: Regex = Regex::new(
+        r#"^(?:[^"]*?: )?(\S+):(\d+) \[([^\]]+)\] (\S+) ([^\s/]+)/(\S+) (-?\d+)/(-?\d+)/(-?\d+)/(-?\d+)/\+?(-?\d+) (-?\d+) \+?(\d+) (\S+) (\S+) (\S+) (\d+)/(\d+)/(\d+)/(\d+)/\+?(\d+) (\d+)/(\d+)(?: \{([^}]*)\})?(?: \{([^}]*)\})? "(.*)"$"#
+    )
+    .unwrap();
+}
 
-    pub(crate) fn datatypes() -> Vec<DataType> {
-        AWS_ELB_DATATYPES.clone()
-    }
+lazy_static! {
+    static ref SYSLOG_FIELD_NAMES: Vec<String> = {
+        vec![
+            "priority".to_string(),
+            "facility".to_string(),
+            "severity".to_string(),
+            "version".to_string(),
+            "timestamp".to_string(),
+            "hostname".to_string(),
+            "app_name".to_string(),
+            "procid".to_string(),
+            "msgid".to_string(),
+            "structured_data".to_string(),
+            "message".to_string(),
+        ]
+    };
+}
 
-    pub(crate) fn datatype(idx: usize) -> DataType {
-        AWS_ELB_DATATYPES[idx].clone()
-    }
+lazy_static! {
+    static ref SYSLOG_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::DateTime,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::String,
+        ]
+    };
+}
 
-    pub(crate) fn schema() -> Vec<(String, DataType)> {
-        let fields = Self::field_names().clone();
-        let datatypes = Self::datatypes();
-        fields.into_iter().zip(datatypes.into_iter()).collect()
-    }
+lazy_static! {
+    //The priority, the version, the timestamp, the hostname, the app name, the procid, the msgid, the structured data
+    //and the message of RFC5424
+    static ref SYSLOG_RFC5424_LINE_REGEX: Regex = Regex::new(
+        r#"^<(\d{1,3})>(\d{1,2}) (\S+) (\S+) (\S+) (\S+) (\S+) (-|(?:\[[^\s\]]+(?: [^=\s\]]+="(?:[^"\\]|\\.)*")*\])+)(?: (.*))?$"#
+    )
+    .unwrap();
+    //The priority, the timestamp, the hostname, the tag with the pid and the message of RFC3164. The files written by
+    //rsyslog have no priority, and the timestamp is in RFC3339 with the high precision format of rsyslog.
+    static ref SYSLOG_RFC3164_LINE_REGEX: Regex = Regex::new(
+        r#"^(?:<(\d{1,3})>)?([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) (\S+) (?:([^\s:\[]+)(?:\[([^\]]*)\])?: ?)?(.*)$"#
+    )
+    .unwrap();
+    static ref SYSLOG_SD_ELEMENT_REGEX: Regex = Regex::new(r#"\[([^\s\]]+)((?: [^=\s\]]+="(?:[^"\\]|\\.)*")*)\]"#).unwrap();
+    static ref SYSLOG_SD_PARAM_REGEX: Regex = Regex::new(r#" ([^=\s\]]+)="((?:[^"\\]|\\.)*)""#).unwrap();
 }
 
-//Reference: https://docs.aws.amazon.com/elasticloadbalancing/latest/application/load-balancer-access-logs.html
-pub(crate) enum ApplicationLoadBalancerLogField {
-    Type = 0,
-    Timestamp = 1,
-    Elbname = 2,
-    ClientAndPort = 3,
-    TargetAndPort = 4,
-    RequestProcessingTime = 5,
-    TargetProcessingTime = 6,
-    ResponseProcessingTime = 7,
-    ELBStatusCode = 8,
-    TargetStatusCode = 9,
-    ReceivedBytes = 10,
-    SentBytes = 11,
-    Request = 12,
-    UserAgent = 13,
-    SSLCipher = 14,
-    SSLProtocol = 15,
+lazy_static! {
+    static ref TRAEFIK_FIELD_NAMES: Vec<String> = {
+        vec![
+            "client_host".to_string(),
+            "client_port".to_string(),
+            "client_username".to_string(),
+            "timestamp".to_string(),
+            "request".to_string(),
+            "request_host".to_string(),
+            "request_scheme".to_string(),
+            "downstream_status".to_string(),
+            "downstream_content_size".to_string(),
+            "referer".to_string(),
+            "user_agent".to_string(),
+            "request_count".to_string(),
+            "router_name".to_string(),
+            "service_name".to_string(),
+            "service_url".to_string(),
+            "origin_status".to_string(),
+            "origin_content_size".to_string(),
+            "duration_ms".to_string(),
+            "origin_duration_ms".to_string(),
+            "retry_attempts".to_string(),
+            "entry_point_name".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref TRAEFIK_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::String,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::FormattedDateTime(TRAEFIK_CLF_TIME_FORMAT.to_string()),
+            DataType::HttpRequest,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::String,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Float,
+            DataType::Float,
+            DataType::Integral,
+            DataType::String,
+        ]
+    };
+}
+
+lazy_static! {
+    //The quoted values may have the escaped quotes, the duration is in milliseconds with the "ms" suffix
+    static ref TRAEFIK_CLF_LINE_REGEX: Regex = Regex::new(
+        r#"^(\S+) - (\S+) \[([^\]]+)\] "((?:[^"\\]|\\.)*)" (\S+) (\S+) (?:"((?:[^"\\]|\\.)*)"|-) (?:"((?:[^"\\]|\\.)*)"|-) (\S+) (?:"((?:[^"\\]|\\.)*)"|-) (?:"((?:[^"\\]|\\.)*)"|-) (\d+)ms$"#
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    static ref CADDY_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "level".to_string(),
+            "logger".to_string(),
+            "msg".to_string(),
+            "request".to_string(),
+            "bytes_read".to_string(),
+            "user_id".to_string(),
+            "duration".to_string(),
+            "size".to_string(),
+            "status".to_string(),
+            "resp_headers".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref CADDY_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::Integral,
+            DataType::String,
+            DataType::Float,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Json,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref CLOUDFLARE_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "ray_id".to_string(),
+            "zone_name".to_string(),
+            "client_ip".to_string(),
+            "client_country".to_string(),
+            "client_request_host".to_string(),
+            "client_request_method".to_string(),
+            "client_request_uri".to_string(),
+            "client_request_protocol".to_string(),
+            "client_request_user_agent".to_string(),
+            "client_request_referer".to_string(),
+            "client_request_bytes".to_string(),
+            "edge_colo_code".to_string(),
+            "edge_response_status".to_string(),
+            "edge_response_bytes".to_string(),
+            "edge_time_to_first_byte_ms".to_string(),
+            "cache_cache_status".to_string(),
+            "origin_ip".to_string(),
+            "origin_response_status".to_string(),
+            "origin_response_time_ms".to_string(),
+            "security_action".to_string(),
+            "bot_score".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref CLOUDFLARE_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::Float,
+            DataType::String,
+            DataType::Integral,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref FASTLY_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "cache_server".to_string(),
+            "service".to_string(),
+            "client_ip".to_string(),
+            "user".to_string(),
+            "request".to_string(),
+            "status".to_string(),
+            "body_size".to_string(),
+            "host".to_string(),
+            "referer".to_string(),
+            "user_agent".to_string(),
+            "cache_state".to_string(),
+            "geo_country".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref FASTLY_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::FormattedDateTime(FASTLY_CLF_TIME_FORMAT.to_string()),
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::HttpRequest,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+        ]
+    };
+}
+
+lazy_static! {
+    //The syslog header of the classic message type, e.g. "<134>2016-07-04T22:37:50Z cache-sjc3120 my-service[240869]: "
+    static ref FASTLY_SYSLOG_PREFIX_REGEX: Regex =
+        Regex::new(r#"^<\d+>\S+ (\S+) ([^\s\[:]+)(?:\[\d+\])?: "#).unwrap();
+    static ref FASTLY_CLF_LINE_REGEX: Regex =
+        Regex::new(r#"^(\S+) \S+ (\S+) \[([^\]]+)\] "((?:[^"\\]|\\.)*)" (\S+) (\S+)$"#).unwrap();
+}
+
+lazy_static! {
+    static ref AZURE_APPGW_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "resource_id".to_string(),
+            "instance_id".to_string(),
+            "client_ip".to_string(),
+            "client_port".to_string(),
+            "http_method".to_string(),
+            "request_uri".to_string(),
+            "request_query".to_string(),
+            "user_agent".to_string(),
+            "http_status".to_string(),
+            "http_version".to_string(),
+            "received_bytes".to_string(),
+            "sent_bytes".to_string(),
+            "time_taken".to_string(),
+            "ssl_enabled".to_string(),
+            "ssl_cipher".to_string(),
+            "ssl_protocol".to_string(),
+            "server_routed".to_string(),
+            "server_status".to_string(),
+            "server_response_latency".to_string(),
+            "host".to_string(),
+            "original_host".to_string(),
+            "transaction_id".to_string(),
+            "error_info".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AZURE_APPGW_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Float,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::Float,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref VPC_FLOW_FIELD_NAMES: Vec<String> = {
+        vec![
+            "version".to_string(),
+            "account_id".to_string(),
+            "interface_id".to_string(),
+            "srcaddr".to_string(),
+            "dstaddr".to_string(),
+            "srcport".to_string(),
+            "dstport".to_string(),
+            "protocol".to_string(),
+            "packets".to_string(),
+            "bytes".to_string(),
+            "start".to_string(),
+            "end".to_string(),
+            "action".to_string(),
+            "log_status".to_string(),
+            "vpc_id".to_string(),
+            "subnet_id".to_string(),
+            "instance_id".to_string(),
+            "tcp_flags".to_string(),
+            "type".to_string(),
+            "pkt_srcaddr".to_string(),
+            "pkt_dstaddr".to_string(),
+            "region".to_string(),
+            "az_id".to_string(),
+            "sublocation_type".to_string(),
+            "sublocation_id".to_string(),
+            "pkt_src_aws_service".to_string(),
+            "pkt_dst_aws_service".to_string(),
+            "flow_direction".to_string(),
+            "traffic_path".to_string(),
+            "timestamp".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref VPC_FLOW_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::DateTime,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_WAF_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "format_version".to_string(),
+            "webacl_id".to_string(),
+            "terminating_rule_id".to_string(),
+            "terminating_rule_type".to_string(),
+            "action".to_string(),
+            "terminating_rule_match_details".to_string(),
+            "http_source_name".to_string(),
+            "http_source_id".to_string(),
+            "rule_group_list".to_string(),
+            "rate_based_rule_list".to_string(),
+            "non_terminating_matching_rules".to_string(),
+            "response_code_sent".to_string(),
+            "client_ip".to_string(),
+            "country".to_string(),
+            "uri".to_string(),
+            "args".to_string(),
+            "http_version".to_string(),
+            "http_method".to_string(),
+            "request_id".to_string(),
+            "host".to_string(),
+            "user_agent".to_string(),
+            "headers".to_string(),
+            "labels".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_WAF_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::Json,
+            DataType::Json,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::Json,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_CLOUDTRAIL_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "event_version".to_string(),
+            "event_source".to_string(),
+            "event_name".to_string(),
+            "aws_region".to_string(),
+            "source_ip_address".to_string(),
+            "user_agent".to_string(),
+            "user_identity_type".to_string(),
+            "user_identity_arn".to_string(),
+            "user_identity_account_id".to_string(),
+            "user_identity_principal_id".to_string(),
+            "user_identity_access_key_id".to_string(),
+            "user_identity_user_name".to_string(),
+            "user_identity_invoked_by".to_string(),
+            "error_code".to_string(),
+            "error_message".to_string(),
+            "request_id".to_string(),
+            "event_id".to_string(),
+            "event_type".to_string(),
+            "event_category".to_string(),
+            "read_only".to_string(),
+            "management_event".to_string(),
+            "recipient_account_id".to_string(),
+            "request_parameters".to_string(),
+            "response_elements".to_string(),
+            "resources".to_string(),
+            "user_identity".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_CLOUDTRAIL_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::Json,
+            DataType::String,
+            DataType::Json,
+            DataType::Json,
+            DataType::Json,
+            DataType::Json,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_ROUTE53_RESOLVER_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "version".to_string(),
+            "account_id".to_string(),
+            "region".to_string(),
+            "vpc_id".to_string(),
+            "query_name".to_string(),
+            "query_type".to_string(),
+            "query_class".to_string(),
+            "rcode".to_string(),
+            "answers".to_string(),
+            "srcaddr".to_string(),
+            "srcport".to_string(),
+            "transport".to_string(),
+            "srcids_instance".to_string(),
+            "srcids_resolver_endpoint".to_string(),
+            "firewall_rule_action".to_string(),
+            "firewall_rule_group_id".to_string(),
+            "firewall_domain_list_id".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_ROUTE53_RESOLVER_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::DateTime,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Json,
+            DataType::String,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref AWS_APIGATEWAY_FIELD_NAMES: Vec<String> = {
+        vec![
+            "timestamp".to_string(),
+            "request_id".to_string(),
+            "extended_request_id".to_string(),
+            "ip".to_string(),
+            "caller".to_string(),
+            "user".to_string(),
+            "user_agent".to_string(),
+            "http_method".to_string(),
+            "resource_path".to_string(),
+            "path".to_string(),
+            "protocol".to_string(),
+            "status".to_string(),
+            "response_length".to_string(),
+            "latency".to_string(),
+            "integration_latency".to_string(),
+            "integration_status".to_string(),
+            "error_message".to_string(),
+            "domain_name".to_string(),
+            "stage".to_string(),
+            "api_id".to_string(),
+        ]
+    };
+}
+
+//The $context.requestTime, in the common log format
+const APIGATEWAY_TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+lazy_static! {
+    static ref AWS_APIGATEWAY_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::FormattedDateTime(APIGATEWAY_TIME_FORMAT.to_string()),
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+            DataType::String,
+        ]
+    };
+}
+
+lazy_static! {
+    static ref CLOUDFRONT_FIELD_NAMES: Vec<String> = {
+        vec![
+            "date".to_string(),
+            "time".to_string(),
+            "edge_location".to_string(),
+            "sc_bytes".to_string(),
+            "client_ip".to_string(),
+            "method".to_string(),
+            "host".to_string(),
+            "uri_stem".to_string(),
+            "status".to_string(),
+            "referrer".to_string(),
+            "user_agent".to_string(),
+            "uri_query".to_string(),
+            "cookie".to_string(),
+            "edge_result_type".to_string(),
+            "edge_request_id".to_string(),
+            "host_header".to_string(),
+            "protocol".to_string(),
+            "cs_bytes".to_string(),
+            "time_taken".to_string(),
+            "forwarded_for".to_string(),
+            "ssl_protocol".to_string(),
+            "ssl_cipher".to_string(),
+            "edge_response_result_type".to_string(),
+            "protocol_version".to_string(),
+            "fle_status".to_string(),
+            "fle_encrypted_fields".to_string(),
+            "client_port".to_string(),
+            "time_to_first_byte".to_string(),
+            "edge_detailed_result_type".to_string(),
+            "content_type".to_string(),
+            "content_len".to_string(),
+            "range_start".to_string(),
+            "range_end".to_string(),
+            "timestamp".to_string(),
+        ]
+    };
+}
+
+lazy_static! {
+    static ref CLOUDFRONT_DATATYPES: Vec<DataType> = {
+        vec![
+            DataType::String,
+            DataType::String,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::Float,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Float,
+            DataType::QuotedString,
+            DataType::QuotedString,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::Integral,
+            DataType::DateTime,
+        ]
+    };
+}
+
+//Reference: https://docs.aws.amazon.com/elasticloadbalancing/latest/classic/access-log-collection.html
+pub(crate) enum ClassicLoadBalancerLogField {
+    Timestamp = 0,
+    Elbname = 1,
+    ClientAndPort = 2,
+    BackendAndPort = 3,
+    RequestProcessingTime = 4,
+    BackendProcessingTime = 5,
+    ResponseProcessingTime = 6,
+    ELBStatusCode = 7,
+    BackendStatusCode = 8,
+    ReceivedBytes = 9,
+    SentBytes = 10,
+    Request = 11,
+    UserAgent = 12,
+    SSLCipher = 13,
+    SSLProtocol = 14,
+    TargetGroupArn = 15,
+    TraceID = 16,
+}
+
+impl fmt::Display for ClassicLoadBalancerLogField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ClassicLoadBalancerLogField::Timestamp => "timestamp",
+            ClassicLoadBalancerLogField::Elbname => "elbname",
+            ClassicLoadBalancerLogField::ClientAndPort => "client_and_port",
+            ClassicLoadBalancerLogField::BackendAndPort => "backend_and_port",
+            ClassicLoadBalancerLogField::RequestProcessingTime => "request_processing_time",
+            ClassicLoadBalancerLogField::BackendProcessingTime => "backend_processing_time",
+            ClassicLoadBalancerLogField::ResponseProcessingTime => "response_processing_time",
+            ClassicLoadBalancerLogField::ELBStatusCode => "elb_status_code",
+            ClassicLoadBalancerLogField::BackendStatusCode => "backend_status_code",
+            ClassicLoadBalancerLogField::ReceivedBytes => "received_bytes",
+            ClassicLoadBalancerLogField::SentBytes => "sent_bytes",
+            ClassicLoadBalancerLogField::Request => "request",
+            ClassicLoadBalancerLogField::UserAgent => "user_agent",
+            ClassicLoadBalancerLogField::SSLCipher => "ssl_cipher",
+            ClassicLoadBalancerLogField::SSLProtocol => "ssl_protocol",
+            ClassicLoadBalancerLogField::TargetGroupArn => "target_group_arn",
+            ClassicLoadBalancerLogField::TraceID => "trace_id",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ClassicLoadBalancerLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(ClassicLoadBalancerLogField::Timestamp),
+            "elbname" => Ok(ClassicLoadBalancerLogField::Elbname),
+            "client_and_port" => Ok(ClassicLoadBalancerLogField::ClientAndPort),
+            "backend_and_port" => Ok(ClassicLoadBalancerLogField::BackendAndPort),
+            "request_processing_time" => Ok(ClassicLoadBalancerLogField::RequestProcessingTime),
+            "backend_processing_time" => Ok(ClassicLoadBalancerLogField::BackendProcessingTime),
+            "response_processing_time" => Ok(ClassicLoadBalancerLogField::ResponseProcessingTime),
+            "elb_status_code" => Ok(ClassicLoadBalancerLogField::ELBStatusCode),
+            "backend_status_code" => Ok(ClassicLoadBalancerLogField::BackendStatusCode),
+            "received_bytes" => Ok(ClassicLoadBalancerLogField::ReceivedBytes),
+            "sent_bytes" => Ok(ClassicLoadBalancerLogField::SentBytes),
+            "request" => Ok(ClassicLoadBalancerLogField::Request),
+            "user_agent" => Ok(ClassicLoadBalancerLogField::UserAgent),
+            "ssl_cipher" => Ok(ClassicLoadBalancerLogField::SSLCipher),
+            "ssl_protocol" => Ok(ClassicLoadBalancerLogField::SSLProtocol),
+            "target_group_arn" => Ok(ClassicLoadBalancerLogField::TargetGroupArn),
+            "trace_id" => Ok(ClassicLoadBalancerLogField::TraceID),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl ClassicLoadBalancerLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_ELB_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_ELB_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_ELB_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes.into_iter()).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/elasticloadbalancing/latest/application/load-balancer-access-logs.html
+pub(crate) enum ApplicationLoadBalancerLogField {
+    Type = 0,
+    Timestamp = 1,
+    Elbname = 2,
+    ClientAndPort = 3,
+    TargetAndPort = 4,
+    RequestProcessingTime = 5,
+    TargetProcessingTime = 6,
+    ResponseProcessingTime = 7,
+    ELBStatusCode = 8,
+    TargetStatusCode = 9,
+    ReceivedBytes = 10,
+    SentBytes = 11,
+    Request = 12,
+    UserAgent = 13,
+    SSLCipher = 14,
+    SSLProtocol = 15,
     TargetGroupArn = 16,
     TraceID = 17,
     DomainName = 18,
@@ -379,631 +1437,5328 @@ pub(crate) enum ApplicationLoadBalancerLogField {
     ActionExecuted = 22,
     RedirectUrl = 23,
     ErrorReason = 24,
+    TargetPortList = 25,
+    TargetStatusCodeList = 26,
+    Classification = 27,
+    ClassificationReason = 28,
+    ConnTraceId = 29,
+    TransformedHost = 30,
+    TransformedUri = 31,
+    RequestTransformStatus = 32,
+}
+
+impl FromStr for ApplicationLoadBalancerLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "type" => Ok(ApplicationLoadBalancerLogField::Type),
+            "timestamp" => Ok(ApplicationLoadBalancerLogField::Timestamp),
+            "elbname" => Ok(ApplicationLoadBalancerLogField::Elbname),
+            "client_and_port" => Ok(ApplicationLoadBalancerLogField::ClientAndPort),
+            "backend_and_port" => Ok(ApplicationLoadBalancerLogField::TargetAndPort),
+            "request_processing_time" => Ok(ApplicationLoadBalancerLogField::RequestProcessingTime),
+            "backend_processing_time" => Ok(ApplicationLoadBalancerLogField::TargetProcessingTime),
+            "response_processing_time" => Ok(ApplicationLoadBalancerLogField::ResponseProcessingTime),
+            "elb_status_code" => Ok(ApplicationLoadBalancerLogField::ELBStatusCode),
+            "backend_status_code" => Ok(ApplicationLoadBalancerLogField::TargetStatusCode),
+            "received_bytes" => Ok(ApplicationLoadBalancerLogField::ReceivedBytes),
+            "sent_bytes" => Ok(ApplicationLoadBalancerLogField::SentBytes),
+            "request" => Ok(ApplicationLoadBalancerLogField::Request),
+            "user_agent" => Ok(ApplicationLoadBalancerLogField::UserAgent),
+            "ssl_cipher" => Ok(ApplicationLoadBalancerLogField::SSLCipher),
+            "ssl_protocol" => Ok(ApplicationLoadBalancerLogField::SSLProtocol),
+            "target_group_arn" => Ok(ApplicationLoadBalancerLogField::TargetGroupArn),
+            "trace_id" => Ok(ApplicationLoadBalancerLogField::TraceID),
+            "domain_name" => Ok(ApplicationLoadBalancerLogField::DomainName),
+            "chosen_cert_arn" => Ok(ApplicationLoadBalancerLogField::ChosenCertArn),
+            "matched_rule_priority" => Ok(ApplicationLoadBalancerLogField::MatchedRulePriority),
+            "request_creation_time" => Ok(ApplicationLoadBalancerLogField::RequestCreationTime),
+            "action_executed" => Ok(ApplicationLoadBalancerLogField::ActionExecuted),
+            "redirect_url" => Ok(ApplicationLoadBalancerLogField::RedirectUrl),
+            "error_reason" => Ok(ApplicationLoadBalancerLogField::ErrorReason),
+            "target_port_list" => Ok(ApplicationLoadBalancerLogField::TargetPortList),
+            "target_status_code_list" => Ok(ApplicationLoadBalancerLogField::TargetStatusCodeList),
+            "classification" => Ok(ApplicationLoadBalancerLogField::Classification),
+            "classification_reason" => Ok(ApplicationLoadBalancerLogField::ClassificationReason),
+            "conn_trace_id" => Ok(ApplicationLoadBalancerLogField::ConnTraceId),
+            "transformed_host" => Ok(ApplicationLoadBalancerLogField::TransformedHost),
+            "transformed_uri" => Ok(ApplicationLoadBalancerLogField::TransformedUri),
+            "request_transform_status" => Ok(ApplicationLoadBalancerLogField::RequestTransformStatus),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl ApplicationLoadBalancerLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_ALB_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_ALB_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_ALB_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes.into_iter()).collect()
+    }
+}
+
+// https://docs.aws.amazon.com/AmazonS3/latest/dev/LogFormat.html
+pub(crate) enum S3Field {
+    BucketOwner = 0,
+    Bucket = 1,
+    Time = 2,
+    RemoteIp = 3,
+    Requester = 4,
+    RequestId = 5,
+    Operation = 6,
+    Key = 7,
+    RequestUri = 8,
+    HttpStatus = 9,
+    ErrorCode = 10,
+    BytesSent = 11,
+    ObjectSize = 12,
+    TotalTime = 13,
+    TurnAroundTime = 14,
+    Referrer = 15,
+    UserAgent = 16,
+    VersionId = 17,
+    HostId = 18,
+    SignatureVersion = 19,
+    CipherSuite = 20,
+    AuthenticationType = 21,
+    HostHeader = 22,
+    TlsVersion = 23,
+    AccessPointArn = 24,
+    AclRequired = 25,
+}
+
+impl FromStr for S3Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "bucket_owner" => Ok(S3Field::BucketOwner),
+            "bucket" => Ok(S3Field::Bucket),
+            "time" => Ok(S3Field::Time),
+            "remote_ip" => Ok(S3Field::RemoteIp),
+            "requester" => Ok(S3Field::Requester),
+            "request_id" => Ok(S3Field::RequestId),
+            "operation" => Ok(S3Field::Operation),
+            "key" => Ok(S3Field::Key),
+            "request_uri" => Ok(S3Field::RequestUri),
+            "http_status" => Ok(S3Field::HttpStatus),
+            "error_code" => Ok(S3Field::ErrorCode),
+            "bytes_sent" => Ok(S3Field::BytesSent),
+            "object_size" => Ok(S3Field::ObjectSize),
+            "total_time" => Ok(S3Field::TotalTime),
+            "turn_around_time" => Ok(S3Field::TurnAroundTime),
+            "refererr" => Ok(S3Field::Referrer),
+            "user_agent" => Ok(S3Field::UserAgent),
+            "version_id" => Ok(S3Field::VersionId),
+            "host_id" => Ok(S3Field::HostId),
+            "signature_version" => Ok(S3Field::SignatureVersion),
+            "cipher_suite" => Ok(S3Field::CipherSuite),
+            "authentication_type" => Ok(S3Field::AuthenticationType),
+            "host_header" => Ok(S3Field::HostHeader),
+            "tls_version" => Ok(S3Field::TlsVersion),
+            "access_point_arn" => Ok(S3Field::AccessPointArn),
+            "acl_required" => Ok(S3Field::AclRequired),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl S3Field {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_S3_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_S3_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_S3_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes.into_iter()).collect()
+    }
+}
+
+//Reference: https://wiki.squid-cache.org/Features/LogFormat
+pub(crate) enum SquidLogField {
+    Timestamp = 0,
+    Elapsed = 1,
+    RemoteHost = 2,
+    CodeAndStatus = 3,
+    Bytes = 4,
+    Method = 5,
+    Url = 6,
+    Rfc931 = 7,
+    PeerstatusAndPeerhost = 8,
+    Type = 9,
+}
+
+impl FromStr for SquidLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(SquidLogField::Timestamp),
+            "elapsed" => Ok(SquidLogField::Elapsed),
+            "remote_host" => Ok(SquidLogField::RemoteHost),
+            "code_and_status" => Ok(SquidLogField::CodeAndStatus),
+            "bytes" => Ok(SquidLogField::Bytes),
+            "method" => Ok(SquidLogField::Method),
+            "url" => Ok(SquidLogField::Url),
+            "rfc931" => Ok(SquidLogField::Rfc931),
+            "peer_status_and_peer_host" => Ok(SquidLogField::PeerstatusAndPeerhost),
+            "type" => Ok(SquidLogField::Type),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl SquidLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &SQUID_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        SQUID_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        SQUID_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes.into_iter()).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/elasticloadbalancing/latest/network/load-balancer-access-logs.html
+//The access logs are only written for the TLS listeners
+pub(crate) enum NetworkLoadBalancerLogField {
+    Type = 0,
+    Version = 1,
+    Timestamp = 2,
+    Elb = 3,
+    Listener = 4,
+    ClientAndPort = 5,
+    DestinationAndPort = 6,
+    ConnectionTime = 7,
+    TlsHandshakeTime = 8,
+    ReceivedBytes = 9,
+    SentBytes = 10,
+    IncomingTlsAlert = 11,
+    ChosenCertArn = 12,
+    ChosenCertSerial = 13,
+    TlsCipher = 14,
+    TlsProtocolVersion = 15,
+    TlsNamedGroup = 16,
+    DomainName = 17,
+    AlpnFeProtocol = 18,
+    AlpnBeProtocol = 19,
+    AlpnClientPreferenceList = 20,
+    TlsConnectionCreationTime = 21,
+}
+
+impl FromStr for NetworkLoadBalancerLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "type" => Ok(NetworkLoadBalancerLogField::Type),
+            "version" => Ok(NetworkLoadBalancerLogField::Version),
+            "timestamp" => Ok(NetworkLoadBalancerLogField::Timestamp),
+            "elb" => Ok(NetworkLoadBalancerLogField::Elb),
+            "listener" => Ok(NetworkLoadBalancerLogField::Listener),
+            "client_and_port" => Ok(NetworkLoadBalancerLogField::ClientAndPort),
+            "destination_and_port" => Ok(NetworkLoadBalancerLogField::DestinationAndPort),
+            "connection_time" => Ok(NetworkLoadBalancerLogField::ConnectionTime),
+            "tls_handshake_time" => Ok(NetworkLoadBalancerLogField::TlsHandshakeTime),
+            "received_bytes" => Ok(NetworkLoadBalancerLogField::ReceivedBytes),
+            "sent_bytes" => Ok(NetworkLoadBalancerLogField::SentBytes),
+            "incoming_tls_alert" => Ok(NetworkLoadBalancerLogField::IncomingTlsAlert),
+            "chosen_cert_arn" => Ok(NetworkLoadBalancerLogField::ChosenCertArn),
+            "chosen_cert_serial" => Ok(NetworkLoadBalancerLogField::ChosenCertSerial),
+            "tls_cipher" => Ok(NetworkLoadBalancerLogField::TlsCipher),
+            "tls_protocol_version" => Ok(NetworkLoadBalancerLogField::TlsProtocolVersion),
+            "tls_named_group" => Ok(NetworkLoadBalancerLogField::TlsNamedGroup),
+            "domain_name" => Ok(NetworkLoadBalancerLogField::DomainName),
+            "alpn_fe_protocol" => Ok(NetworkLoadBalancerLogField::AlpnFeProtocol),
+            "alpn_be_protocol" => Ok(NetworkLoadBalancerLogField::AlpnBeProtocol),
+            "alpn_client_preference_list" => Ok(NetworkLoadBalancerLogField::AlpnClientPreferenceList),
+            "tls_connection_creation_time" => Ok(NetworkLoadBalancerLogField::TlsConnectionCreationTime),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl NetworkLoadBalancerLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_NLB_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_NLB_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_NLB_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/elasticloadbalancing/latest/application/load-balancer-connection-logs.html
+//One line per TLS connection to the load balancer, including the ones failing the handshake or the mTLS verification
+pub(crate) enum ApplicationLoadBalancerConnectionLogField {
+    Timestamp = 0,
+    ClientIp = 1,
+    ClientPort = 2,
+    ListenerPort = 3,
+    TlsProtocol = 4,
+    TlsCipher = 5,
+    TlsHandshakeLatency = 6,
+    LeafClientCertSubject = 7,
+    LeafClientCertValidity = 8,
+    LeafClientCertSerialNumber = 9,
+    TlsVerifyStatus = 10,
+    ConnTraceId = 11,
+}
+
+impl FromStr for ApplicationLoadBalancerConnectionLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(ApplicationLoadBalancerConnectionLogField::Timestamp),
+            "client_ip" => Ok(ApplicationLoadBalancerConnectionLogField::ClientIp),
+            "client_port" => Ok(ApplicationLoadBalancerConnectionLogField::ClientPort),
+            "listener_port" => Ok(ApplicationLoadBalancerConnectionLogField::ListenerPort),
+            "tls_protocol" => Ok(ApplicationLoadBalancerConnectionLogField::TlsProtocol),
+            "tls_cipher" => Ok(ApplicationLoadBalancerConnectionLogField::TlsCipher),
+            "tls_handshake_latency" => Ok(ApplicationLoadBalancerConnectionLogField::TlsHandshakeLatency),
+            "leaf_client_cert_subject" => Ok(ApplicationLoadBalancerConnectionLogField::LeafClientCertSubject),
+            "leaf_client_cert_validity" => Ok(ApplicationLoadBalancerConnectionLogField::LeafClientCertValidity),
+            "leaf_client_cert_serial_number" => {
+                Ok(ApplicationLoadBalancerConnectionLogField::LeafClientCertSerialNumber)
+            }
+            "tls_verify_status" => Ok(ApplicationLoadBalancerConnectionLogField::TlsVerifyStatus),
+            "conn_trace_id" => Ok(ApplicationLoadBalancerConnectionLogField::ConnTraceId),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl ApplicationLoadBalancerConnectionLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_ALB_CONN_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_ALB_CONN_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_ALB_CONN_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.haproxy.org/2.8/configuration.html#8.2.3
+//The HTTP log format of "option httplog". The timers are in milliseconds and -1 when the phase is not reached, the
+//captured headers are the first and the second braces in the line.
+const HAPROXY_TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S%.f";
+
+pub(crate) enum HaproxyHttpLogField {
+    ClientIp = 0,
+    ClientPort = 1,
+    Timestamp = 2,
+    FrontendName = 3,
+    BackendName = 4,
+    ServerName = 5,
+    Tq = 6,
+    Tw = 7,
+    Tc = 8,
+    Tr = 9,
+    Tt = 10,
+    StatusCode = 11,
+    BytesRead = 12,
+    CapturedRequestCookie = 13,
+    CapturedResponseCookie = 14,
+    TerminationState = 15,
+    Actconn = 16,
+    Feconn = 17,
+    Beconn = 18,
+    SrvConn = 19,
+    Retries = 20,
+    SrvQueue = 21,
+    BackendQueue = 22,
+    CapturedRequestHeaders = 23,
+    CapturedResponseHeaders = 24,
+    Request = 25,
+}
+
+impl FromStr for HaproxyHttpLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "client_ip" => Ok(HaproxyHttpLogField::ClientIp),
+            "client_port" => Ok(HaproxyHttpLogField::ClientPort),
+            "timestamp" => Ok(HaproxyHttpLogField::Timestamp),
+            "frontend_name" => Ok(HaproxyHttpLogField::FrontendName),
+            "backend_name" => Ok(HaproxyHttpLogField::BackendName),
+            "server_name" => Ok(HaproxyHttpLogField::ServerName),
+            "tq" => Ok(HaproxyHttpLogField::Tq),
+            "tw" => Ok(HaproxyHttpLogField::Tw),
+            "tc" => Ok(HaproxyHttpLogField::Tc),
+            "tr" => Ok(HaproxyHttpLogField::Tr),
+            "tt" => Ok(HaproxyHttpLogField::Tt),
+            "status_code" => Ok(HaproxyHttpLogField::StatusCode),
+            "bytes_read" => Ok(HaproxyHttpLogField::BytesRead),
+            "captured_request_cookie" => Ok(HaproxyHttpLogField::CapturedRequestCookie),
+            "captured_response_cookie" => Ok(HaproxyHttpLogField::CapturedResponseCookie),
+            "termination_state" => Ok(HaproxyHttpLogField::TerminationState),
+            "actconn" => Ok(HaproxyHttpLogField::Actconn),
+            "feconn" => Ok(HaproxyHttpLogField::Feconn),
+            "beconn" => Ok(HaproxyHttpLogField::Beconn),
+            "srv_conn" => Ok(HaproxyHttpLogField::SrvConn),
+            "retries" => Ok(HaproxyHttpLogField::Retries),
+            "srv_queue" => Ok(HaproxyHttpLogField::SrvQueue),
+            "backend_queue" => Ok(HaproxyHttpLogField::BackendQueue),
+            "captured_request_headers" => Ok(HaproxyHttpLogField::CapturedRequestHeaders),
+            "captured_response_headers" => Ok(HaproxyHttpLogField::CapturedResponseHeaders),
+            "request" => Ok(HaproxyHttpLogField::Request),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl HaproxyHttpLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &HAPROXY_HTTP_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        HAPROXY_HTTP_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        HAPROXY_HTTP_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://datatracker.ietf.org/doc/html/rfc5424 and https://datatracker.ietf.org/doc/html/rfc3164
+//The lines of either RFC, e.g. the LB logs shipped through rsyslog. The facility and the severity are split from the
+//priority, the tag of RFC3164 is the app name with the pid in the brackets as the procid, and the structured data is
+//the object of the params by the SD-ID. The RFC3164 timestamp has no year, it is the one of the last 12 months.
+pub(crate) enum SyslogField {
+    Priority = 0,
+    Facility = 1,
+    Severity = 2,
+    Version = 3,
+    Timestamp = 4,
+    Hostname = 5,
+    AppName = 6,
+    Procid = 7,
+    Msgid = 8,
+    StructuredData = 9,
+    Message = 10,
+}
+
+impl FromStr for SyslogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "priority" => Ok(SyslogField::Priority),
+            "facility" => Ok(SyslogField::Facility),
+            "severity" => Ok(SyslogField::Severity),
+            "version" => Ok(SyslogField::Version),
+            "timestamp" => Ok(SyslogField::Timestamp),
+            "hostname" => Ok(SyslogField::Hostname),
+            "app_name" => Ok(SyslogField::AppName),
+            "procid" => Ok(SyslogField::Procid),
+            "msgid" => Ok(SyslogField::Msgid),
+            "structured_data" => Ok(SyslogField::StructuredData),
+            "message" => Ok(SyslogField::Message),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl SyslogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &SYSLOG_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        SYSLOG_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        SYSLOG_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://doc.traefik.io/traefik/observability/access-logs/
+//The access logs in either the common log format extended by Traefik or the JSON format. The JSON lines have more
+//fields, the ones missing from the common log format lines are null. The durations are in milliseconds.
+const TRAEFIK_CLF_TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+pub(crate) enum TraefikLogField {
+    ClientHost = 0,
+    ClientPort = 1,
+    ClientUsername = 2,
+    Timestamp = 3,
+    Request = 4,
+    RequestHost = 5,
+    RequestScheme = 6,
+    DownstreamStatus = 7,
+    DownstreamContentSize = 8,
+    Referer = 9,
+    UserAgent = 10,
+    RequestCount = 11,
+    RouterName = 12,
+    ServiceName = 13,
+    ServiceUrl = 14,
+    OriginStatus = 15,
+    OriginContentSize = 16,
+    DurationMs = 17,
+    OriginDurationMs = 18,
+    RetryAttempts = 19,
+    EntryPointName = 20,
+}
+
+//The column of each capture group of the common log format lines
+const TRAEFIK_CLF_COLUMNS: [usize; 12] = [
+    TraefikLogField::ClientHost as usize,
+    TraefikLogField::ClientUsername as usize,
+    TraefikLogField::Timestamp as usize,
+    TraefikLogField::Request as usize,
+    TraefikLogField::DownstreamStatus as usize,
+    TraefikLogField::DownstreamContentSize as usize,
+    TraefikLogField::Referer as usize,
+    TraefikLogField::UserAgent as usize,
+    TraefikLogField::RequestCount as usize,
+    TraefikLogField::RouterName as usize,
+    TraefikLogField::ServiceUrl as usize,
+    TraefikLogField::DurationMs as usize,
+];
+
+//The key of each column in the JSON object, in the order of TraefikLogField. The timestamp, the request and the
+//durations in nanoseconds are converted separately, the headers are only there if kept in the configuration.
+const TRAEFIK_JSON_PATHS: [&[&str]; 21] = [
+    &["ClientHost"],
+    &["ClientPort"],
+    &["ClientUsername"],
+    &[],
+    &[],
+    &["RequestHost"],
+    &["RequestScheme"],
+    &["DownstreamStatus"],
+    &["DownstreamContentSize"],
+    &["request_Referer"],
+    &["request_User-Agent"],
+    &["RequestCount"],
+    &["RouterName"],
+    &["ServiceName"],
+    &["ServiceURL"],
+    &["OriginStatus"],
+    &["OriginContentSize"],
+    &[],
+    &[],
+    &["RetryAttempts"],
+    &["entryPointName"],
+];
+
+impl FromStr for TraefikLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "client_host" => Ok(TraefikLogField::ClientHost),
+            "client_port" => Ok(TraefikLogField::ClientPort),
+            "client_username" => Ok(TraefikLogField::ClientUsername),
+            "timestamp" => Ok(TraefikLogField::Timestamp),
+            "request" => Ok(TraefikLogField::Request),
+            "request_host" => Ok(TraefikLogField::RequestHost),
+            "request_scheme" => Ok(TraefikLogField::RequestScheme),
+            "downstream_status" => Ok(TraefikLogField::DownstreamStatus),
+            "downstream_content_size" => Ok(TraefikLogField::DownstreamContentSize),
+            "referer" => Ok(TraefikLogField::Referer),
+            "user_agent" => Ok(TraefikLogField::UserAgent),
+            "request_count" => Ok(TraefikLogField::RequestCount),
+            "router_name" => Ok(TraefikLogField::RouterName),
+            "service_name" => Ok(TraefikLogField::ServiceName),
+            "service_url" => Ok(TraefikLogField::ServiceUrl),
+            "origin_status" => Ok(TraefikLogField::OriginStatus),
+            "origin_content_size" => Ok(TraefikLogField::OriginContentSize),
+            "duration_ms" => Ok(TraefikLogField::DurationMs),
+            "origin_duration_ms" => Ok(TraefikLogField::OriginDurationMs),
+            "retry_attempts" => Ok(TraefikLogField::RetryAttempts),
+            "entry_point_name" => Ok(TraefikLogField::EntryPointName),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl TraefikLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &TRAEFIK_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        TRAEFIK_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        TRAEFIK_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://caddyserver.com/docs/caddyfile/directives/log
+//The structured access logs of the http.log.access logger, one JSON object per line. The request is the nested object
+//of remote_ip, method, host, uri, headers and so on, and the duration is in seconds. The other keys are kept as they are.
+pub(crate) enum CaddyLogField {
+    Timestamp = 0,
+    Level = 1,
+    Logger = 2,
+    Msg = 3,
+    Request = 4,
+    BytesRead = 5,
+    UserId = 6,
+    Duration = 7,
+    Size = 8,
+    Status = 9,
+    RespHeaders = 10,
+}
+
+impl FromStr for CaddyLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(CaddyLogField::Timestamp),
+            "level" => Ok(CaddyLogField::Level),
+            "logger" => Ok(CaddyLogField::Logger),
+            "msg" => Ok(CaddyLogField::Msg),
+            "request" => Ok(CaddyLogField::Request),
+            "bytes_read" => Ok(CaddyLogField::BytesRead),
+            "user_id" => Ok(CaddyLogField::UserId),
+            "duration" => Ok(CaddyLogField::Duration),
+            "size" => Ok(CaddyLogField::Size),
+            "status" => Ok(CaddyLogField::Status),
+            "resp_headers" => Ok(CaddyLogField::RespHeaders),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl CaddyLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &CADDY_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        CADDY_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        CADDY_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://developers.cloudflare.com/logs/reference/log-fields/zone/http_requests/
+//The HTTP requests dataset of Logpush, one JSON object per line with the fields chosen in the job. The timestamp is the
+//EdgeStartTimestamp in any of the timestamp formats of Logpush, and the origin response time is in milliseconds.
+pub(crate) enum CloudflareField {
+    Timestamp = 0,
+    RayId = 1,
+    ZoneName = 2,
+    ClientIp = 3,
+    ClientCountry = 4,
+    ClientRequestHost = 5,
+    ClientRequestMethod = 6,
+    ClientRequestUri = 7,
+    ClientRequestProtocol = 8,
+    ClientRequestUserAgent = 9,
+    ClientRequestReferer = 10,
+    ClientRequestBytes = 11,
+    EdgeColoCode = 12,
+    EdgeResponseStatus = 13,
+    EdgeResponseBytes = 14,
+    EdgeTimeToFirstByteMs = 15,
+    CacheCacheStatus = 16,
+    OriginIp = 17,
+    OriginResponseStatus = 18,
+    OriginResponseTimeMs = 19,
+    SecurityAction = 20,
+    BotScore = 21,
+}
+
+//The key of each column in the JSON object, in the order of CloudflareField, the ones without a key are left to the reader
+const CLOUDFLARE_JSON_PATHS: [&[&str]; 22] = [
+    &[],
+    &["RayID"],
+    &["ZoneName"],
+    &["ClientIP"],
+    &["ClientCountry"],
+    &["ClientRequestHost"],
+    &["ClientRequestMethod"],
+    &["ClientRequestURI"],
+    &["ClientRequestProtocol"],
+    &["ClientRequestUserAgent"],
+    &["ClientRequestReferer"],
+    &["ClientRequestBytes"],
+    &["EdgeColoCode"],
+    &["EdgeResponseStatus"],
+    &["EdgeResponseBytes"],
+    &["EdgeTimeToFirstByteMs"],
+    &["CacheCacheStatus"],
+    &["OriginIP"],
+    &["OriginResponseStatus"],
+    &[],
+    &["SecurityAction"],
+    &["BotScore"],
+];
+
+impl FromStr for CloudflareField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(CloudflareField::Timestamp),
+            "ray_id" => Ok(CloudflareField::RayId),
+            "zone_name" => Ok(CloudflareField::ZoneName),
+            "client_ip" => Ok(CloudflareField::ClientIp),
+            "client_country" => Ok(CloudflareField::ClientCountry),
+            "client_request_host" => Ok(CloudflareField::ClientRequestHost),
+            "client_request_method" => Ok(CloudflareField::ClientRequestMethod),
+            "client_request_uri" => Ok(CloudflareField::ClientRequestUri),
+            "client_request_protocol" => Ok(CloudflareField::ClientRequestProtocol),
+            "client_request_user_agent" => Ok(CloudflareField::ClientRequestUserAgent),
+            "client_request_referer" => Ok(CloudflareField::ClientRequestReferer),
+            "client_request_bytes" => Ok(CloudflareField::ClientRequestBytes),
+            "edge_colo_code" => Ok(CloudflareField::EdgeColoCode),
+            "edge_response_status" => Ok(CloudflareField::EdgeResponseStatus),
+            "edge_response_bytes" => Ok(CloudflareField::EdgeResponseBytes),
+            "edge_time_to_first_byte_ms" => Ok(CloudflareField::EdgeTimeToFirstByteMs),
+            "cache_cache_status" => Ok(CloudflareField::CacheCacheStatus),
+            "origin_ip" => Ok(CloudflareField::OriginIp),
+            "origin_response_status" => Ok(CloudflareField::OriginResponseStatus),
+            "origin_response_time_ms" => Ok(CloudflareField::OriginResponseTimeMs),
+            "security_action" => Ok(CloudflareField::SecurityAction),
+            "bot_score" => Ok(CloudflareField::BotScore),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl CloudflareField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &CLOUDFLARE_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        CLOUDFLARE_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        CLOUDFLARE_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.fastly.com/en/guides/changing-log-line-formats
+//The logs in the default format, the common log format after the syslog header, or in the JSON format recommended
+//by Fastly, with or without the syslog header. The columns missing from the common log format lines are null.
+const FASTLY_CLF_TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+pub(crate) enum FastlyLogField {
+    Timestamp = 0,
+    CacheServer = 1,
+    Service = 2,
+    ClientIp = 3,
+    User = 4,
+    Request = 5,
+    Status = 6,
+    BodySize = 7,
+    Host = 8,
+    Referer = 9,
+    UserAgent = 10,
+    CacheState = 11,
+    GeoCountry = 12,
+}
+
+//The column of each capture group of the common log format lines
+const FASTLY_CLF_COLUMNS: [usize; 6] = [
+    FastlyLogField::ClientIp as usize,
+    FastlyLogField::User as usize,
+    FastlyLogField::Timestamp as usize,
+    FastlyLogField::Request as usize,
+    FastlyLogField::Status as usize,
+    FastlyLogField::BodySize as usize,
+];
+
+//The key of each column in the JSON object, in the order of FastlyLogField. The timestamp and the request are
+//converted separately.
+const FASTLY_JSON_PATHS: [&[&str]; 13] = [
+    &[],
+    &["fastly_server"],
+    &["service_id"],
+    &["client_ip"],
+    &[],
+    &[],
+    &["response_status"],
+    &["response_body_size"],
+    &["host"],
+    &["request_referer"],
+    &["request_user_agent"],
+    &["response_state"],
+    &["geo_country"],
+];
+
+impl FromStr for FastlyLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(FastlyLogField::Timestamp),
+            "cache_server" => Ok(FastlyLogField::CacheServer),
+            "service" => Ok(FastlyLogField::Service),
+            "client_ip" => Ok(FastlyLogField::ClientIp),
+            "user" => Ok(FastlyLogField::User),
+            "request" => Ok(FastlyLogField::Request),
+            "status" => Ok(FastlyLogField::Status),
+            "body_size" => Ok(FastlyLogField::BodySize),
+            "host" => Ok(FastlyLogField::Host),
+            "referer" => Ok(FastlyLogField::Referer),
+            "user_agent" => Ok(FastlyLogField::UserAgent),
+            "cache_state" => Ok(FastlyLogField::CacheState),
+            "geo_country" => Ok(FastlyLogField::GeoCountry),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl FastlyLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &FASTLY_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        FASTLY_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        FASTLY_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://learn.microsoft.com/en-us/azure/application-gateway/monitor-application-gateway-reference
+//The access logs of the diagnostic settings, one JSON record per line or the records array of a document per line,
+//with the fields of the request nested in properties. The timeTaken is in milliseconds for the v1 SKU and in seconds
+//for the v2 SKU, as written in the log.
+pub(crate) enum AzureAppGatewayField {
+    Timestamp = 0,
+    ResourceId = 1,
+    InstanceId = 2,
+    ClientIp = 3,
+    ClientPort = 4,
+    HttpMethod = 5,
+    RequestUri = 6,
+    RequestQuery = 7,
+    UserAgent = 8,
+    HttpStatus = 9,
+    HttpVersion = 10,
+    ReceivedBytes = 11,
+    SentBytes = 12,
+    TimeTaken = 13,
+    SslEnabled = 14,
+    SslCipher = 15,
+    SslProtocol = 16,
+    ServerRouted = 17,
+    ServerStatus = 18,
+    ServerResponseLatency = 19,
+    Host = 20,
+    OriginalHost = 21,
+    TransactionId = 22,
+    ErrorInfo = 23,
+}
+
+//The path of each column in the JSON record, in the order of AzureAppGatewayField
+const AZURE_APPGW_JSON_PATHS: [&[&str]; 24] = [
+    &["time"],
+    &["resourceId"],
+    &["properties", "instanceId"],
+    &["properties", "clientIP"],
+    &["properties", "clientPort"],
+    &["properties", "httpMethod"],
+    &["properties", "requestUri"],
+    &["properties", "requestQuery"],
+    &["properties", "userAgent"],
+    &["properties", "httpStatus"],
+    &["properties", "httpVersion"],
+    &["properties", "receivedBytes"],
+    &["properties", "sentBytes"],
+    &["properties", "timeTaken"],
+    &["properties", "sslEnabled"],
+    &["properties", "sslCipher"],
+    &["properties", "sslProtocol"],
+    &["properties", "serverRouted"],
+    &["properties", "serverStatus"],
+    &["properties", "serverResponseLatency"],
+    &["properties", "host"],
+    &["properties", "originalHost"],
+    &["properties", "transactionId"],
+    &["properties", "error_info"],
+];
+
+impl FromStr for AzureAppGatewayField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(AzureAppGatewayField::Timestamp),
+            "resource_id" => Ok(AzureAppGatewayField::ResourceId),
+            "instance_id" => Ok(AzureAppGatewayField::InstanceId),
+            "client_ip" => Ok(AzureAppGatewayField::ClientIp),
+            "client_port" => Ok(AzureAppGatewayField::ClientPort),
+            "http_method" => Ok(AzureAppGatewayField::HttpMethod),
+            "request_uri" => Ok(AzureAppGatewayField::RequestUri),
+            "request_query" => Ok(AzureAppGatewayField::RequestQuery),
+            "user_agent" => Ok(AzureAppGatewayField::UserAgent),
+            "http_status" => Ok(AzureAppGatewayField::HttpStatus),
+            "http_version" => Ok(AzureAppGatewayField::HttpVersion),
+            "received_bytes" => Ok(AzureAppGatewayField::ReceivedBytes),
+            "sent_bytes" => Ok(AzureAppGatewayField::SentBytes),
+            "time_taken" => Ok(AzureAppGatewayField::TimeTaken),
+            "ssl_enabled" => Ok(AzureAppGatewayField::SslEnabled),
+            "ssl_cipher" => Ok(AzureAppGatewayField::SslCipher),
+            "ssl_protocol" => Ok(AzureAppGatewayField::SslProtocol),
+            "server_routed" => Ok(AzureAppGatewayField::ServerRouted),
+            "server_status" => Ok(AzureAppGatewayField::ServerStatus),
+            "server_response_latency" => Ok(AzureAppGatewayField::ServerResponseLatency),
+            "host" => Ok(AzureAppGatewayField::Host),
+            "original_host" => Ok(AzureAppGatewayField::OriginalHost),
+            "transaction_id" => Ok(AzureAppGatewayField::TransactionId),
+            "error_info" => Ok(AzureAppGatewayField::ErrorInfo),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl AzureAppGatewayField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AZURE_APPGW_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AZURE_APPGW_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AZURE_APPGW_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/vpc/latest/userguide/flow-logs.html#flow-logs-fields
+//The fields up to log_status are the default format, the timestamp is the start of the aggregation interval
+pub(crate) enum VpcFlowLogField {
+    Version = 0,
+    AccountId = 1,
+    InterfaceId = 2,
+    Srcaddr = 3,
+    Dstaddr = 4,
+    Srcport = 5,
+    Dstport = 6,
+    Protocol = 7,
+    Packets = 8,
+    Bytes = 9,
+    Start = 10,
+    End = 11,
+    Action = 12,
+    LogStatus = 13,
+    VpcId = 14,
+    SubnetId = 15,
+    InstanceId = 16,
+    TcpFlags = 17,
+    Type = 18,
+    PktSrcaddr = 19,
+    PktDstaddr = 20,
+    Region = 21,
+    AzId = 22,
+    SublocationType = 23,
+    SublocationId = 24,
+    PktSrcAwsService = 25,
+    PktDstAwsService = 26,
+    FlowDirection = 27,
+    TrafficPath = 28,
+    Timestamp = 29,
+}
+
+//The names in the header line of the log file, in the order of VpcFlowLogField
+const VPC_FLOW_HEADER_NAMES: [&str; 29] = [
+    "version",
+    "account-id",
+    "interface-id",
+    "srcaddr",
+    "dstaddr",
+    "srcport",
+    "dstport",
+    "protocol",
+    "packets",
+    "bytes",
+    "start",
+    "end",
+    "action",
+    "log-status",
+    "vpc-id",
+    "subnet-id",
+    "instance-id",
+    "tcp-flags",
+    "type",
+    "pkt-srcaddr",
+    "pkt-dstaddr",
+    "region",
+    "az-id",
+    "sublocation-type",
+    "sublocation-id",
+    "pkt-src-aws-service",
+    "pkt-dst-aws-service",
+    "flow-direction",
+    "traffic-path",
+];
+
+impl FromStr for VpcFlowLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "version" => Ok(VpcFlowLogField::Version),
+            "account_id" => Ok(VpcFlowLogField::AccountId),
+            "interface_id" => Ok(VpcFlowLogField::InterfaceId),
+            "srcaddr" => Ok(VpcFlowLogField::Srcaddr),
+            "dstaddr" => Ok(VpcFlowLogField::Dstaddr),
+            "srcport" => Ok(VpcFlowLogField::Srcport),
+            "dstport" => Ok(VpcFlowLogField::Dstport),
+            "protocol" => Ok(VpcFlowLogField::Protocol),
+            "packets" => Ok(VpcFlowLogField::Packets),
+            "bytes" => Ok(VpcFlowLogField::Bytes),
+            "start" => Ok(VpcFlowLogField::Start),
+            "end" => Ok(VpcFlowLogField::End),
+            "action" => Ok(VpcFlowLogField::Action),
+            "log_status" => Ok(VpcFlowLogField::LogStatus),
+            "vpc_id" => Ok(VpcFlowLogField::VpcId),
+            "subnet_id" => Ok(VpcFlowLogField::SubnetId),
+            "instance_id" => Ok(VpcFlowLogField::InstanceId),
+            "tcp_flags" => Ok(VpcFlowLogField::TcpFlags),
+            "type" => Ok(VpcFlowLogField::Type),
+            "pkt_srcaddr" => Ok(VpcFlowLogField::PktSrcaddr),
+            "pkt_dstaddr" => Ok(VpcFlowLogField::PktDstaddr),
+            "region" => Ok(VpcFlowLogField::Region),
+            "az_id" => Ok(VpcFlowLogField::AzId),
+            "sublocation_type" => Ok(VpcFlowLogField::SublocationType),
+            "sublocation_id" => Ok(VpcFlowLogField::SublocationId),
+            "pkt_src_aws_service" => Ok(VpcFlowLogField::PktSrcAwsService),
+            "pkt_dst_aws_service" => Ok(VpcFlowLogField::PktDstAwsService),
+            "flow_direction" => Ok(VpcFlowLogField::FlowDirection),
+            "traffic_path" => Ok(VpcFlowLogField::TrafficPath),
+            "timestamp" => Ok(VpcFlowLogField::Timestamp),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl VpcFlowLogField {
+    pub(crate) fn len() -> usize {
+        30
+    }
+
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &VPC_FLOW_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        VPC_FLOW_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        VPC_FLOW_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/waf/latest/developerguide/logging-fields.html
+//The JSON object of a line is flattened into the columns, the host and the user agent are taken from the headers
+pub(crate) enum WafLogField {
+    Timestamp = 0,
+    FormatVersion = 1,
+    WebaclId = 2,
+    TerminatingRuleId = 3,
+    TerminatingRuleType = 4,
+    Action = 5,
+    TerminatingRuleMatchDetails = 6,
+    HttpSourceName = 7,
+    HttpSourceId = 8,
+    RuleGroupList = 9,
+    RateBasedRuleList = 10,
+    NonTerminatingMatchingRules = 11,
+    ResponseCodeSent = 12,
+    ClientIp = 13,
+    Country = 14,
+    Uri = 15,
+    Args = 16,
+    HttpVersion = 17,
+    HttpMethod = 18,
+    RequestId = 19,
+    Host = 20,
+    UserAgent = 21,
+    Headers = 22,
+    Labels = 23,
+}
+
+//The path to each column in the JSON object, in the order of WafLogField, the headers have none
+const WAF_JSON_PATHS: [&[&str]; 24] = [
+    &["timestamp"],
+    &["formatVersion"],
+    &["webaclId"],
+    &["terminatingRuleId"],
+    &["terminatingRuleType"],
+    &["action"],
+    &["terminatingRuleMatchDetails"],
+    &["httpSourceName"],
+    &["httpSourceId"],
+    &["ruleGroupList"],
+    &["rateBasedRuleList"],
+    &["nonTerminatingMatchingRules"],
+    &["responseCodeSent"],
+    &["httpRequest", "clientIp"],
+    &["httpRequest", "country"],
+    &["httpRequest", "uri"],
+    &["httpRequest", "args"],
+    &["httpRequest", "httpVersion"],
+    &["httpRequest", "httpMethod"],
+    &["httpRequest", "requestId"],
+    &[],
+    &[],
+    &["httpRequest", "headers"],
+    &["labels"],
+];
+
+impl FromStr for WafLogField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(WafLogField::Timestamp),
+            "format_version" => Ok(WafLogField::FormatVersion),
+            "webacl_id" => Ok(WafLogField::WebaclId),
+            "terminating_rule_id" => Ok(WafLogField::TerminatingRuleId),
+            "terminating_rule_type" => Ok(WafLogField::TerminatingRuleType),
+            "action" => Ok(WafLogField::Action),
+            "terminating_rule_match_details" => Ok(WafLogField::TerminatingRuleMatchDetails),
+            "http_source_name" => Ok(WafLogField::HttpSourceName),
+            "http_source_id" => Ok(WafLogField::HttpSourceId),
+            "rule_group_list" => Ok(WafLogField::RuleGroupList),
+            "rate_based_rule_list" => Ok(WafLogField::RateBasedRuleList),
+            "non_terminating_matching_rules" => Ok(WafLogField::NonTerminatingMatchingRules),
+            "response_code_sent" => Ok(WafLogField::ResponseCodeSent),
+            "client_ip" => Ok(WafLogField::ClientIp),
+            "country" => Ok(WafLogField::Country),
+            "uri" => Ok(WafLogField::Uri),
+            "args" => Ok(WafLogField::Args),
+            "http_version" => Ok(WafLogField::HttpVersion),
+            "http_method" => Ok(WafLogField::HttpMethod),
+            "request_id" => Ok(WafLogField::RequestId),
+            "host" => Ok(WafLogField::Host),
+            "user_agent" => Ok(WafLogField::UserAgent),
+            "headers" => Ok(WafLogField::Headers),
+            "labels" => Ok(WafLogField::Labels),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl WafLogField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_WAF_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_WAF_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_WAF_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/awscloudtrail/latest/userguide/cloudtrail-event-reference-record-contents.html
+//The events of the Records array are flattened into the columns, the timestamp is the eventTime
+pub(crate) enum CloudTrailField {
+    Timestamp = 0,
+    EventVersion = 1,
+    EventSource = 2,
+    EventName = 3,
+    AwsRegion = 4,
+    SourceIpAddress = 5,
+    UserAgent = 6,
+    UserIdentityType = 7,
+    UserIdentityArn = 8,
+    UserIdentityAccountId = 9,
+    UserIdentityPrincipalId = 10,
+    UserIdentityAccessKeyId = 11,
+    UserIdentityUserName = 12,
+    UserIdentityInvokedBy = 13,
+    ErrorCode = 14,
+    ErrorMessage = 15,
+    RequestId = 16,
+    EventId = 17,
+    EventType = 18,
+    EventCategory = 19,
+    ReadOnly = 20,
+    ManagementEvent = 21,
+    RecipientAccountId = 22,
+    RequestParameters = 23,
+    ResponseElements = 24,
+    Resources = 25,
+    UserIdentity = 26,
+}
+
+//The path to each column in the event, in the order of CloudTrailField
+const CLOUDTRAIL_JSON_PATHS: [&[&str]; 27] = [
+    &["eventTime"],
+    &["eventVersion"],
+    &["eventSource"],
+    &["eventName"],
+    &["awsRegion"],
+    &["sourceIPAddress"],
+    &["userAgent"],
+    &["userIdentity", "type"],
+    &["userIdentity", "arn"],
+    &["userIdentity", "accountId"],
+    &["userIdentity", "principalId"],
+    &["userIdentity", "accessKeyId"],
+    &["userIdentity", "userName"],
+    &["userIdentity", "invokedBy"],
+    &["errorCode"],
+    &["errorMessage"],
+    &["requestID"],
+    &["eventID"],
+    &["eventType"],
+    &["eventCategory"],
+    &["readOnly"],
+    &["managementEvent"],
+    &["recipientAccountId"],
+    &["requestParameters"],
+    &["responseElements"],
+    &["resources"],
+    &["userIdentity"],
+];
+
+impl FromStr for CloudTrailField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(CloudTrailField::Timestamp),
+            "event_version" => Ok(CloudTrailField::EventVersion),
+            "event_source" => Ok(CloudTrailField::EventSource),
+            "event_name" => Ok(CloudTrailField::EventName),
+            "aws_region" => Ok(CloudTrailField::AwsRegion),
+            "source_ip_address" => Ok(CloudTrailField::SourceIpAddress),
+            "user_agent" => Ok(CloudTrailField::UserAgent),
+            "user_identity_type" => Ok(CloudTrailField::UserIdentityType),
+            "user_identity_arn" => Ok(CloudTrailField::UserIdentityArn),
+            "user_identity_account_id" => Ok(CloudTrailField::UserIdentityAccountId),
+            "user_identity_principal_id" => Ok(CloudTrailField::UserIdentityPrincipalId),
+            "user_identity_access_key_id" => Ok(CloudTrailField::UserIdentityAccessKeyId),
+            "user_identity_user_name" => Ok(CloudTrailField::UserIdentityUserName),
+            "user_identity_invoked_by" => Ok(CloudTrailField::UserIdentityInvokedBy),
+            "error_code" => Ok(CloudTrailField::ErrorCode),
+            "error_message" => Ok(CloudTrailField::ErrorMessage),
+            "request_id" => Ok(CloudTrailField::RequestId),
+            "event_id" => Ok(CloudTrailField::EventId),
+            "event_type" => Ok(CloudTrailField::EventType),
+            "event_category" => Ok(CloudTrailField::EventCategory),
+            "read_only" => Ok(CloudTrailField::ReadOnly),
+            "management_event" => Ok(CloudTrailField::ManagementEvent),
+            "recipient_account_id" => Ok(CloudTrailField::RecipientAccountId),
+            "request_parameters" => Ok(CloudTrailField::RequestParameters),
+            "response_elements" => Ok(CloudTrailField::ResponseElements),
+            "resources" => Ok(CloudTrailField::Resources),
+            "user_identity" => Ok(CloudTrailField::UserIdentity),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl CloudTrailField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_CLOUDTRAIL_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_CLOUDTRAIL_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_CLOUDTRAIL_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/Route53/latest/DeveloperGuide/resolver-query-logs-format.html
+//The JSON object of a line is flattened into the columns, the timestamp is the query_timestamp
+pub(crate) enum Route53ResolverField {
+    Timestamp = 0,
+    Version = 1,
+    AccountId = 2,
+    Region = 3,
+    VpcId = 4,
+    QueryName = 5,
+    QueryType = 6,
+    QueryClass = 7,
+    Rcode = 8,
+    Answers = 9,
+    Srcaddr = 10,
+    Srcport = 11,
+    Transport = 12,
+    SrcidsInstance = 13,
+    SrcidsResolverEndpoint = 14,
+    FirewallRuleAction = 15,
+    FirewallRuleGroupId = 16,
+    FirewallDomainListId = 17,
+}
+
+//The path to each column in the JSON object, in the order of Route53ResolverField
+const ROUTE53_RESOLVER_JSON_PATHS: [&[&str]; 18] = [
+    &["query_timestamp"],
+    &["version"],
+    &["account_id"],
+    &["region"],
+    &["vpc_id"],
+    &["query_name"],
+    &["query_type"],
+    &["query_class"],
+    &["rcode"],
+    &["answers"],
+    &["srcaddr"],
+    &["srcport"],
+    &["transport"],
+    &["srcids", "instance"],
+    &["srcids", "resolver_endpoint"],
+    &["firewall_rule_action"],
+    &["firewall_rule_group_id"],
+    &["firewall_domain_list_id"],
+];
+
+impl FromStr for Route53ResolverField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(Route53ResolverField::Timestamp),
+            "version" => Ok(Route53ResolverField::Version),
+            "account_id" => Ok(Route53ResolverField::AccountId),
+            "region" => Ok(Route53ResolverField::Region),
+            "vpc_id" => Ok(Route53ResolverField::VpcId),
+            "query_name" => Ok(Route53ResolverField::QueryName),
+            "query_type" => Ok(Route53ResolverField::QueryType),
+            "query_class" => Ok(Route53ResolverField::QueryClass),
+            "rcode" => Ok(Route53ResolverField::Rcode),
+            "answers" => Ok(Route53ResolverField::Answers),
+            "srcaddr" => Ok(Route53ResolverField::Srcaddr),
+            "srcport" => Ok(Route53ResolverField::Srcport),
+            "transport" => Ok(Route53ResolverField::Transport),
+            "srcids_instance" => Ok(Route53ResolverField::SrcidsInstance),
+            "srcids_resolver_endpoint" => Ok(Route53ResolverField::SrcidsResolverEndpoint),
+            "firewall_rule_action" => Ok(Route53ResolverField::FirewallRuleAction),
+            "firewall_rule_group_id" => Ok(Route53ResolverField::FirewallRuleGroupId),
+            "firewall_domain_list_id" => Ok(Route53ResolverField::FirewallDomainListId),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl Route53ResolverField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_ROUTE53_RESOLVER_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_ROUTE53_RESOLVER_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_ROUTE53_RESOLVER_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/apigateway/latest/developerguide/set-up-logging.html
+//The access logs in the JSON format of the $context variables, one object per line with the values mostly as strings
+pub(crate) enum ApiGatewayField {
+    Timestamp = 0,
+    RequestId = 1,
+    ExtendedRequestId = 2,
+    Ip = 3,
+    Caller = 4,
+    User = 5,
+    UserAgent = 6,
+    HttpMethod = 7,
+    ResourcePath = 8,
+    Path = 9,
+    Protocol = 10,
+    Status = 11,
+    ResponseLength = 12,
+    Latency = 13,
+    IntegrationLatency = 14,
+    IntegrationStatus = 15,
+    ErrorMessage = 16,
+    DomainName = 17,
+    Stage = 18,
+    ApiId = 19,
+}
+
+//The key of each column in the JSON object, in the order of ApiGatewayField, the HTTP APIs write latency as responseLatency
+const APIGATEWAY_JSON_PATHS: [&[&str]; 20] = [
+    &["requestTime"],
+    &["requestId"],
+    &["extendedRequestId"],
+    &["ip"],
+    &["caller"],
+    &["user"],
+    &["userAgent"],
+    &["httpMethod"],
+    &["resourcePath"],
+    &["path"],
+    &["protocol"],
+    &["status"],
+    &["responseLength"],
+    &["responseLatency"],
+    &["integrationLatency"],
+    &["integrationStatus"],
+    &["errorMessage"],
+    &["domainName"],
+    &["stage"],
+    &["apiId"],
+];
+
+impl FromStr for ApiGatewayField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "timestamp" => Ok(ApiGatewayField::Timestamp),
+            "request_id" => Ok(ApiGatewayField::RequestId),
+            "extended_request_id" => Ok(ApiGatewayField::ExtendedRequestId),
+            "ip" => Ok(ApiGatewayField::Ip),
+            "caller" => Ok(ApiGatewayField::Caller),
+            "user" => Ok(ApiGatewayField::User),
+            "user_agent" => Ok(ApiGatewayField::UserAgent),
+            "http_method" => Ok(ApiGatewayField::HttpMethod),
+            "resource_path" => Ok(ApiGatewayField::ResourcePath),
+            "path" => Ok(ApiGatewayField::Path),
+            "protocol" => Ok(ApiGatewayField::Protocol),
+            "status" => Ok(ApiGatewayField::Status),
+            "response_length" => Ok(ApiGatewayField::ResponseLength),
+            "latency" => Ok(ApiGatewayField::Latency),
+            "integration_latency" => Ok(ApiGatewayField::IntegrationLatency),
+            "integration_status" => Ok(ApiGatewayField::IntegrationStatus),
+            "error_message" => Ok(ApiGatewayField::ErrorMessage),
+            "domain_name" => Ok(ApiGatewayField::DomainName),
+            "stage" => Ok(ApiGatewayField::Stage),
+            "api_id" => Ok(ApiGatewayField::ApiId),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl ApiGatewayField {
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &AWS_APIGATEWAY_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        AWS_APIGATEWAY_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        AWS_APIGATEWAY_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//Reference: https://docs.aws.amazon.com/AmazonCloudFront/latest/DeveloperGuide/AccessLogs.html#LogFileFormat
+//The fields are separated by tabs, and the timestamp is made of the date and the time fields in UTC
+pub(crate) enum CloudFrontField {
+    Date = 0,
+    Time = 1,
+    EdgeLocation = 2,
+    ScBytes = 3,
+    ClientIp = 4,
+    Method = 5,
+    Host = 6,
+    UriStem = 7,
+    Status = 8,
+    Referrer = 9,
+    UserAgent = 10,
+    UriQuery = 11,
+    Cookie = 12,
+    EdgeResultType = 13,
+    EdgeRequestId = 14,
+    HostHeader = 15,
+    Protocol = 16,
+    CsBytes = 17,
+    TimeTaken = 18,
+    ForwardedFor = 19,
+    SslProtocol = 20,
+    SslCipher = 21,
+    EdgeResponseResultType = 22,
+    ProtocolVersion = 23,
+    FleStatus = 24,
+    FleEncryptedFields = 25,
+    ClientPort = 26,
+    TimeToFirstByte = 27,
+    EdgeDetailedResultType = 28,
+    ContentType = 29,
+    ContentLen = 30,
+    RangeStart = 31,
+    RangeEnd = 32,
+    Timestamp = 33,
+}
+
+//The names in the #Fields header of the log file, in the order of CloudFrontField
+const CLOUDFRONT_W3C_FIELD_NAMES: [&str; 33] = [
+    "date",
+    "time",
+    "x-edge-location",
+    "sc-bytes",
+    "c-ip",
+    "cs-method",
+    "cs(Host)",
+    "cs-uri-stem",
+    "sc-status",
+    "cs(Referer)",
+    "cs(User-Agent)",
+    "cs-uri-query",
+    "cs(Cookie)",
+    "x-edge-result-type",
+    "x-edge-request-id",
+    "x-host-header",
+    "cs-protocol",
+    "cs-bytes",
+    "time-taken",
+    "x-forwarded-for",
+    "ssl-protocol",
+    "ssl-cipher",
+    "x-edge-response-result-type",
+    "cs-protocol-version",
+    "fle-status",
+    "fle-encrypted-fields",
+    "c-port",
+    "time-to-first-byte",
+    "x-edge-detailed-result-type",
+    "sc-content-type",
+    "sc-content-len",
+    "sc-range-start",
+    "sc-range-end",
+];
+
+impl FromStr for CloudFrontField {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "date" => Ok(CloudFrontField::Date),
+            "time" => Ok(CloudFrontField::Time),
+            "edge_location" => Ok(CloudFrontField::EdgeLocation),
+            "sc_bytes" => Ok(CloudFrontField::ScBytes),
+            "client_ip" => Ok(CloudFrontField::ClientIp),
+            "method" => Ok(CloudFrontField::Method),
+            "host" => Ok(CloudFrontField::Host),
+            "uri_stem" => Ok(CloudFrontField::UriStem),
+            "status" => Ok(CloudFrontField::Status),
+            "referrer" => Ok(CloudFrontField::Referrer),
+            "user_agent" => Ok(CloudFrontField::UserAgent),
+            "uri_query" => Ok(CloudFrontField::UriQuery),
+            "cookie" => Ok(CloudFrontField::Cookie),
+            "edge_result_type" => Ok(CloudFrontField::EdgeResultType),
+            "edge_request_id" => Ok(CloudFrontField::EdgeRequestId),
+            "host_header" => Ok(CloudFrontField::HostHeader),
+            "protocol" => Ok(CloudFrontField::Protocol),
+            "cs_bytes" => Ok(CloudFrontField::CsBytes),
+            "time_taken" => Ok(CloudFrontField::TimeTaken),
+            "forwarded_for" => Ok(CloudFrontField::ForwardedFor),
+            "ssl_protocol" => Ok(CloudFrontField::SslProtocol),
+            "ssl_cipher" => Ok(CloudFrontField::SslCipher),
+            "edge_response_result_type" => Ok(CloudFrontField::EdgeResponseResultType),
+            "protocol_version" => Ok(CloudFrontField::ProtocolVersion),
+            "fle_status" => Ok(CloudFrontField::FleStatus),
+            "fle_encrypted_fields" => Ok(CloudFrontField::FleEncryptedFields),
+            "client_port" => Ok(CloudFrontField::ClientPort),
+            "time_to_first_byte" => Ok(CloudFrontField::TimeToFirstByte),
+            "edge_detailed_result_type" => Ok(CloudFrontField::EdgeDetailedResultType),
+            "content_type" => Ok(CloudFrontField::ContentType),
+            "content_len" => Ok(CloudFrontField::ContentLen),
+            "range_start" => Ok(CloudFrontField::RangeStart),
+            "range_end" => Ok(CloudFrontField::RangeEnd),
+            "timestamp" => Ok(CloudFrontField::Timestamp),
+            _ => Err("unknown column name".to_string()),
+        }
+    }
+}
+
+impl CloudFrontField {
+    pub(crate) fn len() -> usize {
+        34
+    }
+
+    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
+        &CLOUDFRONT_FIELD_NAMES
+    }
+
+    pub(crate) fn datatypes() -> Vec<DataType> {
+        CLOUDFRONT_DATATYPES.clone()
+    }
+
+    pub(crate) fn datatype(idx: usize) -> DataType {
+        CLOUDFRONT_DATATYPES[idx].clone()
+    }
+
+    pub(crate) fn schema() -> Vec<(String, DataType)> {
+        let fields = Self::field_names().clone();
+        let datatypes = Self::datatypes();
+        fields.into_iter().zip(datatypes).collect()
+    }
+}
+
+//The position of each column in the log file by the header line, the unknown columns are ignored
+fn columns_from_header(header_names: &[&str], header: &str) -> Vec<Option<usize>> {
+    header
+        .split_whitespace()
+        .map(|name| header_names.iter().position(|n| n.eq_ignore_ascii_case(name)))
+        .collect()
+}
+
+//The columns of the JSON formats by their paths into the JSON object, the missing ones are null and so are the
+//ones without a path, which are left to the reader
+fn flatten_json(parsed: &JsonValue, paths: &[&[&str]], field_names: &[String]) -> Variables {
+    paths
+        .iter()
+        .zip(field_names.iter())
+        .map(|(path, field_name)| {
+            if path.is_empty() {
+                return (field_name.clone(), Value::Null);
+            }
+
+            let json_value = path.iter().fold(parsed, |v, key| &v[*key]);
+            (field_name.clone(), json_to_data_model(json_value))
+        })
+        .collect()
+}
+
+//Without a header line the columns are in the order of the header names
+fn parse_columns<'a, I: Iterator<Item = &'a str>>(
+    tokens: I,
+    columns: Option<&Vec<Option<usize>>>,
+    header_names: &[&str],
+    datatype: fn(usize) -> DataType,
+    values: &mut [Value],
+) -> ReaderResult<()> {
+    for (i, s) in tokens.enumerate() {
+        let idx = match columns {
+            Some(columns) => columns.get(i).copied().flatten(),
+            None => Some(i).filter(|i| *i < header_names.len()),
+        };
+
+        if let Some(idx) = idx {
+            values[idx] = parse_field(s, &datatype(idx))?;
+        }
+    }
+
+    Ok(())
+}
+
+//Reference: https://learn.microsoft.com/en-us/windows/win32/http/w3c-logging
+//The fields of the IIS W3C extended log are chosen per site, so the columns are named by the #Fields header of each
+//file, e.g. "cs-uri-stem" is cs_uri_stem and "cs(User-Agent)" is cs_user_agent. The fields by default of IIS are
+//assumed until a header is read.
+const IIS_DEFAULT_FIELDS: &str = "date time s-ip cs-method cs-uri-stem cs-uri-query s-port cs-username c-ip \
+                                  cs(User-Agent) cs(Referer) sc-status sc-substatus sc-win32-status time-taken";
+
+//The known fields are typed, the others including the custom ones are strings
+fn iis_datatype(field: &str) -> DataType {
+    match field.to_ascii_lowercase().as_str() {
+        "s-port" | "sc-status" | "sc-substatus" | "sc-win32-status" | "sc-bytes" | "cs-bytes" | "time-taken" => {
+            DataType::Integral
+        }
+        "date" | "time" => DataType::String,
+        _ => DataType::QuotedString,
+    }
+}
+
+fn iis_schema(header: &str) -> Vec<(String, DataType)> {
+    header
+        .split_whitespace()
+        .map(|field| {
+            let name = field.to_ascii_lowercase().replace(['-', '('], "_").replace(')', "");
+            (name, iis_datatype(field))
+        })
+        .collect()
+}
+
+//Reference: https://nginx.org/en/docs/http/ngx_http_log_module.html#log_format
+//The predefined combined format, used unless the log_format of the server is given
+const NGINX_COMBINED_FORMAT: &str = r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent "$http_referer" "$http_user_agent""#;
+const NGINX_TIME_LOCAL_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+//Reference: https://kubernetes.github.io/ingress-nginx/user-guide/nginx-configuration/log-format/
+//The upstreaminfo format of the ingress-nginx controller, the combined format followed by the upstream of the request
+const INGRESS_NGINX_FORMAT: &str = r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent "$http_referer" "$http_user_agent" $request_length $request_time [$proxy_upstream_name] [$proxy_alternative_upstream_name] $upstream_addr $upstream_response_length $upstream_response_time $upstream_status $req_id"#;
+
+//Reference: https://httpd.apache.org/docs/current/mod/mod_log_config.html#formats
+//The combined format with the %D latency, the combined and the common formats, a line is read by the first that matches
+const APACHE_DEFAULT_FORMATS: [&str; 3] = [
+    r#"%h %l %u %t "%r" %>s %b "%{Referer}i" "%{User-agent}i" %D"#,
+    r#"%h %l %u %t "%r" %>s %b "%{Referer}i" "%{User-agent}i""#,
+    r#"%h %l %u %t "%r" %>s %b"#,
+];
+
+//Reference: https://www.squid-cache.org/Doc/config/logformat/
+//Reference: https://docs.aws.amazon.com/AmazonCloudFront/latest/DeveloperGuide/real-time-logs.html
+//The fields of the CloudFront real-time logs in the order they are delivered, a configuration has some of them
+const CLOUDFRONT_RT_FIELDS: [&str; 45] = [
+    "timestamp",
+    "c-ip",
+    "time-to-first-byte",
+    "sc-status",
+    "sc-bytes",
+    "cs-method",
+    "cs-protocol",
+    "cs-host",
+    "cs-uri-stem",
+    "cs-bytes",
+    "x-edge-location",
+    "x-edge-request-id",
+    "x-host-header",
+    "time-taken",
+    "cs-protocol-version",
+    "c-ip-version",
+    "cs-user-agent",
+    "cs-referer",
+    "cs-cookie",
+    "cs-uri-query",
+    "x-edge-response-result-type",
+    "x-forwarded-for",
+    "ssl-protocol",
+    "ssl-cipher",
+    "x-edge-result-type",
+    "fle-encrypted-fields",
+    "fle-status",
+    "sc-content-type",
+    "sc-content-len",
+    "sc-range-start",
+    "sc-range-end",
+    "c-port",
+    "x-edge-detailed-result-type",
+    "c-country",
+    "cs-accept-encoding",
+    "cs-accept",
+    "cache-behavior-path-pattern",
+    "cs-headers",
+    "cs-header-names",
+    "cs-headers-count",
+    "primary-distribution-id",
+    "primary-distribution-dns-name",
+    "origin-fbl",
+    "origin-lbl",
+    "asn",
+];
+
+//The predefined formats of squid, by the name given to the log_format option
+const SQUID_LOG_FORMATS: [(&str, &str); 5] = [
+    ("squid", "%ts.%03tu %6tr %>a %Ss/%03>Hs %<st %rm %ru %[un %Sh/%<a %mt"),
+    ("common", r#"%>a %[ui %[un [%tl] "%rm %ru HTTP/%rv" %>Hs %<st %Ss:%Sh"#),
+    (
+        "combined",
+        r#"%>a %[ui %[un [%tl] "%rm %ru HTTP/%rv" %>Hs %<st "%{Referer}>h" "%{User-Agent}>h" %Ss:%Sh"#,
+    ),
+    ("referrer", "%ts.%03tu %>a %{Referer}>h %ru"),
+    ("useragent", r#"%>a [%tl] "%{User-Agent}>h""#),
+];
+
+lazy_static! {
+    static ref NGINX_COMBINED: LogFormat = LogFormat::parse_nginx(NGINX_COMBINED_FORMAT).unwrap();
+    static ref INGRESS_NGINX_UPSTREAMINFO: LogFormat = LogFormat::parse_nginx(INGRESS_NGINX_FORMAT).unwrap();
+    static ref CLOUDFRONT_RT_ALL_FIELDS: LogFormat = LogFormat::parse_cloudfront_rt(&CLOUDFRONT_RT_FIELDS.join(",")).unwrap();
+    static ref NGINX_VARIABLE_REGEX: Regex = Regex::new(r#"\$(?:\{([0-9a-zA-Z_]+)\}|([0-9a-zA-Z_]+))"#).unwrap();
+    static ref APACHE_DEFAULT: LogFormat = {
+        let mut log_format = LogFormat::parse_apache(APACHE_DEFAULT_FORMATS[0]).unwrap();
+        for alternative in APACHE_DEFAULT_FORMATS[1..].iter() {
+            let alternative = LogFormat::parse_apache(alternative).unwrap();
+            log_format.line_regexes.extend(alternative.line_regexes);
+        }
+        log_format
+    };
+    static ref APACHE_DIRECTIVE_REGEX: Regex = Regex::new(r#"%(?:%|[<>]?(?:\{([^}]*)\})?([a-zA-Z]))"#).unwrap();
+    //The quoting modifier, the width, the argument and the format code, e.g. %6tr or %"{User-Agent}>h
+    static ref SQUID_CODE_REGEX: Regex = Regex::new(
+        r#"%(?:%|["'\[#/]?(-?[0-9]*(?:\.[0-9]+)?)(?:\{([^}]*)\})?(>Hs|<Hs|<st|>st|>a|>A|>p|<a|<A|<p|>h|<h|ts|tu|tl|tg|tr|Ss|Sh|rm|ru|rp|rv|un|ul|ui|us|ue|mt|la|lp|st))"#
+    )
+    .unwrap();
+}
+
+//A column of a log format: the literal text before it, the capture group, the variable and the field it is read into
+type LogFormatColumn = (String, &'static str, String, String, DataType);
+
+//The configurable line format of a web server, the log_format of nginx or the LogFormat of apache. Each variable or
+//directive is a column, the time of the request is the timestamp. The datatypes of the well-known variables are known,
+//the others are strings where "-" is null. A format may have shorter alternatives with a prefix of its columns, the
+//columns missing from the line are null.
+#[derive(Debug, Clone)]
+pub(crate) struct LogFormat {
+    line_regexes: Vec<Regex>,
+    field_names: Vec<String>,
+    datatypes: Vec<DataType>,
+}
+
+impl PartialEq for LogFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.signature() == other.signature()
+    }
+}
+
+impl Eq for LogFormat {}
+
+impl LogFormat {
+    //The format given for a table, only the web server formats have one
+    pub(crate) fn parse(file_format: &str, log_format: &str) -> result::Result<Self, String> {
+        match file_format {
+            "nginx" | "ingress_nginx" => Self::parse_nginx(log_format),
+            "apache" => Self::parse_apache(log_format),
+            "squid" => Self::parse_squid(log_format),
+            "regex" => Self::parse_regex(log_format),
+            "cloudfront_rt" => Self::parse_cloudfront_rt(log_format),
+            _ => Err(format!(
+                "the log format is not supported for the {} tables",
+                file_format
+            )),
+        }
+    }
+
+    //The squid tables are read in the native layout unless a format is given
+    pub(crate) fn is_supported(file_format: &str) -> bool {
+        matches!(
+            file_format,
+            "nginx" | "ingress_nginx" | "apache" | "squid" | "regex" | "cloudfront_rt"
+        )
+    }
+
+    pub(crate) fn default_of(file_format: &str) -> Option<&'static LogFormat> {
+        match file_format {
+            "nginx" => Some(&NGINX_COMBINED),
+            "ingress_nginx" => Some(&INGRESS_NGINX_UPSTREAMINFO),
+            "apache" => Some(&APACHE_DEFAULT),
+            "cloudfront_rt" => Some(&CLOUDFRONT_RT_ALL_FIELDS),
+            _ => None,
+        }
+    }
+
+    fn parse_nginx(log_format: &str) -> result::Result<Self, String> {
+        let mut columns = Vec::new();
+        let mut last = 0;
+        for cap in NGINX_VARIABLE_REGEX.captures_iter(log_format) {
+            let m = cap.get(0).unwrap();
+            let variable = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+            let (field_name, datatype) = Self::nginx_column_of(variable);
+            //The upstream variables are lists when the request was passed to more than one server, e.g.
+            //"10.0.0.1:80, 10.0.0.2:80", a lazy group would end at the first space
+            let group = match variable {
+                "upstream_addr"
+                | "upstream_status"
+                | "upstream_response_time"
+                | "upstream_response_length"
+                | "upstream_connect_time"
+                | "upstream_header_time"
+                | "upstream_bytes_received"
+                | "upstream_bytes_sent" => r"([^\s,]+(?:(?:, | : )[^\s,]+)*)",
+                _ => "(.*?)",
+            };
+            columns.push((
+                log_format[last..m.start()].to_string(),
+                group,
+                m.as_str().to_string(),
+                field_name,
+                datatype,
+            ));
+            last = m.end();
+        }
+
+        Self::new(columns, &log_format[last..])
+    }
+
+    fn nginx_column_of(variable: &str) -> (String, DataType) {
+        let datatype = match variable {
+            "time_local" => DataType::FormattedDateTime(NGINX_TIME_LOCAL_FORMAT.to_string()),
+            "time_iso8601" => DataType::DateTime,
+            "request" => DataType::HttpRequest,
+            "status"
+            | "body_bytes_sent"
+            | "bytes_sent"
+            | "request_length"
+            | "connection"
+            | "connection_requests"
+            | "remote_port"
+            | "server_port" => DataType::Integral,
+            "request_time" | "msec" => DataType::Float,
+            _ => DataType::QuotedString,
+        };
+
+        match variable {
+            "time_local" | "time_iso8601" => ("timestamp".to_string(), datatype),
+            _ => (variable.to_string(), datatype),
+        }
+    }
+
+    fn parse_apache(log_format: &str) -> result::Result<Self, String> {
+        let mut columns = Vec::new();
+        let mut literal = String::new();
+        let mut last = 0;
+        for cap in APACHE_DIRECTIVE_REGEX.captures_iter(log_format) {
+            let m = cap.get(0).unwrap();
+            literal.push_str(&log_format[last..m.start()]);
+            last = m.end();
+            if m.as_str() == "%%" {
+                literal.push('%');
+                continue;
+            }
+
+            let (field_name, datatype) =
+                Self::apache_column_of(cap.get(1).map(|a| a.as_str()), cap.get(2).unwrap().as_str())
+                    .ok_or_else(|| format!("unsupported directive {}", m.as_str()))?;
+            //The time is logged in brackets
+            let group = if m.as_str() == "%t" { r"\[(.*?)\]" } else { "(.*?)" };
+            columns.push((
+                std::mem::take(&mut literal),
+                group,
+                m.as_str().to_string(),
+                field_name,
+                datatype,
+            ));
+        }
+        literal.push_str(&log_format[last..]);
+
+        Self::new(columns, &literal)
+    }
+
+    fn apache_column_of(argument: Option<&str>, directive: &str) -> Option<(String, DataType)> {
+        let column = match (argument, directive) {
+            (None, "h") => ("remote_host", DataType::QuotedString),
+            (None, "a") => ("remote_addr", DataType::QuotedString),
+            (None, "l") => ("remote_logname", DataType::QuotedString),
+            (None, "u") => ("remote_user", DataType::QuotedString),
+            (None, "t") => (
+                "timestamp",
+                DataType::FormattedDateTime(NGINX_TIME_LOCAL_FORMAT.to_string()),
+            ),
+            (None, "r") => ("request", DataType::HttpRequest),
+            (None, "s") => ("status", DataType::Integral),
+            (None, "b") | (None, "B") => ("bytes_sent", DataType::Integral),
+            (None, "D") => ("request_time_us", DataType::Integral),
+            (None, "T") => ("request_time", DataType::Integral),
+            (None, "I") => ("bytes_received", DataType::Integral),
+            (None, "O") => ("bytes_transferred", DataType::Integral),
+            (None, "v") => ("server_name", DataType::QuotedString),
+            (None, "p") => ("server_port", DataType::Integral),
+            (None, "m") => ("method", DataType::QuotedString),
+            (None, "U") => ("url_path", DataType::QuotedString),
+            (None, "q") => ("query_string", DataType::QuotedString),
+            (None, "H") => ("protocol", DataType::QuotedString),
+            (Some(header), "i") => {
+                //The request headers are named like the variables of nginx, e.g. %{User-agent}i is user_agent
+                let field_name = header.to_ascii_lowercase().replace('-', "_");
+                return Some((field_name, DataType::QuotedString));
+            }
+            _ => return None,
+        };
+
+        Some((column.0.to_string(), column.1))
+    }
+
+    //Any other format as a regex, each named capture group is a column, e.g. (?P<status>\d+)
+    fn parse_regex(log_format: &str) -> result::Result<Self, String> {
+        let line_regex = Regex::new(log_format).map_err(|e| e.to_string())?;
+        let field_names: Vec<String> = line_regex.capture_names().flatten().map(|n| n.to_string()).collect();
+        if field_names.is_empty() {
+            return Err(format!("no named capture group in \"{}\"", log_format));
+        }
+
+        Ok(LogFormat {
+            line_regexes: vec![line_regex],
+            datatypes: vec![DataType::String; field_names.len()],
+            field_names,
+        })
+    }
+
+    fn parse_squid(log_format: &str) -> result::Result<Self, String> {
+        let log_format = SQUID_LOG_FORMATS
+            .iter()
+            .find(|(name, _)| *name == log_format)
+            .map_or(log_format, |(_, definition)| *definition);
+        //The seconds and the milliseconds since the epoch are one timestamp
+        let log_format = log_format.replace("%ts.%03tu", "%ts");
+        let mut columns = Vec::new();
+        let mut literal = String::new();
+        let mut last = 0;
+        for cap in SQUID_CODE_REGEX.captures_iter(&log_format) {
+            let m = cap.get(0).unwrap();
+            literal.push_str(&log_format[last..m.start()]);
+            last = m.end();
+            if m.as_str() == "%%" {
+                literal.push('%');
+                continue;
+            }
+
+            let (field_name, datatype) =
+                Self::squid_column_of(cap.get(2).map(|a| a.as_str()), cap.get(3).unwrap().as_str())
+                    .ok_or_else(|| format!("unsupported format code {}", m.as_str()))?;
+            //The values of a width are padded with spaces, on the right if it is left aligned
+            let group = match cap.get(1).map_or("", |w| w.as_str()) {
+                "" => "(.*?)",
+                width if width.starts_with('-') => r"(.*?)\s*",
+                _ => r"\s*(.*?)",
+            };
+            columns.push((
+                std::mem::take(&mut literal),
+                group,
+                m.as_str().to_string(),
+                field_name,
+                datatype,
+            ));
+        }
+        literal.push_str(&log_format[last..]);
+
+        Self::new(columns, &literal)
+    }
+
+    fn squid_column_of(argument: Option<&str>, code: &str) -> Option<(String, DataType)> {
+        let column = match (argument, code) {
+            (_, "ts") => ("timestamp", DataType::FormattedDateTime("%s%.f".to_string())),
+            (_, "tl") | (_, "tg") => (
+                "timestamp",
+                DataType::FormattedDateTime(NGINX_TIME_LOCAL_FORMAT.to_string()),
+            ),
+            (_, "tu") => ("timestamp_ms", DataType::Integral),
+            (_, "tr") => ("elapsed", DataType::Integral),
+            (_, ">a") => ("remote_host", DataType::QuotedString),
+            (_, ">A") => ("remote_fqdn", DataType::QuotedString),
+            (_, ">p") => ("remote_port", DataType::Integral),
+            (_, "<a") => ("peer_host", DataType::QuotedString),
+            (_, "<A") => ("peer_fqdn", DataType::QuotedString),
+            (_, "<p") => ("peer_port", DataType::Integral),
+            (_, "la") => ("local_addr", DataType::QuotedString),
+            (_, "lp") => ("local_port", DataType::Integral),
+            (_, "Ss") => ("squid_status", DataType::QuotedString),
+            (_, "Sh") => ("hierarchy_status", DataType::QuotedString),
+            (_, ">Hs") => ("status", DataType::Integral),
+            (_, "<Hs") => ("peer_status", DataType::Integral),
+            (_, "<st") => ("bytes", DataType::Integral),
+            (_, ">st") => ("request_bytes", DataType::Integral),
+            (_, "st") => ("total_bytes", DataType::Integral),
+            (_, "rm") => ("method", DataType::QuotedString),
+            (_, "ru") => ("url", DataType::QuotedString),
+            (_, "rp") => ("url_path", DataType::QuotedString),
+            (_, "rv") => ("protocol_version", DataType::QuotedString),
+            (_, "un") => ("user_name", DataType::QuotedString),
+            (_, "ul") => ("user_login", DataType::QuotedString),
+            (_, "ui") => ("user_ident", DataType::QuotedString),
+            (_, "us") => ("user_ssl", DataType::QuotedString),
+            (_, "ue") => ("user_external", DataType::QuotedString),
+            (_, "mt") => ("mime_type", DataType::QuotedString),
+            (Some(header), ">h") | (Some(header), "<h") => {
+                //The headers of the reply are told apart by a prefix, e.g. %{Content-Type}<h is reply_content_type
+                let prefix = if code == "<h" { "reply_" } else { "" };
+                let field_name = format!("{}{}", prefix, header.to_ascii_lowercase().replace('-', "_"));
+                return Some((field_name, DataType::QuotedString));
+            }
+            _ => return None,
+        };
+
+        Some((column.0.to_string(), column.1))
+    }
+
+    //The fields of the real-time log configuration in their order, separated by commas or spaces, or "@" and the path
+    //of a file with them, e.g. "timestamp,c-ip,sc-status,cs-uri-stem" or "@fields.txt"
+    fn parse_cloudfront_rt(log_format: &str) -> result::Result<Self, String> {
+        let log_format = match log_format.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?,
+            None => log_format.to_string(),
+        };
+        let mut columns = Vec::new();
+        for (i, field) in log_format
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .enumerate()
+        {
+            let datatype = Self::cloudfront_rt_column_of(field).ok_or(format!("unknown field {}", field))?;
+            let literal = if i == 0 { "" } else { "\t" };
+            columns.push((
+                literal.to_string(),
+                "([^\t]*)",
+                field.to_string(),
+                field.replace('-', "_"),
+                datatype,
+            ));
+        }
+
+        Self::new(columns, "")
+    }
+
+    fn cloudfront_rt_column_of(field: &str) -> Option<DataType> {
+        let datatype = match field {
+            "timestamp" => DataType::FormattedDateTime("%s%.f".to_string()),
+            "time-to-first-byte" | "time-taken" | "origin-fbl" | "origin-lbl" => DataType::Float,
+            "sc-status" | "sc-bytes" | "cs-bytes" | "sc-content-len" | "sc-range-start" | "sc-range-end" | "c-port"
+            | "cs-headers-count" | "asn" => DataType::Integral,
+            field if CLOUDFRONT_RT_FIELDS.contains(&field) => DataType::QuotedString,
+            _ => return None,
+        };
+
+        Some(datatype)
+    }
+
+    fn new(columns: Vec<LogFormatColumn>, tail: &str) -> result::Result<Self, String> {
+        if columns.is_empty() {
+            return Err(format!("no variable in \"{}\"", tail));
+        }
+
+        let mut pattern = "^".to_string();
+        let mut field_names: Vec<String> = Vec::new();
+        let mut datatypes = Vec::new();
+        for (literal, group, variable, field_name, datatype) in columns.into_iter() {
+            //A second time of the request keeps the name of its variable
+            let field_name = if field_name == "timestamp" && field_names.iter().any(|f| f == "timestamp") {
+                variable.clone()
+            } else {
+                field_name
+            };
+            if field_names.contains(&field_name) {
+                return Err(format!("duplicate variable {}", variable));
+            }
+
+            pattern.push_str(&regex::escape(&literal));
+            pattern.push_str(group);
+            field_names.push(field_name);
+            datatypes.push(datatype);
+        }
+        pattern.push_str(&regex::escape(tail));
+        pattern.push('$');
+
+        Ok(LogFormat {
+            line_regexes: vec![Regex::new(&pattern).map_err(|e| e.to_string())?],
+            field_names,
+            datatypes,
+        })
+    }
+
+    pub(crate) fn signature(&self) -> String {
+        let patterns: Vec<&str> = self.line_regexes.iter().map(|r| r.as_str()).collect();
+        patterns.join("|")
+    }
+
+    pub(crate) fn field_names(&self) -> &Vec<String> {
+        &self.field_names
+    }
+
+    pub(crate) fn schema(&self) -> Vec<(String, DataType)> {
+        self.field_names
+            .iter()
+            .cloned()
+            .zip(self.datatypes.iter().cloned())
+            .collect()
+    }
+
+    fn read_values(&self, line: &str) -> ReaderResult<Vec<Value>> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let cap = self
+            .line_regexes
+            .iter()
+            .find_map(|r| r.captures(line))
+            .ok_or(ReaderError::FormatMismatch)?;
+        let mut values = Vec::with_capacity(self.datatypes.len());
+        for (i, datatype) in self.datatypes.iter().enumerate() {
+            values.push(parse_logged_field(
+                cap.get(i + 1).map_or("-", |m| m.as_str()),
+                datatype,
+            )?);
+        }
+
+        Ok(values)
+    }
+
+    //The values of the named groups of a regex, typed like the columns of a csv. An unmatched group or "-" is null.
+    fn read_inferred_values(&self, line: &str, field_types: &FieldTypes) -> ReaderResult<Vec<Value>> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let cap = self
+            .line_regexes
+            .iter()
+            .find_map(|r| r.captures(line))
+            .ok_or(ReaderError::FormatMismatch)?;
+        let mut values = Vec::with_capacity(self.field_names.len());
+        for field_name in self.field_names.iter() {
+            let value = match cap.name(field_name).map(|m| m.as_str()) {
+                None | Some("") | Some("-") => Value::Null,
+                Some(s) => match field_types.iter().find(|(name, _)| name == field_name) {
+                    Some((_, datatype)) => parse_field(s, datatype)?,
+                    None => infer_value(s),
+                },
+            };
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+}
+
+//The field names and the datatypes of the log formats with a fixed layout, the generic format has none
+pub(crate) fn field_names_of(file_format: &str) -> Option<&'static Vec<String>> {
+    match file_format {
+        "elb" => Some(ClassicLoadBalancerLogField::field_names()),
+        "alb" => Some(ApplicationLoadBalancerLogField::field_names()),
+        "nlb" => Some(NetworkLoadBalancerLogField::field_names()),
+        "alb_conn" => Some(ApplicationLoadBalancerConnectionLogField::field_names()),
+        "s3" => Some(S3Field::field_names()),
+        "waf" => Some(WafLogField::field_names()),
+        "cloudtrail" => Some(CloudTrailField::field_names()),
+        "route53resolver" => Some(Route53ResolverField::field_names()),
+        "apigateway" => Some(ApiGatewayField::field_names()),
+        "vpcflow" => Some(VpcFlowLogField::field_names()),
+        "squid" => Some(SquidLogField::field_names()),
+        "cloudfront" => Some(CloudFrontField::field_names()),
+        "haproxy" => Some(HaproxyHttpLogField::field_names()),
+        "syslog" => Some(SyslogField::field_names()),
+        "traefik" => Some(TraefikLogField::field_names()),
+        "caddy" => Some(CaddyLogField::field_names()),
+        "cloudflare" => Some(CloudflareField::field_names()),
+        "fastly" => Some(FastlyLogField::field_names()),
+        "azure_appgw" => Some(AzureAppGatewayField::field_names()),
+        "nginx" | "ingress_nginx" | "apache" | "cloudfront_rt" => {
+            LogFormat::default_of(file_format).map(|f| f.field_names())
+        }
+        _ => None,
+    }
+}
+
+fn datatype_of(file_format: &str, idx: usize) -> DataType {
+    match file_format {
+        "elb" => ClassicLoadBalancerLogField::datatype(idx),
+        "alb" => ApplicationLoadBalancerLogField::datatype(idx),
+        "nlb" => NetworkLoadBalancerLogField::datatype(idx),
+        "alb_conn" => ApplicationLoadBalancerConnectionLogField::datatype(idx),
+        "s3" => S3Field::datatype(idx),
+        "waf" => WafLogField::datatype(idx),
+        "cloudtrail" => CloudTrailField::datatype(idx),
+        "route53resolver" => Route53ResolverField::datatype(idx),
+        "apigateway" => ApiGatewayField::datatype(idx),
+        "vpcflow" => VpcFlowLogField::datatype(idx),
+        "squid" => SquidLogField::datatype(idx),
+        "cloudfront" => CloudFrontField::datatype(idx),
+        "haproxy" => HaproxyHttpLogField::datatype(idx),
+        "syslog" => SyslogField::datatype(idx),
+        "traefik" => TraefikLogField::datatype(idx),
+        "caddy" => CaddyLogField::datatype(idx),
+        "cloudflare" => CloudflareField::datatype(idx),
+        "fastly" => FastlyLogField::datatype(idx),
+        "azure_appgw" => AzureAppGatewayField::datatype(idx),
+        _ => unreachable!(),
+    }
+}
+
+pub(crate) fn schema_of(file_format: &str) -> Option<Vec<(String, DataType)>> {
+    match file_format {
+        "elb" => Some(ClassicLoadBalancerLogField::schema()),
+        "alb" => Some(ApplicationLoadBalancerLogField::schema()),
+        "nlb" => Some(NetworkLoadBalancerLogField::schema()),
+        "alb_conn" => Some(ApplicationLoadBalancerConnectionLogField::schema()),
+        "s3" => Some(S3Field::schema()),
+        "waf" => Some(WafLogField::schema()),
+        "cloudtrail" => Some(CloudTrailField::schema()),
+        "route53resolver" => Some(Route53ResolverField::schema()),
+        "apigateway" => Some(ApiGatewayField::schema()),
+        "vpcflow" => Some(VpcFlowLogField::schema()),
+        "squid" => Some(SquidLogField::schema()),
+        "cloudfront" => Some(CloudFrontField::schema()),
+        "haproxy" => Some(HaproxyHttpLogField::schema()),
+        "syslog" => Some(SyslogField::schema()),
+        "traefik" => Some(TraefikLogField::schema()),
+        "caddy" => Some(CaddyLogField::schema()),
+        "cloudflare" => Some(CloudflareField::schema()),
+        "fastly" => Some(FastlyLogField::schema()),
+        "azure_appgw" => Some(AzureAppGatewayField::schema()),
+        "nginx" | "ingress_nginx" | "apache" | "cloudfront_rt" => {
+            LogFormat::default_of(file_format).map(|f| f.schema())
+        }
+        _ => None,
+    }
+}
+
+pub(crate) const SUPPORTED_FORMATS: &[&str] = &[
+    "elb",
+    "alb",
+    "nlb",
+    "alb_conn",
+    "squid",
+    "s3",
+    "vpcflow",
+    "waf",
+    "cloudtrail",
+    "route53resolver",
+    "apigateway",
+    "cloudfront",
+    "cloudfront_rt",
+    "nginx",
+    "ingress_nginx",
+    "apache",
+    "haproxy",
+    "syslog",
+    "traefik",
+    "caddy",
+    "iis",
+    "cloudflare",
+    "fastly",
+    "azure_appgw",
+    "jsonl",
+    "csv",
+    "tsv",
+];
+
+//The columns of the generic formats and iis are known only from the content of the log file
+pub(crate) fn is_supported_format(file_format: &str) -> bool {
+    is_generic_format(file_format) || file_format == "iis" || field_names_of(file_format).is_some()
+}
+
+//The formats of any data rather than of a log, their fields can be annotated with types. The columns of a regex table
+//are the named groups of the regex given with the log_format option, so it has no golden files.
+pub(crate) fn is_generic_format(file_format: &str) -> bool {
+    matches!(file_format, "jsonl" | "csv" | "tsv" | "regex")
+}
+
+pub(crate) type ReaderResult<T> = result::Result<T, ReaderError>;
+
+#[derive(Debug)]
+pub(crate) enum ReaderError {
+    Io(io::Error),
+    ParseDateTime(chrono::format::ParseError),
+    ParseIntegral(std::num::ParseIntError),
+    ParseFloat(std::num::ParseFloatError),
+    ParseUrl(url::ParseError),
+    ParseHost(common::types::ParseHostError),
+    ParseHttpRequest(common::types::ParseHttpRequestError),
+    ParseJson(json::JsonError),
+    NotAnObject,
+    FormatMismatch,
+    UnknownFieldCount(usize),
+}
+
+impl fmt::Display for ReaderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReaderError::Io(e) => write!(fmt, "{}", e),
+            ReaderError::ParseDateTime(e) => write!(fmt, "{}", e),
+            ReaderError::ParseIntegral(e) => write!(fmt, "{}", e),
+            ReaderError::ParseFloat(e) => write!(fmt, "{}", e),
+            ReaderError::ParseUrl(e) => write!(fmt, "{}", e),
+            ReaderError::ParseHost(e) => write!(fmt, "{}", e),
+            ReaderError::ParseHttpRequest(e) => write!(fmt, "{}", e),
+            ReaderError::ParseJson(e) => write!(fmt, "{}", e),
+            ReaderError::NotAnObject => fmt.write_str("Expect a JSON object per line"),
+            ReaderError::FormatMismatch => fmt.write_str("The line does not match the log format"),
+            ReaderError::UnknownFieldCount(n) => {
+                write!(fmt, "The line has {} fields, which no version of the format has", n)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReaderError::Io(e) => Some(e),
+            ReaderError::ParseDateTime(e) => Some(e),
+            ReaderError::ParseIntegral(e) => Some(e),
+            ReaderError::ParseFloat(e) => Some(e),
+            ReaderError::ParseUrl(e) => Some(e),
+            ReaderError::ParseHost(e) => Some(e),
+            ReaderError::ParseHttpRequest(e) => Some(e),
+            ReaderError::ParseJson(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReaderError {
+    fn from(err: io::Error) -> ReaderError {
+        ReaderError::Io(err)
+    }
+}
+
+impl From<chrono::format::ParseError> for ReaderError {
+    fn from(err: chrono::format::ParseError) -> ReaderError {
+        ReaderError::ParseDateTime(err)
+    }
+}
+
+impl From<std::num::ParseIntError> for ReaderError {
+    fn from(err: std::num::ParseIntError) -> ReaderError {
+        ReaderError::ParseIntegral(err)
+    }
+}
+
+impl From<std::num::ParseFloatError> for ReaderError {
+    fn from(err: std::num::ParseFloatError) -> ReaderError {
+        ReaderError::ParseFloat(err)
+    }
+}
+
+impl From<common::types::ParseHostError> for ReaderError {
+    fn from(err: common::types::ParseHostError) -> ReaderError {
+        ReaderError::ParseHost(err)
+    }
+}
+
+impl From<common::types::ParseHttpRequestError> for ReaderError {
+    fn from(err: common::types::ParseHttpRequestError) -> ReaderError {
+        ReaderError::ParseHttpRequest(err)
+    }
+}
+
+impl From<url::ParseError> for ReaderError {
+    fn from(err: url::ParseError) -> ReaderError {
+        ReaderError::ParseUrl(err)
+    }
+}
+
+impl From<json::JsonError> for ReaderError {
+    fn from(err: json::JsonError) -> ReaderError {
+        ReaderError::ParseJson(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ReaderBuilder {
+    capacity: usize,
+    file_format: String,
+    field_types: FieldTypes,
+    time_format: Option<String>,
+    log_format: Option<Box<LogFormat>>,
+    columns: Option<Vec<String>>,
+}
+
+pub(crate) trait RecordRead {
+    fn read_record(&mut self) -> ReaderResult<Option<Record>>;
+
+    fn metadata(&self) -> SourceMetadata;
+
+    //The line number from 1 and the byte offset of the line the last record or error is read from, none if the
+    //source has no lines, e.g. a converted copy
+    fn position(&self) -> Option<(usize, u64)>;
+}
+
+impl ReaderBuilder {
+    pub(crate) fn new(file_format: String) -> Self {
+        ReaderBuilder {
+            capacity: 8 * (1 << 10),
+            file_format: file_format,
+            field_types: FieldTypes::new(),
+            time_format: None,
+            log_format: None,
+            columns: None,
+        }
+    }
+
+    pub(crate) fn field_types(mut self, field_types: FieldTypes) -> Self {
+        self.field_types = field_types;
+        self
+    }
+
+    pub(crate) fn time_format(mut self, time_format: Option<String>) -> Self {
+        self.time_format = time_format;
+        self
+    }
+
+    pub(crate) fn log_format(mut self, log_format: Option<Box<LogFormat>>) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
+    //The columns read from a converted copy, the log file itself is parsed whole
+    pub(crate) fn columns(mut self, columns: Option<Vec<String>>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub(crate) fn columns_to_read(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+
+    pub(crate) fn with_path<P: AsRef<Path>>(&self, path: P) -> ReaderResult<Reader<FileSource>> {
+        self.with_source(FileSource::open(path.as_ref(), self.capacity)?)
+    }
+
+    pub(crate) fn with_tail(&self, path: &Path) -> ReaderResult<Reader<TailSource>> {
+        self.with_source(TailSource::open(path, self.capacity)?)
+    }
+
+    //Prefers the converted copy of the log file made by the "convert" subcommand
+    pub(crate) fn open(&self, path: &Path) -> ReaderResult<Box<dyn RecordRead>> {
+        match columnar::open_converted(self, path)? {
+            Some(reader) => Ok(Box::new(reader)),
+            None => Ok(Box::new(self.with_path(path)?)),
+        }
+    }
+
+    pub(crate) fn signature(&self) -> String {
+        format!(
+            "{}|{:?}|{:?}|{:?}",
+            self.file_format,
+            self.field_types,
+            self.time_format,
+            self.log_format.as_ref().map(|f| f.signature())
+        )
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn with_reader<R: io::Read>(&self, rdr: R) -> Reader<ReadSource<R>> {
+        Reader::new(
+            self,
+            ReadSource::new("reader", rdr, self.capacity),
+            self.file_format.clone(),
+        )
+    }
+
+    pub(crate) fn with_stdin(&self) -> Reader<ReadSource<io::Stdin>> {
+        Reader::new(self, ReadSource::stdin(self.capacity), self.file_format.clone())
+    }
+
+    pub(crate) fn with_command(&self, command: &str) -> ReaderResult<Reader<ReadSource<CommandOutput>>> {
+        self.with_source(ReadSource::command(command, self.capacity)?)
+    }
+
+    pub(crate) fn with_source<S: Source>(&self, source: S) -> ReaderResult<Reader<S>> {
+        Ok(Reader::new(self, source, self.file_format.clone()))
+    }
+}
+
+fn json_to_data_model(parsed: &JsonValue) -> Value {
+    match parsed {
+        json::JsonValue::Object(o) => {
+            let t: LinkedHashMap<String, Value> =
+                o.iter().map(|(k, v)| (k.to_string(), json_to_data_model(v))).collect();
+            Value::Object(t)
+        }
+        json::JsonValue::Array(a) => {
+            let a: Vec<Value> = a.iter().map(|v| json_to_data_model(v)).collect();
+            Value::Array(a)
+        }
+        json::JsonValue::Null => Value::Null,
+        json::JsonValue::String(s) => Value::String(s.clone()),
+        json::JsonValue::Short(s) => Value::String(s.to_string()),
+        json::JsonValue::Boolean(b) => Value::Boolean(*b),
+        json::JsonValue::Number(n) => {
+            let fixed = n.as_fixed_point_i64(4).unwrap();
+
+            if fixed % 10000 == 0 {
+                Value::Int((fixed / 10000) as i32)
+            } else {
+                let f: f32 = fixed as f32 / 10000.0;
+                Value::Float(OrderedFloat::from(f))
+            }
+        }
+    }
+}
+
+fn parse_datetime(s: &str, format: &str) -> ReaderResult<chrono::DateTime<chrono::FixedOffset>> {
+    let s = s.trim_matches(|c| c == '[' || c == ']');
+    match chrono::DateTime::parse_from_str(s, format) {
+        Ok(dt) => Ok(dt),
+        Err(_) => {
+            //The format without timezone is treated as UTC
+            let naive = chrono::NaiveDateTime::parse_from_str(s, format)?;
+            Ok(chrono::DateTime::from_utc(naive, chrono::FixedOffset::east(0)))
+        }
+    }
+}
+
+fn parse_field(s: &str, datatype: &DataType) -> ReaderResult<Value> {
+    match datatype {
+        DataType::DateTime | DataType::FormattedDateTime(_) if s == "-" => Ok(Value::Null),
+        DataType::DateTime => {
+            let dt = chrono::DateTime::parse_from_rfc3339(s)?;
+            Ok(Value::DateTime(dt))
+        }
+        DataType::FormattedDateTime(format) => {
+            let dt = parse_datetime(s, format)?;
+            Ok(Value::DateTime(dt))
+        }
+        DataType::String => Ok(Value::String(s.to_string())),
+        DataType::QuotedString => {
+            let s = s.trim_matches('"');
+            if s == "-" {
+                Ok(Value::Null)
+            } else {
+                Ok(Value::String(s.to_string()))
+            }
+        }
+        DataType::QuotedList => {
+            let s = s.trim_matches('"');
+            if s == "-" {
+                return Ok(Value::Null);
+            }
+
+            //The values stay in the order of the targets, "-" is the target without a response
+            let values = s
+                .split_whitespace()
+                .map(|v| match v {
+                    "-" => Value::Null,
+                    v => Value::String(v.to_string()),
+                })
+                .collect();
+            Ok(Value::Array(values))
+        }
+        DataType::Integral => {
+            if s == "-" {
+                return Ok(Value::Null);
+            }
+
+            let i_val = s.parse::<i32>()?;
+            Ok(Value::Int(i_val))
+        }
+        DataType::Float => {
+            if s == "-" {
+                return Ok(Value::Null);
+            }
+
+            let f = s.parse::<f32>()?;
+            Ok(Value::Float(OrderedFloat::from(f)))
+        }
+        DataType::Host => {
+            if s == "-" {
+                Ok(Value::Null)
+            } else {
+                let host = common::types::parse_host(s)?;
+                Ok(Value::Host(host))
+            }
+        }
+        DataType::HttpRequest => {
+            let s = s.trim_matches('"');
+            if s == "-" {
+                return Ok(Value::Null);
+            }
+
+            let request = common::types::parse_http_request(s)?;
+            Ok(Value::HttpRequest(request))
+        }
+        DataType::Json => {
+            let parsed = json::parse(s)?;
+            Ok(json_to_data_model(&parsed))
+        }
+    }
+}
+
+//The request line of a bad request, e.g. of a scanner, is kept as it is in the logs of the web servers and the proxies
+fn parse_logged_field(s: &str, datatype: &DataType) -> ReaderResult<Value> {
+    match (parse_field(s, datatype), datatype) {
+        (Err(_), DataType::HttpRequest) => Ok(Value::String(s.to_string())),
+        (value, _) => value,
+    }
+}
+
+//The structured data of RFC5424, e.g. [exampleSDID@32473 iut="3" eventSource="Application"], as the object of the params
+//by the SD-ID, the escaped '"', '\\' and ']' of the values unescaped
+fn parse_structured_data(s: &str) -> Value {
+    if s == "-" {
+        return Value::Null;
+    }
+
+    let mut elements = LinkedHashMap::new();
+    for element in SYSLOG_SD_ELEMENT_REGEX.captures_iter(s) {
+        let mut params = LinkedHashMap::new();
+        for param in SYSLOG_SD_PARAM_REGEX.captures_iter(&element[2]) {
+            let mut value = String::new();
+            let mut chars = param[2].chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some(next @ ('"' | '\\' | ']'))) => {
+                        value.push(next);
+                        chars.next();
+                    }
+                    _ => value.push(c),
+                }
+            }
+            params.insert(param[1].to_string(), Value::String(value));
+        }
+        elements.insert(element[1].to_string(), Value::Object(params));
+    }
+
+    Value::Object(elements)
+}
+
+//The timestamp of RFC3164 like "Oct  5 22:14:15" is in UTC of the year which does not put it more than a day ahead
+fn syslog_timestamp(s: &str) -> ReaderResult<Value> {
+    if s.as_bytes()[0].is_ascii_digit() {
+        return parse_field(s, &DataType::DateTime);
+    }
+
+    let now = chrono::Utc::now();
+    let year = chrono::Datelike::year(&now);
+    let s = s.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mut dt = parse_datetime(&format!("{} {}", year, s), "%Y %b %d %H:%M:%S")?;
+    if dt > now + chrono::Duration::days(1) {
+        dt = parse_datetime(&format!("{} {}", year - 1, s), "%Y %b %d %H:%M:%S")?;
+    }
+
+    Ok(Value::DateTime(dt))
+}
+
+fn split_delimited(line: &str, delimiter: u8) -> ReaderResult<Vec<String>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .quoting(delimiter == b',')
+        .from_reader(line.as_bytes());
+    match rdr.records().next() {
+        Some(Ok(record)) => Ok(record.iter().map(|s| s.to_string()).collect()),
+        Some(Err(_)) => Err(ReaderError::FormatMismatch),
+        None => Ok(Vec::new()),
+    }
+}
+
+//The type of a value of a csv column without an annotation
+fn infer_value(s: &str) -> Value {
+    let looks_numeric = s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.');
+    if let (true, Ok(i)) = (looks_numeric, s.parse::<i32>()) {
+        Value::Int(i)
+    } else if let (true, Ok(f)) = (looks_numeric, s.parse::<f32>()) {
+        Value::Float(OrderedFloat::from(f))
+    } else if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+        Value::DateTime(dt)
+    } else if let Ok(dt) = parse_datetime(s, "%Y-%m-%d %H:%M:%S%.f") {
+        Value::DateTime(dt)
+    } else {
+        Value::String(s.to_string())
+    }
+}
+
+//The generic reader of a JSON object per line, the annotated fields are converted to their types
+fn json_object(parsed: &JsonValue, field_types: &FieldTypes) -> ReaderResult<Variables> {
+    match json_to_data_model(parsed) {
+        Value::Object(o) => {
+            let mut o = nest_dotted_keys(o);
+            for (field_name, datatype) in field_types.iter() {
+                let path: Vec<&str> = field_name.split('.').collect();
+                if let Some(value) = nested_value_mut(&mut o, &path) {
+                    *value = annotate_value(value.clone(), datatype)?;
+                }
+            }
+
+            Ok(o)
+        }
+        _ => Err(ReaderError::NotAnObject),
+    }
+}
+
+//The flat keys with dots, e.g. "log.level" of the Elastic Common Schema, are the same as the nested objects, so that
+//both are queried as log.level. A key clashing with a value of the nested objects is kept as it is.
+fn nest_dotted_keys(o: Variables) -> Variables {
+    let is_dotted = |key: &str| key.contains('.') && !key.split('.').any(|segment| segment.is_empty());
+    if !o.keys().any(|key| is_dotted(key)) {
+        return o;
+    }
+
+    let (dotted, mut nested): (Variables, Variables) = o.into_iter().partition(|(key, _)| is_dotted(key));
+    for (key, value) in dotted.into_iter() {
+        let path: Vec<&str> = key.split('.').collect();
+        if let Some(value) = insert_nested(&mut nested, &path, value) {
+            nested.insert(key, value);
+        }
+    }
+
+    nested
+}
+
+//The value is given back if the path clashes with a value already there
+fn insert_nested(o: &mut Variables, path: &[&str], value: Value) -> Option<Value> {
+    match path {
+        [key] if !o.contains_key(*key) => {
+            o.insert(key.to_string(), value);
+            None
+        }
+        [key, rest @ ..] if !rest.is_empty() => {
+            match o
+                .entry(key.to_string())
+                .or_insert_with(|| Value::Object(Variables::default()))
+            {
+                Value::Object(child) => insert_nested(child, rest, value),
+                _ => Some(value),
+            }
+        }
+        _ => Some(value),
+    }
+}
+
+fn nested_value_mut<'a>(o: &'a mut Variables, path: &[&str]) -> Option<&'a mut Value> {
+    match path {
+        [] => None,
+        [key] => o.get_mut(*key),
+        [key, rest @ ..] => match o.get_mut(*key) {
+            Some(Value::Object(child)) => nested_value_mut(child, rest),
+            _ => None,
+        },
+    }
+}
+
+fn annotate_value(value: Value, datatype: &DataType) -> ReaderResult<Value> {
+    match (value, datatype) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::String(s), _) => parse_field(&s, datatype),
+        (Value::Int(i), DataType::Float) => Ok(Value::Float(OrderedFloat::from(i as f32))),
+        (Value::Int(i), DataType::String) => Ok(Value::String(i.to_string())),
+        (Value::Float(f), DataType::String) => Ok(Value::String(f.to_string())),
+        (Value::Int(i), _) => parse_field(&i.to_string(), datatype),
+        (Value::Float(f), _) => parse_field(&f.to_string(), datatype),
+        (other, _) => Ok(other),
+    }
+}
+
+pub(crate) struct Reader<S> {
+    source: S,
+    file_format: String,
+    field_types: FieldTypes,
+    time_format: Option<String>,
+    log_format: Option<LogFormat>,
+    //The columns named by the last header line, e.g. #Fields of the W3C formats
+    header_columns: Option<Vec<Option<usize>>>,
+    //The schema of the formats without a fixed layout, e.g. IIS, taken from the last header line
+    header_schema: Vec<(String, DataType)>,
+    //The records of a JSON document read at once, e.g. the Records array of CloudTrail
+    pending: VecDeque<Record>,
+    //The number of fields of the last line, which tells the version of the formats AWS appended fields to
+    variant_width: Option<usize>,
+    lines_read: usize,
+    bytes_read: u64,
+    position: (usize, u64),
+}
+
+impl<S: Source> Reader<S> {
+    pub(crate) fn new(builder: &ReaderBuilder, source: S, file_format: String) -> Reader<S> {
+        let field_types = builder
+            .field_types
+            .iter()
+            .map(|(field_name, datatype)| match (datatype, &builder.time_format) {
+                (DataType::DateTime, Some(format)) => (field_name.clone(), DataType::FormattedDateTime(format.clone())),
+                _ => (field_name.clone(), datatype.clone()),
+            })
+            .collect();
+        let log_format = builder
+            .log_format
+            .as_deref()
+            .or_else(|| LogFormat::default_of(&file_format))
+            .cloned();
+
+        Reader {
+            source,
+            file_format,
+            field_types,
+            time_format: builder.time_format.clone(),
+            log_format,
+            header_columns: None,
+            header_schema: Vec::new(),
+            pending: VecDeque::new(),
+            variant_width: None,
+            lines_read: 0,
+            bytes_read: 0,
+            position: (0, 0),
+        }
+    }
+
+    //Every line is read through here to keep the position of the last one
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let n = self.source.read_line(buf)?;
+        if n > 0 {
+            self.lines_read += 1;
+            self.position = (self.lines_read, self.bytes_read);
+            self.bytes_read += n as u64;
+        }
+
+        Ok(n)
+    }
+
+    fn is_time_field(&self, idx: usize) -> bool {
+        match &*self.file_format {
+            "elb" => idx == ClassicLoadBalancerLogField::Timestamp as usize,
+            "alb" => idx == ApplicationLoadBalancerLogField::Timestamp as usize,
+            "nlb" => idx == NetworkLoadBalancerLogField::Timestamp as usize,
+            "alb_conn" => idx == ApplicationLoadBalancerConnectionLogField::Timestamp as usize,
+            "s3" => idx == S3Field::Time as usize,
+            "squid" => idx == SquidLogField::Timestamp as usize,
+            _ => false,
+        }
+    }
+
+    //The W3C formats like the CloudFront access log are tab separated, with the #Version and #Fields header lines
+    //which can appear again in the middle of the stream when the log files are concatenated.
+    fn read_w3c_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        loop {
+            let line = buf.trim_end_matches(['\r', '\n']);
+            if let Some(header) = line.strip_prefix("#Fields:") {
+                self.header_columns = Some(columns_from_header(&CLOUDFRONT_W3C_FIELD_NAMES, header));
+            } else if !line.is_empty() && !line.starts_with('#') {
+                break;
+            }
+
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let field_names = CloudFrontField::field_names();
+        let mut values = vec![Value::Null; CloudFrontField::len()];
+        let line = buf.trim_end_matches(['\r', '\n']);
+        parse_columns(
+            line.split('\t'),
+            self.header_columns.as_ref(),
+            &CLOUDFRONT_W3C_FIELD_NAMES,
+            CloudFrontField::datatype,
+            &mut values,
+        )?;
+
+        let timestamp_idx = CloudFrontField::Timestamp as usize;
+        if let (Value::String(date), Value::String(time)) = (
+            &values[CloudFrontField::Date as usize],
+            &values[CloudFrontField::Time as usize],
+        ) {
+            let dt = parse_datetime(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")?;
+            values[timestamp_idx] = Value::DateTime(dt);
+        }
+
+        let record_vars = field_names.iter().cloned().zip(values).collect();
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    //The fields are separated by spaces, the spaces in a value are written as "+"
+    fn read_iis_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        loop {
+            let line = buf.trim_end_matches(['\r', '\n']);
+            if let Some(header) = line.strip_prefix("#Fields:") {
+                self.header_schema = iis_schema(header);
+            } else if !line.is_empty() && !line.starts_with('#') {
+                break;
+            }
+
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        if self.header_schema.is_empty() {
+            self.header_schema = iis_schema(IIS_DEFAULT_FIELDS);
+        }
+
+        let mut record_vars = Variables::default();
+        let mut tokens = buf.split_whitespace();
+        for (name, datatype) in self.header_schema.iter() {
+            let value = match tokens.next() {
+                Some(s) => parse_field(s, datatype)?,
+                None => Value::Null,
+            };
+            record_vars.insert(name.clone(), value);
+        }
+
+        //The date and the time are in UTC
+        let timestamp = match (record_vars.get("date"), record_vars.get("time")) {
+            (Some(Value::String(date)), Some(Value::String(time))) => {
+                Value::DateTime(parse_datetime(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")?)
+            }
+            _ => Value::Null,
+        };
+        record_vars.insert("timestamp".to_string(), timestamp);
+
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    //The first line of a csv or tsv table is the header naming the columns. The values of the columns without a type
+    //annotation are inferred, an integer, a float, a timestamp or otherwise a string, and an empty value is null.
+    fn read_delimited_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        let delimiter = if self.file_format == "tsv" { b'\t' } else { b',' };
+        loop {
+            //A quoted value of a csv may span lines, the tsv values are not quoted
+            while delimiter == b',' && buf.matches('"').count() % 2 == 1 {
+                if self.read_line(&mut buf)? == 0 {
+                    break;
+                }
+            }
+            if !buf.trim().is_empty() && !self.header_schema.is_empty() {
+                break;
+            }
+
+            if !buf.trim().is_empty() {
+                self.header_schema = split_delimited(&buf, delimiter)?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, name)| {
+                        let name = if name.trim().is_empty() {
+                            format!("_{}", i + 1)
+                        } else {
+                            name.trim().to_string()
+                        };
+                        let datatype = self
+                            .field_types
+                            .iter()
+                            .find(|(field_name, _)| *field_name == name)
+                            .map_or(DataType::String, |(_, datatype)| datatype.clone());
+                        (name, datatype)
+                    })
+                    .collect();
+            }
+
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let values = split_delimited(&buf, delimiter)?;
+        let mut record_vars = Variables::default();
+        for (i, (name, datatype)) in self.header_schema.iter().enumerate() {
+            let value = match values.get(i).map(|s| s.as_str()) {
+                None | Some("") => Value::Null,
+                Some(s) if self.field_types.iter().any(|(field_name, _)| field_name == name) => {
+                    parse_field(s, datatype)?
+                }
+                Some(s) => infer_value(s),
+            };
+            record_vars.insert(name.clone(), value);
+        }
+
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    fn cloudtrail_record(event: &JsonValue) -> ReaderResult<Record> {
+        let field_names = CloudTrailField::field_names();
+        let mut record_vars = flatten_json(event, &CLOUDTRAIL_JSON_PATHS, field_names);
+        let timestamp = match event["eventTime"].as_str() {
+            Some(s) => parse_field(s, &DataType::DateTime)?,
+            None => Value::Null,
+        };
+        record_vars.insert(field_names[CloudTrailField::Timestamp as usize].clone(), timestamp);
+        Ok(Record::new_with_variables(record_vars))
+    }
+
+    //A CloudTrail log file is one JSON document with the events in the Records array, often pretty-printed, so the
+    //whole input is read at once. The events one per line, e.g. exported from CloudWatch Logs, are read as well.
+    fn read_cloudtrail_record(&mut self) -> ReaderResult<Option<Record>> {
+        if let Some(record) = self.pending.pop_front() {
+            return Ok(Some(record));
+        }
+
+        let mut content = String::new();
+        while self.read_line(&mut content)? > 0 {}
+        if content.is_empty() {
+            return Ok(None);
+        }
+        //The events of the document all point to its start
+        self.position = (1, 0);
+
+        let documents = match json::parse(&content) {
+            Ok(parsed) => vec![parsed],
+            Err(_) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(json::parse)
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        for parsed in documents.iter() {
+            if parsed["Records"].is_array() {
+                for event in parsed["Records"].members() {
+                    self.pending.push_back(Reader::<S>::cloudtrail_record(event)?);
+                }
+            } else {
+                self.pending.push_back(Reader::<S>::cloudtrail_record(parsed)?);
+            }
+        }
+
+        Ok(self.pending.pop_front())
+    }
+
+    fn read_waf_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let parsed = json::parse(&buf)?;
+        let header = |name: &str| {
+            parsed["httpRequest"]["headers"]
+                .members()
+                .find(|h| h["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+                .and_then(|h| h["value"].as_str())
+                .map_or(Value::Null, |v| Value::String(v.to_string()))
+        };
+
+        let field_names = WafLogField::field_names();
+        let mut record_vars = flatten_json(&parsed, &WAF_JSON_PATHS, field_names);
+        //The milliseconds since the epoch, too large for the Int of the generic JSON conversion
+        let timestamp = parsed["timestamp"].as_i64().and_then(|ms| {
+            chrono::NaiveDateTime::from_timestamp_opt(ms / 1000, (ms % 1000) as u32 * 1_000_000)
+                .map(|naive| Value::DateTime(chrono::DateTime::from_utc(naive, chrono::FixedOffset::east(0))))
+        });
+        record_vars.insert(
+            field_names[WafLogField::Timestamp as usize].clone(),
+            timestamp.unwrap_or(Value::Null),
+        );
+        record_vars.insert(field_names[WafLogField::Host as usize].clone(), header("host"));
+        record_vars.insert(
+            field_names[WafLogField::UserAgent as usize].clone(),
+            header("user-agent"),
+        );
+
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    fn read_haproxy_record(&mut self, buf: String) -> ReaderResult<Option<Record>> {
+        let line = buf.trim_end_matches(['\n', '\r']);
+        let cap = HAPROXY_HTTP_LINE_REGEX
+            .captures(line)
+            .ok_or(ReaderError::FormatMismatch)?;
+        let mut values = Vec::with_capacity(HAPROXY_HTTP_DATATYPES.len());
+        for (i, datatype) in HAPROXY_HTTP_DATATYPES.iter().enumerate() {
+            values.push(parse_logged_field(
+                cap.get(i + 1).map_or("-", |m| m.as_str()),
+                datatype,
+            )?);
+        }
+
+        Ok(Some(Record::new(HaproxyHttpLogField::field_names(), values)))
+    }
+
+    fn read_syslog_record(&mut self, buf: String) -> ReaderResult<Option<Record>> {
+        let line = buf.trim_end_matches(['\n', '\r']);
+        let nil = |m: Option<regex::Match>| match m.map(|m| m.as_str()) {
+            None | Some("-") => Value::Null,
+            Some(s) => Value::String(s.to_string()),
+        };
+        let mut values = vec![Value::Null; SyslogField::field_names().len()];
+        let priority = if let Some(cap) = SYSLOG_RFC5424_LINE_REGEX.captures(line) {
+            values[SyslogField::Version as usize] = parse_field(&cap[2], &DataType::Integral)?;
+            values[SyslogField::Timestamp as usize] = parse_field(&cap[3], &DataType::DateTime)?;
+            values[SyslogField::Hostname as usize] = nil(cap.get(4));
+            values[SyslogField::AppName as usize] = nil(cap.get(5));
+            values[SyslogField::Procid as usize] = nil(cap.get(6));
+            values[SyslogField::Msgid as usize] = nil(cap.get(7));
+            values[SyslogField::StructuredData as usize] = parse_structured_data(&cap[8]);
+            //The message in UTF-8 starts with the BOM
+            let message = cap.get(9).map(|m| m.as_str().trim_start_matches('\u{feff}'));
+            values[SyslogField::Message as usize] = message.map_or(Value::Null, |m| Value::String(m.to_string()));
+            cap.get(1).map(|m| m.as_str().to_string())
+        } else {
+            let cap = SYSLOG_RFC3164_LINE_REGEX
+                .captures(line)
+                .ok_or(ReaderError::FormatMismatch)?;
+            values[SyslogField::Timestamp as usize] = syslog_timestamp(&cap[2])?;
+            values[SyslogField::Hostname as usize] = nil(cap.get(3));
+            values[SyslogField::AppName as usize] = nil(cap.get(4));
+            values[SyslogField::Procid as usize] = nil(cap.get(5));
+            values[SyslogField::Message as usize] = Value::String(cap[6].to_string());
+            cap.get(1).map(|m| m.as_str().to_string())
+        };
+
+        if let Some(priority) = priority {
+            let priority = priority.parse::<i32>()?;
+            values[SyslogField::Priority as usize] = Value::Int(priority);
+            values[SyslogField::Facility as usize] = Value::Int(priority / 8);
+            values[SyslogField::Severity as usize] = Value::Int(priority % 8);
+        }
+
+        Ok(Some(Record::new(SyslogField::field_names(), values)))
+    }
+
+    fn read_traefik_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let field_names = TraefikLogField::field_names();
+        if !buf.trim_start().starts_with('{') {
+            let line = buf.trim_end_matches(['\n', '\r']);
+            let cap = TRAEFIK_CLF_LINE_REGEX
+                .captures(line)
+                .ok_or(ReaderError::FormatMismatch)?;
+            let mut values = vec![Value::Null; field_names.len()];
+            for (i, idx) in TRAEFIK_CLF_COLUMNS.iter().enumerate() {
+                //The captures of the quoted values are without the quotes already
+                values[*idx] = match (
+                    cap.get(i + 1).map_or("-", |m| m.as_str()),
+                    TraefikLogField::datatype(*idx),
+                ) {
+                    ("-", _) => Value::Null,
+                    (s, DataType::QuotedString) => Value::String(s.to_string()),
+                    (s, datatype) => parse_logged_field(s, &datatype)?,
+                };
+            }
+
+            return Ok(Some(Record::new(field_names, values)));
+        }
+
+        let parsed = json::parse(&buf)?;
+        let mut record_vars = flatten_json(&parsed, &TRAEFIK_JSON_PATHS, field_names);
+        for (idx, field_name) in field_names.iter().enumerate() {
+            let value = record_vars.remove(field_name).unwrap_or(Value::Null);
+            let value = match (value, TraefikLogField::datatype(idx)) {
+                (Value::String(s), DataType::QuotedString) => parse_field(&s, &DataType::QuotedString)?,
+                (value, datatype) => annotate_value(value, &datatype)?,
+            };
+            record_vars.insert(field_name.clone(), value);
+        }
+
+        let timestamp = match parsed["StartUTC"].as_str() {
+            Some(s) => parse_field(s, &DataType::DateTime)?,
+            None => Value::Null,
+        };
+        record_vars.insert(field_names[TraefikLogField::Timestamp as usize].clone(), timestamp);
+
+        let request = match (parsed["RequestMethod"].as_str(), parsed["RequestPath"].as_str()) {
+            (Some(method), Some(path)) => {
+                let protocol = parsed["RequestProtocol"].as_str().unwrap_or("HTTP/1.1");
+                parse_logged_field(&format!("{} {} {}", method, path, protocol), &DataType::HttpRequest)?
+            }
+            _ => Value::Null,
+        };
+        record_vars.insert(field_names[TraefikLogField::Request as usize].clone(), request);
+
+        for (field, key) in [
+            (TraefikLogField::DurationMs, "Duration"),
+            (TraefikLogField::OriginDurationMs, "OriginDuration"),
+        ] {
+            let duration = parsed[key]
+                .as_f64()
+                .map_or(Value::Null, |ns| Value::Float(OrderedFloat::from((ns / 1e6) as f32)));
+            record_vars.insert(field_names[field as usize].clone(), duration);
+        }
+
+        let values = field_names
+            .iter()
+            .map(|name| record_vars.remove(name).unwrap_or(Value::Null))
+            .collect();
+        Ok(Some(Record::new(field_names, values)))
+    }
+
+    fn read_fastly_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let field_names = FastlyLogField::field_names();
+        let line = buf.trim_end_matches(['\n', '\r']);
+        let (prefix, message) = match FASTLY_SYSLOG_PREFIX_REGEX.captures(line) {
+            Some(cap) => {
+                let header = (
+                    cap.get(1).map_or("-", |m| m.as_str()),
+                    cap.get(2).map_or("-", |m| m.as_str()),
+                );
+                (Some(header), &line[cap.get(0).unwrap().end()..])
+            }
+            None => (None, line),
+        };
+
+        let mut values = if !message.trim_start().starts_with('{') {
+            let cap = FASTLY_CLF_LINE_REGEX
+                .captures(message)
+                .ok_or(ReaderError::FormatMismatch)?;
+            let mut values = vec![Value::Null; field_names.len()];
+            for (i, idx) in FASTLY_CLF_COLUMNS.iter().enumerate() {
+                values[*idx] = match cap.get(i + 1).map_or("-", |m| m.as_str()) {
+                    "-" => Value::Null,
+                    s => parse_logged_field(s, &FastlyLogField::datatype(*idx))?,
+                };
+            }
+            values
+        } else {
+            let parsed = json::parse(message)?;
+            let mut record_vars = flatten_json(&parsed, &FASTLY_JSON_PATHS, field_names);
+            let mut values = Vec::with_capacity(field_names.len());
+            for (idx, field_name) in field_names.iter().enumerate() {
+                let value = match record_vars.remove(field_name).unwrap_or(Value::Null) {
+                    Value::String(s) if s == "-" || s == "(null)" => Value::Null,
+                    value => annotate_value(value, &FastlyLogField::datatype(idx))?,
+                };
+                values.push(value);
+            }
+
+            //The strftime of the recommended format has the offset without the colon
+            values[FastlyLogField::Timestamp as usize] = match parsed["timestamp"].as_str() {
+                Some(s) => match chrono::DateTime::parse_from_rfc3339(s) {
+                    Ok(dt) => Value::DateTime(dt),
+                    Err(_) => Value::DateTime(parse_datetime(s, "%Y-%m-%dT%H:%M:%S%z")?),
+                },
+                None => Value::Null,
+            };
+            values[FastlyLogField::Request as usize] = match (parsed["request_method"].as_str(), parsed["url"].as_str())
+            {
+                (Some(method), Some(url)) => {
+                    let protocol = parsed["request_protocol"].as_str().unwrap_or("HTTP/1.1");
+                    parse_logged_field(&format!("{} {} {}", method, url, protocol), &DataType::HttpRequest)?
+                }
+                _ => Value::Null,
+            };
+            values
+        };
+
+        if let Some((cache_server, service)) = prefix {
+            if values[FastlyLogField::CacheServer as usize] == Value::Null {
+                values[FastlyLogField::CacheServer as usize] = Value::String(cache_server.to_string());
+            }
+            if values[FastlyLogField::Service as usize] == Value::Null {
+                values[FastlyLogField::Service as usize] = Value::String(service.to_string());
+            }
+        }
+
+        Ok(Some(Record::new(field_names, values)))
+    }
+
+    fn azure_appgw_record(parsed: &JsonValue) -> ReaderResult<Record> {
+        let field_names = AzureAppGatewayField::field_names();
+        let mut record_vars = flatten_json(parsed, &AZURE_APPGW_JSON_PATHS, field_names);
+        let mut values = Vec::with_capacity(field_names.len());
+        for (idx, field_name) in field_names.iter().enumerate() {
+            //The fields without a value are written as "-" or empty strings, e.g. the sslCipher without TLS
+            let value = match record_vars.remove(field_name).unwrap_or(Value::Null) {
+                Value::String(s) if s.is_empty() || s == "-" => Value::Null,
+                value => annotate_value(value, &AzureAppGatewayField::datatype(idx))?,
+            };
+            values.push(value);
+        }
+
+        //The time taken in seconds of the v2 SKU has more decimals than the generic JSON conversion keeps
+        if let Some(time_taken) = parsed["properties"]["timeTaken"].as_f64() {
+            values[AzureAppGatewayField::TimeTaken as usize] = Value::Float(OrderedFloat::from(time_taken as f32));
+        }
+
+        Ok(Record::new(field_names, values))
+    }
+
+    fn read_azure_appgw_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let parsed = json::parse(&buf)?;
+        if !parsed["records"].is_array() {
+            return Ok(Some(Reader::<S>::azure_appgw_record(&parsed)?));
+        }
+
+        for record in parsed["records"].members() {
+            self.pending.push_back(Reader::<S>::azure_appgw_record(record)?);
+        }
+        match self.pending.pop_front() {
+            Some(record) => Ok(Some(record)),
+            None => self.read_record(),
+        }
+    }
+
+    fn read_caddy_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let parsed = json::parse(&buf)?;
+        let mut o = json_object(&parsed, &self.field_types)?;
+        //The ts is in seconds since the epoch by default, too precise for a Float, or a string of the time_format
+        o.remove("ts");
+        let timestamp = match (parsed["ts"].as_f64(), parsed["ts"].as_str()) {
+            (Some(ts), _) => chrono::NaiveDateTime::from_timestamp_opt(ts.floor() as i64, (ts.fract() * 1e9) as u32)
+                .map_or(Value::Null, |naive| {
+                    Value::DateTime(chrono::DateTime::from_utc(naive, chrono::FixedOffset::east(0)))
+                }),
+            (None, Some(s)) => parse_field(s, &DataType::DateTime)?,
+            (None, None) => Value::Null,
+        };
+        o.insert(
+            CaddyLogField::field_names()[CaddyLogField::Timestamp as usize].clone(),
+            timestamp,
+        );
+        //The sub-millisecond durations need more than the four decimal places of the generic conversion
+        if let Some(duration) = parsed["duration"].as_f64() {
+            let field_name = &CaddyLogField::field_names()[CaddyLogField::Duration as usize];
+            o.insert(field_name.clone(), Value::Float(OrderedFloat::from(duration as f32)));
+        }
+
+        let mut record_vars = Variables::default();
+        for (idx, field_name) in CaddyLogField::field_names().iter().enumerate() {
+            let value = match o.remove(field_name) {
+                Some(value) => annotate_value(value, &CaddyLogField::datatype(idx))?,
+                None => Value::Null,
+            };
+            record_vars.insert(field_name.clone(), value);
+        }
+        record_vars.extend(o);
+
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    fn read_route53_resolver_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let parsed = json::parse(&buf)?;
+        let field_names = Route53ResolverField::field_names();
+        let mut record_vars = flatten_json(&parsed, &ROUTE53_RESOLVER_JSON_PATHS, field_names);
+        let timestamp = match parsed["query_timestamp"].as_str() {
+            Some(s) => parse_field(s, &DataType::DateTime)?,
+            None => Value::Null,
+        };
+        record_vars.insert(field_names[Route53ResolverField::Timestamp as usize].clone(), timestamp);
+        //The port is written as a string
+        if let Some(s) = parsed["srcport"].as_str() {
+            record_vars.insert(
+                field_names[Route53ResolverField::Srcport as usize].clone(),
+                parse_field(s, &DataType::Integral)?,
+            );
+        }
+
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    fn read_apigateway_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let mut parsed = json::parse(&buf)?;
+        if parsed["responseLatency"].is_null() {
+            parsed["responseLatency"] = parsed["latency"].take();
+        }
+
+        let field_names = ApiGatewayField::field_names();
+        let mut record_vars = flatten_json(&parsed, &APIGATEWAY_JSON_PATHS, field_names);
+        for (idx, field_name) in field_names.iter().enumerate() {
+            let value = record_vars.remove(field_name).unwrap_or(Value::Null);
+            //The $context variables without a value are written as "-"
+            let value = match value {
+                Value::String(s) if s == "-" => Value::Null,
+                value => annotate_value(value, &ApiGatewayField::datatype(idx))?,
+            };
+            record_vars.insert(field_name.clone(), value);
+        }
+
+        if record_vars[&field_names[ApiGatewayField::Timestamp as usize]] == Value::Null {
+            //The $context.requestTimeEpoch in milliseconds
+            let timestamp = parsed["requestTimeEpoch"]
+                .as_i64()
+                .or_else(|| parsed["requestTimeEpoch"].as_str().and_then(|s| s.parse::<i64>().ok()))
+                .and_then(|ms| chrono::NaiveDateTime::from_timestamp_opt(ms / 1000, (ms % 1000) as u32 * 1_000_000))
+                .map_or(Value::Null, |naive| {
+                    Value::DateTime(chrono::DateTime::from_utc(naive, chrono::FixedOffset::east(0)))
+                });
+            record_vars.insert(field_names[ApiGatewayField::Timestamp as usize].clone(), timestamp);
+        }
+
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    //The job may write the timestamps as RFC 3339 strings, or as the seconds or the nanoseconds since the epoch
+    fn read_cloudflare_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        while buf.trim().is_empty() {
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let parsed = json::parse(&buf)?;
+        let field_names = CloudflareField::field_names();
+        let mut record_vars = flatten_json(&parsed, &CLOUDFLARE_JSON_PATHS, field_names);
+        for (idx, field_name) in field_names.iter().enumerate() {
+            let value = record_vars.remove(field_name).unwrap_or(Value::Null);
+            //The fields without a value are empty strings, e.g. the OriginIP of a cached response
+            let value = match value {
+                Value::String(s) if s.is_empty() => Value::Null,
+                value => annotate_value(value, &CloudflareField::datatype(idx))?,
+            };
+            record_vars.insert(field_name.clone(), value);
+        }
+
+        let start = &parsed["EdgeStartTimestamp"];
+        let timestamp = match (start.as_str(), start.as_i64()) {
+            (Some(s), _) => parse_field(s, &DataType::DateTime)?,
+            (None, Some(n)) => {
+                let (secs, nanos) = if n > 100_000_000_000 {
+                    (n / 1_000_000_000, (n % 1_000_000_000) as u32)
+                } else {
+                    (n, 0)
+                };
+                chrono::NaiveDateTime::from_timestamp_opt(secs, nanos).map_or(Value::Null, |naive| {
+                    Value::DateTime(chrono::DateTime::from_utc(naive, chrono::FixedOffset::east(0)))
+                })
+            }
+            _ => Value::Null,
+        };
+        record_vars.insert(field_names[CloudflareField::Timestamp as usize].clone(), timestamp);
+
+        //The OriginResponseTime in nanoseconds is replaced by OriginResponseDurationMs in the newer jobs
+        let origin_response_time = match (
+            parsed["OriginResponseDurationMs"].as_f64(),
+            parsed["OriginResponseTime"].as_f64(),
+        ) {
+            (Some(ms), _) => Value::Float(OrderedFloat::from(ms as f32)),
+            (None, Some(ns)) => Value::Float(OrderedFloat::from((ns / 1e6) as f32)),
+            _ => Value::Null,
+        };
+        record_vars.insert(
+            field_names[CloudflareField::OriginResponseTimeMs as usize].clone(),
+            origin_response_time,
+        );
+        if record_vars[&field_names[CloudflareField::SecurityAction as usize]] == Value::Null {
+            let waf_action = json_to_data_model(&parsed["WAFAction"]);
+            record_vars.insert(
+                field_names[CloudflareField::SecurityAction as usize].clone(),
+                waf_action,
+            );
+        }
+
+        let values = field_names
+            .iter()
+            .map(|name| record_vars.remove(name).unwrap_or(Value::Null))
+            .collect();
+        Ok(Some(Record::new(field_names, values)))
+    }
+
+    //The flow logs delivered to S3 start with a header line of the field names, which also tells the custom format
+    fn read_vpc_flow_record(&mut self, mut buf: String) -> ReaderResult<Option<Record>> {
+        loop {
+            let columns = columns_from_header(&VPC_FLOW_HEADER_NAMES, &buf);
+            if !columns.is_empty() && columns.iter().all(Option::is_some) {
+                self.header_columns = Some(columns);
+            } else if !buf.trim().is_empty() {
+                break;
+            }
+
+            buf.clear();
+            if self.read_line(&mut buf)? == 0 {
+                return Ok(None);
+            }
+        }
+
+        let field_names = VpcFlowLogField::field_names();
+        let mut values = vec![Value::Null; VpcFlowLogField::len()];
+        parse_columns(
+            buf.split_whitespace(),
+            self.header_columns.as_ref(),
+            &VPC_FLOW_HEADER_NAMES[..VpcFlowLogField::LogStatus as usize + 1],
+            VpcFlowLogField::datatype,
+            &mut values,
+        )?;
+
+        if let Value::Int(start) = values[VpcFlowLogField::Start as usize] {
+            let naive = chrono::NaiveDateTime::from_timestamp(i64::from(start), 0);
+            let dt = chrono::DateTime::from_utc(naive, chrono::FixedOffset::east(0));
+            values[VpcFlowLogField::Timestamp as usize] = Value::DateTime(dt);
+        }
+
+        let record_vars = field_names.iter().cloned().zip(values).collect();
+        Ok(Some(Record::new_with_variables(record_vars)))
+    }
+
+    //The lines of an ALB log have the fields of one of the versions, the older ones are read with the newer fields as
+    //null. The fields appended by a version newer than the schema are dropped with a warning once per file.
+    fn check_alb_width(&mut self, width: usize, known: usize) -> ReaderResult<()> {
+        if width <= known && !ALB_VARIANT_WIDTHS.contains(&width) {
+            return Err(ReaderError::UnknownFieldCount(width));
+        }
+
+        //Warned on the first of the lines of the newer version, the rest of the file is likely the same
+        if width > known && !matches!(self.variant_width, Some(variant_width) if variant_width > known) {
+            common::trace::warning(format_args!(
+                "the ALB log has {} more fields than the {} known ones, they are dropped",
+                width - known,
+                known
+            ));
+        }
+        self.variant_width = Some(width);
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    fn close(&self) {}
 }
 
-impl FromStr for ApplicationLoadBalancerLogField {
-    type Err = String;
+impl<S: Source> RecordRead for Reader<S> {
+    fn metadata(&self) -> SourceMetadata {
+        self.source.metadata()
+    }
 
-    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
-        match s {
-            "type" => Ok(ApplicationLoadBalancerLogField::Type),
-            "timestamp" => Ok(ApplicationLoadBalancerLogField::Timestamp),
-            "elbname" => Ok(ApplicationLoadBalancerLogField::Elbname),
-            "client_and_port" => Ok(ApplicationLoadBalancerLogField::ClientAndPort),
-            "backend_and_port" => Ok(ApplicationLoadBalancerLogField::TargetAndPort),
-            "request_processing_time" => Ok(ApplicationLoadBalancerLogField::RequestProcessingTime),
-            "backend_processing_time" => Ok(ApplicationLoadBalancerLogField::TargetProcessingTime),
-            "response_processing_time" => Ok(ApplicationLoadBalancerLogField::ResponseProcessingTime),
-            "elb_status_code" => Ok(ApplicationLoadBalancerLogField::ELBStatusCode),
-            "backend_status_code" => Ok(ApplicationLoadBalancerLogField::TargetStatusCode),
-            "received_bytes" => Ok(ApplicationLoadBalancerLogField::ReceivedBytes),
-            "sent_bytes" => Ok(ApplicationLoadBalancerLogField::SentBytes),
-            "request" => Ok(ApplicationLoadBalancerLogField::Request),
-            "user_agent" => Ok(ApplicationLoadBalancerLogField::UserAgent),
-            "ssl_cipher" => Ok(ApplicationLoadBalancerLogField::SSLCipher),
-            "ssl_protocol" => Ok(ApplicationLoadBalancerLogField::SSLProtocol),
-            "target_group_arn" => Ok(ApplicationLoadBalancerLogField::TargetGroupArn),
-            "trace_id" => Ok(ApplicationLoadBalancerLogField::TraceID),
-            "domain_name" => Ok(ApplicationLoadBalancerLogField::DomainName),
-            "chosen_cert_arn" => Ok(ApplicationLoadBalancerLogField::ChosenCertArn),
-            "matched_rule_priority" => Ok(ApplicationLoadBalancerLogField::MatchedRulePriority),
-            "request_creation_time" => Ok(ApplicationLoadBalancerLogField::RequestCreationTime),
-            "action_executed" => Ok(ApplicationLoadBalancerLogField::ActionExecuted),
-            "redirect_url" => Ok(ApplicationLoadBalancerLogField::RedirectUrl),
-            "error_reason" => Ok(ApplicationLoadBalancerLogField::ErrorReason),
-            _ => Err("unknown column name".to_string()),
+    fn position(&self) -> Option<(usize, u64)> {
+        Some(self.position)
+    }
+
+    fn read_record(&mut self) -> ReaderResult<Option<Record>> {
+        if self.file_format == "cloudtrail" {
+            return self.read_cloudtrail_record();
+        }
+        //The rest of the records of a JSON document read from one line
+        if let Some(record) = self.pending.pop_front() {
+            return Ok(Some(record));
+        }
+
+        let mut buf = String::new();
+        let more_data = self.read_line(&mut buf)?;
+
+        if more_data > 0 && self.file_format == "cloudfront" {
+            self.read_w3c_record(buf)
+        } else if more_data > 0 && self.file_format == "iis" {
+            self.read_iis_record(buf)
+        } else if more_data > 0 && (self.file_format == "csv" || self.file_format == "tsv") {
+            self.read_delimited_record(buf)
+        } else if more_data > 0 && self.file_format == "waf" {
+            self.read_waf_record(buf)
+        } else if more_data > 0 && self.file_format == "cloudflare" {
+            self.read_cloudflare_record(buf)
+        } else if more_data > 0 && self.file_format == "apigateway" {
+            self.read_apigateway_record(buf)
+        } else if more_data > 0 && self.file_format == "route53resolver" {
+            self.read_route53_resolver_record(buf)
+        } else if more_data > 0 && self.file_format == "vpcflow" {
+            self.read_vpc_flow_record(buf)
+        } else if more_data > 0 && self.file_format == "haproxy" {
+            self.read_haproxy_record(buf)
+        } else if more_data > 0 && self.file_format == "syslog" {
+            self.read_syslog_record(buf)
+        } else if more_data > 0 && self.file_format == "traefik" {
+            self.read_traefik_record(buf)
+        } else if more_data > 0 && self.file_format == "fastly" {
+            self.read_fastly_record(buf)
+        } else if more_data > 0 && self.file_format == "azure_appgw" {
+            self.read_azure_appgw_record(buf)
+        } else if more_data > 0 && self.file_format == "caddy" {
+            self.read_caddy_record(buf)
+        } else if more_data > 0 && self.file_format == "regex" {
+            let log_format = self.log_format.as_ref().ok_or(ReaderError::FormatMismatch)?;
+            let values = log_format.read_inferred_values(&buf, &self.field_types)?;
+            Ok(Some(Record::new(log_format.field_names(), values)))
+        } else if let (true, Some(log_format)) = (more_data > 0, self.log_format.as_ref()) {
+            let values = log_format.read_values(&buf)?;
+            Ok(Some(Record::new(log_format.field_names(), values)))
+        } else if more_data > 0 && self.file_format != "jsonl" {
+            let field_names = field_names_of(&self.file_format).unwrap();
+            let fields: Vec<&str> = SPLIT_READER_LINE_REGEX.find_iter(&buf).map(|m| m.as_str()).collect();
+            if self.file_format == "alb" {
+                self.check_alb_width(fields.len(), field_names.len())?;
+            }
+
+            let mut record_vars = common::types::Variables::default();
+            let mut value_cnt: usize = 0;
+
+            for (i, s) in fields.into_iter().enumerate() {
+                if i >= field_names.len() {
+                    break;
+                }
+
+                let datatype = match &self.time_format {
+                    Some(format) if self.is_time_field(i) => DataType::FormattedDateTime(format.clone()),
+                    _ => datatype_of(&self.file_format, i),
+                };
+
+                let value = parse_field(s, &datatype)?;
+                record_vars.insert(field_names[i].clone(), value);
+
+                value_cnt += 1;
+            }
+
+            //Adjust the width to be the same, the files written before AWS appended the newer fields have them as null
+            while value_cnt < field_names.len() {
+                record_vars.insert(field_names[value_cnt].clone(), Value::Null);
+                value_cnt += 1;
+            }
+
+            let record = Record::new_with_variables(record_vars);
+            Ok(Some(record))
+        } else if more_data > 0 && self.file_format == "jsonl" {
+            let parsed = json::parse(&buf)?;
+            let record = Record::new_with_variables(json_object(&parsed, &self.field_types)?);
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+//The virtual columns derived from the path of the log file, they are prefixed with "__" so that "select *" skips them.
+//The account, region, load balancer and end time are parsed from the AWS naming convention of the log files.
+pub(crate) fn path_columns(path_opt: Option<&Path>) -> Vec<(String, Value)> {
+    let file = path_opt.map_or(Value::Null, |p| Value::String(p.to_string_lossy().to_string()));
+    let mut columns = vec![
+        ("__file".to_string(), file),
+        ("__account_id".to_string(), Value::Null),
+        ("__region".to_string(), Value::Null),
+        ("__load_balancer".to_string(), Value::Null),
+        ("__end_time".to_string(), Value::Null),
+        ("__date".to_string(), Value::Null),
+    ];
+
+    let file_name = path_opt
+        .and_then(|p| p.file_name())
+        .map(|f| f.to_string_lossy().to_string());
+    if let Some(cap) = file_name.and_then(|f| {
+        AWS_LOG_FILE_NAME_REGEX.captures(&f).map(|c| {
+            (1..=4)
+                .map(|i| c.get(i).map_or("", |m| m.as_str()).to_string())
+                .collect::<Vec<String>>()
+        })
+    }) {
+        columns[1].1 = Value::String(cap[0].clone());
+        columns[2].1 = Value::String(cap[1].clone());
+        columns[3].1 = Value::String(cap[2].clone());
+        if let Ok(dt) = parse_datetime(&cap[3], "%Y%m%dT%H%MZ") {
+            columns[5].1 = Value::String(dt.format("%Y-%m-%d").to_string());
+            columns[4].1 = Value::DateTime(dt);
+        }
+    }
+
+    columns
+}
+
+//The root of the X-Amzn-Trace-Id, e.g. "Root=1-58337262-36d228ad5d99923122bbe354", which correlates the records
+//of the same request across the load balancer and the WAF logs
+pub(crate) fn trace_root(trace_id: &str) -> Option<&str> {
+    trace_id
+        .trim_matches('"')
+        .split(';')
+        .find_map(|part| part.strip_prefix("Root="))
+        .filter(|root| !root.is_empty())
+}
+
+//The classic load balancer stage at which a request failed, derived from the -1 timings and the status codes, see
+//https://docs.aws.amazon.com/elasticloadbalancing/latest/classic/ts-elb-error-message.html
+fn failure_stage(variables: &Variables) -> Option<&'static str> {
+    let is_unset = |field_name: &str| match variables.get(field_name) {
+        Some(Value::Float(f)) => f.into_inner() < 0.0,
+        Some(Value::Int(i)) => *i < 0,
+        Some(Value::String(s)) => s == "-",
+        _ => true,
+    };
+    let is_client_error = match variables.get("elb_status_code") {
+        Some(Value::String(s)) => s.starts_with('4'),
+        Some(Value::Int(i)) => (400..500).contains(i),
+        _ => false,
+    };
+
+    variables.get("backend_status_code")?;
+    if is_unset("request_processing_time") {
+        //The request never reached a backend, either the client or the connection to the backend failed
+        Some(if is_client_error {
+            "client-connect"
+        } else {
+            "backend-connect"
+        })
+    } else if is_unset("backend_processing_time") || is_unset("response_processing_time") {
+        Some("backend-response")
+    } else if is_unset("backend_status_code") {
+        Some(if is_client_error {
+            "client-connect"
+        } else {
+            "backend-connect"
+        })
+    } else {
+        Some("ok")
+    }
+}
+
+//Reference: https://grpc.github.io/grpc/core/md_doc_statuscodes.html
+const GRPC_STATUS_NAMES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+fn grpc_status_name(code: i32) -> Option<&'static str> {
+    if code < 0 {
+        None
+    } else {
+        GRPC_STATUS_NAMES.get(code as usize).copied()
+    }
+}
+
+//The ALB logs the gRPC requests with the type "grpcs" and the gRPC status code as the target status code
+fn grpc_status(variables: &Variables) -> Option<(Value, Value)> {
+    let target_status_code = variables.get("target_status_code")?;
+    let is_grpc = matches!(variables.get("type"), Some(Value::String(t)) if t.starts_with("grpc"));
+    let code = match target_status_code {
+        Value::String(s) if is_grpc => s.parse::<i32>().ok(),
+        _ => None,
+    };
+
+    Some(match code {
+        Some(code) => (
+            Value::Int(code),
+            grpc_status_name(code).map_or(Value::Null, |name| Value::String(name.to_string())),
+        ),
+        None => (Value::Null, Value::Null),
+    })
+}
+
+//The HTTP status class of the gRPC status, as the gRPC gateways map the codes to the HTTP statuses, e.g. NOT_FOUND to 404,
+//RESOURCE_EXHAUSTED to 429 and UNAVAILABLE to 503
+fn grpc_status_class(code: i32) -> Option<&'static str> {
+    match code {
+        0 => Some("2xx"),
+        1 | 3 | 5 | 6 | 7 | 8 | 9 | 10 | 11 | 16 => Some("4xx"),
+        2 | 4 | 12 | 13 | 14 | 15 => Some("5xx"),
+        _ => None,
+    }
+}
+
+//The class of the status of the load balancer, e.g. "5xx", from the gRPC status for the gRPC requests, which are 200
+//for the load balancer even if the call failed, and null for the requests without a response
+fn status_class(variables: &Variables) -> Option<Value> {
+    let elb_status_code = variables.get("elb_status_code")?;
+    let class = match grpc_status(variables) {
+        Some((Value::Int(code), _)) => grpc_status_class(code).map(|class| class.to_string()),
+        _ => match elb_status_code {
+            Value::String(s) if s.len() == 3 && (b'1'..=b'5').contains(&s.as_bytes()[0]) => {
+                Some(format!("{}xx", &s[..1]))
+            }
+            _ => None,
+        },
+    };
+    Some(class.map_or(Value::Null, Value::String))
+}
+
+//Whether the request upgraded the connection, e.g. to a WebSocket, by the ALB type "ws" or "wss", the ws:// or
+//wss:// url, or the status 101 Switching Protocols
+fn is_upgrade(variables: &Variables) -> Option<bool> {
+    let elb_status_code = variables.get("elb_status_code")?;
+    let is_upgrade_type = matches!(variables.get("type"), Some(Value::String(t)) if t == "ws" || t == "wss");
+    let is_upgrade_url = matches!(variables.get("request"), Some(Value::HttpRequest(r)) if r.url.scheme() == "ws" || r.url.scheme() == "wss");
+    let is_switching_protocols = ["backend_status_code", "target_status_code"]
+        .iter()
+        .filter_map(|field_name| variables.get(*field_name))
+        .chain(std::iter::once(elb_status_code))
+        .any(|status_code| matches!(status_code, Value::String(s) if s == "101"));
+
+    Some(is_upgrade_type || is_upgrade_url || is_switching_protocols)
+}
+
+//The class of the error reason of the ALB with a Lambda target, these errors don't show up in the target metrics, see
+//https://docs.aws.amazon.com/elasticloadbalancing/latest/application/load-balancer-access-logs.html#error-reason-codes
+fn lambda_error_class(error_reason: &str) -> &'static str {
+    match error_reason {
+        "LambdaUnhandled" => "function-error",
+        "LambdaInvalidResponse" | "LambdaResponseTooLarge" => "invalid-response",
+        "LambdaBadRequest" | "LambdaRequestTooLarge" => "invalid-request",
+        "LambdaThrottling" | "LambdaEC2ThrottledException" => "throttling",
+        "LambdaConnectionError" | "LambdaConnectionTimeout" => "connection",
+        "LambdaServiceException" | "LambdaEC2UnexpectedException" => "service",
+        r if r.contains("AccessDenied") => "permission",
+        r if r.starts_with("LambdaKMS")
+            || r.starts_with("LambdaInvalid")
+            || r.starts_with("LambdaENI")
+            || r == "LambdaResourceNotFound"
+            || r == "LambdaSubnetIPAddressLimitReachedException" =>
+        {
+            "configuration"
         }
+        _ => "other",
+    }
+}
+
+//The cache result of the squid status code, e.g. "hit" for TCP_MEM_HIT or TCP_REFRESH_UNMODIFIED, see
+//https://wiki.squid-cache.org/SquidFaq/SquidLogs#squid-result-codes
+fn squid_cache_result(squid_status: &str) -> &'static str {
+    let tags: Vec<&str> = squid_status.split('_').collect();
+    let has = |tag: &str| tags.contains(&tag);
+    if has("DENIED") {
+        "denied"
+    } else if has("TUNNEL") {
+        "tunnel"
+    } else if has("HIT") || has("UNMODIFIED") || (has("FAIL") && has("OLD")) {
+        "hit"
+    } else if has("MISS") || has("MODIFIED") || has("FAIL") {
+        "miss"
+    } else {
+        "other"
     }
 }
 
-impl ApplicationLoadBalancerLogField {
-    pub(crate) fn len() -> usize {
-        25
-    }
+//The columns of the "TCP_MISS/200" and "HIER_DIRECT/10.0.0.1" fields of the native squid layout, the same as the ones
+//read by the %Ss/%>Hs and %Sh/%<a codes of a log format
+fn squid_columns(variables: &Variables) -> Vec<(String, Value)> {
+    let split = |field_name: &str| match variables.get(field_name) {
+        Some(Value::String(s)) => {
+            let mut parts = s.splitn(2, '/');
+            let code = parts.next().map(|c| c.to_string());
+            (code, parts.next().map(|v| v.to_string()))
+        }
+        _ => (None, None),
+    };
+    let string_or_null = |s: Option<String>| match s {
+        Some(s) if s != "-" && !s.is_empty() => Value::String(s),
+        _ => Value::Null,
+    };
 
-    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
-        &AWS_ALB_FIELD_NAMES
+    let mut columns = Vec::new();
+    if variables.contains_key("code_and_status") {
+        let (code, status) = split("code_and_status");
+        let status = status
+            .and_then(|s| s.parse::<i32>().ok())
+            .map_or(Value::Null, Value::Int);
+        columns.push(("squid_status".to_string(), string_or_null(code)));
+        columns.push(("status".to_string(), status));
     }
-
-    pub(crate) fn datatypes() -> Vec<DataType> {
-        AWS_ALB_DATATYPES.clone()
+    if variables.contains_key("peer_status_and_peer_host") {
+        let (code, host) = split("peer_status_and_peer_host");
+        columns.push(("hierarchy_status".to_string(), string_or_null(code)));
+        columns.push(("peer_host".to_string(), string_or_null(host)));
     }
 
-    pub(crate) fn datatype(idx: usize) -> DataType {
-        AWS_ALB_DATATYPES[idx].clone()
-    }
+    columns
+}
 
-    pub(crate) fn schema() -> Vec<(String, DataType)> {
-        let fields = Self::field_names().clone();
-        let datatypes = Self::datatypes();
-        fields.into_iter().zip(datatypes.into_iter()).collect()
+//The virtual columns derived from the fields of the record
+pub(crate) fn derived_columns(variables: &Variables) -> Vec<(String, Value)> {
+    let root = match variables.get("trace_id") {
+        Some(Value::String(trace_id)) => trace_root(trace_id).map_or(Value::Null, |r| Value::String(r.to_string())),
+        _ => Value::Null,
+    };
+    let mut columns = vec![("__trace_root".to_string(), root)];
+    if let Some(stage) = failure_stage(variables) {
+        columns.push(("__failure_stage".to_string(), Value::String(stage.to_string())));
+    }
+    match variables.get("error_reason") {
+        Some(Value::String(error_reason)) if error_reason.starts_with("Lambda") => {
+            let class = lambda_error_class(error_reason);
+            columns.push(("lambda_error_class".to_string(), Value::String(class.to_string())));
+        }
+        Some(_) => columns.push(("lambda_error_class".to_string(), Value::Null)),
+        None => {}
+    }
+    if let Some(upgrade) = is_upgrade(variables) {
+        columns.push(("is_upgrade".to_string(), Value::Boolean(upgrade)));
+    }
+    if let Some(class) = status_class(variables) {
+        columns.push(("__status_class".to_string(), class));
+    }
+    if let Some((status, name)) = grpc_status(variables) {
+        columns.push(("grpc_status".to_string(), status));
+        columns.push(("grpc_status_name".to_string(), name));
+    }
+    if let Some(client) = variables.get("client_and_port") {
+        let (ip, port) = match client {
+            Value::Host(h) => (Value::String(h.hostname.clone()), Value::Int(i32::from(h.port))),
+            _ => (Value::Null, Value::Null),
+        };
+        columns.push(("client_ip".to_string(), ip));
+        columns.push(("client_port".to_string(), port));
+    }
+    columns.extend(squid_columns(variables));
+    let squid_status = match variables.get("squid_status") {
+        Some(squid_status) => Some(squid_status),
+        None => columns.iter().find(|(k, _)| k == "squid_status").map(|(_, v)| v),
+    };
+    if let Some(squid_status) = squid_status {
+        let cache_result = match squid_status {
+            Value::String(s) => Value::String(squid_cache_result(s).to_string()),
+            _ => Value::Null,
+        };
+        columns.push(("cache_result".to_string(), cache_result));
     }
-}
 
-// https://docs.aws.amazon.com/AmazonS3/latest/dev/LogFormat.html
-pub(crate) enum S3Field {
-    BucketOwner = 0,
-    Bucket = 1,
-    Time = 2,
-    RemoteIp = 3,
-    Requester = 4,
-    RequestId = 5,
-    Operation = 6,
-    Key = 7,
-    RequestUri = 8,
-    HttpStatus = 9,
-    ErrorCode = 10,
-    BytesSent = 11,
-    ObjectSize = 12,
-    TotalTime = 13,
-    TurnAroundTime = 14,
-    Referrer = 15,
-    UserAgent = 16,
-    VersionId = 17,
-    HostId = 18,
-    SignatureVersion = 19,
-    CipherSuite = 20,
-    AuthenticationType = 21,
-    HostHeader = 22,
-    TlsVersion = 23,
+    columns
 }
 
-impl FromStr for S3Field {
-    type Err = String;
+//The log files under the directory in the path order, the hidden files and the converted copies are skipped
+pub(crate) fn list_log_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
 
-    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
-        match s {
-            "bucket_owner" => Ok(S3Field::BucketOwner),
-            "bucket" => Ok(S3Field::Bucket),
-            "time" => Ok(S3Field::Time),
-            "remote_ip" => Ok(S3Field::RemoteIp),
-            "requester" => Ok(S3Field::Requester),
-            "request_id" => Ok(S3Field::RequestId),
-            "operation" => Ok(S3Field::Operation),
-            "key" => Ok(S3Field::Key),
-            "request_uri" => Ok(S3Field::RequestUri),
-            "http_status" => Ok(S3Field::HttpStatus),
-            "error_code" => Ok(S3Field::ErrorCode),
-            "bytes_sent" => Ok(S3Field::BytesSent),
-            "object_size" => Ok(S3Field::ObjectSize),
-            "total_time" => Ok(S3Field::TotalTime),
-            "turn_around_time" => Ok(S3Field::TurnAroundTime),
-            "refererr" => Ok(S3Field::Referrer),
-            "user_agent" => Ok(S3Field::UserAgent),
-            "version_id" => Ok(S3Field::VersionId),
-            "host_id" => Ok(S3Field::HostId),
-            "signature_version" => Ok(S3Field::SignatureVersion),
-            "cipher_suite" => Ok(S3Field::CipherSuite),
-            "authentication_type" => Ok(S3Field::AuthenticationType),
-            "host_header" => Ok(S3Field::HostHeader),
-            "tls_version" => Ok(S3Field::TlsVersion),
-            _ => Err("unknown column name".to_string()),
+    for path in entries.into_iter() {
+        let hidden = path.file_name().is_some_and(|f| f.to_string_lossy().starts_with('.'));
+        if hidden || columnar::is_converted_path(&path) {
+            continue;
+        }
+
+        if path.is_dir() {
+            files.extend(list_log_files(&path)?);
+        } else {
+            files.push(path);
         }
     }
+
+    Ok(files)
 }
 
-impl S3Field {
-    pub(crate) fn len() -> usize {
-        24
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common;
+    use crate::execution::fixtures;
+    use crate::syntax::ast::{PathExpr, PathSegment};
+    use chrono;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_aws_elb_reader() {
+        let content = r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 200 200 0 42355 "GET https://example.com:443/ HTTP/1.1" "Mozilla/5.0 (Windows NT 5.1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/46.0.2490.80 Safari/537.36" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2"#;
+        let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = ClassicLoadBalancerLogField::field_names();
+        let data = vec![
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2015-11-07T18:45:33.559871Z").unwrap()),
+            Value::String("elb1".to_string()),
+            Value::Host(common::types::parse_host("78.168.134.92:4586").unwrap()),
+            Value::Host(common::types::parse_host("10.0.0.215:80").unwrap()),
+            Value::Float(OrderedFloat::from(0.000_036)),
+            Value::Float(OrderedFloat::from(0.001_035)),
+            Value::Float(OrderedFloat::from(0.000_025)),
+            Value::String("200".to_string()),
+            Value::String("200".to_string()),
+            Value::Int(0),
+            Value::Int(42355),
+            Value::HttpRequest(common::types::parse_http_request("GET https://example.com:443/ HTTP/1.1").unwrap()),
+            Value::String("\"Mozilla/5.0 (Windows NT 5.1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/46.0.2490.80 Safari/537.36\"".to_string()),
+            Value::String("ECDHE-RSA-AES128-GCM-SHA256".to_string()),
+            Value::String("TLSv1.2".to_string()),
+            Value::Null,
+            Value::Null
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
+
+        assert_eq!(expected, record);
+
+        let content = r#"2015-11-07T18:45:37.691548Z elb1 176.219.166.226:48384 10.0.2.143:80 0.000023 0.000348 0.000025 200 200 0 41690 "GET http://example.com:80/?mode=json&after=&iteration=1 HTTP/1.1" "Mozilla/5.0 (Linux; Android 5.1.1; Nexus 5 Build/LMY48I; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/46.0.2490.76 Mobile Safari/537.36 [FB_IAB/FB4A;FBAV/52.0.0.12.18;]" - - arn:aws:elasticloadbalancing:us-west-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354""#;
+        let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = ClassicLoadBalancerLogField::field_names();
+        let data = vec![
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2015-11-07T18:45:37.691548Z").unwrap()),
+            Value::String("elb1".to_string()),
+            Value::Host(common::types::parse_host("176.219.166.226:48384").unwrap()),
+            Value::Host(common::types::parse_host("10.0.2.143:80").unwrap()),
+            Value::Float(OrderedFloat::from(0.000_023)),
+            Value::Float(OrderedFloat::from(0.000_348)),
+            Value::Float(OrderedFloat::from(0.000_025)),
+            Value::String("200".to_string()),
+            Value::String("200".to_string()),
+            Value::Int(0),
+            Value::Int(41690),
+            Value::HttpRequest(common::types::parse_http_request("GET http://example.com:80/?mode=json&after=&iteration=1 HTTP/1.1").unwrap()),
+            Value::String("\"Mozilla/5.0 (Linux; Android 5.1.1; Nexus 5 Build/LMY48I; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/46.0.2490.76 Mobile Safari/537.36 [FB_IAB/FB4A;FBAV/52.0.0.12.18;]\"".to_string()),
+            Value::String("-".to_string()),
+            Value::String("-".to_string()),
+            Value::String("arn:aws:elasticloadbalancing:us-west-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067".to_string()),
+            Value::String("\"Root=1-58337262-36d228ad5d99923122bbe354\"".to_string()),
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
+
+        assert_eq!(expected, record)
+    }
+
+    #[test]
+    fn test_aws_alb_reader() {
+        let content = r#"http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 200 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-""#;
+        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = ApplicationLoadBalancerLogField::field_names();
+        let data = vec![
+            Value::String("http".to_string()),
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2018-07-02T22:23:00.186641Z").unwrap()),
+            Value::String("app/my-loadbalancer/50dc6c495c0c9188".to_string()),
+            Value::Host(common::types::parse_host("192.168.131.39:2817").unwrap()),
+            Value::Host(common::types::parse_host("10.0.0.1:80").unwrap()),
+            Value::Float(OrderedFloat::from(0.000)),
+            Value::Float(OrderedFloat::from(0.001)),
+            Value::Float(OrderedFloat::from(0.000)),
+            Value::String("200".to_string()),
+            Value::String("200".to_string()),
+            Value::Int(34),
+            Value::Int(366),
+            Value::HttpRequest(common::types::parse_http_request("GET http://www.example.com:80/ HTTP/1.1").unwrap()),
+            Value::String("\"curl/7.46.0\"".to_string()),
+            Value::String("-".to_string()),
+            Value::String("-".to_string()),
+            Value::String(
+                "arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067"
+                    .to_string(),
+            ),
+            Value::String("\"Root=1-58337262-36d228ad5d99923122bbe354\"".to_string()),
+            Value::Null,
+            Value::String("\"-\"".to_string()),
+            Value::Int(0),
+            Value::String("2018-07-02T22:22:48.364000Z".to_string()),
+            Value::String("forward".to_string()),
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Null,
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
+
+        assert_eq!(expected, record);
+
+        let content = r#"https 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.086 0.048 0.037 200 200 0 57 "GET https://www.example.com:443/ HTTP/1.1" "curl/7.46.0" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2 arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337281-1d84f3d73c47ec4e58577259" "www.example.com" "arn:aws:acm:us-east-2:123456789012:certificate/12345678-1234-1234-1234-123456789012" 1 2018-07-02T22:22:48.364000Z "authenticate,forward" "-" "-" "10.0.0.1:80" "200" "Ambiguous" "UndefinedContentLengthSemantics""#;
+        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let variables = record.to_variables();
+        assert_eq!(fields.len(), variables.len());
+        assert_eq!(
+            Some(&Value::String("authenticate,forward".to_string())),
+            variables.get("action_executed")
+        );
+        assert_eq!(
+            Some(&Value::Array(vec![Value::String("10.0.0.1:80".to_string())])),
+            variables.get("target_port_list")
+        );
+        assert_eq!(
+            Some(&Value::Array(vec![Value::String("200".to_string())])),
+            variables.get("target_status_code_list")
+        );
+        assert_eq!(
+            Some(&Value::String("Ambiguous".to_string())),
+            variables.get("classification")
+        );
+        assert_eq!(
+            Some(&Value::String("UndefinedContentLengthSemantics".to_string())),
+            variables.get("classification_reason")
+        );
+        assert_eq!(Some(&Value::Null), variables.get("conn_trace_id"));
+
+        let content = r#"https 2024-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.086 0.048 0.037 200 200 0 57 "GET https://www.example.com:443/ HTTP/1.1" "curl/7.46.0" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2 arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337281-1d84f3d73c47ec4e58577259" "www.example.com" "arn:aws:acm:us-east-2:123456789012:certificate/12345678-1234-1234-1234-123456789012" 1 2024-07-02T22:22:48.364000Z "forward" "-" "-" "10.0.0.1:80" "200" "-" "-" "TID_1234abcd5678ef90" "internal.example.com" "/v2/users" "TransformSuccess" "a-field-from-the-future""#;
+        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let variables = record.to_variables();
+        assert_eq!(fields.len(), variables.len());
+        assert_eq!(
+            Some(&Value::String("TID_1234abcd5678ef90".to_string())),
+            variables.get("conn_trace_id")
+        );
+        assert_eq!(
+            Some(&Value::String("internal.example.com".to_string())),
+            variables.get("transformed_host")
+        );
+        assert_eq!(
+            Some(&Value::String("/v2/users".to_string())),
+            variables.get("transformed_uri")
+        );
+        assert_eq!(
+            Some(&Value::String("TransformSuccess".to_string())),
+            variables.get("request_transform_status")
+        );
+
+        //A line of a width no version has is malformed rather than padded
+        let content = r#"http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 200 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward""#;
+        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        match reader.read_record() {
+            Err(ReaderError::UnknownFieldCount(23)) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_aws_s3_reader() {
+        let content = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be awsexamplebucket [06/Feb/2019:00:00:38 +0000] 192.0.2.3 79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be 3E57427F3EXAMPLE REST.GET.VERSIONING - "GET /awsexamplebucket?versioning HTTP/1.1" 200 - 113 - 7 - "-" "S3Console/0.4" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234= SigV2 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader awsexamplebucket.s3.amazonaws.com TLSV1.1"#;
+        let mut reader = ReaderBuilder::new("s3".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = S3Field::field_names();
+        let data = vec![
+            Value::String("79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be".to_string()),
+            Value::String("awsexamplebucket".to_string()),
+            Value::String("[06/Feb/2019:00:00:38 +0000]".to_string()),
+            Value::String("192.0.2.3".to_string()),
+            Value::String("79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be".to_string()),
+            Value::String("3E57427F3EXAMPLE".to_string()),
+            Value::String("REST.GET.VERSIONING".to_string()),
+            Value::String("-".to_string()),
+            Value::String("\"GET /awsexamplebucket?versioning HTTP/1.1\"".to_string()),
+            Value::String("200".to_string()),
+            Value::String("-".to_string()),
+            Value::String("113".to_string()),
+            Value::String("-".to_string()),
+            Value::Int(7),
+            Value::Null,
+            Value::String("\"-\"".to_string()),
+            Value::String("\"S3Console/0.4\"".to_string()),
+            Value::String("-".to_string()),
+            Value::String("s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234=".to_string()),
+            Value::String("SigV2".to_string()),
+            Value::String("ECDHE-RSA-AES128-GCM-SHA256".to_string()),
+            Value::String("AuthHeader".to_string()),
+            Value::String("awsexamplebucket.s3.amazonaws.com".to_string()),
+            Value::String("TLSV1.1".to_string()),
+            Value::Null,
+            Value::Null,
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
+
+        assert_eq!(expected, record);
+
+        let content = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be awsexamplebucket [06/Feb/2019:00:00:38 +0000] 192.0.2.3 79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be 3E57427F3EXAMPLE REST.GET.OBJECT photos/2019/08/puppy.jpg "GET /photos/2019/08/puppy.jpg HTTP/1.1" 200 - 113 113 7 6 "-" "S3Console/0.4" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234= SigV4 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader myap-111122223333.s3-accesspoint.us-west-2.amazonaws.com TLSv1.2 arn:aws:s3:us-west-2:111122223333:accesspoint/myap Yes"#;
+        let mut reader = ReaderBuilder::new("s3".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let variables = record.to_variables();
+        assert_eq!(
+            Some(&Value::String("TLSv1.2".to_string())),
+            variables.get("tls_version")
+        );
+        assert_eq!(
+            Some(&Value::String(
+                "arn:aws:s3:us-west-2:111122223333:accesspoint/myap".to_string()
+            )),
+            variables.get("access_point_arn")
+        );
+        assert_eq!(Some(&Value::String("Yes".to_string())), variables.get("acl_required"));
+    }
+
+    #[test]
+    fn test_squid_reader() {
+        let content = r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_DENIED/407 3922 CONNECT d.dropbox.com:443 - HIER_NONE/- text/html"#;
+        let mut reader = ReaderBuilder::new("squid".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = SquidLogField::field_names();
+        let data = vec![
+            Value::String("1515734740.494".to_string()),
+            Value::String("1".to_string()),
+            Value::String("[MASKEDIPADDRESS]".to_string()),
+            Value::String("TCP_DENIED/407".to_string()),
+            Value::String("3922".to_string()),
+            Value::String("CONNECT".to_string()),
+            Value::String("d.dropbox.com:443".to_string()),
+            Value::String("-".to_string()),
+            Value::String("HIER_NONE/-".to_string()),
+            Value::String("text/html".to_string()),
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
+
+        assert_eq!(expected, record);
 
-    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
-        &AWS_S3_FIELD_NAMES
-    }
+        let field = |record: &Record, name: &str| record.to_tuples().into_iter().find(|(k, _)| k == name).unwrap().1;
+        let log_format = LogFormat::parse("squid", "squid").unwrap();
+        let mut reader = ReaderBuilder::new("squid".to_string())
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2018-01-12T05:25:40.494Z").unwrap()),
+            field(&record, "timestamp")
+        );
+        assert_eq!(Value::Int(1), field(&record, "elapsed"));
+        assert_eq!(Value::String("TCP_DENIED".to_string()), field(&record, "squid_status"));
+        assert_eq!(Value::Int(407), field(&record, "status"));
+        assert_eq!(Value::Null, field(&record, "peer_host"));
 
-    pub(crate) fn datatypes() -> Vec<DataType> {
-        AWS_S3_DATATYPES.clone()
-    }
+        let content = r#"10.0.0.1 - alice [07/Jun/2019:18:45:33 +0000] "GET http://example.com/a HTTP/1.1" 200 512 "http://example.org/" "curl/7.64.1" TCP_MISS:HIER_DIRECT"#;
+        let log_format = LogFormat::parse("squid", "combined").unwrap();
+        let mut reader = ReaderBuilder::new("squid".to_string())
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = vec![
+            ("remote_host".to_string(), Value::String("10.0.0.1".to_string())),
+            ("user_ident".to_string(), Value::Null),
+            ("user_name".to_string(), Value::String("alice".to_string())),
+            (
+                "timestamp".to_string(),
+                Value::DateTime(chrono::DateTime::parse_from_rfc3339("2019-06-07T18:45:33Z").unwrap()),
+            ),
+            ("method".to_string(), Value::String("GET".to_string())),
+            ("url".to_string(), Value::String("http://example.com/a".to_string())),
+            ("protocol_version".to_string(), Value::String("1.1".to_string())),
+            ("status".to_string(), Value::Int(200)),
+            ("bytes".to_string(), Value::Int(512)),
+            ("referer".to_string(), Value::String("http://example.org/".to_string())),
+            ("user_agent".to_string(), Value::String("curl/7.64.1".to_string())),
+            ("squid_status".to_string(), Value::String("TCP_MISS".to_string())),
+            ("hierarchy_status".to_string(), Value::String("HIER_DIRECT".to_string())),
+        ];
+        assert_eq!(expected, record.to_tuples());
 
-    pub(crate) fn datatype(idx: usize) -> DataType {
-        AWS_S3_DATATYPES[idx].clone()
+        let log_format = LogFormat::parse("squid", "[%tl] %6tr %>a %{Content-Type}<h 100%%").unwrap();
+        let content = "[07/Jun/2019:18:45:33 +0000]     15 10.0.0.1 text/html 100%";
+        let mut reader = ReaderBuilder::new("squid".to_string())
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(15), field(&record, "elapsed"));
+        assert_eq!(
+            Value::String("text/html".to_string()),
+            field(&record, "reply_content_type")
+        );
+
+        assert!(LogFormat::parse("squid", "%>a %>a").is_err());
     }
 
-    pub(crate) fn schema() -> Vec<(String, DataType)> {
-        let fields = Self::field_names().clone();
-        let datatypes = Self::datatypes();
-        fields.into_iter().zip(datatypes.into_iter()).collect()
+    #[test]
+    fn test_aws_alb_conn_reader() {
+        let content = r#"2023-10-04T13:43:30.425823Z 192.0.2.1 29238 443 TLSv1.2 ECDHE-RSA-AES128-GCM-SHA256 3.047 "CN=client-cert,O=Client Inc.,ST=WA,C=US" NotBefore=2023-09-21T22:43:21Z;NotAfter=2026-06-17T22:43:21Z FEF257D9F0FC4A3F Success TID_1234abcd5678ef90"#;
+        let mut reader = ReaderBuilder::new("alb_conn".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = ApplicationLoadBalancerConnectionLogField::field_names();
+        let data = vec![
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2023-10-04T13:43:30.425823Z").unwrap()),
+            Value::String("192.0.2.1".to_string()),
+            Value::Int(29238),
+            Value::Int(443),
+            Value::String("TLSv1.2".to_string()),
+            Value::String("ECDHE-RSA-AES128-GCM-SHA256".to_string()),
+            Value::Float(OrderedFloat::from(3.047)),
+            Value::String("CN=client-cert,O=Client Inc.,ST=WA,C=US".to_string()),
+            Value::String("NotBefore=2023-09-21T22:43:21Z;NotAfter=2026-06-17T22:43:21Z".to_string()),
+            Value::String("FEF257D9F0FC4A3F".to_string()),
+            Value::String("Success".to_string()),
+            Value::String("TID_1234abcd5678ef90".to_string()),
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
+
+        assert_eq!(expected, record);
     }
-}
 
-//Reference: https://wiki.squid-cache.org/Features/LogFormat
-pub(crate) enum SquidLogField {
-    Timestamp = 0,
-    Elapsed = 1,
-    RemoteHost = 2,
-    CodeAndStatus = 3,
-    Bytes = 4,
-    Method = 5,
-    Url = 6,
-    Rfc931 = 7,
-    PeerstatusAndPeerhost = 8,
-    Type = 9,
-}
+    #[test]
+    fn test_nginx_reader() {
+        let content =
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "-" "curl/7.68.0""#;
+        let mut reader = ReaderBuilder::new("nginx".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = LogFormat::default_of("nginx").unwrap().field_names();
+        let data = vec![
+            Value::String("127.0.0.1".to_string()),
+            Value::Null,
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2000-10-10T13:55:36-07:00").unwrap()),
+            Value::HttpRequest(common::types::parse_http_request("GET /apache_pb.gif HTTP/1.0").unwrap()),
+            Value::Int(200),
+            Value::Int(2326),
+            Value::Null,
+            Value::String("curl/7.68.0".to_string()),
+        ];
+        assert_eq!(Some(Record::new(fields, data)), record);
 
-impl FromStr for SquidLogField {
-    type Err = String;
+        let log_format = LogFormat::parse(
+            "nginx",
+            r#"$remote_addr [$time_iso8601] "$request" $status $request_time ${upstream_addr}"#,
+        )
+        .unwrap();
+        let content = r#"10.0.0.1 [2000-10-10T13:55:36+00:00] "GET / HTTP/1.1" 200 0.004 10.0.1.1:80, 10.0.1.2:80"#;
+        let mut reader = ReaderBuilder::new("nginx".to_string())
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = vec![
+            ("remote_addr".to_string(), Value::String("10.0.0.1".to_string())),
+            (
+                "timestamp".to_string(),
+                Value::DateTime(chrono::DateTime::parse_from_rfc3339("2000-10-10T13:55:36Z").unwrap()),
+            ),
+            (
+                "request".to_string(),
+                Value::HttpRequest(common::types::parse_http_request("GET / HTTP/1.1").unwrap()),
+            ),
+            ("status".to_string(), Value::Int(200)),
+            ("request_time".to_string(), Value::Float(OrderedFloat::from(0.004))),
+            (
+                "upstream_addr".to_string(),
+                Value::String("10.0.1.1:80, 10.0.1.2:80".to_string()),
+            ),
+        ];
+        assert_eq!(expected, record.to_tuples());
 
-    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
-        match s {
-            "timestamp" => Ok(SquidLogField::Timestamp),
-            "elapsed" => Ok(SquidLogField::Elapsed),
-            "remote_host" => Ok(SquidLogField::RemoteHost),
-            "code_and_status" => Ok(SquidLogField::CodeAndStatus),
-            "bytes" => Ok(SquidLogField::Bytes),
-            "method" => Ok(SquidLogField::Method),
-            "url" => Ok(SquidLogField::Url),
-            "rfc931" => Ok(SquidLogField::Rfc931),
-            "peer_status_and_peer_host" => Ok(SquidLogField::PeerstatusAndPeerhost),
-            "type" => Ok(SquidLogField::Type),
-            _ => Err("unknown column name".to_string()),
-        }
+        assert!(LogFormat::parse("nginx", "$status $status").is_err());
+        assert!(LogFormat::parse("nginx", "static text").is_err());
     }
-}
 
-impl SquidLogField {
-    pub(crate) fn len() -> usize {
-        10
+    #[test]
+    fn test_ingress_nginx_reader() {
+        let content = r#"10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /api/items HTTP/1.1" 502 150 "-" "curl/7.68.0" 85 0.012 [default-api-80] [] 10.244.0.12:8080, 10.244.0.13:8080 0, 150 0.004, 0.008 502, 502 5d4b4c1d1b0f4d8e9e2b2c0a1f3e4d5c"#;
+        let mut reader =
+            ReaderBuilder::new("ingress_nginx".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let variables = record.to_variables();
+        assert_eq!(Some(&Value::Int(502)), variables.get("status"));
+        assert_eq!(Some(&Value::Int(85)), variables.get("request_length"));
+        assert_eq!(
+            Some(&Value::Float(OrderedFloat::from(0.012))),
+            variables.get("request_time")
+        );
+        assert_eq!(
+            Some(&Value::String("default-api-80".to_string())),
+            variables.get("proxy_upstream_name")
+        );
+        assert_eq!(
+            Some(&Value::String("".to_string())),
+            variables.get("proxy_alternative_upstream_name")
+        );
+        assert_eq!(
+            Some(&Value::String("10.244.0.12:8080, 10.244.0.13:8080".to_string())),
+            variables.get("upstream_addr")
+        );
+        assert_eq!(
+            Some(&Value::String("0.004, 0.008".to_string())),
+            variables.get("upstream_response_time")
+        );
+        assert_eq!(
+            Some(&Value::String("5d4b4c1d1b0f4d8e9e2b2c0a1f3e4d5c".to_string())),
+            variables.get("req_id")
+        );
     }
 
-    pub(crate) fn field_names<'a>() -> &'a Vec<String> {
-        &SQUID_FIELD_NAMES
-    }
+    #[test]
+    fn test_apache_reader() {
+        let content = r#"192.168.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /index.html?q=1 HTTP/1.0" 200 2326 "http://example.com/" "Mozilla/4.08" 1520
+192.168.0.1 - - [10/Oct/2000:13:55:37 -0700] "POST /login HTTP/1.1" 302 -
+192.168.0.1 - - [10/Oct/2000:13:55:38 -0700] "" 400 226 "-" "-"
+"#;
+        let field = |record: &Record, name: &str| record.to_tuples().into_iter().find(|(k, _)| k == name).unwrap().1;
+        let mut reader = ReaderBuilder::new("apache".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = vec![
+            ("remote_host".to_string(), Value::String("192.168.0.1".to_string())),
+            ("remote_logname".to_string(), Value::Null),
+            ("remote_user".to_string(), Value::String("frank".to_string())),
+            (
+                "timestamp".to_string(),
+                Value::DateTime(chrono::DateTime::parse_from_rfc3339("2000-10-10T13:55:36-07:00").unwrap()),
+            ),
+            (
+                "request".to_string(),
+                Value::HttpRequest(common::types::parse_http_request("GET /index.html?q=1 HTTP/1.0").unwrap()),
+            ),
+            ("status".to_string(), Value::Int(200)),
+            ("bytes_sent".to_string(), Value::Int(2326)),
+            ("referer".to_string(), Value::String("http://example.com/".to_string())),
+            ("user_agent".to_string(), Value::String("Mozilla/4.08".to_string())),
+            ("request_time_us".to_string(), Value::Int(1520)),
+        ];
+        assert_eq!(expected, record.to_tuples());
 
-    pub(crate) fn datatypes() -> Vec<DataType> {
-        SQUID_DATATYPES.clone()
+        //The common format has none of the trailing fields
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(302), field(&record, "status"));
+        assert_eq!(Value::Null, field(&record, "bytes_sent"));
+        assert_eq!(Value::Null, field(&record, "user_agent"));
+        assert_eq!(Value::Null, field(&record, "request_time_us"));
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::String(r"".to_string()), field(&record, "request"));
+        assert_eq!(None, reader.read_record().unwrap());
+
+        let log_format = LogFormat::parse("apache", r#"%v:%p %a %t "%r" %>s %T 100%%"#).unwrap();
+        let content = r#"example.com:443 10.0.0.1 [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 2 100%"#;
+        let mut reader = ReaderBuilder::new("apache".to_string())
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(443), field(&record, "server_port"));
+        assert_eq!(Value::Int(2), field(&record, "request_time"));
+
+        assert!(LogFormat::parse("apache", "%h %{%Y}t").is_err());
+        assert!(LogFormat::parse("apache", "%s %s").is_err());
     }
 
-    pub(crate) fn datatype(idx: usize) -> DataType {
-        SQUID_DATATYPES[idx].clone()
+    #[test]
+    fn test_haproxy_reader() {
+        let content = r#"Feb  6 12:14:14 localhost haproxy[14389]: 10.0.1.2:33317 [06/Feb/2009:12:14:14.655] http-in static/srv1 10/0/30/69/+109 200 2750 - - ---- 1/1/1/1/0 0/0 {1wt.eu} {} "GET /index.html HTTP/1.1""#;
+        let mut reader = ReaderBuilder::new("haproxy".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let tuples = record.to_tuples();
+        let field = |name: &str| tuples.iter().find(|(k, _)| k == name).unwrap().1.clone();
+        assert_eq!(HaproxyHttpLogField::field_names().len(), tuples.len());
+        assert_eq!(Value::String("10.0.1.2".to_string()), field("client_ip"));
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2009-02-06T12:14:14.655Z").unwrap()),
+            field("timestamp")
+        );
+        assert_eq!(Value::String("static".to_string()), field("backend_name"));
+        assert_eq!(Value::String("srv1".to_string()), field("server_name"));
+        assert_eq!(Value::Int(69), field("tr"));
+        assert_eq!(Value::Int(109), field("tt"));
+        assert_eq!(Value::Int(200), field("status_code"));
+        assert_eq!(Value::Null, field("captured_request_cookie"));
+        assert_eq!(Value::String("----".to_string()), field("termination_state"));
+        assert_eq!(Value::Int(0), field("backend_queue"));
+        assert_eq!(Value::String("1wt.eu".to_string()), field("captured_request_headers"));
+        assert_eq!(
+            Value::HttpRequest(common::types::parse_http_request("GET /index.html HTTP/1.1").unwrap()),
+            field("request")
+        );
+
+        let content = "Feb  6 12:14:18 localhost haproxy[14389]: Proxy http-in started.";
+        let mut reader = ReaderBuilder::new("haproxy".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        assert!(reader.read_record().is_err());
     }
 
-    pub(crate) fn schema() -> Vec<(String, DataType)> {
-        let fields = Self::field_names().clone();
-        let datatypes = Self::datatypes();
-        fields.into_iter().zip(datatypes.into_iter()).collect()
+    #[test]
+    fn test_syslog_reader() {
+        let content = "<34>Oct  5 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8\n\
+                       <165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut=\"3\"] \u{feff}An application event log entry\n";
+        let mut reader = ReaderBuilder::new("syslog".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let variables = record.to_variables();
+        assert_eq!(SyslogField::field_names().len(), variables.len());
+        assert_eq!(Some(&Value::Int(4)), variables.get("facility"));
+        assert_eq!(Some(&Value::Int(2)), variables.get("severity"));
+        assert_eq!(Some(&Value::Null), variables.get("version"));
+        assert_eq!(Some(&Value::String("su".to_string())), variables.get("app_name"));
+        match variables.get("timestamp") {
+            Some(Value::DateTime(dt)) => {
+                assert_eq!("10-05 22:14:15", dt.format("%m-%d %H:%M:%S").to_string());
+                assert!(*dt <= chrono::Utc::now() + chrono::Duration::days(1));
+                assert!(*dt > chrono::Utc::now() - chrono::Duration::days(366));
+            }
+            v => panic!("unexpected timestamp {:?}", v),
+        }
+
+        let record = reader.read_record().unwrap().unwrap();
+        let variables = record.to_variables();
+        assert_eq!(Some(&Value::Int(1)), variables.get("version"));
+        assert_eq!(Some(&Value::Null), variables.get("procid"));
+        assert_eq!(Some(&Value::String("ID47".to_string())), variables.get("msgid"));
+        assert_eq!(
+            Some(&Value::String("An application event log entry".to_string())),
+            variables.get("message")
+        );
+        let mut params = LinkedHashMap::new();
+        params.insert("iut".to_string(), Value::String("3".to_string()));
+        let mut structured_data = LinkedHashMap::new();
+        structured_data.insert("exampleSDID@32473".to_string(), Value::Object(params));
+        assert_eq!(Some(&Value::Object(structured_data)), variables.get("structured_data"));
     }
-}
 
-pub(crate) type ReaderResult<T> = result::Result<T, ReaderError>;
+    #[test]
+    fn test_traefik_reader() {
+        let content = r#"192.168.1.10 - - [10/Oct/2023:13:55:36 +0000] "GET /api/users HTTP/1.1" 200 1534 "-" "curl/8.1.2" 42 "api@docker" "http://172.17.0.3:8080" 12ms
+{"ClientHost":"192.168.1.10","ClientPort":"52810","DownstreamStatus":404,"Duration":1500000,"RequestMethod":"GET","RequestPath":"/missing","RequestProtocol":"HTTP/2.0","RouterName":"api@docker","StartUTC":"2023-10-10T13:55:40Z"}"#;
+        let mut reader = ReaderBuilder::new("traefik".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let field = |record: &Record, name: &str| record.to_tuples().into_iter().find(|(k, _)| k == name).unwrap().1;
 
-#[derive(Fail, Debug)]
-pub(crate) enum ReaderError {
-    #[fail(display = "{}", _0)]
-    Io(#[cause] io::Error),
-    #[fail(display = "{}", _0)]
-    ParseDateTime(#[cause] chrono::format::ParseError),
-    #[fail(display = "{}", _0)]
-    ParseIntegral(#[cause] std::num::ParseIntError),
-    #[fail(display = "{}", _0)]
-    ParseFloat(#[cause] std::num::ParseFloatError),
-    #[fail(display = "{}", _0)]
-    ParseUrl(#[cause] url::ParseError),
-    #[fail(display = "{}", _0)]
-    ParseHost(#[cause] common::types::ParseHostError),
-    #[fail(display = "{}", _0)]
-    ParseHttpRequest(#[cause] common::types::ParseHttpRequestError),
-    #[fail(display = "{}", _0)]
-    ParseJson(#[cause] json::JsonError),
-}
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(TraefikLogField::field_names().len(), record.to_tuples().len());
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2023-10-10T13:55:36Z").unwrap()),
+            field(&record, "timestamp")
+        );
+        assert_eq!(Value::Int(200), field(&record, "downstream_status"));
+        assert_eq!(Value::Null, field(&record, "referer"));
+        assert_eq!(Value::String("curl/8.1.2".to_string()), field(&record, "user_agent"));
+        assert_eq!(Value::String("api@docker".to_string()), field(&record, "router_name"));
+        assert_eq!(Value::Float(OrderedFloat::from(12.0)), field(&record, "duration_ms"));
+        assert_eq!(Value::Null, field(&record, "client_port"));
 
-impl From<io::Error> for ReaderError {
-    fn from(err: io::Error) -> ReaderError {
-        ReaderError::Io(err)
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            TraefikLogField::field_names(),
+            &record.to_tuples().into_iter().map(|(k, _)| k).collect::<Vec<_>>()
+        );
+        assert_eq!(Value::Int(52810), field(&record, "client_port"));
+        assert_eq!(Value::Int(404), field(&record, "downstream_status"));
+        assert_eq!(
+            Value::HttpRequest(common::types::parse_http_request("GET /missing HTTP/2.0").unwrap()),
+            field(&record, "request")
+        );
+        assert_eq!(Value::Float(OrderedFloat::from(1.5)), field(&record, "duration_ms"));
+        assert!(reader.read_record().unwrap().is_none());
     }
-}
 
-impl From<chrono::format::ParseError> for ReaderError {
-    fn from(err: chrono::format::ParseError) -> ReaderError {
-        ReaderError::ParseDateTime(err)
-    }
-}
+    #[test]
+    fn test_azure_appgw_reader() {
+        let content = r#"{"records":[{"resourceId":"/SUBSCRIPTIONS/ID/RESOURCEGROUPS/RG/PROVIDERS/MICROSOFT.NETWORK/APPLICATIONGATEWAYS/GW","operationName":"ApplicationGatewayAccess","time":"2021-05-05T09:46:26.1234567Z","category":"ApplicationGatewayAccessLog","properties":{"instanceId":"appgw_1","clientIP":"192.0.2.1","clientPort":46886,"httpMethod":"GET","requestUri":"/index.html","requestQuery":"","userAgent":"curl/7.68.0","httpStatus":200,"httpVersion":"HTTP/1.1","receivedBytes":65,"sentBytes":553,"timeTaken":0.088,"sslEnabled":"on","sslCipher":"ECDHE-RSA-AES256-GCM-SHA384","sslProtocol":"TLSv1.2","serverRouted":"10.0.0.4:443","serverStatus":"200","serverResponseLatency":"0.084","host":"www.contoso.com"}},{"time":"2021-05-05T09:46:27Z","properties":{"httpStatus":502,"serverRouted":"-","sslCipher":""}}]}
+{"time":"2021-05-05T09:46:28Z","properties":{"httpStatus":404,"timeTaken":205}}
+"#;
+        let mut reader = ReaderBuilder::new("azure_appgw".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
 
-impl From<std::num::ParseIntError> for ReaderError {
-    fn from(err: std::num::ParseIntError) -> ReaderError {
-        ReaderError::ParseIntegral(err)
-    }
-}
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2021-05-05T09:46:26.1234567Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(Value::String("192.0.2.1".to_string()), record.get(&path("client_ip")));
+        assert_eq!(Value::Int(200), record.get(&path("http_status")));
+        assert_eq!(Value::Int(200), record.get(&path("server_status")));
+        assert_eq!(Value::Float(OrderedFloat::from(0.088)), record.get(&path("time_taken")));
+        assert_eq!(Value::String("TLSv1.2".to_string()), record.get(&path("ssl_protocol")));
+        assert_eq!(
+            Value::String("10.0.0.4:443".to_string()),
+            record.get(&path("server_routed"))
+        );
+        assert_eq!(Value::Null, record.get(&path("request_query")));
 
-impl From<std::num::ParseFloatError> for ReaderError {
-    fn from(err: std::num::ParseFloatError) -> ReaderError {
-        ReaderError::ParseFloat(err)
-    }
-}
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(502), record.get(&path("http_status")));
+        assert_eq!(Value::Null, record.get(&path("server_routed")));
+        assert_eq!(Value::Null, record.get(&path("ssl_cipher")));
 
-impl From<common::types::ParseHostError> for ReaderError {
-    fn from(err: common::types::ParseHostError) -> ReaderError {
-        ReaderError::ParseHost(err)
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Float(OrderedFloat::from(205.0)), record.get(&path("time_taken")));
+        assert!(reader.read_record().unwrap().is_none());
     }
-}
 
-impl From<common::types::ParseHttpRequestError> for ReaderError {
-    fn from(err: common::types::ParseHttpRequestError) -> ReaderError {
-        ReaderError::ParseHttpRequest(err)
+    #[test]
+    fn test_fastly_reader() {
+        let content = r#"<134>2016-07-04T22:37:50Z cache-sjc3120 my-service[240869]: 192.0.2.1 - - [04/Jul/2016:22:37:50 +0000] "GET /index.html HTTP/1.1" 200 4230
+<134>2016-07-04T22:37:51Z cache-sjc3121 my-service[240869]: {"timestamp":"2016-07-04T22:37:51+0000","client_ip":"192.0.2.2","url":"/api","request_method":"POST","response_status":503,"response_body_size":0,"response_state":"MISS"}
+"#;
+        let mut reader = ReaderBuilder::new("fastly".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2016-07-04T22:37:50Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(
+            Value::String("cache-sjc3120".to_string()),
+            record.get(&path("cache_server"))
+        );
+        assert_eq!(Value::String("my-service".to_string()), record.get(&path("service")));
+        assert_eq!(Value::String("192.0.2.1".to_string()), record.get(&path("client_ip")));
+        assert_eq!(Value::Int(200), record.get(&path("status")));
+        assert_eq!(Value::Null, record.get(&path("cache_state")));
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2016-07-04T22:37:51Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(
+            Value::String("cache-sjc3121".to_string()),
+            record.get(&path("cache_server"))
+        );
+        assert_eq!(Value::Int(503), record.get(&path("status")));
+        assert_eq!(Value::String("MISS".to_string()), record.get(&path("cache_state")));
+        assert!(matches!(record.get(&path("request")), Value::HttpRequest(_)));
     }
-}
 
-impl From<url::ParseError> for ReaderError {
-    fn from(err: url::ParseError) -> ReaderError {
-        ReaderError::ParseUrl(err)
+    #[test]
+    fn test_cloudflare_reader() {
+        let content = r#"{"RayID":"7c5d1f2a3b4c5d6e","ClientIP":"192.0.2.1","ClientRequestHost":"example.com","ClientRequestMethod":"GET","ClientRequestURI":"/index.html?x=1","EdgeResponseStatus":200,"EdgeResponseBytes":4230,"OriginIP":"","OriginResponseTime":250000000,"EdgeStartTimestamp":1686650730123456789,"WAFAction":"unknown"}
+{"RayID":"7c5d1f2a3b4c5d6f","EdgeResponseStatus":502,"OriginResponseDurationMs":1200,"EdgeStartTimestamp":"2023-06-13T10:05:31Z","SecurityAction":"block"}
+"#;
+        let mut reader = ReaderBuilder::new("cloudflare".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2023-06-13T10:05:30.123456789Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(Value::String("192.0.2.1".to_string()), record.get(&path("client_ip")));
+        assert_eq!(
+            Value::String("/index.html?x=1".to_string()),
+            record.get(&path("client_request_uri"))
+        );
+        assert_eq!(Value::Int(200), record.get(&path("edge_response_status")));
+        assert_eq!(Value::Null, record.get(&path("origin_ip")));
+        assert_eq!(
+            Value::Float(OrderedFloat::from(250.0)),
+            record.get(&path("origin_response_time_ms"))
+        );
+        assert_eq!(
+            Value::String("unknown".to_string()),
+            record.get(&path("security_action"))
+        );
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2023-06-13T10:05:31Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(
+            Value::Float(OrderedFloat::from(1200.0)),
+            record.get(&path("origin_response_time_ms"))
+        );
+        assert_eq!(Value::String("block".to_string()), record.get(&path("security_action")));
+        assert_eq!(Value::Null, record.get(&path("client_ip")));
     }
-}
 
-impl From<json::JsonError> for ReaderError {
-    fn from(err: json::JsonError) -> ReaderError {
-        ReaderError::ParseJson(err)
+    #[test]
+    fn test_iis_reader() {
+        let content = "#Software: Microsoft Internet Information Services 10.0
+#Fields: date time s-ip cs-method cs-uri-stem cs-uri-query s-port c-ip cs(User-Agent) sc-status time-taken
+2021-06-01 10:00:00 10.0.0.1 GET /default.aspx id=1 443 192.0.2.1 Mozilla/5.0+(Windows+NT+10.0) 200 15
+#Fields: date time cs-method cs-uri-stem sc-status X-Forwarded-For
+2021-06-01 10:00:01 POST /login 302 -
+";
+        let mut reader = ReaderBuilder::new("iis".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2021-06-01T10:00:00Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(
+            Value::String("/default.aspx".to_string()),
+            record.get(&path("cs_uri_stem"))
+        );
+        assert_eq!(
+            Value::String("Mozilla/5.0+(Windows+NT+10.0)".to_string()),
+            record.get(&path("cs_user_agent"))
+        );
+        assert_eq!(Value::Int(200), record.get(&path("sc_status")));
+        assert_eq!(Value::Int(15), record.get(&path("time_taken")));
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(302), record.get(&path("sc_status")));
+        assert_eq!(Value::Null, record.get(&path("x_forwarded_for")));
+        assert_eq!(Value::Missing, record.get(&path("c_ip")));
+        assert!(reader.read_record().unwrap().is_none());
     }
-}
 
-#[derive(Debug)]
-pub(crate) struct ReaderBuilder {
-    capacity: usize,
-    file_format: String,
-}
+    #[test]
+    fn test_csv_reader() {
+        let content = "host,status,latency,time,,note
+10.0.0.1,200,0.25,2019-06-07 18:45:33,x,\"hello, \"\"world\"\"\"
 
-pub(crate) trait RecordRead {
-    fn read_record(&mut self) -> ReaderResult<Option<Record>>;
-}
+10.0.0.2,0500,-,2019-06-07T18:45:34Z,y,\"multi
+line\"
+";
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+        let mut reader = ReaderBuilder::new("csv".to_string())
+            .field_types(vec![("status".to_string(), DataType::String)])
+            .with_reader(BufReader::new(content.as_bytes()));
 
-impl ReaderBuilder {
-    pub(crate) fn new(file_format: String) -> Self {
-        ReaderBuilder {
-            capacity: 8 * (1 << 10),
-            file_format: file_format,
-        }
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::String("200".to_string()), record.get(&path("status")));
+        assert_eq!(Value::Float(OrderedFloat::from(0.25)), record.get(&path("latency")));
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2019-06-07T18:45:33Z").unwrap()),
+            record.get(&path("time"))
+        );
+        assert_eq!(Value::String("x".to_string()), record.get(&path("_5")));
+        assert_eq!(Value::String("hello, \"world\"".to_string()), record.get(&path("note")));
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::String("0500".to_string()), record.get(&path("status")));
+        assert_eq!(Value::String("-".to_string()), record.get(&path("latency")));
+        assert_eq!(Value::String("multi\nline".to_string()), record.get(&path("note")));
+        assert!(reader.read_record().unwrap().is_none());
+
+        let content = "a\tb\n1\t\"x\n";
+        let mut reader = ReaderBuilder::new("tsv".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(1), record.get(&path("a")));
+        assert_eq!(Value::String("\"x".to_string()), record.get(&path("b")));
     }
 
-    pub(crate) fn with_path<P: AsRef<Path>>(&self, path: P) -> ReaderResult<Reader<File>> {
-        Ok(Reader::new(self, File::open(path)?, self.file_format.clone()))
+    #[test]
+    fn test_regex_reader() {
+        let log_format = LogFormat::parse(
+            "regex",
+            r"^(?P<timestamp>\S+) (?P<level>\w+) (?P<path>\S+) (?P<latency>\S+)(?: (?P<user>\w+))?$",
+        )
+        .unwrap();
+        let content = "2021-06-01T10:00:00Z INFO /users 12
+2021-06-01T10:00:01Z WARN - 3.5 alice
+not a line
+";
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+        let mut reader = ReaderBuilder::new("regex".to_string())
+            .field_types(vec![("latency".to_string(), DataType::Float)])
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = vec![
+            (
+                "timestamp".to_string(),
+                Value::DateTime(chrono::DateTime::parse_from_rfc3339("2021-06-01T10:00:00Z").unwrap()),
+            ),
+            ("level".to_string(), Value::String("INFO".to_string())),
+            ("path".to_string(), Value::String("/users".to_string())),
+            ("latency".to_string(), Value::Float(OrderedFloat::from(12.0))),
+            ("user".to_string(), Value::Null),
+        ];
+        assert_eq!(expected, record.to_tuples());
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Null, record.get(&path("path")));
+        assert_eq!(Value::String("alice".to_string()), record.get(&path("user")));
+        assert!(reader.read_record().is_err());
+
+        assert!(LogFormat::parse("regex", r"(\d+) (\w+)").is_err());
+        assert!(LogFormat::parse("regex", r"(?P<a>").is_err());
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn with_reader<R: io::Read>(&self, rdr: R) -> Reader<R> {
-        Reader::new(self, rdr, self.file_format.clone())
+    #[test]
+    fn test_caddy_reader() {
+        let content = r#"{"level":"info","ts":1646861401.5,"logger":"http.log.access","msg":"handled request","request":{"remote_ip":"127.0.0.1","method":"GET","host":"example.com","uri":"/index.html"},"duration":0.25,"size":10900,"status":200,"trace_id":"abc"}"#;
+        let mut reader = ReaderBuilder::new("caddy".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let path = |s: &str| {
+            PathExpr::new(
+                s.split('.')
+                    .map(|segment| PathSegment::AttrName(segment.to_string()))
+                    .collect(),
+            )
+        };
+
+        assert_eq!(
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2022-03-09T21:30:01.5Z").unwrap()),
+            record.get(&path("timestamp"))
+        );
+        assert_eq!(
+            Value::String("example.com".to_string()),
+            record.get(&path("request.host"))
+        );
+        assert_eq!(
+            Value::String("/index.html".to_string()),
+            record.get(&path("request.uri"))
+        );
+        assert_eq!(Value::Int(200), record.get(&path("status")));
+        assert_eq!(Value::Float(OrderedFloat::from(0.25)), record.get(&path("duration")));
+        assert_eq!(Value::Null, record.get(&path("bytes_read")));
+        assert_eq!(Value::String("abc".to_string()), record.get(&path("trace_id")));
+        assert_eq!(Value::Missing, record.get(&path("ts")));
     }
-}
 
-fn json_to_data_model(parsed: &JsonValue) -> Value {
-    match parsed {
-        json::JsonValue::Object(o) => {
-            let t: LinkedHashMap<String, Value> =
-                o.iter().map(|(k, v)| (k.to_string(), json_to_data_model(v))).collect();
-            Value::Object(t)
-        }
-        json::JsonValue::Array(a) => {
-            let a: Vec<Value> = a.iter().map(|v| json_to_data_model(v)).collect();
-            Value::Array(a)
-        }
-        json::JsonValue::Null => Value::Null,
-        json::JsonValue::String(s) => Value::String(s.clone()),
-        json::JsonValue::Short(s) => Value::String(s.to_string()),
-        json::JsonValue::Boolean(b) => Value::Boolean(*b),
-        json::JsonValue::Number(n) => {
-            let fixed = n.as_fixed_point_i64(4).unwrap();
+    #[test]
+    fn test_aws_nlb_reader() {
+        let content = r#"tls 2.0 2018-12-20T02:59:40 net/my-network-loadbalancer/c6e77e28c25b2234 g3d4b5e8bb8464cd 72.21.218.154:51341 172.100.100.185:443 5 2 98 246 - arn:aws:acm:us-east-2:671290407336:certificate/2a108f19-aded-46b0-8493-c63eb1ef4a99 - ECDHE-RSA-AES128-SHA tlsv12 - my-network-loadbalancer-c6e77e28c25b2234.elb.us-east-2.amazonaws.com h2 h2 "h2","http/1.1" 2020-04-01T08:51:42"#;
+        let mut reader = ReaderBuilder::new("nlb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap();
+        let fields = NetworkLoadBalancerLogField::field_names();
+        let data = vec![
+            Value::String("tls".to_string()),
+            Value::String("2.0".to_string()),
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2018-12-20T02:59:40Z").unwrap()),
+            Value::String("net/my-network-loadbalancer/c6e77e28c25b2234".to_string()),
+            Value::String("g3d4b5e8bb8464cd".to_string()),
+            Value::Host(common::types::parse_host("72.21.218.154:51341").unwrap()),
+            Value::Host(common::types::parse_host("172.100.100.185:443").unwrap()),
+            Value::Int(5),
+            Value::Int(2),
+            Value::Int(98),
+            Value::Int(246),
+            Value::Null,
+            Value::String(
+                "arn:aws:acm:us-east-2:671290407336:certificate/2a108f19-aded-46b0-8493-c63eb1ef4a99".to_string(),
+            ),
+            Value::Null,
+            Value::String("ECDHE-RSA-AES128-SHA".to_string()),
+            Value::String("tlsv12".to_string()),
+            Value::Null,
+            Value::String("my-network-loadbalancer-c6e77e28c25b2234.elb.us-east-2.amazonaws.com".to_string()),
+            Value::String("h2".to_string()),
+            Value::String("h2".to_string()),
+            Value::String("\"h2\",\"http/1.1\"".to_string()),
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2020-04-01T08:51:42Z").unwrap()),
+        ];
+        let expected: Option<Record> = Some(Record::new(fields, data));
 
-            if fixed % 10000 == 0 {
-                Value::Int((fixed / 10000) as i32)
-            } else {
-                let f: f32 = fixed as f32 / 10000.0;
-                Value::Float(OrderedFloat::from(f))
-            }
-        }
+        assert_eq!(expected, record);
     }
-}
 
-#[derive(Debug)]
-pub(crate) struct Reader<R> {
-    rdr: io::BufReader<R>,
-    file_format: String,
-}
+    #[test]
+    fn test_vpc_flow_reader() {
+        let content = "2 123456789010 eni-1235b8ca123456789 172.31.16.139 172.31.16.21 20641 22 6 20 4249 1418530010 1418530070 ACCEPT OK\n\
+                       2 123456789010 eni-1235b8ca123456789 - - - - - - - 1431280876 1431280934 - NODATA\n\
+                       \n\
+                       version vpc-id srcaddr dstaddr bytes start action tcp-flags\n\
+                       3 vpc-abcdefab012345678 10.0.1.5 10.0.0.220 60 1566848875 REJECT 2\n";
+        let mut reader = ReaderBuilder::new("vpcflow".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let variables = Variables::default();
 
-impl<R: io::Read> Reader<R> {
-    pub(crate) fn new(builder: &ReaderBuilder, rdr: R, file_format: String) -> Reader<R> {
-        Reader {
-            rdr: io::BufReader::with_capacity(builder.capacity, rdr),
-            file_format,
-        }
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(VpcFlowLogField::len(), record.len());
+        assert_eq!(Value::String("172.31.16.139".to_string()), record["srcaddr"]);
+        assert_eq!(Value::Int(22), record["dstport"]);
+        assert_eq!(Value::Int(4249), record["bytes"]);
+        assert_eq!(Value::String("OK".to_string()), record["log_status"]);
+        assert_eq!(Value::Null, record["vpc_id"]);
+        let dt = chrono::DateTime::parse_from_rfc3339("2014-12-14T04:06:50Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::Null, record["srcaddr"]);
+        assert_eq!(Value::Null, record["bytes"]);
+        assert_eq!(Value::String("NODATA".to_string()), record["log_status"]);
+
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::Int(3), record["version"]);
+        assert_eq!(Value::String("vpc-abcdefab012345678".to_string()), record["vpc_id"]);
+        assert_eq!(Value::Int(60), record["bytes"]);
+        assert_eq!(Value::String("REJECT".to_string()), record["action"]);
+        assert_eq!(Value::Int(2), record["tcp_flags"]);
+        assert_eq!(Value::Null, record["dstport"]);
+
+        assert_eq!(None, reader.read_record().unwrap());
     }
 
-    #[allow(dead_code)]
-    fn close(&self) {}
-}
+    #[test]
+    fn test_waf_reader() {
+        let content = r#"{"timestamp":1576280412771,"formatVersion":1,"webaclId":"arn:aws:wafv2:ap-southeast-2:111122223333:regional/webacl/STMTest/1EXAMPLE-2ARN-3ARN-4ARN-123456EXAMPLE","terminatingRuleId":"STMTest_SQLi_XSS","terminatingRuleType":"REGULAR","action":"BLOCK","terminatingRuleMatchDetails":[{"conditionType":"SQL_INJECTION","location":"UNKNOWN","matchedData":["10","AND","1"]}],"httpSourceName":"ALB","httpSourceId":"alb","ruleGroupList":[],"rateBasedRuleList":[],"nonTerminatingMatchingRules":[],"httpRequest":{"clientIp":"1.1.1.1","country":"AU","headers":[{"name":"Host","value":"localhost:1989"},{"name":"User-Agent","value":"curl/7.61.1"}],"uri":"/myUri","args":"","httpVersion":"HTTP/1.1","httpMethod":"GET","requestId":"1-5df3cd5c-ab4a7e2d33fdc56fb7458d8b"},"labels":[{"name":"value"}]}
 
-impl<R: io::Read> RecordRead for Reader<R> {
-    fn read_record(&mut self) -> ReaderResult<Option<Record>> {
-        let mut buf = String::new();
-        let more_data = self.rdr.read_line(&mut buf)?;
-
-        if more_data > 0 && self.file_format != "jsonl" {
-            let field_names = if self.file_format == "elb" {
-                ClassicLoadBalancerLogField::field_names()
-            } else if self.file_format == "alb" {
-                ApplicationLoadBalancerLogField::field_names()
-            } else if self.file_format == "s3" {
-                S3Field::field_names()
-            } else {
-                SquidLogField::field_names()
-            };
+{"timestamp":1576280412772,"action":"ALLOW","httpRequest":{"clientIp":"2.2.2.2","headers":[]}}"#;
+        let mut reader = ReaderBuilder::new("waf".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let variables = Variables::default();
 
-            let mut record_vars = common::types::Variables::default();
-            let mut value_cnt: usize = 0;
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(WafLogField::field_names().len(), record.len());
+        let dt = chrono::DateTime::parse_from_rfc3339("2019-12-13T23:40:12.771Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+        assert_eq!(Value::Int(1), record["format_version"]);
+        assert_eq!(Value::String("BLOCK".to_string()), record["action"]);
+        assert_eq!(Value::String("1.1.1.1".to_string()), record["client_ip"]);
+        assert_eq!(Value::String("/myUri".to_string()), record["uri"]);
+        assert_eq!(Value::String("localhost:1989".to_string()), record["host"]);
+        assert_eq!(Value::String("curl/7.61.1".to_string()), record["user_agent"]);
+        assert_eq!(Value::Null, record["response_code_sent"]);
+        assert_eq!(Value::Array(Vec::new()), record["rule_group_list"]);
+        match &record["terminating_rule_match_details"] {
+            Value::Array(details) => assert_eq!(1, details.len()),
+            other => panic!("unexpected {:?}", other),
+        }
 
-            for (i, m) in SPLIT_READER_LINE_REGEX.find_iter(&buf).enumerate() {
-                if self.file_format == "elb" {
-                    if i >= ClassicLoadBalancerLogField::len() {
-                        break;
-                    }
-                } else if self.file_format == "alb" {
-                    if i >= ApplicationLoadBalancerLogField::len() {
-                        break;
-                    }
-                } else if self.file_format == "squid" {
-                    if i >= SquidLogField::len() {
-                        break;
-                    }
-                } else if self.file_format == "s3" {
-                    if i >= S3Field::len() {
-                        break;
-                    }
-                } else {
-                    unreachable!();
-                }
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::String("ALLOW".to_string()), record["action"]);
+        assert_eq!(Value::Null, record["host"]);
+        assert_eq!(Value::Null, record["terminating_rule_id"]);
+
+        assert_eq!(None, reader.read_record().unwrap());
+    }
+
+    #[test]
+    fn test_apigateway_reader() {
+        let content = r#"{"requestId":"c6af9ac6-7b61-11e6-9a41-93e8deadbeef","ip":"203.0.113.10","caller":"-","user":"-","requestTime":"04/Feb/2021:17:51:55 +0000","httpMethod":"GET","resourcePath":"/pets/{petId}","status":"200","protocol":"HTTP/1.1","responseLength":"72","latency":"45","integrationLatency":"38"}
 
-                let s = m.as_str();
-                let datatype = if self.file_format == "elb" {
-                    ClassicLoadBalancerLogField::datatype(i)
-                } else if self.file_format == "alb" {
-                    ApplicationLoadBalancerLogField::datatype(i)
-                } else if self.file_format == "s3" {
-                    S3Field::datatype(i)
-                } else {
-                    SquidLogField::datatype(i)
-                };
+{"requestId":"d7bf0bd7-7b61-11e6-9a41-93e8deadbeef","requestTimeEpoch":1612461116000,"httpMethod":"POST","resourcePath":"/pets","status":502,"responseLatency":"29001","integrationLatency":"-"}"#;
+        let mut reader = ReaderBuilder::new("apigateway".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let variables = Variables::default();
 
-                match datatype {
-                    DataType::DateTime => {
-                        let dt = chrono::DateTime::parse_from_rfc3339(s)?;
-                        record_vars.insert(field_names[i].clone(), Value::DateTime(dt));
-                    }
-                    DataType::String => {
-                        record_vars.insert(field_names[i].clone(), Value::String(s.to_string()));
-                    }
-                    DataType::Integral => {
-                        let i_val = s.parse::<i32>()?;
-                        record_vars.insert(field_names[i].clone(), Value::Int(i_val));
-                    }
-                    DataType::Float => {
-                        let f = s.parse::<f32>()?;
-                        record_vars.insert(field_names[i].clone(), Value::Float(OrderedFloat::from(f)));
-                    }
-                    DataType::Host => {
-                        if s == "-" {
-                            record_vars.insert(field_names[i].clone(), Value::Null);
-                        } else {
-                            let host = common::types::parse_host(s)?;
-                            record_vars.insert(field_names[i].clone(), Value::Host(host));
-                        }
-                    }
-                    DataType::HttpRequest => {
-                        let s = s.trim_matches('"');
-                        let request = common::types::parse_http_request(s)?;
-                        record_vars.insert(field_names[i].clone(), Value::HttpRequest(request));
-                    }
-                }
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(ApiGatewayField::field_names().len(), record.len());
+        let dt = chrono::DateTime::parse_from_rfc3339("2021-02-04T17:51:55Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+        assert_eq!(
+            Value::String("c6af9ac6-7b61-11e6-9a41-93e8deadbeef".to_string()),
+            record["request_id"]
+        );
+        assert_eq!(Value::String("GET".to_string()), record["http_method"]);
+        assert_eq!(Value::String("/pets/{petId}".to_string()), record["resource_path"]);
+        assert_eq!(Value::Int(200), record["status"]);
+        assert_eq!(Value::Int(45), record["latency"]);
+        assert_eq!(Value::Int(38), record["integration_latency"]);
+        assert_eq!(Value::Null, record["caller"]);
 
-                value_cnt += 1;
-            }
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        let dt = chrono::DateTime::parse_from_rfc3339("2021-02-04T17:51:56Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+        assert_eq!(Value::Int(502), record["status"]);
+        assert_eq!(Value::Int(29001), record["latency"]);
+        assert_eq!(Value::Null, record["integration_latency"]);
 
-            //Adjust the width to be the same
-            while value_cnt < field_names.len() {
-                record_vars.insert(field_names[value_cnt].clone(), Value::Null);
-                value_cnt += 1;
-            }
+        assert_eq!(None, reader.read_record().unwrap());
+    }
 
-            let record = Record::new_with_variables(record_vars);
-            Ok(Some(record))
-        } else if more_data > 0 && self.file_format == "jsonl" {
-            let parsed = json::parse(&buf)?;
-            let data_model = json_to_data_model(&parsed);
+    #[test]
+    fn test_route53_resolver_reader() {
+        let content = r#"{"version":"1.100000","account_id":"111122223333","region":"us-east-1","vpc_id":"vpc-7example","query_timestamp":"2021-02-04T17:51:55Z","query_name":"example.com.","query_type":"A","query_class":"IN","rcode":"NOERROR","answers":[{"Rdata":"203.0.113.9","Type":"A","Class":"IN"}],"srcaddr":"172.31.45.113","srcport":"49428","transport":"UDP","srcids":{"instance":"i-0d15cd0d3example"}}
 
-            match data_model {
-                Value::Object(o) => {
-                    let record = Record::new_with_variables(o);
-                    Ok(Some(record))
-                }
-                _ => {
-                    unimplemented!("Array or value on the first layer is not supported yet")
-                }
-            }
-        } else {
-            Ok(None)
+{"query_timestamp":"2021-02-04T17:51:56Z","query_name":"nowhere.example.","query_type":"AAAA","rcode":"NXDOMAIN","answers":[],"srcaddr":"172.31.45.114"}"#;
+        let mut reader =
+            ReaderBuilder::new("route53resolver".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let variables = Variables::default();
+
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Route53ResolverField::field_names().len(), record.len());
+        let dt = chrono::DateTime::parse_from_rfc3339("2021-02-04T17:51:55Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+        assert_eq!(Value::String("example.com.".to_string()), record["query_name"]);
+        assert_eq!(Value::String("A".to_string()), record["query_type"]);
+        assert_eq!(Value::String("NOERROR".to_string()), record["rcode"]);
+        assert_eq!(Value::String("172.31.45.113".to_string()), record["srcaddr"]);
+        assert_eq!(Value::Int(49428), record["srcport"]);
+        assert_eq!(
+            Value::String("i-0d15cd0d3example".to_string()),
+            record["srcids_instance"]
+        );
+        match &record["answers"] {
+            Value::Array(answers) => assert_eq!(1, answers.len()),
+            other => panic!("unexpected {:?}", other),
         }
+
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::String("NXDOMAIN".to_string()), record["rcode"]);
+        assert_eq!(Value::Null, record["srcport"]);
+        assert_eq!(Value::Null, record["srcids_instance"]);
+
+        assert_eq!(None, reader.read_record().unwrap());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::common;
-    use chrono;
-    use std::io::BufReader;
-    use std::str::FromStr;
+    #[test]
+    fn test_cloudtrail_reader() {
+        let content = r#"{"Records": [
+    {
+        "eventVersion": "1.08",
+        "userIdentity": {"type": "IAMUser", "principalId": "AIDAEXAMPLE", "arn": "arn:aws:iam::123456789012:user/Alice", "accountId": "123456789012", "userName": "Alice"},
+        "eventTime": "2014-03-06T21:22:54Z",
+        "eventSource": "ec2.amazonaws.com",
+        "eventName": "StartInstances",
+        "awsRegion": "us-east-2",
+        "sourceIPAddress": "205.251.233.176",
+        "userAgent": "ec2-api-tools 1.6.12.2",
+        "requestParameters": {"instancesSet": {"items": [{"instanceId": "i-ebeaf9e2"}]}},
+        "readOnly": false
+    },
+    {
+        "eventTime": "2014-03-06T21:23:01Z",
+        "eventSource": "iam.amazonaws.com",
+        "eventName": "CreateUser",
+        "errorCode": "AccessDenied",
+        "userIdentity": {"type": "AssumedRole"}
+    }
+]}"#;
+        let mut reader = ReaderBuilder::new("cloudtrail".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let variables = Variables::default();
+
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(CloudTrailField::field_names().len(), record.len());
+        let dt = chrono::DateTime::parse_from_rfc3339("2014-03-06T21:22:54Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+        assert_eq!(Value::String("StartInstances".to_string()), record["event_name"]);
+        assert_eq!(Value::String("ec2.amazonaws.com".to_string()), record["event_source"]);
+        assert_eq!(
+            Value::String("205.251.233.176".to_string()),
+            record["source_ip_address"]
+        );
+        assert_eq!(
+            Value::String("arn:aws:iam::123456789012:user/Alice".to_string()),
+            record["user_identity_arn"]
+        );
+        assert_eq!(Value::Null, record["error_code"]);
+        assert_eq!(Value::Boolean(false), record["read_only"]);
+
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::String("AccessDenied".to_string()), record["error_code"]);
+        assert_eq!(Value::String("AssumedRole".to_string()), record["user_identity_type"]);
+        assert_eq!(Value::Null, record["user_identity_arn"]);
+        assert_eq!(None, reader.read_record().unwrap());
+
+        let content = "{\"eventName\":\"GetObject\",\"eventTime\":\"2014-03-06T21:22:54Z\"}\n\n\
+                       {\"eventName\":\"PutObject\",\"eventTime\":\"2014-03-06T21:22:55Z\"}\n";
+        let mut reader = ReaderBuilder::new("cloudtrail".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::String("GetObject".to_string()), record["event_name"]);
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::String("PutObject".to_string()), record["event_name"]);
+        assert_eq!(None, reader.read_record().unwrap());
+    }
 
     #[test]
-    fn test_aws_elb_reader() {
-        let content = r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 200 200 0 42355 "GET https://example.com:443/ HTTP/1.1" "Mozilla/5.0 (Windows NT 5.1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/46.0.2490.80 Safari/537.36" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2"#;
-        let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(content.as_bytes()));
-        let record = reader.read_record().unwrap();
-        let fields = ClassicLoadBalancerLogField::field_names();
-        let data = vec![
-            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2015-11-07T18:45:33.559871Z").unwrap()),
-            Value::String("elb1".to_string()),
-            Value::Host(common::types::parse_host("78.168.134.92:4586").unwrap()),
-            Value::Host(common::types::parse_host("10.0.0.215:80").unwrap()),
-            Value::Float(OrderedFloat::from(0.000_036)),
-            Value::Float(OrderedFloat::from(0.001_035)),
-            Value::Float(OrderedFloat::from(0.000_025)),
-            Value::String("200".to_string()),
-            Value::String("200".to_string()),
-            Value::Int(0),
-            Value::Int(42355),
-            Value::HttpRequest(common::types::parse_http_request("GET https://example.com:443/ HTTP/1.1").unwrap()),
-            Value::String("\"Mozilla/5.0 (Windows NT 5.1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/46.0.2490.80 Safari/537.36\"".to_string()),
-            Value::String("ECDHE-RSA-AES128-GCM-SHA256".to_string()),
-            Value::String("TLSv1.2".to_string()),
-            Value::Null,
-            Value::Null
-        ];
-        let expected: Option<Record> = Some(Record::new(fields, data));
+    fn test_cloudfront_reader() {
+        let content = "#Version: 1.0\n\
+                       #Fields: date time x-edge-location sc-bytes c-ip cs-method cs(Host) cs-uri-stem sc-status\n\
+                       2019-12-04\t21:02:31\tLAX1-C3\t392\t192.0.2.100\tGET\td111111abcdef8.cloudfront.net\t/index.html\t200\n\
+                       #Version: 1.0\n\
+                       #Fields: time date sc-status time-taken x-unknown\n\
+                       \n\
+                       01:02:03\t2019-12-05\t-\t0.082\tfoo\n";
+        let mut reader = ReaderBuilder::new("cloudfront".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let variables = Variables::default();
 
-        assert_eq!(expected, record);
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(CloudFrontField::len(), record.len());
+        assert_eq!(Value::String("LAX1-C3".to_string()), record["edge_location"]);
+        assert_eq!(Value::Int(392), record["sc_bytes"]);
+        assert_eq!(Value::String("/index.html".to_string()), record["uri_stem"]);
+        assert_eq!(Value::Int(200), record["status"]);
+        assert_eq!(Value::Null, record["time_taken"]);
+        let dt = chrono::DateTime::parse_from_rfc3339("2019-12-04T21:02:31Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
 
-        let content = r#"2015-11-07T18:45:37.691548Z elb1 176.219.166.226:48384 10.0.2.143:80 0.000023 0.000348 0.000025 200 200 0 41690 "GET http://example.com:80/?mode=json&after=&iteration=1 HTTP/1.1" "Mozilla/5.0 (Linux; Android 5.1.1; Nexus 5 Build/LMY48I; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/46.0.2490.76 Mobile Safari/537.36 [FB_IAB/FB4A;FBAV/52.0.0.12.18;]" - - arn:aws:elasticloadbalancing:us-west-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354""#;
-        let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(content.as_bytes()));
-        let record = reader.read_record().unwrap();
-        let fields = ClassicLoadBalancerLogField::field_names();
-        let data = vec![
-            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2015-11-07T18:45:37.691548Z").unwrap()),
-            Value::String("elb1".to_string()),
-            Value::Host(common::types::parse_host("176.219.166.226:48384").unwrap()),
-            Value::Host(common::types::parse_host("10.0.2.143:80").unwrap()),
-            Value::Float(OrderedFloat::from(0.000_023)),
-            Value::Float(OrderedFloat::from(0.000_348)),
-            Value::Float(OrderedFloat::from(0.000_025)),
-            Value::String("200".to_string()),
-            Value::String("200".to_string()),
-            Value::Int(0),
-            Value::Int(41690),
-            Value::HttpRequest(common::types::parse_http_request("GET http://example.com:80/?mode=json&after=&iteration=1 HTTP/1.1").unwrap()),
-            Value::String("\"Mozilla/5.0 (Linux; Android 5.1.1; Nexus 5 Build/LMY48I; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/46.0.2490.76 Mobile Safari/537.36 [FB_IAB/FB4A;FBAV/52.0.0.12.18;]\"".to_string()),
-            Value::String("-".to_string()),
-            Value::String("-".to_string()),
-            Value::String("arn:aws:elasticloadbalancing:us-west-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067".to_string()),
-            Value::String("\"Root=1-58337262-36d228ad5d99923122bbe354\"".to_string()),
+        let record = reader.read_record().unwrap().unwrap().merged_variables(&variables);
+        assert_eq!(Value::Null, record["status"]);
+        assert_eq!(Value::Float(OrderedFloat::from(0.082)), record["time_taken"]);
+        let dt = chrono::DateTime::parse_from_rfc3339("2019-12-05T01:02:03Z").unwrap();
+        assert_eq!(Value::DateTime(dt), record["timestamp"]);
+
+        assert_eq!(None, reader.read_record().unwrap());
+    }
+
+    #[test]
+    fn test_cloudfront_rt_reader() {
+        let content = "1583436457.123\t192.0.2.100\t200\t-\t/index.html\t0.002\n";
+        let log_format = LogFormat::parse(
+            "cloudfront_rt",
+            "timestamp,c-ip sc-status,x-forwarded-for cs-uri-stem time-taken",
+        );
+        let mut reader = ReaderBuilder::new("cloudfront_rt".to_string())
+            .log_format(Some(Box::new(log_format.unwrap())))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = vec![
+            (
+                "timestamp".to_string(),
+                Value::DateTime(chrono::DateTime::parse_from_rfc3339("2020-03-05T19:27:37.123Z").unwrap()),
+            ),
+            ("c_ip".to_string(), Value::String("192.0.2.100".to_string())),
+            ("sc_status".to_string(), Value::Int(200)),
+            ("x_forwarded_for".to_string(), Value::Null),
+            ("cs_uri_stem".to_string(), Value::String("/index.html".to_string())),
+            ("time_taken".to_string(), Value::Float(OrderedFloat::from(0.002))),
         ];
-        let expected: Option<Record> = Some(Record::new(fields, data));
+        assert_eq!(expected, record.to_tuples());
+        assert_eq!(None, reader.read_record().unwrap());
 
-        assert_eq!(expected, record)
+        let dir = tempfile::tempdir().unwrap();
+        let fields_path = dir.path().join("fields.txt");
+        std::fs::write(
+            &fields_path,
+            "timestamp\nc-ip\nsc-status\nx-forwarded-for\ncs-uri-stem\ntime-taken\n",
+        )
+        .unwrap();
+        let log_format = LogFormat::parse("cloudfront_rt", &format!("@{}", fields_path.display())).unwrap();
+        let field_names: Vec<String> = expected.iter().map(|(k, _)| k.clone()).collect();
+        assert_eq!(&field_names, log_format.field_names());
+
+        assert!(LogFormat::parse("cloudfront_rt", "timestamp,cs(host)").is_err());
+        assert!(LogFormat::parse("cloudfront_rt", "@/nonexistent/fields.txt").is_err());
     }
 
     #[test]
-    fn test_aws_alb_reader() {
-        let content = r#"http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 200 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-""#;
-        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
-        let record = reader.read_record().unwrap();
-        let fields = ApplicationLoadBalancerLogField::field_names();
-        let data = vec![
-            Value::String("http".to_string()),
-            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2018-07-02T22:23:00.186641Z").unwrap()),
-            Value::String("app/my-loadbalancer/50dc6c495c0c9188".to_string()),
-            Value::Host(common::types::parse_host("192.168.131.39:2817").unwrap()),
-            Value::Host(common::types::parse_host("10.0.0.1:80").unwrap()),
-            Value::Float(OrderedFloat::from(0.000)),
-            Value::Float(OrderedFloat::from(0.001)),
-            Value::Float(OrderedFloat::from(0.000)),
-            Value::String("200".to_string()),
-            Value::String("200".to_string()),
-            Value::Int(34),
-            Value::Int(366),
-            Value::HttpRequest(common::types::parse_http_request("GET http://www.example.com:80/ HTTP/1.1").unwrap()),
-            Value::String("\"curl/7.46.0\"".to_string()),
-            Value::String("-".to_string()),
-            Value::String("-".to_string()),
-            Value::String(
-                "arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067"
-                    .to_string(),
+    fn test_parse_field_types() {
+        let field_types = parse_field_types("Status:int,time:float,timestamp:datetime(%d/%b/%Y:%H:%M:%S %z)").unwrap();
+        let expected = vec![
+            ("status".to_string(), DataType::Integral),
+            ("time".to_string(), DataType::Float),
+            (
+                "timestamp".to_string(),
+                DataType::FormattedDateTime("%d/%b/%Y:%H:%M:%S %z".to_string()),
             ),
-            Value::String("\"Root=1-58337262-36d228ad5d99923122bbe354\"".to_string()),
-            Value::String("\"-\"".to_string()),
-            Value::String("\"-\"".to_string()),
-            Value::String("0".to_string()),
-            Value::String("2018-07-02T22:22:48.364000Z".to_string()),
-            Value::String("\"forward\"".to_string()),
-            Value::String("\"-\"".to_string()),
-            Value::String("\"-\"".to_string()),
         ];
-        let expected: Option<Record> = Some(Record::new(fields, data));
+        assert_eq!(expected, field_types);
 
-        assert_eq!(expected, record);
+        assert!(parse_field_types("status").is_err());
+        assert!(parse_field_types("status:decimal").is_err());
     }
 
     #[test]
-    fn test_aws_s3_reader() {
-        let content = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be awsexamplebucket [06/Feb/2019:00:00:38 +0000] 192.0.2.3 79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be 3E57427F3EXAMPLE REST.GET.VERSIONING - "GET /awsexamplebucket?versioning HTTP/1.1" 200 - 113 - 7 - "-" "S3Console/0.4" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234= SigV2 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader awsexamplebucket.s3.amazonaws.com TLSV1.1"#;
-        let mut reader = ReaderBuilder::new("s3".to_string()).with_reader(BufReader::new(content.as_bytes()));
+    fn test_jsonl_reader_with_field_types() {
+        let content = r#"{"status": "200", "bytes": 12, "timestamp": "10/Oct/2000:13:55:36 -0700", "path": "/"}"#;
+        let field_types = parse_field_types("status:int,bytes:float,timestamp:datetime(%d/%b/%Y:%H:%M:%S %z)").unwrap();
+        let mut reader = ReaderBuilder::new("jsonl".to_string())
+            .field_types(field_types)
+            .with_reader(BufReader::new(content.as_bytes()));
         let record = reader.read_record().unwrap();
-        let fields = S3Field::field_names();
+        let fields = vec![
+            "status".to_string(),
+            "bytes".to_string(),
+            "timestamp".to_string(),
+            "path".to_string(),
+        ];
         let data = vec![
-            Value::String("79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be".to_string()),
-            Value::String("awsexamplebucket".to_string()),
-            Value::String("[06/Feb/2019:00:00:38 +0000]".to_string()),
-            Value::String("192.0.2.3".to_string()),
-            Value::String("79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be".to_string()),
-            Value::String("3E57427F3EXAMPLE".to_string()),
-            Value::String("REST.GET.VERSIONING".to_string()),
-            Value::String("-".to_string()),
-            Value::String("\"GET /awsexamplebucket?versioning HTTP/1.1\"".to_string()),
-            Value::String("200".to_string()),
-            Value::String("-".to_string()),
-            Value::String("113".to_string()),
-            Value::String("-".to_string()),
-            Value::String("7".to_string()),
-            Value::String("-".to_string()),
-            Value::String("\"-\"".to_string()),
-            Value::String("\"S3Console/0.4\"".to_string()),
-            Value::String("-".to_string()),
-            Value::String("s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234=".to_string()),
-            Value::String("SigV2".to_string()),
-            Value::String("ECDHE-RSA-AES128-GCM-SHA256".to_string()),
-            Value::String("AuthHeader".to_string()),
-            Value::String("awsexamplebucket.s3.amazonaws.com".to_string()),
-            Value::String("TLSV1.1".to_string()),
+            Value::Int(200),
+            Value::Float(OrderedFloat::from(12.0)),
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2000-10-10T13:55:36-07:00").unwrap()),
+            Value::String("/".to_string()),
         ];
-        let expected: Option<Record> = Some(Record::new(fields, data));
+        let expected: Option<Record> = Some(Record::new(&fields, data));
 
         assert_eq!(expected, record);
     }
 
     #[test]
-    fn test_squid_reader() {
+    fn test_jsonl_reader_with_dotted_keys() {
+        let content = r#"{"log.level": "info", "http.response.status_code": "200", "http": {"request": {"method": "GET"}}, "a.": 1}
+{"http": 1, "http.version": "1.1"}"#;
+        let path = |s: &str| {
+            PathExpr::new(
+                s.split('.')
+                    .map(|segment| PathSegment::AttrName(segment.to_string()))
+                    .collect(),
+            )
+        };
+        let mut reader = ReaderBuilder::new("jsonl".to_string())
+            .field_types(parse_field_types("http.response.status_code:int").unwrap())
+            .with_reader(BufReader::new(content.as_bytes()));
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::String("info".to_string()), record.get(&path("log.level")));
+        assert_eq!(Value::Int(200), record.get(&path("http.response.status_code")));
+        assert_eq!(
+            Value::String("GET".to_string()),
+            record.get(&path("http.request.method"))
+        );
+        assert_eq!(
+            Value::Int(1),
+            record.get(&PathExpr::new(vec![PathSegment::AttrName("a.".to_string())]))
+        );
+
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Value::Int(1), record.get(&path("http")));
+        assert_eq!(
+            Value::String("1.1".to_string()),
+            record.get(&PathExpr::new(vec![PathSegment::AttrName("http.version".to_string())]))
+        );
+    }
+
+    #[test]
+    fn test_reader_with_time_format() {
         let content = r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_DENIED/407 3922 CONNECT d.dropbox.com:443 - HIER_NONE/- text/html"#;
-        let mut reader = ReaderBuilder::new("squid".to_string()).with_reader(BufReader::new(content.as_bytes()));
-        let record = reader.read_record().unwrap();
-        let fields = SquidLogField::field_names();
-        let data = vec![
-            Value::String("1515734740.494".to_string()),
-            Value::String("1".to_string()),
-            Value::String("[MASKEDIPADDRESS]".to_string()),
-            Value::String("TCP_DENIED/407".to_string()),
-            Value::String("3922".to_string()),
-            Value::String("CONNECT".to_string()),
-            Value::String("d.dropbox.com:443".to_string()),
-            Value::String("-".to_string()),
-            Value::String("HIER_NONE/-".to_string()),
-            Value::String("text/html".to_string()),
-        ];
-        let expected: Option<Record> = Some(Record::new(fields, data));
+        let mut reader = ReaderBuilder::new("squid".to_string())
+            .time_format(Some("%s%.3f".to_string()))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = Value::DateTime(chrono::DateTime::parse_from_rfc3339("2018-01-12T05:25:40.494Z").unwrap());
+        assert_eq!(expected, record.to_tuples()[0].1);
 
-        assert_eq!(expected, record);
+        let content = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be awsexamplebucket [06/Feb/2019:00:00:38 +0000] 192.0.2.3 79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be 3E57427F3EXAMPLE REST.GET.VERSIONING - "GET /awsexamplebucket?versioning HTTP/1.1" 200 - 113 - 7 - "-" "S3Console/0.4" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234= SigV2 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader awsexamplebucket.s3.amazonaws.com TLSV1.1"#;
+        let mut reader = ReaderBuilder::new("s3".to_string())
+            .time_format(Some("%d/%b/%Y:%H:%M:%S %z".to_string()))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let expected = Value::DateTime(chrono::DateTime::parse_from_rfc3339("2019-02-06T00:00:38Z").unwrap());
+        assert_eq!(expected, record.to_tuples()[2].1);
     }
 
     #[test]
@@ -1032,4 +6787,249 @@ mod tests {
             assert_eq!(&format_field_name, field_name)
         }
     }
+
+    #[test]
+    fn test_path_columns() {
+        let path = Path::new(
+            "AWSLogs/123456789012/elasticloadbalancing/us-east-1/2014/02/15/123456789012_elasticloadbalancing_us-east-1_app.my-lb.1234567890abcdef_20140215T2340Z_172.160.001.192_20sg8hgm.log.gz",
+        );
+        let columns: LinkedHashMap<String, Value> = path_columns(Some(path)).into_iter().collect();
+        assert_eq!(
+            Some(&Value::String("123456789012".to_string())),
+            columns.get("__account_id")
+        );
+        assert_eq!(Some(&Value::String("us-east-1".to_string())), columns.get("__region"));
+        assert_eq!(
+            Some(&Value::String("app.my-lb.1234567890abcdef".to_string())),
+            columns.get("__load_balancer")
+        );
+        assert_eq!(
+            Some(&Value::DateTime(
+                chrono::DateTime::parse_from_rfc3339("2014-02-15T23:40:00Z").unwrap()
+            )),
+            columns.get("__end_time")
+        );
+        assert_eq!(Some(&Value::String("2014-02-15".to_string())), columns.get("__date"));
+
+        let columns: LinkedHashMap<String, Value> =
+            path_columns(Some(Path::new("data/AWSELB.log"))).into_iter().collect();
+        assert_eq!(
+            Some(&Value::String("data/AWSELB.log".to_string())),
+            columns.get("__file")
+        );
+        assert_eq!(Some(&Value::Null), columns.get("__region"));
+    }
+
+    #[test]
+    fn test_failure_stage() {
+        let lines = [
+            (
+                r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 200 200 0 42355 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" - -"#,
+                "ok",
+            ),
+            (
+                r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 - -1 -1 -1 503 - 0 0 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" - -"#,
+                "backend-connect",
+            ),
+            (
+                r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 - -1 -1 -1 408 - 0 0 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" - -"#,
+                "client-connect",
+            ),
+            (
+                r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 -1 -1 504 - 0 0 "GET https://example.com:443/ HTTP/1.1" "curl/7.46.0" - -"#,
+                "backend-response",
+            ),
+        ];
+        for (line, expected) in lines.iter() {
+            let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(line.as_bytes()));
+            let record = reader.read_record().unwrap().unwrap();
+            let columns: LinkedHashMap<String, Value> = derived_columns(record.to_variables()).into_iter().collect();
+            assert_eq!(
+                Some(&Value::String(expected.to_string())),
+                columns.get("__failure_stage")
+            );
+        }
+    }
+
+    #[test]
+    fn test_grpc_status() {
+        let content = r#"grpcs 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 14 34 366 "POST https://www.example.com:443/helloworld.Greeter/SayHello HTTP/2.0" "grpc-go/1.20.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-"
+http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 200 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-""#;
+        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+
+        let record = reader.read_record().unwrap().unwrap();
+        let columns: LinkedHashMap<String, Value> = derived_columns(record.to_variables()).into_iter().collect();
+        assert_eq!(Some(&Value::Int(14)), columns.get("grpc_status"));
+        assert_eq!(
+            Some(&Value::String("UNAVAILABLE".to_string())),
+            columns.get("grpc_status_name")
+        );
+        assert_eq!(Some(&Value::String("5xx".to_string())), columns.get("__status_class"));
+
+        let record = reader.read_record().unwrap().unwrap();
+        let columns: LinkedHashMap<String, Value> = derived_columns(record.to_variables()).into_iter().collect();
+        assert_eq!(Some(&Value::Null), columns.get("grpc_status"));
+        assert_eq!(Some(&Value::Null), columns.get("grpc_status_name"));
+        assert_eq!(Some(&Value::String("2xx".to_string())), columns.get("__status_class"));
+    }
+
+    #[test]
+    fn test_client_port() {
+        let content = r#"2015-05-13T23:39:43.945958Z my-loadbalancer 192.168.131.39:2817 10.0.0.1:80 0.000073 0.001048 0.000057 200 200 0 29 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.38.0" - -"#;
+        let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let columns: LinkedHashMap<String, Value> = derived_columns(record.to_variables()).into_iter().collect();
+        assert_eq!(
+            Some(&Value::String("192.168.131.39".to_string())),
+            columns.get("client_ip")
+        );
+        assert_eq!(Some(&Value::Int(2817)), columns.get("client_port"));
+
+        let mut variables = Variables::default();
+        variables.insert("client_and_port".to_string(), Value::Null);
+        let columns: LinkedHashMap<String, Value> = derived_columns(&variables).into_iter().collect();
+        assert_eq!(Some(&Value::Null), columns.get("client_port"));
+    }
+
+    #[test]
+    fn test_is_upgrade() {
+        let content = r#"wss 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 101 101 34 366 "GET https://www.example.com:443/chat HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-"
+http 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.000 0.001 0.000 200 200 34 366 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.46.0" - - arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "-" "-" 0 2018-07-02T22:22:48.364000Z "forward" "-" "-""#;
+        let mut reader = ReaderBuilder::new("alb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Some(true), is_upgrade(record.to_variables()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Some(false), is_upgrade(record.to_variables()));
+
+        let content = r#"2015-11-07T18:45:33.559871Z elb1 78.168.134.92:4586 10.0.0.215:80 0.000036 0.001035 0.000025 101 101 0 42355 "GET wss://example.com:443/chat HTTP/1.1" "curl/7.46.0" - -"#;
+        let mut reader = ReaderBuilder::new("elb".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        assert_eq!(Some(true), is_upgrade(record.to_variables()));
+
+        let record = Record::new(&vec!["a".to_string()], vec![Value::Int(1)]);
+        assert_eq!(None, is_upgrade(record.to_variables()));
+    }
+
+    #[test]
+    fn test_lambda_error_class() {
+        assert_eq!("invalid-response", lambda_error_class("LambdaInvalidResponse"));
+        assert_eq!("function-error", lambda_error_class("LambdaUnhandled"));
+        assert_eq!("permission", lambda_error_class("LambdaKMSAccessDeniedException"));
+        assert_eq!("configuration", lambda_error_class("LambdaInvalidSubnetIDException"));
+        assert_eq!("other", lambda_error_class("LambdaSomethingNew"));
+
+        let mut variables = Variables::default();
+        variables.insert("error_reason".to_string(), Value::Null);
+        let columns: LinkedHashMap<String, Value> = derived_columns(&variables).into_iter().collect();
+        assert_eq!(Some(&Value::Null), columns.get("lambda_error_class"));
+    }
+
+    #[test]
+    fn test_squid_cache_result() {
+        assert_eq!("hit", squid_cache_result("TCP_MEM_HIT"));
+        assert_eq!("hit", squid_cache_result("TCP_REFRESH_UNMODIFIED"));
+        assert_eq!("hit", squid_cache_result("TCP_REFRESH_FAIL_OLD"));
+        assert_eq!("miss", squid_cache_result("TCP_MISS_ABORTED"));
+        assert_eq!("miss", squid_cache_result("TCP_REFRESH_MODIFIED"));
+        assert_eq!("denied", squid_cache_result("TCP_DENIED_REPLY"));
+        assert_eq!("tunnel", squid_cache_result("TCP_TUNNEL"));
+        assert_eq!("other", squid_cache_result("NONE_NONE"));
+
+        let content = r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_REFRESH_UNMODIFIED/304 3922 GET http://example.com/ - FIRSTUP_PARENT/10.0.0.2 text/html"#;
+        let mut reader = ReaderBuilder::new("squid".to_string()).with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let columns: LinkedHashMap<String, Value> = derived_columns(record.to_variables()).into_iter().collect();
+        assert_eq!(
+            Some(&Value::String("TCP_REFRESH_UNMODIFIED".to_string())),
+            columns.get("squid_status")
+        );
+        assert_eq!(Some(&Value::Int(304)), columns.get("status"));
+        assert_eq!(
+            Some(&Value::String("FIRSTUP_PARENT".to_string())),
+            columns.get("hierarchy_status")
+        );
+        assert_eq!(Some(&Value::String("10.0.0.2".to_string())), columns.get("peer_host"));
+        assert_eq!(Some(&Value::String("hit".to_string())), columns.get("cache_result"));
+
+        let content = r#"1515734740.494      1 [MASKEDIPADDRESS] TCP_DENIED/407 3922 CONNECT d.dropbox.com:443 - HIER_NONE/- text/html"#;
+        let log_format = LogFormat::parse("squid", "squid").unwrap();
+        let mut reader = ReaderBuilder::new("squid".to_string())
+            .log_format(Some(Box::new(log_format)))
+            .with_reader(BufReader::new(content.as_bytes()));
+        let record = reader.read_record().unwrap().unwrap();
+        let columns: LinkedHashMap<String, Value> = derived_columns(record.to_variables()).into_iter().collect();
+        assert_eq!(None, columns.get("peer_host"));
+        assert_eq!(Some(&Value::String("denied".to_string())), columns.get("cache_result"));
+    }
+
+    #[test]
+    fn test_trace_root() {
+        assert_eq!(
+            Some("1-58337262-36d228ad5d99923122bbe354"),
+            trace_root("\"Root=1-58337262-36d228ad5d99923122bbe354\"")
+        );
+        assert_eq!(
+            Some("1-5759e988-bd862e3fe1be46a994272793"),
+            trace_root("Self=1-5759e988-bd862e3fe1be46a994272794;Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=1")
+        );
+        assert_eq!(None, trace_root("\"-\""));
+        assert_eq!(None, trace_root("-"));
+    }
+
+    #[test]
+    fn test_time_range_may_overlap_file() {
+        let dt = |s: &str| Some(chrono::DateTime::parse_from_rfc3339(s).unwrap());
+        let path = Path::new("123456789012_elasticloadbalancing_us-east-1_my-lb_20190607T1850Z_10.0.0.1_a.log");
+
+        let time_range = TimeRange {
+            start: dt("2019-06-07T18:00:00Z"),
+            end: dt("2019-06-07T19:00:00Z"),
+        };
+        assert!(time_range.may_overlap_file(path));
+
+        let time_range = TimeRange {
+            start: dt("2019-06-07T19:00:00Z"),
+            end: None,
+        };
+        assert!(!time_range.may_overlap_file(path));
+
+        let time_range = TimeRange {
+            start: None,
+            end: dt("2019-06-07T17:00:00Z"),
+        };
+        assert!(!time_range.may_overlap_file(path));
+        assert!(time_range.may_overlap_file(Path::new("data/AWSELB.log")));
+    }
+
+    #[test]
+    fn test_list_log_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2015/11/07")).unwrap();
+        File::create(dir.path().join("2015/11/07/b.log")).unwrap();
+        File::create(dir.path().join("2015/11/07/.hidden")).unwrap();
+        File::create(dir.path().join("a.log")).unwrap();
+
+        let files = list_log_files(dir.path()).unwrap();
+        assert_eq!(
+            vec![dir.path().join("2015/11/07/b.log"), dir.path().join("a.log")],
+            files
+        );
+    }
+
+    #[test]
+    fn test_golden_files() {
+        for file_format in SUPPORTED_FORMATS.iter() {
+            fixtures::check_golden(file_format);
+        }
+    }
+
+    #[test]
+    fn test_mutated_lines() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(259);
+        for file_format in SUPPORTED_FORMATS.iter() {
+            fixtures::check_mutations(file_format, 200, &mut rng);
+        }
+    }
 }
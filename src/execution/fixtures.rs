@@ -0,0 +1,214 @@
+//The test fixtures of the log formats. Every format in SUPPORTED_FORMATS needs a golden pair under tests/golden,
+//<format>.log with the sample lines and <format>.expected with the records they are read into, and its sample lines
+//are mutated to check that no input panics the reader. Set LOGQ_UPDATE_GOLDEN=1 to regenerate the expected files.
+//The fixtures are public with the fixtures feature, so the tests of a new format can call check_format on it.
+use crate::common::types::{value_to_json, DateTimeFormat};
+use crate::execution::datasource::{field_names_of, schema_of, DataType, ReaderBuilder, RecordRead, SUPPORTED_FORMATS};
+use rand::{Rng, SeedableRng};
+use std::fs;
+use std::path::PathBuf;
+
+//The whitespace separated formats, their lines are generated from the datatypes of the fields
+const SPLIT_FORMATS: &[&str] = &["elb", "alb", "nlb", "alb_conn", "s3", "squid", "vpcflow"];
+//The formats of a JSON object per line, their lines are objects of some of the fields
+const JSON_FORMATS: &[&str] = &["waf", "route53resolver", "apigateway", "cloudflare"];
+
+pub fn formats() -> &'static [&'static str] {
+    SUPPORTED_FORMATS
+}
+
+pub fn golden_path(file_format: &str, extension: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.{}", file_format, extension))
+}
+
+//Reads the content to the end, one JSON line per record or per error so that a bad line does not hide the rest
+pub fn read_all(file_format: &str, content: &str) -> Vec<String> {
+    let mut reader = ReaderBuilder::new(file_format.to_string()).with_reader(content.as_bytes());
+    let mut lines = Vec::new();
+    //Every call consumes at least one line, the bound only guards against a reader stuck at the same position
+    for _ in 0..=content.lines().count() + 1 {
+        match reader.read_record() {
+            Ok(Some(record)) => {
+                let mut obj = json::JsonValue::new_object();
+                for (k, v) in record.to_tuples().iter() {
+                    obj[k.as_str()] = value_to_json(v, &DateTimeFormat::Default);
+                }
+                lines.push(obj.dump());
+            }
+            Ok(None) => return lines,
+            Err(e) => lines.push(json::object! { "error" => e.to_string() }.dump()),
+        }
+    }
+
+    panic!("the {} reader does not reach the end of the input", file_format);
+}
+
+//Checks a record read from any input has the whole schema, the missing fields as null
+pub fn check_schema(file_format: &str, line: &str) {
+    if let Some(field_names) = field_names_of(file_format) {
+        let parsed = json::parse(line).unwrap();
+        if !parsed.has_key("error") {
+            for name in field_names.iter() {
+                assert!(parsed.has_key(name), "{} misses {} in {}", file_format, name, line);
+            }
+        }
+    }
+}
+
+pub fn check_golden(file_format: &str) {
+    let input = fs::read_to_string(golden_path(file_format, "log"))
+        .unwrap_or_else(|_| panic!("missing the golden sample lines of {}", file_format));
+    let actual = read_all(file_format, &input).join("\n") + "\n";
+    for line in actual.lines() {
+        check_schema(file_format, line);
+    }
+
+    let expected_path = golden_path(file_format, "expected");
+    if std::env::var("LOGQ_UPDATE_GOLDEN").is_ok() {
+        fs::write(&expected_path, &actual).unwrap();
+    } else {
+        let expected = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|_| panic!("missing {}, run with LOGQ_UPDATE_GOLDEN=1", expected_path.display()));
+        assert_eq!(
+            expected, actual,
+            "the records of {} differ from the golden file",
+            file_format
+        );
+    }
+}
+
+//One random edit that a truncated or corrupted log line may have
+pub fn mutate<R: Rng>(line: &str, rng: &mut R) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let pos = if chars.is_empty() {
+        0
+    } else {
+        rng.gen_range(0..chars.len())
+    };
+    match rng.gen_range(0..5) {
+        0 => chars[..pos].iter().collect(),
+        1 => {
+            let mut tokens: Vec<&str> = line.split(' ').collect();
+            if !tokens.is_empty() {
+                tokens.remove(rng.gen_range(0..tokens.len()));
+            }
+            tokens.join(" ")
+        }
+        2 => {
+            let noise = ['"', '\'', '[', ']', '{', '}', '\\', ' ', ':', '-'];
+            let mut chars = chars;
+            chars.insert(pos, noise[rng.gen_range(0..noise.len())]);
+            chars.into_iter().collect()
+        }
+        3 => {
+            let mut chars = chars;
+            chars.insert(pos, rng.gen_range('\u{80}'..'\u{2000}'));
+            chars.into_iter().collect()
+        }
+        _ => line
+            .split(' ')
+            .map(|t| if rng.gen_bool(0.2) { "-" } else { t })
+            .collect::<Vec<&str>>()
+            .join(" "),
+    }
+}
+
+fn random_token<R: Rng>(datatype: &DataType, rng: &mut R) -> String {
+    if rng.gen_bool(0.1) {
+        return "-".to_string();
+    }
+
+    match datatype {
+        DataType::DateTime => format!(
+            "2019-0{}-1{}T0{}:30:00.000000Z",
+            rng.gen_range(1..10),
+            rng.gen_range(0..10),
+            rng.gen_range(0..10)
+        ),
+        DataType::FormattedDateTime(_) => format!("[0{}/Feb/2019:00:00:38 +0000]", rng.gen_range(1..10)),
+        DataType::Integral => rng.gen_range(-1..100_000).to_string(),
+        DataType::Float => format!("{:.6}", rng.gen_range(-1.0..100.0)),
+        DataType::Host => match rng.gen_range(0..3) {
+            0 => format!(
+                "10.0.{}.{}:{}",
+                rng.gen_range(0..256),
+                rng.gen_range(0..256),
+                rng.gen_range(1..65536)
+            ),
+            1 => format!("[2001:db8::{:x}]:{}", rng.gen_range(0..65536), rng.gen_range(1..65536)),
+            _ => format!("2001:db8::{:x}", rng.gen_range(0..65536)),
+        },
+        DataType::HttpRequest => {
+            let methods = ["GET", "POST", "-"];
+            format!(
+                "\"{} https://example.com:443/{} HTTP/1.1\"",
+                methods[rng.gen_range(0..methods.len())],
+                rng.gen_range(0..1000)
+            )
+        }
+        DataType::QuotedString => format!("\"value {}\"", rng.gen_range(0..1000)),
+        DataType::QuotedList => format!("\"10.0.0.{}:80 -\"", rng.gen_range(1..255)),
+        DataType::String | DataType::Json => format!("v{}", rng.gen_range(0..1000)),
+    }
+}
+
+//A line of random but well formed values, none for the formats without a generator
+pub fn random_line<R: Rng>(file_format: &str, rng: &mut R) -> Option<String> {
+    let field_names = field_names_of(file_format)?;
+    if SPLIT_FORMATS.contains(&file_format) {
+        let schema = schema_of(file_format)?;
+        let len = rng.gen_range(0..=schema.len());
+        let tokens: Vec<String> = schema[..len].iter().map(|(_, d)| random_token(d, rng)).collect();
+        Some(tokens.join(" "))
+    } else if !JSON_FORMATS.contains(&file_format) {
+        None
+    } else {
+        let mut obj = json::JsonValue::new_object();
+        for name in field_names.iter() {
+            if rng.gen_bool(0.5) {
+                continue;
+            }
+            obj[name.as_str()] = match rng.gen_range(0..4) {
+                0 => json::Null,
+                1 => rng.gen_range(0..100_000).into(),
+                2 => format!("{}", rng.gen_range(0..100_000)).into(),
+                _ => "-".into(),
+            };
+        }
+        Some(obj.dump())
+    }
+}
+
+//Reads the sample lines mutated, and the random lines of the format, the rounds times each. The records only need to
+//have the schema, what matters is that the reader neither panics nor gets stuck.
+pub fn check_mutations<R: Rng>(file_format: &str, rounds: usize, rng: &mut R) {
+    let sample = fs::read_to_string(golden_path(file_format, "log"))
+        .unwrap_or_else(|_| panic!("missing the golden sample lines of {}", file_format));
+    for _ in 0..rounds {
+        let content = sample
+            .lines()
+            .map(|line| mutate(line, rng))
+            .collect::<Vec<String>>()
+            .join("\n");
+        for line in read_all(file_format, &content).iter() {
+            check_schema(file_format, line);
+        }
+    }
+
+    for _ in 0..rounds {
+        if let Some(line) = random_line(file_format, rng) {
+            for record in read_all(file_format, &line).iter() {
+                check_schema(file_format, record);
+            }
+        }
+    }
+}
+
+//The golden files and the mutations of a format, the same checks every supported format is tested with
+pub fn check_format(file_format: &str) {
+    check_golden(file_format);
+    check_mutations(file_format, 200, &mut rand::rngs::StdRng::seed_from_u64(259));
+}
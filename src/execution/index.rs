@@ -0,0 +1,340 @@
+use super::datasource::TimeRange;
+use crate::common::types::Value;
+use chrono::{DateTime, FixedOffset};
+use hashbrown::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const INDEX_FILE_NAME: &str = "logq_index.json";
+const SKETCH_BITS: usize = 2048;
+const SKETCH_HASHES: u64 = 4;
+
+//A bloom filter of the values of a field in a file. It can tell that a value doesn't occur in the file, but not
+//that it does, so a file is only skipped when an equality in the where clause can't match any of its records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ValueSketch {
+    bits: Vec<u64>,
+}
+
+//FNV-1a of the variant and the content of the value, stable across the runs unlike the hasher of std
+fn value_hash(value: &Value) -> Option<u64> {
+    let (tag, content) = match value {
+        Value::String(s) => (b's', s.clone()),
+        Value::Int(i) => (b'i', i.to_string()),
+        Value::Boolean(b) => (b'b', b.to_string()),
+        _ => return None,
+    };
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in std::iter::once(tag).chain(content.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Some(hash)
+}
+
+impl Default for ValueSketch {
+    fn default() -> Self {
+        ValueSketch {
+            bits: vec![0; SKETCH_BITS / 64],
+        }
+    }
+}
+
+impl ValueSketch {
+    fn positions(hash: u64) -> impl Iterator<Item = usize> {
+        let (h1, h2) = (hash & 0xffff_ffff, hash >> 32);
+        (0..SKETCH_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % SKETCH_BITS as u64) as usize)
+    }
+
+    //The values other than the strings, the integers and the booleans are not sketched, they are never equal to
+    //the literals pushed down from the where clause
+    pub(crate) fn insert(&mut self, value: &Value) {
+        if let Some(hash) = value_hash(value) {
+            for pos in Self::positions(hash) {
+                self.bits[pos / 64] |= 1 << (pos % 64);
+            }
+        }
+    }
+
+    pub(crate) fn may_contain(&self, value: &Value) -> bool {
+        match value_hash(value) {
+            Some(hash) => Self::positions(hash).all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0),
+            None => true,
+        }
+    }
+
+    //A sketch with most of its bits set skips nearly nothing, not worth the space in the index
+    fn is_saturated(&self) -> bool {
+        let ones: u32 = self.bits.iter().map(|b| b.count_ones()).sum();
+        ones as usize > SKETCH_BITS / 2
+    }
+
+    fn to_hex(&self) -> String {
+        self.bits.iter().map(|b| format!("{:016x}", b)).collect()
+    }
+
+    fn from_hex(s: &str) -> Option<Self> {
+        if s.len() != SKETCH_BITS / 4 {
+            return None;
+        }
+        let bits = (0..s.len())
+            .step_by(16)
+            .map(|i| u64::from_str_radix(s.get(i..i + 16)?, 16).ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some(ValueSketch { bits })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileStats {
+    pub(crate) size: u64,
+    pub(crate) modified: u64,
+    pub(crate) min_timestamp: Option<DateTime<FixedOffset>>,
+    pub(crate) max_timestamp: Option<DateTime<FixedOffset>>,
+    //The sketches of the fields present in every record of the file
+    pub(crate) sketches: HashMap<String, ValueSketch>,
+}
+
+impl FileStats {
+    pub(crate) fn may_overlap(&self, time_range: &TimeRange) -> bool {
+        match (self.min_timestamp, self.max_timestamp) {
+            (Some(min), Some(max)) => {
+                let after_range = time_range.end.is_some_and(|end| min > end);
+                let before_range = time_range.start.is_some_and(|start| max < start);
+                !(after_range || before_range)
+            }
+            //The timestamps are unknown, e.g. the strings of a jsonl file, so the file can't be ruled out
+            _ => true,
+        }
+    }
+
+    //Whether some record of the file may satisfy all the equalities of the fields with the literals
+    pub(crate) fn may_match(&self, value_filters: &[(String, Value)]) -> bool {
+        value_filters
+            .iter()
+            .all(|(field, value)| self.sketches.get(field).is_none_or(|sketch| sketch.may_contain(value)))
+    }
+}
+
+//The sidecar index of the timestamp ranges and the value sketches of the scanned log files, stored as a json file in the index directory.
+//An entry is only used while the size and the modification time of the file are unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileIndex {
+    index_path: PathBuf,
+    entries: HashMap<String, FileStats>,
+}
+
+fn file_key(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+fn file_signature(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+fn parse_timestamp(value: &json::JsonValue) -> Option<DateTime<FixedOffset>> {
+    value.as_str().and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+}
+
+impl FileIndex {
+    pub(crate) fn open(index_dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(index_dir)?;
+        let index_path = index_dir.join(INDEX_FILE_NAME);
+
+        let mut entries = HashMap::new();
+        //A missing or corrupted index is rebuilt from scratch
+        if let Ok(content) = fs::read_to_string(&index_path) {
+            if let Ok(json::JsonValue::Object(o)) = json::parse(&content) {
+                for (key, value) in o.iter() {
+                    if let (Some(size), Some(modified)) = (value["size"].as_u64(), value["modified"].as_u64()) {
+                        let stats = FileStats {
+                            size,
+                            modified,
+                            min_timestamp: parse_timestamp(&value["min_timestamp"]),
+                            max_timestamp: parse_timestamp(&value["max_timestamp"]),
+                            sketches: value["sketches"]
+                                .entries()
+                                .filter_map(|(field, hex)| {
+                                    let sketch = ValueSketch::from_hex(hex.as_str()?)?;
+                                    Some((field.to_string(), sketch))
+                                })
+                                .collect(),
+                        };
+                        entries.insert(key.to_string(), stats);
+                    }
+                }
+            }
+        }
+
+        Ok(FileIndex { index_path, entries })
+    }
+
+    pub(crate) fn get(&self, path: &Path) -> Option<&FileStats> {
+        let stats = self.entries.get(&file_key(path))?;
+        match file_signature(path) {
+            Ok((size, modified)) if size == stats.size && modified == stats.modified => Some(stats),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn may_match(&self, path: &Path, time_range: &TimeRange, value_filters: &[(String, Value)]) -> bool {
+        self.get(path)
+            .is_none_or(|stats| stats.may_overlap(time_range) && stats.may_match(value_filters))
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        path: &Path,
+        min_timestamp: Option<DateTime<FixedOffset>>,
+        max_timestamp: Option<DateTime<FixedOffset>>,
+        sketches: HashMap<String, ValueSketch>,
+    ) -> io::Result<()> {
+        let (size, modified) = file_signature(path)?;
+        let stats = FileStats {
+            size,
+            modified,
+            min_timestamp,
+            max_timestamp,
+            sketches: sketches
+                .into_iter()
+                .filter(|(_, sketch)| !sketch.is_saturated())
+                .collect(),
+        };
+        self.entries.insert(file_key(path), stats);
+        Ok(())
+    }
+
+    pub(crate) fn save(&self) -> io::Result<()> {
+        let mut data = json::JsonValue::new_object();
+        for (key, stats) in self.entries.iter() {
+            let mut obj = json::JsonValue::new_object();
+            obj["size"] = stats.size.into();
+            obj["modified"] = stats.modified.into();
+            obj["min_timestamp"] = stats.min_timestamp.map(|dt| dt.to_rfc3339()).into();
+            obj["max_timestamp"] = stats.max_timestamp.map(|dt| dt.to_rfc3339()).into();
+            let mut sketches = json::JsonValue::new_object();
+            for (field, sketch) in stats.sketches.iter() {
+                sketches[field.as_str()] = sketch.to_hex().into();
+            }
+            obj["sketches"] = sketches;
+            data[key.as_str()] = obj;
+        }
+
+        //Write to a temporary file first so that an interrupted query doesn't leave a truncated index
+        let tmp_path = self.index_path.with_extension("json.tmp");
+        fs::write(&tmp_path, data.dump())?;
+        fs::rename(&tmp_path, &self.index_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_file_index_round_trip() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("a.log");
+        let mut file = File::create(&log_path).unwrap();
+        writeln!(file, "content").unwrap();
+        drop(file);
+
+        let index_dir = dir.path().join("index");
+        let min = DateTime::parse_from_rfc3339("2019-06-07T18:45:33Z").unwrap();
+        let max = DateTime::parse_from_rfc3339("2019-06-07T18:45:37Z").unwrap();
+
+        let mut index = FileIndex::open(&index_dir).unwrap();
+        assert_eq!(None, index.get(&log_path));
+        index.insert(&log_path, Some(min), Some(max), HashMap::new()).unwrap();
+        index.save().unwrap();
+
+        let index = FileIndex::open(&index_dir).unwrap();
+        let stats = index.get(&log_path).unwrap();
+        assert_eq!(Some(min), stats.min_timestamp);
+        assert_eq!(Some(max), stats.max_timestamp);
+
+        let before = TimeRange {
+            start: None,
+            end: Some(DateTime::parse_from_rfc3339("2019-06-07T18:00:00Z").unwrap()),
+        };
+        assert!(!index.may_match(&log_path, &before, &[]));
+        assert!(index.may_match(&log_path, &TimeRange::default(), &[]));
+
+        //The entry is stale once the file changes
+        let mut file = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        writeln!(file, "more content").unwrap();
+        drop(file);
+        assert_eq!(None, index.get(&log_path));
+        assert!(index.may_match(&log_path, &before, &[]));
+    }
+
+    #[test]
+    fn test_file_index_unknown_timestamps() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("a.log");
+        fs::write(&log_path, "{\"a\":2,\"timestamp\":\"2019-06-08T10:00:00Z\"}\n").unwrap();
+
+        let index_dir = dir.path().join("index");
+        let mut index = FileIndex::open(&index_dir).unwrap();
+        index.insert(&log_path, None, None, HashMap::new()).unwrap();
+        index.save().unwrap();
+
+        let index = FileIndex::open(&index_dir).unwrap();
+        let after = TimeRange {
+            start: Some(DateTime::parse_from_rfc3339("2019-06-07T18:00:00Z").unwrap()),
+            end: None,
+        };
+        assert!(index.may_match(&log_path, &after, &[]));
+    }
+
+    #[test]
+    fn test_value_sketch() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("a.log");
+        fs::write(&log_path, "content\n").unwrap();
+
+        let mut sketch = ValueSketch::default();
+        sketch.insert(&Value::Int(200));
+        sketch.insert(&Value::String("GET".to_string()));
+        let mut sketches = HashMap::new();
+        sketches.insert("status".to_string(), sketch);
+        //The saturated sketch is dropped
+        let mut saturated = ValueSketch::default();
+        for i in 0..10000 {
+            saturated.insert(&Value::Int(i));
+        }
+        sketches.insert("id".to_string(), saturated);
+
+        let index_dir = dir.path().join("index");
+        let mut index = FileIndex::open(&index_dir).unwrap();
+        index.insert(&log_path, None, None, sketches).unwrap();
+        index.save().unwrap();
+
+        let index = FileIndex::open(&index_dir).unwrap();
+        let stats = index.get(&log_path).unwrap();
+        assert_eq!(vec!["status"], stats.sketches.keys().collect::<Vec<_>>());
+
+        let time_range = TimeRange::default();
+        let status = |v: Value| vec![("status".to_string(), v)];
+        assert!(index.may_match(&log_path, &time_range, &status(Value::Int(200))));
+        assert!(!index.may_match(&log_path, &time_range, &status(Value::Int(404))));
+        assert!(!index.may_match(&log_path, &time_range, &status(Value::String("200".to_string()))));
+        assert!(index.may_match(&log_path, &time_range, &[("id".to_string(), Value::Int(-1))]));
+        assert!(index.may_match(&log_path, &time_range, &[("other".to_string(), Value::Int(404))]));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod columnar;
 pub mod datasource;
+pub(crate) mod field_docs;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod index;
+pub mod source;
 pub mod stream;
 pub mod types;
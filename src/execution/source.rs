@@ -0,0 +1,274 @@
+use crate::common::compress::{GzipReader, GZIP_MAGIC};
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+//What a source tells about itself, the path only for the files
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct SourceMetadata {
+    pub(crate) name: String,
+    pub(crate) path: Option<PathBuf>,
+}
+
+//Where the lines of a table come from. The readers of the log formats only see the lines, so a new transport,
+//e.g. a compressed file, is added by implementing this without touching the parsers.
+pub(crate) trait Source {
+    //Reads the next line into buf including the line ending, 0 at the end of the source
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+
+    fn metadata(&self) -> SourceMetadata;
+}
+
+pub(crate) struct FileSource {
+    rdr: io::BufReader<Box<dyn io::Read + Send>>,
+    path: PathBuf,
+}
+
+impl FileSource {
+    //The gzip files are told by the magic bytes rather than the extension, e.g. the ALB logs delivered to S3 are
+    //always gzipped, and are decompressed as they are read
+    pub(crate) fn open(path: &Path, capacity: usize) -> io::Result<Self> {
+        let mut file = io::BufReader::with_capacity(capacity, File::open(path)?);
+        let rdr: Box<dyn io::Read + Send> = if file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Box::new(GzipReader::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        Ok(FileSource {
+            rdr: io::BufReader::with_capacity(capacity, rdr),
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Source for FileSource {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.rdr.read_line(buf)
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            name: self.path.to_string_lossy().to_string(),
+            path: Some(self.path.clone()),
+        }
+    }
+}
+
+//A log file still being written, read by --follow. A line without its line ending is held back until the rest of it
+//is written, so the end of the source is the end of the complete lines written so far.
+pub(crate) struct TailSource {
+    file: FileSource,
+    partial: String,
+}
+
+impl TailSource {
+    pub(crate) fn open(path: &Path, capacity: usize) -> io::Result<Self> {
+        Ok(TailSource {
+            file: FileSource::open(path, capacity)?,
+            partial: String::new(),
+        })
+    }
+}
+
+impl Source for TailSource {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.file.read_line(&mut self.partial)?;
+        if !self.partial.ends_with('\n') {
+            return Ok(0);
+        }
+
+        let len = self.partial.len();
+        buf.push_str(&self.partial);
+        self.partial.clear();
+        Ok(len)
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        self.file.metadata()
+    }
+}
+
+//Any stream of bytes, e.g. the stdin or the output of a command, gunzipped if it starts with the gzip magic bytes
+pub(crate) struct ReadSource<R: io::Read> {
+    rdr: Option<Decoded<R>>,
+    name: String,
+}
+
+//The stream can't be told as gzipped until the first bytes arrive, e.g. of a slow command, so it is checked on the
+//first read rather than when the table is opened
+enum Decoded<R: io::Read> {
+    Unknown(io::BufReader<R>),
+    Plain(io::BufReader<R>),
+    Gzip(io::BufReader<GzipReader<io::BufReader<R>>>),
+}
+
+impl<R: io::Read> ReadSource<R> {
+    pub(crate) fn new(name: &str, rdr: R, capacity: usize) -> Self {
+        ReadSource {
+            rdr: Some(Decoded::Unknown(io::BufReader::with_capacity(capacity, rdr))),
+            name: name.to_string(),
+        }
+    }
+}
+
+impl ReadSource<io::Stdin> {
+    pub(crate) fn stdin(capacity: usize) -> Self {
+        ReadSource::new("stdin", io::stdin(), capacity)
+    }
+}
+
+impl ReadSource<CommandOutput> {
+    pub(crate) fn command(command: &str, capacity: usize) -> io::Result<Self> {
+        Ok(ReadSource::new(command, CommandOutput::spawn(command)?, capacity))
+    }
+}
+
+impl<R: io::Read> Source for ReadSource<R> {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        if let Some(Decoded::Unknown(_)) = self.rdr {
+            let mut rdr = match self.rdr.take() {
+                Some(Decoded::Unknown(rdr)) => rdr,
+                _ => unreachable!(),
+            };
+            let decoded = match rdr.fill_buf() {
+                Ok(bytes) if bytes.starts_with(&GZIP_MAGIC) => {
+                    let capacity = rdr.capacity();
+                    Decoded::Gzip(io::BufReader::with_capacity(capacity, GzipReader::new(rdr)))
+                }
+                Ok(_) => Decoded::Plain(rdr),
+                Err(e) => {
+                    self.rdr = Some(Decoded::Unknown(rdr));
+                    return Err(e);
+                }
+            };
+            self.rdr = Some(decoded);
+        }
+
+        match self.rdr.as_mut() {
+            Some(Decoded::Plain(rdr)) => rdr.read_line(buf),
+            Some(Decoded::Gzip(rdr)) => rdr.read_line(buf),
+            _ => unreachable!(),
+        }
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            name: self.name.clone(),
+            path: None,
+        }
+    }
+}
+
+//The stdout of a shell command feeding a table, e.g. "aws s3 cp s3://bucket/key -"
+pub(crate) struct CommandOutput {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl CommandOutput {
+    pub(crate) fn spawn(command: &str) -> io::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().unwrap();
+
+        Ok(CommandOutput { child, stdout })
+    }
+}
+
+impl io::Read for CommandOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            //A failing command should not look like an empty table
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("command exited with {}", status)));
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+//The table may be dropped before the command ends, e.g. after a limit, so the command is killed and reaped rather
+//than left running as a zombie
+impl Drop for CommandOutput {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sources() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        File::create(&path).unwrap().write_all(b"a\nb").unwrap();
+
+        let mut source = FileSource::open(&path, 16).unwrap();
+        let mut buf = String::new();
+        assert_eq!(2, source.read_line(&mut buf).unwrap());
+        assert_eq!("a\n", buf);
+        buf.clear();
+        assert_eq!(1, source.read_line(&mut buf).unwrap());
+        buf.clear();
+        assert_eq!(0, source.read_line(&mut buf).unwrap());
+        assert_eq!(Some(path.clone()), source.metadata().path);
+
+        let gz_path = dir.path().join("access.log.gz");
+        let mut wtr = crate::common::compress::GzipWriter::new(File::create(&gz_path).unwrap()).unwrap();
+        wtr.write_all(b"a\nb\n").unwrap();
+        wtr.finish().unwrap();
+        let mut source = FileSource::open(&gz_path, 16).unwrap();
+        let mut buf = String::new();
+        assert_eq!(2, source.read_line(&mut buf).unwrap());
+        assert_eq!(2, source.read_line(&mut buf).unwrap());
+        assert_eq!("a\nb\n", buf);
+
+        let mut source = ReadSource::command("printf 'x\\ny\\n'", 16).unwrap();
+        let mut lines = Vec::new();
+        let mut buf = String::new();
+        while source.read_line(&mut buf).unwrap() > 0 {
+            lines.push(buf.clone());
+            buf.clear();
+        }
+        assert_eq!(vec!["x\n".to_string(), "y\n".to_string()], lines);
+        assert_eq!(None, source.metadata().path);
+
+        let mut source = ReadSource::command("exit 3", 16).unwrap();
+        assert!(source.read_line(&mut buf).is_err());
+
+        //The gzipped output of a command, e.g. of "aws s3 cp", is gunzipped like the gzipped files
+        let command = format!("cat {}", gz_path.display());
+        let mut source = ReadSource::command(&command, 16).unwrap();
+        let mut buf = String::new();
+        while source.read_line(&mut buf).unwrap() > 0 {}
+        assert_eq!("a\nb\n", buf);
+    }
+
+    #[test]
+    fn test_command_output_drop() {
+        let mut output = CommandOutput::spawn("while true; do echo line; done").unwrap();
+        let pid = output.child.id() as libc::pid_t;
+        let mut buf = [0; 5];
+        io::Read::read_exact(&mut output, &mut buf).unwrap();
+        assert_eq!(b"line\n", &buf);
+
+        drop(output);
+        //The killed command is reaped, so there is no such process anymore
+        assert_eq!(-1, unsafe { libc::kill(pid, 0) });
+    }
+}
@@ -1,12 +1,25 @@
-use super::datasource::RecordRead;
-use super::types::{Aggregate, Formula, Named, NamedAggregate, StreamResult};
+use super::datasource::{derived_columns, list_log_files, path_columns, ReaderBuilder, ReaderError, RecordRead};
+use super::index::{FileIndex, ValueSketch};
+use super::types::{
+    compare_records, Aggregate, Formula, Join, Named, NamedAggregate, NamedWindow, StreamError, StreamResult,
+};
 use crate::common;
-use crate::common::types::{Tuple, Value, VariableName, Variables};
+use crate::common::trace;
+use crate::common::types::{
+    value_to_json, DateTimeFormat, NumberFormat, Renderer, Tuple, Value, VariableName, Variables,
+};
 use crate::syntax::ast;
+use chrono::{DateTime, FixedOffset};
+use hashbrown::HashMap;
 use linked_hash_map::LinkedHashMap;
 use prettytable::Cell;
 use std::collections::hash_set;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
 
 fn get_value_by_path_expr(path_expr: &ast::PathExpr, i: usize, variables: &Variables) -> Value {
     if i >= path_expr.path_segments.len() {
@@ -57,11 +70,24 @@ fn get_value_by_path_expr(path_expr: &ast::PathExpr, i: usize, variables: &Varia
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 pub(crate) struct Record {
     variables: LinkedHashMap<String, Value>,
+    //The columns like __file and __line, visible to the expressions but not part of "select *"
+    virtual_columns: Variables,
+    //The record it was projected from, shared rather than copied, its columns stay visible to the expressions
+    source: Option<Arc<Record>>,
 }
 
+//The virtual columns are the context of the record rather than its content, they are not compared
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.variables == other.variables
+    }
+}
+
+impl Eq for Record {}
+
 impl Record {
     pub(crate) fn new(field_names: &Vec<VariableName>, data: Vec<Value>) -> Self {
         let mut variables = LinkedHashMap::default();
@@ -69,11 +95,48 @@ impl Record {
             variables.insert(field_names[i].clone(), v);
         }
 
-        Record { variables }
+        Record {
+            variables,
+            virtual_columns: Variables::default(),
+            source: None,
+        }
     }
 
     pub(crate) fn new_with_variables(variables: Variables) -> Self {
-        Record { variables }
+        Record {
+            variables,
+            virtual_columns: Variables::default(),
+            source: None,
+        }
+    }
+
+    pub(crate) fn with_source(mut self, source: Arc<Record>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub(crate) fn insert_virtual_column(&mut self, field_name: VariableName, value: Value) {
+        self.virtual_columns.insert(field_name, value);
+    }
+
+    //The variables to evaluate the expressions against, the record fields shadow the virtual columns, which shadow
+    //the columns of the source record
+    pub(crate) fn merged_variables(&self, variables: &Variables) -> Variables {
+        let mut merged = match &self.source {
+            Some(source) => source.merged_variables(variables),
+            None => variables.clone(),
+        };
+        merged.extend(
+            self.virtual_columns
+                .iter()
+                .chain(self.variables.iter())
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        merged
+    }
+
+    pub(crate) fn insert(&mut self, field_name: VariableName, value: Value) {
+        self.variables.insert(field_name, value);
     }
 
     pub(crate) fn get(&self, field_name: &ast::PathExpr) -> Value {
@@ -114,48 +177,64 @@ impl Record {
         self.variables.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
 
-    pub(crate) fn to_row(&self) -> Vec<Cell> {
+    pub(crate) fn to_row(&self, datetime_format: &DateTimeFormat, number_format: NumberFormat) -> Vec<Cell> {
         self.variables
             .values()
             .map(|val| match val {
                 Value::String(s) => Cell::new(&*s),
-                Value::Int(i) => Cell::new(&*i.to_string()),
-                Value::Float(f) => Cell::new(&*f.to_string()),
+                Value::Int(i) => Cell::new(&number_format.format_int(i64::from(*i))),
+                Value::Long(l) => Cell::new(&number_format.format_int(*l)),
+                Value::Float(f) => Cell::new(&number_format.format_float(f.into_inner())),
                 Value::Boolean(b) => Cell::new(&*b.to_string()),
                 Value::Null => Cell::new("<null>"),
-                Value::DateTime(dt) => Cell::new(&*dt.to_string()),
+                Value::DateTime(dt) => Cell::new(&datetime_format.format(dt)),
                 Value::HttpRequest(request) => Cell::new(&*request.to_string()),
                 Value::Host(host) => Cell::new(&*host.to_string()),
                 Value::Missing => Cell::new("<null>"),
-                Value::Object(_) => Cell::new("{...}"),
-                Value::Array(_) => Cell::new("[...]"),
+                Value::Object(_) | Value::Array(_) => Cell::new(&value_to_json(val, datetime_format).dump()),
             })
             .collect()
     }
 
-    pub(crate) fn to_csv_record(&self) -> Vec<String> {
+    pub(crate) fn to_csv_record(&self, datetime_format: &DateTimeFormat) -> Vec<String> {
         self.variables
             .values()
             .map(|val| match val {
                 Value::String(s) => s.to_string(),
                 Value::Int(i) => i.to_string(),
+                Value::Long(l) => l.to_string(),
                 Value::Float(f) => f.to_string(),
                 Value::Boolean(b) => b.to_string(),
                 Value::Null => "<null>".to_string(),
-                Value::DateTime(dt) => dt.to_string(),
+                Value::DateTime(dt) => datetime_format.format(dt),
                 Value::HttpRequest(request) => request.to_string(),
                 Value::Host(host) => host.to_string(),
                 Value::Missing => "<null>".to_string(),
-                Value::Object(_) => "{...}".to_string(),
-                Value::Array(_) => "[...]".to_string(),
+                Value::Object(_) | Value::Array(_) => value_to_json(val, datetime_format).dump(),
             })
             .collect()
     }
 }
 
+//What a stream gives in the next run of --follow after it is resumed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resume {
+    //It can't continue, the query has to run again from the start
+    Unsupported,
+    //The records of the lines appended since the previous run
+    Incremental,
+    //The whole result again, updated with the lines appended since the previous run, e.g. the aggregates
+    Full,
+}
+
 pub(crate) trait RecordStream {
     fn next(&mut self) -> StreamResult<Option<Record>>;
     fn close(&self);
+
+    //Prepares the exhausted stream for the next run of --follow, keeping its state, e.g. the aggregates
+    fn resume(&mut self) -> Resume {
+        Resume::Unsupported
+    }
 }
 
 pub(crate) struct MapStream {
@@ -179,9 +258,13 @@ impl RecordStream for MapStream {
         self.source.close();
     }
 
+    fn resume(&mut self) -> Resume {
+        self.source.resume()
+    }
+
     fn next(&mut self) -> StreamResult<Option<Record>> {
         if let Some(record) = self.source.next()? {
-            let variables = common::types::merge(&self.variables, record.to_variables());
+            let variables = record.merged_variables(&self.variables);
 
             let capacity = self.named_list.len();
             let mut field_names = Vec::with_capacity(capacity);
@@ -209,8 +292,9 @@ impl RecordStream for MapStream {
                 }
             }
 
-            let record = Record::new(&field_names, data);
-            Ok(Some(record))
+            //The fields of the source record stay visible to the where clause applied after the projection
+            let mapped_record = Record::new(&field_names, data).with_source(Arc::new(record));
+            Ok(Some(mapped_record))
         } else {
             Ok(None)
         }
@@ -248,31 +332,39 @@ impl RecordStream for LimitStream {
     fn close(&self) {
         self.source.close();
     }
+
+    //The limit is of every run for the whole result, and of all the runs for the appended records
+    fn resume(&mut self) -> Resume {
+        let resume = self.source.resume();
+        if resume == Resume::Full {
+            self.curr = 0;
+        }
+        resume
+    }
 }
 
-pub(crate) struct FilterStream {
-    formula: Formula,
-    variables: Variables,
+//Passes the first record of each distinct key through, the records are already in the order of the query
+pub(crate) struct DistinctOnStream {
+    keys: Vec<ast::PathExpr>,
+    seen: hash_set::HashSet<Vec<Value>>,
     source: Box<dyn RecordStream>,
 }
 
-impl FilterStream {
-    pub(crate) fn new(formula: Formula, variables: Variables, source: Box<dyn RecordStream>) -> Self {
-        FilterStream {
-            formula,
-            variables,
+impl DistinctOnStream {
+    pub(crate) fn new(keys: Vec<ast::PathExpr>, source: Box<dyn RecordStream>) -> Self {
+        DistinctOnStream {
+            keys,
+            seen: hash_set::HashSet::new(),
             source,
         }
     }
 }
 
-impl RecordStream for FilterStream {
+impl RecordStream for DistinctOnStream {
     fn next(&mut self) -> StreamResult<Option<Record>> {
         while let Some(record) = self.source.next()? {
-            let variables = common::types::merge(&self.variables, record.to_variables());
-            let predicate = self.formula.evaluate(&variables)?;
-
-            if predicate {
+            let key: Vec<Value> = self.keys.iter().map(|k| record.get(k)).collect();
+            if self.seen.insert(key) {
                 return Ok(Some(record));
             }
         }
@@ -283,217 +375,165 @@ impl RecordStream for FilterStream {
     fn close(&self) {
         self.source.close();
     }
+
+    fn resume(&mut self) -> Resume {
+        match self.source.resume() {
+            Resume::Incremental => Resume::Incremental,
+            Resume::Full => {
+                self.seen.clear();
+                Resume::Full
+            }
+            Resume::Unsupported => Resume::Unsupported,
+        }
+    }
 }
 
-pub(crate) struct InMemoryStream {
-    pub(crate) data: VecDeque<Record>,
+//The hash join, the records of the right side are kept by their keys and the records of the left side are streamed
+//through. A null key matches nothing, the left join gives the missing right side to the rows without a match.
+pub(crate) struct JoinStream {
+    join: Join,
+    left: Box<dyn RecordStream>,
+    right: Option<Box<dyn RecordStream>>,
+    table: HashMap<Tuple, Vec<Value>>,
+    pending: VecDeque<Record>,
 }
 
-impl InMemoryStream {
-    pub(crate) fn new(data: VecDeque<Record>) -> InMemoryStream {
-        InMemoryStream { data }
+impl JoinStream {
+    pub(crate) fn new(join: Join, left: Box<dyn RecordStream>, right: Box<dyn RecordStream>) -> Self {
+        JoinStream {
+            join,
+            left,
+            right: Some(right),
+            table: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    //The keys of the two sides compare as the same type, e.g. the host of a log format and the string of a jsonl table
+    fn key(record: &Record, keys: &[ast::PathExpr]) -> Tuple {
+        record
+            .get_many(keys)
+            .into_iter()
+            .map(|value| match value {
+                Value::Int(i) => Value::Long(i64::from(i)),
+                Value::Host(host) => Value::String(host.to_string()),
+                Value::HttpRequest(request) => Value::String(request.to_string()),
+                value => value,
+            })
+            .collect()
+    }
+
+    fn joined(&self, left: Value, right: Value) -> Record {
+        let mut variables = Variables::default();
+        variables.insert(self.join.left_name.clone(), left);
+        variables.insert(self.join.right_name.clone(), right);
+        Record::new_with_variables(variables)
     }
 }
 
-impl RecordStream for InMemoryStream {
+impl RecordStream for JoinStream {
     fn next(&mut self) -> StreamResult<Option<Record>> {
-        if let Some(record) = self.data.pop_front() {
-            Ok(Some(record))
-        } else {
-            Ok(None)
+        if let Some(mut right) = self.right.take() {
+            while let Some(record) = right.next()? {
+                let key = JoinStream::key(&record, &self.join.right_keys);
+                if key.iter().any(|v| *v == Value::Null || *v == Value::Missing) {
+                    continue;
+                }
+                self.table
+                    .entry(key)
+                    .or_default()
+                    .push(Value::Object(record.to_variables().clone()));
+            }
+            right.close();
+        }
+
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Ok(Some(record));
+            }
+
+            let record = match self.left.next()? {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            let left = Value::Object(record.to_variables().clone());
+            match self.table.get(&JoinStream::key(&record, &self.join.left_keys)) {
+                Some(matches) => {
+                    let joined: Vec<Record> = matches
+                        .iter()
+                        .map(|right| self.joined(left.clone(), right.clone()))
+                        .collect();
+                    self.pending.extend(joined);
+                }
+                None if self.join.kind == ast::JoinKind::Left => return Ok(Some(self.joined(left, Value::Missing))),
+                None => {}
+            }
         }
     }
 
-    fn close(&self) {}
+    fn close(&self) {
+        self.left.close();
+        if let Some(right) = &self.right {
+            right.close();
+        }
+    }
 }
 
-pub(crate) struct GroupByStream {
-    keys: Vec<ast::PathExpr>,
-    variables: Variables,
-    aggregates: Vec<NamedAggregate>,
+//Appends the window functions to the records, which are in the order of the query already. The previous rows are the
+//last ones with the same partition keys, and the time column gives the seconds elapsed since the previous row.
+pub(crate) struct WindowStream {
+    windows: Vec<NamedWindow>,
+    partition_keys: Vec<ast::PathExpr>,
+    time: ast::PathExpr,
+    preceding_rows: usize,
+    previous: HashMap<Vec<Value>, VecDeque<Record>>,
     source: Box<dyn RecordStream>,
-    group_iterator: Option<hash_set::IntoIter<Option<Tuple>>>,
 }
 
-impl<'a> GroupByStream {
+impl WindowStream {
     pub(crate) fn new(
-        keys: Vec<ast::PathExpr>,
-        variables: Variables,
-        aggregates: Vec<NamedAggregate>,
+        windows: Vec<NamedWindow>,
+        partition_keys: Vec<ast::PathExpr>,
+        time: ast::PathExpr,
         source: Box<dyn RecordStream>,
     ) -> Self {
-        GroupByStream {
-            keys,
-            variables,
-            aggregates,
+        let preceding_rows = windows.iter().map(|w| w.function.preceding_rows()).max().unwrap_or(0);
+        WindowStream {
+            windows,
+            partition_keys,
+            time,
+            preceding_rows,
+            previous: HashMap::new(),
             source,
-            group_iterator: None,
         }
     }
 }
 
-impl RecordStream for GroupByStream {
+impl RecordStream for WindowStream {
     fn next(&mut self) -> StreamResult<Option<Record>> {
-        if self.group_iterator.is_none() {
-            let mut groups: hash_set::HashSet<Option<Tuple>> = hash_set::HashSet::new();
-            while let Some(record) = self.source.next()? {
-                let variables = common::types::merge(&self.variables, record.to_variables());
-
-                let key = if self.keys.is_empty() {
-                    None
-                } else {
-                    Some(record.get_many(&self.keys))
-                };
-
-                groups.insert(key.clone());
-                for named_agg in self.aggregates.iter_mut() {
-                    match &mut named_agg.aggregate {
-                        Aggregate::GroupAs(ref mut inner, named) => {
-                            let val = match named {
-                                Named::Expression(_expr, _) => Value::Object(record.to_variables().clone()),
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-
-                            inner.add_record(&key, &val)?;
-                        }
-                        Aggregate::Avg(ref mut inner, named) => {
-                            let val = match named {
-                                Named::Expression(expr, _) => expr.expression_value(&variables)?,
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-
-                            inner.add_record(&key, &val)?;
-                        }
-                        Aggregate::Count(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    inner.add_row(key.clone())?;
-                                }
-                            };
-                        }
-                        Aggregate::First(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-                        }
-                        Aggregate::Last(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-                        }
-                        Aggregate::Max(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-                        }
-                        Aggregate::Min(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-                        }
-                        Aggregate::Sum(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-                        }
-                        Aggregate::ApproxCountDistinct(ref mut inner, named) => {
-                            match named {
-                                Named::Expression(expr, _) => {
-                                    let val = expr.expression_value(&variables)?;
-                                    inner.add_record(&key, &val)?;
-                                }
-                                Named::Star => {
-                                    unreachable!();
-                                }
-                            };
-                        }
-                        Aggregate::PercentileDisc(ref mut inner, column_name) => {
-                            let val = variables.get(column_name).unwrap();
-                            inner.add_record(&key, val)?;
-                        }
-                        Aggregate::ApproxPercentile(ref mut inner, column_name) => {
-                            let val = variables.get(column_name).unwrap();
-                            inner.add_record(&key, val)?;
-                        }
-                    }
-                }
-            }
-
-            self.group_iterator = Some(groups.into_iter());
-        }
-
-        let iter = self.group_iterator.as_mut().unwrap();
-        if let Some(key) = iter.next() {
-            let mut values: Vec<Value> = Vec::new();
-            let mut fields: Vec<VariableName> = Vec::new();
-
-            if let Some(values_in_key) = &key {
-                for (position_idx, k) in self.keys.iter().enumerate() {
-                    match k.path_segments.last().unwrap() {
-                        ast::PathSegment::AttrName(s) => {
-                            fields.push(s.clone());
-                        }
-                        ast::PathSegment::ArrayIndex(_s, _idx) => {
-                            fields.push(format!("_{}", position_idx + 1));
-                        }
-                    }
-                }
-
-                for v in values_in_key {
-                    values.push(v.clone());
-                }
+        if let Some(mut record) = self.source.next()? {
+            let key = record.get_many(&self.partition_keys);
+            let (time, windows) = (&self.time, &self.windows);
+            let previous = self.previous.entry(key).or_default();
+            let elapsed = match (record.get(time), previous.back().map(|r| r.get(time))) {
+                (Value::DateTime(a), Some(Value::DateTime(b))) => Some(a - b),
+                _ => None,
+            };
+            let values: Vec<Value> = windows
+                .iter()
+                .map(|w| {
+                    let previous_values: Vec<Value> = previous.iter().map(|r| r.get(&w.column)).collect();
+                    w.function.apply(&record.get(&w.column), &previous_values, elapsed)
+                })
+                .collect();
+
+            previous.push_back(record.clone());
+            if previous.len() > self.preceding_rows {
+                previous.pop_front();
             }
-
-            for named_agg in self.aggregates.iter_mut() {
-                if let Some(ref field_name) = named_agg.name_opt {
-                    fields.push(field_name.clone());
-                } else {
-                    let idx = fields.len() + 1;
-                    fields.push(format!("_{}", idx));
-                }
-                let v = named_agg.aggregate.get_aggregated(&key)?;
-                values.push(v);
+            for (window, value) in self.windows.iter().zip(values) {
+                record.insert(window.name.clone(), value);
             }
-
-            let record = Record::new(&fields, values);
             Ok(Some(record))
         } else {
             Ok(None)
@@ -503,17 +543,838 @@ impl RecordStream for GroupByStream {
     fn close(&self) {
         self.source.close();
     }
+
+    //The previous rows carry over to the appended records, but not to the whole result again
+    fn resume(&mut self) -> Resume {
+        match self.source.resume() {
+            Resume::Incremental => Resume::Incremental,
+            _ => Resume::Unsupported,
+        }
+    }
 }
 
-pub(crate) struct ProjectionStream {
-    pub(crate) source: Box<dyn RecordStream>,
-    pub(crate) bindings: Vec<common::types::Binding>,
-    produced_records: Option<Vec<Record>>,
-    idx: usize,
+//Sorts the records of the source, which are all read at the first record
+pub(crate) struct OrderByStream {
+    column_names: Vec<ast::PathExpr>,
+    orderings: Vec<super::types::Ordering>,
+    sorted: Option<VecDeque<Record>>,
+    source: Box<dyn RecordStream>,
 }
 
-impl ProjectionStream {
-    pub(crate) fn new(source: Box<dyn RecordStream>, bindings: Vec<common::types::Binding>) -> Self {
+impl OrderByStream {
+    pub(crate) fn new(
+        column_names: Vec<ast::PathExpr>,
+        orderings: Vec<super::types::Ordering>,
+        source: Box<dyn RecordStream>,
+    ) -> Self {
+        OrderByStream {
+            column_names,
+            orderings,
+            sorted: None,
+            source,
+        }
+    }
+}
+
+impl RecordStream for OrderByStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        if self.sorted.is_none() {
+            let mut records = Vec::new();
+            while let Some(record) = self.source.next()? {
+                records.push(record);
+            }
+            records.sort_by(|a, b| compare_records(a, b, &self.column_names, &self.orderings));
+            self.sorted = Some(VecDeque::from(records));
+        }
+
+        Ok(self.sorted.as_mut().and_then(|s| s.pop_front()))
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    fn resume(&mut self) -> Resume {
+        self.sorted = None;
+        self.source.resume()
+    }
+}
+
+//Inserts the rows of the time buckets missing between the first and the last bucket of each partition, the other
+//columns of the inserted rows are the fill value. The rows come out by partition and then by time.
+pub(crate) struct GapFillStream {
+    time: ast::PathExpr,
+    interval: chrono::Duration,
+    partition_keys: Vec<ast::PathExpr>,
+    fill: Value,
+    filled: Option<VecDeque<Record>>,
+    source: Box<dyn RecordStream>,
+}
+
+impl GapFillStream {
+    pub(crate) fn new(
+        time: ast::PathExpr,
+        interval: chrono::Duration,
+        partition_keys: Vec<ast::PathExpr>,
+        fill: Value,
+        source: Box<dyn RecordStream>,
+    ) -> Self {
+        GapFillStream {
+            time,
+            interval,
+            partition_keys,
+            fill,
+            filled: None,
+            source,
+        }
+    }
+
+    fn gap_record(&self, record: &Record, bucket: DateTime<FixedOffset>) -> Record {
+        let time = self.time.unwrap_last();
+        let keys: Vec<VariableName> = self.partition_keys.iter().map(|k| k.unwrap_last()).collect();
+        let (field_names, values): (Vec<VariableName>, Vec<Value>) = record
+            .to_tuples()
+            .into_iter()
+            .map(|(name, value)| {
+                if name == time {
+                    (name, Value::DateTime(bucket))
+                } else if keys.contains(&name) {
+                    (name, value)
+                } else {
+                    (name, self.fill.clone())
+                }
+            })
+            .collect();
+        Record::new(&field_names, values)
+    }
+
+    fn fill(&mut self) -> StreamResult<VecDeque<Record>> {
+        let mut partitions: LinkedHashMap<Vec<Value>, Vec<Record>> = LinkedHashMap::new();
+        while let Some(record) = self.source.next()? {
+            let key = record.get_many(&self.partition_keys);
+            partitions.entry(key).or_insert_with(Vec::new).push(record);
+        }
+
+        let mut filled = VecDeque::new();
+        for (_, mut records) in partitions.into_iter() {
+            //The rows without a time bucket, e.g. of a null timestamp, are kept after the others
+            records.sort_by_key(|r| match r.get(&self.time) {
+                Value::DateTime(dt) => (false, Some(dt)),
+                _ => (true, None),
+            });
+            let mut previous: Option<DateTime<FixedOffset>> = None;
+            for record in records.into_iter() {
+                if let Value::DateTime(dt) = record.get(&self.time) {
+                    if let Some(mut bucket) = previous {
+                        bucket = bucket + self.interval;
+                        while bucket < dt {
+                            filled.push_back(self.gap_record(&record, bucket));
+                            bucket = bucket + self.interval;
+                        }
+                    }
+                    previous = Some(dt);
+                }
+                filled.push_back(record);
+            }
+        }
+
+        Ok(filled)
+    }
+}
+
+impl RecordStream for GapFillStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        if self.filled.is_none() {
+            self.filled = Some(self.fill()?);
+        }
+
+        Ok(self.filled.as_mut().and_then(|f| f.pop_front()))
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    //The gaps between the appended records and the earlier ones are unknown without the earlier ones
+    fn resume(&mut self) -> Resume {
+        match self.source.resume() {
+            Resume::Full => {
+                self.filled = None;
+                Resume::Full
+            }
+            _ => Resume::Unsupported,
+        }
+    }
+}
+
+//Logs the records passing through the operator, and the count and the time spent once the operator is exhausted
+pub(crate) struct TracedStream {
+    span: trace::Span,
+    records: usize,
+    source: Box<dyn RecordStream>,
+}
+
+impl TracedStream {
+    pub(crate) fn new(span: trace::Span, source: Box<dyn RecordStream>) -> Self {
+        TracedStream {
+            span,
+            records: 0,
+            source,
+        }
+    }
+}
+
+impl RecordStream for TracedStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        let source = &mut self.source;
+        let ans = self.span.in_scope(|| source.next());
+        match &ans {
+            Ok(Some(record)) => {
+                self.records += 1;
+                trace::event(
+                    trace::Level::Trace,
+                    self.span.name(),
+                    format_args!("record {}: {:?}", self.records, record.to_tuples()),
+                );
+            }
+            Ok(None) => self.span.close(format_args!("records={}", self.records)),
+            Err(e) => trace::event(
+                trace::Level::Error,
+                self.span.name(),
+                format_args!("failed after {} records: {}", self.records, e),
+            ),
+        }
+
+        ans
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    fn resume(&mut self) -> Resume {
+        self.source.resume()
+    }
+}
+
+impl Drop for TracedStream {
+    fn drop(&mut self) {
+        //The operator stopped early, e.g. by a limit or an error
+        self.span
+            .close(format_args!("records={} (not exhausted)", self.records));
+    }
+}
+
+//Selects and reorders the output columns by name. A column missing from the first record is an error,
+//since it is most likely a typo, and it is null in the later records without it.
+pub(crate) struct ColumnsStream {
+    columns: Vec<VariableName>,
+    checked: bool,
+    source: Box<dyn RecordStream>,
+}
+
+impl ColumnsStream {
+    pub(crate) fn new(columns: Vec<VariableName>, source: Box<dyn RecordStream>) -> Self {
+        ColumnsStream {
+            columns,
+            checked: false,
+            source,
+        }
+    }
+}
+
+impl RecordStream for ColumnsStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        if let Some(record) = self.source.next()? {
+            if !self.checked {
+                if let Some(column) = self.columns.iter().find(|c| !record.to_variables().contains_key(*c)) {
+                    return Err(StreamError::UnknownColumn(column.clone()));
+                }
+                self.checked = true;
+            }
+
+            let data = self
+                .columns
+                .iter()
+                .map(|c| record.to_variables().get(c).cloned().unwrap_or(Value::Null))
+                .collect();
+            Ok(Some(Record::new(&self.columns, data)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    fn resume(&mut self) -> Resume {
+        self.source.resume()
+    }
+}
+
+//Renders the output columns for the humans, after the query so that the expressions see the raw values.
+//A column missing from the first record is an error like in ColumnsStream.
+pub(crate) struct RenderStream {
+    renders: Vec<(VariableName, Renderer)>,
+    checked: bool,
+    source: Box<dyn RecordStream>,
+}
+
+impl RenderStream {
+    pub(crate) fn new(renders: Vec<(VariableName, Renderer)>, source: Box<dyn RecordStream>) -> Self {
+        RenderStream {
+            renders,
+            checked: false,
+            source,
+        }
+    }
+}
+
+impl RecordStream for RenderStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        if let Some(mut record) = self.source.next()? {
+            if !self.checked {
+                if let Some((column, _)) = self.renders.iter().find(|(c, _)| !record.variables.contains_key(c)) {
+                    return Err(StreamError::UnknownColumn(column.clone()));
+                }
+                self.checked = true;
+            }
+
+            for (column, renderer) in self.renders.iter() {
+                if let Some(value) = record.variables.get_mut(column) {
+                    *value = renderer.render(value);
+                }
+            }
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    fn resume(&mut self) -> Resume {
+        self.source.resume()
+    }
+}
+
+//Concatenates the results of the select statements of an "union all"
+pub(crate) struct UnionStream {
+    curr: usize,
+    sources: Vec<Box<dyn RecordStream>>,
+}
+
+impl UnionStream {
+    pub(crate) fn new(sources: Vec<Box<dyn RecordStream>>) -> Self {
+        UnionStream { curr: 0, sources }
+    }
+}
+
+impl RecordStream for UnionStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        while self.curr < self.sources.len() {
+            if let Some(record) = self.sources[self.curr].next()? {
+                return Ok(Some(record));
+            }
+            self.curr += 1;
+        }
+
+        Ok(None)
+    }
+
+    fn close(&self) {
+        for source in self.sources.iter() {
+            source.close();
+        }
+    }
+
+    //The statements have to agree on what the next run gives
+    fn resume(&mut self) -> Resume {
+        self.curr = 0;
+        let resumes: Vec<Resume> = self.sources.iter_mut().map(|source| source.resume()).collect();
+        match resumes.first() {
+            Some(&first) if resumes.iter().all(|&r| r == first) => first,
+            _ => Resume::Unsupported,
+        }
+    }
+}
+
+pub(crate) struct FilterStream {
+    formula: Formula,
+    variables: Variables,
+    source: Box<dyn RecordStream>,
+}
+
+impl FilterStream {
+    pub(crate) fn new(formula: Formula, variables: Variables, source: Box<dyn RecordStream>) -> Self {
+        FilterStream {
+            formula,
+            variables,
+            source,
+        }
+    }
+}
+
+impl RecordStream for FilterStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        while let Some(record) = self.source.next()? {
+            let variables = record.merged_variables(&self.variables);
+            let predicate = self.formula.evaluate(&variables)?;
+
+            if predicate {
+                return Ok(Some(record));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    fn resume(&mut self) -> Resume {
+        self.source.resume()
+    }
+}
+
+#[allow(dead_code)]
+pub(crate) struct InMemoryStream {
+    pub(crate) data: VecDeque<Record>,
+}
+
+#[allow(dead_code)]
+impl InMemoryStream {
+    pub(crate) fn new(data: VecDeque<Record>) -> InMemoryStream {
+        InMemoryStream { data }
+    }
+}
+
+impl RecordStream for InMemoryStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        if let Some(record) = self.data.pop_front() {
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn close(&self) {}
+}
+
+//The records of a stream running on its own thread. The channel holds at most capacity records, so the thread blocks
+//when the consumer falls behind, e.g. a slow output, instead of buffering the whole input in memory.
+pub(crate) struct ChannelStream {
+    receiver: mpsc::Receiver<StreamResult<Record>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ChannelStream {
+    pub(crate) fn spawn<F>(capacity: usize, create: F) -> Self
+    where
+        F: FnOnce() -> StreamResult<Box<dyn RecordStream>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let handle = std::thread::spawn(move || {
+            let mut stream = match create() {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    return;
+                }
+            };
+
+            loop {
+                let item = match stream.next() {
+                    Ok(Some(record)) => Ok(record),
+                    Ok(None) => break,
+                    Err(e) => Err(e),
+                };
+                let failed = item.is_err();
+                //The consumer is gone, e.g. after a limit, or the stream has failed
+                if sender.send(item).is_err() || failed {
+                    break;
+                }
+            }
+            stream.close();
+        });
+
+        ChannelStream {
+            receiver,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl RecordStream for ChannelStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        match self.receiver.recv() {
+            Ok(item) => item.map(Some),
+            //The thread has finished, either at the end of the stream or by a panic which must not pass for the end
+            Err(_) => match self.handle.take().map(|handle| handle.join()) {
+                Some(Err(payload)) => {
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown cause".to_string());
+                    Err(StreamError::ReaderPanicked(message))
+                }
+                _ => Ok(None),
+            },
+        }
+    }
+
+    fn close(&self) {}
+}
+
+//The bound on the number of the distinct keys of a group by, beyond which a warning is printed or the query is aborted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GroupLimit {
+    pub(crate) max_groups: usize,
+    pub(crate) abort: bool,
+}
+
+pub(crate) struct GroupByStream {
+    keys: Vec<ast::PathExpr>,
+    variables: Variables,
+    aggregates: Vec<NamedAggregate>,
+    source: Box<dyn RecordStream>,
+    group_limit: Option<GroupLimit>,
+    //The keys seen so far, kept across the runs of --follow with the aggregates
+    groups: hash_set::HashSet<Option<Tuple>>,
+    warned: bool,
+    group_iterator: Option<hash_set::IntoIter<Option<Tuple>>>,
+}
+
+impl<'a> GroupByStream {
+    pub(crate) fn new(
+        keys: Vec<ast::PathExpr>,
+        variables: Variables,
+        aggregates: Vec<NamedAggregate>,
+        source: Box<dyn RecordStream>,
+    ) -> Self {
+        GroupByStream {
+            keys,
+            variables,
+            aggregates,
+            source,
+            group_limit: None,
+            groups: hash_set::HashSet::new(),
+            warned: false,
+            group_iterator: None,
+        }
+    }
+
+    pub(crate) fn group_limit(mut self, group_limit: Option<GroupLimit>) -> Self {
+        self.group_limit = group_limit;
+        self
+    }
+}
+
+impl RecordStream for GroupByStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        if self.group_iterator.is_none() {
+            while let Some(record) = self.source.next()? {
+                let variables = common::types::merge(&self.variables, record.to_variables());
+
+                let key = if self.keys.is_empty() {
+                    None
+                } else {
+                    Some(record.get_many(&self.keys))
+                };
+
+                if self.groups.insert(key.clone()) && !self.warned {
+                    if let Some(group_limit) = &self.group_limit {
+                        if self.groups.len() > group_limit.max_groups {
+                            if group_limit.abort {
+                                return Err(StreamError::TooManyGroups(group_limit.max_groups));
+                            }
+                            trace::warning(format_args!("{}", StreamError::TooManyGroups(group_limit.max_groups)));
+                            self.warned = true;
+                        }
+                    }
+                }
+                for named_agg in self.aggregates.iter_mut() {
+                    match &mut named_agg.aggregate {
+                        Aggregate::GroupAs(ref mut inner, named) => {
+                            let val = match named {
+                                Named::Expression(_expr, _) => Value::Object(record.to_variables().clone()),
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+
+                            inner.add_record(&key, &val)?;
+                        }
+                        Aggregate::Avg(ref mut inner, named) => {
+                            let val = match named {
+                                Named::Expression(expr, _) => expr.expression_value(&variables)?,
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+
+                            inner.add_record(&key, &val)?;
+                        }
+                        Aggregate::Count(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    inner.add_row(key.clone())?;
+                                }
+                            };
+                        }
+                        Aggregate::First(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Last(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Max(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Min(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Sum(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::ApproxCountDistinct(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Rate(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::AnyValue(ref mut inner, named) | Aggregate::Sample(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::ApproxTopK(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Entropy(ref mut inner, named) | Aggregate::Concentration(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::AvgWeighted(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::MinBy(ref mut inner, named) | Aggregate::MaxBy(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Corr(ref mut inner, named) | Aggregate::Covar(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::Ratio(ref mut inner, named) => {
+                            match named {
+                                Named::Expression(expr, _) => {
+                                    let val = expr.expression_value(&variables)?;
+                                    inner.add_record(&key, &val)?;
+                                }
+                                Named::Star => {
+                                    unreachable!();
+                                }
+                            };
+                        }
+                        Aggregate::PercentileDisc(ref mut inner, column_name, weight_opt) => {
+                            let val = variables.get(column_name).unwrap();
+                            if let Some(weight) = weight_opt {
+                                let w = variables.get(weight).unwrap();
+                                inner.add_weighted_record(&key, val, w)?;
+                            } else {
+                                inner.add_record(&key, val)?;
+                            }
+                        }
+                        Aggregate::ApproxPercentile(ref mut inner, column_name, weight_opt) => {
+                            let val = variables.get(column_name).unwrap();
+                            if let Some(weight) = weight_opt {
+                                let w = variables.get(weight).unwrap();
+                                inner.add_weighted_record(&key, val, w)?;
+                            } else {
+                                inner.add_record(&key, val)?;
+                            }
+                        }
+                    }
+                }
+            }
+
+            self.group_iterator = Some(self.groups.clone().into_iter());
+        }
+
+        let iter = self.group_iterator.as_mut().unwrap();
+        if let Some(key) = iter.next() {
+            let mut values: Vec<Value> = Vec::new();
+            let mut fields: Vec<VariableName> = Vec::new();
+
+            if let Some(values_in_key) = &key {
+                for (position_idx, k) in self.keys.iter().enumerate() {
+                    match k.path_segments.last().unwrap() {
+                        ast::PathSegment::AttrName(s) => {
+                            fields.push(s.clone());
+                        }
+                        ast::PathSegment::ArrayIndex(_s, _idx) => {
+                            fields.push(format!("_{}", position_idx + 1));
+                        }
+                    }
+                }
+
+                for v in values_in_key {
+                    values.push(v.clone());
+                }
+            }
+
+            for named_agg in self.aggregates.iter_mut() {
+                if let Some(ref field_name) = named_agg.name_opt {
+                    fields.push(field_name.clone());
+                } else {
+                    let idx = fields.len() + 1;
+                    fields.push(format!("_{}", idx));
+                }
+                let v = named_agg.aggregate.get_aggregated(&key)?;
+                values.push(v);
+            }
+
+            let record = Record::new(&fields, values);
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn close(&self) {
+        self.source.close();
+    }
+
+    //The appended records are added to the aggregates, and all the groups are given again
+    fn resume(&mut self) -> Resume {
+        match self.source.resume() {
+            Resume::Incremental => {
+                self.group_iterator = None;
+                Resume::Full
+            }
+            _ => Resume::Unsupported,
+        }
+    }
+}
+
+pub(crate) struct ProjectionStream {
+    pub(crate) source: Box<dyn RecordStream>,
+    pub(crate) bindings: Vec<common::types::Binding>,
+    produced_records: Option<Vec<Record>>,
+    idx: usize,
+}
+
+impl ProjectionStream {
+    pub(crate) fn new(source: Box<dyn RecordStream>, bindings: Vec<common::types::Binding>) -> Self {
         ProjectionStream {
             source,
             bindings,
@@ -616,22 +1477,238 @@ impl RecordStream for ProjectionStream {
                         self.produced_records = Some(produced_records);
                         self.idx = 1;
 
-                        return Ok(Some(r));
-                    }
-                } else {
-                    return Ok(None);
+                        return Ok(Some(r));
+                    }
+                } else {
+                    return Ok(None);
+                }
+            } else {
+                let idx = self.idx;
+                let records = self.produced_records.as_ref().unwrap();
+
+                if idx < records.len() {
+                    let record = records[idx].clone();
+                    self.idx += 1;
+                    return Ok(Some(record));
+                } else {
+                    self.produced_records = None;
+                }
+            }
+        }
+    }
+
+    fn close(&self) {}
+
+    fn resume(&mut self) -> Resume {
+        self.source.resume()
+    }
+}
+
+//The lines read by all the log file streams of the run, for the summary after the output
+static LINES_SCANNED: AtomicUsize = AtomicUsize::new(0);
+static LINES_SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+//The (scanned, skipped due to parse errors) lines so far
+pub(crate) fn scanned_lines() -> (usize, usize) {
+    (
+        LINES_SCANNED.load(Ordering::Relaxed),
+        LINES_SKIPPED.load(Ordering::Relaxed),
+    )
+}
+
+pub(crate) struct LogFileStream {
+    pub(crate) reader: Box<dyn RecordRead>,
+    pub(crate) path_columns: Vec<(VariableName, Value)>,
+    pub(crate) line: usize,
+    pub(crate) skipped: usize,
+}
+
+impl LogFileStream {
+    //The virtual columns of the path are of the source, none for the stdin or a command
+    pub(crate) fn new(reader: Box<dyn RecordRead>) -> Self {
+        let path_columns = path_columns(reader.metadata().path.as_deref());
+        LogFileStream {
+            reader,
+            path_columns,
+            line: 0,
+            skipped: 0,
+        }
+    }
+
+    //The line of the last record or error in the source, counted here if the reader has no lines
+    fn reader_line(&self) -> usize {
+        match self.reader.position() {
+            Some((line, _)) => line,
+            None => self.line + 1,
+        }
+    }
+}
+
+impl RecordStream for LogFileStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        loop {
+            let mut record = match self.reader.read_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => return Ok(None),
+                Err(ReaderError::Io(e)) => return Err(ReaderError::Io(e).into()),
+                //The malformed line is skipped rather than failing the whole run
+                Err(e) => {
+                    self.line = self.reader_line();
+                    self.skipped += 1;
+                    LINES_SCANNED.fetch_add(1, Ordering::Relaxed);
+                    LINES_SKIPPED.fetch_add(1, Ordering::Relaxed);
+                    trace::event(
+                        trace::Level::Warn,
+                        "reader",
+                        format_args!("skipped line {} of {}: {}", self.line, self.reader.metadata().name, e),
+                    );
+                    continue;
+                }
+            };
+
+            self.line = self.reader_line();
+            LINES_SCANNED.fetch_add(1, Ordering::Relaxed);
+            for (name, value) in self.path_columns.iter() {
+                record.insert_virtual_column(name.clone(), value.clone());
+            }
+            //Long past 2 GiB or 2^31 lines
+            let byte_offset = match self.reader.position() {
+                Some((_, offset)) => i64::try_from(offset).map_or(Value::Null, Value::integral),
+                None => Value::Null,
+            };
+            let line = i64::try_from(self.line).map_or(Value::Null, Value::integral);
+            record.insert_virtual_column("__line".to_string(), line);
+            record.insert_virtual_column("__byte_offset".to_string(), byte_offset);
+            for (name, value) in derived_columns(record.to_variables()) {
+                record.insert_virtual_column(name, value);
+            }
+
+            return Ok(Some(record));
+        }
+    }
+
+    fn close(&self) {}
+}
+
+//Reads the log files of a directory one after another
+pub(crate) struct LogFilesStream {
+    builder: ReaderBuilder,
+    paths: VecDeque<PathBuf>,
+    curr: Option<(PathBuf, LogFileStream)>,
+    index: Option<FileIndex>,
+    index_updated: bool,
+    timestamp_range: Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)>,
+    //The sketches of the fields seen in every record of the current file so far
+    sketches: Option<HashMap<String, ValueSketch>>,
+}
+
+impl LogFilesStream {
+    pub(crate) fn new(builder: ReaderBuilder, paths: Vec<PathBuf>, index: Option<FileIndex>) -> Self {
+        LogFilesStream {
+            builder,
+            paths: paths.into_iter().collect(),
+            curr: None,
+            index,
+            index_updated: false,
+            timestamp_range: None,
+            sketches: None,
+        }
+    }
+
+    fn track_record(&mut self, record: &Record) {
+        let timestamp = ["timestamp", "time"].iter().find_map(|name| {
+            match record.get(&ast::PathExpr::new(vec![ast::PathSegment::AttrName(name.to_string())])) {
+                Value::DateTime(dt) => Some(dt),
+                _ => None,
+            }
+        });
+
+        if let Some(dt) = timestamp {
+            self.timestamp_range = match self.timestamp_range {
+                Some((min, max)) => Some((std::cmp::min(min, dt), std::cmp::max(max, dt))),
+                None => Some((dt, dt)),
+            };
+        }
+
+        //A field missing from some record could be matched by a variable of the same name instead, so only the
+        //fields of every record are sketched
+        let variables = record.to_variables();
+        match self.sketches.as_mut() {
+            Some(sketches) => {
+                sketches.retain(|field, _| variables.contains_key(field));
+                for (field, sketch) in sketches.iter_mut() {
+                    sketch.insert(&variables[field]);
                 }
-            } else {
-                let idx = self.idx;
-                let records = self.produced_records.as_ref().unwrap();
+            }
+            None => {
+                let sketches = variables
+                    .iter()
+                    .map(|(field, value)| {
+                        let mut sketch = ValueSketch::default();
+                        sketch.insert(value);
+                        (field.clone(), sketch)
+                    })
+                    .collect();
+                self.sketches = Some(sketches);
+            }
+        }
+    }
 
-                if idx < records.len() {
-                    let record = records[idx].clone();
-                    self.idx += 1;
+    //Remember the timestamp range and the sketches of a fully scanned file for the later queries
+    fn finish_file(&mut self, path: &Path) {
+        let timestamp_range = self.timestamp_range.take();
+        let sketches = self.sketches.take().unwrap_or_default();
+        if let Some(index) = self.index.as_mut() {
+            let result = index.insert(
+                path,
+                timestamp_range.map(|r| r.0),
+                timestamp_range.map(|r| r.1),
+                sketches,
+            );
+            match result {
+                Ok(_) => self.index_updated = true,
+                Err(e) => {
+                    trace::warning(format_args!("failed to update the index: {}", e));
+                    self.index = None;
+                }
+            }
+        }
+    }
+
+    //Failing to save the index only costs the skipping in the later queries, so it is not an error of this query
+    fn save_index(&mut self) {
+        if !self.index_updated {
+            return;
+        }
+        self.index_updated = false;
+        if let Some(Err(e)) = self.index.as_ref().map(|index| index.save()) {
+            trace::warning(format_args!("failed to save the index: {}", e));
+        }
+    }
+}
+
+impl RecordStream for LogFilesStream {
+    fn next(&mut self) -> StreamResult<Option<Record>> {
+        loop {
+            if let Some((_, stream)) = self.curr.as_mut() {
+                if let Some(record) = stream.next()? {
+                    if self.index.is_some() {
+                        self.track_record(&record);
+                    }
                     return Ok(Some(record));
-                } else {
-                    self.produced_records = None;
                 }
+
+                let (path, _) = self.curr.take().unwrap();
+                self.finish_file(&path);
+            }
+
+            if let Some(path) = self.paths.pop_front() {
+                let reader = self.builder.open(&path)?;
+                let stream = LogFileStream::new(reader);
+                self.curr = Some((path, stream));
+            } else {
+                self.save_index();
+                return Ok(None);
             }
         }
     }
@@ -639,26 +1716,75 @@ impl RecordStream for ProjectionStream {
     fn close(&self) {}
 }
 
-pub(crate) struct LogFileStream {
-    pub(crate) reader: Box<dyn RecordRead>,
+//The files scanned before a limit stops the query are still worth remembering
+impl Drop for LogFilesStream {
+    fn drop(&mut self) {
+        self.save_index();
+    }
 }
 
-impl LogFileStream {
-    pub(crate) fn new(reader: Box<dyn RecordRead>) -> Self {
-        LogFileStream { reader }
+//The lines appended to the log files since the previous run of --follow. A directory is listed again every run, and
+//the new files are read from the start.
+pub(crate) struct TailFilesStream {
+    builder: ReaderBuilder,
+    path: PathBuf,
+    files: Vec<(PathBuf, LogFileStream)>,
+    curr: usize,
+}
+
+impl TailFilesStream {
+    pub(crate) fn new(builder: ReaderBuilder, path: PathBuf) -> StreamResult<Self> {
+        let mut stream = TailFilesStream {
+            builder,
+            path,
+            files: Vec::new(),
+            curr: 0,
+        };
+        stream.open_new_files()?;
+        Ok(stream)
+    }
+
+    fn open_new_files(&mut self) -> StreamResult<()> {
+        let paths = if self.path.is_dir() {
+            list_log_files(&self.path).map_err(ReaderError::Io)?
+        } else {
+            vec![self.path.clone()]
+        };
+
+        for path in paths.into_iter() {
+            if self.files.iter().all(|(p, _)| *p != path) {
+                let reader = self.builder.with_tail(&path)?;
+                self.files.push((path, LogFileStream::new(Box::new(reader))));
+            }
+        }
+        Ok(())
     }
 }
 
-impl RecordStream for LogFileStream {
+impl RecordStream for TailFilesStream {
     fn next(&mut self) -> StreamResult<Option<Record>> {
-        if let Some(record) = self.reader.read_record()? {
-            Ok(Some(record))
-        } else {
-            Ok(None)
+        while let Some((_, stream)) = self.files.get_mut(self.curr) {
+            if let Some(record) = stream.next()? {
+                return Ok(Some(record));
+            }
+            self.curr += 1;
         }
+
+        Ok(None)
     }
 
     fn close(&self) {}
+
+    fn resume(&mut self) -> Resume {
+        self.curr = 0;
+        match self.open_new_files() {
+            Ok(_) => Resume::Incremental,
+            Err(e) => {
+                trace::warning(format_args!("failed to open the new files: {}", e));
+                Resume::Unsupported
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -669,6 +1795,62 @@ mod tests {
     use crate::execution::types;
     use crate::execution::types::Expression;
 
+    #[test]
+    fn test_channel_stream() {
+        let mut stream = ChannelStream::spawn(2, || {
+            let records = (0..10)
+                .map(|i| Record::new(&vec!["i".to_string()], vec![Value::Int(i)]))
+                .collect();
+            Ok(Box::new(InMemoryStream::new(records)) as Box<dyn RecordStream>)
+        });
+
+        let mut result = Vec::new();
+        while let Some(record) = stream.next().unwrap() {
+            result.push(record.to_tuples()[0].1.clone());
+        }
+        assert_eq!((0..10).map(Value::Int).collect::<Vec<_>>(), result);
+
+        let mut stream = ChannelStream::spawn(2, || Err(StreamError::Reader));
+        assert!(stream.next().is_err());
+        assert_eq!(None, stream.next().unwrap());
+    }
+
+    struct PanickingStream {
+        count: i32,
+    }
+
+    impl RecordStream for PanickingStream {
+        fn next(&mut self) -> StreamResult<Option<Record>> {
+            if self.count == 3 {
+                panic!("corrupted input");
+            }
+            self.count += 1;
+            Ok(Some(Record::new(&vec!["i".to_string()], vec![Value::Int(self.count)])))
+        }
+
+        fn close(&self) {}
+    }
+
+    #[test]
+    fn test_channel_stream_panic() {
+        let mut stream = ChannelStream::spawn(
+            1,
+            || Ok(Box::new(PanickingStream { count: 0 }) as Box<dyn RecordStream>),
+        );
+
+        let mut count = 0;
+        let err = loop {
+            match stream.next() {
+                Ok(Some(_)) => count += 1,
+                Ok(None) => panic!("the panic of the reader passed for the end of the stream"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(3, count);
+        assert_eq!("The reader thread panicked: corrupted input", err.to_string());
+        assert_eq!(None, stream.next().unwrap());
+    }
+
     #[test]
     fn test_limit_stream() {
         let mut variables: Variables = Variables::default();
@@ -704,6 +1886,421 @@ mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_distinct_on_stream() {
+        let field_names = vec!["host".to_string(), "port".to_string()];
+        let mut records = VecDeque::new();
+        records.push_back(Record::new(
+            &field_names,
+            vec![Value::String("example01.com".to_string()), Value::Int(8000)],
+        ));
+        records.push_back(Record::new(
+            &field_names,
+            vec![Value::String("example.com".to_string()), Value::Int(8001)],
+        ));
+        records.push_back(Record::new(
+            &field_names,
+            vec![Value::String("example01.com".to_string()), Value::Int(8002)],
+        ));
+        let stream = Box::new(InMemoryStream::new(records));
+
+        let keys = vec![ast::PathExpr::new(vec![ast::PathSegment::AttrName("host".to_string())])];
+        let mut distinct_on_stream = DistinctOnStream::new(keys, stream);
+
+        let mut result = Vec::new();
+        while let Some(n) = distinct_on_stream.next().unwrap() {
+            result.push(n);
+        }
+
+        let expected = vec![
+            Record::new(
+                &field_names,
+                vec![Value::String("example01.com".to_string()), Value::Int(8000)],
+            ),
+            Record::new(
+                &field_names,
+                vec![Value::String("example.com".to_string()), Value::Int(8001)],
+            ),
+        ];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_window_stream() {
+        let field_names = vec!["host".to_string(), "t".to_string(), "c".to_string()];
+        let dt = |s: &str| Value::DateTime(DateTime::parse_from_rfc3339(s).unwrap());
+        let mut records = VecDeque::new();
+        for (host, t, c) in [
+            ("a", "2019-06-07T18:45:00Z", 2),
+            ("b", "2019-06-07T18:45:00Z", 5),
+            ("a", "2019-06-07T18:46:00Z", 8),
+            ("b", "2019-06-07T18:47:00Z", 2),
+        ]
+        .iter()
+        {
+            records.push_back(Record::new(
+                &field_names,
+                vec![Value::String(host.to_string()), dt(t), Value::Int(*c)],
+            ));
+        }
+        let stream = Box::new(InMemoryStream::new(records));
+
+        let path = |s: &str| ast::PathExpr::new(vec![ast::PathSegment::AttrName(s.to_string())]);
+        let windows = vec![
+            NamedWindow::new(types::WindowFunction::Delta, path("c"), "d".to_string()),
+            NamedWindow::new(types::WindowFunction::RatePerSec, path("c"), "r".to_string()),
+        ];
+        let mut window_stream = WindowStream::new(windows, vec![path("host")], path("t"), stream);
+
+        let mut result = Vec::new();
+        while let Some(record) = window_stream.next().unwrap() {
+            result.push((record.get(&path("d")), record.get(&path("r"))));
+        }
+
+        let float = |f: f32| Value::Float(ordered_float::OrderedFloat::from(f));
+        assert_eq!(
+            vec![
+                (Value::Null, Value::Null),
+                (Value::Null, Value::Null),
+                (Value::Int(6), float(0.1)),
+                (Value::Int(-3), float(-0.025)),
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn test_gap_fill_stream() {
+        let field_names = vec!["host".to_string(), "t".to_string(), "c".to_string()];
+        let dt = |s: &str| Value::DateTime(DateTime::parse_from_rfc3339(s).unwrap());
+        let mut records = VecDeque::new();
+        for (host, t, c) in [
+            ("a", "2019-06-07T18:48:00Z", 2),
+            ("b", "2019-06-07T18:45:00Z", 5),
+            ("a", "2019-06-07T18:45:00Z", 8),
+            ("b", "2019-06-07T18:46:00Z", 2),
+        ]
+        .iter()
+        {
+            records.push_back(Record::new(
+                &field_names,
+                vec![Value::String(host.to_string()), dt(t), Value::Int(*c)],
+            ));
+        }
+        let stream = Box::new(InMemoryStream::new(records));
+
+        let path = |s: &str| ast::PathExpr::new(vec![ast::PathSegment::AttrName(s.to_string())]);
+        let mut gap_fill_stream = GapFillStream::new(
+            path("t"),
+            chrono::Duration::minutes(1),
+            vec![path("host")],
+            Value::Int(0),
+            stream,
+        );
+
+        let mut result = Vec::new();
+        while let Some(record) = gap_fill_stream.next().unwrap() {
+            result.push(record.get_many(&[path("host"), path("t"), path("c")]));
+        }
+
+        let row = |host: &str, t: &str, c: i32| vec![Value::String(host.to_string()), dt(t), Value::Int(c)];
+        assert_eq!(
+            vec![
+                row("a", "2019-06-07T18:45:00Z", 8),
+                row("a", "2019-06-07T18:46:00Z", 0),
+                row("a", "2019-06-07T18:47:00Z", 0),
+                row("a", "2019-06-07T18:48:00Z", 2),
+                row("b", "2019-06-07T18:45:00Z", 5),
+                row("b", "2019-06-07T18:46:00Z", 2),
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn test_window_stream_with_moving_avg() {
+        let field_names = vec!["t".to_string(), "c".to_string()];
+        let mut records = VecDeque::new();
+        for (t, c) in [
+            (0, Value::Int(2)),
+            (1, Value::Int(4)),
+            (2, Value::Null),
+            (3, Value::Int(9)),
+        ]
+        .iter()
+        {
+            records.push_back(Record::new(&field_names, vec![Value::Int(*t), c.clone()]));
+        }
+        let stream = Box::new(InMemoryStream::new(records));
+
+        let path = |s: &str| ast::PathExpr::new(vec![ast::PathSegment::AttrName(s.to_string())]);
+        let windows = vec![NamedWindow::new(
+            types::WindowFunction::MovingAvg(3),
+            path("c"),
+            "m".to_string(),
+        )];
+        let mut window_stream = WindowStream::new(windows, Vec::new(), path("t"), stream);
+
+        let mut result = Vec::new();
+        while let Some(record) = window_stream.next().unwrap() {
+            result.push(record.get(&path("m")));
+        }
+
+        let float = |f: f32| Value::Float(ordered_float::OrderedFloat::from(f));
+        assert_eq!(vec![float(2.0), float(3.0), float(3.0), float(6.5)], result);
+    }
+
+    #[test]
+    fn test_columns_stream() {
+        let mut records = VecDeque::new();
+        records.push_back(Record::new(
+            &vec!["host".to_string(), "port".to_string(), "c".to_string()],
+            vec![
+                Value::String("example01.com".to_string()),
+                Value::Int(8000),
+                Value::Int(1),
+            ],
+        ));
+        records.push_back(Record::new(
+            &vec!["host".to_string(), "c".to_string()],
+            vec![Value::String("example.com".to_string()), Value::Int(2)],
+        ));
+        let stream = Box::new(InMemoryStream::new(records.clone()));
+
+        let mut columns_stream = ColumnsStream::new(vec!["port".to_string(), "host".to_string()], stream);
+
+        let mut result = Vec::new();
+        while let Some(n) = columns_stream.next().unwrap() {
+            result.push(n);
+        }
+
+        let expected = vec![
+            Record::new(
+                &vec!["port".to_string(), "host".to_string()],
+                vec![Value::Int(8000), Value::String("example01.com".to_string())],
+            ),
+            Record::new(
+                &vec!["port".to_string(), "host".to_string()],
+                vec![Value::Null, Value::String("example.com".to_string())],
+            ),
+        ];
+        assert_eq!(expected, result);
+
+        let stream = Box::new(InMemoryStream::new(records));
+        let mut columns_stream = ColumnsStream::new(vec!["hots".to_string()], stream);
+        assert!(matches!(
+            columns_stream.next(),
+            Err(StreamError::UnknownColumn(ref c)) if c == "hots"
+        ));
+    }
+
+    #[test]
+    fn test_render_stream() {
+        let mut records = VecDeque::new();
+        records.push_back(Record::new(
+            &vec!["host".to_string(), "sent_bytes".to_string()],
+            vec![Value::String("example.com".to_string()), Value::Int(2048)],
+        ));
+        let stream = Box::new(InMemoryStream::new(records.clone()));
+
+        let mut render_stream = RenderStream::new(vec![("sent_bytes".to_string(), Renderer::Bytes)], stream);
+        let expected = Record::new(
+            &vec!["host".to_string(), "sent_bytes".to_string()],
+            vec![
+                Value::String("example.com".to_string()),
+                Value::String("2.0 KiB".to_string()),
+            ],
+        );
+        assert_eq!(Some(expected), render_stream.next().unwrap());
+
+        let stream = Box::new(InMemoryStream::new(records));
+        let mut render_stream = RenderStream::new(vec![("bytes".to_string(), Renderer::Bytes)], stream);
+        assert!(matches!(
+            render_stream.next(),
+            Err(StreamError::UnknownColumn(ref c)) if c == "bytes"
+        ));
+    }
+
+    #[test]
+    fn test_tail_files_stream_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.jsonl");
+        std::fs::write(&path, "{\"host\":\"a.com\"}\n{\"host\":\"b.com\"}\n").unwrap();
+        let append = |s: &str| {
+            let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+            std::io::Write::write_all(&mut file, s.as_bytes()).unwrap();
+        };
+
+        let stream = TailFilesStream::new(ReaderBuilder::new("jsonl".to_string()), path.clone()).unwrap();
+        let keys = vec![ast::PathExpr::new(vec![ast::PathSegment::AttrName("host".to_string())])];
+        let aggregates = vec![NamedAggregate::new(
+            Aggregate::Count(types::CountAggregate::new(), Named::Star),
+            Some("c".to_string()),
+        )];
+        let group_by_stream = GroupByStream::new(keys.clone(), Variables::default(), aggregates, Box::new(stream));
+        let mut stream = OrderByStream::new(keys, vec![types::Ordering::Asc], Box::new(group_by_stream));
+        let counts = |stream: &mut OrderByStream| {
+            let mut counts = Vec::new();
+            while let Some(record) = stream.next().unwrap() {
+                counts.push(record.to_tuples().into_iter().map(|(_, v)| v).collect::<Vec<_>>());
+            }
+            counts
+        };
+        let row = |host: &str, c: i32| vec![Value::String(host.to_string()), Value::Int(c)];
+        assert_eq!(vec![row("a.com", 1), row("b.com", 1)], counts(&mut stream));
+
+        //The line still being written is held back until it is complete
+        append("{\"host\":\"a.com\"}\n{\"host\":");
+        assert_eq!(Resume::Full, stream.resume());
+        assert_eq!(vec![row("a.com", 2), row("b.com", 1)], counts(&mut stream));
+
+        append("\"c.com\"}\n");
+        assert_eq!(Resume::Full, stream.resume());
+        assert_eq!(
+            vec![row("a.com", 2), row("b.com", 1), row("c.com", 1)],
+            counts(&mut stream)
+        );
+    }
+
+    #[test]
+    fn test_group_by_stream_with_group_limit() {
+        let mut records = VecDeque::new();
+        for host in ["a.com", "b.com", "c.com", "a.com"].iter() {
+            records.push_back(Record::new(
+                &vec!["host".to_string()],
+                vec![Value::String(host.to_string())],
+            ));
+        }
+        let keys = vec![ast::PathExpr::new(vec![ast::PathSegment::AttrName("host".to_string())])];
+        let aggregates = vec![NamedAggregate::new(
+            Aggregate::Count(types::CountAggregate::new(), Named::Star),
+            Some("c".to_string()),
+        )];
+
+        let group_limit = GroupLimit {
+            max_groups: 3,
+            abort: true,
+        };
+        let stream = Box::new(InMemoryStream::new(records.clone()));
+        let mut group_by_stream = GroupByStream::new(keys.clone(), Variables::default(), aggregates.clone(), stream)
+            .group_limit(Some(group_limit));
+        let mut count = 0;
+        while group_by_stream.next().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(3, count);
+
+        let group_limit = GroupLimit {
+            max_groups: 2,
+            abort: true,
+        };
+        let stream = Box::new(InMemoryStream::new(records.clone()));
+        let mut group_by_stream = GroupByStream::new(keys.clone(), Variables::default(), aggregates.clone(), stream)
+            .group_limit(Some(group_limit));
+        assert!(matches!(group_by_stream.next(), Err(StreamError::TooManyGroups(2))));
+
+        //Without aborting only a warning is printed
+        let group_limit = GroupLimit {
+            max_groups: 2,
+            abort: false,
+        };
+        let stream = Box::new(InMemoryStream::new(records));
+        let mut group_by_stream =
+            GroupByStream::new(keys, Variables::default(), aggregates, stream).group_limit(Some(group_limit));
+        let mut count = 0;
+        while group_by_stream.next().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(3, count);
+    }
+
+    #[test]
+    fn test_union_stream() {
+        let field_names = vec!["host".to_string()];
+        let mut first = VecDeque::new();
+        first.push_back(Record::new(&field_names, vec![Value::String("a.com".to_string())]));
+        let mut second = VecDeque::new();
+        second.push_back(Record::new(&field_names, vec![Value::String("b.com".to_string())]));
+        second.push_back(Record::new(&field_names, vec![Value::String("c.com".to_string())]));
+
+        let sources: Vec<Box<dyn RecordStream>> = vec![
+            Box::new(InMemoryStream::new(first)),
+            Box::new(InMemoryStream::new(VecDeque::new())),
+            Box::new(InMemoryStream::new(second)),
+        ];
+        let mut union_stream = UnionStream::new(sources);
+
+        let mut result = Vec::new();
+        while let Some(n) = union_stream.next().unwrap() {
+            result.push(n);
+        }
+
+        let expected = vec![
+            Record::new(&field_names, vec![Value::String("a.com".to_string())]),
+            Record::new(&field_names, vec![Value::String("b.com".to_string())]),
+            Record::new(&field_names, vec![Value::String("c.com".to_string())]),
+        ];
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_join_stream() {
+        let id = ast::PathExpr::new(vec![ast::PathSegment::AttrName("id".to_string())]);
+        let field_names = vec!["id".to_string()];
+        let mut left = VecDeque::new();
+        left.push_back(Record::new(&field_names, vec![Value::Int(1)]));
+        left.push_back(Record::new(&field_names, vec![Value::Int(2)]));
+        left.push_back(Record::new(&field_names, vec![Value::Null]));
+        let mut right = VecDeque::new();
+        right.push_back(Record::new(&field_names, vec![Value::Int(1)]));
+        right.push_back(Record::new(&field_names, vec![Value::Null]));
+        right.push_back(Record::new(&field_names, vec![Value::Long(1)]));
+
+        let object = |value: Value| {
+            let mut variables = Variables::default();
+            variables.insert("id".to_string(), value);
+            Value::Object(variables)
+        };
+        let joined = |l: Value, r: Value| Record::new(&vec!["x".to_string(), "y".to_string()], vec![l, r]);
+        let join = |kind: ast::JoinKind| {
+            let join = Join::new(
+                kind,
+                "x".to_string(),
+                "y".to_string(),
+                vec![id.clone()],
+                vec![id.clone()],
+            );
+            JoinStream::new(
+                join,
+                Box::new(InMemoryStream::new(left.clone())),
+                Box::new(InMemoryStream::new(right.clone())),
+            )
+        };
+
+        //The nulls match nothing, the integers match whatever their width
+        let mut join_stream = join(ast::JoinKind::Inner);
+        let mut result = Vec::new();
+        while let Some(n) = join_stream.next().unwrap() {
+            result.push(n);
+        }
+        let matched = vec![
+            joined(object(Value::Int(1)), object(Value::Int(1))),
+            joined(object(Value::Int(1)), object(Value::Long(1))),
+        ];
+        assert_eq!(matched, result);
+
+        let mut join_stream = join(ast::JoinKind::Left);
+        let mut result = Vec::new();
+        while let Some(n) = join_stream.next().unwrap() {
+            result.push(n);
+        }
+        let mut expected = matched;
+        expected.push(joined(object(Value::Int(2)), Value::Missing));
+        expected.push(joined(object(Value::Null), Value::Missing));
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn test_filter_stream() {
         let path_expr_host = ast::PathExpr::new(vec![ast::PathSegment::AttrName("host".to_string())]);
@@ -794,6 +2391,121 @@ mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_map_and_filter_stream_with_virtual_columns() {
+        let field_names = vec!["host".to_string()];
+        let mut records = VecDeque::new();
+        for line in 1..=2 {
+            let mut record = Record::new(&field_names, vec![Value::String("example.com".to_string())]);
+            record.insert_virtual_column("__line".to_string(), Value::Int(line));
+            records.push_back(record);
+        }
+        let stream = Box::new(InMemoryStream::new(records));
+
+        let map_stream = MapStream::new(vec![Named::Star], Variables::default(), stream);
+        let mut variables: Variables = Variables::default();
+        variables.insert("const".to_string(), Value::Int(2));
+        let formula = types::Formula::Predicate(
+            types::Relation::Equal,
+            Box::new(Expression::Variable(ast::PathExpr::new(vec![
+                ast::PathSegment::AttrName("__line".to_string()),
+            ]))),
+            Box::new(Expression::Variable(ast::PathExpr::new(vec![
+                ast::PathSegment::AttrName("const".to_string()),
+            ]))),
+        );
+        let mut filtered_stream = FilterStream::new(formula, variables, Box::new(map_stream));
+
+        let mut result = Vec::new();
+        while let Some(n) = filtered_stream.next().unwrap() {
+            result.push(n.to_tuples());
+        }
+
+        //The virtual columns are not part of "select *"
+        let expected = vec![vec![("host".to_string(), Value::String("example.com".to_string()))]];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_map_stream_shares_source() {
+        let field_names = vec!["host".to_string()];
+        let mut record = Record::new(&field_names, vec![Value::String("example.com".to_string())]);
+        record.insert_virtual_column("__line".to_string(), Value::Int(1));
+        let stream = Box::new(InMemoryStream::new(VecDeque::from(vec![record.clone()])));
+
+        let mut map_stream = MapStream::new(vec![Named::Star], Variables::default(), stream);
+        let mapped = map_stream.next().unwrap().unwrap();
+
+        //The source record is referred to rather than copied into the virtual columns
+        assert!(mapped.virtual_columns.is_empty());
+        assert_eq!(Some(&record), mapped.source.as_deref());
+        assert_eq!(Value::Int(1), mapped.merged_variables(&Variables::default())["__line"]);
+    }
+
+    #[test]
+    fn test_log_file_stream_skips_malformed_lines() {
+        let reader =
+            ReaderBuilder::new("jsonl".to_string()).with_reader("{\"a\": 1}\nnot json\n{\"a\": 2}\n".as_bytes());
+        let mut stream = LogFileStream::new(Box::new(reader));
+
+        let mut lines = Vec::new();
+        let mut offsets = Vec::new();
+        while let Some(record) = stream.next().unwrap() {
+            let variables = record.merged_variables(&Variables::default());
+            lines.push(variables["__line"].clone());
+            offsets.push(variables["__byte_offset"].clone());
+        }
+
+        assert_eq!(vec![Value::Int(1), Value::Int(3)], lines);
+        assert_eq!(vec![Value::Int(0), Value::Int(18)], offsets);
+        assert_eq!(1, stream.skipped);
+    }
+
+    //A reader deep into a large file
+    struct FarReader {
+        records: usize,
+    }
+
+    impl RecordRead for FarReader {
+        fn read_record(&mut self) -> Result<Option<Record>, ReaderError> {
+            if self.records == 0 {
+                return Ok(None);
+            }
+            self.records -= 1;
+            Ok(Some(Record::new(&vec!["a".to_string()], vec![Value::Int(1)])))
+        }
+
+        fn metadata(&self) -> crate::execution::source::SourceMetadata {
+            crate::execution::source::SourceMetadata::default()
+        }
+
+        fn position(&self) -> Option<(usize, u64)> {
+            Some((3_000_000_000, 5_000_000_000))
+        }
+    }
+
+    #[test]
+    fn test_log_file_stream_large_positions() {
+        let mut stream = LogFileStream::new(Box::new(FarReader { records: 1 }));
+
+        let record = stream.next().unwrap().unwrap();
+        let variables = record.merged_variables(&Variables::default());
+        assert_eq!(Value::Long(3_000_000_000), variables["__line"]);
+        assert_eq!(Value::Long(5_000_000_000), variables["__byte_offset"]);
+    }
+
+    #[test]
+    fn test_log_file_stream_counts_header_lines() {
+        let content = "#Version: 1.0\n#Fields: date time sc-status\n2019-12-04\t21:02:31\t200\n";
+        let reader = ReaderBuilder::new("cloudfront".to_string()).with_reader(content.as_bytes());
+        let mut stream = LogFileStream::new(Box::new(reader));
+
+        let record = stream.next().unwrap().unwrap();
+        let variables = record.merged_variables(&Variables::default());
+        assert_eq!(Value::Int(3), variables["__line"]);
+        assert_eq!(Value::Int(43), variables["__byte_offset"]);
+    }
+
     #[test]
     fn test_map_stream_with_names() {
         let path_expr_port = ast::PathExpr::new(vec![ast::PathSegment::AttrName("port".to_string())]);
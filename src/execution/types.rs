@@ -1,24 +1,47 @@
-use super::datasource::{ReaderBuilder, ReaderError};
-use super::stream::{FilterStream, GroupByStream, InMemoryStream, LimitStream, LogFileStream, MapStream, RecordStream};
+use super::datasource::{list_log_files, ReaderBuilder, ReaderError};
+use super::index::FileIndex;
+use super::stream::{
+    ChannelStream, DistinctOnStream, FilterStream, GapFillStream, GroupByStream, GroupLimit, JoinStream, LimitStream,
+    LogFileStream, LogFilesStream, MapStream, OrderByStream, Record, RecordStream, TailFilesStream, TracedStream,
+    WindowStream,
+};
 use crate::common;
+use crate::common::trace;
 use crate::common::types::{DataSource, Tuple, Value, VariableName, Variables};
 use crate::execution::stream::ProjectionStream;
-use crate::syntax::ast::{PathExpr, PathSegment};
+use crate::syntax::ast::{JoinKind, PathExpr, PathSegment};
 use chrono::Timelike;
 use hashbrown::HashMap;
 use ordered_float::OrderedFloat;
 use pdatastructs::hyperloglog::HyperLogLog;
-use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
 use std::io;
+use std::path::PathBuf;
 use std::result;
-use tdigest::TDigest;
+use tdigest::{Centroid, TDigest};
 
 pub(crate) type EvaluateResult<T> = result::Result<T, EvaluateError>;
 
-#[derive(Fail, PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug)]
 pub(crate) enum EvaluateError {
-    #[fail(display = "{}", _0)]
-    Expression(#[cause] ExpressionError),
+    Expression(ExpressionError),
+}
+
+impl fmt::Display for EvaluateError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvaluateError::Expression(e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EvaluateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EvaluateError::Expression(e) => Some(e),
+        }
+    }
 }
 
 impl From<ExpressionError> for EvaluateError {
@@ -29,16 +52,25 @@ impl From<ExpressionError> for EvaluateError {
 
 pub(crate) type CreateStreamResult<T> = result::Result<T, CreateStreamError>;
 
-#[derive(Fail, PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug)]
 pub enum CreateStreamError {
-    #[fail(display = "Io Error")]
     Io,
-    #[fail(display = "Reader Error")]
     Reader,
-    #[fail(display = "Stream Error")]
     Stream,
 }
 
+impl fmt::Display for CreateStreamError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CreateStreamError::Io => fmt.write_str("Io Error"),
+            CreateStreamError::Reader => fmt.write_str("Reader Error"),
+            CreateStreamError::Stream => fmt.write_str("Stream Error"),
+        }
+    }
+}
+
+impl std::error::Error for CreateStreamError {}
+
 impl From<io::Error> for CreateStreamError {
     fn from(_: io::Error) -> CreateStreamError {
         CreateStreamError::Io
@@ -59,18 +91,43 @@ impl From<StreamError> for CreateStreamError {
 
 pub(crate) type StreamResult<T> = result::Result<T, StreamError>;
 
-#[derive(Fail, PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug)]
 pub(crate) enum StreamError {
-    #[fail(display = "{}", _0)]
-    Get(#[cause] CreateStreamError),
-    #[fail(display = "{}", _0)]
-    Evaluate(#[cause] EvaluateError),
-    #[fail(display = "{}", _0)]
-    Expression(#[cause] ExpressionError),
-    #[fail(display = "Reader Error")]
+    Get(CreateStreamError),
+    Evaluate(EvaluateError),
+    Expression(ExpressionError),
     Reader,
-    #[fail(display = "Aggregate Error")]
-    Aggregate,
+    Aggregate(AggregateError),
+    UnknownColumn(String),
+    TooManyGroups(usize),
+    ReaderPanicked(String),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Get(e) => write!(fmt, "{}", e),
+            StreamError::Evaluate(e) => write!(fmt, "{}", e),
+            StreamError::Expression(e) => write!(fmt, "{}", e),
+            StreamError::Reader => fmt.write_str("Reader Error"),
+            StreamError::Aggregate(e) => write!(fmt, "{}", e),
+            StreamError::UnknownColumn(s) => write!(fmt, "Unknown column \"{}\"", s),
+            StreamError::TooManyGroups(n) => write!(fmt, "The group by has more than {} distinct keys, consider normalizing the urls, e.g. with url_path_bucket, or using the approximate aggregates", n),
+            StreamError::ReaderPanicked(s) => write!(fmt, "The reader thread panicked: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Get(e) => Some(e),
+            StreamError::Evaluate(e) => Some(e),
+            StreamError::Expression(e) => Some(e),
+            StreamError::Aggregate(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<CreateStreamError> for StreamError {
@@ -98,37 +155,54 @@ impl From<ReaderError> for StreamError {
 }
 
 impl From<AggregateError> for StreamError {
-    fn from(_: AggregateError) -> StreamError {
-        StreamError::Aggregate
+    fn from(err: AggregateError) -> StreamError {
+        StreamError::Aggregate(err)
     }
 }
 
 pub(crate) type ExpressionResult<T> = result::Result<T, ExpressionError>;
 
-#[derive(Fail, PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug)]
 pub(crate) enum ExpressionError {
-    #[fail(display = "Key Not Found")]
     KeyNotFound,
-    #[fail(display = "Invalid Arguments")]
     InvalidArguments,
-    #[fail(display = "Unknown Function")]
     UnknownFunction,
-    #[fail(display = "Invalid Star")]
     InvalidStar,
-    #[fail(display = "Missing Else")]
     MissingElse,
-    #[fail(display = "Type Mismatch")]
     TypeMismatch,
-    #[fail(display = "{}", _0)]
-    ParseTimeInterval(#[cause] common::types::ParseTimeIntervalError),
-    #[fail(display = "TimeInterval Not Supported Yet")]
+    ParseTimeInterval(common::types::ParseTimeIntervalError),
     TimeIntervalNotSupported,
-    #[fail(display = "Zero TimeInterval")]
     TimeIntervalZero,
-    #[fail(display = "DatePartUnit Not Supported Yet")]
     DatePartUnitNotSupported,
-    #[fail(display = "{}", _0)]
-    ParseDatePart(#[cause] common::types::ParseDatePartError),
+    ParseDatePart(common::types::ParseDatePartError),
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::KeyNotFound => fmt.write_str("Key Not Found"),
+            ExpressionError::InvalidArguments => fmt.write_str("Invalid Arguments"),
+            ExpressionError::UnknownFunction => fmt.write_str("Unknown Function"),
+            ExpressionError::InvalidStar => fmt.write_str("Invalid Star"),
+            ExpressionError::MissingElse => fmt.write_str("Missing Else"),
+            ExpressionError::TypeMismatch => fmt.write_str("Type Mismatch"),
+            ExpressionError::ParseTimeInterval(e) => write!(fmt, "{}", e),
+            ExpressionError::TimeIntervalNotSupported => fmt.write_str("TimeInterval Not Supported Yet"),
+            ExpressionError::TimeIntervalZero => fmt.write_str("Zero TimeInterval"),
+            ExpressionError::DatePartUnitNotSupported => fmt.write_str("DatePartUnit Not Supported Yet"),
+            ExpressionError::ParseDatePart(e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExpressionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExpressionError::ParseTimeInterval(e) => Some(e),
+            ExpressionError::ParseDatePart(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<EvaluateError> for ExpressionError {
@@ -187,21 +261,15 @@ fn get_value_by_path_expr(path_expr: &PathExpr, i: usize, variables: &Variables)
             if let Some(val) = variables.get(attr_name) {
                 if i + 1 == path_expr.path_segments.len() {
                     match val {
-                        Value::Array(a) => {
-                            let a = &a[*idx];
-                            return a.clone();
-                        }
+                        Value::Array(a) => a.get(*idx).cloned().unwrap_or(Value::Missing),
                         _ => Value::Missing,
                     }
                 } else {
                     match val {
-                        Value::Array(a) => {
-                            let a = &a[*idx];
-                            match a {
-                                Value::Object(o) => get_value_by_path_expr(path_expr, i + 1, o as &Variables),
-                                _ => Value::Missing,
-                            }
-                        }
+                        Value::Array(a) => match a.get(*idx) {
+                            Some(Value::Object(o)) => get_value_by_path_expr(path_expr, i + 1, o as &Variables),
+                            _ => Value::Missing,
+                        },
                         _ => Value::Missing,
                     }
                 }
@@ -336,6 +404,25 @@ fn evaluate_url_functions(func_name: &str, arguments: &[Value]) -> ExpressionRes
                 _ => Err(ExpressionError::InvalidArguments),
             }
         }
+        "url_query_params" => {
+            if arguments.len() != 1 {
+                return Err(ExpressionError::InvalidArguments);
+            }
+
+            match &arguments[0] {
+                Value::HttpRequest(r) => {
+                    //The first value is kept for the repeated key
+                    let mut params = Variables::default();
+                    for (k, v) in r.url.query_pairs() {
+                        params
+                            .entry(k.to_string())
+                            .or_insert_with(|| Value::String(v.to_string()));
+                    }
+                    Ok(Value::Object(params))
+                }
+                _ => Err(ExpressionError::InvalidArguments),
+            }
+        }
         "url_path_segments" => {
             if arguments.len() != 2 {
                 return Err(ExpressionError::InvalidArguments);
@@ -414,6 +501,278 @@ fn evaluate_host_functions(func_name: &str, arguments: &[Value]) -> ExpressionRe
     }
 }
 
+//Whether the comma separated actions executed by the ALB, e.g. "authenticate,forward", include the action
+fn has_action(actions: &Value, action: &str) -> ExpressionResult<Value> {
+    match actions {
+        Value::String(s) => Ok(Value::Boolean(s.split(',').any(|a| a.trim() == action))),
+        Value::Null | Value::Missing => Ok(Value::Boolean(false)),
+        _ => Err(ExpressionError::InvalidArguments),
+    }
+}
+
+const ACTION_FUNCTIONS: [&str; 3] = ["was_redirected", "was_fixed_response", "has_action"];
+
+fn evaluate_action_functions(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
+    match func_name {
+        "was_redirected" => {
+            if arguments.len() != 1 {
+                return Err(ExpressionError::InvalidArguments);
+            }
+
+            has_action(&arguments[0], "redirect")
+        }
+        "was_fixed_response" => {
+            if arguments.len() != 1 {
+                return Err(ExpressionError::InvalidArguments);
+            }
+
+            has_action(&arguments[0], "fixed-response")
+        }
+        "has_action" => {
+            if arguments.len() != 2 {
+                return Err(ExpressionError::InvalidArguments);
+            }
+
+            match &arguments[1] {
+                Value::String(action) => has_action(&arguments[0], action),
+                _ => Err(ExpressionError::InvalidArguments),
+            }
+        }
+        _ => Err(ExpressionError::UnknownFunction),
+    }
+}
+
+//The multi-label public suffixes commonly seen in the host headers, a domain under them keeps one more label
+const MULTI_LABEL_SUFFIXES: [&str; 12] = [
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "com.au", "net.au", "org.au", "co.jp", "co.nz", "com.br", "com.cn", "co.in",
+];
+
+fn normalize_domain(value: &Value) -> ExpressionResult<Option<String>> {
+    let domain = match value {
+        Value::String(s) => s.clone(),
+        Value::HttpRequest(r) => match r.url.host_str() {
+            Some(host) => host.to_string(),
+            None => return Ok(None),
+        },
+        Value::Host(h) => h.hostname.clone(),
+        Value::Null | Value::Missing => return Ok(None),
+        _ => return Err(ExpressionError::InvalidArguments),
+    };
+
+    //The host header may carry the port and the trailing dot of the fully qualified name
+    let domain = domain
+        .split(':')
+        .next()
+        .unwrap_or("")
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    if domain.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(domain))
+    }
+}
+
+const DOMAIN_FUNCTIONS: [&str; 2] = ["reverse_domain", "registrable_domain"];
+
+fn evaluate_domain_functions(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
+    if arguments.len() != 1 {
+        return Err(ExpressionError::InvalidArguments);
+    }
+
+    let domain = match normalize_domain(&arguments[0])? {
+        Some(domain) => domain,
+        None => return Ok(Value::Null),
+    };
+    //The ip addresses are not domains, they are kept as is
+    if domain.parse::<std::net::IpAddr>().is_ok() {
+        return Ok(Value::String(domain));
+    }
+    let labels: Vec<&str> = domain.split('.').collect();
+
+    match func_name {
+        "reverse_domain" => {
+            let reversed: Vec<&str> = labels.into_iter().rev().collect();
+            Ok(Value::String(reversed.join(".")))
+        }
+        "registrable_domain" => {
+            let suffix_len =
+                if labels.len() > 2 && MULTI_LABEL_SUFFIXES.contains(&&*labels[labels.len() - 2..].join(".")) {
+                    2
+                } else {
+                    1
+                };
+            let start = labels.len().saturating_sub(suffix_len + 1);
+            Ok(Value::String(labels[start..].join(".")))
+        }
+        _ => Err(ExpressionError::UnknownFunction),
+    }
+}
+
+//The hosts of the search engines by the registrable domain, and the name of the engine
+const SEARCH_ENGINES: [(&str, &str); 8] = [
+    ("google", "google"),
+    ("bing.com", "bing"),
+    ("yahoo.com", "yahoo"),
+    ("duckduckgo.com", "duckduckgo"),
+    ("baidu.com", "baidu"),
+    ("yandex", "yandex"),
+    ("ecosia.org", "ecosia"),
+    ("naver.com", "naver"),
+];
+
+fn referrer_host(value: &Value) -> ExpressionResult<Option<String>> {
+    let referrer = match value {
+        Value::String(s) => s.trim_matches('"'),
+        Value::Null | Value::Missing => return Ok(None),
+        _ => return Err(ExpressionError::InvalidArguments),
+    };
+
+    Ok(url::Url::parse(referrer)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase())))
+}
+
+const REFERRER_FUNCTIONS: [&str; 2] = ["referrer_host", "search_engine"];
+
+fn evaluate_referrer_functions(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
+    if arguments.len() != 1 {
+        return Err(ExpressionError::InvalidArguments);
+    }
+
+    let host = match referrer_host(&arguments[0])? {
+        Some(host) => host,
+        None => return Ok(Value::Null),
+    };
+
+    match func_name {
+        "referrer_host" => Ok(Value::String(host)),
+        "search_engine" => {
+            //Matches the engine domain on the label boundary, e.g. www.google.co.uk but not notgoogle.com
+            let labels: Vec<&str> = host.split('.').collect();
+            let engine = SEARCH_ENGINES.iter().find(|(domain, _)| {
+                (0..labels.len()).any(|i| {
+                    let rest = labels[i..].join(".");
+                    rest == *domain || rest.starts_with(&format!("{}.", domain))
+                })
+            });
+            Ok(engine.map_or(Value::Null, |(_, name)| Value::String(name.to_string())))
+        }
+        _ => Err(ExpressionError::UnknownFunction),
+    }
+}
+
+//The values of a list, e.g. the target lists of the ALB retries which are read as arrays, where null is the empty list
+fn list_values(value: &Value) -> ExpressionResult<Vec<Value>> {
+    match value {
+        Value::Array(a) => Ok(a.clone()),
+        Value::Null | Value::Missing => Ok(Vec::new()),
+        _ => Err(ExpressionError::InvalidArguments),
+    }
+}
+
+const LIST_FUNCTIONS: [&str; 7] = [
+    "list",
+    "list_len",
+    "list_first",
+    "list_last",
+    "had_retry",
+    "contains",
+    "element_at",
+];
+
+fn evaluate_list_functions(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
+    //The list literal [a, b] is the list function on its elements
+    if func_name == "list" {
+        return Ok(Value::Array(arguments.to_vec()));
+    }
+
+    let arity = match func_name {
+        "contains" | "element_at" => 2,
+        _ => 1,
+    };
+    if arguments.len() != arity {
+        return Err(ExpressionError::InvalidArguments);
+    }
+
+    let values = list_values(&arguments[0])?;
+    match func_name {
+        "list_len" => Ok(Value::Int(values.len() as i32)),
+        "list_first" => Ok(values.first().cloned().unwrap_or(Value::Null)),
+        "list_last" => Ok(values.last().cloned().unwrap_or(Value::Null)),
+        //More than one target in the list means the request was retried on another target
+        "had_retry" => Ok(Value::Boolean(values.len() > 1)),
+        "contains" => Ok(Value::Boolean(values.contains(&arguments[1]))),
+        "element_at" => {
+            //The index starts from 0, the negative index counts from the end of the list
+            let idx = match &arguments[1] {
+                Value::Int(i) if *i < 0 => values.len() as i64 + i64::from(*i),
+                Value::Int(i) => i64::from(*i),
+                _ => return Err(ExpressionError::InvalidArguments),
+            };
+
+            if idx < 0 {
+                return Ok(Value::Null);
+            }
+            Ok(values.get(idx as usize).cloned().unwrap_or(Value::Null))
+        }
+        _ => Err(ExpressionError::UnknownFunction),
+    }
+}
+
+const MAP_FUNCTIONS: [&str; 2] = ["map_get", "map_keys"];
+
+fn evaluate_map_functions(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
+    match (func_name, arguments) {
+        ("map_get", [Value::Object(o), Value::String(key)]) => Ok(o.get(key).cloned().unwrap_or(Value::Missing)),
+        ("map_get", [Value::Null, _]) | ("map_get", [Value::Missing, _]) => Ok(Value::Missing),
+        ("map_keys", [Value::Object(o)]) => Ok(Value::Array(o.keys().map(|k| Value::String(k.clone())).collect())),
+        ("map_get", _) | ("map_keys", _) => Err(ExpressionError::InvalidArguments),
+        _ => Err(ExpressionError::UnknownFunction),
+    }
+}
+
+//The name of the resource in the ARN, e.g. "my-targets" of
+//"arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067"
+fn arn_resource(arn: &str) -> Option<&str> {
+    let resource = arn.trim_matches('"').splitn(6, ':').nth(5)?;
+    let segments: Vec<&str> = resource.split(['/', ':']).collect();
+    let name = match segments[..] {
+        ["targetgroup", name, _] => name,
+        ["loadbalancer", _, name, _] => name,
+        ["function", name, ..] => name,
+        [.., last] => last,
+        [] => return None,
+    };
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+//The first segments of the directories of an S3 object key, e.g. "photos/2019/" of "photos/2019/08/puppy.jpg" and 2,
+//the object name is never a part of it so that the prefixes stay few
+fn key_prefix(key: &str, depth: usize) -> String {
+    let directories: Vec<&str> = key.split('/').collect();
+    let directories = &directories[..directories.len() - 1];
+    directories[..depth.min(directories.len())]
+        .iter()
+        .map(|d| format!("{}/", d))
+        .collect()
+}
+
+//The arithmetic with a float operand is done in floats, e.g. request_processing_time + target_processing_time
+fn float_operands(arguments: &[Value]) -> Option<(f32, f32)> {
+    match (&arguments[0], &arguments[1]) {
+        (Value::Float(a), Value::Float(b)) => Some((a.into_inner(), b.into_inner())),
+        (Value::Float(a), b) => b.as_integral().map(|b| (a.into_inner(), b as f32)),
+        (a, Value::Float(b)) => a.as_integral().map(|a| (a as f32, b.into_inner())),
+        _ => None,
+    }
+}
+
 fn evaluate(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
     if func_name.starts_with("url_") {
         return evaluate_url_functions(func_name, arguments);
@@ -423,6 +782,26 @@ fn evaluate(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
         return evaluate_host_functions(func_name, arguments);
     }
 
+    if ACTION_FUNCTIONS.contains(&func_name) {
+        return evaluate_action_functions(func_name, arguments);
+    }
+
+    if DOMAIN_FUNCTIONS.contains(&func_name) {
+        return evaluate_domain_functions(func_name, arguments);
+    }
+
+    if REFERRER_FUNCTIONS.contains(&func_name) {
+        return evaluate_referrer_functions(func_name, arguments);
+    }
+
+    if LIST_FUNCTIONS.contains(&func_name) {
+        return evaluate_list_functions(func_name, arguments);
+    }
+
+    if MAP_FUNCTIONS.contains(&func_name) {
+        return evaluate_map_functions(func_name, arguments);
+    }
+
     match func_name {
         "Plus" => {
             if arguments.len() != 2 {
@@ -431,7 +810,16 @@ fn evaluate(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
 
             match (&arguments[0], &arguments[1]) {
                 (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a + b)),
-                _ => Err(ExpressionError::InvalidArguments),
+                (a, b) if a.as_integral().is_some() && b.as_integral().is_some() => {
+                    match a.as_integral().unwrap().checked_add(b.as_integral().unwrap()) {
+                        Some(n) => Ok(Value::integral(n)),
+                        None => Err(ExpressionError::InvalidArguments),
+                    }
+                }
+                _ => match float_operands(arguments) {
+                    Some((a, b)) => Ok(Value::Float(OrderedFloat(a + b))),
+                    None => Err(ExpressionError::InvalidArguments),
+                },
             }
         }
         "Minus" => {
@@ -441,7 +829,16 @@ fn evaluate(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
 
             match (&arguments[0], &arguments[1]) {
                 (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a - b)),
-                _ => Err(ExpressionError::InvalidArguments),
+                (a, b) if a.as_integral().is_some() && b.as_integral().is_some() => {
+                    match a.as_integral().unwrap().checked_sub(b.as_integral().unwrap()) {
+                        Some(n) => Ok(Value::integral(n)),
+                        None => Err(ExpressionError::InvalidArguments),
+                    }
+                }
+                _ => match float_operands(arguments) {
+                    Some((a, b)) => Ok(Value::Float(OrderedFloat(a - b))),
+                    None => Err(ExpressionError::InvalidArguments),
+                },
             }
         }
         "Times" => {
@@ -451,7 +848,16 @@ fn evaluate(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
 
             match (&arguments[0], &arguments[1]) {
                 (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a * b)),
-                _ => Err(ExpressionError::InvalidArguments),
+                (a, b) if a.as_integral().is_some() && b.as_integral().is_some() => {
+                    match a.as_integral().unwrap().checked_mul(b.as_integral().unwrap()) {
+                        Some(n) => Ok(Value::integral(n)),
+                        None => Err(ExpressionError::InvalidArguments),
+                    }
+                }
+                _ => match float_operands(arguments) {
+                    Some((a, b)) => Ok(Value::Float(OrderedFloat(a * b))),
+                    None => Err(ExpressionError::InvalidArguments),
+                },
             }
         }
         "Divide" => {
@@ -461,6 +867,48 @@ fn evaluate(func_name: &str, arguments: &[Value]) -> ExpressionResult<Value> {
 
             match (&arguments[0], &arguments[1]) {
                 (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a / b)),
+                (a, b) if a.as_integral().is_some() && b.as_integral().is_some() => {
+                    match a.as_integral().unwrap().checked_div(b.as_integral().unwrap()) {
+                        Some(n) => Ok(Value::integral(n)),
+                        None => Err(ExpressionError::InvalidArguments),
+                    }
+                }
+                _ => match float_operands(arguments) {
+                    Some((a, b)) => Ok(Value::Float(OrderedFloat(a / b))),
+                    None => Err(ExpressionError::InvalidArguments),
+                },
+            }
+        }
+        "arn_resource" => {
+            if arguments.len() != 1 {
+                return Err(ExpressionError::InvalidArguments);
+            }
+
+            match &arguments[0] {
+                Value::String(s) => Ok(arn_resource(s).map_or(Value::Null, |name| Value::String(name.to_string()))),
+                Value::Null | Value::Missing => Ok(Value::Null),
+                _ => Err(ExpressionError::InvalidArguments),
+            }
+        }
+        "key_prefix" => {
+            if arguments.len() != 2 {
+                return Err(ExpressionError::InvalidArguments);
+            }
+
+            match (&arguments[0], &arguments[1]) {
+                (Value::String(key), Value::Int(depth)) if *depth >= 0 => {
+                    if key == "-" {
+                        Ok(Value::Null)
+                    } else {
+                        Ok(Value::String(key_prefix(key, *depth as usize)))
+                    }
+                }
+                //The path of a request is keyed the same way, e.g. the requests to an S3 origin behind the ALB
+                (Value::HttpRequest(r), Value::Int(depth)) if *depth >= 0 => Ok(Value::String(key_prefix(
+                    r.url.path().trim_start_matches('/'),
+                    *depth as usize,
+                ))),
+                (Value::Null, _) | (Value::Missing, _) => Ok(Value::Null),
                 _ => Err(ExpressionError::InvalidArguments),
             }
         }
@@ -601,28 +1049,69 @@ impl Relation {
             Relation::NotEqual => Ok(left_result != right_result),
             Relation::GreaterEqual => match (left_result, right_result) {
                 (Value::Int(l), Value::Int(r)) => Ok(l >= r),
+                (l, r) if l.as_integral().is_some() && r.as_integral().is_some() => {
+                    Ok(l.as_integral() >= r.as_integral())
+                }
                 (Value::Float(l), Value::Float(r)) => Ok(l >= r),
-                _ => Err(ExpressionError::TypeMismatch),
+                (l, r) => compare_datetimes(&l, &r).map(|o| o != std::cmp::Ordering::Less),
             },
             Relation::LessEqual => match (left_result, right_result) {
                 (Value::Int(l), Value::Int(r)) => Ok(l <= r),
+                (l, r) if l.as_integral().is_some() && r.as_integral().is_some() => {
+                    Ok(l.as_integral() <= r.as_integral())
+                }
                 (Value::Float(l), Value::Float(r)) => Ok(l <= r),
-                _ => Err(ExpressionError::TypeMismatch),
+                (l, r) => compare_datetimes(&l, &r).map(|o| o != std::cmp::Ordering::Greater),
             },
             Relation::MoreThan => match (left_result, right_result) {
                 (Value::Int(l), Value::Int(r)) => Ok(l > r),
+                (l, r) if l.as_integral().is_some() && r.as_integral().is_some() => {
+                    Ok(l.as_integral() > r.as_integral())
+                }
                 (Value::Float(l), Value::Float(r)) => Ok(l > r),
-                _ => Err(ExpressionError::TypeMismatch),
+                (l, r) => compare_datetimes(&l, &r).map(|o| o == std::cmp::Ordering::Greater),
             },
             Relation::LessThan => match (left_result, right_result) {
                 (Value::Int(l), Value::Int(r)) => Ok(l < r),
+                (l, r) if l.as_integral().is_some() && r.as_integral().is_some() => {
+                    Ok(l.as_integral() < r.as_integral())
+                }
                 (Value::Float(l), Value::Float(r)) => Ok(l < r),
-                _ => Err(ExpressionError::TypeMismatch),
+                (l, r) => compare_datetimes(&l, &r).map(|o| o == std::cmp::Ordering::Less),
             },
         }
     }
 }
 
+//DateTime is comparable with another DateTime or an RFC 3339 string, e.g. timestamp > "2015-11-07T18:45:00Z". Two
+//strings are compared as the timestamps or as the integers they both hold, e.g. elb_status_code >= "500" where the
+//status code is a string, but never as the plain strings, since the lowercased query would compare "...t18:00:00z"
+//after "...T18:30:00Z".
+fn compare_datetimes(left: &Value, right: &Value) -> ExpressionResult<std::cmp::Ordering> {
+    let to_datetime = |v: &Value| match v {
+        Value::DateTime(dt) => Some(*dt),
+        Value::String(s) => chrono::DateTime::parse_from_rfc3339(s).ok(),
+        _ => None,
+    };
+
+    match (left, right) {
+        (Value::DateTime(_), _) | (_, Value::DateTime(_)) => match (to_datetime(left), to_datetime(right)) {
+            (Some(l), Some(r)) => Ok(l.cmp(&r)),
+            _ => Err(ExpressionError::TypeMismatch),
+        },
+        (Value::String(l), Value::String(r)) => {
+            if let (Ok(l), Ok(r)) = (l.parse::<i64>(), r.parse::<i64>()) {
+                return Ok(l.cmp(&r));
+            }
+            match (to_datetime(left), to_datetime(right)) {
+                (Some(l), Some(r)) => Ok(l.cmp(&r)),
+                _ => Err(ExpressionError::TypeMismatch),
+            }
+        }
+        _ => Err(ExpressionError::TypeMismatch),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum Named {
     Expression(Expression, Option<VariableName>),
@@ -669,13 +1158,43 @@ pub(crate) enum Node {
     DataSource(DataSource, Vec<common::types::Binding>),
     Filter(Box<Node>, Box<Formula>),
     Map(Vec<Named>, Box<Node>),
-    GroupBy(Vec<PathExpr>, Vec<NamedAggregate>, Box<Node>),
+    GroupBy(Vec<PathExpr>, Vec<NamedAggregate>, Option<GroupLimit>, Box<Node>),
     Limit(u32, Box<Node>),
     OrderBy(Vec<PathExpr>, Vec<Ordering>, Box<Node>),
+    DistinctOn(Vec<PathExpr>, Box<Node>),
+    Window(Vec<NamedWindow>, Vec<PathExpr>, PathExpr, Box<Node>),
+    GapFill(PathExpr, chrono::Duration, Vec<PathExpr>, Value, Box<Node>),
+    Join(Join, Box<Node>, Box<Node>),
 }
 
 impl Node {
     pub(crate) fn get(&self, variables: Variables) -> CreateStreamResult<Box<dyn RecordStream>> {
+        let stream = self.create_stream(variables)?;
+        if !trace::enabled(trace::Level::Debug) {
+            return Ok(stream);
+        }
+
+        let span = match self {
+            Node::DataSource(data_source, _) => trace::Span::enter(
+                trace::Level::Debug,
+                "reader",
+                format_args!("table={} source={:?}", data_source.table_name(), data_source),
+            ),
+            Node::Filter(_, formula) => {
+                trace::Span::enter(trace::Level::Debug, "filter", format_args!("formula={:?}", formula))
+            }
+            Node::GroupBy(fields, named_aggregates, _, _) => trace::Span::enter(
+                trace::Level::Debug,
+                "aggregate",
+                format_args!("fields={:?} aggregates={}", fields, named_aggregates.len()),
+            ),
+            _ => return Ok(stream),
+        };
+
+        Ok(Box::new(TracedStream::new(span, stream)))
+    }
+
+    fn create_stream(&self, variables: Variables) -> CreateStreamResult<Box<dyn RecordStream>> {
         match self {
             Node::Filter(source, formula) => {
                 let record_stream = source.get(variables.clone())?;
@@ -689,29 +1208,89 @@ impl Node {
 
                 Ok(Box::new(stream))
             }
+            Node::DataSource(data_source, bindings) if data_source.options().channel_capacity.is_some() => {
+                let capacity = data_source.options().channel_capacity.unwrap();
+                let data_source = data_source.clone().with_channel_capacity(None);
+                let bindings = bindings.clone();
+                let stream = ChannelStream::spawn(capacity, move || {
+                    Ok(Node::DataSource(data_source, bindings).create_stream(variables)?)
+                });
+
+                Ok(Box::new(stream))
+            }
             Node::DataSource(data_source, bindings) => match data_source {
-                DataSource::File(path, file_format, _table_name) => {
-                    let reader = ReaderBuilder::new(file_format.clone()).with_path(path)?;
-                    let file_stream = LogFileStream::new(Box::new(reader));
+                DataSource::File(path, file_format, _table_name, options) => {
+                    let builder = ReaderBuilder::new(file_format.clone())
+                        .field_types(options.field_types.clone())
+                        .time_format(options.time_format.clone())
+                        .log_format(options.log_format.clone())
+                        .columns(options.columns.clone());
+                    let index = match &options.index_dir {
+                        Some(index_dir) => Some(FileIndex::open(index_dir)?),
+                        None => None,
+                    };
+                    let file_stream: Box<dyn RecordStream> = if options.follow {
+                        Box::new(TailFilesStream::new(builder, path.clone())?)
+                    } else if path.is_dir() || index.is_some() {
+                        let paths = if path.is_dir() {
+                            list_log_files(path)?
+                        } else {
+                            vec![path.clone()]
+                        };
+                        let paths: Vec<PathBuf> = paths
+                            .into_iter()
+                            .filter(|p| options.time_range.may_overlap_file(p))
+                            .filter(|p| {
+                                index
+                                    .as_ref()
+                                    .is_none_or(|i| i.may_match(p, &options.time_range, &options.value_filters))
+                            })
+                            .collect();
+                        Box::new(LogFilesStream::new(builder, paths, index))
+                    } else {
+                        let reader = builder.open(path)?;
+                        Box::new(LogFileStream::new(reader))
+                    };
 
                     if !bindings.is_empty() {
-                        let stream = ProjectionStream::new(Box::new(file_stream), bindings.clone());
+                        let stream = ProjectionStream::new(file_stream, bindings.clone());
 
                         return Ok(Box::new(stream));
                     } else {
-                        Ok(Box::new(file_stream))
+                        Ok(file_stream)
                     }
                 }
-                DataSource::Stdin(file_format, _table_name) => {
-                    let reader = ReaderBuilder::new(file_format.clone()).with_reader(io::stdin());
+                DataSource::Stdin(file_format, _table_name, options) => {
+                    let reader = ReaderBuilder::new(file_format.clone())
+                        .field_types(options.field_types.clone())
+                        .time_format(options.time_format.clone())
+                        .log_format(options.log_format.clone())
+                        .with_stdin();
                     let stream = LogFileStream::new(Box::new(reader));
 
                     Ok(Box::new(stream))
                 }
+                DataSource::Command(command, file_format, _table_name, options) => {
+                    let reader = ReaderBuilder::new(file_format.clone())
+                        .field_types(options.field_types.clone())
+                        .time_format(options.time_format.clone())
+                        .log_format(options.log_format.clone())
+                        .with_command(command)?;
+                    let command_stream = LogFileStream::new(Box::new(reader));
+
+                    if !bindings.is_empty() {
+                        let stream = ProjectionStream::new(Box::new(command_stream), bindings.clone());
+
+                        Ok(Box::new(stream))
+                    } else {
+                        Ok(Box::new(command_stream))
+                    }
+                }
             },
-            Node::GroupBy(fields, named_aggregates, source) => {
+            Node::GroupBy(fields, named_aggregates, group_limit, source) => {
                 let record_stream = source.get(variables.clone())?;
-                let stream = GroupByStream::new(fields.clone(), variables, named_aggregates.clone(), record_stream);
+                let stream = GroupByStream::new(fields.clone(), variables, named_aggregates.clone(), record_stream)
+                    .group_limit(group_limit.clone());
                 Ok(Box::new(stream))
             }
             Node::Limit(row_count, source) => {
@@ -719,117 +1298,228 @@ impl Node {
                 let stream = LimitStream::new(*row_count, record_stream);
                 Ok(Box::new(stream))
             }
+            Node::DistinctOn(keys, source) => {
+                let record_stream = source.get(variables.clone())?;
+                let stream = DistinctOnStream::new(keys.clone(), record_stream);
+                Ok(Box::new(stream))
+            }
+            Node::Window(windows, partition_keys, time, source) => {
+                let record_stream = source.get(variables.clone())?;
+                let stream = WindowStream::new(windows.clone(), partition_keys.clone(), time.clone(), record_stream);
+                Ok(Box::new(stream))
+            }
+            Node::GapFill(time, interval, partition_keys, fill, source) => {
+                let record_stream = source.get(variables.clone())?;
+                let stream = GapFillStream::new(
+                    time.clone(),
+                    *interval,
+                    partition_keys.clone(),
+                    fill.clone(),
+                    record_stream,
+                );
+                Ok(Box::new(stream))
+            }
             Node::OrderBy(column_names, orderings, source) => {
-                let mut record_stream = source.get(variables.clone())?;
-                let mut records = Vec::new();
-
-                while let Some(record) = record_stream.next()? {
-                    records.push(record);
-                }
-
-                records.sort_by(|a, b| {
-                    for idx in 0..column_names.len() {
-                        let column_name = &column_names[idx];
-                        let curr_ordering = &orderings[idx];
-
-                        let a_value = a.get(column_name);
-                        let b_value = b.get(column_name);
-
-                        match (a_value, b_value) {
-                            (Value::Int(i1), Value::Int(i2)) => match curr_ordering {
-                                Ordering::Asc => {
-                                    return i1.cmp(&i2);
-                                }
-                                Ordering::Desc => {
-                                    return i2.cmp(&i1);
-                                }
-                            },
-                            (Value::Boolean(b1), Value::Boolean(b2)) => match curr_ordering {
-                                Ordering::Asc => {
-                                    return b1.cmp(&b2);
-                                }
-                                Ordering::Desc => {
-                                    return b2.cmp(&b1);
-                                }
-                            },
-                            (Value::Float(f1), Value::Float(f2)) => match curr_ordering {
-                                Ordering::Asc => {
-                                    return f1.cmp(&f2);
-                                }
-                                Ordering::Desc => {
-                                    return f2.cmp(&f1);
-                                }
-                            },
-                            (Value::String(s1), Value::String(s2)) => match curr_ordering {
-                                Ordering::Asc => {
-                                    return s1.cmp(&s2);
-                                }
-                                Ordering::Desc => {
-                                    return s2.cmp(&s1);
-                                }
-                            },
-                            (Value::DateTime(dt1), Value::DateTime(dt2)) => match curr_ordering {
-                                Ordering::Asc => {
-                                    return dt1.cmp(&dt2);
-                                }
-                                Ordering::Desc => {
-                                    return dt2.cmp(&dt1);
-                                }
-                            },
-                            (Value::Null, Value::Null) => {
-                                return std::cmp::Ordering::Equal;
-                            }
-                            (Value::Host(h1), Value::Host(h2)) => {
-                                let s1 = h1.to_string();
-                                let s2 = h2.to_string();
-
-                                match curr_ordering {
-                                    Ordering::Asc => {
-                                        return s1.cmp(&s2);
-                                    }
-                                    Ordering::Desc => {
-                                        return s2.cmp(&s1);
-                                    }
-                                }
-                            }
-                            (Value::HttpRequest(h1), Value::HttpRequest(h2)) => {
-                                let s1 = h1.to_string();
-                                let s2 = h2.to_string();
-
-                                match curr_ordering {
-                                    Ordering::Asc => {
-                                        return s1.cmp(&s2);
-                                    }
-                                    Ordering::Desc => {
-                                        return s2.cmp(&s1);
-                                    }
-                                }
-                            }
-                            _ => {
-                                unreachable!();
-                            }
-                        }
-                    }
-
-                    std::cmp::Ordering::Equal
-                });
-
-                let stream = InMemoryStream::new(VecDeque::from(records));
+                let record_stream = source.get(variables.clone())?;
+                let stream = OrderByStream::new(column_names.clone(), orderings.clone(), record_stream);
+                Ok(Box::new(stream))
+            }
+            Node::Join(join, left, right) => {
+                let left_stream = left.get(variables.clone())?;
+                let right_stream = right.get(variables)?;
+                let stream = JoinStream::new(join.clone(), left_stream, right_stream);
                 Ok(Box::new(stream))
             }
         }
     }
 }
 
-pub(crate) type AggregateResult<T> = result::Result<T, AggregateError>;
+//The order of the records by the columns of an order by, the first column which differs decides
+pub(crate) fn compare_records(
+    a: &Record,
+    b: &Record,
+    column_names: &[PathExpr],
+    orderings: &[Ordering],
+) -> std::cmp::Ordering {
+    for (column_name, curr_ordering) in column_names.iter().zip(orderings.iter()) {
+        let a_value = a.get(column_name);
+        let b_value = b.get(column_name);
+
+        let ordering = match (a_value, b_value) {
+            (Value::Int(i1), Value::Int(i2)) => i1.cmp(&i2),
+            (v1, v2) if v1.as_integral().is_some() && v2.as_integral().is_some() => {
+                v1.as_integral().cmp(&v2.as_integral())
+            }
+            (Value::Boolean(b1), Value::Boolean(b2)) => b1.cmp(&b2),
+            (Value::Float(f1), Value::Float(f2)) => f1.cmp(&f2),
+            (Value::String(s1), Value::String(s2)) => s1.cmp(&s2),
+            (Value::DateTime(dt1), Value::DateTime(dt2)) => dt1.cmp(&dt2),
+            //The nulls, e.g. the missing side of a left join, come first
+            (Value::Null | Value::Missing, Value::Null | Value::Missing) => std::cmp::Ordering::Equal,
+            (Value::Null | Value::Missing, _) => std::cmp::Ordering::Less,
+            (_, Value::Null | Value::Missing) => std::cmp::Ordering::Greater,
+            (Value::Host(h1), Value::Host(h2)) => h1.to_string().cmp(&h2.to_string()),
+            (Value::HttpRequest(h1), Value::HttpRequest(h2)) => h1.to_string().cmp(&h2.to_string()),
+            _ => {
+                unreachable!();
+            }
+        };
 
-#[derive(Fail, PartialEq, Eq, Debug)]
-pub enum AggregateError {
-    #[fail(display = "Key Not Found")]
-    KeyNotFound,
-    #[fail(display = "Invalid Type")]
-    InvalidType,
-}
+        let ordering = match curr_ordering {
+            Ordering::Asc => ordering,
+            Ordering::Desc => ordering.reverse(),
+        };
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    std::cmp::Ordering::Equal
+}
+
+pub(crate) type AggregateResult<T> = result::Result<T, AggregateError>;
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum AggregateError {
+    KeyNotFound,
+    InvalidType,
+    Overflow,
+    Incompatible,
+}
+
+impl fmt::Display for AggregateError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AggregateError::KeyNotFound => fmt.write_str("Key Not Found"),
+            AggregateError::InvalidType => fmt.write_str("Invalid Type"),
+            AggregateError::Overflow => fmt.write_str("Integer Overflow"),
+            AggregateError::Incompatible => fmt.write_str("Incompatible Aggregates"),
+        }
+    }
+}
+
+impl std::error::Error for AggregateError {}
+
+//The functions of a row and the previous row of its partition, e.g. the change of the count between the time buckets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WindowFunction {
+    Delta,
+    RatePerSec,
+    MovingAvg(usize),
+}
+
+impl WindowFunction {
+    //The number of the previous rows of the partition the function looks at
+    pub(crate) fn preceding_rows(&self) -> usize {
+        match self {
+            WindowFunction::MovingAvg(n) => n - 1,
+            _ => 1,
+        }
+    }
+
+    //The previous values are in the order of the rows. Null for the first row of a partition or the values which are
+    //not numbers, the moving average is over the rows so far until there are enough.
+    pub(crate) fn apply(&self, current: &Value, previous: &[Value], elapsed: Option<chrono::Duration>) -> Value {
+        let previous_value = match (self, previous.last()) {
+            (WindowFunction::MovingAvg(n), _) => {
+                let numbers: Vec<f32> = previous
+                    .iter()
+                    .rev()
+                    .take(n - 1)
+                    .chain(std::iter::once(current))
+                    .filter_map(|v| match v {
+                        Value::Int(i) => Some(*i as f32),
+                        Value::Float(f) => Some(f.into_inner()),
+                        _ => None,
+                    })
+                    .collect();
+                if numbers.is_empty() {
+                    return Value::Null;
+                }
+                return Value::Float(OrderedFloat(numbers.iter().sum::<f32>() / numbers.len() as f32));
+            }
+            (_, Some(previous_value)) => previous_value,
+            (_, None) => return Value::Null,
+        };
+
+        let delta = match (current, previous_value) {
+            (Value::Int(a), Value::Int(b)) => match a.checked_sub(*b) {
+                Some(d) => Value::Int(d),
+                None => Value::Float(OrderedFloat(*a as f32 - *b as f32)),
+            },
+            _ => match float_operands(&[current.clone(), previous_value.clone()]) {
+                Some((a, b)) => Value::Float(OrderedFloat(a - b)),
+                None => return Value::Null,
+            },
+        };
+
+        match self {
+            WindowFunction::RatePerSec => {
+                let seconds = match elapsed {
+                    Some(elapsed) if elapsed > chrono::Duration::zero() => elapsed.num_milliseconds() as f32 / 1000.0,
+                    _ => return Value::Null,
+                };
+                match delta {
+                    Value::Int(d) => Value::Float(OrderedFloat(d as f32 / seconds)),
+                    Value::Float(d) => Value::Float(OrderedFloat(d.into_inner() / seconds)),
+                    _ => Value::Null,
+                }
+            }
+            _ => delta,
+        }
+    }
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WindowFunction::Delta => fmt.write_str("delta"),
+            WindowFunction::RatePerSec => fmt.write_str("rate_per_sec"),
+            WindowFunction::MovingAvg(_) => fmt.write_str("moving_avg"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NamedWindow {
+    pub(crate) function: WindowFunction,
+    pub(crate) column: PathExpr,
+    pub(crate) name: VariableName,
+}
+
+impl NamedWindow {
+    pub(crate) fn new(function: WindowFunction, column: PathExpr, name: VariableName) -> Self {
+        NamedWindow { function, column, name }
+    }
+}
+
+//The equalities of the columns of the two sides of a join, the records of each side are nested under its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Join {
+    pub(crate) kind: JoinKind,
+    pub(crate) left_name: VariableName,
+    pub(crate) right_name: VariableName,
+    pub(crate) left_keys: Vec<PathExpr>,
+    pub(crate) right_keys: Vec<PathExpr>,
+}
+
+impl Join {
+    pub(crate) fn new(
+        kind: JoinKind,
+        left_name: VariableName,
+        right_name: VariableName,
+        left_keys: Vec<PathExpr>,
+        right_keys: Vec<PathExpr>,
+    ) -> Self {
+        Join {
+            kind,
+            left_name,
+            right_name,
+            left_keys,
+            right_keys,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct NamedAggregate {
@@ -853,9 +1543,21 @@ pub(crate) enum Aggregate {
     Min(MinAggregate, Named),
     Sum(SumAggregate, Named),
     ApproxCountDistinct(ApproxCountDistinctAggregate, Named),
-    PercentileDisc(PercentileDiscAggregate, String),
-    ApproxPercentile(ApproxPercentileAggregate, String),
+    Rate(RateAggregate, Named),
+    Ratio(RatioAggregate, Named),
+    PercentileDisc(PercentileDiscAggregate, String, Option<String>),
+    ApproxPercentile(ApproxPercentileAggregate, String, Option<String>),
     GroupAs(GroupAsAggregate, Named),
+    AnyValue(SampleAggregate, Named),
+    Sample(SampleAggregate, Named),
+    Corr(CovarianceAggregate, Named),
+    Covar(CovarianceAggregate, Named),
+    Entropy(FrequencyAggregate, Named),
+    Concentration(FrequencyAggregate, Named),
+    ApproxTopK(ApproxTopKAggregate, Named),
+    MinBy(ExtremeByAggregate, Named),
+    MaxBy(ExtremeByAggregate, Named),
+    AvgWeighted(AvgWeightedAggregate, Named),
 }
 
 impl Aggregate {
@@ -871,8 +1573,20 @@ impl Aggregate {
             Aggregate::Max(agg, _) => agg.add_record(key, value),
             Aggregate::Min(agg, _) => agg.add_record(key, value),
             Aggregate::ApproxCountDistinct(agg, _) => agg.add_record(key, value),
-            Aggregate::PercentileDisc(agg, _) => agg.add_record(key, value),
-            Aggregate::ApproxPercentile(agg, _) => agg.add_record(key, value),
+            Aggregate::Rate(agg, _) => agg.add_record(key, value),
+            Aggregate::Ratio(agg, _) => agg.add_record(key, value),
+            Aggregate::PercentileDisc(agg, _, _) => agg.add_record(key, value),
+            Aggregate::ApproxPercentile(agg, _, _) => agg.add_record(key, value),
+            Aggregate::AnyValue(agg, _) => agg.add_record(key, value),
+            Aggregate::Sample(agg, _) => agg.add_record(key, value),
+            Aggregate::Corr(agg, _) => agg.add_record(key, value),
+            Aggregate::Covar(agg, _) => agg.add_record(key, value),
+            Aggregate::Entropy(agg, _) => agg.add_record(key, value),
+            Aggregate::Concentration(agg, _) => agg.add_record(key, value),
+            Aggregate::ApproxTopK(agg, _) => agg.add_record(key, value),
+            Aggregate::MinBy(agg, _) => agg.add_record(key, value),
+            Aggregate::MaxBy(agg, _) => agg.add_record(key, value),
+            Aggregate::AvgWeighted(agg, _) => agg.add_record(key, value),
         }
     }
     pub(crate) fn get_aggregated(&mut self, key: &Option<Tuple>) -> AggregateResult<Value> {
@@ -886,15 +1600,59 @@ impl Aggregate {
             Aggregate::Max(agg, _) => agg.get_aggregated(key),
             Aggregate::Min(agg, _) => agg.get_aggregated(key),
             Aggregate::ApproxCountDistinct(agg, _) => agg.get_aggregated(key),
-            Aggregate::PercentileDisc(agg, _) => agg.get_aggregated(key),
-            Aggregate::ApproxPercentile(agg, _) => agg.get_aggregated(key),
+            Aggregate::Rate(agg, _) => agg.get_aggregated(key),
+            Aggregate::Ratio(agg, _) => agg.get_aggregated(key),
+            Aggregate::PercentileDisc(agg, _, _) => agg.get_aggregated(key),
+            Aggregate::ApproxPercentile(agg, _, _) => agg.get_aggregated(key),
+            Aggregate::AnyValue(agg, _) => agg.get_any(key),
+            Aggregate::Sample(agg, _) => agg.get_aggregated(key),
+            Aggregate::Corr(agg, _) => agg.get_correlation(key),
+            Aggregate::Covar(agg, _) => agg.get_aggregated(key),
+            Aggregate::Entropy(agg, _) => agg.get_entropy(key),
+            Aggregate::Concentration(agg, _) => agg.get_concentration(key),
+            Aggregate::ApproxTopK(agg, _) => agg.get_aggregated(key),
+            Aggregate::MinBy(agg, _) => agg.get_aggregated(key),
+            Aggregate::MaxBy(agg, _) => agg.get_aggregated(key),
+            Aggregate::AvgWeighted(agg, _) => agg.get_aggregated(key),
+        }
+    }
+
+    //Combines the state of the same aggregate over another partition of the records, e.g. from a parallel scan.
+    //The other partition is taken as the records after the ones of this aggregate, which matters to first and last.
+    #[allow(dead_code)]
+    pub(crate) fn merge(&mut self, other: Aggregate) -> AggregateResult<()> {
+        match (self, other) {
+            (Aggregate::GroupAs(agg, _), Aggregate::GroupAs(other, _)) => agg.merge(other),
+            (Aggregate::Avg(agg, _), Aggregate::Avg(other, _)) => agg.merge(other),
+            (Aggregate::Count(agg, _), Aggregate::Count(other, _)) => agg.merge(other),
+            (Aggregate::First(agg, _), Aggregate::First(other, _)) => agg.merge(other),
+            (Aggregate::Last(agg, _), Aggregate::Last(other, _)) => agg.merge(other),
+            (Aggregate::Sum(agg, _), Aggregate::Sum(other, _)) => agg.merge(other),
+            (Aggregate::Max(agg, _), Aggregate::Max(other, _)) => agg.merge(other),
+            (Aggregate::Min(agg, _), Aggregate::Min(other, _)) => agg.merge(other),
+            (Aggregate::ApproxCountDistinct(agg, _), Aggregate::ApproxCountDistinct(other, _)) => agg.merge(other),
+            (Aggregate::Rate(agg, _), Aggregate::Rate(other, _)) => agg.merge(other),
+            (Aggregate::Ratio(agg, _), Aggregate::Ratio(other, _)) => agg.merge(other),
+            (Aggregate::PercentileDisc(agg, _, _), Aggregate::PercentileDisc(other, _, _)) => agg.merge(other),
+            (Aggregate::ApproxPercentile(agg, _, _), Aggregate::ApproxPercentile(other, _, _)) => agg.merge(other),
+            (Aggregate::AnyValue(agg, _), Aggregate::AnyValue(other, _)) => agg.merge(other),
+            (Aggregate::Sample(agg, _), Aggregate::Sample(other, _)) => agg.merge(other),
+            (Aggregate::Corr(agg, _), Aggregate::Corr(other, _)) => agg.merge(other),
+            (Aggregate::Covar(agg, _), Aggregate::Covar(other, _)) => agg.merge(other),
+            (Aggregate::Entropy(agg, _), Aggregate::Entropy(other, _)) => agg.merge(other),
+            (Aggregate::Concentration(agg, _), Aggregate::Concentration(other, _)) => agg.merge(other),
+            (Aggregate::ApproxTopK(agg, _), Aggregate::ApproxTopK(other, _)) => agg.merge(other),
+            (Aggregate::MinBy(agg, _), Aggregate::MinBy(other, _)) => agg.merge(other),
+            (Aggregate::MaxBy(agg, _), Aggregate::MaxBy(other, _)) => agg.merge(other),
+            (Aggregate::AvgWeighted(agg, _), Aggregate::AvgWeighted(other, _)) => agg.merge(other),
+            _ => Err(AggregateError::Incompatible),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct PercentileDiscAggregate {
-    pub(crate) partitions: HashMap<Option<Tuple>, Vec<Value>>,
+    pub(crate) partitions: HashMap<Option<Tuple>, Vec<(Value, OrderedFloat<f64>)>>,
     pub(crate) percentile: OrderedFloat<f32>,
     pub(crate) ordering: Ordering,
 }
@@ -909,8 +1667,26 @@ impl PercentileDiscAggregate {
     }
 
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        self.add_weighted_record(key, value, &Value::Int(1))
+    }
+
+    pub(crate) fn add_weighted_record(
+        &mut self,
+        key: &Option<Tuple>,
+        value: &Value,
+        weight: &Value,
+    ) -> AggregateResult<()> {
+        let w = percentile_weight(weight)?;
         let v = self.partitions.entry(key.clone()).or_insert(Vec::new());
-        v.push(value.clone());
+        v.push((value.clone(), OrderedFloat::from(w)));
+
+        Ok(())
+    }
+
+    pub(crate) fn merge(&mut self, other: PercentileDiscAggregate) -> AggregateResult<()> {
+        for (key, values) in other.partitions.into_iter() {
+            self.partitions.entry(key).or_default().extend(values);
+        }
 
         Ok(())
     }
@@ -918,7 +1694,7 @@ impl PercentileDiscAggregate {
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
         //FIXME: expensive operation
         let mut v = self.partitions.get(key).unwrap().clone();
-        v.sort_by(|a, b| match (a, b) {
+        v.sort_by(|(a, _), (b, _)| match (a, b) {
             (Value::Int(i1), Value::Int(i2)) => match self.ordering {
                 Ordering::Asc => i1.cmp(i2),
                 Ordering::Desc => i2.cmp(i1),
@@ -963,17 +1739,31 @@ impl PercentileDiscAggregate {
             }
         });
 
+        //Pick the first value whose cumulative weight passes the percentile, with unit weights
+        //this is the value at floor(n * p)
+        let total: f64 = v.iter().map(|(_, w)| w.into_inner()).sum();
+        if total <= 0.0 {
+            return Ok(Value::Null);
+        }
+
         let f32_percentile: f32 = self.percentile.into();
-        let idx: usize = ((v.len() as f32) * f32_percentile) as usize;
-        let ans = v[idx].clone();
-        Ok(ans)
+        let target = f64::from(total as f32 * f32_percentile);
+        let mut cumulative = 0.0;
+        for (val, w) in v.iter() {
+            cumulative += w.into_inner();
+            if cumulative > target {
+                return Ok(val.clone());
+            }
+        }
+
+        Ok(v.last().unwrap().0.clone())
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ApproxPercentileAggregate {
     pub(crate) partitions: HashMap<Option<Tuple>, TDigest>,
-    pub(crate) buffer: HashMap<Option<Tuple>, Vec<Value>>,
+    pub(crate) buffer: HashMap<Option<Tuple>, Vec<(Value, OrderedFloat<f64>)>>,
     pub(crate) percentile: OrderedFloat<f32>,
     pub(crate) ordering: Ordering,
 }
@@ -989,8 +1779,18 @@ impl ApproxPercentileAggregate {
     }
 
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        self.add_weighted_record(key, value, &Value::Int(1))
+    }
+
+    pub(crate) fn add_weighted_record(
+        &mut self,
+        key: &Option<Tuple>,
+        value: &Value,
+        weight: &Value,
+    ) -> AggregateResult<()> {
+        let w = percentile_weight(weight)?;
         let buf = self.buffer.entry(key.clone()).or_insert(Vec::new());
-        buf.push(value.clone());
+        buf.push((value.clone(), OrderedFloat::from(w)));
 
         if buf.len() < 10000 {
             Ok(())
@@ -1000,22 +1800,7 @@ impl ApproxPercentileAggregate {
                 .entry(key.clone())
                 .or_insert(TDigest::new_with_size(100));
 
-            let mut fvec = Vec::new();
-            for val in buf.iter() {
-                match val {
-                    Value::Float(f) => {
-                        fvec.push(f64::from(f.into_inner()));
-                    }
-                    Value::Int(i) => {
-                        fvec.push(f64::from(*i));
-                    }
-                    _ => {
-                        return Err(AggregateError::InvalidType);
-                    }
-                }
-            }
-
-            let new_digest = v.merge_unsorted(fvec);
+            let new_digest = merge_into_digest(v, buf)?;
             self.partitions.insert(key.clone(), new_digest);
             buf.clear();
 
@@ -1023,6 +1808,22 @@ impl ApproxPercentileAggregate {
         }
     }
 
+    //The digests are merged, the buffered values are carried over and merged into the digest later
+    pub(crate) fn merge(&mut self, other: ApproxPercentileAggregate) -> AggregateResult<()> {
+        for (key, digest) in other.partitions.into_iter() {
+            let merged = match self.partitions.remove(&key) {
+                Some(d) => TDigest::merge_digests(vec![d, digest]),
+                None => digest,
+            };
+            self.partitions.insert(key, merged);
+        }
+        for (key, values) in other.buffer.into_iter() {
+            self.buffer.entry(key).or_default().extend(values);
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_aggregated(&mut self, key: &Option<Tuple>) -> AggregateResult<Value> {
         let buf = self.buffer.entry(key.clone()).or_insert(Vec::new());
         let t = if !buf.is_empty() {
@@ -1031,26 +1832,15 @@ impl ApproxPercentileAggregate {
                 .entry(key.clone())
                 .or_insert(TDigest::new_with_size(100));
 
-            let mut fvec = Vec::new();
-            for val in buf.iter() {
-                match val {
-                    Value::Float(f) => {
-                        fvec.push(f64::from(f.into_inner()));
-                    }
-                    Value::Int(i) => {
-                        fvec.push(f64::from(*i));
-                    }
-                    _ => {
-                        return Err(AggregateError::InvalidType);
-                    }
-                }
-            }
-
-            v.merge_unsorted(fvec)
+            merge_into_digest(v, buf)?
         } else {
             self.partitions.get(key).unwrap().clone()
         };
 
+        if t.is_empty() {
+            return Ok(Value::Null);
+        }
+
         let f32_percentile: f32 = self.percentile.into();
         let f64_percentile: f64 = f64::from(f32_percentile);
         let f64_ans = t.estimate_quantile(f64_percentile);
@@ -1059,6 +1849,51 @@ impl ApproxPercentileAggregate {
     }
 }
 
+//The weight of a percentile input, e.g. received_bytes. Null weights count as zero.
+fn percentile_weight(weight: &Value) -> AggregateResult<f64> {
+    match weight {
+        Value::Int(i) if *i >= 0 => Ok(f64::from(*i)),
+        Value::Float(f) if f.into_inner() >= 0.0 => Ok(f64::from(f.into_inner())),
+        Value::Null | Value::Missing => Ok(0.0),
+        _ => Err(AggregateError::InvalidType),
+    }
+}
+
+fn merge_into_digest(digest: &TDigest, buf: &[(Value, OrderedFloat<f64>)]) -> AggregateResult<TDigest> {
+    let mut pairs: Vec<(f64, f64)> = Vec::new();
+    for (val, w) in buf.iter() {
+        let f = match val {
+            Value::Float(f) => f64::from(f.into_inner()),
+            Value::Int(i) => f64::from(*i),
+            _ => {
+                return Err(AggregateError::InvalidType);
+            }
+        };
+        pairs.push((f, w.into_inner()));
+    }
+
+    if pairs.iter().all(|&(_, w)| (w - 1.0).abs() < f64::EPSILON) {
+        let fvec = pairs.into_iter().map(|(f, _)| f).collect();
+        return Ok(digest.merge_unsorted(fvec));
+    }
+
+    //Weighted values become one centroid each, merged with the existing digest
+    pairs.retain(|&(_, w)| w > 0.0);
+    if pairs.is_empty() {
+        return Ok(digest.clone());
+    }
+    pairs.sort_by(|a, b| OrderedFloat::from(a.0).cmp(&OrderedFloat::from(b.0)));
+
+    let sum: f64 = pairs.iter().map(|&(f, w)| f * w).sum();
+    let count: f64 = pairs.iter().map(|&(_, w)| w).sum();
+    let min = pairs.first().unwrap().0;
+    let max = pairs.last().unwrap().0;
+    let centroids: Vec<Centroid> = pairs.iter().map(|&(f, w)| Centroid::new(f, w)).collect();
+    let new_digest = TDigest::new(centroids, sum, count, max, min, pairs.len());
+
+    Ok(TDigest::merge_digests(vec![digest.clone(), new_digest]))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct AvgAggregate {
     pub(crate) averages: HashMap<Option<Tuple>, OrderedFloat<f32>>,
@@ -1076,7 +1911,13 @@ impl AvgAggregate {
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
         let new_value: OrderedFloat<f32> = match value {
             &Value::Int(i) => OrderedFloat::from(i as f32),
+            &Value::Long(l) => OrderedFloat::from(l as f32),
             &Value::Float(f) => f,
+            //The nulls are skipped, e.g. the "-" times of the failed requests, a group of only nulls averages to null
+            Value::Null | Value::Missing => {
+                self.counts.entry(key.clone()).or_insert(0);
+                return Ok(());
+            }
             _ => {
                 return Err(AggregateError::InvalidType);
             }
@@ -1098,18 +1939,49 @@ impl AvgAggregate {
         }
     }
 
+    pub(crate) fn merge(&mut self, other: AvgAggregate) -> AggregateResult<()> {
+        for (key, other_average) in other.averages.into_iter() {
+            let other_count = other.counts.get(&key).cloned().unwrap_or(0);
+            if let (Some(&average), Some(&count)) = (self.averages.get(&key), self.counts.get(&key)) {
+                let new_count = count + other_count;
+                let new_average: f32 = (average.into_inner() * (count as f32)
+                    + other_average.into_inner() * (other_count as f32))
+                    / (new_count as f32);
+                self.averages.insert(key.clone(), OrderedFloat::from(new_average));
+                self.counts.insert(key, new_count);
+            } else {
+                self.averages.insert(key.clone(), other_average);
+                self.counts.insert(key, other_count);
+            }
+        }
+        for (key, _) in other.counts.into_iter() {
+            self.counts.entry(key).or_insert(0);
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
         if let Some(&average) = self.averages.get(key) {
             Ok(Value::Float(average))
+        } else if self.counts.contains_key(key) {
+            Ok(Value::Null)
         } else {
             Err(AggregateError::KeyNotFound)
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PartialSum {
+    Integral(i128),
+    Float(OrderedFloat<f64>),
+}
+
+//None for a group of only nulls
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct SumAggregate {
-    pub(crate) sums: HashMap<Option<Tuple>, OrderedFloat<f32>>,
+    pub(crate) sums: HashMap<Option<Tuple>, Option<PartialSum>>,
 }
 
 impl SumAggregate {
@@ -1118,32 +1990,86 @@ impl SumAggregate {
     }
 
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
-        let new_value: OrderedFloat<f32> = match value {
-            &Value::Int(i) => OrderedFloat::from(i as f32),
-            &Value::Float(f) => f,
+        //The nulls are skipped as in avg, a group of only nulls sums to null
+        if let Value::Null | Value::Missing = value {
+            self.sums.entry(key.clone()).or_insert(None);
+            return Ok(());
+        }
+
+        let current = self.sums.get(key).cloned().flatten();
+        //Integers are accumulated exactly, the sum only degrades to float when a float is involved.
+        let new_sum = match (current, value) {
+            (None, &Value::Long(l)) => PartialSum::Integral(i128::from(l)),
+            (Some(PartialSum::Integral(s)), &Value::Long(l)) => {
+                PartialSum::Integral(s.checked_add(i128::from(l)).ok_or(AggregateError::Overflow)?)
+            }
+            (Some(PartialSum::Float(s)), &Value::Long(l)) => {
+                PartialSum::Float(OrderedFloat::from(s.into_inner() + l as f64))
+            }
+            (None, &Value::Int(i)) => PartialSum::Integral(i128::from(i)),
+            (None, &Value::Float(f)) => PartialSum::Float(OrderedFloat::from(f64::from(f.into_inner()))),
+            (Some(PartialSum::Integral(s)), &Value::Int(i)) => {
+                let sum = s.checked_add(i128::from(i)).ok_or(AggregateError::Overflow)?;
+                PartialSum::Integral(sum)
+            }
+            (Some(PartialSum::Integral(s)), &Value::Float(f)) => {
+                PartialSum::Float(OrderedFloat::from(s as f64 + f64::from(f.into_inner())))
+            }
+            (Some(PartialSum::Float(s)), &Value::Int(i)) => {
+                PartialSum::Float(OrderedFloat::from(s.into_inner() + f64::from(i)))
+            }
+            (Some(PartialSum::Float(s)), &Value::Float(f)) => {
+                PartialSum::Float(OrderedFloat::from(s.into_inner() + f64::from(f.into_inner())))
+            }
             _ => {
                 return Err(AggregateError::InvalidType);
             }
         };
 
-        if let Some(&average) = self.sums.get(key) {
-            let f32_average: f32 = average.into();
-            let f32_new_value: f32 = new_value.into();
-            let new_average: f32 = f32_average + f32_new_value;
-            self.sums.insert(key.clone(), OrderedFloat::from(new_average));
-            Ok(())
-        } else {
-            self.sums.insert(key.clone(), new_value);
+        self.sums.insert(key.clone(), Some(new_sum));
+        Ok(())
+    }
 
-            Ok(())
+    pub(crate) fn merge(&mut self, other: SumAggregate) -> AggregateResult<()> {
+        for (key, other_sum) in other.sums.into_iter() {
+            let other_sum = match other_sum {
+                Some(other_sum) => other_sum,
+                None => {
+                    self.sums.entry(key).or_insert(None);
+                    continue;
+                }
+            };
+            let new_sum = match (self.sums.get(&key).cloned().flatten(), other_sum) {
+                (None, s) => s,
+                (Some(PartialSum::Integral(s1)), PartialSum::Integral(s2)) => {
+                    PartialSum::Integral(s1.checked_add(s2).ok_or(AggregateError::Overflow)?)
+                }
+                (Some(PartialSum::Integral(s1)), PartialSum::Float(s2)) => {
+                    PartialSum::Float(OrderedFloat::from(s1 as f64 + s2.into_inner()))
+                }
+                (Some(PartialSum::Float(s1)), PartialSum::Integral(s2)) => {
+                    PartialSum::Float(OrderedFloat::from(s1.into_inner() + s2 as f64))
+                }
+                (Some(PartialSum::Float(s1)), PartialSum::Float(s2)) => {
+                    PartialSum::Float(OrderedFloat::from(s1.into_inner() + s2.into_inner()))
+                }
+            };
+            self.sums.insert(key, Some(new_sum));
         }
+
+        Ok(())
     }
 
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
-        if let Some(&average) = self.sums.get(key) {
-            Ok(Value::Float(average))
-        } else {
-            Err(AggregateError::KeyNotFound)
+        match self.sums.get(key) {
+            //The sums out of the range of Int are kept exact as Long
+            Some(&Some(PartialSum::Integral(s))) => match i64::try_from(s) {
+                Ok(l) => Ok(Value::integral(l)),
+                Err(_) => Err(AggregateError::Overflow),
+            },
+            Some(&Some(PartialSum::Float(f))) => Ok(Value::Float(OrderedFloat::from(f.into_inner() as f32))),
+            Some(None) => Ok(Value::Null),
+            None => Err(AggregateError::KeyNotFound),
         }
     }
 }
@@ -1187,6 +2113,14 @@ impl CountAggregate {
         }
     }
 
+    pub(crate) fn merge(&mut self, other: CountAggregate) -> AggregateResult<()> {
+        for (key, count) in other.counts.into_iter() {
+            *self.counts.entry(key).or_insert(0) += count;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
         if let Some(&counts) = self.counts.get(key) {
             Ok(Value::Int(counts as i32))
@@ -1216,6 +2150,14 @@ impl GroupAsAggregate {
         }
     }
 
+    pub(crate) fn merge(&mut self, other: GroupAsAggregate) -> AggregateResult<()> {
+        for (key, tuples) in other.tuples.into_iter() {
+            self.tuples.entry(key).or_default().extend(tuples);
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
         if let Some(tuples) = self.tuples.get(key) {
             Ok(Value::Array(tuples.clone()))
@@ -1239,7 +2181,12 @@ impl MaxAggregate {
         if let Some(candidate) = self.maxs.get(key) {
             let less_than = match (candidate, value) {
                 (&Value::Int(i1), &Value::Int(i2)) => i1 < i2,
+                (i1, i2) if i1.as_integral().is_some() && i2.as_integral().is_some() => {
+                    i1.as_integral() < i2.as_integral()
+                }
                 (&Value::Float(f1), &Value::Float(f2)) => f1 < f2,
+                (Value::String(s1), Value::String(s2)) => s1 < s2,
+                (Value::DateTime(d1), Value::DateTime(d2)) => d1 < d2,
                 _ => {
                     return Err(AggregateError::InvalidType);
                 }
@@ -1256,6 +2203,14 @@ impl MaxAggregate {
         }
     }
 
+    pub(crate) fn merge(&mut self, other: MaxAggregate) -> AggregateResult<()> {
+        for (key, value) in other.maxs.iter() {
+            self.add_record(key, value)?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
         if let Some(first) = self.maxs.get(key) {
             Ok(first.clone())
@@ -1279,7 +2234,12 @@ impl MinAggregate {
         if let Some(candidate) = self.mins.get(key) {
             let greater_than = match (candidate, value) {
                 (&Value::Int(i1), &Value::Int(i2)) => i1 > i2,
+                (i1, i2) if i1.as_integral().is_some() && i2.as_integral().is_some() => {
+                    i1.as_integral() > i2.as_integral()
+                }
                 (&Value::Float(f1), &Value::Float(f2)) => f1 > f2,
+                (Value::String(s1), Value::String(s2)) => s1 > s2,
+                (Value::DateTime(d1), Value::DateTime(d2)) => d1 > d2,
                 _ => {
                     return Err(AggregateError::InvalidType);
                 }
@@ -1296,6 +2256,14 @@ impl MinAggregate {
         }
     }
 
+    pub(crate) fn merge(&mut self, other: MinAggregate) -> AggregateResult<()> {
+        for (key, value) in other.mins.iter() {
+            self.add_record(key, value)?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
         if let Some(first) = self.mins.get(key) {
             Ok(first.clone())
@@ -1306,28 +2274,63 @@ impl MinAggregate {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct FirstAggregate {
-    pub(crate) firsts: HashMap<Option<Tuple>, Value>,
+pub(crate) struct RateAggregate {
+    pub(crate) spans: HashMap<
+        Option<Tuple>,
+        (
+            i64,
+            chrono::DateTime<chrono::FixedOffset>,
+            chrono::DateTime<chrono::FixedOffset>,
+        ),
+    >,
 }
 
-impl FirstAggregate {
+impl RateAggregate {
     pub(crate) fn new() -> Self {
-        FirstAggregate { firsts: HashMap::new() }
+        RateAggregate { spans: HashMap::new() }
     }
 
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
-        if self.firsts.get(&key).is_some() {
-            //do nothing
-            Ok(())
-        } else {
-            self.firsts.insert(key.clone(), value.clone());
-            Ok(())
+        let dt = match value {
+            Value::DateTime(dt) => *dt,
+            Value::Null => {
+                return Ok(());
+            }
+            _ => {
+                return Err(AggregateError::InvalidType);
+            }
+        };
+
+        let span = match self.spans.get(key) {
+            Some(&(count, first, last)) => (count + 1, first.min(dt), last.max(dt)),
+            None => (1, dt, dt),
+        };
+
+        self.spans.insert(key.clone(), span);
+        Ok(())
+    }
+
+    pub(crate) fn merge(&mut self, other: RateAggregate) -> AggregateResult<()> {
+        for (key, (other_count, other_first, other_last)) in other.spans.into_iter() {
+            let span = match self.spans.get(&key) {
+                Some(&(count, first, last)) => (count + other_count, first.min(other_first), last.max(other_last)),
+                None => (other_count, other_first, other_last),
+            };
+            self.spans.insert(key, span);
         }
+
+        Ok(())
     }
 
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
-        if let Some(first) = self.firsts.get(key) {
-            Ok(first.clone())
+        if let Some(&(count, first, last)) = self.spans.get(key) {
+            //Events per second over the time range of the records
+            let seconds = (last - first).num_milliseconds() as f64 / 1000.0;
+            if seconds > 0.0 {
+                Ok(Value::Float(OrderedFloat::from((count as f64 / seconds) as f32)))
+            } else {
+                Ok(Value::Null)
+            }
         } else {
             Err(AggregateError::KeyNotFound)
         }
@@ -1335,62 +2338,695 @@ impl FirstAggregate {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct LastAggregate {
-    pub(crate) lasts: HashMap<Option<Tuple>, Value>,
+pub(crate) struct RatioAggregate {
+    pub(crate) counts: HashMap<Option<Tuple>, (i64, i64)>,
 }
 
-impl LastAggregate {
+impl RatioAggregate {
     pub(crate) fn new() -> Self {
-        LastAggregate { lasts: HashMap::new() }
+        RatioAggregate { counts: HashMap::new() }
     }
 
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
-        self.lasts.insert(key.clone(), value.clone());
+        let matched = match value {
+            Value::Boolean(b) => *b,
+            Value::Null | Value::Missing => false,
+            _ => {
+                return Err(AggregateError::InvalidType);
+            }
+        };
+
+        let (matches, total) = self.counts.get(key).cloned().unwrap_or((0, 0));
+        self.counts.insert(key.clone(), (matches + matched as i64, total + 1));
+        Ok(())
+    }
+
+    pub(crate) fn merge(&mut self, other: RatioAggregate) -> AggregateResult<()> {
+        for (key, (other_matches, other_total)) in other.counts.into_iter() {
+            let (matches, total) = self.counts.get(&key).cloned().unwrap_or((0, 0));
+            self.counts.insert(key, (matches + other_matches, total + other_total));
+        }
+
         Ok(())
     }
 
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
-        if let Some(last) = self.lasts.get(key) {
-            Ok(last.clone())
+        if let Some(&(matches, total)) = self.counts.get(key) {
+            Ok(Value::Float(OrderedFloat::from(matches as f32 / total as f32)))
         } else {
             Err(AggregateError::KeyNotFound)
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub(crate) struct ApproxCountDistinctAggregate {
-    pub(crate) counts: HashMap<Option<Tuple>, HyperLogLog<Value>>,
-}
-
-impl PartialEq for ApproxCountDistinctAggregate {
-    fn eq(&self, _other: &Self) -> bool {
-        //Ignoring the detail since we only use Eq for unit test
-        true
-    }
+//The average of the pairs [value, weight] per group weighted by the weights, e.g. avg_weighted(latency, received_bytes),
+//null if the weights add up to zero. The pairs with a null or a value which is not a number are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AvgWeightedAggregate {
+    pub(crate) sums: HashMap<Option<Tuple>, (OrderedFloat<f64>, OrderedFloat<f64>)>,
 }
 
-impl Eq for ApproxCountDistinctAggregate {}
-
-impl ApproxCountDistinctAggregate {
+impl AvgWeightedAggregate {
     pub(crate) fn new() -> Self {
-        ApproxCountDistinctAggregate { counts: HashMap::new() }
+        AvgWeightedAggregate { sums: HashMap::new() }
     }
 
     pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
-        if let &Value::Null = value {
-            //Null value doesn't contribute to the total count
-            return Ok(());
-        };
-
-        if let Some(hll) = self.counts.get_mut(key) {
-            hll.add(value);
-            Ok(())
-        } else {
-            self.counts.insert(key.clone(), HyperLogLog::new(8));
+        let (weighted_sum, total_weight) = self.sums.entry(key.clone()).or_default();
+        match value {
+            Value::Array(pair) if pair.len() == 2 => {
+                let number = |v: &Value| match v {
+                    Value::Int(i) => Some(f64::from(*i)),
+                    Value::Float(f) => Some(f64::from(f.into_inner())),
+                    _ => None,
+                };
+                if let (Some(x), Some(w)) = (number(&pair[0]), number(&pair[1])) {
+                    *weighted_sum = OrderedFloat::from(weighted_sum.0 + x * w);
+                    *total_weight = OrderedFloat::from(total_weight.0 + w);
+                }
+                Ok(())
+            }
+            _ => Err(AggregateError::InvalidType),
+        }
+    }
 
-            Ok(())
+    pub(crate) fn merge(&mut self, other: AvgWeightedAggregate) -> AggregateResult<()> {
+        for (key, (other_sum, other_weight)) in other.sums.into_iter() {
+            let (weighted_sum, total_weight) = self.sums.entry(key).or_default();
+            *weighted_sum = OrderedFloat::from(weighted_sum.0 + other_sum.0);
+            *total_weight = OrderedFloat::from(total_weight.0 + other_weight.0);
         }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        match self.sums.get(key) {
+            Some((_, total_weight)) if total_weight.0 == 0.0 => Ok(Value::Null),
+            Some((weighted_sum, total_weight)) => Ok(Value::Float(OrderedFloat::from(
+                (weighted_sum.0 / total_weight.0) as f32,
+            ))),
+            None => Err(AggregateError::KeyNotFound),
+        }
+    }
+}
+
+//The value of one column at the record where another is the largest, e.g. max_by(request, backend_processing_time),
+//or the smallest for min_by. The records with a null order are skipped and the earlier record wins a tie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExtremeByAggregate {
+    pub(crate) extremes: HashMap<Option<Tuple>, Option<(Value, Value)>>,
+    pub(crate) largest: bool,
+}
+
+impl ExtremeByAggregate {
+    pub(crate) fn new(largest: bool) -> Self {
+        ExtremeByAggregate {
+            extremes: HashMap::new(),
+            largest,
+        }
+    }
+
+    fn compare(left: &Value, right: &Value) -> AggregateResult<std::cmp::Ordering> {
+        match (left, right) {
+            (Value::Int(i1), Value::Int(i2)) => Ok(i1.cmp(i2)),
+            (Value::Float(f1), Value::Float(f2)) => Ok(f1.cmp(f2)),
+            (Value::Float(f1), i2) if i2.as_integral().is_some() => {
+                Ok(f1.cmp(&OrderedFloat::from(i2.as_integral().unwrap() as f32)))
+            }
+            (i1, Value::Float(f2)) if i1.as_integral().is_some() => {
+                Ok(OrderedFloat::from(i1.as_integral().unwrap() as f32).cmp(f2))
+            }
+            (i1, i2) if i1.as_integral().is_some() && i2.as_integral().is_some() => {
+                Ok(i1.as_integral().cmp(&i2.as_integral()))
+            }
+            (Value::String(s1), Value::String(s2)) => Ok(s1.cmp(s2)),
+            (Value::DateTime(d1), Value::DateTime(d2)) => Ok(d1.cmp(d2)),
+            _ => Err(AggregateError::InvalidType),
+        }
+    }
+
+    fn add_candidate(&mut self, key: &Option<Tuple>, value: &Value, order: &Value) -> AggregateResult<()> {
+        let extreme = self.extremes.entry(key.clone()).or_insert(None);
+        if let Value::Null | Value::Missing = order {
+            return Ok(());
+        }
+
+        let replace = match extreme {
+            Some((_, current)) => {
+                let ordering = ExtremeByAggregate::compare(order, current)?;
+                if self.largest {
+                    ordering == std::cmp::Ordering::Greater
+                } else {
+                    ordering == std::cmp::Ordering::Less
+                }
+            }
+            None => true,
+        };
+        if replace {
+            *extreme = Some((value.clone(), order.clone()));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        match value {
+            Value::Array(pair) if pair.len() == 2 => self.add_candidate(key, &pair[0], &pair[1]),
+            _ => Err(AggregateError::InvalidType),
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: ExtremeByAggregate) -> AggregateResult<()> {
+        for (key, extreme) in other.extremes.into_iter() {
+            match extreme {
+                Some((value, order)) => self.add_candidate(&key, &value, &order)?,
+                None => {
+                    self.extremes.entry(key).or_insert(None);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        match self.extremes.get(key) {
+            Some(Some((value, _))) => Ok(value.clone()),
+            Some(None) => Ok(Value::Null),
+            None => Err(AggregateError::KeyNotFound),
+        }
+    }
+}
+
+//The co-moments of the pairs [x, y] per group, updated one pair at a time so that large values keep their precision.
+//The pairs with a null or a value which is not a number are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct CoMoments {
+    n: u64,
+    mean_x: OrderedFloat<f64>,
+    mean_y: OrderedFloat<f64>,
+    c_xy: OrderedFloat<f64>,
+    m2_x: OrderedFloat<f64>,
+    m2_y: OrderedFloat<f64>,
+}
+
+impl CoMoments {
+    fn add(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        let n = self.n as f64;
+        let dx = x - self.mean_x.0;
+        let dy = y - self.mean_y.0;
+        self.mean_x = OrderedFloat::from(self.mean_x.0 + dx / n);
+        self.mean_y = OrderedFloat::from(self.mean_y.0 + dy / n);
+        self.c_xy = OrderedFloat::from(self.c_xy.0 + dx * (y - self.mean_y.0));
+        self.m2_x = OrderedFloat::from(self.m2_x.0 + dx * (x - self.mean_x.0));
+        self.m2_y = OrderedFloat::from(self.m2_y.0 + dy * (y - self.mean_y.0));
+    }
+
+    fn merge(&mut self, other: &CoMoments) {
+        if other.n == 0 {
+            return;
+        }
+        if self.n == 0 {
+            *self = *other;
+            return;
+        }
+
+        let (n_a, n_b) = (self.n as f64, other.n as f64);
+        let n = n_a + n_b;
+        let dx = other.mean_x.0 - self.mean_x.0;
+        let dy = other.mean_y.0 - self.mean_y.0;
+        let w = n_a * n_b / n;
+        self.n += other.n;
+        self.mean_x = OrderedFloat::from(self.mean_x.0 + dx * n_b / n);
+        self.mean_y = OrderedFloat::from(self.mean_y.0 + dy * n_b / n);
+        self.c_xy = OrderedFloat::from(self.c_xy.0 + other.c_xy.0 + dx * dy * w);
+        self.m2_x = OrderedFloat::from(self.m2_x.0 + other.m2_x.0 + dx * dx * w);
+        self.m2_y = OrderedFloat::from(self.m2_y.0 + other.m2_y.0 + dy * dy * w);
+    }
+}
+
+//covar(x, y) is the sample covariance and corr(x, y) the Pearson correlation of the pairs, null for less than two pairs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CovarianceAggregate {
+    pub(crate) moments: HashMap<Option<Tuple>, CoMoments>,
+}
+
+impl CovarianceAggregate {
+    pub(crate) fn new() -> Self {
+        CovarianceAggregate {
+            moments: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        let moments = self.moments.entry(key.clone()).or_default();
+        match value {
+            Value::Array(pair) if pair.len() == 2 => {
+                let number = |v: &Value| match v {
+                    Value::Int(i) => Some(f64::from(*i)),
+                    Value::Float(f) => Some(f64::from(f.into_inner())),
+                    _ => None,
+                };
+                if let (Some(x), Some(y)) = (number(&pair[0]), number(&pair[1])) {
+                    moments.add(x, y);
+                }
+                Ok(())
+            }
+            _ => Err(AggregateError::InvalidType),
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: CovarianceAggregate) -> AggregateResult<()> {
+        for (key, other_moments) in other.moments.into_iter() {
+            self.moments.entry(key).or_default().merge(&other_moments);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        if let Some(moments) = self.moments.get(key) {
+            if moments.n < 2 {
+                return Ok(Value::Null);
+            }
+            let covariance = moments.c_xy.0 / (moments.n - 1) as f64;
+            Ok(Value::Float(OrderedFloat::from(covariance as f32)))
+        } else {
+            Err(AggregateError::KeyNotFound)
+        }
+    }
+
+    //Null as well when either side is constant
+    pub(crate) fn get_correlation(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        if let Some(moments) = self.moments.get(key) {
+            let denominator = (moments.m2_x.0 * moments.m2_y.0).sqrt();
+            if moments.n < 2 || denominator == 0.0 {
+                return Ok(Value::Null);
+            }
+            Ok(Value::Float(OrderedFloat::from((moments.c_xy.0 / denominator) as f32)))
+        } else {
+            Err(AggregateError::KeyNotFound)
+        }
+    }
+}
+
+//The number of records of each non-null value per group. entropy(col) is the Shannon entropy in bits of the values and
+//concentration(col, k) the share of the records with the top k values, both null if there is no value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FrequencyAggregate {
+    pub(crate) counts: HashMap<Option<Tuple>, HashMap<Value, u64>>,
+    pub(crate) k: usize,
+}
+
+impl FrequencyAggregate {
+    pub(crate) fn new(k: usize) -> Self {
+        FrequencyAggregate {
+            counts: HashMap::new(),
+            k,
+        }
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        let counts = self.counts.entry(key.clone()).or_default();
+        if let Value::Null | Value::Missing = value {
+            return Ok(());
+        }
+
+        *counts.entry(value.clone()).or_insert(0) += 1;
+        Ok(())
+    }
+
+    pub(crate) fn merge(&mut self, other: FrequencyAggregate) -> AggregateResult<()> {
+        for (key, other_counts) in other.counts.into_iter() {
+            let counts = self.counts.entry(key).or_default();
+            for (value, count) in other_counts.into_iter() {
+                *counts.entry(value).or_insert(0) += count;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_entropy(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        let counts = self.counts.get(key).ok_or(AggregateError::KeyNotFound)?;
+        let total: u64 = counts.values().sum();
+        if total == 0 {
+            return Ok(Value::Null);
+        }
+
+        let entropy: f64 = counts
+            .values()
+            .map(|&count| {
+                let p = count as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum();
+        Ok(Value::Float(OrderedFloat::from(entropy as f32)))
+    }
+
+    pub(crate) fn get_concentration(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        let counts = self.counts.get(key).ok_or(AggregateError::KeyNotFound)?;
+        let total: u64 = counts.values().sum();
+        if total == 0 {
+            return Ok(Value::Null);
+        }
+
+        let mut sorted: Vec<u64> = counts.values().copied().collect();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+        let top: u64 = sorted.iter().take(self.k).sum();
+        Ok(Value::Float(OrderedFloat::from(top as f32 / total as f32)))
+    }
+}
+
+//The counters of the Space-Saving algorithm, the count of a value is over-estimated by at most its error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SpaceSavingCounter {
+    count: u64,
+    error: u64,
+    //The order of the first record, to keep the order of the values of the same count stable
+    seq: u64,
+}
+
+//The heavy hitters of the non-null values per group with a fixed number of counters, so the memory does not grow with
+//the distinct values. The values more frequent than 1/counters of the records are always kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ApproxTopKAggregate {
+    pub(crate) summaries: HashMap<Option<Tuple>, HashMap<Value, SpaceSavingCounter>>,
+    pub(crate) k: usize,
+    capacity: usize,
+    seq: u64,
+}
+
+impl ApproxTopKAggregate {
+    pub(crate) fn new(k: usize) -> Self {
+        ApproxTopKAggregate {
+            summaries: HashMap::new(),
+            k,
+            capacity: (k * 10).max(100),
+            seq: 0,
+        }
+    }
+
+    fn min_counter(counters: &HashMap<Value, SpaceSavingCounter>) -> Option<(Value, SpaceSavingCounter)> {
+        counters
+            .iter()
+            .min_by_key(|(_, c)| (c.count, std::cmp::Reverse(c.seq)))
+            .map(|(v, c)| (v.clone(), *c))
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        let counters = self.summaries.entry(key.clone()).or_default();
+        if let Value::Null | Value::Missing = value {
+            return Ok(());
+        }
+
+        self.seq += 1;
+        if let Some(counter) = counters.get_mut(value) {
+            counter.count += 1;
+        } else if counters.len() < self.capacity {
+            let counter = SpaceSavingCounter {
+                count: 1,
+                error: 0,
+                seq: self.seq,
+            };
+            counters.insert(value.clone(), counter);
+        } else if let Some((evicted, min)) = ApproxTopKAggregate::min_counter(counters) {
+            //The new value takes over the least frequent counter
+            counters.remove(&evicted);
+            let counter = SpaceSavingCounter {
+                count: min.count + 1,
+                error: min.count,
+                seq: self.seq,
+            };
+            counters.insert(value.clone(), counter);
+        }
+
+        Ok(())
+    }
+
+    //A value missing from a full summary may still have been seen up to the smallest count of that summary
+    pub(crate) fn merge(&mut self, other: ApproxTopKAggregate) -> AggregateResult<()> {
+        for (key, other_counters) in other.summaries.into_iter() {
+            let counters = self.summaries.remove(&key).unwrap_or_default();
+            let floor = |c: &HashMap<Value, SpaceSavingCounter>| match ApproxTopKAggregate::min_counter(c) {
+                Some((_, min)) if c.len() >= self.capacity => min.count,
+                _ => 0,
+            };
+            let (floor, other_floor) = (floor(&counters), floor(&other_counters));
+
+            let mut merged: HashMap<Value, SpaceSavingCounter> = HashMap::new();
+            for (value, counter) in counters.iter() {
+                let (count, error) = match other_counters.get(value) {
+                    Some(other) => (counter.count + other.count, counter.error + other.error),
+                    None => (counter.count + other_floor, counter.error + other_floor),
+                };
+                let seq = counter.seq;
+                merged.insert(value.clone(), SpaceSavingCounter { count, error, seq });
+            }
+            for (value, other) in other_counters.into_iter() {
+                if !merged.contains_key(&value) {
+                    let counter = SpaceSavingCounter {
+                        count: other.count + floor,
+                        error: other.error + floor,
+                        seq: self.seq + other.seq,
+                    };
+                    merged.insert(value, counter);
+                }
+            }
+
+            let mut sorted: Vec<(Value, SpaceSavingCounter)> = merged.into_iter().collect();
+            sorted.sort_by_key(|(_, c)| (std::cmp::Reverse(c.count), c.seq));
+            sorted.truncate(self.capacity);
+            self.summaries.insert(key, sorted.into_iter().collect());
+        }
+        self.seq += other.seq;
+
+        Ok(())
+    }
+
+    //The k most frequent values as the objects of the value and its estimated count, the most frequent first
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        let counters = self.summaries.get(key).ok_or(AggregateError::KeyNotFound)?;
+        let mut sorted: Vec<(&Value, &SpaceSavingCounter)> = counters.iter().collect();
+        sorted.sort_by_key(|(_, c)| (std::cmp::Reverse(c.count), c.seq));
+
+        let top = sorted
+            .into_iter()
+            .take(self.k)
+            .map(|(value, counter)| {
+                let mut obj = linked_hash_map::LinkedHashMap::new();
+                obj.insert("value".to_string(), value.clone());
+                obj.insert(
+                    "count".to_string(),
+                    Value::Int(counter.count.min(i32::MAX as u64) as i32),
+                );
+                Value::Object(obj)
+            })
+            .collect();
+        Ok(Value::Array(top))
+    }
+}
+
+//A reservoir of at most k of the non-null values per group, a uniform sample of them. The random numbers are from a
+//fixed seed, so the same input gives the same examples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SampleAggregate {
+    pub(crate) reservoirs: HashMap<Option<Tuple>, (u64, Vec<Value>)>,
+    pub(crate) k: usize,
+    state: u64,
+}
+
+impl SampleAggregate {
+    pub(crate) fn new(k: usize) -> Self {
+        SampleAggregate {
+            reservoirs: HashMap::new(),
+            k,
+            state: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    //xorshift64*, a number below the bound
+    fn next_random(&mut self, bound: u64) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) % bound
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        if let Value::Null | Value::Missing = value {
+            self.reservoirs.entry(key.clone()).or_insert((0, Vec::new()));
+            return Ok(());
+        }
+
+        let (seen, mut values) = self.reservoirs.remove(key).unwrap_or((0, Vec::new()));
+        if values.len() < self.k {
+            values.push(value.clone());
+        } else {
+            let i = self.next_random(seen + 1) as usize;
+            if i < self.k {
+                values[i] = value.clone();
+            }
+        }
+        self.reservoirs.insert(key.clone(), (seen + 1, values));
+        Ok(())
+    }
+
+    //Each slot is taken from either side in proportion to the values seen there
+    pub(crate) fn merge(&mut self, other: SampleAggregate) -> AggregateResult<()> {
+        for (key, (other_seen, other_values)) in other.reservoirs.into_iter() {
+            let (seen, values) = self.reservoirs.remove(&key).unwrap_or((0, Vec::new()));
+            let (mut left, mut right) = (values.into_iter(), other_values.into_iter());
+            let mut merged = Vec::with_capacity(self.k);
+            while merged.len() < self.k {
+                let from_left = match (left.len(), right.len()) {
+                    (0, 0) => break,
+                    (0, _) => false,
+                    (_, 0) => true,
+                    _ => self.next_random(seen + other_seen) < seen,
+                };
+                merged.extend(if from_left { left.next() } else { right.next() });
+            }
+            self.reservoirs.insert(key, (seen + other_seen, merged));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        if let Some((_, values)) = self.reservoirs.get(key) {
+            Ok(Value::Array(values.clone()))
+        } else {
+            Err(AggregateError::KeyNotFound)
+        }
+    }
+
+    //One of the values, null if there is none
+    pub(crate) fn get_any(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        if let Some((_, values)) = self.reservoirs.get(key) {
+            Ok(values.first().cloned().unwrap_or(Value::Null))
+        } else {
+            Err(AggregateError::KeyNotFound)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FirstAggregate {
+    pub(crate) firsts: HashMap<Option<Tuple>, Value>,
+}
+
+impl FirstAggregate {
+    pub(crate) fn new() -> Self {
+        FirstAggregate { firsts: HashMap::new() }
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        if self.firsts.get(&key).is_some() {
+            //do nothing
+            Ok(())
+        } else {
+            self.firsts.insert(key.clone(), value.clone());
+            Ok(())
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: FirstAggregate) -> AggregateResult<()> {
+        for (key, value) in other.firsts.into_iter() {
+            self.firsts.entry(key).or_insert(value);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        if let Some(first) = self.firsts.get(key) {
+            Ok(first.clone())
+        } else {
+            Err(AggregateError::KeyNotFound)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LastAggregate {
+    pub(crate) lasts: HashMap<Option<Tuple>, Value>,
+}
+
+impl LastAggregate {
+    pub(crate) fn new() -> Self {
+        LastAggregate { lasts: HashMap::new() }
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        self.lasts.insert(key.clone(), value.clone());
+        Ok(())
+    }
+
+    pub(crate) fn merge(&mut self, other: LastAggregate) -> AggregateResult<()> {
+        self.lasts.extend(other.lasts);
+        Ok(())
+    }
+
+    pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
+        if let Some(last) = self.lasts.get(key) {
+            Ok(last.clone())
+        } else {
+            Err(AggregateError::KeyNotFound)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ApproxCountDistinctAggregate {
+    pub(crate) counts: HashMap<Option<Tuple>, HyperLogLog<Value>>,
+}
+
+impl PartialEq for ApproxCountDistinctAggregate {
+    fn eq(&self, _other: &Self) -> bool {
+        //Ignoring the detail since we only use Eq for unit test
+        true
+    }
+}
+
+impl Eq for ApproxCountDistinctAggregate {}
+
+impl ApproxCountDistinctAggregate {
+    pub(crate) fn new() -> Self {
+        ApproxCountDistinctAggregate { counts: HashMap::new() }
+    }
+
+    pub(crate) fn add_record(&mut self, key: &Option<Tuple>, value: &Value) -> AggregateResult<()> {
+        if let &Value::Null = value {
+            //Null value doesn't contribute to the total count
+            return Ok(());
+        };
+
+        if let Some(hll) = self.counts.get_mut(key) {
+            hll.add(value);
+            Ok(())
+        } else {
+            let mut hll = HyperLogLog::new(8);
+            hll.add(value);
+            self.counts.insert(key.clone(), hll);
+
+            Ok(())
+        }
+    }
+
+    //The union of the sketches, all of them are created with the same precision
+    pub(crate) fn merge(&mut self, other: ApproxCountDistinctAggregate) -> AggregateResult<()> {
+        for (key, other_hll) in other.counts.into_iter() {
+            if let Some(hll) = self.counts.get_mut(&key) {
+                hll.merge(&other_hll);
+            } else {
+                self.counts.insert(key, other_hll);
+            }
+        }
+
+        Ok(())
     }
 
     pub(crate) fn get_aggregated(&self, key: &Option<Tuple>) -> AggregateResult<Value> {
@@ -1431,6 +3067,21 @@ mod tests {
         assert_eq!(Ok(Value::Float(OrderedFloat::from(5.5))), aggregate);
     }
 
+    #[test]
+    fn test_avg_aggregate_with_nulls() {
+        let mut iter = Aggregate::Avg(AvgAggregate::new(), Named::Star);
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let nulls = Some(vec![Value::String("nulls".to_string())]);
+        for value in [Value::Int(2), Value::Null, Value::Int(4)].iter() {
+            iter.add_record(&tuple, value).unwrap();
+        }
+        iter.add_record(&nulls, &Value::Null).unwrap();
+        iter.add_record(&tuple, &Value::Long(9)).unwrap();
+
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(5.0))), iter.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::Null), iter.get_aggregated(&nulls));
+    }
+
     #[test]
     fn test_count_aggregate() {
         let mut iter = Aggregate::Count(CountAggregate::new(), Named::Star);
@@ -1457,6 +3108,270 @@ mod tests {
         assert_eq!(Ok(Value::Int(0)), aggregate);
     }
 
+    #[test]
+    fn test_rate_aggregate() {
+        let mut iter = Aggregate::Rate(RateAggregate::new(), Named::Star);
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        for timestamp in ["2015-11-07T18:45:30Z", "2015-11-07T18:45:32Z", "2015-11-07T18:45:34Z"].iter() {
+            let value = Value::DateTime(chrono::DateTime::parse_from_rfc3339(timestamp).unwrap());
+            let _ = iter.add_record(&tuple, &value);
+        }
+
+        let aggregate = iter.get_aggregated(&tuple);
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(0.75))), aggregate);
+    }
+
+    #[test]
+    fn test_sample_aggregate() {
+        let mut iter = Aggregate::Sample(SampleAggregate::new(3), Named::Star);
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let nulls = Some(vec![Value::String("nulls".to_string())]);
+        for i in 0..100 {
+            let _ = iter.add_record(&tuple, &Value::Int(i));
+            let _ = iter.add_record(&tuple, &Value::Null);
+            let _ = iter.add_record(&nulls, &Value::Null);
+        }
+
+        let values = match iter.get_aggregated(&tuple) {
+            Ok(Value::Array(values)) => values,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(3, values.len());
+        assert!(values.iter().all(|v| matches!(v, Value::Int(i) if *i < 100)));
+        assert_eq!(Ok(Value::Array(Vec::new())), iter.get_aggregated(&nulls));
+
+        let mut any = Aggregate::AnyValue(SampleAggregate::new(1), Named::Star);
+        let _ = any.add_record(&nulls, &Value::Null);
+        assert_eq!(Ok(Value::Null), any.get_aggregated(&nulls));
+        let _ = any.add_record(&tuple, &Value::Int(7));
+        assert_eq!(Ok(Value::Int(7)), any.get_aggregated(&tuple));
+
+        let mut left = SampleAggregate::new(2);
+        let mut right = SampleAggregate::new(2);
+        let _ = left.add_record(&tuple, &Value::Int(1));
+        let _ = right.add_record(&tuple, &Value::Int(2));
+        let _ = right.add_record(&tuple, &Value::Int(3));
+        left.merge(right).unwrap();
+        assert_eq!(Some(&3), left.reservoirs.get(&tuple).map(|(seen, _)| seen));
+        assert_eq!(2, left.reservoirs[&tuple].1.len());
+    }
+
+    #[test]
+    fn test_avg_weighted_aggregate() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let nulls = Some(vec![Value::String("nulls".to_string())]);
+        let pair = |x: Value, w: Value| Value::Array(vec![x, w]);
+        let mut iter = Aggregate::AvgWeighted(AvgWeightedAggregate::new(), Named::Star);
+        let _ = iter.add_record(&tuple, &pair(Value::Float(OrderedFloat::from(1.0)), Value::Int(3)));
+        let _ = iter.add_record(&tuple, &pair(Value::Int(5), Value::Int(1)));
+        let _ = iter.add_record(&tuple, &pair(Value::Int(100), Value::Null));
+        let _ = iter.add_record(&nulls, &pair(Value::Null, Value::Int(1)));
+
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(2.0))), iter.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::Null), iter.get_aggregated(&nulls));
+
+        let mut other = AvgWeightedAggregate::new();
+        let _ = other.add_record(&tuple, &pair(Value::Int(6), Value::Int(4)));
+        if let Aggregate::AvgWeighted(ref mut agg, _) = iter {
+            agg.merge(other).unwrap();
+        }
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(4.0))), iter.get_aggregated(&tuple));
+    }
+
+    #[test]
+    fn test_extreme_by_aggregate() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let nulls = Some(vec![Value::String("nulls".to_string())]);
+        let pair = |url: &str, time: Value| Value::Array(vec![Value::String(url.to_string()), time]);
+        let mut max_by = Aggregate::MaxBy(ExtremeByAggregate::new(true), Named::Star);
+        let mut min_by = Aggregate::MinBy(ExtremeByAggregate::new(false), Named::Star);
+        let records = [
+            pair("/a", Value::Float(OrderedFloat::from(0.5))),
+            pair("/b", Value::Int(2)),
+            pair("/c", Value::Null),
+            pair("/d", Value::Int(2)),
+            pair("/e", Value::Float(OrderedFloat::from(0.1))),
+        ];
+        for record in records.iter() {
+            let _ = max_by.add_record(&tuple, record);
+            let _ = min_by.add_record(&tuple, record);
+        }
+        let _ = max_by.add_record(&nulls, &pair("/f", Value::Null));
+
+        assert_eq!(Ok(Value::String("/b".to_string())), max_by.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::String("/e".to_string())), min_by.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::Null), max_by.get_aggregated(&nulls));
+
+        let mut other = ExtremeByAggregate::new(true);
+        let _ = other.add_record(&tuple, &pair("/g", Value::Int(3)));
+        if let Aggregate::MaxBy(ref mut agg, _) = max_by {
+            agg.merge(other).unwrap();
+        }
+        assert_eq!(Ok(Value::String("/g".to_string())), max_by.get_aggregated(&tuple));
+    }
+
+    #[test]
+    fn test_covariance_aggregate() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let pair = |x: i32, y: Value| Value::Array(vec![Value::Int(x), y]);
+        let mut corr = Aggregate::Corr(CovarianceAggregate::new(), Named::Star);
+        let mut covar = Aggregate::Covar(CovarianceAggregate::new(), Named::Star);
+        for i in 1..=4 {
+            let _ = corr.add_record(&tuple, &pair(i, Value::Int(2 * i)));
+            let _ = covar.add_record(&tuple, &pair(i, Value::Int(2 * i)));
+        }
+        let _ = corr.add_record(&tuple, &pair(5, Value::Null));
+
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(1.0))), corr.get_aggregated(&tuple));
+        assert_eq!(
+            Ok(Value::Float(OrderedFloat::from(10.0 / 3.0))),
+            covar.get_aggregated(&tuple)
+        );
+
+        let mut left = CovarianceAggregate::new();
+        let mut right = CovarianceAggregate::new();
+        for i in 1..=2 {
+            let _ = left.add_record(&tuple, &pair(i, Value::Int(-i)));
+        }
+        for i in 3..=4 {
+            let _ = right.add_record(&tuple, &pair(i, Value::Int(-i)));
+        }
+        left.merge(right).unwrap();
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(-1.0))), left.get_correlation(&tuple));
+
+        let constant = Some(vec![Value::String("constant".to_string())]);
+        let _ = left.add_record(&constant, &pair(1, Value::Int(1)));
+        let _ = left.add_record(&constant, &pair(2, Value::Int(1)));
+        assert_eq!(Ok(Value::Null), left.get_correlation(&constant));
+        assert_eq!(
+            Ok(Value::Float(OrderedFloat::from(0.0))),
+            left.get_aggregated(&constant)
+        );
+    }
+
+    #[test]
+    fn test_frequency_aggregate() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let nulls = Some(vec![Value::String("nulls".to_string())]);
+        let mut entropy = Aggregate::Entropy(FrequencyAggregate::new(0), Named::Star);
+        let mut concentration = Aggregate::Concentration(FrequencyAggregate::new(1), Named::Star);
+        for client in ["a", "a", "b", "c"].iter() {
+            let value = Value::String(client.to_string());
+            let _ = entropy.add_record(&tuple, &value);
+            let _ = concentration.add_record(&tuple, &value);
+        }
+        let _ = entropy.add_record(&tuple, &Value::Null);
+        let _ = entropy.add_record(&nulls, &Value::Null);
+
+        assert_eq!(
+            Ok(Value::Float(OrderedFloat::from(1.5))),
+            entropy.get_aggregated(&tuple)
+        );
+        assert_eq!(
+            Ok(Value::Float(OrderedFloat::from(0.5))),
+            concentration.get_aggregated(&tuple)
+        );
+        assert_eq!(Ok(Value::Null), entropy.get_aggregated(&nulls));
+
+        let mut left = FrequencyAggregate::new(2);
+        let mut right = FrequencyAggregate::new(2);
+        let _ = left.add_record(&tuple, &Value::Int(1));
+        let _ = right.add_record(&tuple, &Value::Int(1));
+        let _ = right.add_record(&tuple, &Value::Int(2));
+        let _ = right.add_record(&tuple, &Value::Int(3));
+        left.merge(right).unwrap();
+        assert_eq!(
+            Ok(Value::Float(OrderedFloat::from(0.75))),
+            left.get_concentration(&tuple)
+        );
+    }
+
+    #[test]
+    fn test_approx_top_k_aggregate() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let top = |values: &[(&str, i32)]| {
+            let objects = values
+                .iter()
+                .map(|(url, count)| {
+                    let mut obj = linked_hash_map::LinkedHashMap::new();
+                    obj.insert("value".to_string(), Value::String(url.to_string()));
+                    obj.insert("count".to_string(), Value::Int(*count));
+                    Value::Object(obj)
+                })
+                .collect();
+            Ok(Value::Array(objects))
+        };
+
+        let mut iter = Aggregate::ApproxTopK(ApproxTopKAggregate::new(2), Named::Star);
+        //The heavy hitters survive the evictions of the long tail of the values seen once
+        for i in 0..1000 {
+            let _ = iter.add_record(&tuple, &Value::String("/a".to_string()));
+            if i % 2 == 0 {
+                let _ = iter.add_record(&tuple, &Value::String("/b".to_string()));
+            }
+            let _ = iter.add_record(&tuple, &Value::String(format!("/tail/{}", i)));
+            let _ = iter.add_record(&tuple, &Value::Null);
+        }
+        assert_eq!(top(&[("/a", 1000), ("/b", 500)]), iter.get_aggregated(&tuple));
+
+        let mut left = ApproxTopKAggregate::new(2);
+        let mut right = ApproxTopKAggregate::new(2);
+        for url in ["/a", "/b", "/a"].iter() {
+            let _ = left.add_record(&tuple, &Value::String(url.to_string()));
+        }
+        for url in ["/c", "/b", "/b"].iter() {
+            let _ = right.add_record(&tuple, &Value::String(url.to_string()));
+        }
+        left.merge(right).unwrap();
+        assert_eq!(top(&[("/b", 3), ("/a", 2)]), left.get_aggregated(&tuple));
+    }
+
+    #[test]
+    fn test_ratio_aggregate() {
+        let mut iter = Aggregate::Ratio(RatioAggregate::new(), Named::Star);
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        for i in 0..4 {
+            let _ = iter.add_record(&tuple, &Value::Boolean(i == 0));
+        }
+
+        let aggregate = iter.get_aggregated(&tuple);
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(0.25))), aggregate);
+    }
+
+    #[test]
+    fn test_min_max_aggregate_on_strings_and_datetimes() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let urls = ["/b", "/a", "/c"];
+        let mut min_iter = Aggregate::Min(MinAggregate::new(), Named::Star);
+        let mut max_iter = Aggregate::Max(MaxAggregate::new(), Named::Star);
+        for url in urls.iter() {
+            let value = Value::String(url.to_string());
+            let _ = min_iter.add_record(&tuple, &value);
+            let _ = max_iter.add_record(&tuple, &value);
+        }
+
+        assert_eq!(Ok(Value::String("/a".to_string())), min_iter.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::String("/c".to_string())), max_iter.get_aggregated(&tuple));
+
+        let timestamps = [
+            "2015-11-07T18:45:37.691548Z",
+            "2015-11-07T18:45:33.007671Z",
+            "2015-11-07T18:45:38.000000Z",
+        ];
+        let mut min_iter = Aggregate::Min(MinAggregate::new(), Named::Star);
+        let mut max_iter = Aggregate::Max(MaxAggregate::new(), Named::Star);
+        for timestamp in timestamps.iter() {
+            let value = Value::DateTime(chrono::DateTime::parse_from_rfc3339(timestamp).unwrap());
+            let _ = min_iter.add_record(&tuple, &value);
+            let _ = max_iter.add_record(&tuple, &value);
+        }
+
+        let first_seen = Value::DateTime(chrono::DateTime::parse_from_rfc3339(timestamps[1]).unwrap());
+        let last_seen = Value::DateTime(chrono::DateTime::parse_from_rfc3339(timestamps[2]).unwrap());
+        assert_eq!(Ok(first_seen), min_iter.get_aggregated(&tuple));
+        assert_eq!(Ok(last_seen), max_iter.get_aggregated(&tuple));
+    }
+
     #[test]
     fn test_last_aggregate() {
         let mut iter = Aggregate::Last(LastAggregate::new(), Named::Star);
@@ -1484,6 +3399,146 @@ mod tests {
         assert_eq!(Ok(Value::Float(OrderedFloat::from(55.0))), aggregate);
     }
 
+    #[test]
+    fn test_sum_aggregate_with_nulls() {
+        let mut iter = Aggregate::Sum(SumAggregate::new(), Named::Star);
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let nulls = Some(vec![Value::String("nulls".to_string())]);
+        for value in [Value::Int(2), Value::Null, Value::Missing, Value::Long(4)].iter() {
+            iter.add_record(&tuple, value).unwrap();
+        }
+        iter.add_record(&nulls, &Value::Null).unwrap();
+
+        assert_eq!(Ok(Value::Int(6)), iter.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::Null), iter.get_aggregated(&nulls));
+
+        let mut other = Aggregate::Sum(SumAggregate::new(), Named::Star);
+        other.add_record(&nulls, &Value::Int(1)).unwrap();
+        other.add_record(&tuple, &Value::Null).unwrap();
+        iter.merge(other).unwrap();
+        assert_eq!(Ok(Value::Int(6)), iter.get_aggregated(&tuple));
+        assert_eq!(Ok(Value::Int(1)), iter.get_aggregated(&nulls));
+    }
+
+    #[test]
+    fn test_sum_aggregate_without_overflow() {
+        let mut iter = Aggregate::Sum(SumAggregate::new(), Named::Star);
+        let tuple = Some(vec![Value::String("key".to_string())]);
+
+        for i in &[i32::MAX, 2, 1] {
+            let _ = iter.add_record(&tuple, &Value::Int(*i));
+        }
+
+        //2^31 + 2 is exact, unlike in f32
+        let aggregate = iter.get_aggregated(&tuple);
+        assert_eq!(Ok(Value::Long(2_147_483_650)), aggregate);
+        let _ = iter.add_record(&tuple, &Value::Int(-3));
+        assert_eq!(Ok(Value::Int(i32::MAX)), iter.get_aggregated(&tuple));
+
+        let mut variables = Variables::default();
+        variables.insert("sum".to_string(), Value::Long(2_147_483_650));
+        variables.insert("max".to_string(), Value::Int(i32::MAX));
+        let variable = |name: &str| Expression::Variable(PathExpr::new(vec![PathSegment::AttrName(name.to_string())]));
+        let (sum, max) = (variable("sum"), variable("max"));
+        assert_eq!(Ok(true), Relation::MoreThan.apply(&variables, &sum, &max));
+        assert_eq!(
+            Ok(Value::Int(3)),
+            evaluate("Minus", &[Value::Long(2_147_483_650), Value::Int(2_147_483_647)])
+        );
+
+        let mut iter = Aggregate::Sum(SumAggregate::new(), Named::Star);
+        for i in 1..=10 {
+            let _ = iter.add_record(&tuple, &Value::Int(i));
+        }
+
+        let aggregate = iter.get_aggregated(&tuple);
+        assert_eq!(Ok(Value::Int(55)), aggregate);
+    }
+
+    #[test]
+    fn test_weighted_percentile_aggregates() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+
+        let mut disc = PercentileDiscAggregate::new(OrderedFloat::from(0.5), Ordering::Asc);
+        let mut approx = ApproxPercentileAggregate::new(OrderedFloat::from(0.5), Ordering::Asc);
+        for i in 1..=10 {
+            let value = Value::Float(OrderedFloat::from(i as f32));
+            //The slowest request carries most of the bytes
+            let weight = if i == 10 { Value::Int(100) } else { Value::Int(1) };
+            disc.add_weighted_record(&tuple, &value, &weight).unwrap();
+            approx.add_weighted_record(&tuple, &value, &weight).unwrap();
+        }
+
+        assert_eq!(Ok(Value::Float(OrderedFloat::from(10.0))), disc.get_aggregated(&tuple));
+        match approx.get_aggregated(&tuple) {
+            Ok(Value::Float(f)) => assert!(f.into_inner() > 9.0),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let mut disc = PercentileDiscAggregate::new(OrderedFloat::from(0.5), Ordering::Asc);
+        for i in 1..=10 {
+            disc.add_record(&tuple, &Value::Int(i)).unwrap();
+        }
+        assert_eq!(Ok(Value::Int(6)), disc.get_aggregated(&tuple));
+
+        assert_eq!(
+            Err(AggregateError::InvalidType),
+            disc.add_weighted_record(&tuple, &Value::Int(1), &Value::String("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_merge_partitioned_aggregates() {
+        let tuple = Some(vec![Value::String("key".to_string())]);
+        let aggregates = vec![
+            Aggregate::Avg(AvgAggregate::new(), Named::Star),
+            Aggregate::Count(CountAggregate::new(), Named::Star),
+            Aggregate::First(FirstAggregate::new(), Named::Star),
+            Aggregate::Last(LastAggregate::new(), Named::Star),
+            Aggregate::Max(MaxAggregate::new(), Named::Star),
+            Aggregate::Min(MinAggregate::new(), Named::Star),
+            Aggregate::Sum(SumAggregate::new(), Named::Star),
+            Aggregate::ApproxCountDistinct(ApproxCountDistinctAggregate::new(), Named::Star),
+            Aggregate::PercentileDisc(
+                PercentileDiscAggregate::new(OrderedFloat::from(0.5), Ordering::Asc),
+                "a".to_string(),
+                None,
+            ),
+            Aggregate::ApproxPercentile(
+                ApproxPercentileAggregate::new(OrderedFloat::from(0.5), Ordering::Asc),
+                "a".to_string(),
+                None,
+            ),
+        ];
+
+        for aggregate in aggregates.into_iter() {
+            let mut whole = aggregate.clone();
+            let mut first_half = aggregate.clone();
+            let mut second_half = aggregate.clone();
+            for i in 1..=100 {
+                let value = Value::Int(i);
+                whole.add_record(&tuple, &value).unwrap();
+                if i <= 30 {
+                    first_half.add_record(&tuple, &value).unwrap();
+                } else {
+                    second_half.add_record(&tuple, &value).unwrap();
+                }
+            }
+
+            first_half.merge(second_half).unwrap();
+            let expected = whole.get_aggregated(&tuple).unwrap();
+            let merged = first_half.get_aggregated(&tuple).unwrap();
+            match (&expected, &merged) {
+                (Value::Float(f1), Value::Float(f2)) => assert!((f1.into_inner() - f2.into_inner()).abs() < 1.0),
+                _ => assert_eq!(expected, merged),
+            }
+        }
+
+        let mut count = Aggregate::Count(CountAggregate::new(), Named::Star);
+        let sum = Aggregate::Sum(SumAggregate::new(), Named::Star);
+        assert_eq!(Err(AggregateError::Incompatible), count.merge(sum));
+    }
+
     #[test]
     fn test_max_aggregate() {
         let mut iter = Aggregate::Max(MaxAggregate::new(), Named::Star);
@@ -1519,6 +3574,159 @@ mod tests {
         assert_eq!(port, Value::Int(2817));
     }
 
+    #[test]
+    fn test_evaluate_action_functions() {
+        let v = Value::String("waf,redirect".to_string());
+        let redirected = evaluate_action_functions("was_redirected", std::slice::from_ref(&v)).unwrap();
+        assert_eq!(redirected, Value::Boolean(true));
+        let fixed_response = evaluate_action_functions("was_fixed_response", std::slice::from_ref(&v)).unwrap();
+        assert_eq!(fixed_response, Value::Boolean(false));
+        let waf = evaluate_action_functions("has_action", &[v, Value::String("waf".to_string())]).unwrap();
+        assert_eq!(waf, Value::Boolean(true));
+        let none = evaluate_action_functions("was_redirected", &[Value::Null]).unwrap();
+        assert_eq!(none, Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_evaluate_domain_functions() {
+        let v = Value::String("Shop.Customer.co.uk:443".to_string());
+        let reversed = evaluate_domain_functions("reverse_domain", std::slice::from_ref(&v)).unwrap();
+        assert_eq!(reversed, Value::String("uk.co.customer.shop".to_string()));
+        let registrable = evaluate_domain_functions("registrable_domain", &[v]).unwrap();
+        assert_eq!(registrable, Value::String("customer.co.uk".to_string()));
+
+        let v = Value::String("api.eu.example.com.".to_string());
+        let registrable = evaluate_domain_functions("registrable_domain", &[v]).unwrap();
+        assert_eq!(registrable, Value::String("example.com".to_string()));
+
+        let v = Value::HttpRequest(common::types::parse_http_request("GET http://10.0.0.1:80/ HTTP/1.1").unwrap());
+        let registrable = evaluate_domain_functions("registrable_domain", &[v]).unwrap();
+        assert_eq!(registrable, Value::String("10.0.0.1".to_string()));
+
+        let registrable = evaluate_domain_functions("registrable_domain", &[Value::Null]).unwrap();
+        assert_eq!(registrable, Value::Null);
+    }
+
+    #[test]
+    fn test_evaluate_referrer_functions() {
+        let v = Value::String("\"https://www.Google.co.uk/search?q=logq\"".to_string());
+        let host = evaluate_referrer_functions("referrer_host", std::slice::from_ref(&v)).unwrap();
+        assert_eq!(host, Value::String("www.google.co.uk".to_string()));
+        let engine = evaluate_referrer_functions("search_engine", &[v]).unwrap();
+        assert_eq!(engine, Value::String("google".to_string()));
+
+        let v = Value::String("https://notgoogle.com/".to_string());
+        let engine = evaluate_referrer_functions("search_engine", &[v]).unwrap();
+        assert_eq!(engine, Value::Null);
+
+        let host = evaluate_referrer_functions("referrer_host", &[Value::String("-".to_string())]).unwrap();
+        assert_eq!(host, Value::Null);
+    }
+
+    #[test]
+    fn test_evaluate_list_functions() {
+        let v = Value::Array(vec![
+            Value::String("10.0.0.1:80".to_string()),
+            Value::String("10.0.0.2:80".to_string()),
+        ]);
+        assert_eq!(
+            Value::Int(2),
+            evaluate_list_functions("list_len", std::slice::from_ref(&v)).unwrap()
+        );
+        assert_eq!(
+            Value::String("10.0.0.2:80".to_string()),
+            evaluate_list_functions("list_last", std::slice::from_ref(&v)).unwrap()
+        );
+        assert_eq!(
+            Value::Boolean(true),
+            evaluate_list_functions("had_retry", &[v]).unwrap()
+        );
+
+        let v = Value::Array(vec![Value::Int(502)]);
+        assert_eq!(
+            Value::Int(502),
+            evaluate_list_functions("list_first", std::slice::from_ref(&v)).unwrap()
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            evaluate_list_functions("had_retry", &[v]).unwrap()
+        );
+
+        let v = Value::Null;
+        assert_eq!(
+            Value::Int(0),
+            evaluate_list_functions("list_len", std::slice::from_ref(&v)).unwrap()
+        );
+        assert_eq!(Value::Null, evaluate_list_functions("list_last", &[v]).unwrap());
+        assert!(evaluate_list_functions("list_len", &[Value::String("10.0.0.1:80".to_string())]).is_err());
+
+        let v = evaluate_list_functions("list", &[Value::Int(200), Value::Int(304)]).unwrap();
+        assert_eq!(Value::Array(vec![Value::Int(200), Value::Int(304)]), v);
+        assert_eq!(
+            Value::Boolean(true),
+            evaluate_list_functions("contains", &[v.clone(), Value::Int(304)]).unwrap()
+        );
+        assert_eq!(
+            Value::Boolean(false),
+            evaluate_list_functions("contains", &[v.clone(), Value::Int(502)]).unwrap()
+        );
+        assert_eq!(
+            Value::Int(304),
+            evaluate_list_functions("element_at", &[v.clone(), Value::Int(-1)]).unwrap()
+        );
+        assert_eq!(
+            Value::Null,
+            evaluate_list_functions("element_at", &[v, Value::Int(2)]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_prefix() {
+        assert_eq!("photos/2019/", key_prefix("photos/2019/08/puppy.jpg", 2));
+        assert_eq!("photos/", key_prefix("photos/puppy.jpg", 2));
+        assert_eq!("", key_prefix("puppy.jpg", 1));
+        assert_eq!("logs/", key_prefix("logs/", 3));
+        assert_eq!(
+            Value::String("photos/".to_string()),
+            evaluate(
+                "key_prefix",
+                &[Value::String("photos/2019/08/puppy.jpg".to_string()), Value::Int(1)]
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Value::Null,
+            evaluate("key_prefix", &[Value::String("-".to_string()), Value::Int(1)]).unwrap()
+        );
+        let request = common::types::parse_http_request("GET /photos/2019/08/puppy.jpg?v=2 HTTP/1.1").unwrap();
+        assert_eq!(
+            Value::String("photos/2019/".to_string()),
+            evaluate("key_prefix", &[Value::HttpRequest(request), Value::Int(2)]).unwrap()
+        );
+        assert!(evaluate("key_prefix", &[Value::String("a/b".to_string()), Value::Int(-1)]).is_err());
+    }
+
+    #[test]
+    fn test_arn_resource() {
+        assert_eq!(
+            Some("my-targets"),
+            arn_resource("arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067")
+        );
+        assert_eq!(
+            Some("my-lb"),
+            arn_resource("arn:aws:elasticloadbalancing:us-east-2:123456789012:loadbalancer/app/my-lb/50dc6c495c0c9188")
+        );
+        assert_eq!(
+            Some("12345678-1234-1234-1234-123456789012"),
+            arn_resource("\"arn:aws:acm:us-east-2:123456789012:certificate/12345678-1234-1234-1234-123456789012\"")
+        );
+        assert_eq!(
+            Some("my-function"),
+            arn_resource("arn:aws:lambda:us-east-2:123456789012:function:my-function")
+        );
+        assert_eq!(None, arn_resource("-"));
+    }
+
     #[test]
     fn test_evaluate_url_functions() {
         let v = Value::HttpRequest(
@@ -1537,6 +3745,23 @@ mod tests {
         assert_eq!(fragment, Value::Null);
         let query = evaluate_url_functions("url_query", &vec![v.clone()]).unwrap();
         assert_eq!(query, Value::String("mode=json&after=&iteration=1".to_string()));
+        let params = evaluate_url_functions("url_query_params", std::slice::from_ref(&v)).unwrap();
+        assert_eq!(
+            Value::String("json".to_string()),
+            evaluate_map_functions("map_get", &[params.clone(), Value::String("mode".to_string())]).unwrap()
+        );
+        assert_eq!(
+            Value::Missing,
+            evaluate_map_functions("map_get", &[params.clone(), Value::String("page".to_string())]).unwrap()
+        );
+        assert_eq!(
+            Value::Array(vec![
+                Value::String("mode".to_string()),
+                Value::String("after".to_string()),
+                Value::String("iteration".to_string()),
+            ]),
+            evaluate_map_functions("map_keys", &[params]).unwrap()
+        );
         let path_segments = evaluate_url_functions("url_path_segments", &vec![v.clone(), Value::Int(1)]).unwrap();
         assert_eq!(path_segments, Value::String("123".to_string()));
         let mapped_path = evaluate_url_functions(
@@ -1545,6 +3770,88 @@ mod tests {
         )
         .unwrap();
         assert_eq!(mapped_path, Value::String("/users/_".to_string()));
+
+        //The request of the web servers has no host
+        let request = common::types::parse_http_request("GET /users/123?mode=json HTTP/1.1").unwrap();
+        assert_eq!("GET /users/123?mode=json HTTP/1.1", request.to_string());
+        let v = Value::HttpRequest(request);
+        assert_eq!(
+            Value::Null,
+            evaluate_url_functions("url_host", std::slice::from_ref(&v)).unwrap()
+        );
+        assert_eq!(
+            Value::String("/users/123".to_string()),
+            evaluate_url_functions("url_path", std::slice::from_ref(&v)).unwrap()
+        );
+        assert_eq!(
+            Value::String("mode=json".to_string()),
+            evaluate_url_functions("url_query", &[v]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_compare_records() {
+        let names = vec!["a".to_string(), "b".to_string()];
+        let record = |a: i32, b: &str| Record::new(&names, vec![Value::Int(a), Value::String(b.to_string())]);
+        let path = |name: &str| PathExpr::new(vec![PathSegment::AttrName(name.to_string())]);
+        let column_names = vec![path("a"), path("b")];
+        let orderings = vec![Ordering::Asc, Ordering::Desc];
+
+        let compare = |x: &Record, y: &Record| compare_records(x, y, &column_names, &orderings);
+        assert_eq!(std::cmp::Ordering::Less, compare(&record(1, "x"), &record(2, "a")));
+        assert_eq!(std::cmp::Ordering::Less, compare(&record(1, "y"), &record(1, "x")));
+        assert_eq!(std::cmp::Ordering::Equal, compare(&record(1, "x"), &record(1, "x")));
+    }
+
+    #[test]
+    fn test_relation_on_datetimes() {
+        let mut variables = Variables::default();
+        variables.insert(
+            "timestamp".to_string(),
+            Value::DateTime(chrono::DateTime::parse_from_rfc3339("2019-06-07T18:45:33Z").unwrap()),
+        );
+        variables.insert("a".to_string(), Value::String("2019-06-07t18:00:00z".to_string()));
+        variables.insert("b".to_string(), Value::String("abc".to_string()));
+
+        let var = |name: &str| Expression::Variable(PathExpr::new(vec![PathSegment::AttrName(name.to_string())]));
+        assert_eq!(
+            Ok(true),
+            Relation::MoreThan.apply(&variables, &var("timestamp"), &var("a"))
+        );
+        assert_eq!(
+            Ok(false),
+            Relation::LessEqual.apply(&variables, &var("timestamp"), &var("a"))
+        );
+        assert_eq!(
+            Ok(true),
+            Relation::LessThan.apply(&variables, &var("a"), &var("timestamp"))
+        );
+        assert_eq!(
+            Err(ExpressionError::TypeMismatch),
+            Relation::MoreThan.apply(&variables, &var("timestamp"), &var("b"))
+        );
+        //The strings are compared as the timestamps or the integers, not lexicographically
+        variables.insert("c".to_string(), Value::String("2019-06-07T18:30:00Z".to_string()));
+        variables.insert("status".to_string(), Value::String("502".to_string()));
+        variables.insert("short".to_string(), Value::String("99".to_string()));
+        variables.insert("floor".to_string(), Value::String("400".to_string()));
+        assert_eq!(Ok(true), Relation::MoreThan.apply(&variables, &var("c"), &var("a")));
+        assert_eq!(
+            Ok(false),
+            Relation::GreaterEqual.apply(&variables, &var("a"), &var("c"))
+        );
+        assert_eq!(
+            Ok(true),
+            Relation::GreaterEqual.apply(&variables, &var("status"), &var("floor"))
+        );
+        assert_eq!(
+            Ok(false),
+            Relation::GreaterEqual.apply(&variables, &var("short"), &var("floor"))
+        );
+        assert_eq!(
+            Err(ExpressionError::TypeMismatch),
+            Relation::MoreThan.apply(&variables, &var("b"), &var("a"))
+        );
     }
 
     #[test]
@@ -1561,6 +3868,16 @@ mod tests {
         let v = evaluate("Divide", &vec![Value::Int(2), Value::Int(2)]).unwrap();
         assert_eq!(v, Value::Int(1));
 
+        let v = evaluate("Plus", &[Value::Float(OrderedFloat(0.5)), Value::Int(1)]).unwrap();
+        assert_eq!(v, Value::Float(OrderedFloat(1.5)));
+
+        let v = evaluate(
+            "Divide",
+            &[Value::Float(OrderedFloat(1.0)), Value::Float(OrderedFloat(4.0))],
+        )
+        .unwrap();
+        assert_eq!(v, Value::Float(OrderedFloat(0.25)));
+
         let dt = Value::DateTime(chrono::DateTime::parse_from_rfc3339("2015-11-07T18:45:37.691548Z").unwrap());
         let expected_dt = Value::DateTime(chrono::DateTime::parse_from_rfc3339("2015-11-07T18:45:35.000000Z").unwrap());
         let bucket_dt = evaluate("time_bucket", &vec![Value::String("5 seconds".to_string()), dt.clone()]).unwrap();
@@ -0,0 +1,18 @@
+extern crate chrono;
+extern crate nom;
+extern crate prettytable;
+#[macro_use]
+extern crate lazy_static;
+extern crate pdatastructs;
+
+mod app;
+#[doc(hidden)]
+pub mod cli;
+mod common;
+mod execution;
+mod logical;
+mod syntax;
+
+pub use crate::app::{query, Error, ErrorKind, OutputMode, Span, Table};
+#[cfg(any(test, feature = "fixtures"))]
+pub use crate::execution::fixtures;
@@ -1,2 +1,3 @@
+pub mod optimizer;
 pub mod parser;
 pub mod types;
@@ -0,0 +1,545 @@
+use super::types::{Aggregate, Expression, Formula, LogicInfixOp, LogicPrefixOp, Named, Node, Relation};
+use crate::common::types::{DataSource, Value};
+use crate::execution::datasource::TimeRange;
+use crate::syntax::ast::{PathExpr, PathSegment};
+
+//The rewrites of the logical plan. Each rewrite applied, or skipped for a reason worth knowing, leaves a note so that
+//explain can tell why a predicate wasn't pushed down.
+pub(crate) fn optimize(node: Node) -> (Node, Vec<String>) {
+    let mut notes = Vec::new();
+    let node = fold_constants(node, &mut notes);
+    let node = push_down_predicates(node, &mut notes);
+    let node = push_down_columns(node, true, Vec::new(), &mut notes);
+    (node, notes)
+}
+
+fn map_source<F: FnMut(Node) -> Node>(node: Node, mut f: F) -> Node {
+    match node {
+        Node::DataSource(_, _) => node,
+        Node::Filter(formula, source) => Node::Filter(formula, Box::new(f(*source))),
+        Node::Map(named_list, source) => Node::Map(named_list, Box::new(f(*source))),
+        Node::GroupBy(fields, named_aggregates, source) => {
+            Node::GroupBy(fields, named_aggregates, Box::new(f(*source)))
+        }
+        Node::Limit(row_count, source) => Node::Limit(row_count, Box::new(f(*source))),
+        Node::OrderBy(column_names, orderings, source) => Node::OrderBy(column_names, orderings, Box::new(f(*source))),
+        Node::DistinctOn(keys, source) => Node::DistinctOn(keys, Box::new(f(*source))),
+        Node::Window(windows, partition_keys, time, source) => {
+            Node::Window(windows, partition_keys, time, Box::new(f(*source)))
+        }
+        Node::GapFill(time, interval, partition_keys, fill, source) => {
+            Node::GapFill(time, interval, partition_keys, fill, Box::new(f(*source)))
+        }
+        Node::Join(join, left, right) => {
+            let left = f(*left);
+            Node::Join(join, Box::new(left), Box::new(f(*right)))
+        }
+    }
+}
+
+fn fold_formula(formula: Formula) -> Formula {
+    match formula {
+        Formula::InfixOperator(op, l, r) => match (op, fold_formula(*l), fold_formula(*r)) {
+            (LogicInfixOp::And, Formula::Constant(true), f) | (LogicInfixOp::And, f, Formula::Constant(true)) => f,
+            (LogicInfixOp::And, Formula::Constant(false), _) | (LogicInfixOp::And, _, Formula::Constant(false)) => {
+                Formula::Constant(false)
+            }
+            (LogicInfixOp::Or, Formula::Constant(true), _) | (LogicInfixOp::Or, _, Formula::Constant(true)) => {
+                Formula::Constant(true)
+            }
+            (LogicInfixOp::Or, Formula::Constant(false), f) | (LogicInfixOp::Or, f, Formula::Constant(false)) => f,
+            (op, l, r) => Formula::InfixOperator(op, Box::new(l), Box::new(r)),
+        },
+        Formula::PrefixOperator(LogicPrefixOp::Not, child) => match fold_formula(*child) {
+            Formula::Constant(b) => Formula::Constant(!b),
+            child => Formula::PrefixOperator(LogicPrefixOp::Not, Box::new(child)),
+        },
+        formula => formula,
+    }
+}
+
+fn fold_constants(node: Node, notes: &mut Vec<String>) -> Node {
+    match node {
+        Node::Filter(formula, source) => {
+            let source = fold_constants(*source, notes);
+            let folded = fold_formula(*formula.clone());
+            if folded == Formula::Constant(true) {
+                notes.push("Removed the filter whose condition is always true".to_string());
+                return source;
+            }
+
+            if folded != *formula {
+                notes.push("Folded the constant conditions of the filter".to_string());
+            }
+            Node::Filter(Box::new(folded), Box::new(source))
+        }
+        node => map_source(node, |source| fold_constants(source, notes)),
+    }
+}
+
+fn is_timestamp(expr: &Expression) -> bool {
+    match expr {
+        Expression::Variable(path_expr) => {
+            path_expr.path_segments == vec![PathSegment::AttrName("timestamp".to_string())]
+        }
+        _ => false,
+    }
+}
+
+fn mentions_timestamp(formula: &Formula) -> bool {
+    match formula {
+        Formula::InfixOperator(_, l, r) => mentions_timestamp(l) || mentions_timestamp(r),
+        Formula::PrefixOperator(_, child) => mentions_timestamp(child),
+        Formula::Constant(_) => false,
+        Formula::Predicate(_, l, r) => is_timestamp(l) || is_timestamp(r),
+    }
+}
+
+//The bounds on the timestamp implied by the filter, only the conjunctions of the comparisons
+//against the RFC 3339 literals are considered.
+fn formula_time_range(formula: &Formula) -> TimeRange {
+    let unbounded = TimeRange::default();
+    match formula {
+        Formula::InfixOperator(LogicInfixOp::And, l, r) => formula_time_range(l).intersect(&formula_time_range(r)),
+        Formula::Predicate(relation, l, r) => {
+            let (relation, literal) = match (&**l, &**r) {
+                (c, Expression::Constant(Value::String(s))) if is_timestamp(c) => (relation.clone(), s),
+                (Expression::Constant(Value::String(s)), c) if is_timestamp(c) => {
+                    let flipped = match relation {
+                        Relation::MoreThan => Relation::LessThan,
+                        Relation::LessThan => Relation::MoreThan,
+                        Relation::GreaterEqual => Relation::LessEqual,
+                        Relation::LessEqual => Relation::GreaterEqual,
+                        other => other.clone(),
+                    };
+                    (flipped, s)
+                }
+                _ => return unbounded,
+            };
+
+            let dt = match chrono::DateTime::parse_from_rfc3339(literal) {
+                Ok(dt) => dt,
+                Err(_) => return unbounded,
+            };
+
+            match relation {
+                Relation::MoreThan | Relation::GreaterEqual => TimeRange {
+                    start: Some(dt),
+                    end: None,
+                },
+                Relation::LessThan | Relation::LessEqual => TimeRange {
+                    start: None,
+                    end: Some(dt),
+                },
+                Relation::Equal => TimeRange {
+                    start: Some(dt),
+                    end: Some(dt),
+                },
+                Relation::NotEqual => unbounded,
+            }
+        }
+        _ => unbounded,
+    }
+}
+
+//The equalities of the top level fields with the string, integral or boolean literals in the conjunctions
+fn formula_value_filters(formula: &Formula) -> Vec<(String, Value)> {
+    match formula {
+        Formula::InfixOperator(LogicInfixOp::And, l, r) => {
+            let mut value_filters = formula_value_filters(l);
+            value_filters.extend(formula_value_filters(r));
+            value_filters
+        }
+        Formula::Predicate(Relation::Equal, l, r) => match (&**l, &**r) {
+            (Expression::Variable(path_expr), Expression::Constant(value))
+            | (Expression::Constant(value), Expression::Variable(path_expr)) => {
+                match (path_expr.path_segments.as_slice(), value) {
+                    ([PathSegment::AttrName(name)], Value::String(_))
+                    | ([PathSegment::AttrName(name)], Value::Int(_))
+                    | ([PathSegment::AttrName(name)], Value::Boolean(_)) => vec![(name.clone(), value.clone())],
+                    _ => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+//Whether the filter would see something else than the field of the data source under the name
+fn redefines(named_list: &[Named], field: &str) -> bool {
+    named_list.iter().any(|named| match named {
+        Named::Expression(expr, Some(name)) => {
+            name == field
+                && match expr {
+                    Expression::Variable(path_expr) => {
+                        path_expr.path_segments != vec![PathSegment::AttrName(field.to_string())]
+                    }
+                    _ => true,
+                }
+        }
+        _ => false,
+    })
+}
+
+//The time range of the where clause lets the data source skip the files and the records out of the range, and the
+//equalities of the fields let the data source skip the files whose sketches in the index rule the values out
+fn push_down_predicates(node: Node, notes: &mut Vec<String>) -> Node {
+    match node {
+        Node::Filter(formula, source) => {
+            let (named_list, data_source, bindings) = match *source {
+                Node::DataSource(data_source, bindings) => (None, data_source, bindings),
+                Node::Map(named_list, map_source) => match *map_source {
+                    Node::DataSource(data_source, bindings) => (Some(named_list), data_source, bindings),
+                    map_source => {
+                        let source = Node::Map(named_list, Box::new(push_down_predicates(map_source, notes)));
+                        return Node::Filter(formula, Box::new(source));
+                    }
+                },
+                source => return Node::Filter(formula, Box::new(push_down_predicates(source, notes))),
+            };
+
+            let time_range = formula_time_range(&formula);
+            let data_source = if !mentions_timestamp(&formula) {
+                data_source
+            } else if named_list
+                .as_ref()
+                .is_some_and(|named_list| redefines(named_list, "timestamp"))
+            {
+                notes.push("The time range is not pushed down, timestamp is redefined by the select list".to_string());
+                data_source
+            } else if time_range == TimeRange::default() {
+                notes.push(
+                    "The time range is not pushed down, only the comparisons of timestamp with the RFC 3339 literals \
+                     joined by \"and\" bound the time range"
+                        .to_string(),
+                );
+                data_source
+            } else {
+                let bound = |dt: Option<chrono::DateTime<chrono::FixedOffset>>| {
+                    dt.map_or_else(|| "unbounded".to_string(), |dt| dt.to_rfc3339())
+                };
+                notes.push(format!(
+                    "Pushed the time range from {} to {} down to the data source",
+                    bound(time_range.start),
+                    bound(time_range.end)
+                ));
+                data_source.with_time_range(time_range)
+            };
+
+            //The sketches are only kept in the index
+            let value_filters: Vec<(String, Value)> = formula_value_filters(&formula)
+                .into_iter()
+                .filter(|(field, _)| {
+                    named_list
+                        .as_ref()
+                        .is_none_or(|named_list| !redefines(named_list, field))
+                })
+                .collect();
+            let data_source = if data_source.options().index_dir.is_some() && !value_filters.is_empty() {
+                let fields: Vec<&str> = value_filters.iter().map(|(field, _)| field.as_str()).collect();
+                notes.push(format!(
+                    "Pushed the equalities on {} down to the data source",
+                    fields.join(", ")
+                ));
+                data_source.with_value_filters(value_filters)
+            } else {
+                data_source
+            };
+
+            let source = Node::DataSource(data_source, bindings);
+            let source = match named_list {
+                Some(named_list) => Node::Map(named_list, Box::new(source)),
+                None => source,
+            };
+            Node::Filter(formula, Box::new(source))
+        }
+        node => map_source(node, |source| push_down_predicates(source, notes)),
+    }
+}
+
+fn add_path_field(path_expr: &PathExpr, fields: &mut Vec<String>) {
+    let name = match path_expr.path_segments.first() {
+        Some(PathSegment::AttrName(name)) | Some(PathSegment::ArrayIndex(name, _)) => name,
+        None => return,
+    };
+    if !fields.contains(name) {
+        fields.push(name.clone());
+    }
+}
+
+//Adds the top level fields the expression refers to, false if it refers to all of them
+fn add_named_fields(named: &Named, fields: &mut Vec<String>) -> bool {
+    match named {
+        Named::Expression(expr, _) => add_expression_fields(expr, fields),
+        Named::Star => false,
+    }
+}
+
+fn add_expression_fields(expr: &Expression, fields: &mut Vec<String>) -> bool {
+    match expr {
+        Expression::Constant(_) => true,
+        Expression::Variable(path_expr) => {
+            add_path_field(path_expr, fields);
+            true
+        }
+        Expression::Logic(formula) => add_formula_fields(formula, fields),
+        Expression::Function(_, arguments) => arguments.iter().all(|argument| add_named_fields(argument, fields)),
+        Expression::Branch(condition, then_expr, else_expr) => {
+            add_formula_fields(condition, fields)
+                && add_expression_fields(then_expr, fields)
+                && else_expr
+                    .as_ref()
+                    .is_none_or(|else_expr| add_expression_fields(else_expr, fields))
+        }
+    }
+}
+
+fn add_formula_fields(formula: &Formula, fields: &mut Vec<String>) -> bool {
+    match formula {
+        Formula::InfixOperator(_, l, r) => add_formula_fields(l, fields) && add_formula_fields(r, fields),
+        Formula::PrefixOperator(_, child) => add_formula_fields(child, fields),
+        Formula::Constant(_) => true,
+        Formula::Predicate(_, l, r) => add_expression_fields(l, fields) && add_expression_fields(r, fields),
+    }
+}
+
+fn add_aggregate_fields(aggregate: &Aggregate, fields: &mut Vec<String>) -> bool {
+    match aggregate {
+        //count(*) only counts the records
+        Aggregate::Count(Named::Star) => true,
+        Aggregate::PercentileDisc(_, path_expr, _, weight) | Aggregate::ApproxPercentile(_, path_expr, _, weight) => {
+            add_path_field(path_expr, fields);
+            if let Some(weight) = weight {
+                add_path_field(weight, fields);
+            }
+            true
+        }
+        Aggregate::Avg(named)
+        | Aggregate::Count(named)
+        | Aggregate::First(named)
+        | Aggregate::Last(named)
+        | Aggregate::Max(named)
+        | Aggregate::Min(named)
+        | Aggregate::Sum(named)
+        | Aggregate::ApproxCountDistinct(named)
+        | Aggregate::Rate(named)
+        | Aggregate::Ratio(named)
+        | Aggregate::GroupAsAggregate(named)
+        | Aggregate::AnyValue(named)
+        | Aggregate::Sample(named, _)
+        | Aggregate::Corr(named)
+        | Aggregate::Covar(named)
+        | Aggregate::Entropy(named)
+        | Aggregate::Concentration(named, _)
+        | Aggregate::ApproxTopK(named, _)
+        | Aggregate::MinBy(named)
+        | Aggregate::MaxBy(named)
+        | Aggregate::AvgWeighted(named) => add_named_fields(named, fields),
+    }
+}
+
+//The top level fields the data source needs to give, so that a converted copy only reads the columns of these. The
+//operators above need all the columns of the node if all is set, and the fields besides. The records of a select list
+//share the record they are computed from, so the operators above it may refer to the fields it doesn't select. The
+//names of the aliases and the aggregates may end up among the fields, which costs nothing as there are no such columns.
+fn push_down_columns(node: Node, all: bool, mut fields: Vec<String>, notes: &mut Vec<String>) -> Node {
+    let all = match &node {
+        Node::DataSource(data_source, bindings) => {
+            for binding in bindings.iter() {
+                add_path_field(&binding.path_expr, &mut fields);
+            }
+            return match data_source {
+                DataSource::File(..) if !all => {
+                    notes.push(format!(
+                        "Pushed the columns {} down to the converted copies of the data source",
+                        fields.join(", ")
+                    ));
+                    Node::DataSource(data_source.clone().with_columns(fields), bindings.clone())
+                }
+                _ => node,
+            };
+        }
+        Node::Filter(formula, _) => !add_formula_fields(formula, &mut fields) || all,
+        //The fields after a star are still needed when the operators above don't need all of the columns
+        Node::Map(named_list, _) => {
+            let mut star = false;
+            for named in named_list.iter() {
+                star |= !add_named_fields(named, &mut fields);
+            }
+            star && all
+        }
+        Node::GroupBy(keys, named_aggregates, _) => {
+            fields.clear();
+            keys.iter().for_each(|key| add_path_field(key, &mut fields));
+            let mut all = false;
+            for named_aggregate in named_aggregates.iter() {
+                all |= !add_aggregate_fields(&named_aggregate.aggregate, &mut fields);
+            }
+            all
+        }
+        Node::Limit(_, _) => all,
+        Node::OrderBy(keys, _, _) | Node::DistinctOn(keys, _) => {
+            keys.iter().for_each(|key| add_path_field(key, &mut fields));
+            all
+        }
+        Node::Window(windows, keys, time, _) => {
+            windows
+                .iter()
+                .for_each(|window| add_path_field(&window.column, &mut fields));
+            keys.iter()
+                .chain(std::iter::once(time))
+                .for_each(|key| add_path_field(key, &mut fields));
+            all
+        }
+        Node::GapFill(time, _, keys, _, _) => {
+            keys.iter()
+                .chain(std::iter::once(time))
+                .for_each(|key| add_path_field(key, &mut fields));
+            all
+        }
+        //The fields above are the names of the sides, not the columns of the tables
+        Node::Join(..) => true,
+    };
+
+    map_source(node, |source| push_down_columns(source, all, fields.clone(), notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::DataSource;
+    use crate::execution::datasource::ReaderOptions;
+    use crate::logical::parser::parse_query;
+    use crate::syntax::ast::PathExpr;
+    use crate::syntax::parser::select_query;
+
+    fn optimized_options(query_str: &str, options: ReaderOptions) -> (ReaderOptions, Vec<String>) {
+        let data_source = DataSource::Stdin("jsonl".to_string(), "it".to_string(), options);
+        optimized_source(query_str, data_source)
+    }
+
+    fn optimized_source(query_str: &str, data_source: DataSource) -> (ReaderOptions, Vec<String>) {
+        let (_, stmt) = select_query(query_str).unwrap();
+        let (node, notes) = optimize(parse_query(stmt, data_source).unwrap());
+
+        let mut node = &node;
+        loop {
+            match node {
+                Node::DataSource(data_source, _) => return (data_source.options().clone(), notes),
+                Node::Filter(_, source)
+                | Node::Map(_, source)
+                | Node::GroupBy(_, _, source)
+                | Node::Limit(_, source)
+                | Node::OrderBy(_, _, source)
+                | Node::DistinctOn(_, source)
+                | Node::Window(_, _, _, source)
+                | Node::GapFill(_, _, _, _, source)
+                | Node::Join(_, source, _) => node = source,
+            }
+        }
+    }
+
+    fn optimized_time_range(query_str: &str) -> (TimeRange, Vec<String>) {
+        let (options, notes) = optimized_options(query_str, ReaderOptions::default());
+        (options.time_range, notes)
+    }
+
+    #[test]
+    fn test_push_down_time_range() {
+        let dt = |s: &str| Some(chrono::DateTime::parse_from_rfc3339(s).unwrap());
+
+        let (time_range, notes) = optimized_time_range(
+            r#"select a from it where timestamp >= "2019-06-07t18:00:00z" and "2019-06-07t19:00:00z" > timestamp and a = 1"#,
+        );
+        assert_eq!(time_range.start, dt("2019-06-07T18:00:00Z"));
+        assert_eq!(time_range.end, dt("2019-06-07T19:00:00Z"));
+        assert_eq!(1, notes.len());
+
+        let (time_range, notes) =
+            optimized_time_range(r#"select a from it where timestamp >= "2019-06-07t18:00:00z" or a = 1"#);
+        assert_eq!(time_range, TimeRange::default());
+        assert_eq!(1, notes.len());
+
+        let (time_range, notes) =
+            optimized_time_range(r#"select a as timestamp from it where timestamp >= "2019-06-07t18:00:00z""#);
+        assert_eq!(time_range, TimeRange::default());
+        assert!(notes[0].contains("redefined"));
+    }
+
+    #[test]
+    fn test_push_down_value_filters() {
+        let value_filters = |query_str: &str| {
+            let options = ReaderOptions {
+                index_dir: Some(std::path::PathBuf::from("index")),
+                ..ReaderOptions::default()
+            };
+            optimized_options(query_str, options).0.value_filters
+        };
+
+        assert_eq!(
+            vec![
+                ("a".to_string(), Value::Int(1)),
+                ("b".to_string(), Value::String("x".to_string()))
+            ],
+            value_filters(r#"select a from it where a = 1 and "x" = b and c > 2"#)
+        );
+        assert!(value_filters(r#"select a from it where a = 1 or b = "x""#).is_empty());
+    }
+
+    #[test]
+    fn test_push_down_columns() {
+        let columns = |query_str: &str| {
+            let data_source = DataSource::File(
+                std::path::PathBuf::from("elb.log"),
+                "elb".to_string(),
+                "it".to_string(),
+                ReaderOptions::default(),
+            );
+            optimized_source(query_str, data_source).0.columns
+        };
+
+        let names = |names: &[&str]| Some(names.iter().map(|name| name.to_string()).collect::<Vec<String>>());
+        assert_eq!(
+            names(&["a", "b"]),
+            columns("select a, count(*) as c from it where b = 1 group by a")
+        );
+        assert_eq!(names(&[]), columns("select count(*) from it"));
+        assert_eq!(
+            names(&["t", "sent_bytes", "backend_processing_time", "timestamp"]),
+            columns(
+                r#"select t, sum(sent_bytes) as s, percentile_disc(0.9) within group (order by backend_processing_time asc) as bps from it group by time_bucket("5 seconds", timestamp) as t"#
+            )
+        );
+        assert_eq!(
+            names(&["e", "f"]),
+            columns("select e, count(*) as c from it where f > 1 group by e having c > 1 order by c desc limit 3")
+        );
+        //The filter above the select list may refer to the fields it doesn't select
+        assert_eq!(names(&["b", "a"]), columns("select a from it where b = 1"));
+        assert_eq!(None, columns("select * from it where a = 1"));
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let path_expr = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
+        let predicate = Formula::Predicate(
+            Relation::Equal,
+            Box::new(Expression::Variable(path_expr)),
+            Box::new(Expression::Constant(Value::Int(1))),
+        );
+
+        let formula = Formula::InfixOperator(
+            LogicInfixOp::And,
+            Box::new(Formula::PrefixOperator(
+                LogicPrefixOp::Not,
+                Box::new(Formula::Constant(false)),
+            )),
+            Box::new(predicate.clone()),
+        );
+        assert_eq!(predicate, fold_formula(formula));
+
+        let formula = Formula::InfixOperator(LogicInfixOp::Or, Box::new(predicate), Box::new(Formula::Constant(true)));
+        assert_eq!(Formula::Constant(true), fold_formula(formula));
+    }
+}
@@ -5,30 +5,67 @@ use crate::execution;
 use crate::logical::types::Named;
 use crate::syntax::ast;
 use crate::syntax::ast::{PathExpr, PathSegment, TableReference};
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
+use std::fmt;
 
-#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+//The largest reservoir of sample(col, k)
+const MAX_SAMPLE_SIZE: i32 = 100;
+//The most values of approx_top_k(col, k), the counters are ten times as many
+const MAX_TOP_K: i32 = 100;
+//The most rows of moving_avg(col, n)
+const MAX_MOVING_AVG_ROWS: i32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
-    #[fail(display = "Type Mismatch")]
     TypeMismatch,
-    #[fail(display = "Not Aggregate Function")]
     NotAggregateFunction,
-    #[fail(display = "Group By statement but no aggregate function provided")]
     GroupByWithoutAggregateFunction,
-    #[fail(display = "Group By statement mismatch with the non-aggregate fields")]
     GroupByFieldsMismatch,
-    #[fail(display = "Invalid Arguments: {}", _0)]
     InvalidArguments(String),
-    #[fail(display = "Invalid Arguments: {}", _0)]
     UnknownFunction(String),
-    #[fail(display = "Having clause but no Group By clause provided")]
     HavingClauseWithoutGroupBy,
-    #[fail(display = "Invalid table reference in From Clause")]
     FromClausePathInvalidTableReference,
-    #[fail(display = "Using 'as' to define an alias is required in From Clause for nested path expr")]
     FromClauseMissingAsForPathExpr,
+    InvalidOrderByPosition(usize),
+    DistinctOnNotSelected(String),
+    InvalidEmitInterval(String),
+    InvalidWindow(String),
+    InvalidGapFill,
+    InvalidJoin(String),
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::TypeMismatch => fmt.write_str("Type Mismatch"),
+            ParseError::NotAggregateFunction => fmt.write_str("Not Aggregate Function"),
+            ParseError::GroupByWithoutAggregateFunction => {
+                fmt.write_str("Group By statement but no aggregate function provided")
+            }
+            ParseError::GroupByFieldsMismatch => {
+                fmt.write_str("Group By statement mismatch with the non-aggregate fields")
+            }
+            ParseError::InvalidArguments(s) => write!(fmt, "Invalid Arguments: {}", s),
+            ParseError::UnknownFunction(s) => write!(fmt, "Invalid Arguments: {}", s),
+            ParseError::HavingClauseWithoutGroupBy => fmt.write_str("Having clause but no Group By clause provided"),
+            ParseError::FromClausePathInvalidTableReference => fmt.write_str("Invalid table reference in From Clause"),
+            ParseError::FromClauseMissingAsForPathExpr => {
+                fmt.write_str("Using 'as' to define an alias is required in From Clause for nested path expr")
+            }
+            ParseError::InvalidOrderByPosition(n) => write!(fmt, "Order By position {} is not in the select list", n),
+            ParseError::DistinctOnNotSelected(s) => write!(fmt, "Distinct On column {} is not in the select list", s),
+            ParseError::InvalidEmitInterval(s) => write!(fmt, "Invalid interval {} in Emit Every", s),
+            ParseError::InvalidWindow(s) => write!(fmt, "{} needs a Group By and an Order By of the time bucket", s),
+            ParseError::InvalidGapFill => {
+                fmt.write_str("Gap Fill needs a Group By of time_bucket with a fixed interval")
+            }
+            ParseError::InvalidJoin(s) => write!(fmt, "Invalid Join: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 pub type ParseResult<T> = Result<T, ParseError>;
 
 fn parse_prefix_operator(
@@ -80,6 +117,16 @@ fn parse_logic(ctx: &common::ParsingContext, expr: &ast::Expression) -> ParseRes
             ast::Value::Boolean(b) => Ok(Box::new(types::Formula::Constant(*b))),
             _ => Err(ParseError::TypeMismatch),
         },
+        //The boolean function or column holds when it is true, e.g. where contains(["502", "504"], elb_status_code)
+        ast::Expression::FuncCall(_, _, _) | ast::Expression::Column(_) => {
+            let value = parse_value_expression(ctx, expr)?;
+            let constant = Box::new(types::Expression::Constant(common::Value::Boolean(true)));
+            Ok(Box::new(types::Formula::Predicate(
+                types::Relation::Equal,
+                value,
+                constant,
+            )))
+        }
         _ => unreachable!(),
     }
 }
@@ -170,6 +217,16 @@ fn parse_unary_operator(ctx: &ParsingContext, value_expr: &ast::Expression) -> P
     }
 }
 
+//The functions on a well-known field take the field as the first argument when it is omitted, e.g. was_redirected()
+fn default_field_argument(func_name: &str) -> Option<(&'static str, usize)> {
+    match func_name {
+        "was_redirected" | "was_fixed_response" => Some(("action_executed", 1)),
+        "has_action" => Some(("action_executed", 2)),
+        "referrer_host" | "search_engine" => Some(("referrer", 1)),
+        _ => None,
+    }
+}
+
 fn parse_value_expression(
     ctx: &common::ParsingContext,
     value_expr: &ast::Expression,
@@ -188,6 +245,14 @@ fn parse_value_expression(
                 let arg = parse_expression(ctx, select_expr)?;
                 args.push(*arg);
             }
+
+            if let Some((field_name, arity)) = default_field_argument(func_name) {
+                if args.len() + 1 == arity {
+                    let path_expr = ast::PathExpr::new(vec![ast::PathSegment::AttrName(field_name.to_string())]);
+                    let field = types::Expression::Variable(path_expr);
+                    args.insert(0, types::Named::Expression(field, Some(field_name.to_string())));
+                }
+            }
             Ok(Box::new(types::Expression::Function(func_name.clone(), args)))
         }
         ast::Expression::CaseWhenExpression(_) => parse_case_when_expression(ctx, value_expr),
@@ -230,7 +295,10 @@ fn parse_expression(ctx: &ParsingContext, select_expr: &ast::SelectExpression) -
                         PathSegment::AttrName(s) => Some(s.clone()),
                     };
 
-                    Ok(Box::new(types::Named::Expression(*e.clone(), name)))
+                    Ok(Box::new(types::Named::Expression(
+                        *e.clone(),
+                        name_opt.clone().or(name),
+                    )))
                 }
                 _ => Ok(Box::new(types::Named::Expression(*e, name_opt.clone()))),
             }
@@ -248,6 +316,9 @@ fn from_str(value: &str, named: types::Named) -> ParseResult<types::Aggregate> {
         "min" => Ok(types::Aggregate::Min(named)),
         "sum" => Ok(types::Aggregate::Sum(named)),
         "approx_count_distinct" => Ok(types::Aggregate::ApproxCountDistinct(named)),
+        "rate" => Ok(types::Aggregate::Rate(named)),
+        "any_value" => Ok(types::Aggregate::AnyValue(named)),
+        "entropy" => Ok(types::Aggregate::Entropy(named)),
         _ => Err(ParseError::NotAggregateFunction),
     }
 }
@@ -259,11 +330,156 @@ fn parse_ordering(ordering: ast::Ordering) -> ParseResult<types::Ordering> {
     }
 }
 
+//delta(c), rate_per_sec(c) and moving_avg(c, n) refer to an output column of the grouped rows, e.g. the alias of count(*)
+fn parse_window(
+    select_expr: &ast::SelectExpression,
+    offset: usize,
+) -> ParseResult<Option<execution::types::NamedWindow>> {
+    let (func_name, args, name_opt) = match select_expr {
+        ast::SelectExpression::Expression(expr, name_opt) => match &**expr {
+            ast::Expression::FuncCall(func_name, args, _) => (func_name, args, name_opt),
+            _ => return Ok(None),
+        },
+        ast::SelectExpression::Star => return Ok(None),
+    };
+
+    let (function, arg) = match (func_name.as_str(), &args[..]) {
+        ("delta", [arg]) => (execution::types::WindowFunction::Delta, arg),
+        ("rate_per_sec", [arg]) => (execution::types::WindowFunction::RatePerSec, arg),
+        //The number of rows is a constant, they are kept in memory per partition
+        ("moving_avg", [arg, ast::SelectExpression::Expression(n, _)]) => match &**n {
+            ast::Expression::Value(ast::Value::Integral(n)) if *n > 0 && *n <= MAX_MOVING_AVG_ROWS => {
+                (execution::types::WindowFunction::MovingAvg(*n as usize), arg)
+            }
+            _ => return Err(ParseError::InvalidArguments(func_name.clone())),
+        },
+        ("delta", _) | ("rate_per_sec", _) | ("moving_avg", _) => {
+            return Err(ParseError::InvalidArguments(func_name.clone()));
+        }
+        _ => return Ok(None),
+    };
+
+    match arg {
+        ast::SelectExpression::Expression(arg, _) => match &**arg {
+            ast::Expression::Column(path_expr) => {
+                let column = PathExpr::new(vec![PathSegment::AttrName(path_expr.unwrap_last())]);
+                let name = name_opt.clone().unwrap_or_else(|| format!("_{}", offset + 1));
+                Ok(Some(execution::types::NamedWindow::new(function, column, name)))
+            }
+            _ => Err(ParseError::InvalidArguments(func_name.clone())),
+        },
+        ast::SelectExpression::Star => Err(ParseError::InvalidArguments(func_name.clone())),
+    }
+}
+
 fn parse_aggregate(ctx: &ParsingContext, select_expr: &ast::SelectExpression) -> ParseResult<types::NamedAggregate> {
     match select_expr {
         ast::SelectExpression::Expression(expr, name_opt) => match &**expr {
+            ast::Expression::FuncCall(func_name, args, _) if func_name == "count_if" || func_name == "sum_if" => {
+                //count_if(pred) is sum(case when pred then 1 else 0 end) and sum_if(col, pred) is
+                //sum(case when pred then col else 0 end)
+                let (value_expr, pred) = match (func_name.as_str(), &args[..]) {
+                    ("count_if", [ast::SelectExpression::Expression(pred, _)]) => {
+                        (Box::new(types::Expression::Constant(common::Value::Int(1))), pred)
+                    }
+                    (
+                        "sum_if",
+                        [ast::SelectExpression::Expression(e, _), ast::SelectExpression::Expression(pred, _)],
+                    ) => (parse_value_expression(ctx, e)?, pred),
+                    _ => {
+                        return Err(ParseError::InvalidArguments(func_name.to_string()));
+                    }
+                };
+
+                let branch = types::Expression::Branch(
+                    parse_logic(ctx, pred)?,
+                    value_expr,
+                    Some(Box::new(types::Expression::Constant(common::Value::Int(0)))),
+                );
+                let aggregate = types::Aggregate::Sum(types::Named::Expression(branch, None));
+                Ok(types::NamedAggregate::new(aggregate, name_opt.clone()))
+            }
+            ast::Expression::FuncCall(func_name, args, _) if func_name == "sample" => match &args[..] {
+                //The size of the reservoir is a constant, the examples are kept in memory per group
+                [value_arg, ast::SelectExpression::Expression(k, _)] => match &**k {
+                    ast::Expression::Value(ast::Value::Integral(k)) if *k > 0 && *k <= MAX_SAMPLE_SIZE => {
+                        let named = *parse_expression(ctx, value_arg)?;
+                        Ok(types::NamedAggregate::new(
+                            types::Aggregate::Sample(named, *k as usize),
+                            name_opt.clone(),
+                        ))
+                    }
+                    _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+                },
+                _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+            },
+            ast::Expression::FuncCall(func_name, args, _)
+                if ["corr", "covar", "min_by", "max_by", "avg_weighted"].contains(&func_name.as_str()) =>
+            {
+                //The pair is evaluated as a list, e.g. corr(sent_bytes, backend_processing_time) or
+                //max_by(request, backend_processing_time)
+                let pair = match &args[..] {
+                    [ast::SelectExpression::Expression(x, _), ast::SelectExpression::Expression(y, _)] => vec![
+                        types::Named::Expression(*parse_value_expression(ctx, x)?, None),
+                        types::Named::Expression(*parse_value_expression(ctx, y)?, None),
+                    ],
+                    _ => {
+                        return Err(ParseError::InvalidArguments(func_name.to_string()));
+                    }
+                };
+
+                let named = types::Named::Expression(types::Expression::Function("list".to_string(), pair), None);
+                let aggregate = match func_name.as_str() {
+                    "corr" => types::Aggregate::Corr(named),
+                    "covar" => types::Aggregate::Covar(named),
+                    "min_by" => types::Aggregate::MinBy(named),
+                    "avg_weighted" => types::Aggregate::AvgWeighted(named),
+                    _ => types::Aggregate::MaxBy(named),
+                };
+                Ok(types::NamedAggregate::new(aggregate, name_opt.clone()))
+            }
+            ast::Expression::FuncCall(func_name, args, _) if func_name == "approx_top_k" => match &args[..] {
+                [value_arg, ast::SelectExpression::Expression(k, _)] => match &**k {
+                    ast::Expression::Value(ast::Value::Integral(k)) if *k > 0 && *k <= MAX_TOP_K => {
+                        let named = *parse_expression(ctx, value_arg)?;
+                        Ok(types::NamedAggregate::new(
+                            types::Aggregate::ApproxTopK(named, *k as usize),
+                            name_opt.clone(),
+                        ))
+                    }
+                    _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+                },
+                _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+            },
+            ast::Expression::FuncCall(func_name, args, _) if func_name == "concentration" => match &args[..] {
+                [value_arg, ast::SelectExpression::Expression(k, _)] => match &**k {
+                    ast::Expression::Value(ast::Value::Integral(k)) if *k > 0 => {
+                        let named = *parse_expression(ctx, value_arg)?;
+                        Ok(types::NamedAggregate::new(
+                            types::Aggregate::Concentration(named, *k as usize),
+                            name_opt.clone(),
+                        ))
+                    }
+                    _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+                },
+                _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+            },
+            ast::Expression::FuncCall(func_name, args, _) if func_name == "ratio" => match &args[..] {
+                [ast::SelectExpression::Expression(pred, _)] => {
+                    let formula = parse_logic(ctx, pred)?;
+                    let named = types::Named::Expression(types::Expression::Logic(formula), None);
+                    Ok(types::NamedAggregate::new(
+                        types::Aggregate::Ratio(named),
+                        name_opt.clone(),
+                    ))
+                }
+                _ => Err(ParseError::InvalidArguments(func_name.to_string())),
+            },
             ast::Expression::FuncCall(func_name, args, within_group_opt) => {
-                let named = *parse_expression(ctx, &args[0])?;
+                let first_arg = args
+                    .first()
+                    .ok_or_else(|| ParseError::InvalidArguments(func_name.to_string()))?;
+                let named = *parse_expression(ctx, first_arg)?;
 
                 let aggregate = if let Some(within_group_clause) = within_group_opt {
                     match named {
@@ -272,17 +488,33 @@ fn parse_aggregate(ctx: &ParsingContext, select_expr: &ast::SelectExpression) ->
                                 common::Value::Float(f) => {
                                     let o = parse_ordering(within_group_clause.ordering_term.ordering.clone())?;
 
+                                    //The optional second argument is the weight column, e.g. percentile_disc(0.9, received_bytes)
+                                    let weight_opt = match &args[1..] {
+                                        [] => None,
+                                        [ast::SelectExpression::Expression(e, _)] => match &**e {
+                                            ast::Expression::Column(path_expr) => Some(path_expr.clone()),
+                                            _ => {
+                                                return Err(ParseError::InvalidArguments(func_name.to_string()));
+                                            }
+                                        },
+                                        _ => {
+                                            return Err(ParseError::InvalidArguments(func_name.to_string()));
+                                        }
+                                    };
+
                                     if func_name == "percentile_disc" {
                                         types::Aggregate::PercentileDisc(
                                             f,
                                             within_group_clause.ordering_term.column_name.clone(),
                                             o,
+                                            weight_opt,
                                         )
                                     } else if func_name == "approx_percentile" {
                                         types::Aggregate::ApproxPercentile(
                                             f,
                                             within_group_clause.ordering_term.column_name.clone(),
                                             o,
+                                            weight_opt,
                                         )
                                     } else {
                                         return Err(ParseError::UnknownFunction(func_name.to_string()));
@@ -399,6 +631,75 @@ fn to_bindings(table_name: &String, table_references: &Vec<TableReference>) -> V
         .collect()
 }
 
+//"group by is_error" on "select elb_status_code >= 500 as is_error" groups by the aliased expression
+fn resolve_group_by_aliases(
+    ctx: &ParsingContext,
+    select_clause: &ast::SelectClause,
+    group_by_references: Vec<ast::GroupByReference>,
+) -> Vec<ast::GroupByReference> {
+    let select_exprs = match select_clause {
+        ast::SelectClause::SelectExpressions(select_exprs) => select_exprs,
+        ast::SelectClause::ValueConstructor(_) => return group_by_references,
+    };
+
+    group_by_references
+        .into_iter()
+        .map(|r| {
+            let name = match (&r.column_expr, &r.as_clause) {
+                (ast::Expression::Column(path_expr), None) => match &path_expr.path_segments[..] {
+                    [PathSegment::AttrName(s)] => s.clone(),
+                    _ => return r,
+                },
+                _ => return r,
+            };
+
+            let aliased = select_exprs.iter().find(|select_expr| match select_expr {
+                ast::SelectExpression::Expression(e, Some(alias)) => {
+                    alias == &name
+                        && !matches!(&**e, ast::Expression::Column(_))
+                        && parse_aggregate(ctx, select_expr).is_err()
+                }
+                _ => false,
+            });
+
+            match aliased {
+                Some(ast::SelectExpression::Expression(e, _)) => ast::GroupByReference::new(*e.clone(), Some(name)),
+                _ => r,
+            }
+        })
+        .collect()
+}
+
+//GROUP BY ALL groups by every non-aggregate select expression
+fn expand_group_by_all(ctx: &ParsingContext, select_clause: &ast::SelectClause) -> Vec<ast::GroupByReference> {
+    match select_clause {
+        ast::SelectClause::SelectExpressions(select_exprs) => select_exprs
+            .iter()
+            .filter(|select_expr| parse_aggregate(ctx, select_expr).is_err())
+            .filter_map(|select_expr| match select_expr {
+                ast::SelectExpression::Star => None,
+                ast::SelectExpression::Expression(expr, name_opt) => match &**expr {
+                    ast::Expression::Column(_) => Some(ast::GroupByReference::new(*expr.clone(), None)),
+                    _ => Some(ast::GroupByReference::new(*expr.clone(), name_opt.clone())),
+                },
+            })
+            .collect(),
+        ast::SelectClause::ValueConstructor(_) => Vec::new(),
+    }
+}
+
+//The column of the aggregated record a non-aggregate select expression refers to, and its output name
+fn group_by_output(named: &Named) -> Option<(String, String)> {
+    match named {
+        Named::Expression(types::Expression::Variable(path_expr), name_opt) => match path_expr.path_segments.last() {
+            Some(PathSegment::AttrName(s)) => Some((s.clone(), name_opt.clone().unwrap_or_else(|| s.clone()))),
+            _ => None,
+        },
+        Named::Expression(_, Some(alias)) => Some((alias.clone(), alias.clone())),
+        _ => None,
+    }
+}
+
 fn check_group_by_vars(named: &Named, group_by_vars: &HashSet<String>) -> bool {
     match named {
         Named::Expression(expr, alias) => {
@@ -411,17 +712,14 @@ fn check_group_by_vars(named: &Named, group_by_vars: &HashSet<String>) -> bool {
                         return false;
                     }
                 },
-                types::Expression::Function(_, _) => {
+                //The function, the predicate or the case when is grouped under its alias
+                _ => {
                     if let Some(a) = alias {
                         return group_by_vars.contains(a);
                     } else {
                         return false;
                     }
                 }
-                _ => {
-                    //TODO: branch furhter
-                    return false;
-                }
             }
         }
         _ => {
@@ -430,12 +728,312 @@ fn check_group_by_vars(named: &Named, group_by_vars: &HashSet<String>) -> bool {
     }
 }
 
+fn rewrite_aliases(expr: &mut ast::Expression, aliases: &HashMap<String, PathExpr>) {
+    match expr {
+        ast::Expression::Column(path_expr) => {
+            if let [PathSegment::AttrName(s)] = &path_expr.path_segments[..] {
+                if let Some(target) = aliases.get(s) {
+                    *path_expr = target.clone();
+                }
+            }
+        }
+        ast::Expression::Value(_) => {}
+        ast::Expression::BinaryOperator(_, l, r) => {
+            rewrite_aliases(l, aliases);
+            rewrite_aliases(r, aliases);
+        }
+        ast::Expression::UnaryOperator(_, c) => {
+            rewrite_aliases(c, aliases);
+        }
+        ast::Expression::FuncCall(_, args, _) => {
+            for arg in args.iter_mut() {
+                if let ast::SelectExpression::Expression(e, _) = arg {
+                    rewrite_aliases(e, aliases);
+                }
+            }
+        }
+        ast::Expression::CaseWhenExpression(case_when) => {
+            rewrite_aliases(&mut case_when.condition, aliases);
+            rewrite_aliases(&mut case_when.then_expr, aliases);
+            if let Some(else_expr) = case_when.else_expr.as_mut() {
+                rewrite_aliases(else_expr, aliases);
+            }
+        }
+    }
+}
+
+//Resolve "order by 2 desc" to the name of the second select expression, the expression without a name is given its
+//positional name. The alias on a plain column is not carried to the output, so the references to it in ORDER BY and
+//HAVING are rewritten to the column itself.
+fn resolve_select_references(mut query: ast::SelectStatement) -> ParseResult<ast::SelectStatement> {
+    let select_exprs = match &mut query.select_clause {
+        ast::SelectClause::SelectExpressions(select_exprs) => select_exprs,
+        ast::SelectClause::ValueConstructor(_) => {
+            return Ok(query);
+        }
+    };
+
+    let mut aliases: HashMap<String, PathExpr> = HashMap::new();
+    let mut column_aliases: HashMap<String, String> = HashMap::new();
+    for select_expr in select_exprs.iter() {
+        if let ast::SelectExpression::Expression(expr, Some(name)) = select_expr {
+            if let ast::Expression::Column(path_expr) = &**expr {
+                if let Some(PathSegment::AttrName(s)) = path_expr.path_segments.last() {
+                    aliases.insert(name.clone(), PathExpr::new(vec![PathSegment::AttrName(s.clone())]));
+                    column_aliases.insert(s.clone(), name.clone());
+                }
+            }
+        }
+    }
+    //A column selected under its own name is ordered by that column
+    for select_expr in select_exprs.iter() {
+        if let ast::SelectExpression::Expression(expr, None) = select_expr {
+            if let ast::Expression::Column(path_expr) = &**expr {
+                if let Some(PathSegment::AttrName(s)) = path_expr.path_segments.last() {
+                    column_aliases.remove(s);
+                }
+            }
+        }
+    }
+
+    if let Some(order_by_expr) = query.order_by_expr_opt.as_mut() {
+        for ordering_term in order_by_expr.ordering_terms.iter_mut() {
+            if let Some(position) = ordering_term.position {
+                if position == 0 || position > select_exprs.len() {
+                    return Err(ParseError::InvalidOrderByPosition(position));
+                }
+
+                let name = match &mut select_exprs[position - 1] {
+                    ast::SelectExpression::Star => {
+                        return Err(ParseError::InvalidOrderByPosition(position));
+                    }
+                    ast::SelectExpression::Expression(expr, name_opt) => match &**expr {
+                        ast::Expression::Column(path_expr) => match path_expr.path_segments.last() {
+                            Some(PathSegment::AttrName(s)) => name_opt.clone().unwrap_or_else(|| s.clone()),
+                            _ => {
+                                return Err(ParseError::InvalidOrderByPosition(position));
+                            }
+                        },
+                        _ => {
+                            if name_opt.is_none() {
+                                *name_opt = Some(format!("_{}", position));
+                            }
+                            name_opt.clone().unwrap()
+                        }
+                    },
+                };
+
+                ordering_term.column_name = PathExpr::new(vec![PathSegment::AttrName(name)]);
+                ordering_term.position = None;
+            } else if let [PathSegment::AttrName(s)] = &ordering_term.column_name.path_segments[..] {
+                //The output column is named by the alias, order by the aliased column through its alias
+                if let Some(alias) = column_aliases.get(s) {
+                    ordering_term.column_name = PathExpr::new(vec![PathSegment::AttrName(alias.clone())]);
+                }
+            }
+        }
+    }
+
+    if let Some(having_expr) = query.having_expr_opt.as_mut() {
+        rewrite_aliases(&mut having_expr.expr, &aliases);
+    }
+
+    //The keys of "distinct on" are the output columns, checked against the select list unless it has a star
+    if let Some(keys) = query.distinct_on_opt.as_mut() {
+        let has_star = select_exprs.contains(&ast::SelectExpression::Star);
+        let output_names: HashSet<String> = select_exprs
+            .iter()
+            .enumerate()
+            .filter_map(|(offset, select_expr)| match select_expr {
+                ast::SelectExpression::Expression(_, Some(name)) => Some(name.clone()),
+                ast::SelectExpression::Expression(expr, None) => match &**expr {
+                    ast::Expression::Column(path_expr) => match path_expr.path_segments.last() {
+                        Some(PathSegment::AttrName(s)) => Some(s.clone()),
+                        _ => None,
+                    },
+                    _ => Some(format!("_{}", offset + 1)),
+                },
+                ast::SelectExpression::Star => None,
+            })
+            .collect();
+
+        for key in keys.iter_mut() {
+            if let [PathSegment::AttrName(s)] = &key.path_segments[..] {
+                if let Some(alias) = column_aliases.get(s) {
+                    *key = PathExpr::new(vec![PathSegment::AttrName(alias.clone())]);
+                }
+            }
+
+            let name = key.unwrap_last();
+            if !has_star && !output_names.contains(&name) {
+                return Err(ParseError::DistinctOnNotSelected(name));
+            }
+        }
+    }
+
+    Ok(query)
+}
+
+//"emit every" groups by the tumbling window of the timestamp as well, the window is the first output column
+fn expand_emit_every(mut query: ast::SelectStatement) -> ParseResult<ast::SelectStatement> {
+    let interval = match &query.emit_every_opt {
+        Some(interval) => interval.clone(),
+        None => {
+            return Ok(query);
+        }
+    };
+    common::parse_time_interval(&interval).map_err(|_| ParseError::InvalidEmitInterval(interval.clone()))?;
+
+    let select_exprs = match &mut query.select_clause {
+        ast::SelectClause::SelectExpressions(select_exprs) => select_exprs,
+        ast::SelectClause::ValueConstructor(_) => {
+            return Err(ParseError::InvalidArguments("emit every".to_string()));
+        }
+    };
+    let window = ast::Expression::FuncCall(
+        "time_bucket".to_string(),
+        vec![
+            ast::SelectExpression::Expression(
+                Box::new(ast::Expression::Value(ast::Value::StringLiteral(interval))),
+                None,
+            ),
+            ast::SelectExpression::Expression(
+                Box::new(ast::Expression::Column(PathExpr::new(vec![PathSegment::AttrName(
+                    "timestamp".to_string(),
+                )]))),
+                None,
+            ),
+        ],
+        None,
+    );
+    select_exprs.insert(
+        0,
+        ast::SelectExpression::Expression(Box::new(window), Some("window".to_string())),
+    );
+
+    let window_ref = ast::GroupByReference::new(
+        ast::Expression::Column(PathExpr::new(vec![PathSegment::AttrName("window".to_string())])),
+        None,
+    );
+    match query.group_by_exprs_opt.as_mut() {
+        Some(group_by) if group_by.all => {}
+        Some(group_by) => group_by.exprs.insert(0, window_ref),
+        None => query.group_by_exprs_opt = Some(ast::GroupByExpression::new(vec![window_ref], None)),
+    }
+
+    Ok(query)
+}
+
+//The columns of the equalities like "x.id = y.id" joined by "and", the key of each side without the name of the side
+fn parse_join_keys(
+    condition: &ast::Expression,
+    names: (&str, &str),
+    left_keys: &mut Vec<PathExpr>,
+    right_keys: &mut Vec<PathExpr>,
+) -> ParseResult<()> {
+    let side = |expr: &ast::Expression| match expr {
+        ast::Expression::Column(path_expr) => match path_expr.path_segments.split_first() {
+            Some((PathSegment::AttrName(name), key)) if !key.is_empty() => {
+                Some((name.clone(), PathExpr::new(key.to_vec())))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match condition {
+        ast::Expression::BinaryOperator(ast::BinaryOperator::And, l, r) => {
+            parse_join_keys(l, names, left_keys, right_keys)?;
+            parse_join_keys(r, names, left_keys, right_keys)
+        }
+        ast::Expression::BinaryOperator(ast::BinaryOperator::Equal, l, r) => match (side(l), side(r)) {
+            (Some((l_name, l_key)), Some((r_name, r_key))) if (l_name.as_str(), r_name.as_str()) == names => {
+                left_keys.push(l_key);
+                right_keys.push(r_key);
+                Ok(())
+            }
+            (Some((l_name, l_key)), Some((r_name, r_key))) if (r_name.as_str(), l_name.as_str()) == names => {
+                left_keys.push(r_key);
+                right_keys.push(l_key);
+                Ok(())
+            }
+            _ => Err(ParseError::InvalidJoin(format!(
+                "the condition compares a column of {} with a column of {}",
+                names.0, names.1
+            ))),
+        },
+        _ => Err(ParseError::InvalidJoin(
+            "the condition is the equalities of the columns joined by and".to_string(),
+        )),
+    }
+}
+
+//The sides of the join are named by their aliases, or else by their tables, e.g. "from a join b on a.id = b.id"
+fn parse_join(
+    join: &ast::Join,
+    table_references: &[TableReference],
+    data_source: common::DataSource,
+    join_data_source: common::DataSource,
+) -> ParseResult<types::Node> {
+    let side_name =
+        |table_reference: &TableReference, table_name: &str| match &table_reference.path_expr.path_segments[..] {
+            [PathSegment::AttrName(s)] if s == table_name && table_reference.at_clause.is_none() => {
+                Ok(table_reference.as_clause.clone().unwrap_or_else(|| s.clone()))
+            }
+            _ => Err(ParseError::InvalidJoin("only the whole tables are joined".to_string())),
+        };
+
+    let left_name = match table_references {
+        [table_reference] => side_name(table_reference, data_source.table_name())?,
+        _ => return Err(ParseError::InvalidJoin("only two tables are joined".to_string())),
+    };
+    let right_name = side_name(&join.table_reference, join_data_source.table_name())?;
+    if left_name == right_name {
+        return Err(ParseError::InvalidJoin(format!("both sides are named {}", left_name)));
+    }
+
+    let mut left_keys = Vec::new();
+    let mut right_keys = Vec::new();
+    parse_join_keys(
+        &join.condition,
+        (&left_name, &right_name),
+        &mut left_keys,
+        &mut right_keys,
+    )?;
+
+    let join = execution::types::Join::new(join.kind.clone(), left_name, right_name, left_keys, right_keys);
+    Ok(types::Node::Join(
+        join,
+        Box::new(types::Node::DataSource(data_source, Vec::new())),
+        Box::new(types::Node::DataSource(join_data_source, Vec::new())),
+    ))
+}
+
 pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::DataSource) -> ParseResult<types::Node> {
+    parse_select(query, data_source, None)
+}
+
+//The query joining the table of its join clause, which is read from the join data source
+pub(crate) fn parse_join_query(
+    query: ast::SelectStatement,
+    data_source: common::DataSource,
+    join_data_source: common::DataSource,
+) -> ParseResult<types::Node> {
+    parse_select(query, data_source, Some(join_data_source))
+}
+
+fn parse_select(
+    query: ast::SelectStatement,
+    data_source: common::DataSource,
+    join_data_source: Option<common::DataSource>,
+) -> ParseResult<types::Node> {
+    let query = resolve_select_references(expand_emit_every(query)?)?;
     let table_references = &query.table_references;
 
     let (file_format, table_name) = match &data_source {
-        common::DataSource::File(_, file_format, table_name) => (file_format.clone(), table_name.clone()),
-        common::DataSource::Stdin(file_format, table_name) => (file_format.clone(), table_name.clone()),
+        common::DataSource::File(_, file_format, table_name, _) => (file_format.clone(), table_name.clone()),
+        common::DataSource::Stdin(file_format, table_name, _) => (file_format.clone(), table_name.clone()),
+        common::DataSource::Command(_, file_format, table_name, _) => (file_format.clone(), table_name.clone()),
     };
 
     check_env(&table_name, table_references)?;
@@ -445,14 +1043,27 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
     };
     let bindings = to_bindings(&table_name, table_references);
 
-    let mut root = types::Node::DataSource(data_source, bindings);
+    let mut root = match (&query.join_opt, join_data_source) {
+        (None, _) => types::Node::DataSource(data_source, bindings),
+        (Some(join), Some(join_data_source)) => parse_join(join, table_references, data_source, join_data_source)?,
+        (Some(_), None) => return Err(ParseError::InvalidJoin("the joined table is not given".to_string())),
+    };
     let mut named_aggregates = Vec::new();
     let mut named_list: Vec<types::Named> = Vec::new();
     let mut non_aggregates: Vec<types::Named> = Vec::new();
     let mut group_by_vars: HashSet<String> = HashSet::default();
+    //The (column of the aggregated record, output name) of each select expression in the select order
+    let mut select_outputs: Option<Vec<(String, String)>> = Some(Vec::new());
+    let mut windows: Vec<execution::types::NamedWindow> = Vec::new();
+
+    let group_by_references: Vec<ast::GroupByReference> = match &query.group_by_exprs_opt {
+        Some(group_by) if group_by.all => expand_group_by_all(&parsing_context, &query.select_clause),
+        Some(group_by) => resolve_group_by_aliases(&parsing_context, &query.select_clause, group_by.exprs.clone()),
+        None => Vec::new(),
+    };
 
-    if let Some(group_by) = &query.group_by_exprs_opt {
-        for (position, r) in group_by.exprs.iter().enumerate() {
+    if query.group_by_exprs_opt.is_some() {
+        for (position, r) in group_by_references.iter().enumerate() {
             let e = parse_value_expression(&parsing_context, &r.column_expr)?;
             let named = match &*e {
                 types::Expression::Variable(path_expr) => {
@@ -493,7 +1104,21 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
         ast::SelectClause::SelectExpressions(select_exprs) => {
             if !select_exprs.is_empty() {
                 for (offset, select_expr) in select_exprs.iter().enumerate() {
-                    if let Ok(mut named_aggregate) = parse_aggregate(&parsing_context, select_expr) {
+                    if let Some(window) = parse_window(select_expr, offset)? {
+                        if let Some(outputs) = select_outputs.as_mut() {
+                            outputs.push((window.name.clone(), window.name.clone()));
+                        }
+                        windows.push(window);
+                    } else if let Ok(mut named_aggregate) = parse_aggregate(&parsing_context, select_expr) {
+                        //Name the aggregate by its position so that the output columns can be put in the select order
+                        if named_aggregate.name_opt.is_none() {
+                            named_aggregate.name_opt = Some(format!("_{}", offset + 1));
+                        }
+                        if let Some(outputs) = select_outputs.as_mut() {
+                            let name = named_aggregate.name_opt.clone().unwrap();
+                            outputs.push((name.clone(), name));
+                        }
+
                         match &named_aggregate.aggregate {
                             types::Aggregate::GroupAsAggregate(_) => {
                                 unreachable!();
@@ -557,10 +1182,24 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
                                                 }
                                             }
                                         }
-                                        _ => {
+                                        types::Expression::Constant(_) => {
                                             named_aggregates.push(named_aggregate.clone());
                                             named_list.push(named.clone());
                                         }
+                                        _ => {
+                                            //Evaluate the expression in the projection and sum up the projected column,
+                                            //since the fields it refers to are not available after the projection.
+                                            let s = format!("__sum_{}", offset);
+                                            let projected = types::Named::Expression(expr.clone(), Some(s.clone()));
+                                            let p = PathExpr::new(vec![PathSegment::AttrName(s)]);
+                                            let n = types::Named::Expression(
+                                                types::Expression::Variable(p),
+                                                opt_name.clone(),
+                                            );
+                                            named_aggregate.aggregate = types::Aggregate::Sum(n);
+                                            named_aggregates.push(named_aggregate);
+                                            named_list.push(projected);
+                                        }
                                     },
                                 }
                             }
@@ -585,28 +1224,112 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
                                 named_aggregates.push(named_aggregate.clone());
                                 named_list.push(named.clone());
                             }
-                            types::Aggregate::PercentileDisc(_, column_name, _) => {
+                            types::Aggregate::Rate(named) => {
+                                if let types::Named::Star = named {
+                                    return Err(ParseError::InvalidArguments("rate".to_string()));
+                                }
+                                named_aggregates.push(named_aggregate.clone());
+                                named_list.push(named.clone());
+                            }
+                            types::Aggregate::AnyValue(named) | types::Aggregate::Sample(named, _) => {
+                                if let types::Named::Star = named {
+                                    return Err(ParseError::InvalidArguments("sample".to_string()));
+                                }
+                                named_aggregates.push(named_aggregate.clone());
+                                named_list.push(named.clone());
+                            }
+                            types::Aggregate::ApproxTopK(named, _) => {
+                                if let types::Named::Star = named {
+                                    return Err(ParseError::InvalidArguments("approx_top_k".to_string()));
+                                }
+                                named_aggregates.push(named_aggregate.clone());
+                                named_list.push(named.clone());
+                            }
+                            types::Aggregate::Entropy(named) | types::Aggregate::Concentration(named, _) => {
+                                if let types::Named::Star = named {
+                                    return Err(ParseError::InvalidArguments("entropy".to_string()));
+                                }
+                                named_aggregates.push(named_aggregate.clone());
+                                named_list.push(named.clone());
+                            }
+                            types::Aggregate::Corr(named)
+                            | types::Aggregate::Covar(named)
+                            | types::Aggregate::MinBy(named)
+                            | types::Aggregate::MaxBy(named)
+                            | types::Aggregate::AvgWeighted(named) => {
+                                //The pair is evaluated in the projection like the predicate of ratio
+                                if let types::Named::Expression(expr, opt_name) = named {
+                                    let s = format!("__pair_{}", offset);
+                                    let projected = types::Named::Expression(expr.clone(), Some(s.clone()));
+                                    let p = PathExpr::new(vec![PathSegment::AttrName(s)]);
+                                    let n = types::Named::Expression(types::Expression::Variable(p), opt_name.clone());
+                                    named_list.push(projected);
+                                    named_aggregate.aggregate = match named_aggregate.aggregate {
+                                        types::Aggregate::Corr(_) => types::Aggregate::Corr(n),
+                                        types::Aggregate::Covar(_) => types::Aggregate::Covar(n),
+                                        types::Aggregate::MinBy(_) => types::Aggregate::MinBy(n),
+                                        types::Aggregate::AvgWeighted(_) => types::Aggregate::AvgWeighted(n),
+                                        _ => types::Aggregate::MaxBy(n),
+                                    };
+                                    named_aggregates.push(named_aggregate);
+                                }
+                            }
+                            types::Aggregate::Ratio(named) => {
+                                //The predicate is evaluated in the projection, the aggregate takes the projected column.
+                                if let types::Named::Expression(expr, opt_name) = named {
+                                    let s = format!("__ratio_{}", offset);
+                                    let projected = types::Named::Expression(expr.clone(), Some(s.clone()));
+                                    let p = PathExpr::new(vec![PathSegment::AttrName(s)]);
+                                    let n = types::Named::Expression(types::Expression::Variable(p), opt_name.clone());
+                                    named_list.push(projected);
+                                    named_aggregate.aggregate = types::Aggregate::Ratio(n);
+                                    named_aggregates.push(named_aggregate);
+                                }
+                            }
+                            types::Aggregate::PercentileDisc(_, column_name, _, weight_opt) => {
                                 named_aggregates.push(named_aggregate.clone());
                                 named_list.push(types::Named::Expression(
                                     types::Expression::Variable(column_name.clone()),
                                     Some(column_name.unwrap_last()),
-                                ))
+                                ));
+                                if let Some(weight) = weight_opt {
+                                    named_list.push(types::Named::Expression(
+                                        types::Expression::Variable(weight.clone()),
+                                        Some(weight.unwrap_last()),
+                                    ));
+                                }
                             }
-                            types::Aggregate::ApproxPercentile(_, column_name, _) => {
+                            types::Aggregate::ApproxPercentile(_, column_name, _, weight_opt) => {
                                 named_aggregates.push(named_aggregate.clone());
                                 named_list.push(types::Named::Expression(
                                     types::Expression::Variable(column_name.clone()),
                                     Some(column_name.unwrap_last()),
-                                ))
+                                ));
+                                if let Some(weight) = weight_opt {
+                                    named_list.push(types::Named::Expression(
+                                        types::Expression::Variable(weight.clone()),
+                                        Some(weight.unwrap_last()),
+                                    ));
+                                }
                             }
                         }
                     } else {
-                        let named = *parse_expression(&parsing_context, select_expr)?;
+                        let named = match *parse_expression(&parsing_context, select_expr)? {
+                            types::Named::Expression(expr, None) => {
+                                types::Named::Expression(expr, Some(format!("_{}", offset + 1)))
+                            }
+                            named => named,
+                        };
 
                         if query.group_by_exprs_opt.is_some() {
                             if !check_group_by_vars(&named, &group_by_vars) {
                                 return Err(ParseError::GroupByFieldsMismatch);
                             }
+
+                            match (select_outputs.as_mut(), group_by_output(&named)) {
+                                (Some(outputs), Some(output)) => outputs.push(output),
+                                _ => select_outputs = None,
+                            }
                         } else if let Some(group_as_clause) = query
                             .group_by_exprs_opt
                             .as_ref()
@@ -620,6 +1343,7 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
                                 ));
                             }
                         } else {
+                            select_outputs = None;
                             non_aggregates.push(named.clone());
                             named_list.push(named.clone());
                         }
@@ -639,10 +1363,14 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
         root = types::Node::Filter(filter_formula, Box::new(root));
     }
 
+    //The projection back to the select order, after the window functions if any since they add the columns
+    let mut projection: Option<Vec<types::Named>> = None;
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut group_keys: Vec<String> = Vec::new();
     if !named_aggregates.is_empty() {
-        if let Some(group_by) = query.group_by_exprs_opt {
-            let fields: Vec<PathExpr> = group_by
-                .exprs
+        let mut grouped_columns: Vec<String> = Vec::new();
+        if query.group_by_exprs_opt.is_some() {
+            let fields: Vec<PathExpr> = group_by_references
                 .iter()
                 .enumerate()
                 .map(|(position, r)| {
@@ -678,16 +1406,40 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
                 return Err(ParseError::GroupByFieldsMismatch);
             }
 
-            root = types::Node::GroupBy(fields, named_aggregates, Box::new(root));
+            grouped_columns.extend(fields.iter().map(|f| f.unwrap_last()));
+            group_keys = grouped_columns.clone();
+            root = types::Node::GroupBy(fields, named_aggregates.clone(), Box::new(root));
         } else {
             let fields = Vec::new();
-            root = types::Node::GroupBy(fields, named_aggregates, Box::new(root));
+            root = types::Node::GroupBy(fields, named_aggregates.clone(), Box::new(root));
         }
 
         if let Some(having_expr) = query.having_expr_opt {
             let filter_formula = parse_logic(&parsing_context, &having_expr.expr)?;
             root = types::Node::Filter(filter_formula, Box::new(root));
         }
+
+        //The group by keys come first in the aggregated records, put the columns back in the select order
+        grouped_columns.extend(named_aggregates.iter().filter_map(|a| a.name_opt.clone()));
+        if let Some(outputs) = select_outputs {
+            let is_in_order = outputs.len() == grouped_columns.len()
+                && outputs
+                    .iter()
+                    .zip(grouped_columns.iter())
+                    .all(|((column, name), grouped)| column == grouped && name == grouped);
+            if !is_in_order {
+                renames.extend(outputs.iter().map(|(column, name)| (name.clone(), column.clone())));
+                projection = Some(
+                    outputs
+                        .into_iter()
+                        .map(|(column, name)| {
+                            let path_expr = PathExpr::new(vec![PathSegment::AttrName(column)]);
+                            types::Named::Expression(types::Expression::Variable(path_expr), Some(name))
+                        })
+                        .collect(),
+                );
+            }
+        }
     } else {
         //sanity check if there is a group by statement
         if let Some(group_by_exprs) = query.group_by_exprs_opt {
@@ -705,18 +1457,97 @@ pub(crate) fn parse_query(query: ast::SelectStatement, data_source: common::Data
         }
     }
 
+    if let Some(gap_fill) = &query.gap_fill_opt {
+        //The interval of the time bucket in the group by is the step between the rows
+        let bucket = group_by_references
+            .iter()
+            .zip(group_keys.iter())
+            .find_map(|(r, key)| match &r.column_expr {
+                ast::Expression::FuncCall(name, args, _) if name == "time_bucket" => match args.first() {
+                    Some(ast::SelectExpression::Expression(e, _)) => match &**e {
+                        ast::Expression::Value(ast::Value::StringLiteral(s)) => Some((key.clone(), s.clone())),
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            });
+        let (time, interval) = match bucket {
+            Some((time, s)) => match common::parse_time_interval(&s).ok().and_then(|i| i.duration()) {
+                Some(interval) if interval > chrono::Duration::zero() => (time, interval),
+                _ => {
+                    return Err(ParseError::InvalidGapFill);
+                }
+            },
+            None => {
+                return Err(ParseError::InvalidGapFill);
+            }
+        };
+        let fill = match &gap_fill.value_opt {
+            Some(value) => match *parse_value(value)? {
+                types::Expression::Constant(v) => v,
+                _ => common::Value::Null,
+            },
+            None => common::Value::Null,
+        };
+        let partition_keys = group_keys
+            .iter()
+            .filter(|k| **k != time)
+            .map(|k| PathExpr::new(vec![PathSegment::AttrName(k.clone())]))
+            .collect();
+        let time = PathExpr::new(vec![PathSegment::AttrName(time)]);
+        root = types::Node::GapFill(time, interval, partition_keys, fill, Box::new(root));
+    }
+
+    if windows.is_empty() {
+        if let Some(projection) = projection.take() {
+            root = types::Node::Map(projection, Box::new(root));
+        }
+    }
+
+    let mut time_column: Option<PathExpr> = None;
     if let Some(order_by_expr) = query.order_by_expr_opt {
         let mut column_names = Vec::new();
         let mut orderings = Vec::new();
         for ordering_term in order_by_expr.ordering_terms {
-            column_names.push(ordering_term.column_name.clone());
+            //Ordered before the projection back to the select order, by the columns of the aggregated records
+            let column_name = match renames.get(&ordering_term.column_name.unwrap_last()) {
+                Some(column) if !windows.is_empty() => PathExpr::new(vec![PathSegment::AttrName(column.clone())]),
+                _ => ordering_term.column_name.clone(),
+            };
+            column_names.push(column_name);
             let ordering = parse_ordering(ordering_term.ordering)?;
             orderings.push(ordering);
         }
 
+        time_column = column_names.first().cloned();
         root = types::Node::OrderBy(column_names, orderings, Box::new(root));
     }
 
+    if let Some(window) = windows.first() {
+        //The rows of the other group by keys, e.g. per host, are the partitions over the time buckets
+        let time = match time_column {
+            Some(time) if group_keys.contains(&time.unwrap_last()) => time,
+            _ => {
+                return Err(ParseError::InvalidWindow(window.function.to_string()));
+            }
+        };
+        let partition_keys = group_keys
+            .iter()
+            .filter(|k| **k != time.unwrap_last())
+            .map(|k| PathExpr::new(vec![PathSegment::AttrName(k.clone())]))
+            .collect();
+        root = types::Node::Window(windows, partition_keys, time, Box::new(root));
+
+        if let Some(projection) = projection {
+            root = types::Node::Map(projection, Box::new(root));
+        }
+    }
+
+    if let Some(keys) = query.distinct_on_opt {
+        root = types::Node::DistinctOn(keys, Box::new(root));
+    }
+
     if let Some(limit_expr) = query.limit_expr_opt {
         root = types::Node::Limit(limit_expr.row_count, Box::new(root));
     }
@@ -745,48 +1576,24 @@ fn is_match_group_by_fields(variables: &[ast::PathExpr], named_list: &[types::Na
                 }
             }
             types::Named::Star => {
-                if file_format == "elb" {
-                    for field_name in execution::datasource::ClassicLoadBalancerLogField::field_names().into_iter() {
-                        b.insert(PathExpr::new(vec![PathSegment::AttrName(field_name.clone())]));
-                    }
-                } else if file_format == "alb" {
-                    for field_name in execution::datasource::ApplicationLoadBalancerLogField::field_names().into_iter()
-                    {
-                        b.insert(PathExpr::new(vec![PathSegment::AttrName(field_name.clone())]));
-                    }
-                } else if file_format == "squid" {
-                    for field_name in execution::datasource::SquidLogField::field_names().into_iter() {
-                        b.insert(PathExpr::new(vec![PathSegment::AttrName(field_name.clone())]));
-                    }
-                } else if file_format == "s3" {
-                    for field_name in execution::datasource::S3Field::field_names().into_iter() {
-                        b.insert(PathExpr::new(vec![PathSegment::AttrName(field_name.clone())]));
-                    }
-                } else {
-                    unreachable!();
+                let field_names = match execution::datasource::field_names_of(file_format) {
+                    Some(field_names) => field_names,
+                    None => unreachable!(),
+                };
+                for field_name in field_names.iter() {
+                    b.insert(PathExpr::new(vec![PathSegment::AttrName(field_name.clone())]));
                 }
             }
         }
     }
 
-    if a.len() != b.len() {
-        false
-    } else {
-        let mut a_iter = a.iter();
-        let mut b_iter = b.iter();
-        while let (Some(aa), Some(bb)) = (a_iter.next(), b_iter.next()) {
-            if aa != bb {
-                return false;
-            }
-        }
-
-        true
-    }
+    a == b
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::execution::datasource::ReaderOptions;
     use crate::syntax::ast::{PathSegment, SelectClause};
 
     #[test]
@@ -824,6 +1631,16 @@ mod test {
         };
         let ans = parse_logic_expression(&parsing_context, &before).unwrap();
         assert_eq!(expected, ans);
+
+        let path_expr = ast::PathExpr::new(vec![ast::PathSegment::AttrName("a".to_string())]);
+        let before = ast::Expression::Column(path_expr.clone());
+        let expected = Box::new(types::Expression::Logic(Box::new(types::Formula::Predicate(
+            types::Relation::Equal,
+            Box::new(types::Expression::Variable(path_expr)),
+            Box::new(types::Expression::Constant(common::Value::Boolean(true))),
+        ))));
+        let ans = parse_logic_expression(&parsing_context, &before).unwrap();
+        assert_eq!(expected, ans);
     }
 
     #[test]
@@ -862,6 +1679,45 @@ mod test {
         assert_eq!(expected, ans);
     }
 
+    #[test]
+    fn test_parse_action_function_with_default_actions() {
+        let parsing_context = ParsingContext {
+            table_name: "a".to_string(),
+        };
+        let actions = types::Named::Expression(
+            types::Expression::Variable(PathExpr::new(vec![PathSegment::AttrName(
+                "action_executed".to_string(),
+            )])),
+            Some("action_executed".to_string()),
+        );
+
+        let before = ast::Expression::FuncCall("was_redirected".to_string(), vec![], None);
+        let expected = Box::new(types::Expression::Function(
+            "was_redirected".to_string(),
+            vec![actions.clone()],
+        ));
+        let ans = parse_value_expression(&parsing_context, &before).unwrap();
+        assert_eq!(expected, ans);
+
+        let waf = ast::SelectExpression::Expression(
+            Box::new(ast::Expression::Value(ast::Value::StringLiteral("waf".to_string()))),
+            None,
+        );
+        let before = ast::Expression::FuncCall("has_action".to_string(), vec![waf], None);
+        let expected = Box::new(types::Expression::Function(
+            "has_action".to_string(),
+            vec![
+                actions,
+                types::Named::Expression(
+                    types::Expression::Constant(common::Value::String("waf".to_string())),
+                    None,
+                ),
+            ],
+        ));
+        let ans = parse_value_expression(&parsing_context, &before).unwrap();
+        assert_eq!(expected, ans);
+    }
+
     #[test]
     fn test_parse_aggregate() {
         let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
@@ -941,7 +1797,7 @@ mod test {
             None,
             None,
         );
-        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string());
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
 
         let filtered_formula = Box::new(types::Formula::Predicate(
             types::Relation::Equal,
@@ -965,7 +1821,7 @@ mod test {
                     types::Named::Expression(types::Expression::Variable(path_expr_b.clone()), Some("b".to_string())),
                 ],
                 Box::new(types::Node::DataSource(
-                    common::DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                    common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                     bindings,
                 )),
             )),
@@ -1002,7 +1858,7 @@ mod test {
             None,
             None,
         );
-        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string());
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
 
         let filtered_formula = Box::new(types::Formula::Predicate(
             types::Relation::Equal,
@@ -1018,7 +1874,7 @@ mod test {
                     types::Named::Expression(types::Expression::Variable(path_expr_b.clone()), Some("b".to_string())),
                 ],
                 Box::new(types::Node::DataSource(
-                    common::DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                    common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                     vec![],
                 )),
             )),
@@ -1028,6 +1884,246 @@ mod test {
         assert_eq!(expected, ans);
     }
 
+    #[test]
+    fn test_parse_query_with_group_by_all() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let (_, explicit) = crate::syntax::parser::select_query(
+            r#"select a, time_bucket("5 seconds", t) as x, count(*) as c from it group by a, time_bucket("5 seconds", t) as x"#,
+        )
+        .unwrap();
+        let (_, all) = crate::syntax::parser::select_query(
+            r#"select a, time_bucket("5 seconds", t) as x, count(*) as c from it group by all"#,
+        )
+        .unwrap();
+
+        let expected = parse_query(explicit, data_source.clone()).unwrap();
+        let ans = parse_query(all, data_source).unwrap();
+        assert_eq!(expected, ans);
+    }
+
+    #[test]
+    fn test_parse_query_group_by_predicate_alias() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let (_, explicit) =
+            crate::syntax::parser::select_query(r#"select e, count(*) as c from it group by a >= 500 as e"#).unwrap();
+        let (_, aliased) =
+            crate::syntax::parser::select_query(r#"select a >= 500 as e, count(*) as c from it group by e"#).unwrap();
+
+        let expected = parse_query(explicit, data_source.clone()).unwrap();
+        let ans = parse_query(aliased, data_source).unwrap();
+        assert_eq!(expected, ans);
+    }
+
+    #[test]
+    fn test_parse_query_output_in_select_order() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let output_names = |query_str: &str| -> Option<Vec<String>> {
+            let (_, query) = crate::syntax::parser::select_query(query_str).unwrap();
+            match parse_query(query, data_source.clone()).unwrap() {
+                types::Node::Map(named_list, _) => Some(
+                    named_list
+                        .into_iter()
+                        .filter_map(|named| match named {
+                            types::Named::Expression(_, name) => name,
+                            types::Named::Star => None,
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            Some(vec!["c".to_string(), "x".to_string(), "_3".to_string()]),
+            output_names("select count(*) as c, b as x, sum(a) from it group by b")
+        );
+        assert_eq!(
+            Some(vec!["b".to_string(), "y".to_string(), "_3".to_string()]),
+            output_names("select b, a as y, a + 1 from it")
+        );
+        //Already in the select order, no projection is needed
+        assert_eq!(None, output_names("select b, count(*) from it group by b"));
+    }
+
+    #[test]
+    fn test_parse_query_with_order_by_position_and_alias() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let (_, by_name) = crate::syntax::parser::select_query(
+            r#"select a as x, count(*) as _2 from it group by a having a = 1 order by _2 desc, x asc"#,
+        )
+        .unwrap();
+        let (_, by_position) = crate::syntax::parser::select_query(
+            r#"select a as x, count(*) from it group by a having x = 1 order by 2 desc, x asc"#,
+        )
+        .unwrap();
+
+        let expected = parse_query(by_name, data_source.clone()).unwrap();
+        let ans = parse_query(by_position, data_source.clone()).unwrap();
+        assert_eq!(expected, ans);
+
+        let (_, out_of_range) = crate::syntax::parser::select_query(r#"select a from it order by 2 desc"#).unwrap();
+        let ans = parse_query(out_of_range, data_source);
+        assert_eq!(Err(ParseError::InvalidOrderByPosition(2)), ans);
+    }
+
+    #[test]
+    fn test_parse_join_query() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let join_data_source =
+            common::DataSource::Stdin("jsonl".to_string(), "other".to_string(), ReaderOptions::default());
+        let path =
+            |segments: &[&str]| PathExpr::new(segments.iter().map(|s| PathSegment::AttrName(s.to_string())).collect());
+
+        let (_, query) = crate::syntax::parser::select_query(
+            r#"select * from it as x left join other on other.id = x.id and x.a.b = other.b"#,
+        )
+        .unwrap();
+        let ans = parse_join_query(query, data_source.clone(), join_data_source.clone()).unwrap();
+        let join = execution::types::Join::new(
+            ast::JoinKind::Left,
+            "x".to_string(),
+            "other".to_string(),
+            vec![path(&["id"]), path(&["a", "b"])],
+            vec![path(&["id"]), path(&["b"])],
+        );
+        let expected = types::Node::Map(
+            vec![types::Named::Star],
+            Box::new(types::Node::Join(
+                join,
+                Box::new(types::Node::DataSource(data_source.clone(), Vec::new())),
+                Box::new(types::Node::DataSource(join_data_source.clone(), Vec::new())),
+            )),
+        );
+        assert_eq!(expected, ans);
+
+        let (_, query) =
+            crate::syntax::parser::select_query(r#"select * from it join other on it.id < other.id"#).unwrap();
+        let ans = parse_join_query(query, data_source.clone(), join_data_source.clone());
+        assert!(matches!(ans, Err(ParseError::InvalidJoin(_))));
+
+        let (_, query) =
+            crate::syntax::parser::select_query(r#"select * from it join other on it.id = it.id"#).unwrap();
+        let ans = parse_join_query(query, data_source, join_data_source);
+        assert!(matches!(ans, Err(ParseError::InvalidJoin(_))));
+    }
+
+    #[test]
+    fn test_parse_query_with_window() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+        let (_, query) = crate::syntax::parser::select_query(
+            r#"select host as h, t, count(*) as c, delta(c) as d from it group by host, time_bucket("1 minute", ts) as t order by t asc"#,
+        )
+        .unwrap();
+
+        match parse_query(query, data_source.clone()).unwrap() {
+            types::Node::Map(_, window) => match *window {
+                types::Node::Window(windows, partition_keys, time, _) => {
+                    let expected = vec![execution::types::NamedWindow::new(
+                        execution::types::WindowFunction::Delta,
+                        path("c"),
+                        "d".to_string(),
+                    )];
+                    assert_eq!(expected, windows);
+                    assert_eq!(vec![path("host")], partition_keys);
+                    assert_eq!(path("t"), time);
+                }
+                node => panic!("unexpected {:?}", node),
+            },
+            node => panic!("unexpected {:?}", node),
+        }
+
+        for query_str in [
+            r#"select t, count(*) as c, rate_per_sec(c) as r from it group by time_bucket("1 minute", ts) as t"#,
+            r#"select count(*) as c, delta(c) as d from it order by c asc"#,
+        ]
+        .iter()
+        {
+            let (_, query) = crate::syntax::parser::select_query(query_str).unwrap();
+            assert!(matches!(
+                parse_query(query, data_source.clone()),
+                Err(ParseError::InvalidWindow(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_query_with_gap_fill() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let path = |s: &str| PathExpr::new(vec![PathSegment::AttrName(s.to_string())]);
+        let (_, query) = crate::syntax::parser::select_query(
+            r#"select host, t, count(*) as c from it group by host, time_bucket("5 minutes", ts) as t gap fill with 0"#,
+        )
+        .unwrap();
+
+        match parse_query(query, data_source.clone()).unwrap() {
+            types::Node::GapFill(time, interval, partition_keys, fill, _) => {
+                assert_eq!(path("t"), time);
+                assert_eq!(chrono::Duration::minutes(5), interval);
+                assert_eq!(vec![path("host")], partition_keys);
+                assert_eq!(common::Value::Int(0), fill);
+            }
+            node => panic!("unexpected {:?}", node),
+        }
+
+        for query_str in [
+            r#"select host, count(*) as c from it group by host gap fill"#,
+            r#"select t, count(*) as c from it group by time_bucket("1 month", ts) as t gap fill"#,
+        ]
+        .iter()
+        {
+            let (_, query) = crate::syntax::parser::select_query(query_str).unwrap();
+            assert_eq!(Err(ParseError::InvalidGapFill), parse_query(query, data_source.clone()));
+        }
+    }
+
+    #[test]
+    fn test_parse_query_with_count_if() {
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
+        let (_, query) = crate::syntax::parser::select_query(r#"select count_if(a = 1) as c from it"#).unwrap();
+
+        let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
+        let path_expr_sum = PathExpr::new(vec![PathSegment::AttrName("__sum_0".to_string())]);
+        let branch = types::Expression::Branch(
+            Box::new(types::Formula::Predicate(
+                types::Relation::Equal,
+                Box::new(types::Expression::Variable(path_expr_a)),
+                Box::new(types::Expression::Constant(common::Value::Int(1))),
+            )),
+            Box::new(types::Expression::Constant(common::Value::Int(1))),
+            Some(Box::new(types::Expression::Constant(common::Value::Int(0)))),
+        );
+        let expected = types::Node::GroupBy(
+            vec![],
+            vec![types::NamedAggregate::new(
+                types::Aggregate::Sum(types::Named::Expression(
+                    types::Expression::Variable(path_expr_sum),
+                    None,
+                )),
+                Some("c".to_string()),
+            )],
+            Box::new(types::Node::Map(
+                vec![types::Named::Expression(branch, Some("__sum_0".to_string()))],
+                Box::new(types::Node::DataSource(data_source.clone(), vec![])),
+            )),
+        );
+
+        let ans = parse_query(query, data_source.clone()).unwrap();
+        assert_eq!(expected, ans);
+
+        let (_, query) = crate::syntax::parser::select_query(r#"select sum_if(a) as c from it"#).unwrap();
+        let ans = parse_aggregate(
+            &ParsingContext {
+                table_name: "it".to_string(),
+            },
+            &match query.select_clause {
+                SelectClause::SelectExpressions(select_exprs) => select_exprs[0].clone(),
+                _ => unreachable!(),
+            },
+        );
+        assert_eq!(Err(ParseError::InvalidArguments("sum_if".to_string())), ans);
+    }
+
     #[test]
     fn test_parse_query_with_group_by() {
         let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
@@ -1065,7 +2161,7 @@ mod test {
             None,
             None,
         );
-        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string());
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
 
         let filtered_formula = Box::new(types::Formula::Predicate(
             types::Relation::Equal,
@@ -1088,7 +2184,7 @@ mod test {
                     types::Named::Expression(types::Expression::Variable(path_expr_a.clone()), Some("a".to_string())),
                 ],
                 Box::new(types::Node::DataSource(
-                    common::DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                    common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                     vec![],
                 )),
             )),
@@ -1099,11 +2195,19 @@ mod test {
                 types::Expression::Variable(path_expr_a),
                 Some("a".to_string()),
             )),
-            None,
+            Some("_1".to_string()),
         )];
 
         let fields = vec![path_expr_b.clone()];
-        let expected = types::Node::GroupBy(fields, named_aggregates, Box::new(filter));
+        let group_by = types::Node::GroupBy(fields, named_aggregates, Box::new(filter));
+        let path_expr_1 = PathExpr::new(vec![PathSegment::AttrName("_1".to_string())]);
+        let expected = types::Node::Map(
+            vec![
+                types::Named::Expression(types::Expression::Variable(path_expr_1), Some("_1".to_string())),
+                types::Named::Expression(types::Expression::Variable(path_expr_b), Some("b".to_string())),
+            ],
+            Box::new(group_by),
+        );
 
         let ans = parse_query(before, data_source).unwrap();
         assert_eq!(expected, ans);
@@ -1138,7 +2242,7 @@ mod test {
             None,
             None,
         );
-        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string());
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
         let ans = parse_query(before, data_source);
         let expected = Err(ParseError::GroupByFieldsMismatch);
         assert_eq!(expected, ans);
@@ -1179,7 +2283,7 @@ mod test {
             None,
             None,
         );
-        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string());
+        let data_source = common::DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default());
         let ans = parse_query(before, data_source);
         let expected = Err(ParseError::GroupByFieldsMismatch);
         assert_eq!(expected, ans);
@@ -1,20 +1,31 @@
 use crate::common::types as common;
 use crate::common::types::{DataSource, VariableName};
+use crate::execution::stream::GroupLimit;
 use crate::execution::types as execution;
 use crate::syntax::ast;
 use crate::syntax::ast::PathExpr;
 use ordered_float::OrderedFloat;
+use std::fmt;
 use std::result;
 
 pub(crate) type PhysicalResult<T> = result::Result<T, PhysicalPlanError>;
 
-#[derive(Fail, PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug)]
 pub enum PhysicalPlanError {
-    #[fail(display = "Type Mismatch")]
     #[allow(dead_code)]
     TypeMisMatch,
 }
 
+impl fmt::Display for PhysicalPlanError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhysicalPlanError::TypeMisMatch => fmt.write_str("Type Mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for PhysicalPlanError {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum Node {
     DataSource(DataSource, Vec<common::Binding>),
@@ -23,6 +34,10 @@ pub(crate) enum Node {
     GroupBy(Vec<ast::PathExpr>, Vec<NamedAggregate>, Box<Node>),
     Limit(u32, Box<Node>),
     OrderBy(Vec<PathExpr>, Vec<Ordering>, Box<Node>),
+    DistinctOn(Vec<PathExpr>, Box<Node>),
+    Window(Vec<execution::NamedWindow>, Vec<PathExpr>, PathExpr, Box<Node>),
+    GapFill(PathExpr, chrono::Duration, Vec<PathExpr>, common::Value, Box<Node>),
+    Join(execution::Join, Box<Node>, Box<Node>),
 }
 
 impl Node {
@@ -74,7 +89,12 @@ impl Node {
                 let (child, child_variables) = source.physical(physical_plan_creator)?;
                 let return_variables = common::merge(&variables, &child_variables);
 
-                let node = execution::Node::GroupBy(fields.clone(), physical_aggregates, child);
+                let node = execution::Node::GroupBy(
+                    fields.clone(),
+                    physical_aggregates,
+                    physical_plan_creator.group_limit.clone(),
+                    child,
+                );
 
                 Ok((Box::new(node), return_variables))
             }
@@ -99,6 +119,66 @@ impl Node {
                 let node = execution::Node::OrderBy(column_names.clone(), physical_orderings, child);
                 Ok((Box::new(node), return_variables))
             }
+            Node::DistinctOn(keys, source) => {
+                let (child, child_variables) = source.physical(physical_plan_creator)?;
+                let node = execution::Node::DistinctOn(keys.clone(), child);
+                Ok((Box::new(node), child_variables))
+            }
+            Node::Window(windows, partition_keys, time, source) => {
+                let (child, child_variables) = source.physical(physical_plan_creator)?;
+                let node = execution::Node::Window(windows.clone(), partition_keys.clone(), time.clone(), child);
+                Ok((Box::new(node), child_variables))
+            }
+            Node::GapFill(time, interval, partition_keys, fill, source) => {
+                let (child, child_variables) = source.physical(physical_plan_creator)?;
+                let node =
+                    execution::Node::GapFill(time.clone(), *interval, partition_keys.clone(), fill.clone(), child);
+                Ok((Box::new(node), child_variables))
+            }
+            Node::Join(join, left, right) => {
+                let (left_child, left_variables) = left.physical(physical_plan_creator)?;
+                let (right_child, right_variables) = right.physical(physical_plan_creator)?;
+                let return_variables = common::merge(&left_variables, &right_variables);
+                let node = execution::Node::Join(join.clone(), left_child, right_child);
+                Ok((Box::new(node), return_variables))
+            }
+        }
+    }
+
+    //The names of the output columns in order, None if they are only known when reading, e.g. the star over jsonl
+    pub(crate) fn output_columns(&self) -> Option<Vec<String>> {
+        match self {
+            Node::DataSource(data_source, _) => crate::execution::datasource::schema_of(data_source.file_format())
+                .map(|schema| schema.into_iter().map(|(field, _)| field).collect()),
+            Node::Filter(_, source)
+            | Node::Limit(_, source)
+            | Node::OrderBy(_, _, source)
+            | Node::DistinctOn(_, source)
+            | Node::GapFill(_, _, _, _, source) => source.output_columns(),
+            Node::Map(named_list, source) => {
+                let mut columns = Vec::new();
+                for (idx, named) in named_list.iter().enumerate() {
+                    match named {
+                        Named::Expression(_, Some(name)) => columns.push(name.clone()),
+                        Named::Expression(_, None) => columns.push(format!("_{}", idx)),
+                        Named::Star => columns.extend(source.output_columns()?),
+                    }
+                }
+                Some(columns)
+            }
+            Node::GroupBy(fields, named_aggregates, _) => {
+                let mut columns: Vec<String> = fields.iter().map(|field| field.unwrap_last()).collect();
+                for named_aggregate in named_aggregates.iter() {
+                    columns.push(named_aggregate.name_opt.clone()?);
+                }
+                Some(columns)
+            }
+            Node::Window(windows, _, _, source) => {
+                let mut columns = source.output_columns()?;
+                columns.extend(windows.iter().map(|window| window.name.clone()));
+                Some(columns)
+            }
+            Node::Join(join, _, _) => Some(vec![join.left_name.clone(), join.right_name.clone()]),
         }
     }
 }
@@ -273,6 +353,7 @@ impl Formula {
 pub(crate) struct PhysicalPlanCreator {
     counter: u32,
     data_source: DataSource,
+    group_limit: Option<GroupLimit>,
 }
 
 impl PhysicalPlanCreator {
@@ -280,9 +361,15 @@ impl PhysicalPlanCreator {
         PhysicalPlanCreator {
             counter: 0,
             data_source,
+            group_limit: None,
         }
     }
 
+    pub(crate) fn group_limit(mut self, group_limit: Option<GroupLimit>) -> Self {
+        self.group_limit = group_limit;
+        self
+    }
+
     pub(crate) fn new_constant_name(&mut self) -> VariableName {
         let constant_name = format!("const_{:09}", self.counter);
         self.counter += 1;
@@ -323,9 +410,21 @@ pub(crate) enum Aggregate {
     Min(Named),
     Sum(Named),
     ApproxCountDistinct(Named),
-    PercentileDisc(OrderedFloat<f32>, ast::PathExpr, Ordering),
-    ApproxPercentile(OrderedFloat<f32>, ast::PathExpr, Ordering),
+    Rate(Named),
+    Ratio(Named),
+    PercentileDisc(OrderedFloat<f32>, ast::PathExpr, Ordering, Option<ast::PathExpr>),
+    ApproxPercentile(OrderedFloat<f32>, ast::PathExpr, Ordering, Option<ast::PathExpr>),
     GroupAsAggregate(Named),
+    AnyValue(Named),
+    Sample(Named, usize),
+    Corr(Named),
+    Covar(Named),
+    Entropy(Named),
+    Concentration(Named, usize),
+    ApproxTopK(Named, usize),
+    MinBy(Named),
+    MaxBy(Named),
+    AvgWeighted(Named),
 }
 
 impl Aggregate {
@@ -481,23 +580,172 @@ impl Aggregate {
                     execution::Aggregate::ApproxCountDistinct(approx_count_distinct_aggregate, physical_named);
                 Ok((aggregate, variables))
             }
-            Aggregate::PercentileDisc(percentile, column_name, ordering) => {
+            Aggregate::Rate(named) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let rate_aggregate = execution::RateAggregate::new();
+                let aggregate = execution::Aggregate::Rate(rate_aggregate, physical_named);
+                Ok((aggregate, variables))
+            }
+            Aggregate::Ratio(named) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let ratio_aggregate = execution::RatioAggregate::new();
+                let aggregate = execution::Aggregate::Ratio(ratio_aggregate, physical_named);
+                Ok((aggregate, variables))
+            }
+            Aggregate::ApproxTopK(named, k) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let approx_top_k_aggregate = execution::ApproxTopKAggregate::new(*k);
+                let aggregate = execution::Aggregate::ApproxTopK(approx_top_k_aggregate, physical_named);
+                Ok((aggregate, variables))
+            }
+            Aggregate::Entropy(named) | Aggregate::Concentration(named, _) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let aggregate = match self {
+                    Aggregate::Concentration(_, k) => {
+                        execution::Aggregate::Concentration(execution::FrequencyAggregate::new(*k), physical_named)
+                    }
+                    _ => execution::Aggregate::Entropy(execution::FrequencyAggregate::new(0), physical_named),
+                };
+                Ok((aggregate, variables))
+            }
+            Aggregate::Corr(named) | Aggregate::Covar(named) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let covariance_aggregate = execution::CovarianceAggregate::new();
+                let aggregate = match self {
+                    Aggregate::Corr(_) => execution::Aggregate::Corr(covariance_aggregate, physical_named),
+                    _ => execution::Aggregate::Covar(covariance_aggregate, physical_named),
+                };
+                Ok((aggregate, variables))
+            }
+            Aggregate::AvgWeighted(named) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let avg_weighted_aggregate = execution::AvgWeightedAggregate::new();
+                let aggregate = execution::Aggregate::AvgWeighted(avg_weighted_aggregate, physical_named);
+                Ok((aggregate, variables))
+            }
+            Aggregate::MinBy(named) | Aggregate::MaxBy(named) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let aggregate = match self {
+                    Aggregate::MinBy(_) => {
+                        execution::Aggregate::MinBy(execution::ExtremeByAggregate::new(false), physical_named)
+                    }
+                    _ => execution::Aggregate::MaxBy(execution::ExtremeByAggregate::new(true), physical_named),
+                };
+                Ok((aggregate, variables))
+            }
+            Aggregate::AnyValue(named) | Aggregate::Sample(named, _) => {
+                let mut variables = common::empty_variables();
+
+                let physical_named = match named {
+                    Named::Expression(expr, name) => {
+                        let (physical_expr, expr_variables) = expr.physical(physical_plan_creator)?;
+                        variables = common::merge(&variables, &expr_variables);
+                        execution::Named::Expression(*physical_expr, name.clone())
+                    }
+                    Named::Star => execution::Named::Star,
+                };
+
+                let aggregate = match self {
+                    Aggregate::Sample(_, k) => {
+                        execution::Aggregate::Sample(execution::SampleAggregate::new(*k), physical_named)
+                    }
+                    _ => execution::Aggregate::AnyValue(execution::SampleAggregate::new(1), physical_named),
+                };
+                Ok((aggregate, variables))
+            }
+            Aggregate::PercentileDisc(percentile, column_name, ordering, weight_opt) => {
                 let variables = common::empty_variables();
                 let physical_ordering = ordering.physical()?;
 
                 let percentile_disc_aggregate = execution::PercentileDiscAggregate::new(*percentile, physical_ordering);
-                let aggregate =
-                    execution::Aggregate::PercentileDisc(percentile_disc_aggregate, column_name.unwrap_last());
+                let aggregate = execution::Aggregate::PercentileDisc(
+                    percentile_disc_aggregate,
+                    column_name.unwrap_last(),
+                    weight_opt.as_ref().map(|w| w.unwrap_last()),
+                );
                 Ok((aggregate, variables))
             }
-            Aggregate::ApproxPercentile(percentile, column_name, ordering) => {
+            Aggregate::ApproxPercentile(percentile, column_name, ordering, weight_opt) => {
                 let variables = common::empty_variables();
                 let physical_ordering = ordering.physical()?;
 
                 let approx_percentile_aggregate =
                     execution::ApproxPercentileAggregate::new(*percentile, physical_ordering);
-                let aggregate =
-                    execution::Aggregate::ApproxPercentile(approx_percentile_aggregate, column_name.unwrap_last());
+                let aggregate = execution::Aggregate::ApproxPercentile(
+                    approx_percentile_aggregate,
+                    column_name.unwrap_last(),
+                    weight_opt.as_ref().map(|w| w.unwrap_last()),
+                );
                 Ok((aggregate, variables))
             }
         }
@@ -545,6 +793,7 @@ impl Ordering {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::execution::datasource::ReaderOptions;
     use crate::syntax::ast::{PathExpr, PathSegment};
 
     #[test]
@@ -563,8 +812,11 @@ mod test {
             Box::new(Formula::Constant(true)),
             Box::new(Formula::Constant(false)),
         );
-        let mut physical_plan_creator =
-            PhysicalPlanCreator::new(DataSource::Stdin("jsonl".to_string(), "it".to_string()));
+        let mut physical_plan_creator = PhysicalPlanCreator::new(DataSource::Stdin(
+            "jsonl".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        ));
         let (physical_formula, variables) = formula.physical(&mut physical_plan_creator).unwrap();
         let expected_formula = execution::Formula::And(
             Box::new(execution::Formula::Constant(true)),
@@ -582,8 +834,11 @@ mod test {
         let path_expr_const = PathExpr::new(vec![PathSegment::AttrName("const_000000000".to_string())]);
 
         let expr = Expression::Constant(common::Value::Int(1));
-        let mut physical_plan_creator =
-            PhysicalPlanCreator::new(DataSource::Stdin("jsonl".to_string(), "it".to_string()));
+        let mut physical_plan_creator = PhysicalPlanCreator::new(DataSource::Stdin(
+            "jsonl".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        ));
         let (physical_expr, variables) = expr.physical(&mut physical_plan_creator).unwrap();
         let expected_formula = execution::Expression::Variable(path_expr_const.clone());
 
@@ -620,14 +875,17 @@ mod test {
                     Named::Expression(Expression::Variable(path_expr_b.clone()), Some("b".to_string())),
                 ],
                 Box::new(Node::DataSource(
-                    DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                    DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                     vec![],
                 )),
             )),
         );
 
-        let mut physical_plan_creator =
-            PhysicalPlanCreator::new(DataSource::Stdin("jsonl".to_string(), "it".to_string()));
+        let mut physical_plan_creator = PhysicalPlanCreator::new(DataSource::Stdin(
+            "jsonl".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        ));
         let (physical_formula, variables) = filter.physical(&mut physical_plan_creator).unwrap();
 
         let expected_filtered_formula = execution::Formula::Predicate(
@@ -654,7 +912,7 @@ mod test {
                 ),
             ],
             Box::new(execution::Node::DataSource(
-                DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                 vec![],
             )),
         );
@@ -694,7 +952,7 @@ mod test {
                     Named::Expression(Expression::Variable(path_expr_b.clone()), Some("b".to_string())),
                 ],
                 Box::new(Node::DataSource(
-                    DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                    DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                     vec![],
                 )),
             )),
@@ -720,8 +978,11 @@ mod test {
         let fields = vec![path_expr_b.clone()];
         let group_by = Node::GroupBy(fields, named_aggregates, Box::new(filter));
 
-        let mut physical_plan_creator =
-            PhysicalPlanCreator::new(DataSource::Stdin("jsonl".to_string(), "it".to_string()));
+        let mut physical_plan_creator = PhysicalPlanCreator::new(DataSource::Stdin(
+            "jsonl".to_string(),
+            "it".to_string(),
+            ReaderOptions::default(),
+        ));
         let (physical_formula, variables) = group_by.physical(&mut physical_plan_creator).unwrap();
 
         let expected_filtered_formula = execution::Formula::Predicate(
@@ -749,7 +1010,7 @@ mod test {
                 ),
             ],
             Box::new(execution::Node::DataSource(
-                DataSource::Stdin("jsonl".to_string(), "it".to_string()),
+                DataSource::Stdin("jsonl".to_string(), "it".to_string(), ReaderOptions::default()),
                 vec![],
             )),
         );
@@ -779,6 +1040,7 @@ mod test {
                     None,
                 ),
             ],
+            None,
             Box::new(expected_filter),
         );
 
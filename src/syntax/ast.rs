@@ -13,6 +13,10 @@ pub(crate) struct SelectStatement {
     pub(crate) having_expr_opt: Option<WhereExpression>,
     pub(crate) order_by_expr_opt: Option<OrderByExpression>,
     pub(crate) limit_expr_opt: Option<LimitExpression>,
+    pub(crate) distinct_on_opt: Option<Vec<PathExpr>>,
+    pub(crate) emit_every_opt: Option<String>,
+    pub(crate) gap_fill_opt: Option<GapFill>,
+    pub(crate) join_opt: Option<Join>,
 }
 
 impl SelectStatement {
@@ -33,8 +37,32 @@ impl SelectStatement {
             having_expr_opt,
             order_by_expr_opt,
             limit_expr_opt,
+            distinct_on_opt: None,
+            emit_every_opt: None,
+            gap_fill_opt: None,
+            join_opt: None,
         }
     }
+
+    pub(crate) fn distinct_on(mut self, distinct_on_opt: Option<Vec<PathExpr>>) -> Self {
+        self.distinct_on_opt = distinct_on_opt;
+        self
+    }
+
+    pub(crate) fn emit_every(mut self, emit_every_opt: Option<String>) -> Self {
+        self.emit_every_opt = emit_every_opt;
+        self
+    }
+
+    pub(crate) fn gap_fill(mut self, gap_fill_opt: Option<GapFill>) -> Self {
+        self.gap_fill_opt = gap_fill_opt;
+        self
+    }
+
+    pub(crate) fn join(mut self, join_opt: Option<Join>) -> Self {
+        self.join_opt = join_opt;
+        self
+    }
 }
 
 impl fmt::Display for SelectStatement {
@@ -98,6 +126,30 @@ impl TableReference {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum JoinKind {
+    Inner,
+    Left,
+}
+
+//The table joined to the table of the from clause, e.g. "left join b as y on x.id = y.id"
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Join {
+    pub(crate) kind: JoinKind,
+    pub(crate) table_reference: TableReference,
+    pub(crate) condition: Expression,
+}
+
+impl Join {
+    pub(crate) fn new(kind: JoinKind, table_reference: TableReference, condition: Expression) -> Self {
+        Join {
+            kind,
+            table_reference,
+            condition,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) struct TupleConstructor {
     pub(crate) key_values: Vec<(String, Expression)>,
@@ -236,11 +288,25 @@ impl GroupByReference {
 pub(crate) struct GroupByExpression {
     pub(crate) exprs: Vec<GroupByReference>,
     pub(crate) group_as_clause: Option<String>,
+    pub(crate) all: bool,
 }
 
 impl GroupByExpression {
     pub(crate) fn new(exprs: Vec<GroupByReference>, group_as_clause: Option<String>) -> Self {
-        GroupByExpression { exprs, group_as_clause }
+        GroupByExpression {
+            exprs,
+            group_as_clause,
+            all: false,
+        }
+    }
+
+    //GROUP BY ALL, the references are filled by the non-aggregate select expressions later
+    pub(crate) fn all(group_as_clause: Option<String>) -> Self {
+        GroupByExpression {
+            exprs: Vec::new(),
+            group_as_clause,
+            all: true,
+        }
     }
 }
 
@@ -255,6 +321,18 @@ impl LimitExpression {
     }
 }
 
+//The value of the aggregates in the missing time buckets, null if not given
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct GapFill {
+    pub(crate) value_opt: Option<Value>,
+}
+
+impl GapFill {
+    pub(crate) fn new(value_opt: Option<Value>) -> Self {
+        GapFill { value_opt }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) enum Ordering {
     Asc,
@@ -277,6 +355,8 @@ impl FromStr for Ordering {
 pub(crate) struct OrderingTerm {
     pub(crate) column_name: PathExpr,
     pub(crate) ordering: Ordering,
+    //1-based position in the select list, e.g. "order by 2 desc", resolved to the column name in logical planning
+    pub(crate) position: Option<usize>,
 }
 
 impl OrderingTerm {
@@ -284,6 +364,15 @@ impl OrderingTerm {
         OrderingTerm {
             column_name,
             ordering: Ordering::from_str(ordering).unwrap(),
+            position: None,
+        }
+    }
+
+    pub(crate) fn new_with_position(position: usize, ordering: &str) -> Self {
+        OrderingTerm {
+            column_name: PathExpr::new(Vec::new()),
+            ordering: Ordering::from_str(ordering).unwrap(),
+            position: Some(position),
         }
     }
 }
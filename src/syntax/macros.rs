@@ -0,0 +1,337 @@
+use super::ast;
+use super::parser::macro_definition;
+use hashbrown::HashMap;
+use std::fmt;
+use std::result;
+
+pub(crate) type MacroResult<T> = result::Result<T, MacroError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MacroError {
+    InvalidDefinition(usize, String),
+    Duplicate(String),
+    Recursive(String),
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MacroError::InvalidDefinition(n, s) => write!(fmt, "Invalid macro definition at line {}: {}", n, s),
+            MacroError::Duplicate(s) => write!(fmt, "Macro \"{}\" is defined more than once", s),
+            MacroError::Recursive(s) => write!(fmt, "Macro \"{}\" refers to itself", s),
+        }
+    }
+}
+
+impl std::error::Error for MacroError {}
+
+//The reusable expressions defined as "name := expression", one per line, and expanded wherever the query refers to
+//the name as a column, e.g. "latency_total := request_processing_time + backend_processing_time". The ones after a
+//"[name]" line are the derived columns of the tables of that format or with that table name only.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Macros {
+    definitions: HashMap<String, ast::Expression>,
+    scoped_definitions: HashMap<String, Vec<DerivedColumn>>,
+    //The derived columns of the scopes of a table, in the order of the config
+    derived_columns: Vec<DerivedColumn>,
+}
+
+//A column defined in a "[name]" section, selected by "select *" and listed in the schema along with the fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DerivedColumn {
+    pub(crate) name: String,
+    pub(crate) definition: String,
+    expr: ast::Expression,
+}
+
+impl Macros {
+    pub(crate) fn parse(s: &str) -> MacroResult<Self> {
+        let mut definitions = HashMap::new();
+        let mut scoped_definitions: HashMap<String, Vec<DerivedColumn>> = HashMap::new();
+        let mut scope: Option<String> = None;
+        for (line_number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim();
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    return Err(MacroError::InvalidDefinition(line_number + 1, line.to_string()));
+                }
+                scope = Some(name.to_string());
+                scoped_definitions.entry(name.to_string()).or_default();
+                continue;
+            }
+
+            let (name, expr) = match macro_definition(line) {
+                Ok(("", definition)) => definition,
+                Ok((rest, _)) => {
+                    return Err(MacroError::InvalidDefinition(line_number + 1, rest.to_string()));
+                }
+                Err(_) => {
+                    return Err(MacroError::InvalidDefinition(line_number + 1, line.to_string()));
+                }
+            };
+
+            match &scope {
+                Some(scope) => {
+                    let columns = scoped_definitions.get_mut(scope).unwrap();
+                    if columns.iter().any(|column| column.name == name) {
+                        return Err(MacroError::Duplicate(name));
+                    }
+                    let definition = line.split_once(":=").map_or("", |(_, e)| e).trim().to_string();
+                    columns.push(DerivedColumn { name, definition, expr });
+                }
+                None => {
+                    if definitions.insert(name.clone(), expr).is_some() {
+                        return Err(MacroError::Duplicate(name));
+                    }
+                }
+            }
+        }
+
+        let macros = Macros {
+            definitions,
+            scoped_definitions,
+            derived_columns: Vec::new(),
+        };
+        //The cycles are rejected up front rather than on the first query using them
+        let mut scopes: Vec<Option<&str>> = vec![None];
+        scopes.extend(macros.scoped_definitions.keys().map(|s| Some(s.as_str())));
+        for scope in scopes.into_iter() {
+            let scoped = macros.scoped(scope.as_slice());
+            //In order of the names so that the same cycle is always reported by the same name
+            let mut names: Vec<&String> = scoped.definitions.keys().collect();
+            names.sort();
+            for name in names.into_iter() {
+                scoped.expand_macro(name, &mut Vec::new())?;
+            }
+        }
+
+        Ok(macros)
+    }
+
+    //The macros of a table, the ones of its scopes, e.g. its format and its name, override the unscoped ones in order
+    pub(crate) fn scoped(&self, scopes: &[&str]) -> Macros {
+        let mut definitions = self.definitions.clone();
+        let mut derived_columns: Vec<DerivedColumn> = Vec::new();
+        for scope in scopes.iter() {
+            for column in self.scoped_definitions.get(*scope).into_iter().flatten() {
+                definitions.insert(column.name.clone(), column.expr.clone());
+                match derived_columns.iter_mut().find(|c| c.name == column.name) {
+                    Some(overridden) => *overridden = column.clone(),
+                    None => derived_columns.push(column.clone()),
+                }
+            }
+        }
+
+        Macros {
+            definitions,
+            scoped_definitions: HashMap::new(),
+            derived_columns,
+        }
+    }
+
+    pub(crate) fn derived_columns(&self) -> &[DerivedColumn] {
+        &self.derived_columns
+    }
+
+    fn expand_macro(&self, name: &str, expanding: &mut Vec<String>) -> MacroResult<ast::Expression> {
+        if expanding.iter().any(|n| n == name) {
+            return Err(MacroError::Recursive(name.to_string()));
+        }
+
+        let mut expr = self.definitions[name].clone();
+        expanding.push(name.to_string());
+        self.expand_expression(&mut expr, expanding)?;
+        expanding.pop();
+        Ok(expr)
+    }
+
+    fn macro_name(&self, expr: &ast::Expression) -> Option<String> {
+        match expr {
+            ast::Expression::Column(path_expr) => match &path_expr.path_segments[..] {
+                [ast::PathSegment::AttrName(s)] if self.definitions.contains_key(s) => Some(s.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn expand_expression(&self, expr: &mut ast::Expression, expanding: &mut Vec<String>) -> MacroResult<()> {
+        if let Some(name) = self.macro_name(expr) {
+            *expr = self.expand_macro(&name, expanding)?;
+            return Ok(());
+        }
+
+        match expr {
+            ast::Expression::Column(_) | ast::Expression::Value(_) => {}
+            ast::Expression::BinaryOperator(_, l, r) => {
+                self.expand_expression(l, expanding)?;
+                self.expand_expression(r, expanding)?;
+            }
+            ast::Expression::UnaryOperator(_, c) => {
+                self.expand_expression(c, expanding)?;
+            }
+            ast::Expression::FuncCall(_, args, _) => {
+                for arg in args.iter_mut() {
+                    if let ast::SelectExpression::Expression(e, _) = arg {
+                        self.expand_expression(e, expanding)?;
+                    }
+                }
+            }
+            ast::Expression::CaseWhenExpression(case_when) => {
+                self.expand_expression(&mut case_when.condition, expanding)?;
+                self.expand_expression(&mut case_when.then_expr, expanding)?;
+                if let Some(else_expr) = case_when.else_expr.as_mut() {
+                    self.expand_expression(else_expr, expanding)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    //The macro selected or grouped by without an alias is named after the macro
+    pub(crate) fn expand(&self, mut stmt: ast::SelectStatement) -> MacroResult<ast::SelectStatement> {
+        if self.definitions.is_empty() {
+            return Ok(stmt);
+        }
+
+        if let ast::SelectClause::SelectExpressions(select_exprs) = &mut stmt.select_clause {
+            //The star selects the derived columns too, right after the fields
+            if let Some(star) = select_exprs.iter().position(|e| *e == ast::SelectExpression::Star) {
+                let derived = self.derived_columns.iter().map(|column| {
+                    let path_expr = ast::PathExpr::new(vec![ast::PathSegment::AttrName(column.name.clone())]);
+                    ast::SelectExpression::Expression(Box::new(ast::Expression::Column(path_expr)), None)
+                });
+                select_exprs.splice(star + 1..star + 1, derived);
+            }
+            for select_expr in select_exprs.iter_mut() {
+                if let ast::SelectExpression::Expression(e, name_opt) = select_expr {
+                    if name_opt.is_none() {
+                        *name_opt = self.macro_name(e);
+                    }
+                    self.expand_expression(e, &mut Vec::new())?;
+                }
+            }
+        }
+
+        if let Some(where_expr) = stmt.where_expr_opt.as_mut() {
+            self.expand_expression(&mut where_expr.expr, &mut Vec::new())?;
+        }
+
+        if let Some(group_by) = stmt.group_by_exprs_opt.as_mut() {
+            for r in group_by.exprs.iter_mut() {
+                if r.as_clause.is_none() {
+                    r.as_clause = self.macro_name(&r.column_expr);
+                }
+                self.expand_expression(&mut r.column_expr, &mut Vec::new())?;
+            }
+        }
+
+        if let Some(having_expr) = stmt.having_expr_opt.as_mut() {
+            self.expand_expression(&mut having_expr.expr, &mut Vec::new())?;
+        }
+
+        Ok(stmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::select_query;
+
+    #[test]
+    fn test_expand_macros() {
+        let macros = Macros::parse(
+            "# the derived metrics\n\
+             latency_total := request_processing_time + backend_processing_time\n\
+             \n\
+             is_slow := latency_total > 1\n",
+        )
+        .unwrap();
+
+        let (_, stmt) =
+            select_query("select latency_total, count(*) as c from it where is_slow group by latency_total").unwrap();
+        let (_, expected) = select_query(
+            "select request_processing_time + backend_processing_time as latency_total, count(*) as c from it \
+             where request_processing_time + backend_processing_time > 1 \
+             group by request_processing_time + backend_processing_time as latency_total",
+        )
+        .unwrap();
+        assert_eq!(expected, macros.expand(stmt).unwrap());
+    }
+
+    #[test]
+    fn test_expand_scoped_macros() {
+        let macros = Macros::parse(
+            "is_slow := elapsed > 1\n\
+             [alb]\n\
+             tenant := url_path_segments(request, 1)\n\
+             is_slow := target_processing_time > 1\n\
+             [squid]\n\
+             tenant := user_name\n",
+        )
+        .unwrap();
+
+        let (_, stmt) = select_query("select tenant, count(*) as c from it where is_slow group by tenant").unwrap();
+        let (_, expected) = select_query(
+            "select url_path_segments(request, 1) as tenant, count(*) as c from it \
+             where target_processing_time > 1 group by url_path_segments(request, 1) as tenant",
+        )
+        .unwrap();
+        assert_eq!(expected, macros.scoped(&["alb", "it"]).expand(stmt.clone()).unwrap());
+
+        let (_, expected) =
+            select_query("select tenant, count(*) as c from it where elapsed > 1 group by tenant").unwrap();
+        assert_eq!(expected, macros.scoped(&["elb", "it"]).expand(stmt).unwrap());
+
+        //The star selects the derived columns of the scopes, not the unscoped macros
+        let (_, stmt) = select_query("select * from it").unwrap();
+        let (_, expected) = select_query(
+            "select *, url_path_segments(request, 1) as tenant, target_processing_time > 1 as is_slow from it",
+        )
+        .unwrap();
+        let scoped = macros.scoped(&["alb", "it"]);
+        assert_eq!(expected, scoped.expand(stmt.clone()).unwrap());
+        assert_eq!(stmt, macros.scoped(&["elb", "it"]).expand(stmt.clone()).unwrap());
+        let derived: Vec<(&str, &str)> = scoped
+            .derived_columns()
+            .iter()
+            .map(|c| (c.name.as_str(), c.definition.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("tenant", "url_path_segments(request, 1)"),
+                ("is_slow", "target_processing_time > 1")
+            ],
+            derived
+        );
+
+        assert_eq!(
+            Err(MacroError::InvalidDefinition(1, "[a b]".to_string())),
+            Macros::parse("[a b]\na := 1")
+        );
+        assert_eq!(
+            Err(MacroError::Recursive("a".to_string())),
+            Macros::parse("a := b\n[alb]\nb := a")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_macros() {
+        assert_eq!(
+            Err(MacroError::InvalidDefinition(2, "a +".to_string())),
+            Macros::parse("a := 1\na +")
+        );
+        assert_eq!(
+            Err(MacroError::Duplicate("a".to_string())),
+            Macros::parse("a := 1\na := 2")
+        );
+        assert_eq!(Err(MacroError::Recursive("a".to_string())), Macros::parse("a := a + 1"));
+    }
+}
@@ -5,10 +5,10 @@ use std::str::FromStr;
 use nom::{
     branch::alt,
     bytes::complete::{escaped, tag},
-    character::complete::{char, digit1, multispace0, none_of, one_of, space0, space1},
+    character::complete::{char, digit1, multispace0, none_of, one_of, satisfy, space0, space1},
     combinator::{cut, map, map_res, not, opt},
-    error::{context, VerboseError},
-    multi::separated_list0,
+    error::{context, VerboseError, VerboseErrorKind},
+    multi::{separated_list0, separated_list1},
     number::complete,
     sequence::{delimited, pair, preceded, terminated, tuple},
     AsChar, IResult, InputTakeAtPosition,
@@ -18,6 +18,7 @@ use crate::syntax::ast::{
     ArrayConstructor, PathExpr, PathSegment, SelectClause, SelectExpression, TableReference, TupleConstructor,
 };
 use hashbrown::hash_map::HashMap;
+use std::cell::Cell;
 
 lazy_static! {
     static ref KEYWORDS: Vec<&'static str> = {
@@ -202,6 +203,7 @@ fn factor(i: &str) -> IResult<&str, ast::Expression, VerboseError<&str>> {
         alt((
             parens,
             map(value, ast::Expression::Value),
+            list_literal,
             func_call,
             map(select_column_reference, |path_expr| ast::Expression::Column(path_expr)),
         )),
@@ -268,27 +270,10 @@ fn where_expression(i: &str) -> IResult<&str, ast::WhereExpression, VerboseError
     map(preceded(tag("where"), expression), ast::WhereExpression::new)(i)
 }
 
-fn column_name_in_group_by(i: &str) -> IResult<&str, PathExpr, VerboseError<&str>> {
-    terminated(path_expr, not(char('(')))(i)
-}
-
-fn column_factor(i: &str) -> IResult<&str, ast::Expression, VerboseError<&str>> {
-    delimited(
-        space0,
-        alt((
-            parens,
-            map(value, ast::Expression::Value),
-            func_call,
-            map(column_name_in_group_by, |path_expr| ast::Expression::Column(path_expr)),
-        )),
-        space0,
-    )(i)
-}
-
 fn column_reference(i: &str) -> IResult<&str, ast::GroupByReference, VerboseError<&str>> {
     map(
         tuple((
-            column_factor,
+            expression,
             opt(preceded(tuple((space0, tag("as"), space1)), identifier)),
         )),
         |(column_expr, as_clasue)| ast::GroupByReference::new(column_expr, as_clasue.map(|s| s.to_string())),
@@ -308,32 +293,41 @@ fn column_expression_list(i: &str) -> IResult<&str, Vec<ast::GroupByReference>,
     )(i)
 }
 
+//The index of the array, or the key of the map, e.g. headers['content-type']
 fn path_bracket(i: &str) -> IResult<&str, ast::Value, VerboseError<&str>> {
-    delimited(tag("["), integral, tag("]"))(i)
+    delimited(
+        tag("["),
+        alt((
+            integral,
+            map(single_quote_string_literal, ast::Value::StringLiteral),
+            double_quote_string_literal,
+        )),
+        tag("]"),
+    )(i)
 }
 
 fn path_expr(i: &str) -> IResult<&str, PathExpr, VerboseError<&str>> {
     map(
         terminated(
-            separated_list0(char('.'), pair(identifier, opt(path_bracket))),
+            separated_list1(char('.'), pair(identifier, opt(path_bracket))),
             not(char('(')),
         ),
         |v| {
-            let segments = v
-                .iter()
-                .map(|(attr_name, opt_array_idx)| {
-                    if let Some(array_idx) = opt_array_idx {
-                        match array_idx {
-                            ast::Value::Integral(i) => PathSegment::ArrayIndex(attr_name.to_string(), *i as usize),
-                            _ => {
-                                unreachable!()
-                            }
-                        }
-                    } else {
-                        PathSegment::AttrName(attr_name.to_string())
+            let mut segments = Vec::new();
+            for (attr_name, opt_array_idx) in v.iter() {
+                match opt_array_idx {
+                    Some(ast::Value::Integral(i)) => {
+                        segments.push(PathSegment::ArrayIndex(attr_name.to_string(), *i as usize));
+                    }
+                    //The key of the map is the same as the attribute of the object, map['key'] is map.key
+                    Some(ast::Value::StringLiteral(key)) => {
+                        segments.push(PathSegment::AttrName(attr_name.to_string()));
+                        segments.push(PathSegment::AttrName(key.clone()));
                     }
-                })
-                .collect();
+                    Some(_) => unreachable!(),
+                    None => segments.push(PathSegment::AttrName(attr_name.to_string())),
+                }
+            }
             PathExpr::new(segments)
         },
     )(i)
@@ -370,21 +364,39 @@ fn having_expression(i: &str) -> IResult<&str, ast::WhereExpression, VerboseErro
     map(preceded(tag("having"), expression), ast::WhereExpression::new)(i)
 }
 
+fn group_by_all(i: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    terminated(
+        tag("all"),
+        tuple((
+            not(satisfy(|c: char| {
+                c.is_alphanumeric() || c == '_' || c == '.' || c == '['
+            })),
+            space0,
+        )),
+    )(i)
+}
+
 fn group_by_expression(i: &str) -> IResult<&str, ast::GroupByExpression, VerboseError<&str>> {
-    map(
-        preceded(
-            tuple((tag("group"), space1, tag("by"), space1)),
-            pair(
-                column_expression_list,
-                opt(preceded(
-                    tuple((space0, tag("group"), space1, tag("as"), space1)),
-                    identifier,
-                )),
+    let group_as_clause = || {
+        opt(preceded(
+            tuple((space0, tag("group"), space1, tag("as"), space1)),
+            identifier,
+        ))
+    };
+
+    preceded(
+        tuple((tag("group"), space1, tag("by"), space1)),
+        alt((
+            map(preceded(group_by_all, group_as_clause()), |group_as_clause| {
+                ast::GroupByExpression::all(group_as_clause.map(|s| s.to_string()))
+            }),
+            map(
+                pair(column_expression_list, group_as_clause()),
+                |(group_by_references, group_as_clause)| {
+                    ast::GroupByExpression::new(group_by_references, group_as_clause.map(|s| s.to_string()))
+                },
             ),
-        ),
-        |(group_by_references, group_as_clause)| {
-            ast::GroupByExpression::new(group_by_references, group_as_clause.map(|s| s.to_string()))
-        },
+        )),
     )(i)
 }
 
@@ -395,35 +407,132 @@ fn limit_expression(i: &str) -> IResult<&str, ast::LimitExpression, VerboseError
     })(i)
 }
 
+//The tumbling window of the aggregates, e.g. emit every "1 minute"
+fn emit_every_clause(i: &str) -> IResult<&str, String, VerboseError<&str>> {
+    preceded(
+        tuple((tag("emit"), space1, tag("every"), space1)),
+        alt((
+            single_quote_string_literal,
+            map(
+                preceded(
+                    char('\"'),
+                    cut(terminated(double_quote_string_literal_interior, char('\"'))),
+                ),
+                |s| s.to_string(),
+            ),
+        )),
+    )(i)
+}
+
 fn ordering_term(i: &str) -> IResult<&str, ast::OrderingTerm, VerboseError<&str>> {
     map(
         pair(
-            column_name_in_group_by,
+            select_column_reference,
             preceded(space1, alt((tag("asc"), tag("desc")))),
         ),
         |(column_name, ordering)| ast::OrderingTerm::new(column_name, ordering),
     )(i)
 }
 
+//A number too large for a position fails rather than to be tried as a column
+fn ordinal_position(i: &str) -> IResult<&str, usize, VerboseError<&str>> {
+    let (rest, position) = terminated(digit1, not(satisfy(|c: char| c.is_alphanumeric() || c == '_')))(i)?;
+    match position.parse::<usize>() {
+        Ok(position) => Ok((rest, position)),
+        Err(_) => Err(nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context("order by position out of range"))],
+        })),
+    }
+}
+
+fn ordinal_ordering_term(i: &str) -> IResult<&str, ast::OrderingTerm, VerboseError<&str>> {
+    map(
+        pair(ordinal_position, preceded(space1, alt((tag("asc"), tag("desc"))))),
+        |(position, ordering): (usize, &str)| ast::OrderingTerm::new_with_position(position, ordering),
+    )(i)
+}
+
 fn order_by_clause(i: &str) -> IResult<&str, ast::OrderByExpression, VerboseError<&str>> {
     map(
         preceded(
             tuple((tag("order"), space1, tag("by"), space1)),
-            terminated(separated_list0(preceded(space0, char(',')), ordering_term), space0),
+            terminated(
+                separated_list0(
+                    preceded(space0, char(',')),
+                    preceded(space0, alt((ordinal_ordering_term, ordering_term))),
+                ),
+                space0,
+            ),
         ),
         ast::OrderByExpression::new,
     )(i)
 }
 
+//The table joined by the equalities of the columns of both sides, e.g. "left join b as y on x.id = y.id"
+fn join_clause(i: &str) -> IResult<&str, ast::Join, VerboseError<&str>> {
+    map(
+        tuple((
+            opt(terminated(tag("left"), space1)),
+            terminated(tag("join"), space1),
+            table_reference,
+            preceded(tuple((space1, tag("on"))), expression),
+        )),
+        |(left, _, table_reference, condition)| {
+            let kind = if left.is_some() {
+                ast::JoinKind::Left
+            } else {
+                ast::JoinKind::Inner
+            };
+            ast::Join::new(kind, table_reference, condition)
+        },
+    )(i)
+}
+
 fn from_clause(i: &str) -> IResult<&str, Vec<TableReference>, VerboseError<&str>> {
     terminated(preceded(tuple((tag("from"), space1)), table_reference_list), space0)(i)
 }
 
+//The nesting of the parentheses, the function calls and the case expressions recurses in the parser, while the
+//chains of the binary operators are parsed in a loop but still nest the tree which is evaluated recursively. Both are
+//bounded to fail with a syntax error rather than to overflow the stack.
+const MAX_NESTING_DEPTH: usize = 128;
+const MAX_TREE_DEPTH: usize = 1024;
+
+thread_local! {
+    //The nesting and the tree depth of the expression being parsed
+    static EXPRESSION_DEPTH: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+fn enter_expression(i: &str, nesting: usize, levels: usize) -> Result<(), nom::Err<VerboseError<&str>>> {
+    EXPRESSION_DEPTH.with(|depth| {
+        let (current_nesting, current_levels) = depth.get();
+        if current_nesting + nesting > MAX_NESTING_DEPTH || current_levels + nesting + levels > MAX_TREE_DEPTH {
+            Err(nom::Err::Failure(VerboseError {
+                errors: vec![(i, VerboseErrorKind::Context("expression nested too deeply"))],
+            }))
+        } else {
+            depth.set((current_nesting + nesting, current_levels + nesting + levels));
+            Ok(())
+        }
+    })
+}
+
+fn leave_expression(nesting: usize, levels: usize) {
+    EXPRESSION_DEPTH.with(|depth| {
+        let (current_nesting, current_levels) = depth.get();
+        depth.set((current_nesting - nesting, current_levels - nesting - levels));
+    });
+}
+
 fn parse_expression_atom(i: &str) -> IResult<&str, ast::Expression, VerboseError<&str>> {
-    alt((
+    enter_expression(i, 1, 0)?;
+    let result = alt((
         map(case_when_expression, |n| ast::Expression::CaseWhenExpression(n)),
         expression_term_opt_not,
-    ))(i)
+    ))(i);
+    leave_expression(1, 0);
+
+    result
 }
 
 fn parse_expression_op(i: &str) -> IResult<&str, &str, VerboseError<&str>> {
@@ -434,12 +543,12 @@ fn parse_expression_op(i: &str) -> IResult<&str, &str, VerboseError<&str>> {
         tag("/"),
         tag("="),
         tag("!="),
-        tag(">"),
-        tag("<"),
         tag(">="),
         tag("<="),
-        tag("and"),
-        tag("or"),
+        tag(">"),
+        tag("<"),
+        terminated(tag("and"), not(satisfy(|c: char| c.is_alphanumeric() || c == '_'))),
+        terminated(tag("or"), not(satisfy(|c: char| c.is_alphanumeric() || c == '_'))),
     ))(i)
 }
 
@@ -449,6 +558,8 @@ fn parse_expression_at_precedence<'a>(
     precedence_table: &HashMap<String, (u32, bool)>,
 ) -> IResult<&'a str, ast::Expression, VerboseError<&'a str>> {
     let (mut i1, mut expr) = parse_expression_atom(i0)?;
+    //Every operator of the chain nests the expression so far one level deeper
+    let mut levels = 0;
     while let Ok((i2, op)) = parse_expression_op(i1) {
         let (op_precedence, op_left_associative) = *precedence_table.get(op).unwrap();
 
@@ -456,10 +567,23 @@ fn parse_expression_at_precedence<'a>(
             break;
         }
 
-        let (i3, b) = if op_left_associative {
-            parse_expression_at_precedence(i2, op_precedence + 1, precedence_table)?
+        if let Err(e) = enter_expression(i1, 0, 1) {
+            leave_expression(0, levels);
+            return Err(e);
+        }
+        levels += 1;
+
+        let parsed = if op_left_associative {
+            parse_expression_at_precedence(i2, op_precedence + 1, precedence_table)
         } else {
-            parse_expression_at_precedence(i2, op_precedence, precedence_table)?
+            parse_expression_at_precedence(i2, op_precedence, precedence_table)
+        };
+        let (i3, b) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                leave_expression(0, levels);
+                return Err(e);
+            }
         };
 
         let op = ast::BinaryOperator::from_str(op).unwrap();
@@ -467,6 +591,7 @@ fn parse_expression_at_precedence<'a>(
 
         i1 = i3;
     }
+    leave_expression(0, levels);
 
     Ok((i1, expr))
 }
@@ -524,6 +649,18 @@ fn array_constructor(i: &str) -> IResult<&str, ast::ArrayConstructor, VerboseErr
     )(i)
 }
 
+//The list literal [a, b] is the call of the list function on its elements
+fn list_literal(i: &str) -> IResult<&str, ast::Expression, VerboseError<&str>> {
+    map(array_constructor, |v| {
+        let args = v
+            .values
+            .into_iter()
+            .map(|e| ast::SelectExpression::Expression(Box::new(e), None))
+            .collect();
+        ast::Expression::FuncCall("list".to_string(), args, None)
+    })(i)
+}
+
 fn value_constructor(i: &str) -> IResult<&str, ast::SelectClause, VerboseError<&str>> {
     delimited(
         space0,
@@ -549,21 +686,60 @@ fn select_clause_expression_list(i: &str) -> IResult<&str, ast::SelectClause, Ve
     map(select_expression_list, |v| SelectClause::SelectExpressions(v))(i)
 }
 
+//"distinct on (a, b)" keeps the first record of each key
+fn distinct_on_clause(i: &str) -> IResult<&str, Vec<PathExpr>, VerboseError<&str>> {
+    preceded(
+        tuple((space1, tag("distinct"), space1, tag("on"), space0)),
+        delimited(
+            tag("("),
+            separated_list1(preceded(space0, char(',')), preceded(space0, select_column_reference)),
+            preceded(space0, tag(")")),
+        ),
+    )(i)
+}
+
+//Inserts the rows of the missing time buckets, e.g. gap fill with 0
+fn gap_fill_clause(i: &str) -> IResult<&str, ast::GapFill, VerboseError<&str>> {
+    map(
+        preceded(
+            tuple((tag("gap"), space1, tag("fill"))),
+            opt(preceded(tuple((space1, tag("with"), space1)), value)),
+        ),
+        ast::GapFill::new,
+    )(i)
+}
+
 pub(crate) fn select_query(i: &str) -> IResult<&str, ast::SelectStatement, VerboseError<&str>> {
     map(
         preceded(
             tag("select"),
             tuple((
+                opt(distinct_on_clause),
                 alt((value_constructor, select_clause_expression_list)),
                 from_clause,
+                opt(terminated(join_clause, space0)),
                 opt(where_expression),
                 opt(group_by_expression),
                 opt(having_expression),
                 opt(order_by_clause),
                 opt(limit_expression),
+                opt(preceded(multispace0, emit_every_clause)),
+                opt(preceded(multispace0, gap_fill_clause)),
             )),
         ),
-        |(select_clause, table_references, where_expr, group_by_expr, having_expr, order_by_expr, limit_expr)| {
+        |(
+            distinct_on,
+            select_clause,
+            table_references,
+            join,
+            where_expr,
+            group_by_expr,
+            having_expr,
+            order_by_expr,
+            limit_expr,
+            emit_every,
+            gap_fill,
+        )| {
             ast::SelectStatement::new(
                 select_clause,
                 table_references,
@@ -573,10 +749,43 @@ pub(crate) fn select_query(i: &str) -> IResult<&str, ast::SelectStatement, Verbo
                 order_by_expr,
                 limit_expr,
             )
+            .distinct_on(distinct_on)
+            .emit_every(emit_every)
+            .gap_fill(gap_fill)
+            .join(join)
         },
     )(i)
 }
 
+//The definition of a macro in the config, e.g. "latency_total := request_processing_time + backend_processing_time"
+pub(crate) fn macro_definition(i: &str) -> IResult<&str, (String, ast::Expression), VerboseError<&str>> {
+    map(
+        tuple((identifier, space0, tag(":="), expression)),
+        |(name, _, _, expr): (&str, _, _, ast::Expression)| (name.to_string(), expr),
+    )(i)
+}
+
+//Several select statements over possibly different tables, e.g. "select a from x union all select a from y". The
+//order by and the limit after the last select are of the whole union, so they may not come before it.
+pub(crate) fn union_query(i: &str) -> IResult<&str, Vec<ast::SelectStatement>, VerboseError<&str>> {
+    let (rest, select_stmts) =
+        separated_list1(tuple((space0, tag("union"), space1, tag("all"), space1)), select_query)(i)?;
+    let (_, init) = select_stmts.split_last().unwrap();
+    if init
+        .iter()
+        .any(|stmt| stmt.order_by_expr_opt.is_some() || stmt.limit_expr_opt.is_some())
+    {
+        return Err(nom::Err::Failure(VerboseError {
+            errors: vec![(
+                i,
+                VerboseErrorKind::Context("order by and limit only after the last select of a union"),
+            )],
+        }));
+    }
+
+    Ok((rest, select_stmts))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1044,6 +1253,84 @@ mod test {
         assert_eq!(order_by_clause("order by a asc"), Ok(("", expected)));
     }
 
+    #[test]
+    fn test_keyword_operator_boundary() {
+        let (rest, _) =
+            select_query("select a, count(*) as c from it group by a having a = 1 order by c desc").unwrap();
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_emit_every() {
+        let (rest, stmt) =
+            select_query("select a, count(*) as c from it group by a limit 10 emit every \"1 minute\"").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(Some("1 minute".to_string()), stmt.emit_every_opt);
+
+        let (rest, stmt) = select_query("select count(*) as c from it emit every '5 seconds'").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(Some("5 seconds".to_string()), stmt.emit_every_opt);
+    }
+
+    #[test]
+    fn test_gap_fill() {
+        let (rest, stmt) =
+            select_query(r#"select t, count(*) as c from it group by time_bucket("1 minute", ts) as t gap fill"#)
+                .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(Some(ast::GapFill::new(None)), stmt.gap_fill_opt);
+
+        let (rest, stmt) = select_query("select count(*) as c from it emit every '5 seconds' gap fill with 0").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            Some(ast::GapFill::new(Some(ast::Value::Integral(0)))),
+            stmt.gap_fill_opt
+        );
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let (rest, stmt) = select_query("select a from it where a >= 1 and b <= 2").unwrap();
+        assert_eq!(rest, "");
+
+        let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
+        let path_expr_b = PathExpr::new(vec![PathSegment::AttrName("b".to_string())]);
+        let expected = ast::Expression::BinaryOperator(
+            ast::BinaryOperator::And,
+            Box::new(ast::Expression::BinaryOperator(
+                ast::BinaryOperator::GreaterEqual,
+                Box::new(ast::Expression::Column(path_expr_a)),
+                Box::new(ast::Expression::Value(ast::Value::Integral(1))),
+            )),
+            Box::new(ast::Expression::BinaryOperator(
+                ast::BinaryOperator::LessEqual,
+                Box::new(ast::Expression::Column(path_expr_b)),
+                Box::new(ast::Expression::Value(ast::Value::Integral(2))),
+            )),
+        );
+        assert_eq!(stmt.where_expr_opt.unwrap().expr, expected);
+    }
+
+    #[test]
+    fn test_order_by_clause_with_position() {
+        let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
+        let expected = ast::OrderByExpression::new(vec![
+            ast::OrderingTerm::new_with_position(2, "desc"),
+            ast::OrderingTerm::new(path_expr_a.clone(), "asc"),
+        ]);
+
+        assert_eq!(order_by_clause("order by 2 desc, a asc"), Ok(("", expected)));
+        assert_eq!(
+            order_by_clause("order by 99999999999999999999999 desc"),
+            Err(nom::Err::Failure(VerboseError {
+                errors: vec![(
+                    "99999999999999999999999 desc",
+                    VerboseErrorKind::Context("order by position out of range")
+                )]
+            }))
+        );
+    }
+
     #[test]
     fn test_group_by_expression() {
         let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
@@ -1071,6 +1358,19 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_group_by_all_expression() {
+        assert_eq!(
+            group_by_expression("group by all "),
+            Ok(("", ast::GroupByExpression::all(None)))
+        );
+
+        let path_expr_all_x = PathExpr::new(vec![PathSegment::AttrName("all_x".to_string())]);
+        let group_by_ref = ast::GroupByReference::new(ast::Expression::Column(path_expr_all_x), None);
+        let expected = ast::GroupByExpression::new(vec![group_by_ref], None);
+        assert_eq!(group_by_expression("group by all_x"), Ok(("", expected)));
+    }
+
     #[test]
     fn test_select_statement_with_order() {
         let path_expr_a = PathExpr::new(vec![PathSegment::AttrName("a".to_string())]);
@@ -1282,6 +1582,14 @@ mod test {
         ];
         let expected = ast::PathExpr::new(path_segments);
         assert_eq!(expected, ans);
+
+        let (_, ans) = path_expr("a['content-type']").unwrap();
+        let path_segments = vec![
+            ast::PathSegment::AttrName("a".to_string()),
+            ast::PathSegment::AttrName("content-type".to_string()),
+        ];
+        let expected = ast::PathExpr::new(path_segments);
+        assert_eq!(expected, ans);
     }
 
     #[test]
@@ -1321,6 +1629,29 @@ mod test {
         assert_eq!(expected, ans);
     }
 
+    #[test]
+    fn test_list_literal() {
+        let (_, ans) = expression("contains([200, 304], a)").unwrap();
+        let list = ast::Expression::FuncCall(
+            "list".to_string(),
+            vec![
+                ast::SelectExpression::Expression(Box::new(ast::Expression::Value(ast::Value::Integral(200))), None),
+                ast::SelectExpression::Expression(Box::new(ast::Expression::Value(ast::Value::Integral(304))), None),
+            ],
+            None,
+        );
+        let column = ast::Expression::Column(ast::PathExpr::new(vec![ast::PathSegment::AttrName("a".to_string())]));
+        let expected = ast::Expression::FuncCall(
+            "contains".to_string(),
+            vec![
+                ast::SelectExpression::Expression(Box::new(list), None),
+                ast::SelectExpression::Expression(Box::new(column), None),
+            ],
+            None,
+        );
+        assert_eq!(expected, ans);
+    }
+
     #[test]
     fn test_value_constructor() {
         let (_, ans) = value_constructor(" value [a, b]").unwrap();
@@ -1340,4 +1671,161 @@ mod test {
         let expected = ast::SelectClause::ValueConstructor(ast::ValueConstructor::Expression(expr));
         assert_eq!(expected, ans);
     }
+
+    #[test]
+    fn test_union_query() {
+        let (rest, ans) = union_query("select a from x union all select a from y where a = 1 limit 1").unwrap();
+        assert_eq!("", rest);
+        assert_eq!(2, ans.len());
+        assert_eq!(
+            ast::PathExpr::new(vec![ast::PathSegment::AttrName("y".to_string())]),
+            ans[1].table_references[0].path_expr
+        );
+
+        let (rest, ans) = union_query("select a from x").unwrap();
+        assert_eq!("", rest);
+        assert_eq!(1, ans.len());
+
+        assert!(union_query("select a from x limit 1 union all select a from y").is_err());
+        assert!(union_query("select a from x order by a asc union all select a from y").is_err());
+    }
+
+    #[test]
+    fn test_select_query_with_distinct_on() {
+        let (rest, ans) = select_query("select distinct on (a, b) a, b, c from it order by c asc").unwrap();
+        assert_eq!("", rest);
+        let expected = vec![
+            ast::PathExpr::new(vec![ast::PathSegment::AttrName("a".to_string())]),
+            ast::PathExpr::new(vec![ast::PathSegment::AttrName("b".to_string())]),
+        ];
+        assert_eq!(Some(expected), ans.distinct_on_opt);
+
+        let (_, ans) = select_query("select distinct_count from it").unwrap();
+        assert_eq!(None, ans.distinct_on_opt);
+    }
+
+    #[test]
+    fn test_select_query_with_join() {
+        let (rest, ans) =
+            select_query("select x.a, y.b from it as x left join other as y on x.id = y.id where x.a > 1").unwrap();
+        assert_eq!("", rest);
+        assert!(ans.where_expr_opt.is_some());
+        let join = ans.join_opt.unwrap();
+        assert_eq!(ast::JoinKind::Left, join.kind);
+        assert_eq!(Some("y".to_string()), join.table_reference.as_clause);
+        let column = |table: &str| {
+            Box::new(ast::Expression::Column(ast::PathExpr::new(vec![
+                ast::PathSegment::AttrName(table.to_string()),
+                ast::PathSegment::AttrName("id".to_string()),
+            ])))
+        };
+        let expected = ast::Expression::BinaryOperator(ast::BinaryOperator::Equal, column("x"), column("y"));
+        assert_eq!(expected, join.condition);
+
+        let (_, ans) = select_query("select a from it join other on it.id = other.id").unwrap();
+        assert_eq!(ast::JoinKind::Inner, ans.join_opt.unwrap().kind);
+    }
+
+    #[test]
+    fn test_expression_depth() {
+        let nested = format!("select {}1{} from it", "(".repeat(100), ")".repeat(100));
+        assert!(select_query(&nested).is_ok());
+
+        let too_deep = format!("select {}1{} from it", "(".repeat(10000), ")".repeat(10000));
+        match select_query(&too_deep) {
+            Err(nom::Err::Failure(e)) => {
+                assert_eq!(VerboseErrorKind::Context("expression nested too deeply"), e.errors[0].1)
+            }
+            other => panic!("unexpected {:?}", other.map(|(rest, _)| rest)),
+        }
+
+        let chain = format!("select a from it where {}", vec!["a = 1"; 1000].join(" or "));
+        assert!(select_query(&chain).is_ok());
+        let chain = format!("select a from it where {}", vec!["a = 1"; 10000].join(" or "));
+        assert!(select_query(&chain).is_err());
+
+        //The depth is restored after the failure
+        assert!(select_query(&nested).is_ok());
+        let list = format!("select a from it where contains([{}], a)", vec!["1"; 10000].join(", "));
+        let (rest, _) = select_query(&list).unwrap();
+        assert_eq!("", rest);
+    }
+
+    #[test]
+    fn test_random_queries() {
+        use rand::{Rng, SeedableRng};
+
+        let tokens = [
+            "select",
+            "value",
+            "from",
+            "it",
+            "where",
+            "group",
+            "by",
+            "order",
+            "limit",
+            "as",
+            "and",
+            "or",
+            "not",
+            "case",
+            "when",
+            "then",
+            "else",
+            "end",
+            "union",
+            "all",
+            "distinct",
+            "on",
+            "within",
+            "emit",
+            "every",
+            "a",
+            "b.c",
+            "d[0]",
+            "*",
+            "(",
+            ")",
+            "[",
+            "]",
+            "{",
+            "}",
+            ",",
+            ":",
+            "=",
+            "!=",
+            ">=",
+            "<",
+            "+",
+            "-",
+            "/",
+            "'x'",
+            "\"y\"",
+            "\"",
+            "'",
+            "1",
+            "1.5",
+            "-2",
+            "true",
+            "count(",
+            "percentile_disc(0.5)",
+            "\\",
+            "é",
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(260);
+        for _ in 0..5000 {
+            let len = rng.gen_range(0..30);
+            let mut query = String::new();
+            for _ in 0..len {
+                query.push_str(tokens[rng.gen_range(0..tokens.len())]);
+                if rng.gen_bool(0.7) {
+                    query.push(' ');
+                }
+            }
+
+            let _ = union_query(&query);
+            let _ = select_query(&query);
+        }
+    }
 }
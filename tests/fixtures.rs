@@ -0,0 +1,8 @@
+#![cfg(feature = "fixtures")]
+
+#[test]
+fn test_formats_through_public_fixtures() {
+    for file_format in logq::fixtures::formats().iter() {
+        logq::fixtures::check_format(file_format);
+    }
+}